@@ -1,8 +1,9 @@
 use core::{
-    mem::{transmute, MaybeUninit},
+    mem::{transmute, ManuallyDrop, MaybeUninit},
     ops::{Index, IndexMut},
 };
 
+/// A vector with a fixed capacity of `N` elements, stored inline rather than on the heap
 pub struct FixedVec<T, const N: usize> {
     data: [MaybeUninit<T>; N],
     len: usize,
@@ -48,18 +49,36 @@ impl<T, const N: usize> FixedVec<T, N> {
     }
 
     pub fn clear(&mut self) {
+        let len = self.len;
+        // Length is reset first, so that a panic whilst dropping can't cause a double drop
         self.len = 0;
+
+        for element in self.data[0..len].iter_mut() {
+            unsafe { element.assume_init_drop() };
+        }
     }
 
     pub fn len(&self) -> usize {
         self.len
     }
 
-    pub fn iter(&self) -> core::slice::Iter<T> {
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    pub fn is_full(&self) -> bool {
+        self.len == N
+    }
+
+    pub const fn capacity(&self) -> usize {
+        N
+    }
+
+    pub fn iter(&self) -> core::slice::Iter<'_, T> {
         self.as_slice().iter()
     }
 
-    pub fn iter_mut(&mut self) -> core::slice::IterMut<T> {
+    pub fn iter_mut(&mut self) -> core::slice::IterMut<'_, T> {
         self.as_mut_slice().iter_mut()
     }
 
@@ -120,6 +139,24 @@ impl<T, const N: usize> FixedVec<T, N> {
     }
 }
 
+impl<T: core::fmt::Debug, const N: usize> core::fmt::Debug for FixedVec<T, N> {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.debug_list().entries(self.iter()).finish()
+    }
+}
+
+impl<T, const N: usize> Default for FixedVec<T, N> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T, const N: usize> Drop for FixedVec<T, N> {
+    fn drop(&mut self) {
+        self.clear();
+    }
+}
+
 impl<T, const N: usize> Index<usize> for FixedVec<T, N> {
     type Output = T;
 
@@ -133,3 +170,154 @@ impl<T, const N: usize> IndexMut<usize> for FixedVec<T, N> {
         self.get_mut(index).unwrap()
     }
 }
+
+impl<T, const N: usize> FromIterator<T> for FixedVec<T, N> {
+    /// Panics if the iterator yields more than `N` elements
+    fn from_iter<I: IntoIterator<Item = T>>(iter: I) -> Self {
+        let mut vec = Self::new();
+
+        for value in iter {
+            vec.push(value);
+        }
+
+        vec
+    }
+}
+
+impl<'a, T, const N: usize> IntoIterator for &'a FixedVec<T, N> {
+    type Item = &'a T;
+    type IntoIter = core::slice::Iter<'a, T>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+
+impl<'a, T, const N: usize> IntoIterator for &'a mut FixedVec<T, N> {
+    type Item = &'a mut T;
+    type IntoIter = core::slice::IterMut<'a, T>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter_mut()
+    }
+}
+
+impl<T, const N: usize> IntoIterator for FixedVec<T, N> {
+    type Item = T;
+    type IntoIter = IntoIter<T, N>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        IntoIter {
+            vec: ManuallyDrop::new(self),
+            index: 0,
+        }
+    }
+}
+
+/// Moves elements out of a [FixedVec], from front to back
+pub struct IntoIter<T, const N: usize> {
+    // Elements before `index` have already been moved out, so the vec must not drop them
+    vec: ManuallyDrop<FixedVec<T, N>>,
+    index: usize,
+}
+
+impl<T, const N: usize> Iterator for IntoIter<T, N> {
+    type Item = T;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.index == self.vec.len {
+            return None;
+        }
+
+        let value = unsafe { self.vec.data[self.index].assume_init_read() };
+        self.index += 1;
+
+        Some(value)
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let remaining = self.vec.len - self.index;
+        (remaining, Some(remaining))
+    }
+}
+
+impl<T, const N: usize> ExactSizeIterator for IntoIter<T, N> {}
+
+impl<T, const N: usize> Drop for IntoIter<T, N> {
+    fn drop(&mut self) {
+        let (start, end) = (self.index, self.vec.len);
+        self.index = end;
+
+        for element in self.vec.data[start..end].iter_mut() {
+            unsafe { element.assume_init_drop() };
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::rc::Rc;
+
+    use super::*;
+
+    #[test]
+    fn push_pop_test() {
+        let mut vec = FixedVec::<i32, 4>::new();
+        assert!(vec.is_empty());
+
+        vec.push(1);
+        vec.push(2);
+        vec.push(3);
+        assert_eq!(vec.as_slice(), &[1, 2, 3]);
+
+        assert_eq!(vec.pop(), Some(3));
+        assert_eq!(vec.swap_remove(0), Some(1));
+        assert_eq!(vec.as_slice(), &[2]);
+    }
+
+    #[test]
+    #[should_panic]
+    fn push_full_test() {
+        let mut vec = FixedVec::<i32, 2>::new();
+
+        vec.push(1);
+        vec.push(2);
+        vec.push(3);
+    }
+
+    #[test]
+    fn drop_test() {
+        let value = Rc::new(());
+
+        let mut vec = FixedVec::<Rc<()>, 4>::new();
+        vec.push(value.clone());
+        vec.push(value.clone());
+        vec.push(value.clone());
+        assert_eq!(Rc::strong_count(&value), 4);
+
+        vec.clear();
+        assert_eq!(Rc::strong_count(&value), 1);
+
+        vec.push(value.clone());
+        drop(vec);
+        assert_eq!(Rc::strong_count(&value), 1);
+    }
+
+    #[test]
+    fn iterator_test() {
+        let vec: FixedVec<i32, 8> = (0..5).collect();
+        assert_eq!(vec.len(), 5);
+        assert_eq!((&vec).into_iter().sum::<i32>(), 10);
+
+        let values: Vec<i32> = vec.into_iter().collect();
+        assert_eq!(values, vec![0, 1, 2, 3, 4]);
+
+        // Elements that are not consumed are still dropped
+        let value = Rc::new(());
+        let vec: FixedVec<Rc<()>, 4> = (0..3).map(|_| value.clone()).collect();
+        let mut iter = vec.into_iter();
+        iter.next();
+        drop(iter);
+        assert_eq!(Rc::strong_count(&value), 1);
+    }
+}
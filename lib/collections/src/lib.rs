@@ -1,3 +1,7 @@
 mod fixed_vec;
+mod ring_buffer;
+mod small_vec;
 
 pub use fixed_vec::FixedVec;
+pub use ring_buffer::RingBuffer;
+pub use small_vec::SmallVec;
@@ -0,0 +1,308 @@
+use core::mem::{transmute, ManuallyDrop, MaybeUninit};
+
+/// A queue with a fixed capacity of `N` elements, stored inline. Once full, pushing an element
+/// overwrites the oldest one, which makes it suited to keeping a rolling history of values.
+pub struct RingBuffer<T, const N: usize> {
+    data: [MaybeUninit<T>; N],
+    /// Index of the oldest element
+    head: usize,
+    len: usize,
+}
+
+impl<T, const N: usize> RingBuffer<T, N> {
+    pub fn new() -> Self {
+        Self {
+            data: unsafe { MaybeUninit::uninit().assume_init() },
+            head: 0,
+            len: 0,
+        }
+    }
+
+    /// Adds an element to the back, returning the oldest element if it had to be overwritten
+    pub fn push(&mut self, value: T) -> Option<T> {
+        if N == 0 {
+            return Some(value);
+        }
+
+        if self.len == N {
+            let oldest = core::mem::replace(&mut self.data[self.head], MaybeUninit::new(value));
+            self.head = (self.head + 1) % N;
+
+            return Some(unsafe { oldest.assume_init() });
+        }
+
+        self.data[(self.head + self.len) % N].write(value);
+        self.len += 1;
+
+        None
+    }
+
+    /// Removes the oldest element
+    pub fn pop_front(&mut self) -> Option<T> {
+        if self.len == 0 {
+            return None;
+        }
+
+        let value = unsafe { self.data[self.head].assume_init_read() };
+        self.head = (self.head + 1) % N;
+        self.len -= 1;
+
+        Some(value)
+    }
+
+    pub fn clear(&mut self) {
+        let (first, second) = self.as_mut_slices();
+        let (first, second) = (first as *mut [T], second as *mut [T]);
+
+        // Reset first, so that a panic whilst dropping can't cause a double drop
+        self.head = 0;
+        self.len = 0;
+
+        unsafe {
+            core::ptr::drop_in_place(first);
+            core::ptr::drop_in_place(second);
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    pub fn is_full(&self) -> bool {
+        self.len == N
+    }
+
+    pub const fn capacity(&self) -> usize {
+        N
+    }
+
+    /// Returns the element at `index`, where 0 is the oldest
+    pub fn get(&self, index: usize) -> Option<&T> {
+        if index >= self.len {
+            return None;
+        }
+
+        Some(unsafe { self.data[(self.head + index) % N].assume_init_ref() })
+    }
+
+    pub fn front(&self) -> Option<&T> {
+        self.get(0)
+    }
+
+    pub fn back(&self) -> Option<&T> {
+        self.get(self.len.wrapping_sub(1))
+    }
+
+    /// Returns the elements as two slices, which together run from oldest to newest
+    pub fn as_slices(&self) -> (&[T], &[T]) {
+        let first_len = self.len.min(N - self.head);
+        let second_len = self.len - first_len;
+
+        unsafe {
+            (
+                transmute::<&[MaybeUninit<T>], &[T]>(&self.data[self.head..self.head + first_len]),
+                transmute::<&[MaybeUninit<T>], &[T]>(&self.data[0..second_len]),
+            )
+        }
+    }
+
+    pub fn as_mut_slices(&mut self) -> (&mut [T], &mut [T]) {
+        let first_len = self.len.min(N - self.head);
+        let second_len = self.len - first_len;
+
+        let (start, end) = self.data.split_at_mut(self.head);
+
+        unsafe {
+            (
+                transmute::<&mut [MaybeUninit<T>], &mut [T]>(&mut end[0..first_len]),
+                transmute::<&mut [MaybeUninit<T>], &mut [T]>(&mut start[0..second_len]),
+            )
+        }
+    }
+
+    /// Iterates from the oldest element to the newest
+    pub fn iter(&self) -> Iter<'_, T> {
+        let (first, second) = self.as_slices();
+        first.iter().chain(second.iter())
+    }
+
+    pub fn iter_mut(&mut self) -> IterMut<'_, T> {
+        let (first, second) = self.as_mut_slices();
+        first.iter_mut().chain(second.iter_mut())
+    }
+}
+
+pub type Iter<'a, T> = core::iter::Chain<core::slice::Iter<'a, T>, core::slice::Iter<'a, T>>;
+pub type IterMut<'a, T> =
+    core::iter::Chain<core::slice::IterMut<'a, T>, core::slice::IterMut<'a, T>>;
+
+impl<T: core::fmt::Debug, const N: usize> core::fmt::Debug for RingBuffer<T, N> {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.debug_list().entries(self.iter()).finish()
+    }
+}
+
+impl<T, const N: usize> Default for RingBuffer<T, N> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T, const N: usize> Drop for RingBuffer<T, N> {
+    fn drop(&mut self) {
+        self.clear();
+    }
+}
+
+impl<T, const N: usize> FromIterator<T> for RingBuffer<T, N> {
+    /// Keeps only the last `N` elements yielded by the iterator
+    fn from_iter<I: IntoIterator<Item = T>>(iter: I) -> Self {
+        let mut buffer = Self::new();
+
+        for value in iter {
+            buffer.push(value);
+        }
+
+        buffer
+    }
+}
+
+impl<'a, T, const N: usize> IntoIterator for &'a RingBuffer<T, N> {
+    type Item = &'a T;
+    type IntoIter = Iter<'a, T>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+
+impl<'a, T, const N: usize> IntoIterator for &'a mut RingBuffer<T, N> {
+    type Item = &'a mut T;
+    type IntoIter = IterMut<'a, T>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter_mut()
+    }
+}
+
+impl<T, const N: usize> IntoIterator for RingBuffer<T, N> {
+    type Item = T;
+    type IntoIter = IntoIter<T, N>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        IntoIter {
+            buffer: ManuallyDrop::new(self),
+        }
+    }
+}
+
+/// Moves elements out of a [RingBuffer], from oldest to newest
+pub struct IntoIter<T, const N: usize> {
+    buffer: ManuallyDrop<RingBuffer<T, N>>,
+}
+
+impl<T, const N: usize> Iterator for IntoIter<T, N> {
+    type Item = T;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.buffer.pop_front()
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (self.buffer.len, Some(self.buffer.len))
+    }
+}
+
+impl<T, const N: usize> ExactSizeIterator for IntoIter<T, N> {}
+
+impl<T, const N: usize> Drop for IntoIter<T, N> {
+    fn drop(&mut self) {
+        self.buffer.clear();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::rc::Rc;
+
+    use super::*;
+
+    #[test]
+    fn push_test() {
+        let mut buffer = RingBuffer::<i32, 3>::new();
+        assert!(buffer.is_empty());
+
+        assert_eq!(buffer.push(1), None);
+        assert_eq!(buffer.push(2), None);
+        assert_eq!(buffer.push(3), None);
+        assert!(buffer.is_full());
+
+        // Overwrites the oldest once full
+        assert_eq!(buffer.push(4), Some(1));
+        assert_eq!(buffer.push(5), Some(2));
+        assert_eq!(buffer.len(), 3);
+
+        assert_eq!(buffer.iter().copied().collect::<Vec<_>>(), vec![3, 4, 5]);
+        assert_eq!(buffer.front(), Some(&3));
+        assert_eq!(buffer.back(), Some(&5));
+        assert_eq!(buffer.get(1), Some(&4));
+        assert_eq!(buffer.get(3), None);
+    }
+
+    #[test]
+    fn pop_front_test() {
+        let mut buffer: RingBuffer<i32, 3> = (0..5).collect();
+
+        assert_eq!(buffer.pop_front(), Some(2));
+        buffer.push(5);
+        buffer.push(6);
+
+        assert_eq!(buffer.pop_front(), Some(4));
+        assert_eq!(buffer.pop_front(), Some(5));
+        assert_eq!(buffer.pop_front(), Some(6));
+        assert_eq!(buffer.pop_front(), None);
+        assert_eq!(buffer.back(), None);
+    }
+
+    #[test]
+    fn drop_test() {
+        let value = Rc::new(());
+
+        let mut buffer = RingBuffer::<Rc<()>, 2>::new();
+        buffer.push(value.clone());
+        buffer.push(value.clone());
+        drop(buffer.push(value.clone()));
+        assert_eq!(Rc::strong_count(&value), 3);
+
+        buffer.clear();
+        assert_eq!(Rc::strong_count(&value), 1);
+
+        buffer.push(value.clone());
+        drop(buffer);
+        assert_eq!(Rc::strong_count(&value), 1);
+    }
+
+    #[test]
+    fn iterator_test() {
+        let mut buffer: RingBuffer<i32, 4> = (0..6).collect();
+
+        for value in &mut buffer {
+            *value *= 10;
+        }
+
+        assert_eq!((&buffer).into_iter().sum::<i32>(), 140);
+        assert_eq!(buffer.into_iter().collect::<Vec<_>>(), vec![20, 30, 40, 50]);
+
+        // Elements that are not consumed are still dropped
+        let value = Rc::new(());
+        let buffer: RingBuffer<Rc<()>, 4> = (0..3).map(|_| value.clone()).collect();
+        let mut iter = buffer.into_iter();
+        iter.next();
+        drop(iter);
+        assert_eq!(Rc::strong_count(&value), 1);
+    }
+}
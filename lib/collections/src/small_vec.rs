@@ -0,0 +1,282 @@
+use core::ops::{Index, IndexMut};
+
+use crate::FixedVec;
+
+enum Storage<T, const N: usize> {
+    Inline(FixedVec<T, N>),
+    Heap(Vec<T>),
+}
+
+/// A vector that stores up to `N` elements inline, only moving them onto the heap once it outgrows
+/// that capacity. Suited to lists that are usually small, but have no hard upper limit.
+pub struct SmallVec<T, const N: usize> {
+    storage: Storage<T, N>,
+}
+
+impl<T, const N: usize> SmallVec<T, N> {
+    pub fn new() -> Self {
+        Self {
+            storage: Storage::Inline(FixedVec::new()),
+        }
+    }
+
+    pub fn push(&mut self, value: T) {
+        match &mut self.storage {
+            Storage::Inline(vec) if vec.is_full() => {
+                self.spill(N * 2 + 1).push(value);
+            }
+            Storage::Inline(vec) => vec.push(value),
+            Storage::Heap(vec) => vec.push(value),
+        }
+    }
+
+    pub fn pop(&mut self) -> Option<T> {
+        match &mut self.storage {
+            Storage::Inline(vec) => vec.pop(),
+            Storage::Heap(vec) => vec.pop(),
+        }
+    }
+
+    pub fn swap_remove(&mut self, index: usize) -> Option<T> {
+        match &mut self.storage {
+            Storage::Inline(vec) => vec.swap_remove(index),
+            Storage::Heap(vec) => (index < vec.len()).then(|| vec.swap_remove(index)),
+        }
+    }
+
+    /// Removes all elements. Once spilled, the heap allocation is kept for reuse.
+    pub fn clear(&mut self) {
+        match &mut self.storage {
+            Storage::Inline(vec) => vec.clear(),
+            Storage::Heap(vec) => vec.clear(),
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.as_slice().len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Returns whether the elements have been moved onto the heap
+    pub fn spilled(&self) -> bool {
+        matches!(self.storage, Storage::Heap(_))
+    }
+
+    pub fn as_slice(&self) -> &[T] {
+        match &self.storage {
+            Storage::Inline(vec) => vec.as_slice(),
+            Storage::Heap(vec) => vec.as_slice(),
+        }
+    }
+
+    pub fn as_mut_slice(&mut self) -> &mut [T] {
+        match &mut self.storage {
+            Storage::Inline(vec) => vec.as_mut_slice(),
+            Storage::Heap(vec) => vec.as_mut_slice(),
+        }
+    }
+
+    pub fn iter(&self) -> core::slice::Iter<'_, T> {
+        self.as_slice().iter()
+    }
+
+    pub fn iter_mut(&mut self) -> core::slice::IterMut<'_, T> {
+        self.as_mut_slice().iter_mut()
+    }
+
+    pub fn get(&self, index: usize) -> Option<&T> {
+        self.as_slice().get(index)
+    }
+
+    pub fn get_mut(&mut self, index: usize) -> Option<&mut T> {
+        self.as_mut_slice().get_mut(index)
+    }
+
+    pub fn last(&self) -> Option<&T> {
+        self.as_slice().last()
+    }
+
+    pub fn last_mut(&mut self) -> Option<&mut T> {
+        self.as_mut_slice().last_mut()
+    }
+
+    /// Moves the elements onto the heap, if they are not already, returning the heap storage
+    fn spill(&mut self, capacity: usize) -> &mut Vec<T> {
+        if let Storage::Inline(vec) = &mut self.storage {
+            let mut heap = Vec::with_capacity(capacity.max(vec.len()));
+            heap.extend(core::mem::take(vec));
+
+            self.storage = Storage::Heap(heap);
+        }
+
+        let Storage::Heap(vec) = &mut self.storage else {
+            unreachable!();
+        };
+
+        vec
+    }
+}
+
+impl<T: core::fmt::Debug, const N: usize> core::fmt::Debug for SmallVec<T, N> {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.debug_list().entries(self.iter()).finish()
+    }
+}
+
+impl<T, const N: usize> Default for SmallVec<T, N> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T, const N: usize> Index<usize> for SmallVec<T, N> {
+    type Output = T;
+
+    fn index(&self, index: usize) -> &Self::Output {
+        &self.as_slice()[index]
+    }
+}
+
+impl<T, const N: usize> IndexMut<usize> for SmallVec<T, N> {
+    fn index_mut(&mut self, index: usize) -> &mut Self::Output {
+        &mut self.as_mut_slice()[index]
+    }
+}
+
+impl<T, const N: usize> Extend<T> for SmallVec<T, N> {
+    fn extend<I: IntoIterator<Item = T>>(&mut self, iter: I) {
+        let iter = iter.into_iter();
+
+        // Spill up front if the elements are known not to fit, rather than part way through
+        let (lower, _) = iter.size_hint();
+        if self.len() + lower > N {
+            self.spill(self.len() + lower);
+        }
+
+        for value in iter {
+            self.push(value);
+        }
+    }
+}
+
+impl<T, const N: usize> FromIterator<T> for SmallVec<T, N> {
+    fn from_iter<I: IntoIterator<Item = T>>(iter: I) -> Self {
+        let mut vec = Self::new();
+        vec.extend(iter);
+        vec
+    }
+}
+
+impl<'a, T, const N: usize> IntoIterator for &'a SmallVec<T, N> {
+    type Item = &'a T;
+    type IntoIter = core::slice::Iter<'a, T>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+
+impl<'a, T, const N: usize> IntoIterator for &'a mut SmallVec<T, N> {
+    type Item = &'a mut T;
+    type IntoIter = core::slice::IterMut<'a, T>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter_mut()
+    }
+}
+
+impl<T, const N: usize> IntoIterator for SmallVec<T, N> {
+    type Item = T;
+    type IntoIter = IntoIter<T, N>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        match self.storage {
+            Storage::Inline(vec) => IntoIter::Inline(vec.into_iter()),
+            Storage::Heap(vec) => IntoIter::Heap(vec.into_iter()),
+        }
+    }
+}
+
+/// Moves elements out of a [SmallVec], from front to back
+pub enum IntoIter<T, const N: usize> {
+    Inline(crate::fixed_vec::IntoIter<T, N>),
+    Heap(std::vec::IntoIter<T>),
+}
+
+impl<T, const N: usize> Iterator for IntoIter<T, N> {
+    type Item = T;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        match self {
+            IntoIter::Inline(iter) => iter.next(),
+            IntoIter::Heap(iter) => iter.next(),
+        }
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        match self {
+            IntoIter::Inline(iter) => iter.size_hint(),
+            IntoIter::Heap(iter) => iter.size_hint(),
+        }
+    }
+}
+
+impl<T, const N: usize> ExactSizeIterator for IntoIter<T, N> {}
+
+#[cfg(test)]
+mod tests {
+    use std::rc::Rc;
+
+    use super::*;
+
+    #[test]
+    fn spill_test() {
+        let mut vec = SmallVec::<i32, 2>::new();
+
+        vec.push(1);
+        vec.push(2);
+        assert!(!vec.spilled());
+
+        vec.push(3);
+        assert!(vec.spilled());
+        assert_eq!(vec.as_slice(), &[1, 2, 3]);
+
+        assert_eq!(vec.pop(), Some(3));
+        assert_eq!(vec.swap_remove(0), Some(1));
+        assert_eq!(vec.swap_remove(4), None);
+        assert_eq!(vec[0], 2);
+    }
+
+    #[test]
+    fn drop_test() {
+        let value = Rc::new(());
+
+        let mut vec = SmallVec::<Rc<()>, 2>::new();
+        vec.push(value.clone());
+        vec.push(value.clone());
+        vec.push(value.clone());
+        assert_eq!(Rc::strong_count(&value), 4);
+
+        drop(vec);
+        assert_eq!(Rc::strong_count(&value), 1);
+    }
+
+    #[test]
+    fn iterator_test() {
+        let inline: SmallVec<i32, 8> = (0..4).collect();
+        assert!(!inline.spilled());
+
+        let mut heap: SmallVec<i32, 2> = (0..4).collect();
+        assert!(heap.spilled());
+
+        for value in &mut heap {
+            *value += 1;
+        }
+
+        assert_eq!(inline.into_iter().collect::<Vec<_>>(), vec![0, 1, 2, 3]);
+        assert_eq!(heap.into_iter().collect::<Vec<_>>(), vec![1, 2, 3, 4]);
+    }
+}
@@ -0,0 +1,456 @@
+use window::event::{KeyCode, MouseButton};
+
+use crate::{GamepadButton, Input, Keyboard};
+
+/// Something the player can do, which is bound to one or more inputs through [Bindings]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Action {
+    MoveForward,
+    MoveBackward,
+    StrafeLeft,
+    StrafeRight,
+    /// Only used by the free-fly spectator camera
+    MoveUp,
+    MoveDown,
+    Crouch,
+    Fire,
+    Use,
+    /// Selects the weapon in the given slot, if it is carried
+    Weapon1,
+    Weapon2,
+    Weapon3,
+    Weapon4,
+    NextWeapon,
+    PreviousWeapon,
+    ZoomIn,
+    ZoomOut,
+    ToggleConsole,
+    /// Freezes the world and shows the pause menu, or carries on
+    Pause,
+    MenuUp,
+    MenuDown,
+    MenuSelect,
+
+    ToggleDebug,
+    ToggleSpectator,
+    PlayCutscene,
+    ToggleMonitor,
+    ExportMap,
+    ToggleDistortionCorrection,
+    ToggleFrameCap,
+    ToggleVsync,
+    CycleScaleFilter,
+    ToggleCrt,
+    /// Pauses to step back through the most recently recorded frames, or carries on
+    ToggleRewind,
+    RewindBack,
+    RewindForward,
+
+    WindowScale1,
+    WindowScale2,
+    WindowScale3,
+    WindowScale4,
+    WindowScale5,
+
+    // Test actions for the sector the player is in
+    RaiseCeiling,
+    LowerCeiling,
+    RotateFlatsLeft,
+    RotateFlatsRight,
+    RotateSector,
+}
+
+impl Action {
+    pub const COUNT: usize = 45;
+}
+
+/// A single key or button, on any device
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Binding {
+    Key(KeyCode),
+    /// Mouse buttons only count whilst the mouse is grabbed, so that clicking to grab it doesn't
+    /// also trigger an action
+    Mouse(MouseButton),
+    Gamepad(GamepadButton),
+}
+
+/// Modifier keys that must be held for a [Chord] to trigger, where either the left or right key
+/// will do
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Modifiers {
+    pub ctrl: bool,
+    pub shift: bool,
+    pub alt: bool,
+}
+
+impl Modifiers {
+    pub const NONE: Self = Self {
+        ctrl: false,
+        shift: false,
+        alt: false,
+    };
+    pub const CTRL: Self = Self {
+        ctrl: true,
+        ..Self::NONE
+    };
+    pub const SHIFT: Self = Self {
+        shift: true,
+        ..Self::NONE
+    };
+    pub const ALT: Self = Self {
+        alt: true,
+        ..Self::NONE
+    };
+
+    /// Returns whether all of these modifiers are held. Extra modifiers being held doesn't matter.
+    pub fn is_held(&self, keyboard: &Keyboard) -> bool {
+        let held = |left, right| keyboard.is_key_held(left) || keyboard.is_key_held(right);
+
+        (!self.ctrl || held(KeyCode::ControlLeft, KeyCode::ControlRight))
+            && (!self.shift || held(KeyCode::ShiftLeft, KeyCode::ShiftRight))
+            && (!self.alt || held(KeyCode::AltLeft, KeyCode::AltRight))
+    }
+
+    /// Returns whether these include every one of the other modifiers, and at least one more
+    fn is_more_than(&self, other: &Self) -> bool {
+        *self != *other
+            && (self.ctrl || !other.ctrl)
+            && (self.shift || !other.shift)
+            && (self.alt || !other.alt)
+    }
+}
+
+/// A binding, along with any modifiers that must be held with it, e.g. Ctrl+1
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Chord {
+    pub modifiers: Modifiers,
+    pub binding: Binding,
+}
+
+impl Chord {
+    pub fn new(modifiers: Modifiers, binding: impl Into<Binding>) -> Self {
+        Self {
+            modifiers,
+            binding: binding.into(),
+        }
+    }
+}
+
+impl From<KeyCode> for Binding {
+    fn from(value: KeyCode) -> Self {
+        Binding::Key(value)
+    }
+}
+
+impl From<MouseButton> for Binding {
+    fn from(value: MouseButton) -> Self {
+        Binding::Mouse(value)
+    }
+}
+
+impl From<GamepadButton> for Binding {
+    fn from(value: GamepadButton) -> Self {
+        Binding::Gamepad(value)
+    }
+}
+
+impl From<KeyCode> for Chord {
+    fn from(value: KeyCode) -> Self {
+        Chord::new(Modifiers::NONE, value)
+    }
+}
+
+impl From<MouseButton> for Chord {
+    fn from(value: MouseButton) -> Self {
+        Chord::new(Modifiers::NONE, value)
+    }
+}
+
+impl From<GamepadButton> for Chord {
+    fn from(value: GamepadButton) -> Self {
+        Chord::new(Modifiers::NONE, value)
+    }
+}
+
+/// Table of which chords trigger each action. An action can have any number of chords, and the
+/// same chord can trigger more than one action.
+#[derive(Clone, Debug)]
+pub struct Bindings {
+    // Indexed by `Action`
+    chords: Vec<Vec<Chord>>,
+}
+
+impl Bindings {
+    /// Creates a table with nothing bound. Use `Bindings::default()` for the default controls.
+    pub fn new() -> Self {
+        Bindings {
+            chords: vec![Vec::new(); Action::COUNT],
+        }
+    }
+
+    /// Adds a chord to an action, in addition to any it already has
+    pub fn bind(&mut self, action: Action, chord: impl Into<Chord>) {
+        let chord = chord.into();
+        let chords = &mut self.chords[action as usize];
+
+        if !chords.contains(&chord) {
+            chords.push(chord);
+        }
+    }
+
+    /// Removes a chord from an action, returning whether it was bound
+    pub fn unbind(&mut self, action: Action, chord: impl Into<Chord>) -> bool {
+        let chord = chord.into();
+        let chords = &mut self.chords[action as usize];
+        let len = chords.len();
+
+        chords.retain(|c| *c != chord);
+        chords.len() != len
+    }
+
+    /// Replaces all chords for an action with a single one
+    pub fn rebind(&mut self, action: Action, chord: impl Into<Chord>) {
+        self.clear(action);
+        self.bind(action, chord);
+    }
+
+    pub fn clear(&mut self, action: Action) {
+        self.chords[action as usize].clear();
+    }
+
+    pub fn get(&self, action: Action) -> &[Chord] {
+        &self.chords[action as usize]
+    }
+
+    /// Returns whether a chord is held with more modifiers than it has, which are those of another
+    /// chord with the same binding. The chord with more modifiers takes the press, so that e.g.
+    /// Ctrl+1 doesn't also trigger what 1 does, whilst modifiers that no chord of the binding uses,
+    /// such as Shift to crouch whilst moving, don't get in the way.
+    fn is_shadowed(&self, chord: &Chord, keyboard: &Keyboard) -> bool {
+        self.chords.iter().flatten().any(|other| {
+            other.binding == chord.binding
+                && other.modifiers.is_more_than(&chord.modifiers)
+                && other.modifiers.is_held(keyboard)
+        })
+    }
+}
+
+impl Default for Bindings {
+    fn default() -> Self {
+        use Action::*;
+
+        let mut bindings = Bindings::new();
+
+        bindings.bind(MoveForward, KeyCode::W);
+        bindings.bind(MoveBackward, KeyCode::S);
+        bindings.bind(StrafeLeft, KeyCode::A);
+        bindings.bind(StrafeRight, KeyCode::D);
+        bindings.bind(MoveUp, KeyCode::E);
+        bindings.bind(MoveDown, KeyCode::Q);
+        bindings.bind(Crouch, KeyCode::ShiftLeft);
+        bindings.bind(Crouch, GamepadButton::East);
+        bindings.bind(Fire, MouseButton::Left);
+        bindings.bind(Fire, GamepadButton::RightTrigger);
+        bindings.bind(Use, KeyCode::E);
+        bindings.bind(Use, GamepadButton::South);
+        bindings.bind(Weapon1, KeyCode::Digit1);
+        bindings.bind(Weapon2, KeyCode::Digit2);
+        bindings.bind(Weapon3, KeyCode::Digit3);
+        bindings.bind(Weapon4, KeyCode::Digit4);
+        bindings.bind(NextWeapon, GamepadButton::RightBumper);
+        bindings.bind(PreviousWeapon, GamepadButton::LeftBumper);
+        bindings.bind(ZoomIn, KeyCode::Equal);
+        bindings.bind(ZoomIn, KeyCode::NumpadAdd);
+        bindings.bind(ZoomOut, KeyCode::Minus);
+        bindings.bind(ZoomOut, KeyCode::NumpadSubtract);
+        bindings.bind(ToggleConsole, KeyCode::Backquote);
+        bindings.bind(Pause, KeyCode::Escape);
+        bindings.bind(Pause, KeyCode::P);
+        bindings.bind(Pause, GamepadButton::Start);
+        bindings.bind(MenuUp, KeyCode::ArrowUp);
+        bindings.bind(MenuUp, GamepadButton::DPadUp);
+        bindings.bind(MenuDown, KeyCode::ArrowDown);
+        bindings.bind(MenuDown, GamepadButton::DPadDown);
+        bindings.bind(MenuSelect, KeyCode::Enter);
+        bindings.bind(MenuSelect, GamepadButton::South);
+
+        bindings.bind(ToggleDebug, KeyCode::F3);
+        bindings.bind(ToggleSpectator, KeyCode::F4);
+        bindings.bind(PlayCutscene, KeyCode::F5);
+        bindings.bind(ToggleMonitor, KeyCode::F6);
+        bindings.bind(ExportMap, KeyCode::F7);
+        bindings.bind(ToggleDistortionCorrection, KeyCode::F8);
+        bindings.bind(ToggleFrameCap, KeyCode::F9);
+        bindings.bind(ToggleVsync, KeyCode::F10);
+        bindings.bind(CycleScaleFilter, KeyCode::F11);
+        bindings.bind(ToggleCrt, KeyCode::F12);
+        bindings.bind(ToggleRewind, KeyCode::Pause);
+        bindings.bind(RewindBack, KeyCode::BracketLeft);
+        bindings.bind(RewindForward, KeyCode::BracketRight);
+
+        bindings.bind(WindowScale1, Chord::new(Modifiers::CTRL, KeyCode::Digit1));
+        bindings.bind(WindowScale2, Chord::new(Modifiers::CTRL, KeyCode::Digit2));
+        bindings.bind(WindowScale3, Chord::new(Modifiers::CTRL, KeyCode::Digit3));
+        bindings.bind(WindowScale4, Chord::new(Modifiers::CTRL, KeyCode::Digit4));
+        bindings.bind(WindowScale5, Chord::new(Modifiers::CTRL, KeyCode::Digit5));
+
+        bindings.bind(RaiseCeiling, KeyCode::ArrowUp);
+        bindings.bind(LowerCeiling, KeyCode::ArrowDown);
+        bindings.bind(RotateFlatsLeft, KeyCode::ArrowLeft);
+        bindings.bind(RotateFlatsRight, KeyCode::ArrowRight);
+        bindings.bind(RotateSector, KeyCode::R);
+
+        bindings
+    }
+}
+
+impl Input {
+    /// Returns whether any chord bound to the action started being pressed this frame
+    pub fn is_action_pressed(&self, action: Action) -> bool {
+        self.any_chord(action, |input, binding| match binding {
+            Binding::Key(key) => input.keyboard.is_key_pressed(key),
+            Binding::Mouse(button) => input.mouse.is_button_pressed(button),
+            Binding::Gamepad(button) => input.gamepad.is_button_pressed(button),
+        })
+    }
+
+    /// Returns whether any chord bound to the action is held down
+    pub fn is_action_held(&self, action: Action) -> bool {
+        self.any_chord(action, |input, binding| match binding {
+            Binding::Key(key) => input.keyboard.is_key_held(key),
+            Binding::Mouse(button) => input.mouse.is_button_held(button),
+            Binding::Gamepad(button) => input.gamepad.is_button_held(button),
+        })
+    }
+
+    /// Returns whether any chord bound to the action was let go of this frame
+    pub fn is_action_released(&self, action: Action) -> bool {
+        self.any_chord(action, |input, binding| match binding {
+            Binding::Key(key) => input.keyboard.is_key_released(key),
+            Binding::Mouse(button) => input.mouse.is_button_released(button),
+            Binding::Gamepad(button) => input.gamepad.is_button_released(button),
+        })
+    }
+
+    fn any_chord(&self, action: Action, test: impl Fn(&Input, Binding) -> bool) -> bool {
+        if self.is_capturing_text() {
+            return false;
+        }
+
+        self.bindings.get(action).iter().any(|chord| {
+            let device_active = match chord.binding {
+                Binding::Mouse(_) => self.mouse.is_grabbed(),
+                _ => true,
+            };
+
+            device_active
+                && chord.modifiers.is_held(&self.keyboard)
+                && !self.bindings.is_shadowed(chord, &self.keyboard)
+                && test(self, chord.binding)
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use window::event::{Event, KeyboardEvent, PointerEvent};
+
+    use super::*;
+    use crate::{GamepadAxis, GamepadEvent};
+
+    fn press_key(input: &mut Input, key: KeyCode) {
+        input.handle_event(&Event::KeyboardEvent(KeyboardEvent::KeyPressed(key)));
+    }
+
+    #[test]
+    fn test_action() {
+        let mut input = Input::new();
+        press_key(&mut input, KeyCode::F3);
+
+        assert!(input.is_action_pressed(Action::ToggleDebug));
+        assert!(input.is_action_held(Action::ToggleDebug));
+        assert!(!input.is_action_pressed(Action::ToggleCrt));
+
+        input.update();
+        assert!(!input.is_action_pressed(Action::ToggleDebug));
+        assert!(input.is_action_held(Action::ToggleDebug));
+    }
+
+    #[test]
+    fn test_chord() {
+        let mut input = Input::new();
+        press_key(&mut input, KeyCode::Digit2);
+        assert!(!input.is_action_pressed(Action::WindowScale2));
+
+        input.update();
+        press_key(&mut input, KeyCode::ControlRight);
+        input.handle_event(&Event::KeyboardEvent(KeyboardEvent::KeyReleased(
+            KeyCode::Digit2,
+        )));
+        press_key(&mut input, KeyCode::Digit2);
+        assert!(input.is_action_pressed(Action::WindowScale2));
+        assert!(!input.is_action_pressed(Action::Weapon2));
+    }
+
+    #[test]
+    fn test_chord_shadowing() {
+        let mut input = Input::new();
+        press_key(&mut input, KeyCode::ControlLeft);
+        press_key(&mut input, KeyCode::Digit1);
+        assert!(input.is_action_pressed(Action::WindowScale1));
+        assert!(!input.is_action_pressed(Action::Weapon1));
+
+        // Modifiers that no chord of the key uses don't stop it, so crouching doesn't stop moving
+        input.update();
+        press_key(&mut input, KeyCode::ShiftLeft);
+        press_key(&mut input, KeyCode::W);
+        assert!(input.is_action_held(Action::Crouch));
+        assert!(input.is_action_held(Action::MoveForward));
+    }
+
+    #[test]
+    fn test_rebind() {
+        let mut input = Input::new();
+        input.bindings.rebind(Action::Use, KeyCode::F);
+        assert_eq!(input.bindings.get(Action::Use), &[Chord::from(KeyCode::F)]);
+
+        press_key(&mut input, KeyCode::E);
+        assert!(!input.is_action_pressed(Action::Use));
+        press_key(&mut input, KeyCode::F);
+        assert!(input.is_action_pressed(Action::Use));
+
+        assert!(input.bindings.unbind(Action::Use, KeyCode::F));
+        assert!(!input.bindings.unbind(Action::Use, KeyCode::F));
+        assert!(!input.is_action_pressed(Action::Use));
+    }
+
+    #[test]
+    fn test_text_capture() {
+        let mut input = Input::new();
+        input.set_text_capture(true);
+        press_key(&mut input, KeyCode::E);
+        input
+            .gamepad
+            .handle_gamepad_event(&GamepadEvent::AxisMoved(GamepadAxis::LeftStickY, 1.0));
+        input
+            .gamepad
+            .handle_gamepad_event(&GamepadEvent::ButtonPressed(GamepadButton::South));
+        assert!(!input.is_action_pressed(Action::Use));
+        assert_eq!(input.movement(), maths::linear::Vec2f::ZERO);
+
+        input.set_text_capture(false);
+        assert!(input.is_action_pressed(Action::Use));
+    }
+
+    #[test]
+    fn test_mouse_binding() {
+        let mut input = Input::new();
+        input.handle_event(&Event::PointerEvent(PointerEvent::MouseButtonPressed(
+            MouseButton::Left,
+        )));
+        assert!(!input.is_action_pressed(Action::Fire));
+
+        input.mouse.grabbed = true;
+        assert!(input.is_action_pressed(Action::Fire));
+    }
+}
@@ -0,0 +1,347 @@
+use maths::linear::Vec2f;
+
+use crate::State;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum GamepadButton {
+    /// Bottom face button, e.g. A on Xbox controllers
+    South,
+    East,
+    North,
+    West,
+    LeftBumper,
+    RightBumper,
+    /// The triggers also report how far they are pulled, as a `GamepadAxis`
+    LeftTrigger,
+    RightTrigger,
+    Select,
+    Start,
+    /// Pressing down on the left stick
+    LeftStick,
+    RightStick,
+    DPadUp,
+    DPadDown,
+    DPadLeft,
+    DPadRight,
+}
+
+impl GamepadButton {
+    pub const COUNT: usize = 16;
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum GamepadAxis {
+    /// Positive to the right
+    LeftStickX,
+    /// Positive upwards
+    LeftStickY,
+    RightStickX,
+    RightStickY,
+    /// From 0 when released, to 1 when fully pulled
+    LeftTrigger,
+    RightTrigger,
+}
+
+impl GamepadAxis {
+    pub const COUNT: usize = 6;
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum GamepadEvent {
+    Connected,
+    Disconnected,
+    ButtonPressed(GamepadButton),
+    ButtonReleased(GamepadButton),
+    AxisMoved(GamepadAxis, f32),
+}
+
+/// Range of stick deflection that is ignored or clamped, to hide drift when the stick is at rest, and
+/// make it easy to reach full speed
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Deadzone {
+    /// Deflection below which the stick reads as centred, from 0 to 1
+    pub inner: f32,
+    /// Deflection above which the stick reads as fully pushed, from 0 to 1
+    pub outer: f32,
+}
+
+impl Deadzone {
+    pub fn new(inner: f32, outer: f32) -> Self {
+        Self { inner, outer }
+    }
+
+    /// Applies the deadzone to the length of the stick's deflection, rescaling it so that it still
+    /// runs smoothly from 0 to 1. Being radial, this doesn't snap diagonal movement to the axes.
+    pub fn apply(&self, stick: Vec2f) -> Vec2f {
+        let magnitude = stick.magnitude();
+
+        if magnitude <= self.inner {
+            return Vec2f::ZERO;
+        }
+
+        let scaled = ((magnitude - self.inner) / (self.outer - self.inner).max(f32::EPSILON))
+            .clamp(0.0, 1.0);
+
+        stick / magnitude * scaled
+    }
+}
+
+impl Default for Deadzone {
+    fn default() -> Self {
+        Self::new(0.15, 0.95)
+    }
+}
+
+/// The state of the first connected gamepad
+pub struct Gamepad {
+    connected: bool,
+    button_states: Vec<State>,
+    axes: [f32; GamepadAxis::COUNT],
+    pub stick_deadzone: Deadzone,
+    /// Trigger travel below which the trigger reads as released, from 0 to 1
+    pub trigger_deadzone: f32,
+    #[cfg(feature = "gamepad")]
+    backend: Option<gilrs::Gilrs>,
+}
+
+impl Gamepad {
+    pub fn new() -> Self {
+        Gamepad {
+            connected: false,
+            button_states: vec![State::default(); GamepadButton::COUNT],
+            axes: [0.0; GamepadAxis::COUNT],
+            stick_deadzone: Deadzone::default(),
+            trigger_deadzone: 0.1,
+            #[cfg(feature = "gamepad")]
+            backend: Self::create_backend(),
+        }
+    }
+
+    #[cfg(feature = "gamepad")]
+    fn create_backend() -> Option<gilrs::Gilrs> {
+        // Deadzones are applied here instead, so that they can be configured
+        gilrs::GilrsBuilder::new()
+            .with_default_filters(false)
+            .build()
+            .map_err(|e| log::warn!("Failed to initialise gamepad support: {e}"))
+            .ok()
+    }
+
+    pub fn is_connected(&self) -> bool {
+        self.connected
+    }
+
+    pub fn is_button_pressed(&self, button: GamepadButton) -> bool {
+        self.button_states[button as usize].pressed
+    }
+
+    pub fn is_button_held(&self, button: GamepadButton) -> bool {
+        self.button_states[button as usize].held
+    }
+
+    pub fn is_button_released(&self, button: GamepadButton) -> bool {
+        self.button_states[button as usize].released
+    }
+
+    /// Returns the raw value of an axis, without any deadzone applied
+    pub fn axis(&self, axis: GamepadAxis) -> f32 {
+        self.axes[axis as usize]
+    }
+
+    pub fn left_stick(&self) -> Vec2f {
+        self.stick_deadzone.apply(Vec2f::new(
+            self.axis(GamepadAxis::LeftStickX),
+            self.axis(GamepadAxis::LeftStickY),
+        ))
+    }
+
+    pub fn right_stick(&self) -> Vec2f {
+        self.stick_deadzone.apply(Vec2f::new(
+            self.axis(GamepadAxis::RightStickX),
+            self.axis(GamepadAxis::RightStickY),
+        ))
+    }
+
+    /// Returns how far a trigger is pulled, from 0 to 1, with the deadzone applied
+    pub fn trigger(&self, axis: GamepadAxis) -> f32 {
+        let value = self.axis(axis);
+
+        if value <= self.trigger_deadzone {
+            return 0.0;
+        }
+
+        (value - self.trigger_deadzone) / (1.0 - self.trigger_deadzone)
+    }
+
+    #[cfg_attr(not(feature = "gamepad"), allow(dead_code))]
+    pub(crate) fn handle_gamepad_event(&mut self, event: &GamepadEvent) {
+        match event {
+            GamepadEvent::Connected => {
+                self.connected = true;
+            }
+
+            GamepadEvent::Disconnected => {
+                self.connected = false;
+                self.axes = [0.0; GamepadAxis::COUNT];
+
+                for state in self.button_states.iter_mut() {
+                    state.released |= state.held;
+                    state.held = false;
+                }
+            }
+
+            GamepadEvent::ButtonPressed(button) => {
+                let state = &mut self.button_states[*button as usize];
+                state.pressed = !state.held;
+                state.held = true;
+            }
+
+            GamepadEvent::ButtonReleased(button) => {
+                self.button_states[*button as usize].held = false;
+                self.button_states[*button as usize].released = true;
+            }
+
+            GamepadEvent::AxisMoved(axis, value) => {
+                self.axes[*axis as usize] = value.clamp(-1.0, 1.0);
+            }
+        }
+    }
+
+    /// Handles any events from the gamepad backend since the last poll
+    #[cfg(feature = "gamepad")]
+    pub(crate) fn poll(&mut self) {
+        let Some(backend) = self.backend.as_mut() else {
+            return;
+        };
+
+        let mut events = Vec::new();
+
+        while let Some(gilrs::Event { event, .. }) = backend.next_event() {
+            events.extend(translate_event(event));
+        }
+
+        // Pick up gamepads that were already connected at startup
+        if !self.connected && backend.gamepads().next().is_some() {
+            events.insert(0, GamepadEvent::Connected);
+        }
+
+        for event in events.iter() {
+            self.handle_gamepad_event(event);
+        }
+    }
+
+    #[cfg(not(feature = "gamepad"))]
+    pub(crate) fn poll(&mut self) {}
+
+    pub(crate) fn update(&mut self) {
+        for state in self.button_states.iter_mut() {
+            state.pressed = false;
+            state.released = false;
+        }
+    }
+}
+
+#[cfg(feature = "gamepad")]
+fn translate_event(event: gilrs::EventType) -> Option<GamepadEvent> {
+    use gilrs::EventType;
+
+    match event {
+        EventType::Connected => Some(GamepadEvent::Connected),
+        EventType::Disconnected => Some(GamepadEvent::Disconnected),
+        EventType::ButtonPressed(button, _) => {
+            translate_button(button).map(GamepadEvent::ButtonPressed)
+        }
+        EventType::ButtonReleased(button, _) => {
+            translate_button(button).map(GamepadEvent::ButtonReleased)
+        }
+        // The triggers are analogue buttons on most controllers
+        EventType::ButtonChanged(gilrs::Button::LeftTrigger2, value, _) => {
+            Some(GamepadEvent::AxisMoved(GamepadAxis::LeftTrigger, value))
+        }
+        EventType::ButtonChanged(gilrs::Button::RightTrigger2, value, _) => {
+            Some(GamepadEvent::AxisMoved(GamepadAxis::RightTrigger, value))
+        }
+        EventType::AxisChanged(axis, value, _) => {
+            translate_axis(axis).map(|axis| GamepadEvent::AxisMoved(axis, value))
+        }
+        _ => None,
+    }
+}
+
+#[cfg(feature = "gamepad")]
+fn translate_button(button: gilrs::Button) -> Option<GamepadButton> {
+    use gilrs::Button;
+
+    Some(match button {
+        Button::South => GamepadButton::South,
+        Button::East => GamepadButton::East,
+        Button::North => GamepadButton::North,
+        Button::West => GamepadButton::West,
+        Button::LeftTrigger => GamepadButton::LeftBumper,
+        Button::RightTrigger => GamepadButton::RightBumper,
+        Button::LeftTrigger2 => GamepadButton::LeftTrigger,
+        Button::RightTrigger2 => GamepadButton::RightTrigger,
+        Button::Select => GamepadButton::Select,
+        Button::Start => GamepadButton::Start,
+        Button::LeftThumb => GamepadButton::LeftStick,
+        Button::RightThumb => GamepadButton::RightStick,
+        Button::DPadUp => GamepadButton::DPadUp,
+        Button::DPadDown => GamepadButton::DPadDown,
+        Button::DPadLeft => GamepadButton::DPadLeft,
+        Button::DPadRight => GamepadButton::DPadRight,
+        _ => return None,
+    })
+}
+
+#[cfg(feature = "gamepad")]
+fn translate_axis(axis: gilrs::Axis) -> Option<GamepadAxis> {
+    use gilrs::Axis;
+
+    Some(match axis {
+        Axis::LeftStickX => GamepadAxis::LeftStickX,
+        Axis::LeftStickY => GamepadAxis::LeftStickY,
+        Axis::RightStickX => GamepadAxis::RightStickX,
+        Axis::RightStickY => GamepadAxis::RightStickY,
+        Axis::LeftZ => GamepadAxis::LeftTrigger,
+        Axis::RightZ => GamepadAxis::RightTrigger,
+        _ => return None,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn deadzone_test() {
+        let deadzone = Deadzone::new(0.2, 0.8);
+
+        assert_eq!(deadzone.apply(Vec2f::new(0.1, 0.1)), Vec2f::ZERO);
+        assert_eq!(deadzone.apply(Vec2f::new(0.0, -0.9)), Vec2f::new(0.0, -1.0));
+
+        // Halfway between the inner and outer edges
+        let value = deadzone.apply(Vec2f::new(0.5, 0.0));
+        assert!((value.x - 0.5).abs() < 1e-6);
+        assert_eq!(value.y, 0.0);
+    }
+
+    #[test]
+    fn gamepad_test() {
+        let mut gamepad = Gamepad::new();
+        gamepad.handle_gamepad_event(&GamepadEvent::Connected);
+        gamepad.handle_gamepad_event(&GamepadEvent::ButtonPressed(GamepadButton::South));
+        gamepad.handle_gamepad_event(&GamepadEvent::AxisMoved(GamepadAxis::LeftStickY, 1.0));
+
+        assert!(gamepad.is_connected());
+        assert!(gamepad.is_button_pressed(GamepadButton::South));
+        assert_eq!(gamepad.left_stick(), Vec2f::new(0.0, 1.0));
+
+        gamepad.update();
+        assert!(!gamepad.is_button_pressed(GamepadButton::South));
+        assert!(gamepad.is_button_held(GamepadButton::South));
+
+        gamepad.handle_gamepad_event(&GamepadEvent::Disconnected);
+        assert!(gamepad.is_button_released(GamepadButton::South));
+        assert_eq!(gamepad.left_stick(), Vec2f::ZERO);
+    }
+}
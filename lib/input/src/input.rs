@@ -1,11 +1,18 @@
+use std::time::{Duration, Instant};
+
 use maths::linear::Vec2f;
-use window::event::{Event, KeyCode, KeyboardEvent, MouseButton, PointerEvent};
+use window::{
+    event::{Event, KeyCode, KeyboardEvent, MouseButton, PointerEvent},
+    SurfaceTransform,
+};
+
+use crate::{Action, Bindings, Gamepad};
 
 #[derive(Clone, Copy, Default)]
 pub struct State {
-    pressed: bool,
-    held: bool,
-    released: bool,
+    pub(crate) pressed: bool,
+    pub(crate) held: bool,
+    pub(crate) released: bool,
 }
 
 pub struct Mouse {
@@ -15,8 +22,16 @@ pub struct Mouse {
     pos_y: f64,
     on_window: bool,
     moved: bool,
+    wheel_x: f64,
+    wheel_y: f64,
     pub grabbed: bool,
     button_states: Vec<State>,
+    /// Longest time between presses of the same button for them to count as one multi-click
+    pub double_click_time: Duration,
+    last_click: Option<(MouseButton, Instant)>,
+    click_count: u32,
+    /// Maps the cursor position from the window to the surface, if the app has provided it
+    surface_transform: Option<SurfaceTransform>,
 }
 
 impl Mouse {
@@ -28,9 +43,15 @@ impl Mouse {
             pos_y: 0.0,
             on_window: false,
             moved: false,
+            wheel_x: 0.0,
+            wheel_y: 0.0,
             grabbed: false,
             // `button_states` length of 6 corresponds to `MouseButton` enum length of 6
             button_states: vec![State::default(); 6],
+            double_click_time: Duration::from_millis(500),
+            last_click: None,
+            click_count: 0,
+            surface_transform: None,
         }
     }
 
@@ -46,6 +67,20 @@ impl Mouse {
         self.button_states[button as usize].released
     }
 
+    /// Returns whether the button was pressed this frame, as the second (or later) click in a row
+    pub fn is_double_clicked(&self, button: MouseButton) -> bool {
+        self.is_button_pressed(button) && self.click_count(button) >= 2
+    }
+
+    /// Returns how many times in a row the button has been clicked, each within
+    /// `double_click_time` of the last, or 0 if another button was clicked since
+    pub fn click_count(&self, button: MouseButton) -> u32 {
+        match self.last_click {
+            Some((last, _)) if last == button => self.click_count,
+            _ => 0,
+        }
+    }
+
     pub fn is_moved(&self) -> bool {
         self.moved
     }
@@ -58,14 +93,40 @@ impl Mouse {
         self.on_window
     }
 
+    /// Position of the cursor within the window, in physical pixels from the top left
     pub fn position(&self) -> Vec2f {
         Vec2f::new(self.pos_x as f32, self.pos_y as f32)
     }
 
+    /// Sets how window positions map to the surface, which should be kept up to date with the
+    /// window's size and scaling for `surface_position` to be correct
+    pub fn set_surface_transform(&mut self, transform: SurfaceTransform) {
+        self.surface_transform = Some(transform);
+    }
+
+    /// Position of the cursor in surface pixels from the top left, such as for picking UI elements
+    /// drawn at the internal resolution. Returns `None` if the cursor is off the window, over the
+    /// bars around the surface, or if no transform has been set.
+    pub fn surface_position(&self) -> Option<Vec2f> {
+        if !self.on_window {
+            return None;
+        }
+
+        self.surface_transform
+            .and_then(|transform| transform.to_surface(self.pos_x, self.pos_y))
+            .map(|(x, y)| Vec2f::new(x as f32, y as f32))
+    }
+
     pub fn delta(&self) -> Vec2f {
         Vec2f::new(self.delta_x as f32, self.delta_y as f32)
     }
 
+    /// Returns how far the wheel was scrolled this frame, in lines, where positive y is away from
+    /// the user
+    pub fn wheel_delta(&self) -> Vec2f {
+        Vec2f::new(self.wheel_x as f32, self.wheel_y as f32)
+    }
+
     fn on_pointer_event(&mut self, event: &PointerEvent) {
         match event {
             PointerEvent::MouseMoved { delta: (x, y) } => {
@@ -74,10 +135,23 @@ impl Mouse {
                 self.moved = true;
             }
 
+            PointerEvent::CursorMoved { position: (x, y) } => {
+                self.pos_x = *x;
+                self.pos_y = *y;
+                self.on_window = true;
+            }
+
+            PointerEvent::MouseWheel { delta: (x, y) } => {
+                self.wheel_x += x;
+                self.wheel_y += y;
+            }
+
             PointerEvent::MouseButtonPressed(button) => {
                 let state = &mut self.button_states[*button as usize];
                 state.pressed = true;
                 state.held = true;
+
+                self.register_click(*button, Instant::now());
             }
 
             PointerEvent::MouseButtonReleased(button) => {
@@ -95,6 +169,18 @@ impl Mouse {
         }
     }
 
+    fn register_click(&mut self, button: MouseButton, now: Instant) {
+        let repeated = match self.last_click {
+            Some((last, time)) => {
+                last == button && now.duration_since(time) <= self.double_click_time
+            }
+            None => false,
+        };
+
+        self.click_count = if repeated { self.click_count + 1 } else { 1 };
+        self.last_click = Some((button, now));
+    }
+
     fn update(&mut self) {
         for state in self.button_states.iter_mut() {
             state.pressed = false;
@@ -104,11 +190,15 @@ impl Mouse {
         self.moved = false;
         self.delta_x = 0.0;
         self.delta_y = 0.0;
+        self.wheel_x = 0.0;
+        self.wheel_y = 0.0;
     }
 }
 
 pub struct Keyboard {
     key_states: Vec<State>,
+    /// Characters typed this frame
+    text: String,
 }
 
 impl Keyboard {
@@ -116,6 +206,7 @@ impl Keyboard {
         Keyboard {
             // `key_states` length of 106 corresponds to `KeyCode` enum length of 106
             key_states: vec![State::default(); 108],
+            text: String::new(),
         }
     }
 
@@ -131,6 +222,12 @@ impl Keyboard {
         self.key_states[keycode as usize].released
     }
 
+    /// Returns the text typed this frame, with any control characters removed. Editing keys, such as
+    /// backspace and enter, should be checked for as key presses instead.
+    pub fn text(&self) -> &str {
+        &self.text
+    }
+
     fn handle_keyboard_event(&mut self, event: &KeyboardEvent) {
         match event {
             KeyboardEvent::KeyPressed(keycode) => {
@@ -143,6 +240,12 @@ impl Keyboard {
                 self.key_states[*keycode as usize].held = false;
                 self.key_states[*keycode as usize].released = true;
             }
+
+            KeyboardEvent::CharacterInput(character) => {
+                if !character.is_control() {
+                    self.text.push(*character);
+                }
+            }
         }
     }
 
@@ -151,12 +254,22 @@ impl Keyboard {
             state.pressed = false;
             state.released = false;
         }
+
+        self.text.clear();
     }
 }
 
 pub struct Input {
     pub keyboard: Keyboard,
     pub mouse: Mouse,
+    pub gamepad: Gamepad,
+    pub bindings: Bindings,
+    text_capture: bool,
+    /// Rate of looking around with the right stick fully pushed, in the same units as mouse movement
+    pub stick_look_speed: f32,
+    /// Lets movement go up to 1 along each axis rather than in total, as in classic engines, so
+    /// that moving forwards and strafing at once is faster than either alone
+    pub classic_diagonals: bool,
 }
 
 impl Input {
@@ -164,7 +277,87 @@ impl Input {
         Input {
             keyboard: Keyboard::new(),
             mouse: Mouse::new(),
+            gamepad: Gamepad::new(),
+            bindings: Bindings::default(),
+            text_capture: false,
+            stick_look_speed: 25.0,
+            classic_diagonals: false,
+        }
+    }
+
+    /// Starts or stops capturing input for typing text, e.g. whilst a console is open. Whilst
+    /// capturing, no actions trigger and there is no movement or looking around, so that typing
+    /// doesn't also control the game. The devices themselves can still be queried directly.
+    pub fn set_text_capture(&mut self, capture: bool) {
+        self.text_capture = capture;
+    }
+
+    pub fn is_capturing_text(&self) -> bool {
+        self.text_capture
+    }
+
+    /// Returns the text typed this frame, or nothing if the keyboard isn't being captured
+    pub fn text(&self) -> &str {
+        if self.text_capture {
+            self.keyboard.text()
+        } else {
+            ""
+        }
+    }
+
+    /// Returns the direction to move in from any device, where x is to the right and y is forwards,
+    /// and its length is how fast to move, which is only partway for a partly pushed stick. This is
+    /// at most 1, or 1 along each axis with [Self::classic_diagonals]. The movement actions only
+    /// count whilst the mouse is grabbed.
+    pub fn movement(&self) -> Vec2f {
+        if self.text_capture {
+            return Vec2f::ZERO;
+        }
+
+        let mut movement = self.gamepad.left_stick();
+
+        if self.mouse.is_grabbed() {
+            if self.is_action_held(Action::MoveForward) {
+                movement.y += 1.0;
+            } else if self.is_action_held(Action::MoveBackward) {
+                movement.y -= 1.0;
+            }
+
+            if self.is_action_held(Action::StrafeRight) {
+                movement.x += 1.0;
+            } else if self.is_action_held(Action::StrafeLeft) {
+                movement.x -= 1.0;
+            }
+        }
+
+        if self.classic_diagonals {
+            return Vec2f::new(movement.x.clamp(-1.0, 1.0), movement.y.clamp(-1.0, 1.0));
+        }
+
+        let magnitude = movement.magnitude();
+        if magnitude > 1.0 {
+            movement /= magnitude;
         }
+
+        movement
+    }
+
+    /// Returns how far to look around from any device, where x is to the right and y is upwards.
+    /// Mouse input only counts whilst the mouse is grabbed.
+    pub fn look(&self) -> Vec2f {
+        if self.text_capture {
+            return Vec2f::ZERO;
+        }
+
+        let mut look = self.gamepad.right_stick() * self.stick_look_speed;
+
+        if self.mouse.is_grabbed() {
+            let delta = self.mouse.delta();
+            // Negate y-axis to make up positive, as the y-axis is flipped in screen space
+            look += Vec2f::new(delta.x, -delta.y);
+        }
+
+        look
     }
 
     pub fn handle_event(&mut self, event: &Event) {
@@ -178,11 +371,15 @@ impl Input {
     pub fn update(&mut self) {
         self.keyboard.update();
         self.mouse.update();
+        self.gamepad.update();
+        self.gamepad.poll();
     }
 }
 
 #[cfg(test)]
 mod tests {
+    use window::{ScaleFilter, WindowSize};
+
     use super::*;
 
     #[test]
@@ -202,4 +399,105 @@ mod tests {
         mouse.on_pointer_event(&event);
         assert!(mouse.moved);
     }
+
+    #[test]
+    fn test_mouse_wheel() {
+        let mut mouse = Mouse::new();
+        mouse.on_pointer_event(&PointerEvent::MouseWheel { delta: (0.0, 1.0) });
+        mouse.on_pointer_event(&PointerEvent::MouseWheel { delta: (0.0, 2.0) });
+        assert_eq!(mouse.wheel_delta(), Vec2f::new(0.0, 3.0));
+
+        mouse.update();
+        assert_eq!(mouse.wheel_delta(), Vec2f::ZERO);
+    }
+
+    #[test]
+    fn test_surface_position() {
+        let mut mouse = Mouse::new();
+        mouse.on_pointer_event(&PointerEvent::CursorMoved {
+            position: (500.0, 200.0),
+        });
+        assert_eq!(mouse.position(), Vec2f::new(500.0, 200.0));
+        assert_eq!(mouse.surface_position(), None);
+
+        // Surface scaled up 2x, with bars down either side
+        mouse.set_surface_transform(SurfaceTransform::new(
+            WindowSize::new(320, 200),
+            WindowSize::new(1000, 400),
+            ScaleFilter::Nearest,
+        ));
+        assert_eq!(mouse.surface_position(), Some(Vec2f::new(160.0, 100.0)));
+
+        mouse.on_pointer_event(&PointerEvent::CursorMoved {
+            position: (50.0, 200.0),
+        });
+        assert_eq!(mouse.surface_position(), None);
+    }
+
+    #[test]
+    fn test_double_click() {
+        let mut mouse = Mouse::new();
+        let start = Instant::now();
+
+        mouse.register_click(MouseButton::Left, start);
+        assert_eq!(mouse.click_count(MouseButton::Left), 1);
+
+        mouse.register_click(MouseButton::Left, start + Duration::from_millis(200));
+        assert_eq!(mouse.click_count(MouseButton::Left), 2);
+
+        // Too slow
+        mouse.register_click(MouseButton::Left, start + Duration::from_millis(1000));
+        assert_eq!(mouse.click_count(MouseButton::Left), 1);
+
+        // A different button breaks the run
+        mouse.register_click(MouseButton::Right, start + Duration::from_millis(1100));
+        mouse.register_click(MouseButton::Left, start + Duration::from_millis(1200));
+        assert_eq!(mouse.click_count(MouseButton::Left), 1);
+        assert_eq!(mouse.click_count(MouseButton::Right), 0);
+
+        let mut mouse = Mouse::new();
+        let event = PointerEvent::MouseButtonPressed(MouseButton::Left);
+        mouse.on_pointer_event(&event);
+        assert!(!mouse.is_double_clicked(MouseButton::Left));
+        mouse.update();
+        mouse.on_pointer_event(&event);
+        assert!(mouse.is_double_clicked(MouseButton::Left));
+    }
+
+    #[test]
+    fn test_text_input() {
+        let mut input = Input::new();
+        for character in ['h', 'i', '\u{8}', '!'] {
+            input.handle_event(&Event::KeyboardEvent(KeyboardEvent::CharacterInput(
+                character,
+            )));
+        }
+
+        assert_eq!(input.keyboard.text(), "hi!");
+        assert_eq!(input.text(), "");
+
+        input.set_text_capture(true);
+        assert_eq!(input.text(), "hi!");
+
+        input.update();
+        assert_eq!(input.text(), "");
+    }
+
+    #[test]
+    fn test_movement() {
+        let mut input = Input::new();
+        input.mouse.grabbed = true;
+        input.handle_event(&Event::KeyboardEvent(KeyboardEvent::KeyPressed(KeyCode::W)));
+        input.handle_event(&Event::KeyboardEvent(KeyboardEvent::KeyPressed(KeyCode::D)));
+
+        let movement = input.movement();
+        assert!((movement.magnitude() - 1.0).abs() < 1e-6);
+        assert!(movement.x > 0.0 && movement.y > 0.0);
+
+        input.classic_diagonals = true;
+        assert_eq!(input.movement(), Vec2f::new(1.0, 1.0));
+
+        input.mouse.grabbed = false;
+        assert_eq!(input.movement(), Vec2f::ZERO);
+    }
 }
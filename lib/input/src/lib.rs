@@ -1,3 +1,7 @@
+mod action;
+mod gamepad;
 mod input;
 
-pub use input::*;
\ No newline at end of file
+pub use action::*;
+pub use gamepad::*;
+pub use input::*;
@@ -0,0 +1,357 @@
+use std::{
+    cell::Cell,
+    collections::VecDeque,
+    num::NonZeroUsize,
+    panic::{self, AssertUnwindSafe},
+    sync::{
+        atomic::{AtomicBool, AtomicUsize, Ordering},
+        mpsc::{self, Receiver, TryRecvError},
+        Arc, Condvar, Mutex,
+    },
+    thread::{self, JoinHandle},
+};
+
+use crate::scope::Scope;
+
+pub(crate) type Job = Box<dyn FnOnce() + Send + 'static>;
+
+thread_local! {
+    /// Identifies the job system and queue that belong to the current thread, if it is a worker
+    static WORKER: Cell<Option<(usize, usize)>> = const { Cell::new(None) };
+}
+
+pub(crate) struct Shared {
+    /// One queue per worker. Workers take jobs from the back of their own queue, and steal from the
+    /// front of other queues when theirs is empty.
+    queues: Vec<Mutex<VecDeque<Job>>>,
+    /// Number of jobs that have been queued, but not yet taken
+    pending: AtomicUsize,
+    /// Used to distribute jobs spawned from outside of the workers
+    next_queue: AtomicUsize,
+    shutdown: AtomicBool,
+
+    sleep_lock: Mutex<()>,
+    wake: Condvar,
+}
+
+impl Shared {
+    fn id(&self) -> usize {
+        self as *const Self as usize
+    }
+
+    /// Returns the index of the current thread's queue, if it is a worker of this job system
+    fn worker_index(&self) -> Option<usize> {
+        WORKER
+            .get()
+            .filter(|(id, _)| *id == self.id())
+            .map(|(_, index)| index)
+    }
+
+    pub(crate) fn push(&self, job: Job) {
+        let index = self.worker_index().unwrap_or_else(|| {
+            self.next_queue.fetch_add(1, Ordering::Relaxed) % self.queues.len()
+        });
+
+        // Counted before being queued, so that taking the job can never underflow the count
+        self.pending.fetch_add(1, Ordering::SeqCst);
+        self.queues[index].lock().unwrap().push_back(job);
+
+        // Lock is held whilst notifying, so that a worker cannot miss the wake up between checking
+        // for pending jobs and going to sleep
+        let _guard = self.sleep_lock.lock().unwrap();
+        self.wake.notify_one();
+    }
+
+    /// Takes a job, preferring the queue at `home`, and otherwise stealing from the other queues
+    fn take(&self, home: usize) -> Option<Job> {
+        if self.pending.load(Ordering::SeqCst) == 0 {
+            return None;
+        }
+
+        let job = self.queues[home].lock().unwrap().pop_back().or_else(|| {
+            (1..self.queues.len())
+                .map(|offset| (home + offset) % self.queues.len())
+                .find_map(|index| self.queues[index].lock().unwrap().pop_front())
+        });
+
+        if job.is_some() {
+            self.pending.fetch_sub(1, Ordering::SeqCst);
+        }
+
+        job
+    }
+
+    /// Runs a single queued job on the current thread, returning false if there were none. This
+    /// allows threads that are waiting on jobs to help, rather than block.
+    pub(crate) fn run_one(&self) -> bool {
+        let home = self.worker_index().unwrap_or(0);
+
+        match self.take(home) {
+            Some(job) => {
+                job();
+                true
+            }
+            None => false,
+        }
+    }
+
+    fn worker_loop(&self, index: usize) {
+        WORKER.set(Some((self.id(), index)));
+
+        loop {
+            if let Some(job) = self.take(index) {
+                job();
+                continue;
+            }
+
+            let guard = self.sleep_lock.lock().unwrap();
+
+            if self.shutdown.load(Ordering::SeqCst) {
+                break;
+            }
+
+            if self.pending.load(Ordering::SeqCst) == 0 {
+                drop(self.wake.wait(guard).unwrap());
+            }
+        }
+
+        WORKER.set(None);
+    }
+}
+
+/// A pool of worker threads that execute jobs, balancing work between them by stealing jobs from
+/// each other's queues.
+///
+/// Jobs can either be spawned independently with [JobSystem::spawn], which is suited to longer
+/// running work such as loading assets, or within a [JobSystem::scope], which allows jobs to borrow
+/// data from the caller's stack and guarantees they are all complete by the time the scope returns.
+pub struct JobSystem {
+    shared: Arc<Shared>,
+    workers: Vec<JoinHandle<()>>,
+}
+
+impl JobSystem {
+    pub fn new(worker_count: usize) -> Self {
+        let worker_count = worker_count.max(1);
+
+        let shared = Arc::new(Shared {
+            queues: (0..worker_count)
+                .map(|_| Mutex::new(VecDeque::new()))
+                .collect(),
+            pending: AtomicUsize::new(0),
+            next_queue: AtomicUsize::new(0),
+            shutdown: AtomicBool::new(false),
+
+            sleep_lock: Mutex::new(()),
+            wake: Condvar::new(),
+        });
+
+        let workers = (0..worker_count)
+            .map(|index| {
+                let shared = shared.clone();
+
+                thread::Builder::new()
+                    .name(format!("job-worker-{index}"))
+                    .spawn(move || shared.worker_loop(index))
+                    .expect("Failed to spawn job worker thread")
+            })
+            .collect();
+
+        Self { shared, workers }
+    }
+
+    /// Creates a job system with a worker for each available core, leaving one for the main thread
+    pub fn with_available_parallelism() -> Self {
+        let cores = thread::available_parallelism().map_or(1, NonZeroUsize::get);
+
+        Self::new(cores.saturating_sub(1))
+    }
+
+    pub fn worker_count(&self) -> usize {
+        self.workers.len()
+    }
+
+    /// Queues a job that may outlive the caller. The result can be retrieved through the returned
+    /// handle once the job has finished.
+    pub fn spawn<F, T>(&self, f: F) -> JobHandle<T>
+    where
+        F: FnOnce() -> T + Send + 'static,
+        T: Send + 'static,
+    {
+        let (sender, receiver) = mpsc::sync_channel(1);
+
+        self.shared.push(Box::new(move || {
+            let result = panic::catch_unwind(AssertUnwindSafe(f));
+            // The handle may have been dropped, in which case nobody wants the result
+            let _ = sender.send(result);
+        }));
+
+        JobHandle {
+            receiver,
+            result: None,
+        }
+    }
+
+    /// Creates a scope in which jobs can borrow non-'static data. All jobs spawned within the scope
+    /// are complete before this returns, with the calling thread helping to execute them whilst it
+    /// waits. If any job panics, the panic is propagated once all jobs are complete.
+    pub fn scope<'env, F, R>(&self, f: F) -> R
+    where
+        F: for<'scope> FnOnce(&'scope Scope<'scope, 'env>) -> R,
+    {
+        let scope = Scope::new(&self.shared);
+
+        let result = panic::catch_unwind(AssertUnwindSafe(|| f(&scope)));
+
+        // Jobs may still be borrowing from the environment, so we must wait even if `f` panicked
+        scope.wait();
+
+        match result {
+            Err(payload) => panic::resume_unwind(payload),
+            Ok(_) if scope.panicked() => panic!("A job panicked within a scope"),
+            Ok(result) => result,
+        }
+    }
+
+    /// Splits the slice into chunks and processes each of them in parallel, passing the index of the
+    /// first element in the chunk alongside it.
+    pub fn for_each_chunk_mut<T, F>(&self, items: &mut [T], chunk_size: usize, f: F)
+    where
+        T: Send,
+        F: Fn(usize, &mut [T]) + Sync,
+    {
+        let chunk_size = chunk_size.max(1);
+        let f = &f;
+
+        self.scope(|scope| {
+            for (i, chunk) in items.chunks_mut(chunk_size).enumerate() {
+                scope.spawn(move || f(i * chunk_size, chunk));
+            }
+        });
+    }
+}
+
+impl Drop for JobSystem {
+    fn drop(&mut self) {
+        {
+            let _guard = self.shared.sleep_lock.lock().unwrap();
+            self.shared.shutdown.store(true, Ordering::SeqCst);
+            self.shared.wake.notify_all();
+        }
+
+        for worker in self.workers.drain(..) {
+            let _ = worker.join();
+        }
+    }
+}
+
+/// Handle to the result of a job created with [JobSystem::spawn]
+pub struct JobHandle<T> {
+    receiver: Receiver<thread::Result<T>>,
+    result: Option<thread::Result<T>>,
+}
+
+impl<T> JobHandle<T> {
+    fn poll(&mut self) {
+        if self.result.is_none() {
+            match self.receiver.try_recv() {
+                Ok(result) => self.result = Some(result),
+                Err(TryRecvError::Empty) => {}
+                // Sender is only dropped without sending if the job system shut down first
+                Err(TryRecvError::Disconnected) => {}
+            }
+        }
+    }
+
+    pub fn is_finished(&mut self) -> bool {
+        self.poll();
+        self.result.is_some()
+    }
+
+    /// Returns the result if the job has finished, without blocking. Panics if the job panicked.
+    pub fn try_take(&mut self) -> Option<T> {
+        self.poll();
+
+        match self.result.take()? {
+            Ok(value) => Some(value),
+            Err(payload) => panic::resume_unwind(payload),
+        }
+    }
+
+    /// Blocks until the job has finished, returning its result. Panics if the job panicked.
+    pub fn wait(mut self) -> T {
+        let result = match self.result.take() {
+            Some(result) => result,
+            None => self
+                .receiver
+                .recv()
+                .expect("Job system shut down before the job was run"),
+        };
+
+        match result {
+            Ok(value) => value,
+            Err(payload) => panic::resume_unwind(payload),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn spawn_test() {
+        let jobs = JobSystem::new(2);
+
+        let handles: Vec<_> = (0..16).map(|i| jobs.spawn(move || i * 2)).collect();
+        let results: Vec<_> = handles.into_iter().map(JobHandle::wait).collect();
+
+        assert_eq!(results, (0..16).map(|i| i * 2).collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn scope_borrow_test() {
+        let jobs = JobSystem::new(3);
+        let mut values = vec![0; 1000];
+
+        jobs.for_each_chunk_mut(&mut values, 64, |start, chunk| {
+            for (i, value) in chunk.iter_mut().enumerate() {
+                *value = start + i;
+            }
+        });
+
+        assert!(values.iter().enumerate().all(|(i, value)| *value == i));
+    }
+
+    #[test]
+    fn nested_scope_test() {
+        let jobs = JobSystem::new(2);
+        let counter = AtomicUsize::new(0);
+
+        jobs.scope(|outer| {
+            for _ in 0..4 {
+                outer.spawn(|| {
+                    jobs.scope(|inner| {
+                        for _ in 0..4 {
+                            inner.spawn(|| {
+                                counter.fetch_add(1, Ordering::SeqCst);
+                            });
+                        }
+                    });
+                });
+            }
+        });
+
+        assert_eq!(counter.load(Ordering::SeqCst), 16);
+    }
+
+    #[test]
+    #[should_panic]
+    fn scope_panic_test() {
+        let jobs = JobSystem::new(1);
+
+        jobs.scope(|scope| {
+            scope.spawn(|| panic!("Job failed"));
+        });
+    }
+}
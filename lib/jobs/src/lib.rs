@@ -0,0 +1,5 @@
+mod job_system;
+mod scope;
+
+pub use job_system::{JobHandle, JobSystem};
+pub use scope::Scope;
@@ -0,0 +1,73 @@
+use std::{
+    marker::PhantomData,
+    panic::{self, AssertUnwindSafe},
+    sync::{
+        atomic::{AtomicBool, AtomicUsize, Ordering},
+        Arc,
+    },
+    thread,
+};
+
+use crate::job_system::{Job, Shared};
+
+/// Tracks the jobs spawned within [crate::JobSystem::scope], which may borrow anything that
+/// outlives the scope.
+pub struct Scope<'scope, 'env: 'scope> {
+    shared: &'scope Arc<Shared>,
+    /// Number of spawned jobs that have not yet finished
+    running: AtomicUsize,
+    panicked: AtomicBool,
+
+    // Invariance over both lifetimes, as with `std::thread::Scope`
+    scope: PhantomData<&'scope mut &'scope ()>,
+    env: PhantomData<&'env mut &'env ()>,
+}
+
+impl<'scope, 'env> Scope<'scope, 'env> {
+    pub(crate) fn new(shared: &'scope Arc<Shared>) -> Self {
+        Self {
+            shared,
+            running: AtomicUsize::new(0),
+            panicked: AtomicBool::new(false),
+
+            scope: PhantomData,
+            env: PhantomData,
+        }
+    }
+
+    /// Queues a job that may borrow data from outside of the scope
+    pub fn spawn<F>(&'scope self, f: F)
+    where
+        F: FnOnce() + Send + 'scope,
+    {
+        self.running.fetch_add(1, Ordering::SeqCst);
+
+        let job: Box<dyn FnOnce() + Send + 'scope> = Box::new(move || {
+            if panic::catch_unwind(AssertUnwindSafe(f)).is_err() {
+                self.panicked.store(true, Ordering::SeqCst);
+            }
+
+            self.running.fetch_sub(1, Ordering::SeqCst);
+        });
+
+        // SAFETY: The job only borrows data that lives for 'scope, and the scope does not return
+        // until `running` reaches zero, which is after every job has finished using its borrows.
+        let job: Job = unsafe { core::mem::transmute(job) };
+
+        self.shared.push(job);
+    }
+
+    /// Blocks until all jobs spawned in this scope have finished, executing queued jobs in the
+    /// meantime
+    pub(crate) fn wait(&self) {
+        while self.running.load(Ordering::SeqCst) != 0 {
+            if !self.shared.run_one() {
+                thread::yield_now();
+            }
+        }
+    }
+
+    pub(crate) fn panicked(&self) -> bool {
+        self.panicked.load(Ordering::SeqCst)
+    }
+}
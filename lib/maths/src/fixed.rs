@@ -0,0 +1,147 @@
+use std::ops::{Add, AddAssign, Mul, Neg, Sub, SubAssign};
+
+/// Signed 16.16 fixed point number, as used by classic software renderers in place of floats.
+///
+/// Arithmetic wraps on overflow rather than panicking. This suits stepping texture coordinates,
+/// where only the low bits are used once masked to the size of the texture, but means results are
+/// meaningless beyond about ±32768.
+#[repr(transparent)]
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Fixed(i32);
+
+impl Fixed {
+    /// Number of bits after the binary point
+    pub const FRAC_BITS: u32 = 16;
+
+    pub const ZERO: Self = Self(0);
+    pub const ONE: Self = Self(1 << Self::FRAC_BITS);
+    pub const MIN: Self = Self(i32::MIN);
+    pub const MAX: Self = Self(i32::MAX);
+
+    pub const fn from_bits(bits: i32) -> Self {
+        Self(bits)
+    }
+
+    pub const fn to_bits(self) -> i32 {
+        self.0
+    }
+
+    pub const fn from_int(value: i32) -> Self {
+        Self(value << Self::FRAC_BITS)
+    }
+
+    /// Converts from a float, saturating at [Fixed::MIN] and [Fixed::MAX]. NaN becomes zero.
+    pub fn from_f32(value: f32) -> Self {
+        Self((value * Self::ONE.0 as f32) as i32)
+    }
+
+    /// Converts from a float, keeping only the low 32 bits of the result, like the arithmetic. Values
+    /// out of range wrap around instead of saturating, so the fractional part and the low bits of the
+    /// integer part are kept.
+    pub fn from_f32_wrapping(value: f32) -> Self {
+        Self((value * Self::ONE.0 as f32) as i64 as i32)
+    }
+
+    pub fn to_f32(self) -> f32 {
+        self.0 as f32 / Self::ONE.0 as f32
+    }
+
+    /// Largest integer less than or equal to the number, so negative numbers round away from zero
+    pub const fn floor(self) -> i32 {
+        self.0 >> Self::FRAC_BITS
+    }
+
+    pub const fn abs(self) -> Self {
+        Self(self.0.wrapping_abs())
+    }
+}
+
+impl Add for Fixed {
+    type Output = Self;
+
+    fn add(self, rhs: Self) -> Self {
+        Self(self.0.wrapping_add(rhs.0))
+    }
+}
+
+impl AddAssign for Fixed {
+    fn add_assign(&mut self, rhs: Self) {
+        self.0 = self.0.wrapping_add(rhs.0);
+    }
+}
+
+impl Sub for Fixed {
+    type Output = Self;
+
+    fn sub(self, rhs: Self) -> Self {
+        Self(self.0.wrapping_sub(rhs.0))
+    }
+}
+
+impl SubAssign for Fixed {
+    fn sub_assign(&mut self, rhs: Self) {
+        self.0 = self.0.wrapping_sub(rhs.0);
+    }
+}
+
+impl Mul for Fixed {
+    type Output = Self;
+
+    fn mul(self, rhs: Self) -> Self {
+        Self(((self.0 as i64 * rhs.0 as i64) >> Self::FRAC_BITS) as i32)
+    }
+}
+
+impl Neg for Fixed {
+    type Output = Self;
+
+    fn neg(self) -> Self {
+        Self(self.0.wrapping_neg())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn converts_to_and_from_floats() {
+        assert_eq!(Fixed::from_f32(1.0), Fixed::ONE);
+        assert_eq!(Fixed::from_f32(-2.5).to_f32(), -2.5);
+        assert_eq!(Fixed::from_int(3).to_f32(), 3.0);
+
+        // Saturates by default, but can wrap instead
+        assert_eq!(Fixed::from_f32(1.0e6), Fixed::MAX);
+        assert_eq!(Fixed::from_f32(f32::NAN), Fixed::ZERO);
+        assert_eq!(Fixed::from_f32_wrapping(65536.0 + 1.5).to_f32(), 1.5);
+    }
+
+    #[test]
+    fn floor_rounds_down() {
+        assert_eq!(Fixed::from_f32(2.75).floor(), 2);
+        assert_eq!(Fixed::from_f32(-0.25).floor(), -1);
+        assert_eq!(Fixed::from_int(-3).floor(), -3);
+    }
+
+    #[test]
+    fn arithmetic() {
+        let a = Fixed::from_f32(1.5);
+        let b = Fixed::from_f32(-0.25);
+
+        assert_eq!((a + b).to_f32(), 1.25);
+        assert_eq!((a - b).to_f32(), 1.75);
+        assert_eq!((a * b).to_f32(), -0.375);
+        assert_eq!((-a).to_f32(), -1.5);
+        assert_eq!(b.abs().to_f32(), 0.25);
+    }
+
+    #[test]
+    fn stepping_wraps_without_losing_low_bits() {
+        let mut coord = Fixed::from_f32(32767.5);
+        coord += Fixed::ONE;
+
+        // Wrapped to the bottom of the range, but still in the same place within any power of two
+        assert!(coord < Fixed::ZERO);
+        assert_eq!(coord.floor() & 63, 32768 & 63);
+    }
+}
@@ -31,6 +31,17 @@ impl AABB {
             && self.min.y <= other.min.y
             && self.max.y >= other.max.y;
     }
+
+    /// Corners of the box, anti-clockwise from the minimum. These aren't stored, so aren't available
+    /// from [Shape::points].
+    pub fn corners(&self) -> [Vec2f; 4] {
+        [
+            self.min,
+            Vec2f::new(self.max.x, self.min.y),
+            self.max,
+            Vec2f::new(self.min.x, self.max.y),
+        ]
+    }
 }
 
 impl Shape for AABB {
@@ -4,6 +4,7 @@ use crate::linear::Vec2f;
 
 use super::{shape::Shape, Segment, AABB};
 
+#[derive(Debug, Clone)]
 pub struct Circle {
     pub centre: Vec2f,
     pub radius: f32,
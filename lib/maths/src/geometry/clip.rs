@@ -0,0 +1,185 @@
+use crate::linear::Vec2f;
+
+use super::{Polygon, Segment, Shape};
+
+/// Pieces smaller than this are discarded, as they only result from rounding errors
+const MIN_AREA: f32 = 1e-6;
+
+impl Polygon {
+    /// Keeps the part of the polygon to the left of the infinite line through the edge (i.e. the
+    /// inside, if the edge belongs to a counter-clockwise polygon). If nothing remains, the
+    /// returned polygon has no vertices.
+    pub fn clip_to_line(&self, edge: &Segment) -> Polygon {
+        let direction = edge.b - edge.a;
+        let side = |point: Vec2f| direction.cross(point - edge.a);
+
+        let mut vertices = Vec::with_capacity(self.vertices.len() + 1);
+
+        for i in 0..self.vertices.len() {
+            let current = self.vertices[i];
+            let next = self.vertices[(i + 1) % self.vertices.len()];
+
+            let current_side = side(current);
+            let next_side = side(next);
+
+            if current_side >= 0.0 {
+                vertices.push(current);
+            }
+
+            // Add the point where the polygon's edge crosses the line
+            if (current_side > 0.0 && next_side < 0.0) || (current_side < 0.0 && next_side > 0.0) {
+                let t = current_side / (current_side - next_side);
+                vertices.push(current + (next - current) * t);
+            }
+        }
+
+        if vertices.len() < 3 {
+            vertices.clear();
+        }
+
+        Polygon::from_vertices(vertices)
+    }
+
+    /// Edges of the polygon, running counter-clockwise regardless of its winding order
+    fn counter_clockwise_edges(&self) -> impl Iterator<Item = Segment> + '_ {
+        let reverse = self.signed_area() < 0.0;
+
+        (0..self.vertices.len()).map(move |i| {
+            let a = self.vertices[i];
+            let b = self.vertices[(i + 1) % self.vertices.len()];
+
+            if reverse {
+                Segment::new(b, a)
+            } else {
+                Segment::new(a, b)
+            }
+        })
+    }
+
+    /// Returns the region covered by both polygons, using the Sutherland-Hodgman algorithm. The
+    /// clipping polygon must be convex, whereas this one may be concave (in which case separate
+    /// parts of the result are joined by zero-width edges). If the polygons do not overlap, the
+    /// returned polygon has no vertices.
+    pub fn intersection(&self, clipper: &Polygon) -> Polygon {
+        let mut result = self.clone();
+
+        for edge in clipper.counter_clockwise_edges() {
+            result = result.clip_to_line(&edge);
+
+            if result.vertices.is_empty() {
+                break;
+            }
+        }
+
+        result
+    }
+
+    /// Returns the region covered by this polygon but not the clipping polygon, which must be
+    /// convex. The region is split into pieces, which are convex if this polygon is.
+    pub fn difference(&self, clipper: &Polygon) -> Vec<Polygon> {
+        let mut pieces = Vec::new();
+        let mut remaining = self.clone();
+
+        // Each edge of the clipper cuts off the part of the remaining polygon that lies outside it
+        for edge in clipper.counter_clockwise_edges() {
+            let outside = remaining.clip_to_line(&Segment::new(edge.b, edge.a));
+
+            if outside.area() > MIN_AREA {
+                pieces.push(outside);
+            }
+
+            remaining = remaining.clip_to_line(&edge);
+
+            if remaining.vertices.is_empty() {
+                break;
+            }
+        }
+
+        pieces
+    }
+
+    /// Returns the region covered by either polygon, as a set of pieces that do not overlap. The
+    /// other polygon must be convex, and is always the first piece.
+    pub fn union(&self, other: &Polygon) -> Vec<Polygon> {
+        let mut pieces = vec![other.clone()];
+        pieces.extend(self.difference(other));
+
+        pieces
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn assert_approx_eq(a: f32, b: f32) {
+        assert!((a - b).abs() < 1e-4, "{a} != {b}");
+    }
+
+    #[test]
+    fn clip_to_line_test() {
+        let square = Polygon::from_rect(Vec2f::ZERO, Vec2f::uniform(2.0));
+
+        // Keep the top half
+        let clipped = square.clip_to_line(&Segment::new(Vec2f::ZERO, Vec2f::new(1.0, 0.0)));
+        assert_eq!(clipped.vertices.len(), 4);
+        assert_approx_eq(clipped.area(), 2.0);
+        assert!(clipped.vertices.iter().all(|vertex| vertex.y >= 0.0));
+
+        // Line is completely outside
+        let clipped =
+            square.clip_to_line(&Segment::new(Vec2f::new(0.0, 2.0), Vec2f::new(1.0, 2.0)));
+        assert!(clipped.vertices.is_empty());
+    }
+
+    #[test]
+    fn intersection_test() {
+        let a = Polygon::from_rect(Vec2f::ZERO, Vec2f::uniform(2.0));
+        let b = Polygon::from_rect(Vec2f::uniform(1.0), Vec2f::uniform(2.0));
+
+        let overlap = a.intersection(&b);
+        assert_approx_eq(overlap.area(), 1.0);
+        assert_approx_eq(overlap.centre().x, 0.5);
+        assert_approx_eq(overlap.centre().y, 0.5);
+
+        // Clipper winding order doesn't matter
+        let mut reversed = b.clone();
+        reversed.vertices.reverse();
+        assert_approx_eq(a.intersection(&reversed).area(), 1.0);
+
+        // No overlap
+        let c = Polygon::from_rect(Vec2f::uniform(5.0), Vec2f::uniform(2.0));
+        assert!(a.intersection(&c).vertices.is_empty());
+    }
+
+    #[test]
+    fn difference_test() {
+        let a = Polygon::from_rect(Vec2f::ZERO, Vec2f::uniform(2.0));
+        let b = Polygon::from_rect(Vec2f::uniform(1.0), Vec2f::uniform(2.0));
+
+        let pieces = a.difference(&b);
+        assert_eq!(pieces.len(), 2);
+        assert_approx_eq(pieces.iter().map(|piece| piece.area()).sum(), 3.0);
+        assert!(pieces.iter().all(|piece| piece.is_convex()));
+
+        // Completely covered
+        let c = Polygon::from_rect(Vec2f::ZERO, Vec2f::uniform(4.0));
+        assert!(a.difference(&c).is_empty());
+    }
+
+    #[test]
+    fn union_test() {
+        let a = Polygon::from_rect(Vec2f::ZERO, Vec2f::uniform(2.0));
+        let b = Polygon::from_rect(Vec2f::uniform(1.0), Vec2f::uniform(2.0));
+
+        let pieces = a.union(&b);
+        assert_approx_eq(pieces.iter().map(|piece| piece.area()).sum(), 7.0);
+
+        // Pieces don't overlap each other
+        for (i, piece) in pieces.iter().enumerate() {
+            for other in pieces.iter().skip(i + 1) {
+                assert_approx_eq(piece.intersection(other).area(), 0.0);
+            }
+        }
+    }
+}
@@ -0,0 +1,273 @@
+use crate::linear::Vec2f;
+
+use super::{Segment, AABB};
+
+/// Divides an area into equally sized square cells, where each cell lists the items whose bounds
+/// overlap it. This narrows down which items are near a point, box or line, without checking every
+/// item.
+///
+/// Items that lie partly outside of the area are kept in the cells along its edge, and queries
+/// outside of the area look in those cells too, so nothing is missed if items move beyond the area
+/// after the grid is created.
+#[derive(Debug, Clone)]
+pub struct UniformGrid<T> {
+    origin: Vec2f,
+    cell_size: f32,
+    columns: usize,
+    rows: usize,
+    /// Row by row
+    cells: Vec<Vec<T>>,
+}
+
+// Derived `Default` would needlessly require `T: Default`
+impl<T> Default for UniformGrid<T> {
+    fn default() -> Self {
+        Self {
+            origin: Vec2f::ZERO,
+            cell_size: 0.0,
+            columns: 0,
+            rows: 0,
+            cells: Vec::new(),
+        }
+    }
+}
+
+impl<T: Copy + PartialEq> UniformGrid<T> {
+    pub fn new(bounds: &AABB, cell_size: f32) -> Self {
+        let size = bounds.max - bounds.min;
+        let columns = (size.x / cell_size).max(0.0) as usize + 1;
+        let rows = (size.y / cell_size).max(0.0) as usize + 1;
+
+        Self {
+            origin: bounds.min,
+            cell_size,
+            columns,
+            rows,
+            cells: vec![Vec::new(); columns * rows],
+        }
+    }
+
+    /// Returns whether the grid has no cells, as is the case for a default grid
+    pub fn is_empty(&self) -> bool {
+        self.cells.is_empty()
+    }
+
+    /// Removes all items, keeping the cells
+    pub fn clear(&mut self) {
+        for cell in self.cells.iter_mut() {
+            cell.clear();
+        }
+    }
+
+    /// Adds an item to every cell overlapped by its bounds
+    pub fn insert(&mut self, item: T, bounds: &AABB) {
+        if self.is_empty() {
+            return;
+        }
+
+        let (min_column, min_row) = self.cell(bounds.min);
+        let (max_column, max_row) = self.cell(bounds.max);
+
+        for row in min_row..=max_row {
+            for column in min_column..=max_column {
+                self.cells[row * self.columns + column].push(item);
+            }
+        }
+    }
+
+    /// Adds an item to every cell that the segment passes through
+    pub fn insert_segment(&mut self, item: T, segment: &Segment) {
+        if self.is_empty() {
+            return;
+        }
+
+        let ((min_column, min_row), (max_column, max_row)) = self.segment_range(segment);
+
+        for row in min_row..=max_row {
+            for column in min_column..=max_column {
+                if self.segment_overlaps_cell(segment, column, row) {
+                    self.cells[row * self.columns + column].push(item);
+                }
+            }
+        }
+    }
+
+    /// Removes all copies of an item
+    pub fn remove(&mut self, item: T) {
+        self.retain(|other| *other != item);
+    }
+
+    /// Keeps only the items for which the predicate returns true
+    pub fn retain(&mut self, mut predicate: impl FnMut(&T) -> bool) {
+        for cell in self.cells.iter_mut() {
+            cell.retain(&mut predicate);
+        }
+    }
+
+    /// Returns the items in the cell containing the point
+    pub fn query_point(&self, point: Vec2f) -> &[T] {
+        if self.is_empty() {
+            return &[];
+        }
+
+        let (column, row) = self.cell(point);
+        &self.cells[row * self.columns + column]
+    }
+
+    /// Adds the items in the cells overlapped by the bounds to `items`, skipping any that are
+    /// already in it
+    pub fn query_aabb(&self, bounds: &AABB, items: &mut Vec<T>) {
+        if self.is_empty() {
+            return;
+        }
+
+        let (min_column, min_row) = self.cell(bounds.min);
+        let (max_column, max_row) = self.cell(bounds.max);
+
+        for row in min_row..=max_row {
+            for column in min_column..=max_column {
+                add_unique(items, &self.cells[row * self.columns + column]);
+            }
+        }
+    }
+
+    /// Adds the items in the cells that the segment passes through to `items`, skipping any that are
+    /// already in it
+    pub fn query_segment(&self, segment: &Segment, items: &mut Vec<T>) {
+        if self.is_empty() {
+            return;
+        }
+
+        let ((min_column, min_row), (max_column, max_row)) = self.segment_range(segment);
+
+        for row in min_row..=max_row {
+            for column in min_column..=max_column {
+                if self.segment_overlaps_cell(segment, column, row) {
+                    add_unique(items, &self.cells[row * self.columns + column]);
+                }
+            }
+        }
+    }
+
+    /// Returns the first and last column and row of the cells overlapped by the segment's bounds
+    fn segment_range(&self, segment: &Segment) -> ((usize, usize), (usize, usize)) {
+        let min = Vec2f::new(segment.a.x.min(segment.b.x), segment.a.y.min(segment.b.y));
+        let max = Vec2f::new(segment.a.x.max(segment.b.x), segment.a.y.max(segment.b.y));
+
+        (self.cell(min), self.cell(max))
+    }
+
+    /// Returns whether the segment passes through a cell. Cells along the edge of the grid also hold
+    /// anything beyond the edge, so the segment is always treated as passing through them.
+    fn segment_overlaps_cell(&self, segment: &Segment, column: usize, row: usize) -> bool {
+        if column == 0 || row == 0 || column == self.columns - 1 || row == self.rows - 1 {
+            return true;
+        }
+
+        let min = self.origin + Vec2f::new(column as f32, row as f32) * self.cell_size;
+        let max = min + Vec2f::uniform(self.cell_size);
+
+        segment.overlaps_bounds(&AABB::new(min, max))
+    }
+
+    /// Returns the column and row of the cell containing the point, or the nearest cell if it lies
+    /// outside of the grid
+    fn cell(&self, point: Vec2f) -> (usize, usize) {
+        let offset = (point - self.origin) / self.cell_size;
+
+        (
+            (offset.x.max(0.0) as usize).min(self.columns - 1),
+            (offset.y.max(0.0) as usize).min(self.rows - 1),
+        )
+    }
+}
+
+fn add_unique<T: Copy + PartialEq>(items: &mut Vec<T>, cell: &[T]) {
+    for item in cell.iter() {
+        if !items.contains(item) {
+            items.push(*item);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn grid() -> UniformGrid<usize> {
+        UniformGrid::new(
+            &AABB::new(Vec2f::new(0.0, 0.0), Vec2f::new(39.0, 19.0)),
+            10.0,
+        )
+    }
+
+    #[test]
+    fn query_point_test() {
+        let mut grid = grid();
+        grid.insert(1, &AABB::new(Vec2f::new(5.0, 5.0), Vec2f::new(15.0, 8.0)));
+        grid.insert(
+            2,
+            &AABB::new(Vec2f::new(32.0, 12.0), Vec2f::new(50.0, 50.0)),
+        );
+
+        assert_eq!(grid.query_point(Vec2f::new(12.0, 2.0)), &[1]);
+        assert_eq!(grid.query_point(Vec2f::new(25.0, 5.0)), &[] as &[usize]);
+        // Beyond the edge of the grid
+        assert_eq!(grid.query_point(Vec2f::new(45.0, 45.0)), &[2]);
+
+        grid.remove(1);
+        assert_eq!(grid.query_point(Vec2f::new(12.0, 2.0)), &[] as &[usize]);
+    }
+
+    #[test]
+    fn query_aabb_test() {
+        let mut grid = grid();
+        grid.insert(1, &AABB::new(Vec2f::new(0.0, 0.0), Vec2f::new(25.0, 5.0)));
+        grid.insert(
+            2,
+            &AABB::new(Vec2f::new(35.0, 15.0), Vec2f::new(36.0, 16.0)),
+        );
+
+        let mut items = Vec::new();
+        grid.query_aabb(
+            &AABB::new(Vec2f::new(0.0, 0.0), Vec2f::new(29.0, 9.0)),
+            &mut items,
+        );
+        assert_eq!(items, vec![1]);
+    }
+
+    #[test]
+    fn query_segment_test() {
+        let mut grid = UniformGrid::new(
+            &AABB::new(Vec2f::new(0.0, 0.0), Vec2f::new(59.0, 59.0)),
+            10.0,
+        );
+        grid.insert_segment(
+            1,
+            &Segment::new(Vec2f::new(1.0, 1.0), Vec2f::new(58.0, 58.0)),
+        );
+        grid.insert(
+            2,
+            &AABB::new(Vec2f::new(32.0, 12.0), Vec2f::new(38.0, 18.0)),
+        );
+
+        // The diagonal segment is only in the cells that it passes through
+        assert_eq!(grid.query_point(Vec2f::new(35.0, 15.0)), &[2]);
+        assert_eq!(grid.query_point(Vec2f::new(35.0, 35.0)), &[1]);
+
+        let mut items = Vec::new();
+        grid.query_segment(
+            &Segment::new(Vec2f::new(35.0, 45.0), Vec2f::new(35.0, 15.0)),
+            &mut items,
+        );
+        items.sort_unstable();
+        assert_eq!(items, vec![1, 2]);
+
+        items.clear();
+        grid.query_segment(
+            &Segment::new(Vec2f::new(35.0, 15.0), Vec2f::new(45.0, 15.0)),
+            &mut items,
+        );
+        assert_eq!(items, vec![2]);
+    }
+}
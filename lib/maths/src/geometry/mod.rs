@@ -1,5 +1,7 @@
 mod aabb;
 mod circle;
+mod clip;
+mod grid;
 mod polygon;
 mod segment;
 mod shape;
@@ -8,6 +10,7 @@ mod sat;
 
 pub use aabb::AABB;
 pub use circle::Circle;
+pub use grid::UniformGrid;
 pub use polygon::Polygon;
 pub use segment::Segment;
 pub use shape::Shape;
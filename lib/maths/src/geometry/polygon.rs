@@ -6,6 +6,7 @@ use super::{Segment, Shape, AABB};
 const INFINITY: f32 = 1e30;
 
 
+#[derive(Debug, Clone)]
 pub struct Polygon {
     pub vertices: Vec<Vec2f>,
 }
@@ -31,6 +32,193 @@ impl Polygon {
             centre + Vec2f::new(-half_dimensions.x, half_dimensions.y),
         ]);
     }
+
+    /// Returns the area of the polygon, which is positive if the vertices are in counter-clockwise
+    /// order, and negative if clockwise.
+    pub fn signed_area(&self) -> f32 {
+        let mut area = 0.0;
+
+        for i in 0..self.vertices.len() {
+            let j = (i + 1) % self.vertices.len();
+
+            area += self.vertices[i].cross(self.vertices[j]);
+        }
+
+        return area * 0.5;
+    }
+
+    /// Whether every interior angle is at most 180 degrees. Collinear vertices are allowed, but the
+    /// polygon must have a non-zero area and not intersect itself.
+    pub fn is_convex(&self) -> bool {
+        let indices: Vec<usize> = (0..self.vertices.len()).collect();
+
+        return self.is_convex_subset(&indices);
+    }
+
+    /// Same as [Polygon::is_convex], for the polygon formed by a subset of the vertices
+    fn is_convex_subset(&self, indices: &[usize]) -> bool {
+        let count = indices.len();
+
+        if count < 3 {
+            return false;
+        }
+
+        let mut orientation = 0.0;
+
+        for i in 0..count {
+            let a = self.vertices[indices[i]];
+            let b = self.vertices[indices[(i + 1) % count]];
+            let c = self.vertices[indices[(i + 2) % count]];
+
+            let turn = (b - a).cross(c - b);
+
+            if turn == 0.0 {
+                continue;
+            }
+
+            if orientation == 0.0 {
+                orientation = turn.signum();
+            } else if turn.signum() != orientation {
+                return false;
+            }
+        }
+
+        return orientation != 0.0;
+    }
+
+    /// Splits the polygon into convex pieces, returning the indices of their vertices. This uses the
+    /// Hertel-Mehlhorn algorithm: the polygon is triangulated, then neighbouring pieces are merged
+    /// wherever the result remains convex. There are at most four times as many pieces as in the
+    /// optimal decomposition.
+    pub fn convex_decomposition(&self) -> Vec<Vec<usize>> {
+        if self.is_convex() {
+            return vec![(0..self.vertices.len()).collect()];
+        }
+
+        let mut pieces: Vec<Vec<usize>> = self
+            .triangulate()
+            .into_iter()
+            .map(|triangle| triangle.to_vec())
+            .collect();
+
+        let mut merged = true;
+        while merged {
+            merged = false;
+
+            'search: for i in 0..pieces.len() {
+                for j in (i + 1)..pieces.len() {
+                    if let Some(piece) = self.merge_pieces(&pieces[i], &pieces[j]) {
+                        pieces[i] = piece;
+                        pieces.swap_remove(j);
+
+                        merged = true;
+                        break 'search;
+                    }
+                }
+            }
+        }
+
+        return pieces;
+    }
+
+    /// Joins two pieces along an edge they share, if the result is convex. Both pieces must have
+    /// the same winding order, so the shared edge runs in opposite directions in each of them.
+    fn merge_pieces(&self, a: &[usize], b: &[usize]) -> Option<Vec<usize>> {
+        let a_count = a.len();
+        let b_count = b.len();
+
+        // Find an edge from `u` to `v` in `a`, which runs from `v` to `u` in `b`
+        let (a_start, b_start) = (0..a_count).find_map(|i| {
+            let u = a[i];
+            let v = a[(i + 1) % a_count];
+
+            (0..b_count)
+                .find(|&j| b[j] == v && b[(j + 1) % b_count] == u)
+                .map(|j| ((i + 1) % a_count, (j + 1) % b_count))
+        })?;
+
+        // Walk `a` from `v` round to `u`, then `b` from after `u` round to before `v`
+        let piece: Vec<usize> = (0..a_count)
+            .map(|k| a[(a_start + k) % a_count])
+            .chain((1..b_count - 1).map(|k| b[(b_start + k) % b_count]))
+            .collect();
+
+        if self.is_convex_subset(&piece) {
+            return Some(piece);
+        }
+
+        return None;
+    }
+
+    /// Splits the polygon into triangles using ear clipping, returning the indices of their
+    /// vertices. Triangles have the same winding order as the polygon, which may be concave, but
+    /// must not intersect itself.
+    pub fn triangulate(&self) -> Vec<[usize; 3]> {
+        let orientation = self.signed_area().signum();
+
+        if self.vertices.len() < 3 || orientation == 0.0 {
+            return Vec::new();
+        }
+
+        let mut remaining: Vec<usize> = (0..self.vertices.len()).collect();
+        let mut triangles = Vec::with_capacity(self.vertices.len() - 2);
+
+        while remaining.len() > 3 {
+            let count = remaining.len();
+
+            // A simple polygon always has at least two ears, but rounding errors may hide them, in
+            // which case a vertex is clipped anyway to guarantee progress
+            let i = (0..count)
+                .find(|&i| self.is_ear(&remaining, i, orientation))
+                .unwrap_or(0);
+
+            triangles.push([
+                remaining[(i + count - 1) % count],
+                remaining[i],
+                remaining[(i + 1) % count],
+            ]);
+            remaining.remove(i);
+        }
+
+        triangles.push([remaining[0], remaining[1], remaining[2]]);
+
+        return triangles;
+    }
+
+    /// Whether the triangle formed by the `i`th remaining vertex and its neighbours is convex, and
+    /// contains no other remaining vertices (including on its edges)
+    fn is_ear(&self, remaining: &[usize], i: usize, orientation: f32) -> bool {
+        let count = remaining.len();
+        let prev = (i + count - 1) % count;
+        let next = (i + 1) % count;
+
+        let a = self.vertices[remaining[prev]];
+        let b = self.vertices[remaining[i]];
+        let c = self.vertices[remaining[next]];
+
+        // Reflex (and collinear) vertices cannot be ears
+        if (b - a).cross(c - b) * orientation <= 0.0 {
+            return false;
+        }
+
+        let contains = |point: Vec2f| {
+            (b - a).cross(point - a) * orientation >= 0.0
+                && (c - b).cross(point - b) * orientation >= 0.0
+                && (a - c).cross(point - c) * orientation >= 0.0
+        };
+
+        return remaining.iter().enumerate().all(|(j, &index)| {
+            let vertex = self.vertices[index];
+
+            j == prev
+                || j == i
+                || j == next
+                || vertex == a
+                || vertex == b
+                || vertex == c
+                || !contains(vertex)
+        });
+    }
 }
 
 impl Shape for Polygon {
@@ -164,4 +352,108 @@ mod tests {
         // does not intersect
         assert!(!polygon.intersects_ray(&Segment::new(Vec2f::new(1.5, 0.5), Vec2f::new(3.0, 0.0))));
     }
+
+    #[test]
+    fn triangulate_test() {
+        // Concave "L" shape, clockwise
+        let polygon = Polygon::from_vertices(vec![
+            Vec2f::new(0.0, 0.0),
+            Vec2f::new(0.0, 2.0),
+            Vec2f::new(1.0, 2.0),
+            Vec2f::new(1.0, 1.0),
+            Vec2f::new(2.0, 1.0),
+            Vec2f::new(2.0, 0.0),
+        ]);
+
+        let triangles = polygon.triangulate();
+        assert_eq!(triangles.len(), 4);
+
+        // Triangles cover the polygon exactly, and keep its winding order
+        let mut area = 0.0;
+        for [a, b, c] in triangles {
+            let triangle = Polygon::from_vertices(vec![
+                polygon.vertices[a],
+                polygon.vertices[b],
+                polygon.vertices[c],
+            ]);
+
+            assert!(triangle.signed_area() < 0.0);
+            area += triangle.area();
+        }
+        assert_eq!(area, polygon.area());
+
+        // Degenerate
+        let line = Polygon::from_vertices(vec![Vec2f::new(0.0, 0.0), Vec2f::new(1.0, 0.0)]);
+        assert!(line.triangulate().is_empty());
+    }
+
+    #[test]
+    fn is_convex_test() {
+        // convex, in either winding order
+        let mut square = Polygon::from_rect(Vec2f::ZERO, Vec2f::uniform(2.0));
+        assert!(square.is_convex());
+        square.vertices.reverse();
+        assert!(square.is_convex());
+
+        // collinear vertices are allowed
+        let polygon = Polygon::from_vertices(vec![
+            Vec2f::new(0.0, 0.0),
+            Vec2f::new(1.0, 0.0),
+            Vec2f::new(2.0, 0.0),
+            Vec2f::new(1.0, 1.0),
+        ]);
+        assert!(polygon.is_convex());
+
+        // concave
+        let polygon = Polygon::from_vertices(vec![
+            Vec2f::new(0.0, 0.0),
+            Vec2f::new(2.0, 0.0),
+            Vec2f::new(1.0, 0.5),
+            Vec2f::new(2.0, 2.0),
+            Vec2f::new(0.0, 2.0),
+        ]);
+        assert!(!polygon.is_convex());
+
+        // degenerate
+        let polygon = Polygon::from_vertices(vec![
+            Vec2f::new(0.0, 0.0),
+            Vec2f::new(1.0, 0.0),
+            Vec2f::new(2.0, 0.0),
+        ]);
+        assert!(!polygon.is_convex());
+    }
+
+    #[test]
+    fn convex_decomposition_test() {
+        // Already convex, so left whole
+        let square = Polygon::from_rect(Vec2f::ZERO, Vec2f::uniform(2.0));
+        assert_eq!(square.convex_decomposition(), vec![vec![0, 1, 2, 3]]);
+
+        // "U" shape, which needs at least three pieces
+        let polygon = Polygon::from_vertices(vec![
+            Vec2f::new(0.0, 0.0),
+            Vec2f::new(3.0, 0.0),
+            Vec2f::new(3.0, 2.0),
+            Vec2f::new(2.0, 2.0),
+            Vec2f::new(2.0, 1.0),
+            Vec2f::new(1.0, 1.0),
+            Vec2f::new(1.0, 2.0),
+            Vec2f::new(0.0, 2.0),
+        ]);
+
+        let pieces = polygon.convex_decomposition();
+        assert!(pieces.len() >= 3 && pieces.len() < polygon.triangulate().len());
+
+        let mut area = 0.0;
+        for piece in pieces {
+            let piece = Polygon::from_vertices(
+                piece.iter().map(|index| polygon.vertices[*index]).collect(),
+            );
+
+            assert!(piece.is_convex());
+            assert!(piece.signed_area() > 0.0);
+            area += piece.area();
+        }
+        assert_eq!(area, polygon.area());
+    }
 }
@@ -1,2 +1,4 @@
+pub mod fixed;
 pub mod geometry;
 pub mod linear;
+pub mod trig;
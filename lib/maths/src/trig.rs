@@ -0,0 +1,129 @@
+//! Sine and tangent looked up from precomputed tables of [Fixed] values, as classic engines did,
+//! rather than calculated. Angles are rounded to the nearest of [FINE_ANGLES] steps around the
+//! circle, which is finer than a pixel at any reasonable resolution and field of view.
+
+use std::{f32::consts::TAU, sync::OnceLock};
+
+use crate::fixed::Fixed;
+
+/// Number of steps in a full turn. A power of two, so that angles wrap around by masking.
+pub const FINE_ANGLES: usize = 8192;
+const FINE_MASK: usize = FINE_ANGLES - 1;
+/// Steps in a quarter turn, which is also how far cosine is ahead of sine
+const QUARTER: usize = FINE_ANGLES / 4;
+
+struct Tables {
+    /// Sine of each step of a full turn, followed by another quarter turn so that cosine can be read
+    /// from the same table
+    sine: Box<[Fixed]>,
+    /// Tangent of each step of a half turn, from -90 degrees (inc.) to 90 degrees (exc.). Tangent
+    /// repeats every half turn, and approaches infinity at either end, so saturates there.
+    tangent: Box<[Fixed]>,
+}
+
+static TABLES: OnceLock<Tables> = OnceLock::new();
+
+fn tables() -> &'static Tables {
+    TABLES.get_or_init(|| {
+        let step = TAU / FINE_ANGLES as f32;
+
+        let sine = (0..FINE_ANGLES + QUARTER)
+            .map(|i| nearest((i as f32 * step).sin()))
+            .collect();
+        let tangent = (0..FINE_ANGLES / 2)
+            .map(|i| {
+                let angle = (i as isize - QUARTER as isize) as f32 * step;
+                if i == 0 {
+                    Fixed::MIN
+                } else {
+                    nearest(angle.tan())
+                }
+            })
+            .collect();
+
+        Tables { sine, tangent }
+    })
+}
+
+/// Rounds to the nearest fixed point value, rather than towards zero, so that values like the sine
+/// of 90 degrees, which comes out fractionally less than 1.0 in floating point, are exact
+fn nearest(value: f32) -> Fixed {
+    Fixed::from_bits((value * Fixed::ONE.to_bits() as f32).round() as i32)
+}
+
+/// Converts an angle, in radians, to the nearest step of the tables, wrapped to a full turn
+pub fn fine_angle(radians: f32) -> usize {
+    let steps = (radians * (FINE_ANGLES as f32 / TAU)).round() as i64;
+    steps as usize & FINE_MASK
+}
+
+pub fn sin(radians: f32) -> Fixed {
+    tables().sine[fine_angle(radians)]
+}
+
+pub fn cos(radians: f32) -> Fixed {
+    tables().sine[fine_angle(radians) + QUARTER]
+}
+
+/// Sine and cosine of the same angle, looked up together
+pub fn sin_cos(radians: f32) -> (Fixed, Fixed) {
+    let tables = tables();
+    let angle = fine_angle(radians);
+
+    (tables.sine[angle], tables.sine[angle + QUARTER])
+}
+
+/// Tangent of the angle, which saturates at [Fixed::MIN] and [Fixed::MAX] towards ±90 degrees
+pub fn tan(radians: f32) -> Fixed {
+    // Offset so that -90 degrees is at the start of the table, then wrapped to a half turn
+    let angle = (fine_angle(radians) + QUARTER) & (FINE_MASK >> 1);
+    tables().tangent[angle]
+}
+
+#[cfg(test)]
+mod tests {
+    use std::f32::consts::{FRAC_PI_2, FRAC_PI_4, PI};
+
+    use super::*;
+
+    /// Largest error expected from rounding an angle to the nearest step
+    const STEP_ERROR: f32 = TAU / FINE_ANGLES as f32;
+
+    #[test]
+    fn matches_std_within_a_step() {
+        for i in -100..100 {
+            let angle = i as f32 * 0.0731;
+            let (sin, cos) = sin_cos(angle);
+
+            assert!((sin.to_f32() - angle.sin()).abs() <= STEP_ERROR);
+            assert!((cos.to_f32() - angle.cos()).abs() <= STEP_ERROR);
+            assert_eq!(sin, super::sin(angle));
+            assert_eq!(cos, super::cos(angle));
+        }
+
+        for i in -40..40 {
+            let angle = i as f32 * 0.0331;
+            // Tangent grows steeply, so its error is relative to the gradient at the angle
+            let tolerance = STEP_ERROR / (angle.cos() * angle.cos());
+            assert!((tan(angle).to_f32() - angle.tan()).abs() <= tolerance);
+        }
+    }
+
+    #[test]
+    fn exact_at_right_angles() {
+        assert_eq!(sin(0.0), Fixed::ZERO);
+        assert_eq!(sin(FRAC_PI_2), Fixed::ONE);
+        assert_eq!(cos(0.0), Fixed::ONE);
+        assert_eq!(cos(PI), -Fixed::ONE);
+        assert_eq!(tan(0.0), Fixed::ZERO);
+        assert_eq!(tan(PI), Fixed::ZERO);
+    }
+
+    #[test]
+    fn wraps_around() {
+        assert_eq!(sin(FRAC_PI_4), sin(FRAC_PI_4 + TAU));
+        assert_eq!(sin(-FRAC_PI_4), -sin(FRAC_PI_4));
+        assert_eq!(tan(FRAC_PI_4), tan(FRAC_PI_4 + PI));
+        assert_eq!(tan(-FRAC_PI_2), Fixed::MIN);
+    }
+}
@@ -1,5 +1,5 @@
 use maths::{
-    geometry::{Circle, Segment, Shape, AABB},
+    geometry::{Circle, Polygon, Segment, Shape, AABB},
     linear::Vec2f,
 };
 
@@ -7,10 +7,13 @@ use crate::collision::{
     collision_circles, sat_collision_circle_polygon, sat_collision_polygons, Collision,
 };
 
+#[derive(Debug)]
 pub enum Collider {
     AABB(AABB),
     Circle(Circle),
     Segment(Segment),
+    /// Must be convex, as the separating axis test doesn't work otherwise
+    Polygon(Polygon),
 }
 
 impl Collider {
@@ -26,23 +29,45 @@ impl Collider {
         Self::Segment(segment)
     }
 
+    pub fn from_polygon(polygon: Polygon) -> Self {
+        debug_assert!(polygon.is_convex(), "Polygon colliders must be convex");
+        Self::Polygon(polygon)
+    }
+
     pub fn contains_point(&self, point: Vec2f) -> bool {
         match self {
             Collider::AABB(aabb) => aabb.contains_point(point),
             Collider::Circle(circle) => circle.contains_point(point),
             Collider::Segment(segment) => segment.contains_point(point),
+            Collider::Polygon(polygon) => polygon.contains_point(point),
         }
     }
 
+    /// Finds how far this collider overlaps the other, if at all, with the normal pointing the way
+    /// to push this collider out of the other
     pub fn intersects(&self, other: &Collider) -> Option<Collision> {
         match (self, other) {
             (Collider::Circle(a), Collider::Circle(b)) => collision_circles(a, b),
 
-            (Collider::Circle(circle), other) | (other, Collider::Circle(circle)) => {
-                sat_collision_circle_polygon(circle, other.points())
+            (Collider::Circle(circle), other) => {
+                other.with_vertices(|points| sat_collision_circle_polygon(circle, points))
             }
+            (this, Collider::Circle(circle)) => this
+                .with_vertices(|points| sat_collision_circle_polygon(circle, points))
+                .map(Collision::reversed),
+
+            _ => self.with_vertices(|points_a| {
+                other.with_vertices(|points_b| sat_collision_polygons(points_a, points_b))
+            }),
+        }
+    }
 
-            _ => sat_collision_polygons(self.points(), other.points()),
+    /// Calls the function with the vertices of the collider's outline. Boxes don't store their
+    /// corners, so they are worked out on the stack first.
+    fn with_vertices<T>(&self, f: impl FnOnce(&[Vec2f]) -> T) -> T {
+        match self {
+            Collider::AABB(aabb) => f(&aabb.corners()),
+            _ => f(self.points()),
         }
     }
 
@@ -51,6 +76,7 @@ impl Collider {
             Collider::AABB(aabb) => aabb.intersects_ray(ray),
             Collider::Circle(circle) => circle.intersects_ray(ray),
             Collider::Segment(segment) => segment.intersects_ray(ray),
+            Collider::Polygon(polygon) => polygon.intersects_ray(ray),
         }
     }
 
@@ -59,6 +85,7 @@ impl Collider {
             Collider::AABB(aabb) => aabb.extents(),
             Collider::Circle(circle) => circle.extents(),
             Collider::Segment(segment) => segment.extents(),
+            Collider::Polygon(polygon) => polygon.extents(),
         }
     }
 
@@ -67,6 +94,7 @@ impl Collider {
             Collider::AABB(aabb) => aabb.area(),
             Collider::Circle(circle) => circle.area(),
             Collider::Segment(segment) => segment.area(),
+            Collider::Polygon(polygon) => polygon.area(),
         }
     }
 
@@ -75,6 +103,7 @@ impl Collider {
             Collider::AABB(aabb) => aabb.centre(),
             Collider::Circle(circle) => circle.centre(),
             Collider::Segment(segment) => segment.centre(),
+            Collider::Polygon(polygon) => polygon.centre(),
         }
     }
 
@@ -83,6 +112,7 @@ impl Collider {
             Collider::AABB(aabb) => aabb.translate(translation),
             Collider::Circle(circle) => circle.translate(translation),
             Collider::Segment(segment) => segment.translate(translation),
+            Collider::Polygon(polygon) => polygon.translate(translation),
         }
     }
 
@@ -91,6 +121,7 @@ impl Collider {
             Collider::AABB(aabb) => aabb.scale(scale),
             Collider::Circle(circle) => circle.scale(scale),
             Collider::Segment(segment) => segment.scale(scale),
+            Collider::Polygon(polygon) => polygon.scale(scale),
         }
     }
 
@@ -99,6 +130,7 @@ impl Collider {
             Collider::AABB(aabb) => aabb.rotate(sin, cos),
             Collider::Circle(circle) => circle.rotate(sin, cos),
             Collider::Segment(segment) => segment.rotate(sin, cos),
+            Collider::Polygon(polygon) => polygon.rotate(sin, cos),
         }
     }
 
@@ -107,6 +139,7 @@ impl Collider {
             Collider::AABB(aabb) => aabb.points(),
             Collider::Circle(circle) => circle.points(),
             Collider::Segment(segment) => segment.points(),
+            Collider::Polygon(polygon) => polygon.points(),
         }
     }
 }
@@ -3,6 +3,8 @@ use maths::{
     linear::Vec2f,
 };
 
+/// Overlap between two shapes. The normal is the direction to push the first shape by the depth to
+/// separate them.
 #[derive(Clone, Copy, Debug)]
 pub struct Collision {
     normal: Vec2f,
@@ -21,24 +23,28 @@ impl Collision {
     pub fn depth(&self) -> f32 {
         self.depth
     }
+
+    /// The same collision from the point of view of the other shape
+    pub fn reversed(self) -> Self {
+        Self::new(-self.normal, self.depth)
+    }
 }
 
+/// Tests two convex polygons against each other, using the normal of each edge of both as the axes to
+/// separate them along. The normal of the collision points away from B, towards A.
 pub fn sat_collision_polygons(points_a: &[Vec2f], points_b: &[Vec2f]) -> Option<Collision> {
-    let mut points = [points_a, points_b];
-
     let mut min_depth = f32::MAX;
     let mut collision_normal = Vec2f::ZERO;
 
-    // Run through twice, first with shape A as the reference and then with shape B
-    for _ in 0..2 {
-        let points_a = points[0];
-        let points_b = points[1];
+    // Project each point of each shape onto the normal of each edge of either shape
+    for edges in [points_a, points_b] {
+        for i in 0..edges.len() {
+            let j = (i + 1) % edges.len();
 
-        // Project each point of each shape onto the normal of each edge of shape A
-        for i in 0..points_a.len() {
-            let j = (i + 1) % points_a.len();
-
-            let normal = (points_a[j] - points_a[i]).perpendicular();
+            let normal = (edges[j] - edges[i]).perpendicular().normalise();
+            if normal == Vec2f::ZERO {
+                continue;
+            }
 
             let mut min_a = f32::MAX;
             let mut max_a = f32::MIN;
@@ -60,20 +66,26 @@ pub fn sat_collision_polygons(points_a: &[Vec2f], points_b: &[Vec2f]) -> Option<
                 return None;
             }
 
-            let depth = f32::min(max_a - min_b, max_b - min_a);
+            // A is pushed whichever way along the axis is the shorter way out
+            let (depth, normal) = if max_b - min_a < max_a - min_b {
+                (max_b - min_a, normal)
+            } else {
+                (max_a - min_b, -normal)
+            };
             if depth < min_depth {
                 min_depth = depth;
                 collision_normal = normal;
             }
         }
-
-        points.swap(0, 1);
     }
 
     // All axes overlap so the shapes must intersect
-    return Some(Collision::new(collision_normal.normalise(), min_depth));
+    return Some(Collision::new(collision_normal, min_depth));
 }
 
+/// Tests a circle against a convex polygon, using the normal of each edge of the polygon, along with
+/// the direction from its nearest vertex to the centre of the circle, as the axes to separate them
+/// along. The normal of the collision points away from the polygon, towards the circle.
 pub fn sat_collision_circle_polygon(circle: &Circle, points: &[Vec2f]) -> Option<Collision> {
     let mut closest_point = Vec2f::ZERO;
     let mut closest_distance = f32::MAX;
@@ -85,26 +97,50 @@ pub fn sat_collision_circle_polygon(circle: &Circle, points: &[Vec2f]) -> Option
         }
     }
 
-    let normal = circle.centre - closest_point;
+    let edge_normals = (0..points.len()).map(|i| {
+        (points[(i + 1) % points.len()] - points[i])
+            .perpendicular()
+            .normalise()
+    });
+    let vertex_normal = (circle.centre - closest_point).normalise();
 
-    let mut min_polygon = f32::MAX;
-    let mut max_polygon = f32::MIN;
-    for point in points {
-        let q = point.dot(normal);
-        min_polygon = min_polygon.min(q);
-        max_polygon = max_polygon.max(q);
-    }
+    let mut min_depth = f32::MAX;
+    let mut collision_normal = Vec2f::ZERO;
 
-    let min_circle = circle.centre.dot(normal) - circle.radius;
-    let max_circle = circle.centre.dot(normal) + circle.radius;
+    for normal in edge_normals.chain([vertex_normal]) {
+        // Degenerate edges, or a circle centred on a vertex, give no axis to test
+        if normal == Vec2f::ZERO {
+            continue;
+        }
 
-    if max_polygon < min_circle || min_polygon > max_circle {
-        return None;
-    }
+        let mut min_polygon = f32::MAX;
+        let mut max_polygon = f32::MIN;
+        for point in points {
+            let q = point.dot(normal);
+            min_polygon = min_polygon.min(q);
+            max_polygon = max_polygon.max(q);
+        }
 
-    let depth = f32::min(max_polygon - min_circle, max_circle - min_polygon);
+        let min_circle = circle.centre.dot(normal) - circle.radius;
+        let max_circle = circle.centre.dot(normal) + circle.radius;
 
-    return Some(Collision::new(normal.normalise(), depth));
+        if max_polygon < min_circle || min_polygon > max_circle {
+            return None;
+        }
+
+        // The circle is pushed whichever way along the axis is the shorter way out
+        let (depth, normal) = if max_polygon - min_circle < max_circle - min_polygon {
+            (max_polygon - min_circle, normal)
+        } else {
+            (max_circle - min_polygon, -normal)
+        };
+        if depth < min_depth {
+            min_depth = depth;
+            collision_normal = normal;
+        }
+    }
+
+    return Some(Collision::new(collision_normal, min_depth));
 }
 
 pub fn collision_circles(circle_a: &Circle, circle_b: &Circle) -> Option<Collision> {
@@ -142,3 +178,60 @@ pub fn collision_circle_segment(circle: &Circle, segment: &Segment) -> Option<Co
 
     return Some(Collision::new(normal, depth));
 }
+
+#[cfg(test)]
+mod tests {
+    use maths::geometry::Polygon;
+
+    use super::*;
+
+    fn square() -> Polygon {
+        Polygon::from_rect(Vec2f::ZERO, Vec2f::uniform(2.0))
+    }
+
+    #[test]
+    fn circle_polygon_pushes_out_of_nearest_edge() {
+        let square = square();
+
+        // Beside the middle of the right edge, far from any vertex
+        let circle = Circle::new(Vec2f::new(1.5, 0.1), 1.0);
+        let collision = sat_collision_circle_polygon(&circle, &square.vertices).unwrap();
+        assert_eq!(collision.normal(), Vec2f::new(1.0, 0.0));
+        assert!((collision.depth() - 0.5).abs() < 1e-6);
+
+        // Inside, nearer the bottom edge
+        let circle = Circle::new(Vec2f::new(0.2, -0.7), 0.5);
+        let collision = sat_collision_circle_polygon(&circle, &square.vertices).unwrap();
+        assert_eq!(collision.normal(), Vec2f::new(0.0, -1.0));
+        assert!((collision.depth() - 0.8).abs() < 1e-6);
+    }
+
+    #[test]
+    fn circle_polygon_separated_at_corner() {
+        let square = square();
+
+        // Overlaps the bounds of the square, but not the square itself
+        let circle = Circle::new(Vec2f::new(1.6, 1.6), 0.8);
+        assert!(sat_collision_circle_polygon(&circle, &square.vertices).is_none());
+
+        let circle = Circle::new(Vec2f::new(1.5, 1.5), 0.8);
+        let collision = sat_collision_circle_polygon(&circle, &square.vertices).unwrap();
+        assert!(collision.normal().x > 0.0 && collision.normal().y > 0.0);
+    }
+
+    #[test]
+    fn polygons_push_a_away_from_b() {
+        let a = Polygon::from_rect(Vec2f::new(-1.5, 0.0), Vec2f::uniform(2.0));
+        let b = square();
+
+        let collision = sat_collision_polygons(&a.vertices, &b.vertices).unwrap();
+        assert_eq!(collision.normal(), Vec2f::new(-1.0, 0.0));
+        assert!((collision.depth() - 0.5).abs() < 1e-6);
+
+        let collision = sat_collision_polygons(&b.vertices, &a.vertices).unwrap();
+        assert_eq!(collision.normal(), Vec2f::new(1.0, 0.0));
+
+        let far = Polygon::from_rect(Vec2f::new(5.0, 0.0), Vec2f::uniform(2.0));
+        assert!(sat_collision_polygons(&far.vertices, &b.vertices).is_none());
+    }
+}
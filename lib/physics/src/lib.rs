@@ -1,4 +1,7 @@
 mod collider;
 pub mod collision;
+mod rigid_body;
+pub mod solver;
 
 pub use collider::Collider;
+pub use rigid_body::RigidBody;
@@ -5,6 +5,12 @@ use maths::{
 
 use crate::collider::Collider;
 
+/// Bounciness of a body unless set otherwise, as a fraction of the speed it hits something at
+const DEFAULT_RESTITUTION: f32 = 0.2;
+/// Grip of a body's surface unless set otherwise, as a coefficient of friction
+const DEFAULT_FRICTION: f32 = 0.4;
+
+#[derive(Debug)]
 pub struct RigidBody {
     pub id: usize,
 
@@ -13,6 +19,12 @@ pub struct RigidBody {
     pub fixed: bool,
     pub mass: f32,
     pub inv_mass: f32,
+    /// Fraction of the speed along the normal that is kept after a collision, from 0 (doesn't
+    /// bounce at all) to 1 (perfectly elastic). The lower of the two bodies is used.
+    pub restitution: f32,
+    /// Coefficient of friction, which limits how much sliding along a contact is resisted in
+    /// proportion to how hard the bodies are pushed together
+    pub friction: f32,
 
     pub velocity: Vec2f,
     pub position: Vec2f,
@@ -30,11 +42,23 @@ impl RigidBody {
             fixed,
             mass,
             inv_mass,
+            restitution: DEFAULT_RESTITUTION,
+            friction: DEFAULT_FRICTION,
             velocity: Vec2f::ZERO,
             position: Vec2f::ZERO,
         }
     }
 
+    pub fn with_restitution(mut self, restitution: f32) -> Self {
+        self.restitution = restitution;
+        self
+    }
+
+    pub fn with_friction(mut self, friction: f32) -> Self {
+        self.friction = friction;
+        self
+    }
+
     pub fn translate(&mut self, translation: Vec2f) {
         self.position += translation;
         self.collider.translate(translation);
@@ -0,0 +1,261 @@
+//! Resolves contacts between rigid bodies by applying impulses, so that they bounce off and slide
+//! along each other, then nudges them apart so that they don't sink into each other over time
+
+use std::borrow::{Borrow, BorrowMut};
+
+use maths::linear::Vec2f;
+
+use crate::{collision::Collision, rigid_body::RigidBody};
+
+/// Overlap left alone by positional correction, so that bodies resting against each other don't
+/// jitter between touching and not
+const SLOP: f32 = 0.01;
+/// Fraction of the overlap, beyond the slop, that is corrected each step. Correcting all of it at
+/// once tends to overshoot when a body touches several others.
+const CORRECTION: f32 = 0.8;
+
+/// Two bodies that were found overlapping, by their indices into the bodies given to the solver
+#[derive(Clone, Copy, Debug)]
+pub struct Contact {
+    pub a: usize,
+    pub b: usize,
+    /// The normal points away from `b`, towards `a`
+    pub collision: Collision,
+}
+
+pub struct Solver {
+    /// Number of passes over the contacts each step. Later passes refine the impulses where a body
+    /// touches several others, such as when pushed into a corner.
+    pub iterations: usize,
+    contacts: Vec<Contact>,
+}
+
+impl Solver {
+    pub fn new(iterations: usize) -> Self {
+        Self {
+            iterations,
+            contacts: Vec::new(),
+        }
+    }
+
+    /// Contacts found during the last step
+    pub fn contacts(&self) -> &[Contact] {
+        &self.contacts
+    }
+
+    /// Moves each body by its velocity, then resolves any overlaps that causes. Fixed bodies don't
+    /// move, but others still collide with them. The bodies may be given by reference, so that
+    /// they can stay with whatever they belong to.
+    pub fn step<B: BorrowMut<RigidBody>>(&mut self, bodies: &mut [B], delta_seconds: f32) {
+        for body in bodies.iter_mut().map(|body| body.borrow_mut()) {
+            if !body.fixed {
+                body.integrate_position(delta_seconds);
+            }
+        }
+
+        find_contacts(bodies, &mut self.contacts);
+
+        for _ in 0..self.iterations {
+            for contact in self.contacts.iter() {
+                let (a, b) = pair_mut(bodies, contact.a, contact.b);
+                apply_impulse(a, b, &contact.collision);
+            }
+        }
+
+        for contact in self.contacts.iter() {
+            let (a, b) = pair_mut(bodies, contact.a, contact.b);
+            correct_positions(a, b, &contact.collision);
+        }
+    }
+}
+
+/// Finds every pair of bodies that overlap, checking their bounds before their colliders. Pairs of
+/// fixed bodies are skipped, as neither can be moved.
+pub fn find_contacts<B: Borrow<RigidBody>>(bodies: &[B], contacts: &mut Vec<Contact>) {
+    contacts.clear();
+
+    for (a, body_a) in bodies.iter().map(|body| body.borrow()).enumerate() {
+        for (b, body_b) in bodies
+            .iter()
+            .map(|body| body.borrow())
+            .enumerate()
+            .skip(a + 1)
+        {
+            if (body_a.fixed && body_b.fixed) || !body_a.bounds.intersects(&body_b.bounds) {
+                continue;
+            }
+
+            if let Some(collision) = body_a.collider.intersects(&body_b.collider) {
+                contacts.push(Contact { a, b, collision });
+            }
+        }
+    }
+}
+
+/// Changes the velocities of both bodies so that they stop approaching each other along the normal
+/// of the collision, bouncing apart by their restitution, and resists them sliding along it by
+/// their friction. Does nothing if they are already separating.
+pub fn apply_impulse(a: &mut RigidBody, b: &mut RigidBody, collision: &Collision) {
+    let inv_mass_sum = a.inv_mass + b.inv_mass;
+    if inv_mass_sum == 0.0 {
+        return;
+    }
+
+    let normal = collision.normal();
+    let relative_velocity = a.velocity - b.velocity;
+    let normal_speed = relative_velocity.dot(normal);
+    if normal_speed >= 0.0 {
+        return;
+    }
+
+    let restitution = a.restitution.min(b.restitution);
+    let normal_impulse = -(1.0 + restitution) * normal_speed / inv_mass_sum;
+    a.velocity += normal * normal_impulse * a.inv_mass;
+    b.velocity -= normal * normal_impulse * b.inv_mass;
+
+    // Friction acts against whatever sliding is left, but can never do more than stop it, and is
+    // limited by how hard the bodies were pushed together (Coulomb's law)
+    let relative_velocity = a.velocity - b.velocity;
+    let tangent = (relative_velocity - normal * relative_velocity.dot(normal)).normalise();
+    if tangent == Vec2f::ZERO {
+        return;
+    }
+
+    let friction = (a.friction * b.friction).sqrt();
+    let max_friction_impulse = normal_impulse * friction;
+    let friction_impulse = (-relative_velocity.dot(tangent) / inv_mass_sum)
+        .clamp(-max_friction_impulse, max_friction_impulse);
+    a.velocity += tangent * friction_impulse * a.inv_mass;
+    b.velocity -= tangent * friction_impulse * b.inv_mass;
+}
+
+/// Pushes the bodies apart along the normal of the collision, in proportion to their inverse
+/// masses, so that a lighter body moves further than a heavier one
+pub fn correct_positions(a: &mut RigidBody, b: &mut RigidBody, collision: &Collision) {
+    let inv_mass_sum = a.inv_mass + b.inv_mass;
+    if inv_mass_sum == 0.0 {
+        return;
+    }
+
+    let correction =
+        collision.normal() * ((collision.depth() - SLOP).max(0.0) / inv_mass_sum * CORRECTION);
+    a.translate(correction * a.inv_mass);
+    b.translate(-correction * b.inv_mass);
+}
+
+/// Borrows two different bodies mutably at once, where `a` comes before `b`
+fn pair_mut<B: BorrowMut<RigidBody>>(
+    bodies: &mut [B],
+    a: usize,
+    b: usize,
+) -> (&mut RigidBody, &mut RigidBody) {
+    let (front, back) = bodies.split_at_mut(b);
+    (front[a].borrow_mut(), back[0].borrow_mut())
+}
+
+#[cfg(test)]
+mod tests {
+    use maths::geometry::{Circle, AABB};
+
+    use super::*;
+    use crate::Collider;
+
+    fn ball(centre: Vec2f, velocity: Vec2f) -> RigidBody {
+        let mut body = RigidBody::new(
+            Collider::from_circle(Circle::new(centre, 1.0)),
+            1.0,
+            false,
+            0,
+        );
+        body.velocity = velocity;
+        body
+    }
+
+    fn floor() -> RigidBody {
+        let aabb = AABB::new(Vec2f::new(-10.0, -2.0), Vec2f::new(10.0, 0.0));
+        RigidBody::new(Collider::from_aabb(aabb), 1.0, true, 1)
+    }
+
+    fn approx(a: f32, b: f32) -> bool {
+        (a - b).abs() < 1e-4
+    }
+
+    #[test]
+    fn restitution_keeps_a_fraction_of_the_speed() {
+        let mut ball = ball(Vec2f::new(0.0, 0.9), Vec2f::new(0.0, -10.0)).with_restitution(0.5);
+        let mut floor = floor().with_restitution(1.0);
+
+        let collision = ball.collider.intersects(&floor.collider).unwrap();
+        apply_impulse(&mut ball, &mut floor, &collision);
+
+        assert!(approx(ball.velocity.y, 5.0));
+        assert_eq!(floor.velocity, Vec2f::ZERO);
+    }
+
+    #[test]
+    fn equal_masses_swap_speeds_when_elastic() {
+        let mut a = ball(Vec2f::new(-0.9, 0.0), Vec2f::new(4.0, 0.0)).with_restitution(1.0);
+        let mut b = ball(Vec2f::new(0.9, 0.0), Vec2f::ZERO).with_restitution(1.0);
+
+        let collision = a.collider.intersects(&b.collider).unwrap();
+        apply_impulse(&mut a, &mut b, &collision);
+
+        assert!(approx(a.velocity.x, 0.0));
+        assert!(approx(b.velocity.x, 4.0));
+
+        // Already separating, so left alone
+        apply_impulse(&mut a, &mut b, &collision);
+        assert!(approx(b.velocity.x, 4.0));
+    }
+
+    #[test]
+    fn friction_slows_sliding_up_to_a_limit() {
+        // Grippy enough to stop the sliding entirely
+        let mut ball = ball(Vec2f::new(0.0, 0.9), Vec2f::new(1.0, -10.0))
+            .with_restitution(0.0)
+            .with_friction(1.0);
+        let mut floor = floor().with_friction(1.0);
+        let collision = ball.collider.intersects(&floor.collider).unwrap();
+        apply_impulse(&mut ball, &mut floor, &collision);
+        assert!(approx(ball.velocity.x, 0.0));
+        assert!(approx(ball.velocity.y, 0.0));
+
+        // Slippery, so only slowed by the coefficient of friction times the normal impulse
+        ball.velocity = Vec2f::new(1.0, -10.0);
+        ball.friction = 0.01;
+        floor.friction = 0.01;
+        apply_impulse(&mut ball, &mut floor, &collision);
+        assert!(approx(ball.velocity.x, 1.0 - 10.0 * 0.01));
+    }
+
+    #[test]
+    fn step_separates_overlapping_bodies() {
+        let mut bodies = vec![ball(Vec2f::new(0.0, 0.5), Vec2f::new(0.0, -1.0)), floor()];
+        let mut solver = Solver::new(4);
+
+        for _ in 0..10 {
+            solver.step(&mut bodies, 1.0 / 60.0);
+        }
+
+        // Resting on the floor, give or take the slop, rather than sinking through it
+        let centre = bodies[0].collider.centre();
+        assert!(centre.y > 1.0 - 2.0 * SLOP);
+        assert!(bodies[0].velocity.y > -1e-3);
+        assert_eq!(bodies[1].collider.centre(), Vec2f::new(0.0, -1.0));
+    }
+
+    #[test]
+    fn step_borrowed_bodies() {
+        let mut ball = ball(Vec2f::new(0.0, 0.5), Vec2f::new(0.0, -1.0));
+        let mut floor = floor();
+        let mut solver = Solver::new(4);
+
+        for _ in 0..10 {
+            solver.step(&mut [&mut ball, &mut floor], 1.0 / 60.0);
+        }
+
+        // Moved where they are kept, as if they were given by value
+        assert!(ball.collider.centre().y > 1.0 - 2.0 * SLOP);
+        assert_eq!(floor.collider.centre(), Vec2f::new(0.0, -1.0));
+    }
+}
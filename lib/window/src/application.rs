@@ -1,4 +1,4 @@
-use crate::{event::Event, Window, WindowPosition, WindowSize};
+use crate::{event::Event, Window, WindowId, WindowPosition, WindowSize, WindowTarget};
 
 use winit::event::{Event as WinitEvent, WindowEvent as WinitWindowEvent};
 
@@ -7,6 +7,17 @@ pub trait WindowApplication: Sized + 'static {
     fn get_window_mut(&mut self) -> &mut Window;
     fn on_event(&mut self, event: &Event);
 
+    /// Finds the window that an event is for, so it can be handled natively. By default only the main
+    /// window is found, so applications with additional windows should override this.
+    fn get_window_by_id_mut(&mut self, id: WindowId) -> Option<&mut Window> {
+        let window = self.get_window_mut();
+        (window.id() == id).then_some(window)
+    }
+
+    /// Called once each pass of the event loop has handled all pending events, before the main
+    /// window is redrawn. Additional windows can be created here, using the target.
+    fn on_events_cleared(&mut self, _target: &WindowTarget) {}
+
     /// Default implementation of `run` for `WindowApplication` handles the backend event loop
     /// and calls the user defined event handler `on_event`.
     ///
@@ -33,45 +44,71 @@ pub trait WindowApplication: Sized + 'static {
                     _ => (),
                 },
 
-                WinitEvent::WindowEvent { event, .. } => match event {
-                    WinitWindowEvent::Resized(ref size) => {
-                        let width = size.width as usize;
-                        let height = size.height as usize;
-
-                        let window = self.get_window_mut();
+                WinitEvent::WindowEvent { event, window_id } => {
+                    let is_main = self.get_window().id() == WindowId(window_id);
+                    let Some(window) = self.get_window_by_id_mut(WindowId(window_id)) else {
+                        return;
+                    };
+
+                    match event {
+                        WinitWindowEvent::Resized(ref size) => {
+                            let width = size.width as usize;
+                            let height = size.height as usize;
+
+                            if size.width == 0 && size.height == 0 {
+                                window.attributes.minimised = true;
+                            } else {
+                                window.attributes.minimised = false;
+                                window.attributes.size = WindowSize::new(width, height);
+                                window.internal.window_resized(window.attributes.size);
+
+                                if window.attributes.surface_size.is_none() {
+                                    window.internal.set_surface_size(window.attributes.size);
+                                }
+                            }
+                        }
 
-                        if size.width == 0 && size.height == 0 {
-                            window.attributes.minimised = true;
-                        } else {
-                            window.attributes.minimised = false;
-                            window.attributes.size = WindowSize::new(width, height);
+                        WinitWindowEvent::ScaleFactorChanged {
+                            scale_factor,
+                            new_inner_size,
+                        } => {
+                            window.attributes.scale_factor = scale_factor;
+                            window.attributes.size = WindowSize::new(
+                                new_inner_size.width as usize,
+                                new_inner_size.height as usize,
+                            );
+                            window.internal.window_resized(window.attributes.size);
 
                             if window.attributes.surface_size.is_none() {
                                 window.internal.set_surface_size(window.attributes.size);
                             }
                         }
-                    }
 
-                    WinitWindowEvent::Moved(ref position) => {
-                        self.get_window_mut().attributes.position =
-                            WindowPosition::new(position.x as usize, position.y as usize);
-                    }
+                        WinitWindowEvent::Moved(ref position) => {
+                            window.attributes.position =
+                                WindowPosition::new(position.x as usize, position.y as usize);
+                        }
 
-                    WinitWindowEvent::Focused(focused) => {
-                        self.get_window_mut().attributes.focused = focused;
-                    }
+                        WinitWindowEvent::Focused(focused) => {
+                            window.attributes.focused = focused;
+                        }
 
-                    WinitWindowEvent::CloseRequested => control_flow.set_exit(),
+                        // Closing an additional window is left to the application, by dropping it
+                        WinitWindowEvent::CloseRequested if is_main => control_flow.set_exit(),
 
-                    _ => {}
-                },
+                        _ => {}
+                    }
+                }
 
                 WinitEvent::MainEventsCleared => {
+                    self.on_events_cleared(&WindowTarget(window_target));
                     self.get_window().request_redraw();
                 }
 
-                WinitEvent::RedrawRequested(_) => {
-                    self.get_window_mut().swap_buffers();
+                WinitEvent::RedrawRequested(window_id) => {
+                    if let Some(window) = self.get_window_by_id_mut(WindowId(window_id)) {
+                        window.swap_buffers();
+                    }
                 }
 
                 _ => (),
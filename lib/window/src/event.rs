@@ -1,37 +1,77 @@
-use crate::{WindowPosition, WindowSize};
+use crate::{WindowId, WindowPosition, WindowSize};
 
+/// Roughly how many pixels one notch of a scroll wheel moves, to convert pixel based scrolling
+/// from touchpads into lines
+const PIXELS_PER_LINE: f64 = 20.0;
+
+/// Window and render events are tagged with the window they are for. Pointer and keyboard events
+/// aren't, as input is handled the same whichever window has focus.
 pub enum Event {
-    WindowEvent(WindowEvent),
+    WindowEvent(WindowId, WindowEvent),
     PointerEvent(PointerEvent),
     KeyboardEvent(KeyboardEvent),
     ClipboardEvent(ClipboardEvent),
-    RenderEvent(RenderEvent),
+    RenderEvent(WindowId, RenderEvent),
 }
 
 impl Event {
     pub fn try_from_winit_event(value: &winit::event::Event<'_, ()>) -> Result<Self, &'static str> {
         match value {
-            winit::event::Event::WindowEvent { event, .. } => match event {
+            winit::event::Event::WindowEvent { event, window_id } => match event {
                 winit::event::WindowEvent::Resized(size) => {
-                    return Ok(Event::WindowEvent(WindowEvent::Resized(WindowSize::new(
-                        size.width as usize,
-                        size.height as usize,
-                    ))))
+                    return Ok(Event::WindowEvent(
+                        WindowId(*window_id),
+                        WindowEvent::Resized(WindowSize::new(
+                            size.width as usize,
+                            size.height as usize,
+                        )),
+                    ))
+                }
+
+                winit::event::WindowEvent::ScaleFactorChanged {
+                    scale_factor,
+                    new_inner_size,
+                } => {
+                    return Ok(Event::WindowEvent(
+                        WindowId(*window_id),
+                        WindowEvent::ScaleFactorChanged {
+                            scale_factor: *scale_factor,
+                            size: WindowSize::new(
+                                new_inner_size.width as usize,
+                                new_inner_size.height as usize,
+                            ),
+                        },
+                    ))
                 }
 
                 winit::event::WindowEvent::Moved(position) => {
-                    return Ok(Event::WindowEvent(WindowEvent::Moved(WindowPosition::new(
-                        position.x as usize,
-                        position.y as usize,
-                    ))))
+                    return Ok(Event::WindowEvent(
+                        WindowId(*window_id),
+                        WindowEvent::Moved(WindowPosition::new(
+                            position.x as usize,
+                            position.y as usize,
+                        )),
+                    ))
                 }
 
                 winit::event::WindowEvent::CloseRequested => {
-                    return Ok(Event::WindowEvent(WindowEvent::CloseRequested))
+                    return Ok(Event::WindowEvent(
+                        WindowId(*window_id),
+                        WindowEvent::CloseRequested,
+                    ))
                 }
 
                 winit::event::WindowEvent::Focused(_) => {
-                    return Ok(Event::WindowEvent(WindowEvent::FocusChanged))
+                    return Ok(Event::WindowEvent(
+                        WindowId(*window_id),
+                        WindowEvent::FocusChanged,
+                    ))
+                }
+
+                winit::event::WindowEvent::CursorMoved { position, .. } => {
+                    return Ok(Event::PointerEvent(PointerEvent::CursorMoved {
+                        position: (position.x, position.y),
+                    }))
                 }
 
                 winit::event::WindowEvent::CursorEntered { .. } => {
@@ -55,6 +95,23 @@ impl Event {
                     }
                 },
 
+                winit::event::WindowEvent::MouseWheel { delta, .. } => {
+                    let delta = match delta {
+                        winit::event::MouseScrollDelta::LineDelta(x, y) => (*x as f64, *y as f64),
+                        winit::event::MouseScrollDelta::PixelDelta(position) => {
+                            (position.x / PIXELS_PER_LINE, position.y / PIXELS_PER_LINE)
+                        }
+                    };
+
+                    return Ok(Event::PointerEvent(PointerEvent::MouseWheel { delta }));
+                }
+
+                winit::event::WindowEvent::ReceivedCharacter(character) => {
+                    return Ok(Event::KeyboardEvent(KeyboardEvent::CharacterInput(
+                        *character,
+                    )))
+                }
+
                 winit::event::WindowEvent::KeyboardInput { input, .. } => {
                     if let Some(key_code) = input.virtual_keycode {
                         match input.state {
@@ -85,8 +142,11 @@ impl Event {
                 _ => (),
             },
 
-            winit::event::Event::RedrawRequested(_) => {
-                return Ok(Event::RenderEvent(RenderEvent::RedrawRequested))
+            winit::event::Event::RedrawRequested(window_id) => {
+                return Ok(Event::RenderEvent(
+                    WindowId(*window_id),
+                    RenderEvent::RedrawRequested,
+                ))
             }
 
             _ => (),
@@ -101,7 +161,18 @@ pub enum RenderEvent {
 }
 
 pub enum PointerEvent {
-    MouseMoved { delta: (f64, f64) },
+    /// Raw motion of the mouse, which continues whilst the cursor is grabbed
+    MouseMoved {
+        delta: (f64, f64),
+    },
+    /// Position of the cursor within the window, in physical pixels from the top left
+    CursorMoved {
+        position: (f64, f64),
+    },
+    /// Scrolled distance in lines, where positive y is away from the user
+    MouseWheel {
+        delta: (f64, f64),
+    },
     MouseButtonPressed(MouseButton),
     MouseButtonReleased(MouseButton),
     MouseEntered,
@@ -110,6 +181,13 @@ pub enum PointerEvent {
 
 pub enum WindowEvent {
     Resized(WindowSize),
+    /// The window has moved to a monitor with a different DPI, or the monitor's scaling setting has
+    /// changed. The platform resizes the window to `size`, in physical pixels, to keep its logical
+    /// size the same.
+    ScaleFactorChanged {
+        scale_factor: f64,
+        size: WindowSize,
+    },
     Moved(WindowPosition),
     CloseRequested,
     FocusChanged,
@@ -120,13 +198,16 @@ pub enum WindowEvent {
 pub enum KeyboardEvent {
     KeyPressed(KeyCode),
     KeyReleased(KeyCode),
+    /// A character typed by the user, after applying the keyboard layout and modifiers. This
+    /// includes control characters, such as backspace.
+    CharacterInput(char),
 }
 
 pub enum ClipboardEvent {
     // TODO
 }
 
-#[derive(Clone, Copy, Debug)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
 pub enum MouseButton {
     Left,
     Right,
@@ -150,7 +231,7 @@ impl From<winit::event::MouseButton> for MouseButton {
     }
 }
 
-#[derive(Clone, Copy, Debug)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
 pub enum KeyCode {
     Digit1,
     Digit2,
@@ -1,112 +1,240 @@
-use winit::{event_loop::EventLoop, window::WindowBuilder};
+use winit::{
+    event_loop::EventLoopWindowTarget,
+    window::{WindowBuilder, WindowId},
+};
+
+use crate::{
+    CrtFilter, GraphicsBackend, PresentMode, PresentTiming, ScaleFilter, WindowPosition, WindowSize,
+};
+
+pub enum GraphicsContext<'a> {
+    Software(super::software::GraphicsContext<'a>),
+    OpenGl(super::opengl::GraphicsContext<'a>),
+}
 
-use crate::{WindowPosition, WindowSize};
+impl<'a> GraphicsContext<'a> {
+    pub fn framebuffer(&self) -> &[u32] {
+        match self {
+            Self::Software(context) => context.framebuffer(),
+            Self::OpenGl(context) => context.framebuffer(),
+        }
+    }
 
-pub type GraphicsContext<'a> = super::software::GraphicsContext<'a>;
+    pub fn framebuffer_mut(&mut self) -> &mut [u32] {
+        match self {
+            Self::Software(context) => context.framebuffer_mut(),
+            Self::OpenGl(context) => context.framebuffer_mut(),
+        }
+    }
+}
 
 pub enum InternalWindow {
     Software(super::software::SoftWindow),
+    OpenGl(super::opengl::GlWindow),
 }
 
 impl InternalWindow {
-    pub fn new(window_builder: WindowBuilder) -> (Self, EventLoop<()>) {
-        let (window, event_loop) = super::software::SoftWindow::new(window_builder);
-        return (Self::Software(window), event_loop);
+    /// Creates a window using the requested backend, falling back to software if it is unavailable
+    pub fn new(
+        window_builder: WindowBuilder,
+        backend: GraphicsBackend,
+        present_mode: PresentMode,
+        target: &EventLoopWindowTarget<()>,
+    ) -> Self {
+        if backend == GraphicsBackend::OpenGl {
+            match super::opengl::GlWindow::new(window_builder.clone(), target, present_mode) {
+                Ok(window) => return Self::OpenGl(window),
+                Err(error) => log::warn!("Failed to create OpenGL window, using software: {error}"),
+            }
+        }
+
+        Self::Software(super::software::SoftWindow::new(window_builder, target))
+    }
+
+    pub fn id(&self) -> WindowId {
+        match self {
+            Self::Software(window) => window.id(),
+            Self::OpenGl(window) => window.id(),
+        }
+    }
+
+    pub fn backend(&self) -> GraphicsBackend {
+        match self {
+            Self::Software(_) => GraphicsBackend::Software,
+            Self::OpenGl(_) => GraphicsBackend::OpenGl,
+        }
+    }
+
+    /// Number of physical pixels per logical pixel on the window's current monitor
+    pub fn scale_factor(&self) -> f64 {
+        match self {
+            Self::Software(window) => window.scale_factor(),
+            Self::OpenGl(window) => window.scale_factor(),
+        }
+    }
+
+    pub fn window_resized(&mut self, size: WindowSize) {
+        match self {
+            Self::Software(window) => window.window_resized(size),
+            Self::OpenGl(window) => window.window_resized(size),
+        }
+    }
+
+    /// Only the OpenGL backend filters when scaling, the software backend always uses the nearest
+    /// pixel
+    pub fn set_scale_filter(&mut self, filter: ScaleFilter) {
+        match self {
+            Self::Software(window) => window.set_scale_filter(filter),
+            Self::OpenGl(window) => window.set_scale_filter(filter),
+        }
+    }
+
+    /// The OpenGL backend hands each frame to the driver as soon as it is uploaded, so only the
+    /// software backend has anything to overlap with drawing
+    pub fn set_double_buffered(&mut self, double_buffered: bool) {
+        match self {
+            Self::Software(window) => window.set_double_buffered(double_buffered),
+            Self::OpenGl(_) => {}
+        }
+    }
+
+    pub fn set_crt_filter(&mut self, filter: Option<CrtFilter>) {
+        match self {
+            Self::Software(_) => {}
+            Self::OpenGl(window) => window.set_crt_filter(filter),
+        }
     }
 
     pub fn set_title(&mut self, title: &str) {
         match self {
             Self::Software(window) => window.set_title(title),
+            Self::OpenGl(window) => window.set_title(title),
         }
     }
 
     pub fn set_min_window_size(&mut self, size: Option<WindowSize>) {
         match self {
             Self::Software(window) => window.set_min_window_size(size),
+            Self::OpenGl(window) => window.set_min_window_size(size),
         }
     }
 
     pub fn set_max_window_size(&mut self, size: Option<WindowSize>) {
         match self {
             Self::Software(window) => window.set_max_window_size(size),
+            Self::OpenGl(window) => window.set_max_window_size(size),
         }
     }
 
     pub fn set_window_size(&mut self, size: WindowSize) {
         match self {
             Self::Software(window) => window.set_window_size(size),
+            Self::OpenGl(window) => window.set_window_size(size),
         }
     }
 
     pub fn set_surface_size(&mut self, size: WindowSize) {
         match self {
             Self::Software(window) => window.set_surface_size(size),
+            Self::OpenGl(window) => window.set_surface_size(size),
         }
     }
 
     pub fn set_position(&mut self, position: WindowPosition) {
         match self {
             Self::Software(window) => window.set_position(position),
+            Self::OpenGl(window) => window.set_position(position),
         }
     }
 
     pub fn set_resizable(&mut self, resizable: bool) {
         match self {
             Self::Software(window) => window.set_resizable(resizable),
+            Self::OpenGl(window) => window.set_resizable(resizable),
         }
     }
 
     pub fn set_minimised(&mut self, minimised: bool) {
         match self {
             Self::Software(window) => window.set_minimised(minimised),
+            Self::OpenGl(window) => window.set_minimised(minimised),
         }
     }
 
     pub fn focus(&mut self) {
         match self {
             Self::Software(window) => window.focus(),
+            Self::OpenGl(window) => window.focus(),
         }
     }
 
     pub fn swap_buffers(&mut self) {
         match self {
             Self::Software(window) => window.swap_buffers(),
+            Self::OpenGl(window) => window.swap_buffers(),
         }
     }
 
     pub fn set_maximised(&mut self, maximised: bool) {
         match self {
             Self::Software(window) => window.set_maximised(maximised),
+            Self::OpenGl(window) => window.set_maximised(maximised),
         }
     }
 
     pub fn request_redraw(&self) {
         match self {
             Self::Software(window) => window.request_redraw(),
+            Self::OpenGl(window) => window.request_redraw(),
         }
     }
 
     pub fn set_fullscreen(&mut self, fullscreen: bool) {
         match self {
             Self::Software(window) => window.set_fullscreen(fullscreen),
+            Self::OpenGl(window) => window.set_fullscreen(fullscreen),
         }
     }
 
     pub fn set_cursor_grab(&mut self, grabbed: bool) {
         match self {
             Self::Software(window) => window.set_cursor_grab(grabbed),
+            Self::OpenGl(window) => window.set_cursor_grab(grabbed),
         }
     }
 
     pub fn set_cursor_visible(&mut self, visible: bool) {
         match self {
             Self::Software(window) => window.set_cursor_visible(visible),
+            Self::OpenGl(window) => window.set_cursor_visible(visible),
+        }
+    }
+
+    pub fn supports_present_mode(&self, mode: PresentMode) -> bool {
+        match self {
+            Self::Software(window) => window.supports_present_mode(mode),
+            Self::OpenGl(window) => window.supports_present_mode(mode),
+        }
+    }
+
+    pub fn set_present_mode(&mut self, mode: PresentMode) {
+        match self {
+            Self::Software(window) => window.set_present_mode(mode),
+            Self::OpenGl(window) => window.set_present_mode(mode),
+        }
+    }
+
+    pub fn present_timing(&self) -> PresentTiming {
+        match self {
+            Self::Software(window) => window.present_timing(),
+            Self::OpenGl(window) => window.present_timing(),
         }
     }
 
     pub fn graphics_context(&mut self) -> GraphicsContext {
         match self {
-            Self::Software(window) => window.graphics_context(),
+            Self::Software(window) => GraphicsContext::Software(window.graphics_context()),
+            Self::OpenGl(window) => GraphicsContext::OpenGl(window.graphics_context()),
         }
     }
 }
@@ -1,4 +1,5 @@
 mod internal;
+mod opengl;
 mod vulkan;
 mod software;
 
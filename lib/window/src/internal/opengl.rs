@@ -0,0 +1,481 @@
+use std::time::{Duration, Instant};
+
+use glow::HasContext;
+use glutin::{ContextBuilder, GlProfile, GlRequest, PossiblyCurrent, WindowedContext};
+
+use crate::{
+    scale::Viewport, CrtFilter, PresentMode, PresentTiming, ScaleFilter, WindowPosition, WindowSize,
+};
+
+const VERTEX_SHADER: &str = r#"#version 330 core
+uniform vec2 u_scale;
+out vec2 v_uv;
+
+void main() {
+    // Single triangle that covers the whole viewport, with no vertex buffer needed. The first row of
+    // the framebuffer is the top of the image, but the bottom of the texture.
+    vec2 position = vec2(float((gl_VertexID << 1) & 2), float(gl_VertexID & 2));
+    v_uv = vec2(position.x, 1.0 - position.y);
+    gl_Position = vec4((position * 2.0 - 1.0) * u_scale, 0.0, 1.0);
+}
+"#;
+
+const FRAGMENT_SHADER: &str = r#"#version 330 core
+uniform sampler2D u_framebuffer;
+uniform vec2 u_surface_size;
+uniform bool u_crt;
+// Scanlines, aperture grille and curvature
+uniform vec3 u_crt_params;
+in vec2 v_uv;
+out vec4 colour;
+
+void main() {
+    vec2 uv = v_uv;
+
+    if (u_crt) {
+        // Barrel distortion, pushing points outwards further the more distant they are from the centre
+        vec2 centred = uv * 2.0 - 1.0;
+        centred *= 1.0 + u_crt_params.z * dot(centred, centred);
+        uv = centred * 0.5 + 0.5;
+    }
+
+    // Leave the borders around a scaled down or curved quad black
+    if (any(lessThan(uv, vec2(0.0))) || any(greaterThan(uv, vec2(1.0)))) {
+        discard;
+    }
+
+    vec3 rgb = texture(u_framebuffer, uv).rgb;
+
+    if (u_crt) {
+        // Brightest through the middle of each row of surface pixels, darkest between them
+        float row = fract(uv.y * u_surface_size.y);
+        rgb *= 1.0 - u_crt_params.x * (1.0 - sin(row * 3.14159265));
+
+        // Each column of window pixels lets through mostly one primary colour
+        vec3 mask = vec3(1.0 - u_crt_params.y);
+        mask[int(gl_FragCoord.x) % 3] = 1.0;
+        rgb *= mask;
+
+        // Make up for some of the brightness lost to the masks
+        rgb *= 1.0 + 0.5 * (u_crt_params.x + u_crt_params.y);
+    }
+
+    colour = vec4(rgb, 1.0);
+}
+"#;
+
+pub struct GraphicsContext<'a> {
+    framebuffer: &'a mut [u32],
+}
+
+impl<'a> GraphicsContext<'a> {
+    pub fn framebuffer(&self) -> &[u32] {
+        self.framebuffer
+    }
+
+    pub fn framebuffer_mut(&mut self) -> &mut [u32] {
+        self.framebuffer
+    }
+}
+
+/// Draws the framebuffer as a texture on a single quad, leaving scaling up to the window size to the
+/// GPU, rather than copying it through the CPU
+pub struct GlWindow {
+    /// Only ever `None` whilst the context is being made current. Each window has its own context, so
+    /// with more than one window, it must be made current before any GL calls.
+    pub(crate) context: Option<WindowedContext<PossiblyCurrent>>,
+    pub(crate) gl: glow::Context,
+    pub(crate) program: glow::Program,
+    pub(crate) texture: glow::Texture,
+    pub(crate) vertex_array: glow::VertexArray,
+    pub(crate) scale_location: Option<glow::UniformLocation>,
+    pub(crate) surface_size_location: Option<glow::UniformLocation>,
+    pub(crate) crt_location: Option<glow::UniformLocation>,
+    pub(crate) crt_params_location: Option<glow::UniformLocation>,
+    /// Pixels of the surface, uploaded to the texture when presented
+    pub(crate) framebuffer: Vec<u32>,
+    pub(crate) surface_size: WindowSize,
+    pub(crate) scale_filter: ScaleFilter,
+    /// The swap interval is chosen when the context is created, and can't be changed afterwards
+    pub(crate) present_mode: PresentMode,
+    pub(crate) last_present: Option<Instant>,
+    pub(crate) present_duration: Duration,
+}
+
+impl GlWindow {
+    pub fn new(
+        window_builder: winit::window::WindowBuilder,
+        target: &winit::event_loop::EventLoopWindowTarget<()>,
+        present_mode: PresentMode,
+    ) -> Result<Self, String> {
+        let context = ContextBuilder::new()
+            .with_gl(GlRequest::Specific(glutin::Api::OpenGl, (3, 3)))
+            .with_gl_profile(GlProfile::Core)
+            .with_vsync(present_mode == PresentMode::VSync)
+            .with_double_buffer(Some(true))
+            .build_windowed(window_builder, target)
+            .map_err(|e| e.to_string())?;
+        let context = unsafe { context.make_current() }.map_err(|(_, e)| e.to_string())?;
+
+        let gl = unsafe {
+            glow::Context::from_loader_function(|name| context.get_proc_address(name) as *const _)
+        };
+
+        let size = context.window().inner_size();
+        let surface_size = WindowSize::new(size.width as usize, size.height as usize);
+
+        unsafe {
+            let program = Self::create_program(&gl)?;
+            let texture = gl.create_texture()?;
+            let vertex_array = gl.create_vertex_array()?;
+            let scale_location = gl.get_uniform_location(program, "u_scale");
+            let surface_size_location = gl.get_uniform_location(program, "u_surface_size");
+            let crt_location = gl.get_uniform_location(program, "u_crt");
+            let crt_params_location = gl.get_uniform_location(program, "u_crt_params");
+
+            gl.use_program(Some(program));
+            gl.uniform_1_i32(
+                gl.get_uniform_location(program, "u_framebuffer").as_ref(),
+                0,
+            );
+            gl.bind_vertex_array(Some(vertex_array));
+            gl.active_texture(glow::TEXTURE0);
+            gl.bind_texture(glow::TEXTURE_2D, Some(texture));
+            gl.tex_parameter_i32(
+                glow::TEXTURE_2D,
+                glow::TEXTURE_WRAP_S,
+                glow::CLAMP_TO_EDGE as i32,
+            );
+            gl.tex_parameter_i32(
+                glow::TEXTURE_2D,
+                glow::TEXTURE_WRAP_T,
+                glow::CLAMP_TO_EDGE as i32,
+            );
+            gl.clear_color(0.0, 0.0, 0.0, 1.0);
+
+            let mut window = Self {
+                context: Some(context),
+                gl,
+                program,
+                texture,
+                vertex_array,
+                scale_location,
+                surface_size_location,
+                crt_location,
+                crt_params_location,
+                framebuffer: Vec::new(),
+                surface_size,
+                scale_filter: ScaleFilter::Nearest,
+                present_mode,
+                last_present: None,
+                present_duration: Duration::ZERO,
+            };
+
+            window.set_surface_size(surface_size);
+            window.set_scale_filter(ScaleFilter::Nearest);
+            window.set_crt_filter(None);
+
+            Ok(window)
+        }
+    }
+
+    unsafe fn create_program(gl: &glow::Context) -> Result<glow::Program, String> {
+        let program = gl.create_program()?;
+        let mut shaders = Vec::with_capacity(2);
+
+        for (shader_type, source) in [
+            (glow::VERTEX_SHADER, VERTEX_SHADER),
+            (glow::FRAGMENT_SHADER, FRAGMENT_SHADER),
+        ] {
+            let shader = gl.create_shader(shader_type)?;
+            gl.shader_source(shader, source);
+            gl.compile_shader(shader);
+
+            if !gl.get_shader_compile_status(shader) {
+                return Err(gl.get_shader_info_log(shader));
+            }
+
+            gl.attach_shader(program, shader);
+            shaders.push(shader);
+        }
+
+        gl.link_program(program);
+        if !gl.get_program_link_status(program) {
+            return Err(gl.get_program_info_log(program));
+        }
+
+        for shader in shaders {
+            gl.detach_shader(program, shader);
+            gl.delete_shader(shader);
+        }
+
+        Ok(program)
+    }
+
+    fn context(&self) -> &WindowedContext<PossiblyCurrent> {
+        self.context.as_ref().expect("Context is missing")
+    }
+
+    fn winit_window(&self) -> &winit::window::Window {
+        self.context().window()
+    }
+
+    /// Makes this window's context the current one on this thread, if another window's is
+    fn make_current(&mut self) {
+        if self.context().is_current() {
+            return;
+        }
+
+        let context = self.context.take().expect("Context is missing");
+        self.context = Some(match unsafe { context.make_current() } {
+            Ok(context) => context,
+            Err((context, error)) => {
+                log::warn!("Failed to make OpenGL context current: {error}");
+                context
+            }
+        });
+    }
+
+    pub fn id(&self) -> winit::window::WindowId {
+        self.winit_window().id()
+    }
+
+    pub fn scale_factor(&self) -> f64 {
+        self.winit_window().scale_factor()
+    }
+
+    pub fn set_title(&self, title: &str) {
+        self.winit_window().set_title(title);
+    }
+
+    pub fn set_min_window_size(&mut self, size: Option<WindowSize>) {
+        self.winit_window()
+            .set_min_inner_size(size.map(winit::dpi::PhysicalSize::from));
+    }
+
+    pub fn set_max_window_size(&mut self, size: Option<WindowSize>) {
+        self.winit_window()
+            .set_max_inner_size(size.map(winit::dpi::PhysicalSize::from));
+    }
+
+    pub fn set_window_size(&mut self, size: WindowSize) {
+        self.winit_window()
+            .set_inner_size(winit::dpi::PhysicalSize::from(size));
+    }
+
+    /// Resizes the drawable area of the context, once the window itself has been resized
+    pub fn window_resized(&mut self, size: WindowSize) {
+        self.make_current();
+        self.context().resize(size.into());
+    }
+
+    pub fn set_surface_size(&mut self, size: WindowSize) {
+        assert!(
+            size.width > 0 && size.height > 0,
+            "Surface size must be greater than zero"
+        );
+
+        self.surface_size = size;
+        self.framebuffer = vec![0; size.width * size.height];
+        self.make_current();
+
+        unsafe {
+            self.gl.tex_image_2d(
+                glow::TEXTURE_2D,
+                0,
+                glow::RGBA8 as i32,
+                size.width as i32,
+                size.height as i32,
+                0,
+                glow::BGRA,
+                glow::UNSIGNED_BYTE,
+                None,
+            );
+            self.gl.uniform_2_f32(
+                self.surface_size_location.as_ref(),
+                size.width as f32,
+                size.height as f32,
+            );
+        }
+    }
+
+    pub fn set_scale_filter(&mut self, filter: ScaleFilter) {
+        self.scale_filter = filter;
+
+        let gl_filter = match filter {
+            ScaleFilter::Linear => glow::LINEAR,
+            ScaleFilter::Nearest | ScaleFilter::Integer => glow::NEAREST,
+        } as i32;
+
+        self.make_current();
+        unsafe {
+            self.gl
+                .tex_parameter_i32(glow::TEXTURE_2D, glow::TEXTURE_MIN_FILTER, gl_filter);
+            self.gl
+                .tex_parameter_i32(glow::TEXTURE_2D, glow::TEXTURE_MAG_FILTER, gl_filter);
+        }
+    }
+
+    pub fn set_crt_filter(&mut self, filter: Option<CrtFilter>) {
+        let params = filter.unwrap_or(CrtFilter {
+            scanlines: 0.0,
+            aperture_grille: 0.0,
+            curvature: 0.0,
+        });
+
+        self.make_current();
+        unsafe {
+            self.gl
+                .uniform_1_i32(self.crt_location.as_ref(), filter.is_some() as i32);
+            self.gl.uniform_3_f32(
+                self.crt_params_location.as_ref(),
+                params.scanlines,
+                params.aperture_grille,
+                params.curvature,
+            );
+        }
+    }
+
+    pub fn set_position(&self, position: WindowPosition) {
+        self.winit_window()
+            .set_outer_position(winit::dpi::PhysicalPosition::from(position));
+    }
+
+    pub fn set_resizable(&self, resizable: bool) {
+        self.winit_window().set_resizable(resizable);
+    }
+
+    pub fn set_maximised(&self, maximised: bool) {
+        self.winit_window().set_maximized(maximised);
+    }
+
+    pub fn set_minimised(&self, minimised: bool) {
+        self.winit_window().set_minimized(minimised);
+    }
+
+    pub fn set_cursor_grab(&self, grabbed: bool) {
+        let window = self.winit_window();
+
+        if grabbed {
+            window
+                .set_cursor_grab(winit::window::CursorGrabMode::Locked)
+                .or_else(|_| window.set_cursor_grab(winit::window::CursorGrabMode::Confined))
+                .unwrap();
+        } else {
+            window
+                .set_cursor_grab(winit::window::CursorGrabMode::None)
+                .unwrap();
+        }
+    }
+
+    pub fn set_cursor_visible(&self, visible: bool) {
+        self.winit_window().set_cursor_visible(visible);
+    }
+
+    pub fn focus(&self) {
+        self.winit_window().focus_window();
+    }
+
+    pub fn set_fullscreen(&self, fullscreen: bool) {
+        if fullscreen {
+            self.winit_window()
+                .set_fullscreen(Some(winit::window::Fullscreen::Borderless(None)));
+        } else {
+            self.winit_window().set_fullscreen(None);
+        }
+    }
+
+    pub fn request_redraw(&self) {
+        self.winit_window().request_redraw();
+    }
+
+    /// Fraction of the viewport covered by the quad on each axis, which is centred with black bars
+    /// around it where the surface doesn't fill the window
+    fn quad_scale(&self, window_size: winit::dpi::PhysicalSize<u32>) -> (f32, f32) {
+        let window_size = WindowSize::new(window_size.width as usize, window_size.height as usize);
+        let viewport = Viewport::fit(self.surface_size, window_size, self.scale_filter);
+
+        (
+            viewport.width as f32 / window_size.width as f32,
+            viewport.height as f32 / window_size.height as f32,
+        )
+    }
+
+    pub fn swap_buffers(&mut self) {
+        let start = Instant::now();
+        let window_size = self.winit_window().inner_size();
+        let (scale_x, scale_y) = self.quad_scale(window_size);
+
+        self.make_current();
+        unsafe {
+            let gl = &self.gl;
+
+            gl.tex_sub_image_2d(
+                glow::TEXTURE_2D,
+                0,
+                0,
+                0,
+                self.surface_size.width as i32,
+                self.surface_size.height as i32,
+                glow::BGRA,
+                glow::UNSIGNED_BYTE,
+                glow::PixelUnpackData::Slice(as_bytes(&self.framebuffer)),
+            );
+
+            gl.viewport(0, 0, window_size.width as i32, window_size.height as i32);
+            gl.clear(glow::COLOR_BUFFER_BIT);
+            gl.uniform_2_f32(self.scale_location.as_ref(), scale_x, scale_y);
+            gl.draw_arrays(glow::TRIANGLES, 0, 3);
+        }
+
+        self.context().swap_buffers().unwrap_or_else(|e| {
+            panic!("Failed to present buffer: {}", e);
+        });
+
+        let end = Instant::now();
+        self.present_duration = end - start;
+        self.last_present = Some(end);
+    }
+
+    pub fn supports_present_mode(&self, mode: PresentMode) -> bool {
+        mode == self.present_mode
+    }
+
+    pub fn set_present_mode(&mut self, _mode: PresentMode) {}
+
+    pub fn present_timing(&self) -> PresentTiming {
+        let refresh_rate = self
+            .winit_window()
+            .current_monitor()
+            .and_then(|monitor| monitor.refresh_rate_millihertz())
+            .map(|millihertz| millihertz as f32 / 1000.0);
+
+        PresentTiming {
+            last_present: self.last_present,
+            present_duration: self.present_duration,
+            refresh_rate,
+        }
+    }
+
+    pub fn graphics_context(&mut self) -> GraphicsContext<'_> {
+        GraphicsContext {
+            framebuffer: &mut self.framebuffer,
+        }
+    }
+}
+
+impl Drop for GlWindow {
+    fn drop(&mut self) {
+        self.make_current();
+        unsafe {
+            self.gl.delete_program(self.program);
+            self.gl.delete_texture(self.texture);
+            self.gl.delete_vertex_array(self.vertex_array);
+        }
+    }
+}
+
+/// Reinterprets pixels as their bytes, which are in BGRA order on little-endian targets
+fn as_bytes(pixels: &[u32]) -> &[u8] {
+    unsafe { core::slice::from_raw_parts(pixels.as_ptr() as *const u8, pixels.len() * 4) }
+}
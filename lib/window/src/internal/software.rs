@@ -1,63 +1,89 @@
 use core::num::NonZeroU32;
+use std::time::{Duration, Instant};
 
-use crate::{WindowPosition, WindowSize};
+use crate::{
+    scale::{self, ScaleJob, Scaler},
+    PresentMode, PresentTiming, ScaleFilter, WindowPosition, WindowSize,
+};
 
 pub struct GraphicsContext<'a> {
-    framebuffer: softbuffer::Buffer<'a>,
+    framebuffer: &'a mut [u32],
 }
 
 impl<'a> GraphicsContext<'a> {
-    pub fn new(surface: &'a mut softbuffer::Surface) -> Self {
-        let buffer = surface.buffer_mut().unwrap_or_else(|e| {
-            panic!("Failed to get buffer: {}", e);
-        });
-
-        Self {
-            framebuffer: buffer,
-        }
-    }
-
     pub fn framebuffer(&self) -> &[u32] {
-        &*self.framebuffer
+        self.framebuffer
     }
 
     pub fn framebuffer_mut(&mut self) -> &mut [u32] {
-        &mut *self.framebuffer
+        self.framebuffer
     }
 }
 
+/// Draws the framebuffer into a buffer the size of the window on the CPU, scaling it to fit when
+/// the window is a different size
 pub struct SoftWindow {
     pub(crate) surface: softbuffer::Surface,
     pub(crate) context: softbuffer::Context,
     pub(crate) winit_window: winit::window::Window,
+    /// Pixels of the surface, scaled into the window's buffer when presented
+    pub(crate) framebuffer: Vec<u32>,
+    pub(crate) surface_size: WindowSize,
+    pub(crate) window_size: WindowSize,
+    /// Linear filtering isn't supported, so is treated as nearest
+    pub(crate) scale_filter: ScaleFilter,
+    /// Surface column for each window column, reused between presents
+    pub(crate) columns: Vec<Option<usize>>,
+    /// When double buffered, frames are scaled on another thread and presented on the next swap
+    pub(crate) scaler: Option<Scaler>,
+    /// Buffers returned by the scaler, to draw the next frame into and scale it into
+    pub(crate) back_framebuffer: Vec<u32>,
+    pub(crate) window_buffer: Vec<u32>,
+    pub(crate) last_present: Option<Instant>,
+    pub(crate) present_duration: Duration,
 }
 
 impl SoftWindow {
     pub fn new(
         window_builder: winit::window::WindowBuilder,
-    ) -> (Self, winit::event_loop::EventLoop<()>) {
-        let event_loop = winit::event_loop::EventLoop::new();
-        let window = window_builder.build(&event_loop).unwrap();
+        target: &winit::event_loop::EventLoopWindowTarget<()>,
+    ) -> Self {
+        let window = window_builder.build(target).unwrap();
         let context = unsafe { softbuffer::Context::new(&window) }.unwrap();
-        let mut surface = unsafe { softbuffer::Surface::new(&context, &window) }.unwrap();
+        let surface = unsafe { softbuffer::Surface::new(&context, &window) }.unwrap();
+        let size = WindowSize::new(
+            window.inner_size().width as usize,
+            window.inner_size().height as usize,
+        );
 
-        surface
-            .resize(
-                NonZeroU32::new(window.inner_size().width)
-                    .expect("Width must be greater than zero"),
-                NonZeroU32::new(window.inner_size().height)
-                    .expect("Height must be greater than zero"),
-            )
-            .unwrap();
+        let mut window = Self {
+            surface,
+            context,
+            winit_window: window,
+            framebuffer: Vec::new(),
+            surface_size: size,
+            window_size: size,
+            scale_filter: ScaleFilter::Nearest,
+            columns: Vec::new(),
+            scaler: None,
+            back_framebuffer: Vec::new(),
+            window_buffer: Vec::new(),
+            last_present: None,
+            present_duration: Duration::ZERO,
+        };
+
+        window.window_resized(size);
+        window.set_surface_size(size);
+
+        window
+    }
+
+    pub fn id(&self) -> winit::window::WindowId {
+        self.winit_window.id()
+    }
 
-        (
-            Self {
-                surface,
-                context,
-                winit_window: window,
-            },
-            event_loop,
-        )
+    pub fn scale_factor(&self) -> f64 {
+        self.winit_window.scale_factor()
     }
 
     pub fn set_title(&self, title: &str) {
@@ -79,7 +105,9 @@ impl SoftWindow {
             .set_inner_size(winit::dpi::PhysicalSize::from(size));
     }
 
-    pub fn set_surface_size(&mut self, size: WindowSize) {
+    /// Resizes the buffer presented to the window, once the window itself has been resized
+    pub fn window_resized(&mut self, size: WindowSize) {
+        self.window_size = size;
         self.surface
             .resize(
                 NonZeroU32::new(size.width as u32).expect("Width must be greater than zero"),
@@ -88,6 +116,20 @@ impl SoftWindow {
             .unwrap();
     }
 
+    pub fn set_surface_size(&mut self, size: WindowSize) {
+        assert!(
+            size.width > 0 && size.height > 0,
+            "Surface size must be greater than zero"
+        );
+
+        self.surface_size = size;
+        self.framebuffer = vec![0; size.width * size.height];
+    }
+
+    pub fn set_scale_filter(&mut self, filter: ScaleFilter) {
+        self.scale_filter = filter;
+    }
+
     pub fn set_position(&self, position: WindowPosition) {
         self.winit_window
             .set_outer_position(winit::dpi::PhysicalPosition::from(position));
@@ -143,16 +185,122 @@ impl SoftWindow {
     }
 
     pub fn swap_buffers(&mut self) {
-        let buffer = self.surface.buffer_mut().unwrap_or_else(|e| {
-            panic!("Failed to get buffer: {}", e);
-        });
+        let start = Instant::now();
+
+        if self.scaler.is_some() {
+            self.swap_double_buffered();
+        } else {
+            let mut buffer = self.surface.buffer_mut().unwrap_or_else(|e| {
+                panic!("Failed to get buffer: {}", e);
+            });
+
+            scale::blit_nearest(
+                &self.framebuffer,
+                self.surface_size,
+                &mut buffer,
+                self.window_size,
+                self.scale_filter,
+                &mut self.columns,
+            );
 
-        buffer.present().unwrap_or_else(|e| {
-            panic!("Failed to present buffer: {}", e);
+            buffer.present().unwrap_or_else(|e| {
+                panic!("Failed to present buffer: {}", e);
+            });
+        }
+
+        let end = Instant::now();
+        self.present_duration = end - start;
+        self.last_present = Some(end);
+    }
+
+    /// Presents the previous frame, which was scaled whilst this one was drawn, then hands this frame
+    /// to the scaler and swaps in the other buffer to draw the next frame into
+    fn swap_double_buffered(&mut self) {
+        let Some(scaler) = self.scaler.as_mut() else {
+            return;
+        };
+
+        if let Some(job) = scaler.receive() {
+            let mut buffer = self.surface.buffer_mut().unwrap_or_else(|e| {
+                panic!("Failed to get buffer: {}", e);
+            });
+
+            // The window may have been resized since, in which case it is scaled again to fit
+            if job.window_size == self.window_size {
+                buffer.copy_from_slice(&job.window);
+            } else {
+                scale::blit_nearest(
+                    &job.surface,
+                    job.surface_size,
+                    &mut buffer,
+                    self.window_size,
+                    self.scale_filter,
+                    &mut self.columns,
+                );
+            }
+
+            buffer.present().unwrap_or_else(|e| {
+                panic!("Failed to present buffer: {}", e);
+            });
+
+            if job.surface_size == self.surface_size {
+                self.back_framebuffer = job.surface;
+            }
+            self.window_buffer = job.window;
+        }
+
+        let mut framebuffer = core::mem::take(&mut self.back_framebuffer);
+        framebuffer.resize(self.surface_size.width * self.surface_size.height, 0);
+        core::mem::swap(&mut self.framebuffer, &mut framebuffer);
+
+        scaler.send(ScaleJob {
+            surface: framebuffer,
+            surface_size: self.surface_size,
+            window: core::mem::take(&mut self.window_buffer),
+            window_size: self.window_size,
+            filter: self.scale_filter,
         });
     }
 
+    /// Starts or stops scaling frames on a separate thread. Whilst double buffered, each frame is
+    /// presented on the following swap, a frame late, and the framebuffer handed out afterwards
+    /// holds the frame from two swaps before.
+    pub fn set_double_buffered(&mut self, double_buffered: bool) {
+        if double_buffered == self.scaler.is_some() {
+            return;
+        }
+
+        // Dropping the scaler waits for any frame in flight, which is then never presented
+        self.scaler = double_buffered.then(Scaler::new);
+        self.back_framebuffer = Vec::new();
+        self.window_buffer = Vec::new();
+    }
+
+    /// Softbuffer copies the buffer to the window as soon as it is presented, with no control over
+    /// synchronising to the vertical blank
+    pub fn supports_present_mode(&self, mode: PresentMode) -> bool {
+        mode == PresentMode::Immediate
+    }
+
+    pub fn set_present_mode(&mut self, _mode: PresentMode) {}
+
+    pub fn present_timing(&self) -> PresentTiming {
+        let refresh_rate = self
+            .winit_window
+            .current_monitor()
+            .and_then(|monitor| monitor.refresh_rate_millihertz())
+            .map(|millihertz| millihertz as f32 / 1000.0);
+
+        PresentTiming {
+            last_present: self.last_present,
+            present_duration: self.present_duration,
+            refresh_rate,
+        }
+    }
+
     pub fn graphics_context(&mut self) -> GraphicsContext {
-        GraphicsContext::new(&mut self.surface)
+        GraphicsContext {
+            framebuffer: &mut self.framebuffer,
+        }
     }
 }
@@ -1,6 +1,8 @@
 mod internal;
+mod scale;
 mod window;
 
+pub use scale::SurfaceTransform;
 pub use window::*;
 pub mod event;
 pub mod application;
@@ -0,0 +1,350 @@
+use std::{
+    sync::mpsc::{self, Receiver, Sender},
+    thread::JoinHandle,
+};
+
+use crate::{ScaleFilter, WindowSize};
+
+/// Area of the window, in physical pixels, that the surface is scaled to. It is centred, with black
+/// bars either side of it where the aspect ratios differ. With integer scaling it can be larger than
+/// the window, if the window is smaller than the surface, in which case the offset is negative.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) struct Viewport {
+    pub x: isize,
+    pub y: isize,
+    pub width: usize,
+    pub height: usize,
+}
+
+impl Viewport {
+    pub fn fit(surface: WindowSize, window: WindowSize, filter: ScaleFilter) -> Self {
+        if surface.width == 0 || surface.height == 0 {
+            return Self::centred(window, window.width, window.height);
+        }
+
+        if filter == ScaleFilter::Integer {
+            let scale = (window.width / surface.width)
+                .min(window.height / surface.height)
+                .max(1);
+
+            return Self::centred(window, surface.width * scale, surface.height * scale);
+        }
+
+        // Whichever side of the window is relatively shorter is filled, compared without dividing
+        if window.width * surface.height <= window.height * surface.width {
+            let height = (window.width * surface.height + surface.width / 2) / surface.width;
+            Self::centred(window, window.width, height)
+        } else {
+            let width = (window.height * surface.width + surface.height / 2) / surface.height;
+            Self::centred(window, width, window.height)
+        }
+    }
+
+    fn centred(window: WindowSize, width: usize, height: usize) -> Self {
+        Self {
+            x: (window.width as isize - width as isize) / 2,
+            y: (window.height as isize - height as isize) / 2,
+            width,
+            height,
+        }
+    }
+}
+
+/// Maps between positions in the window and on the surface, undoing the scaling and letterboxing
+/// applied when the surface is drawn to the window
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct SurfaceTransform {
+    viewport: Viewport,
+    surface: WindowSize,
+}
+
+impl SurfaceTransform {
+    pub fn new(surface: WindowSize, window: WindowSize, filter: ScaleFilter) -> Self {
+        Self {
+            viewport: Viewport::fit(surface, window, filter),
+            surface,
+        }
+    }
+
+    /// Converts a position in the window, in physical pixels, to surface pixels. Returns `None` if
+    /// the position is in the black bars around the surface, or outside the window.
+    pub fn to_surface(&self, x: f64, y: f64) -> Option<(f64, f64)> {
+        if self.viewport.width == 0 || self.viewport.height == 0 {
+            return None;
+        }
+
+        let x =
+            (x - self.viewport.x as f64) * self.surface.width as f64 / self.viewport.width as f64;
+        let y =
+            (y - self.viewport.y as f64) * self.surface.height as f64 / self.viewport.height as f64;
+
+        (x >= 0.0 && y >= 0.0 && x < self.surface.width as f64 && y < self.surface.height as f64)
+            .then_some((x, y))
+    }
+
+    /// Converts a position on the surface to where it is drawn in the window, in physical pixels
+    pub fn to_window(&self, x: f64, y: f64) -> (f64, f64) {
+        (
+            x * self.viewport.width as f64 / self.surface.width as f64 + self.viewport.x as f64,
+            y * self.viewport.height as f64 / self.surface.height as f64 + self.viewport.y as f64,
+        )
+    }
+}
+
+/// Copies the surface to the window, scaled to fit the viewport using the nearest pixel, and clears
+/// the bars around it to black. `columns` is scratch space, kept between calls to avoid allocating.
+pub(crate) fn blit_nearest(
+    src: &[u32],
+    surface: WindowSize,
+    dst: &mut [u32],
+    window: WindowSize,
+    filter: ScaleFilter,
+    columns: &mut Vec<Option<usize>>,
+) {
+    if surface == window {
+        dst.copy_from_slice(src);
+        return;
+    }
+
+    let viewport = Viewport::fit(surface, window, filter);
+
+    // Surface column for each window column, or none where it falls in a bar
+    columns.clear();
+    columns.extend((0..window.width).map(|x| {
+        let x = x as isize - viewport.x;
+        (x >= 0 && (x as usize) < viewport.width)
+            .then(|| x as usize * surface.width / viewport.width)
+    }));
+
+    for (y, row) in dst.chunks_exact_mut(window.width).enumerate() {
+        let y = y as isize - viewport.y;
+        if y < 0 || y as usize >= viewport.height {
+            row.fill(0);
+            continue;
+        }
+
+        let src_row = &src[y as usize * surface.height / viewport.height * surface.width..];
+        for (pixel, column) in row.iter_mut().zip(columns.iter()) {
+            *pixel = column.map_or(0, |column| src_row[column]);
+        }
+    }
+}
+
+/// A frame to be scaled to the window size, along with the buffer to scale it into. Both buffers
+/// are handed back once done, to be reused.
+pub(crate) struct ScaleJob {
+    pub surface: Vec<u32>,
+    pub surface_size: WindowSize,
+    pub window: Vec<u32>,
+    pub window_size: WindowSize,
+    pub filter: ScaleFilter,
+}
+
+/// Scales frames on a thread of its own, so the next frame can be drawn whilst the last is being
+/// scaled. Frames are scaled one at a time, in the order they are sent.
+pub(crate) struct Scaler {
+    jobs: Option<Sender<ScaleJob>>,
+    done: Receiver<ScaleJob>,
+    /// Whether a frame has been sent that hasn't been received back yet
+    in_flight: bool,
+    thread: Option<JoinHandle<()>>,
+}
+
+impl Scaler {
+    pub fn new() -> Self {
+        let (jobs, jobs_receiver) = mpsc::channel::<ScaleJob>();
+        let (done_sender, done) = mpsc::channel();
+
+        let thread = std::thread::Builder::new()
+            .name(String::from("scaler"))
+            .spawn(move || {
+                let mut columns = Vec::new();
+                for mut job in jobs_receiver {
+                    job.window
+                        .resize(job.window_size.width * job.window_size.height, 0);
+                    blit_nearest(
+                        &job.surface,
+                        job.surface_size,
+                        &mut job.window,
+                        job.window_size,
+                        job.filter,
+                        &mut columns,
+                    );
+
+                    if done_sender.send(job).is_err() {
+                        break;
+                    }
+                }
+            })
+            .expect("Failed to spawn scaler thread");
+
+        Self {
+            jobs: Some(jobs),
+            done,
+            in_flight: false,
+            thread: Some(thread),
+        }
+    }
+
+    /// Starts scaling the frame. Only one frame can be in flight at a time, so any previous frame
+    /// must have been received first.
+    pub fn send(&mut self, job: ScaleJob) {
+        assert!(!self.in_flight, "A frame is already being scaled");
+
+        if let Some(jobs) = &self.jobs {
+            self.in_flight = jobs.send(job).is_ok();
+        }
+    }
+
+    /// Waits for the frame in flight to finish scaling, if there is one
+    pub fn receive(&mut self) -> Option<ScaleJob> {
+        if !self.in_flight {
+            return None;
+        }
+
+        self.in_flight = false;
+        self.done.recv().ok()
+    }
+}
+
+impl Drop for Scaler {
+    fn drop(&mut self) {
+        // Closing the channel lets the thread finish once it has scaled any frame in flight
+        self.jobs = None;
+        if let Some(thread) = self.thread.take() {
+            let _ = thread.join();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn fit_adds_bars_to_preserve_aspect() {
+        let surface = WindowSize::new(320, 200);
+
+        // Wider window, so bars down the sides
+        let viewport = Viewport::fit(surface, WindowSize::new(1000, 400), ScaleFilter::Nearest);
+        assert_eq!(viewport.height, 400);
+        assert_eq!(viewport.width, 640);
+        assert_eq!(viewport.x, 180);
+        assert_eq!(viewport.y, 0);
+
+        // Taller window, so bars along the top and bottom
+        let viewport = Viewport::fit(surface, WindowSize::new(480, 600), ScaleFilter::Linear);
+        assert_eq!(viewport.width, 480);
+        assert_eq!(viewport.height, 300);
+        assert_eq!(viewport.y, 150);
+    }
+
+    #[test]
+    fn fit_integer_uses_whole_multiples() {
+        let surface = WindowSize::new(320, 200);
+
+        let viewport = Viewport::fit(surface, WindowSize::new(1000, 700), ScaleFilter::Integer);
+        assert_eq!((viewport.width, viewport.height), (960, 600));
+        assert_eq!((viewport.x, viewport.y), (20, 50));
+
+        // Never scales down, so overflows a window smaller than the surface
+        let viewport = Viewport::fit(surface, WindowSize::new(300, 100), ScaleFilter::Integer);
+        assert_eq!((viewport.width, viewport.height), (320, 200));
+        assert_eq!((viewport.x, viewport.y), (-10, -50));
+    }
+
+    #[test]
+    fn transform_round_trips_and_rejects_bars() {
+        let transform = SurfaceTransform::new(
+            WindowSize::new(320, 200),
+            WindowSize::new(1000, 400),
+            ScaleFilter::Nearest,
+        );
+
+        assert_eq!(transform.to_surface(180.0, 0.0), Some((0.0, 0.0)));
+        assert_eq!(transform.to_surface(500.0, 200.0), Some((160.0, 100.0)));
+        assert_eq!(transform.to_window(160.0, 100.0), (500.0, 200.0));
+
+        // In the bars down either side
+        assert_eq!(transform.to_surface(100.0, 200.0), None);
+        assert_eq!(transform.to_surface(820.0, 200.0), None);
+    }
+
+    #[test]
+    fn blit_scales_and_clears_bars() {
+        let surface = WindowSize::new(2, 1);
+        let src = [1, 2];
+        let window = WindowSize::new(4, 4);
+        let mut dst = [u32::MAX; 16];
+
+        blit_nearest(
+            &src,
+            surface,
+            &mut dst,
+            window,
+            ScaleFilter::Nearest,
+            &mut Vec::new(),
+        );
+
+        #[rustfmt::skip]
+        assert_eq!(dst, [
+            0, 0, 0, 0,
+            1, 1, 2, 2,
+            1, 1, 2, 2,
+            0, 0, 0, 0,
+        ]);
+    }
+
+    #[test]
+    fn blit_crops_overflowing_integer_viewport() {
+        let surface = WindowSize::new(4, 1);
+        let src = [1, 2, 3, 4];
+        let window = WindowSize::new(2, 1);
+        let mut dst = [0; 2];
+
+        blit_nearest(
+            &src,
+            surface,
+            &mut dst,
+            window,
+            ScaleFilter::Integer,
+            &mut Vec::new(),
+        );
+
+        assert_eq!(dst, [2, 3]);
+    }
+
+    #[test]
+    fn scaler_matches_blit() {
+        let surface_size = WindowSize::new(3, 2);
+        let surface = vec![1, 2, 3, 4, 5, 6];
+        let window_size = WindowSize::new(8, 5);
+
+        let mut expected = vec![0; 40];
+        blit_nearest(
+            &surface,
+            surface_size,
+            &mut expected,
+            window_size,
+            ScaleFilter::Nearest,
+            &mut Vec::new(),
+        );
+
+        let mut scaler = Scaler::new();
+        assert!(scaler.receive().is_none());
+
+        for _ in 0..2 {
+            scaler.send(ScaleJob {
+                surface: surface.clone(),
+                surface_size,
+                window: Vec::new(),
+                window_size,
+                filter: ScaleFilter::Nearest,
+            });
+
+            let job = scaler.receive().unwrap();
+            assert_eq!(job.surface, surface);
+            assert_eq!(job.window, expected);
+        }
+    }
+}
@@ -1,10 +1,15 @@
+use std::time::{Duration, Instant};
+
 use winit::{
     dpi::{PhysicalPosition, PhysicalSize},
-    event_loop::EventLoop,
+    event_loop::{EventLoop, EventLoopWindowTarget},
     window::{Fullscreen, WindowBuilder},
 };
 
-use crate::internal::{GraphicsContext, InternalWindow};
+use crate::{
+    internal::{GraphicsContext, InternalWindow},
+    SurfaceTransform,
+};
 
 pub struct Window {
     pub(crate) internal: InternalWindow,
@@ -13,7 +18,18 @@ pub struct Window {
 }
 
 impl Window {
+    /// Creates the main window, along with the event loop that every window's events come through
     pub fn new(config: WindowAttributes) -> Self {
+        let event_loop = EventLoop::new();
+        let mut window = Self::with_target(config, &WindowTarget(&event_loop));
+        window.event_loop = Some(event_loop);
+
+        window
+    }
+
+    /// Creates an additional window, sharing the event loop of the main window. Its events are
+    /// tagged with its ID, and it is drawn to and presented separately.
+    pub fn with_target(mut config: WindowAttributes, target: &WindowTarget) -> Self {
         let window_builder = WindowBuilder::new()
             .with_title(config.title.to_owned())
             .with_resizable(config.resizable)
@@ -21,7 +37,14 @@ impl Window {
             .with_position::<PhysicalPosition<u32>>(config.position.into())
             .with_fullscreen(config.fullscreen.then(|| Fullscreen::Borderless(None)));
 
-        let (mut internal, event_loop) = InternalWindow::new(window_builder);
+        let mut internal = InternalWindow::new(
+            window_builder,
+            config.backend,
+            config.present_mode,
+            target.0,
+        );
+        config.backend = internal.backend();
+        config.scale_factor = internal.scale_factor();
 
         // Set other window attributes not handled by the window builder
         internal.set_minimised(config.minimised);
@@ -32,16 +55,35 @@ impl Window {
             internal.focus()
         };
 
-        config.surface_size.map(|size| internal.set_surface_size(size));
+        config
+            .surface_size
+            .map(|size| internal.set_surface_size(size));
 
+        if !internal.supports_present_mode(config.present_mode) {
+            config.present_mode = PresentMode::Immediate;
+        }
+        internal.set_present_mode(config.present_mode);
+        internal.set_scale_filter(config.scale_filter);
+        internal.set_crt_filter(config.crt_filter);
+        internal.set_double_buffered(config.double_buffered);
 
         Self {
             internal,
             attributes: config,
-            event_loop: Some(event_loop),
+            event_loop: None,
         }
     }
 
+    pub fn id(&self) -> WindowId {
+        WindowId(self.internal.id())
+    }
+
+    /// The main window's event loop, for creating additional windows before it starts running. Once
+    /// running, `WindowApplication::on_events_cleared` is given the target instead.
+    pub fn target(&self) -> Option<WindowTarget> {
+        self.event_loop.as_deref().map(WindowTarget)
+    }
+
     pub fn take_event_loop(&mut self) -> EventLoop<()> {
         self.event_loop.take().expect("Event loop already taken")
     }
@@ -67,10 +109,35 @@ impl Window {
         self.internal.set_window_size(size);
     }
 
+    /// Size of the window's contents in physical pixels, which is what `set_size` takes
     pub fn get_size(&self) -> WindowSize {
         self.attributes.size
     }
 
+    /// Size of the window's contents in logical pixels, i.e. before the monitor's scale factor is
+    /// applied
+    pub fn get_logical_size(&self) -> WindowSize {
+        self.attributes
+            .size
+            .to_logical(self.attributes.scale_factor)
+    }
+
+    /// Maps positions in the window, such as the cursor, to pixels of the surface, accounting for
+    /// how the surface is scaled and letterboxed
+    pub fn surface_transform(&self) -> SurfaceTransform {
+        SurfaceTransform::new(
+            self.attributes.surface_size.unwrap_or(self.attributes.size),
+            self.attributes.size,
+            self.attributes.scale_filter,
+        )
+    }
+
+    /// Number of physical pixels per logical pixel on the monitor that the window is on, such as
+    /// 1.5 or 2.0 on a high-DPI display
+    pub fn get_scale_factor(&self) -> f64 {
+        self.attributes.scale_factor
+    }
+
     pub fn set_resizable(&mut self, resizable: bool) {
         if self.attributes.resizable == resizable {
             return;
@@ -151,6 +218,176 @@ impl Window {
     pub fn swap_buffers(&mut self) {
         self.internal.swap_buffers();
     }
+
+    /// Returns whether the backend is able to present using the given mode
+    pub fn supports_present_mode(&self, mode: PresentMode) -> bool {
+        self.internal.supports_present_mode(mode)
+    }
+
+    /// Changes how frames are presented, returning false (and leaving the mode unchanged) if the
+    /// backend does not support it
+    pub fn set_present_mode(&mut self, mode: PresentMode) -> bool {
+        if self.attributes.present_mode == mode {
+            return true;
+        }
+
+        if !self.internal.supports_present_mode(mode) {
+            return false;
+        }
+
+        self.attributes.present_mode = mode;
+        self.internal.set_present_mode(mode);
+        true
+    }
+
+    pub fn get_present_mode(&self) -> PresentMode {
+        self.attributes.present_mode
+    }
+
+    pub fn present_timing(&self) -> PresentTiming {
+        self.internal.present_timing()
+    }
+
+    /// Returns the backend in use, which may differ from the one requested if it was unavailable
+    pub fn get_backend(&self) -> GraphicsBackend {
+        self.attributes.backend
+    }
+
+    pub fn set_scale_filter(&mut self, filter: ScaleFilter) {
+        if self.attributes.scale_filter == filter {
+            return;
+        }
+
+        self.attributes.scale_filter = filter;
+        self.internal.set_scale_filter(filter);
+    }
+
+    pub fn get_scale_filter(&self) -> ScaleFilter {
+        self.attributes.scale_filter
+    }
+
+    /// Lets the next frame be drawn whilst the last is being scaled to the window, at the cost of
+    /// presenting each frame one swap later. Only the software backend is affected.
+    pub fn set_double_buffered(&mut self, double_buffered: bool) {
+        if self.attributes.double_buffered == double_buffered {
+            return;
+        }
+
+        self.attributes.double_buffered = double_buffered;
+        self.internal.set_double_buffered(double_buffered);
+    }
+
+    pub fn get_double_buffered(&self) -> bool {
+        self.attributes.double_buffered
+    }
+
+    pub fn set_crt_filter(&mut self, filter: Option<CrtFilter>) {
+        if self.attributes.crt_filter == filter {
+            return;
+        }
+
+        self.attributes.crt_filter = filter;
+        self.internal.set_crt_filter(filter);
+    }
+
+    pub fn get_crt_filter(&self) -> Option<CrtFilter> {
+        self.attributes.crt_filter
+    }
+}
+
+/// Identifies which window an event is for
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct WindowId(pub(crate) winit::window::WindowId);
+
+/// Needed to create windows, which must share the event loop of the main window
+#[derive(Clone, Copy)]
+pub struct WindowTarget<'a>(pub(crate) &'a EventLoopWindowTarget<()>);
+
+/// How the surface is drawn to the window
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum GraphicsBackend {
+    /// Copies the surface to the window on the CPU
+    Software,
+    /// Uploads the surface as a texture, and draws it scaled to the window on the GPU
+    OpenGl,
+}
+
+/// How the surface is scaled up to the window size. Whatever the size of the window, the surface
+/// keeps its aspect ratio, centred with black bars either side where the two differ.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ScaleFilter {
+    /// Scales to fit the window, using the nearest pixel
+    Nearest,
+    /// Scales to fit the window, blending between neighbouring pixels. Only `GraphicsBackend::OpenGl`
+    /// filters, so the software backend uses the nearest pixel instead.
+    Linear,
+    /// Scales by the largest whole multiple that fits within the window, so that every pixel is the
+    /// same size
+    Integer,
+}
+
+/// Imitates a CRT display whilst scaling the surface up to the window. This only applies to
+/// `GraphicsBackend::OpenGl`.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct CrtFilter {
+    /// How much the gaps between rows of surface pixels are darkened, from 0 to 1
+    pub scanlines: f32,
+    /// Strength of the red, green and blue stripes of an aperture grille, from 0 to 1
+    pub aperture_grille: f32,
+    /// How far the image bulges outwards, where 0 is flat
+    pub curvature: f32,
+}
+
+impl CrtFilter {
+    /// Scales the strength of every effect, e.g. 0.5 for a subtler look
+    pub fn with_intensity(self, intensity: f32) -> Self {
+        Self {
+            scanlines: (self.scanlines * intensity).clamp(0.0, 1.0),
+            aperture_grille: (self.aperture_grille * intensity).clamp(0.0, 1.0),
+            curvature: self.curvature * intensity,
+        }
+    }
+}
+
+impl Default for CrtFilter {
+    fn default() -> Self {
+        Self {
+            scanlines: 0.4,
+            aperture_grille: 0.2,
+            curvature: 0.04,
+        }
+    }
+}
+
+/// How finished frames are handed over to the display
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum PresentMode {
+    /// Presents as soon as the frame is ready, which may tear. The app is responsible for limiting
+    /// its frame rate.
+    Immediate,
+    /// Waits for the display's vertical blank before presenting, so presenting blocks and the frame
+    /// rate is limited to the refresh rate.
+    VSync,
+}
+
+/// Timing of the most recent present, for the app to pace its frames by
+#[derive(Clone, Copy, Debug)]
+pub struct PresentTiming {
+    /// When the last frame was presented, if any have been yet
+    pub last_present: Option<Instant>,
+    /// How long the last present took, including any time spent waiting for the vertical blank
+    pub present_duration: Duration,
+    /// Refresh rate of the monitor that the window is on, in hertz, if known
+    pub refresh_rate: Option<f32>,
+}
+
+impl PresentTiming {
+    /// Time between refreshes of the monitor, if known
+    pub fn refresh_interval(&self) -> Option<Duration> {
+        self.refresh_rate
+            .filter(|rate| *rate > 0.0)
+            .map(|rate| Duration::from_secs_f32(1.0 / rate))
+    }
 }
 
 #[derive(Clone, Copy, Debug, PartialEq)]
@@ -163,6 +400,22 @@ impl WindowSize {
     pub fn new(width: usize, height: usize) -> Self {
         Self { width, height }
     }
+
+    /// Converts a size in physical pixels to logical pixels, rounding to the nearest pixel
+    pub fn to_logical(&self, scale_factor: f64) -> Self {
+        Self {
+            width: (self.width as f64 / scale_factor).round() as usize,
+            height: (self.height as f64 / scale_factor).round() as usize,
+        }
+    }
+
+    /// Converts a size in logical pixels to physical pixels, rounding to the nearest pixel
+    pub fn to_physical(&self, scale_factor: f64) -> Self {
+        Self {
+            width: (self.width as f64 * scale_factor).round() as usize,
+            height: (self.height as f64 * scale_factor).round() as usize,
+        }
+    }
 }
 
 impl From<WindowSize> for winit::dpi::PhysicalSize<u32> {
@@ -197,12 +450,25 @@ pub struct WindowAttributes {
     pub focused: bool,
     pub fullscreen: bool,
     pub position: WindowPosition,
+    /// In physical pixels
     pub size: WindowSize,
     pub max_size: Option<WindowSize>,
     pub min_size: Option<WindowSize>,
     pub surface_size: Option<WindowSize>,
     pub grabbed_cursor: bool,
     pub visible_cursor: bool,
+    /// Falls back to `PresentMode::Immediate` if the backend does not support it
+    pub present_mode: PresentMode,
+    /// Falls back to `GraphicsBackend::Software` if the backend is unavailable
+    pub backend: GraphicsBackend,
+    pub scale_filter: ScaleFilter,
+    pub crt_filter: Option<CrtFilter>,
+    /// Scales each frame on another thread whilst the next is drawn, presenting it on the following
+    /// swap, so the contents of the framebuffer are stale after each swap. Ignored by the OpenGL
+    /// backend.
+    pub double_buffered: bool,
+    /// Set by the window from the monitor it is on, so any value given here is ignored
+    pub scale_factor: f64,
 }
 
 impl Default for WindowAttributes {
@@ -221,6 +487,12 @@ impl Default for WindowAttributes {
             surface_size: None,
             grabbed_cursor: false,
             visible_cursor: true,
+            present_mode: PresentMode::Immediate,
+            backend: GraphicsBackend::Software,
+            scale_filter: ScaleFilter::Nearest,
+            crt_filter: None,
+            double_buffered: false,
+            scale_factor: 1.0,
         }
     }
 }
@@ -0,0 +1,150 @@
+//! Per-frame heap allocation tracking, used to verify that frames are rendered without allocating.
+//!
+//! Counting requires replacing the global allocator, so it is only done when the `alloc-audit`
+//! feature is enabled. Otherwise, all statistics are reported as zero.
+
+#[derive(Clone, Copy, Debug, Default)]
+pub struct AllocStats {
+    /// Number of allocations (including reallocations)
+    pub allocations: usize,
+    pub deallocations: usize,
+    /// Total bytes requested by allocations
+    pub allocated_bytes: usize,
+    /// Highest number of bytes that were allocated at any one time
+    pub peak_live_bytes: usize,
+}
+
+#[cfg(feature = "alloc-audit")]
+mod counting {
+    use std::{
+        alloc::{GlobalAlloc, Layout, System},
+        sync::atomic::{AtomicUsize, Ordering},
+    };
+
+    pub static ALLOCATIONS: AtomicUsize = AtomicUsize::new(0);
+    pub static DEALLOCATIONS: AtomicUsize = AtomicUsize::new(0);
+    pub static ALLOCATED_BYTES: AtomicUsize = AtomicUsize::new(0);
+    pub static LIVE_BYTES: AtomicUsize = AtomicUsize::new(0);
+    pub static PEAK_LIVE_BYTES: AtomicUsize = AtomicUsize::new(0);
+
+    struct CountingAllocator;
+
+    fn on_alloc(size: usize) {
+        ALLOCATIONS.fetch_add(1, Ordering::Relaxed);
+        ALLOCATED_BYTES.fetch_add(size, Ordering::Relaxed);
+
+        let live = LIVE_BYTES.fetch_add(size, Ordering::Relaxed) + size;
+        PEAK_LIVE_BYTES.fetch_max(live, Ordering::Relaxed);
+    }
+
+    fn on_dealloc(size: usize) {
+        DEALLOCATIONS.fetch_add(1, Ordering::Relaxed);
+        LIVE_BYTES.fetch_sub(size, Ordering::Relaxed);
+    }
+
+    unsafe impl GlobalAlloc for CountingAllocator {
+        unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+            let ptr = System.alloc(layout);
+            if !ptr.is_null() {
+                on_alloc(layout.size());
+            }
+            ptr
+        }
+
+        unsafe fn alloc_zeroed(&self, layout: Layout) -> *mut u8 {
+            let ptr = System.alloc_zeroed(layout);
+            if !ptr.is_null() {
+                on_alloc(layout.size());
+            }
+            ptr
+        }
+
+        unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+            System.dealloc(ptr, layout);
+            on_dealloc(layout.size());
+        }
+
+        unsafe fn realloc(&self, ptr: *mut u8, layout: Layout, new_size: usize) -> *mut u8 {
+            let new_ptr = System.realloc(ptr, layout, new_size);
+            if !new_ptr.is_null() {
+                on_dealloc(layout.size());
+                on_alloc(new_size);
+            }
+            new_ptr
+        }
+    }
+
+    #[global_allocator]
+    static ALLOCATOR: CountingAllocator = CountingAllocator;
+}
+
+/// Measures the allocations made between consecutive calls to [AllocAudit::end_frame]
+#[derive(Debug, Default)]
+pub struct AllocAudit {
+    frame_start: AllocStats,
+    last_frame: AllocStats,
+    /// Most allocations made in a single frame, since the audit was created
+    worst_frame_allocations: usize,
+}
+
+impl AllocAudit {
+    pub const ENABLED: bool = cfg!(feature = "alloc-audit");
+
+    pub fn new() -> Self {
+        Self {
+            frame_start: Self::totals(),
+            ..Default::default()
+        }
+    }
+
+    /// Discards all measurements so far, e.g. to exclude allocations made whilst loading
+    pub fn reset(&mut self) {
+        *self = Self::new();
+    }
+
+    #[cfg(feature = "alloc-audit")]
+    fn totals() -> AllocStats {
+        use std::sync::atomic::Ordering;
+
+        AllocStats {
+            allocations: counting::ALLOCATIONS.load(Ordering::Relaxed),
+            deallocations: counting::DEALLOCATIONS.load(Ordering::Relaxed),
+            allocated_bytes: counting::ALLOCATED_BYTES.load(Ordering::Relaxed),
+            // Reset the high-water mark, so that it is tracked per frame
+            peak_live_bytes: counting::PEAK_LIVE_BYTES.swap(
+                counting::LIVE_BYTES.load(Ordering::Relaxed),
+                Ordering::Relaxed,
+            ),
+        }
+    }
+
+    #[cfg(not(feature = "alloc-audit"))]
+    fn totals() -> AllocStats {
+        AllocStats::default()
+    }
+
+    /// Finishes measuring the current frame, and starts measuring the next
+    pub fn end_frame(&mut self) {
+        let totals = Self::totals();
+
+        self.last_frame = AllocStats {
+            allocations: totals.allocations - self.frame_start.allocations,
+            deallocations: totals.deallocations - self.frame_start.deallocations,
+            allocated_bytes: totals.allocated_bytes - self.frame_start.allocated_bytes,
+            peak_live_bytes: totals.peak_live_bytes,
+        };
+        self.worst_frame_allocations = self
+            .worst_frame_allocations
+            .max(self.last_frame.allocations);
+
+        self.frame_start = totals;
+    }
+
+    pub fn last_frame(&self) -> &AllocStats {
+        &self.last_frame
+    }
+
+    pub fn worst_frame_allocations(&self) -> usize {
+        self.worst_frame_allocations
+    }
+}
@@ -1,498 +1,3161 @@
-use std::time::{Duration, Instant};
+use std::{
+    cell::RefCell,
+    error::Error,
+    fmt, io,
+    rc::Rc,
+    sync::Arc,
+    time::{Duration, Instant},
+};
 
-use input::Input;
+use input::{Action, Input};
+use jobs::JobSystem;
 use maths::{
-    geometry::Segment,
+    geometry::Shape,
     linear::{Mat2f, Vec2f},
 };
+use physics::{solver::Solver, RigidBody};
 use window::{
     application::WindowApplication,
-    event::{Event, KeyCode, MouseButton, RenderEvent, WindowEvent},
-    Window, WindowAttributes, WindowPosition, WindowSize,
+    event::{Event, MouseButton, RenderEvent, WindowEvent},
+    CrtFilter, GraphicsBackend, PresentMode, ScaleFilter, Window, WindowAttributes, WindowId,
+    WindowPosition, WindowSize, WindowTarget,
 };
 #[cfg(target_os = "windows")]
 use windows_sys::Win32::Media::{timeBeginPeriod, timeEndPeriod};
 
-use crate::{
-    colour::BGRA8,
-    consts::*,
-    font::{AlignHeight, AlignWidth, Font},
-    player::Player,
-    renderer::Renderer,
-    surface::{Plane, PlaneTexture, Portal, Sector, Sprite, Wall, WallTexture},
-    textures::Texture,
-    timer::Timer,
-};
+use crate::{
+    alloc_audit::AllocAudit,
+    assets::{AssetManager, Handle, LoadError, TextureConfig},
+    camera::{Camera, CameraKeyframe, CameraPath},
+    chase,
+    clock::GameClock,
+    colour::{BlendMode, BGRA8},
+    console::Console,
+    consts::*,
+    crosshair::{Crosshair, CrosshairConfig, CrosshairStyle},
+    damage::{Damage, DamageKind},
+    debris::Debris,
+    demo,
+    destructible::{Breakage, DestructibleWall},
+    door::Door,
+    enemy::{Attack, AttackKind, Enemy},
+    events::{EventQueue, GameEvent},
+    export,
+    font::{AlignHeight, AlignWidth},
+    hud::{self, Hud, HudConfig, WeaponView},
+    interact::{self, Interaction, Target},
+    item::{Item, ItemKind, KeyColour},
+    lift::Lift,
+    lightmap::{self, StaticLight},
+    logger::{self, Toasts},
+    map::{self, MapError, MapIndex, WallId, WallSplit},
+    merge::{self, SectorRemap},
+    motion::SectorMotion,
+    nav::NavMesh,
+    pause::{PauseItem, PauseMenu},
+    perception,
+    pick::{self, Pick, PickTarget, WallPart},
+    player::{MovementConfig, Player, PlayerStats},
+    profile,
+    projectile::Projectile,
+    prop::Prop,
+    renderer::{Framebuffer, Rect, RenderPass, Renderer, WorldTextSize},
+    resolution::AdaptiveResolution,
+    rewind::Rewind,
+    script::{Script, ScriptCommand, ScriptError},
+    settings::{Settings, SettingsError},
+    spectator::Spectator,
+    surface::{Fog, Portal, Sector, Sprite, WallTexture},
+    switch::Switch,
+    thing::{PlayerStart, Spawned, Thing, ThingRegistry},
+    timer::Timer,
+    trigger::{Occupant, TriggerVolume},
+    weapon::{FireMode, Weapon, WeaponKind},
+};
+
+/// Every sprite in the world, in the order that [PickTarget::Sprite] indexes them. The enemies,
+/// debris and projectiles can be given separately, as whilst rewinding they are replaced by those
+/// of the frame looked at. A macro rather than a method, so that the renderer can be borrowed
+/// mutably whilst the sprites are borrowed.
+macro_rules! world_sprites {
+    ($app:expr) => {
+        world_sprites!($app, $app.enemies, $app.debris, $app.projectiles)
+    };
+    ($app:expr, $enemies:expr, $debris:expr, $projectiles:expr) => {
+        $app.sprites
+            .iter()
+            .chain($app.items.iter().map(|item| &item.sprite))
+            .chain($enemies.iter().map(|enemy| &enemy.sprite))
+            .chain($debris.iter().map(|debris| &debris.sprite))
+            .chain($projectiles.iter().map(|projectile| &projectile.sprite))
+            .chain($app.props.iter().map(|prop| &prop.sprite))
+            .chain($app.hanging.iter())
+    };
+}
+
+/// A problem that stops the game from starting, which is shown on screen in place of the game
+#[derive(Debug)]
+enum SetupError {
+    Assets(Vec<LoadError>),
+    Map(MapError),
+}
+
+impl fmt::Display for SetupError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SetupError::Assets(errors) => {
+                for error in errors.iter() {
+                    writeln!(f, "{error}")?;
+                }
+                Ok(())
+            }
+            SetupError::Map(error) => write!(f, "Invalid map: {error}"),
+        }
+    }
+}
+
+impl Error for SetupError {}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum ViewMode {
+    Player,
+    Spectator,
+}
+
+pub struct App {
+    window: Window,
+    input: Input,
+    timer: Timer,
+    /// Time as the world sees it, which stops whilst paused
+    clock: GameClock,
+    pause_menu: PauseMenu,
+    alloc_audit: AllocAudit,
+    renderer: Renderer,
+    events: EventQueue,
+    /// Events taken from the queue to be handled, kept to reuse its allocation
+    handling_events: Vec<GameEvent>,
+
+    hud: Hud,
+    /// Shared with the render pass that draws it
+    crosshair: Rc<RefCell<Crosshair>>,
+    /// Warnings and errors logged recently, shown whilst debugging
+    toasts: Toasts,
+    console: Console<App>,
+
+    view_mode: ViewMode,
+    /// Views the player from behind and above rather than through their eyes
+    chase_camera: bool,
+    player: Player,
+    /// How the player moves, which outlasts the player themselves across respawns
+    movement: MovementConfig,
+    /// How textures are streamed and stored, which is only read as they are loaded
+    texture_config: TextureConfig,
+    spectator: Spectator,
+    /// Recent frames, which can be paused on and stepped back through whilst debugging
+    rewind: Rewind,
+    player_start: PlayerStart,
+    respawn_points: Vec<PlayerStart>,
+    /// Index of the respawn point that the player reached most recently. Until they reach one,
+    /// they respawn at the player start.
+    respawn_point: Option<usize>,
+    /// Seconds until the player respawns, whilst they are dead
+    respawn_timer: Option<f32>,
+
+    // Secondary view rendered to an off-screen framebuffer, and drawn in the corner of the screen
+    /// Size of each framebuffer pixel on screen, in logical pixels, chosen with the window scale keys
+    window_scale: usize,
+    /// Monitor scale factor that the window size was last worked out for
+    window_scale_factor: f64,
+    show_monitor: bool,
+    /// Separate window showing the security camera, opened or closed once the event loop next allows
+    camera_window: Option<Window>,
+    camera_window_wanted: bool,
+    /// Whether presentation should be synchronised to the display, rather than limited by the timer
+    vsync: bool,
+    /// Lowers the render scale whilst the world takes too long to draw, when turned on
+    adaptive_resolution: Option<AdaptiveResolution>,
+    monitor_camera: Camera,
+    monitor_sector_index: usize,
+    monitor_framebuffer: Framebuffer,
+    /// Shown in place of the game once something has gone wrong that it can't recover from
+    error: Option<String>,
+    assets: AssetManager,
+    sectors: Vec<Sector>,
+    /// Where each of the map's sectors ended up once they were merged at load, which the level
+    /// script still numbers them by
+    sector_remap: SectorRemap,
+    sector_motions: Vec<SectorMotion>,
+    map_index: MapIndex,
+    /// Reused between updates when gathering walls from the map index, to avoid allocating
+    nearby_walls: Vec<WallId>,
+    nav_mesh: NavMesh,
+    doors: Vec<Door>,
+    lifts: Vec<Lift>,
+    /// Height of each sector's floor as of the last update, to find how far floors have moved
+    floor_heights: Vec<f32>,
+    switches: Vec<Switch>,
+    destructibles: Vec<DestructibleWall>,
+    debris: Vec<Debris>,
+    /// Bounces debris off props and each other
+    solver: Solver,
+    projectiles: Vec<Projectile>,
+    sprites: Vec<Sprite>,
+    /// Decorations that hang from the ceiling, such as light cones
+    hanging: Vec<Sprite>,
+    items: Vec<Item>,
+    props: Vec<Prop>,
+    triggers: Vec<TriggerVolume>,
+    /// Lights baked into the lightmaps of the sectors
+    lights: Vec<StaticLight>,
+    /// Ambient level that the lightmaps were last baked over, if they are baked, so that they can
+    /// be baked again when the walls change
+    lightmap_ambient: Option<f32>,
+    enemies: Vec<Enemy>,
+    /// Logic specific to the level, such as ambushes and puzzles
+    script: Script,
+    thing_registry: ThingRegistry,
+}
+
+impl App {
+    pub fn new() -> Self {
+        let window = Window::new(WindowAttributes {
+            title: format!("Pseudo3D  {}x{}  ({}x)", INTERNAL_WIDTH, INTERNAL_HEIGHT, 2),
+            size: WindowSize::new(INTERNAL_WIDTH * 2, INTERNAL_HEIGHT * 2),
+            surface_size: Some(WindowSize::new(INTERNAL_WIDTH, INTERNAL_HEIGHT)),
+            position: WindowPosition::new(200, 200),
+            // The framebuffer is letterboxed to fit any size, so the scale keys are just presets
+            resizable: true,
+            min_size: Some(WindowSize::new(INTERNAL_WIDTH, INTERNAL_HEIGHT)),
+            backend: GraphicsBackend::OpenGl,
+            ..Default::default()
+        });
+        let input = Input::new();
+        let timer = Timer::new();
+        let jobs = Arc::new(JobSystem::with_available_parallelism());
+        let renderer = Renderer::new(INTERNAL_WIDTH, INTERNAL_HEIGHT, HFOV, jobs.clone());
+
+        let assets = AssetManager::new(jobs);
+        // Replaced once the map's player start has been spawned
+        let player = Player::new(&PlayerStart::default(), 0.0);
+        let spectator = Spectator::new(player.camera.clone(), player.sector_index);
+
+        App {
+            window,
+            input,
+            timer,
+            clock: GameClock::new(),
+            pause_menu: PauseMenu::new(),
+            alloc_audit: AllocAudit::new(),
+            renderer,
+            events: EventQueue::new(),
+            handling_events: Vec::new(),
+
+            hud: Hud::new(HudConfig::default()),
+            crosshair: Rc::new(RefCell::new(Crosshair::new(CrosshairConfig::default()))),
+            toasts: Toasts::new(),
+            console: Console::new(),
+
+            view_mode: ViewMode::Player,
+            chase_camera: false,
+            player,
+            movement: MovementConfig::DEFAULT,
+            texture_config: TextureConfig::DEFAULT,
+            spectator,
+            rewind: Rewind::new(),
+            player_start: PlayerStart::default(),
+            respawn_points: Vec::new(),
+            respawn_point: None,
+            respawn_timer: None,
+
+            window_scale: 2,
+            window_scale_factor: 0.0,
+            show_monitor: false,
+            camera_window: None,
+            camera_window_wanted: false,
+            vsync: false,
+            adaptive_resolution: None,
+            monitor_camera: Camera::new(Vec2f::ZERO, 0.0),
+            monitor_sector_index: 0,
+            monitor_framebuffer: Framebuffer::new(INTERNAL_WIDTH / 4, INTERNAL_HEIGHT / 4),
+            error: None,
+            assets,
+            sectors: Vec::new(),
+            sector_remap: SectorRemap::default(),
+            sector_motions: Vec::new(),
+            map_index: MapIndex::default(),
+            nearby_walls: Vec::new(),
+            nav_mesh: NavMesh::default(),
+            doors: Vec::new(),
+            lifts: Vec::new(),
+            floor_heights: Vec::new(),
+            switches: Vec::new(),
+            destructibles: Vec::new(),
+            debris: Vec::new(),
+            solver: Solver::new(SOLVER_ITERATIONS),
+            projectiles: Vec::new(),
+            sprites: Vec::new(),
+            hanging: Vec::new(),
+            items: Vec::new(),
+            props: Vec::new(),
+            triggers: Vec::new(),
+            lights: Vec::new(),
+            lightmap_ambient: None,
+            enemies: Vec::new(),
+            script: Script::new(),
+            thing_registry: ThingRegistry::with_defaults(),
+        }
+    }
+
+    /// Sizes the window so that each framebuffer pixel covers `scale` logical pixels. On high-DPI
+    /// monitors this is rounded to a whole number of physical pixels, so pixels stay square and even.
+    fn set_window_scale(&mut self, scale: usize) {
+        let scale_factor = self.window.get_scale_factor();
+        let physical_scale = ((scale as f64 * scale_factor).round() as usize).max(1);
+
+        self.window_scale = scale;
+        self.window_scale_factor = scale_factor;
+        self.window.set_size(WindowSize::new(
+            INTERNAL_WIDTH * physical_scale,
+            INTERNAL_HEIGHT * physical_scale,
+        ));
+        self.window.set_title(&format!(
+            "Pseudo3D  {}x{}  ({}x, {}x physical)",
+            INTERNAL_WIDTH, INTERNAL_HEIGHT, scale, physical_scale
+        ));
+    }
+
+    fn setup(&mut self) -> Result<(), SetupError> {
+        // This is used to reduce the minimum sleep time on Windows from ~15ms to ~1ms
+        #[cfg(target_os = "windows")]
+        unsafe {
+            timeBeginPeriod(1)
+        };
+
+        self.set_window_scale(self.window_scale);
+
+        // The defaults are perfectly playable, so failing to load the settings isn't fatal. They
+        // are loaded first, as they decide how assets are loaded.
+        if let Err(error) = self.load_settings() {
+            log::warn!("{error}");
+        }
+
+        let load_start = Instant::now();
+        self.assets.paletted_textures = self.texture_config.paletted;
+
+        // Load default assets, in order, so that their handles match the indices in consts. When
+        // streaming, the textures used in the map are only loaded once they are seen, other than
+        // the placeholder that is shown until then. The HUD's are always needed, so aren't.
+        for (index, path) in TEXTURE_TILE_PATHS
+            .iter()
+            .chain(TEXTURE_SPRITE_PATHS.iter())
+            .enumerate()
+        {
+            if self.texture_config.streaming && index != PLACEHOLDER {
+                self.assets.stream_texture(*path);
+            } else {
+                self.assets.load_texture(*path);
+            }
+        }
+        for path in TEXTURE_HUD_PATHS.iter() {
+            self.assets.load_texture(*path);
+        }
+        self.assets
+            .textures
+            .set_placeholder(Handle::from_index(PLACEHOLDER));
+        self.assets.textures.set_budget(self.texture_config.budget);
+        for (path, size) in FONT_PATHS.iter().zip(FONT_SIZES.iter()) {
+            self.assets.load_font(*path, size.0, size.1, 1);
+        }
+        let errors = self.assets.wait(|finished, total| {
+            log::debug!("Loading assets ({finished}/{total})");
+        });
+        if !errors.is_empty() {
+            return Err(SetupError::Assets(errors));
+        }
+        let (streamed, _) = self.assets.textures.streamed_count();
+        let asset_count = self.assets.textures.len() + self.assets.fonts.len() - streamed;
+        log::info!(
+            "Loaded {asset_count} assets in {:.1}ms, leaving {streamed} textures to stream",
+            load_start.elapsed().as_secs_f64() * 1000.0
+        );
+
+        // Enable debug drawing by default
+        self.renderer.state_mut().debug = true;
+        let crosshair = Rc::clone(&self.crosshair);
+        self.renderer.insert_pass(
+            RenderPass::custom("crosshair", move |state| crosshair.borrow().draw(state)),
+            None,
+        );
+
+        // Security camera in the corner of sector 3, looking towards sector 2
+        self.monitor_camera = Camera::new(Vec2f::new(145.0, 35.0), 20.0);
+        self.monitor_camera.set_rotation(-0.6, -0.15);
+
+        self.sectors = demo::sectors();
+        let sector_count = self.sectors.len();
+        self.sector_remap = merge::merge_sectors(&mut self.sectors, &demo::PINNED_SECTORS);
+        if self.sectors.len() < sector_count {
+            log::info!(
+                "Merged {} of {sector_count} sectors into their neighbours",
+                sector_count - self.sectors.len()
+            );
+        }
+        // Sectors are referred to below by their index in the map as it was built
+        let sector = |index: usize| self.sector_remap.get(index).unwrap();
+
+        self.monitor_sector_index = sector(3);
+
+        // Sector 5 is a door between sector 0 and sector 6, which requires the red key
+        self.doors = vec![Door::new(
+            &mut self.sectors,
+            sector(5),
+            Some(KeyColour::Red),
+        )];
+
+        // A button on the far wall of sector 6 opens the door from the inside
+        self.doors[0].trigger = Some(DOOR_BUTTON_TRIGGER);
+        self.switches = vec![Switch::new(
+            &self.sectors,
+            WallId {
+                sector_index: sector(6),
+                wall_index: 0,
+            },
+            WallTexture::new(OBSIDIAN, Vec2f::ZERO, Vec2f::uniform(5.0)),
+            Some(1.0),
+            DOOR_BUTTON_TRIGGER,
+        )];
+
+        // Trigger volumes for the level script: one in front of the cracked stretch of the
+        // corridor's wall, and one around the ammo in the lava room
+        self.triggers = vec![
+            TriggerVolume::polygon(
+                0,
+                vec![
+                    Vec2f::new(110.0, 270.0),
+                    Vec2f::new(130.0, 270.0),
+                    Vec2f::new(130.0, 310.0),
+                    Vec2f::new(110.0, 310.0),
+                ],
+            ),
+            TriggerVolume::circle(1, Vec2f::new(105.0, 640.0), 15.0),
+        ];
+
+        // The floor of sector 3 rises once the player steps into it, see the level script
+        self.lifts = vec![Lift::new(&self.sectors, sector(3), 8.0)];
+
+        // A cracked stretch of the corridor's east wall, which crumbles away to rock when shot
+        let cracked_wall = WallId {
+            sector_index: sector(0),
+            wall_index: 1,
+        };
+        let crack = map::split_wall(&mut self.sectors, cracked_wall, Vec2f::new(130.0, 300.0))
+            .map(|split| split.rest);
+        if let Some(crack) = crack {
+            map::split_wall(&mut self.sectors, crack, Vec2f::new(130.0, 280.0));
+            self.destructibles.push(DestructibleWall::new(
+                crack,
+                30,
+                Breakage::Texture(WallTexture::new(ROCK, Vec2f::ZERO, Vec2f::uniform(5.0))),
+            ));
+        }
+
+        // A weak wall of the lava room, hiding sector 7
+        let concrete_wall = WallTexture::new(CONCRETE, Vec2f::ZERO, Vec2f::uniform(5.0));
+        self.destructibles.push(DestructibleWall::new(
+            WallId {
+                sector_index: sector(6),
+                wall_index: 1,
+            },
+            50,
+            Breakage::Portal(Portal::new(sector(7), concrete_wall, concrete_wall)),
+        ));
+
+        map::validate(&self.sectors, self.assets.textures.len()).map_err(SetupError::Map)?;
+
+        // The renderer relies on sectors being convex
+        for sector in self.sectors.iter() {
+            if !sector.polygon().is_convex() {
+                log::warn!(
+                    "Sector {} is not convex, so may not render correctly",
+                    sector.id
+                );
+            }
+        }
+
+        self.map_index = MapIndex::build(&self.sectors);
+        self.nav_mesh = NavMesh::build(&self.sectors);
+
+        let mut things = demo::things();
+        for thing in things.iter_mut() {
+            // Those placed in sectors that don't exist are left to fail validation
+            if let Some(sector_index) = self.sector_remap.get(thing.sector_index) {
+                thing.sector_index = sector_index;
+            }
+        }
+        map::validate_things(&things, &self.sectors).map_err(SetupError::Map)?;
+        for (thing_index, thing) in things.iter().enumerate() {
+            let spawned = self.thing_registry.spawn(thing).ok_or(SetupError::Map(
+                MapError::UnknownThing {
+                    thing: thing_index,
+                    kind: thing.kind.0,
+                },
+            ))?;
+            self.add_spawned(spawned);
+        }
+
+        // Only maps with lights placed in them are worth baking
+        if !self.lights.is_empty() {
+            self.bake_lightmaps(LIGHTMAP_AMBIENT);
+        }
+
+        self.register_commands();
+
+        // The level can still be played without its script, so failing to load it isn't fatal
+        if let Err(error) = self.load_script() {
+            log::error!("{error}");
+        }
+
+        // Only measure allocations made once the game is running
+        self.alloc_audit.reset();
+
+        Ok(())
+    }
+
+    fn register_commands(&mut self) {
+        self.console
+            .register("help", "", "Lists all commands", |app, _| {
+                Ok(app
+                    .console
+                    .commands()
+                    .iter()
+                    .map(|command| format!("{} {} - {}", command.name, command.usage, command.help))
+                    .collect::<Vec<_>>()
+                    .join("\n"))
+            });
+
+        self.console
+            .register("clear", "", "Clears the console", |app, _| {
+                app.console.clear();
+                Ok(String::new())
+            });
+
+        self.console.register(
+            "texture_budget",
+            "[<MiB>]",
+            "Shows or sets how much memory streamed textures may take",
+            |app, args| {
+                if let Some(budget) = args.first() {
+                    let budget = parse_arg::<usize>(budget)?;
+                    app.texture_config.budget = budget.saturating_mul(1024 * 1024);
+                    app.assets.textures.set_budget(app.texture_config.budget);
+                }
+
+                Ok(app.texture_streaming_text())
+            },
+        );
+
+        self.console.register(
+            "rewind",
+            "[<frames>]",
+            "Toggles stepping back through recent frames, or looks the given number back",
+            |app, args| {
+                let Some(frames) = args.first() else {
+                    return Ok(format!(
+                        "Rewind {}",
+                        if app.toggle_rewind() { "on" } else { "off" }
+                    ));
+                };
+
+                let frames = parse_arg::<usize>(frames)?;
+                if !app.rewind.is_paused() && !app.rewind.pause() {
+                    return Err(String::from("No frames have been recorded yet"));
+                }
+                app.rewind.seek(frames, &mut app.player, &mut app.sectors);
+
+                let (frames, recorded) = app.rewind.position().unwrap_or_default();
+                Ok(format!("Looking {frames} of {recorded} frames back"))
+            },
+        );
+
+        self.console
+            .register("pause", "", "Toggles freezing the world", |app, _| {
+                Ok(format!(
+                    "Paused {}",
+                    if app.toggle_pause() { "on" } else { "off" }
+                ))
+            });
+
+        self.console.register(
+            "time_scale",
+            "<scale>",
+            "Sets the rate at which time passes in the world, where below 1 is slow motion",
+            |app, args| {
+                let scale: f32 = parse_arg(args.first().ok_or("Expected a time scale")?)?;
+                let scale = app
+                    .clock
+                    .set_scale(scale)
+                    .ok_or("Expected a finite time scale")?;
+
+                Ok(format!("Time scale set to {scale}"))
+            },
+        );
+
+        self.console.register(
+            "chase_camera",
+            "",
+            "Toggles viewing the player from behind and above",
+            |app, _| {
+                app.chase_camera = !app.chase_camera;
+                Ok(format!(
+                    "Chase camera {}",
+                    if app.chase_camera { "on" } else { "off" }
+                ))
+            },
+        );
+
+        self.console
+            .register("noclip", "", "Toggles passing through walls", |app, _| {
+                app.player.noclip = !app.player.noclip;
+                Ok(format!(
+                    "Noclip {}",
+                    if app.player.noclip { "on" } else { "off" }
+                ))
+            });
+
+        self.console.register(
+            "kill",
+            "",
+            "Kills the player, to test respawning",
+            |app, _| {
+                app.player.stats.health = 0;
+                Ok(String::new())
+            },
+        );
+
+        self.console.register(
+            "hurt",
+            "<amount> [<kind> [<degrees>]]",
+            "Damages the player, as if from a source in the given direction relative to the view",
+            |app, args| {
+                let (amount, kind, angle) = match args {
+                    [amount] => (amount, DamageKind::Ballistic, None),
+                    [amount, kind] => (amount, parse_arg(kind)?, None),
+                    [amount, kind, angle] => {
+                        (amount, parse_arg(kind)?, Some(parse_arg::<f32>(angle)?))
+                    }
+                    _ => return Err(String::from("Expected an amount of damage")),
+                };
+
+                let mut damage = Damage::new(parse_arg(amount)?, kind);
+                if let Some(angle) = angle {
+                    let (sin, cos) = (app.player.camera.yaw + angle.to_radians()).sin_cos();
+                    damage = damage.with_source(app.player.camera.position + Vec2f::new(sin, cos));
+                }
+
+                let health = app.player.stats.health;
+                app.damage_player(damage);
+                Ok(format!("Health {health} -> {}", app.player.stats.health))
+            },
+        );
+
+        self.console.register(
+            "camera_window",
+            "",
+            "Toggles a separate window showing the security camera",
+            |app, _| {
+                app.camera_window_wanted = !app.camera_window_wanted;
+                Ok(format!(
+                    "Camera window {}",
+                    if app.camera_window_wanted {
+                        "open"
+                    } else {
+                        "closed"
+                    }
+                ))
+            },
+        );
+
+        self.console.register(
+            "double_buffer",
+            "",
+            "Toggles scaling each frame on another thread whilst the next is drawn (software only)",
+            |app, _| {
+                let double_buffered = !app.window.get_double_buffered();
+                app.window.set_double_buffered(double_buffered);
+                Ok(format!(
+                    "Double buffering {}",
+                    if double_buffered { "on" } else { "off" }
+                ))
+            },
+        );
+
+        self.console.register(
+            "render_pass",
+            "[<name> <on|off>]",
+            "Lists the render passes in the order they run, or enables or disables one",
+            |app, args| match args {
+                [] => Ok(app
+                    .renderer
+                    .passes()
+                    .map(|(name, enabled)| format!("{name} {}", if enabled { "on" } else { "off" }))
+                    .collect::<Vec<_>>()
+                    .join("\n")),
+                [name, state] => {
+                    let enabled = match *state {
+                        "on" => true,
+                        "off" => false,
+                        _ => return Err(format!("Expected on or off, not '{state}'")),
+                    };
+                    if !app.renderer.set_pass_enabled(name, enabled) {
+                        return Err(format!("No render pass named '{name}'"));
+                    }
+                    Ok(format!("Render pass {name} {state}"))
+                }
+                _ => Err(String::from("Expected a pass name and on or off")),
+            },
+        );
+
+        self.console.register(
+            "crosshair",
+            "<cross|diagonal|dot|circle> [<size> [<gap>]]",
+            "Sets the style of the crosshair, and optionally its size and the gap at its centre",
+            |app, args| {
+                let (style, size, gap) = match args {
+                    [style] => (style, None, None),
+                    [style, size] => (style, Some(parse_arg(size)?), None),
+                    [style, size, gap] => (style, Some(parse_arg(size)?), Some(parse_arg(gap)?)),
+                    _ => return Err(String::from("Expected a style, size and gap")),
+                };
+                let style: CrosshairStyle = parse_arg(style)?;
+
+                let mut crosshair = app.crosshair.borrow_mut();
+                crosshair.config.style = style;
+                crosshair.config.size = size.unwrap_or(crosshair.config.size);
+                crosshair.config.gap = gap.unwrap_or(crosshair.config.gap);
+                crosshair.config.clamp_to(
+                    app.renderer.framebuffer().width(),
+                    app.renderer.framebuffer().height(),
+                );
+                Ok(format!("Crosshair set to {:?}", crosshair.config))
+            },
+        );
+
+        self.console.register(
+            "crosshair_colour",
+            "<r> <g> <b> [<a>]",
+            "Sets the colour of the crosshair, with components from 0 to 255",
+            |app, args| {
+                let (r, g, b, a) = match args {
+                    [r, g, b] => (r, g, b, "255"),
+                    [r, g, b, a] => (r, g, b, *a),
+                    _ => return Err(String::from("Expected red, green, blue and alpha")),
+                };
+                let colour = BGRA8::new(parse_arg(r)?, parse_arg(g)?, parse_arg(b)?, parse_arg(a)?);
+
+                app.crosshair.borrow_mut().config.colour = colour;
+                Ok(format!("Crosshair colour set to {colour:?}"))
+            },
+        );
+
+        self.console.register(
+            "pick",
+            "",
+            "Shows what is under the cursor, or the crosshair whilst the cursor is grabbed",
+            |app, _| {
+                let pick = app.pick().ok_or(String::from("Nothing there"))?;
+                Ok(format!(
+                    "{} at {:.2} {:.2} {:.2}, {:.2} away",
+                    pick.target, pick.position.x, pick.position.y, pick.z, pick.depth
+                ))
+            },
+        );
+
+        self.console.register(
+            "set_texture",
+            "<index>",
+            "Sets the texture of the wall, floor or ceiling that would be picked",
+            |app, args| {
+                let index: usize = parse_arg(args.first().ok_or("Expected a texture index")?)?;
+                if index >= app.assets.textures.len() {
+                    return Err(format!("No texture {index}"));
+                }
+
+                let pick = app.pick().ok_or(String::from("Nothing there"))?;
+                match pick.target {
+                    PickTarget::Wall { wall, part } => {
+                        let wall = &mut app.sectors[wall.sector_index].walls[wall.wall_index];
+                        let texture = match (part, wall.portal.as_mut()) {
+                            (WallPart::Upper, Some(portal)) => &mut portal.upper_texture,
+                            (WallPart::Lower, Some(portal)) => &mut portal.lower_texture,
+                            _ => &mut wall.texture_data,
+                        };
+                        texture.index = index;
+                    }
+                    PickTarget::Floor(sector) => {
+                        app.sectors[sector].floor.texture_data.index = index
+                    }
+                    PickTarget::Ceiling(sector) => {
+                        app.sectors[sector].ceiling.texture_data.index = index
+                    }
+                    PickTarget::Sprite(_) => return Err(String::from("Can't retexture sprites")),
+                }
+
+                Ok(format!("Set texture of {} to {index}", pick.target))
+            },
+        );
+
+        self.console.register(
+            "log_level",
+            "[<level>]",
+            "Shows or sets the most detailed level of messages logged, from off to trace",
+            |_, args| {
+                if let Some(level) = args.first() {
+                    log::set_max_level(parse_arg(level)?);
+                }
+                Ok(format!("Log level {}", log::max_level()))
+            },
+        );
+
+        self.console.register(
+            "linear_lighting",
+            "",
+            "Toggles applying lighting in linear light, for comparison",
+            |app, _| {
+                let state = app.renderer.state_mut();
+                state.linear_lighting = !state.linear_lighting;
+                Ok(format!(
+                    "Linear lighting {}",
+                    if state.linear_lighting { "on" } else { "off" }
+                ))
+            },
+        );
+
+        self.console.register(
+            "hardened_rendering",
+            "",
+            "Toggles skipping degenerate walls, spans and sprites instead of asserting",
+            |app, _| {
+                let state = app.renderer.state_mut();
+                state.hardened = !state.hardened;
+                Ok(format!(
+                    "Hardened rendering {}",
+                    if state.hardened { "on" } else { "off" }
+                ))
+            },
+        );
+
+        self.console.register(
+            "fog",
+            "[<density> [<red> <green> <blue>]]",
+            "Shows or sets the fog of sectors that don't have their own, where 0 density is clear",
+            |app, args| {
+                let state = app.renderer.state_mut();
+                match args {
+                    [] => {}
+                    [density] => state.fog.density = parse_arg(density)?,
+                    [density, red, green, blue] => {
+                        state.fog = Fog::new(
+                            BGRA8::new(parse_arg(red)?, parse_arg(green)?, parse_arg(blue)?, 0xFF),
+                            parse_arg(density)?,
+                        )
+                    }
+                    _ => return Err(String::from("Expected a density and optionally a colour")),
+                }
+
+                let Fog { colour, density } = state.fog;
+                Ok(format!(
+                    "Fog density {density}, colour {} {} {}",
+                    colour.r, colour.g, colour.b
+                ))
+            },
+        );
+
+        self.console.register(
+            "movement",
+            "[<preset>]",
+            "Shows how the player moves, or switches to a preset: default, classic or modern",
+            |app, args| {
+                match args {
+                    [] => {}
+                    [name] => {
+                        let preset = MovementConfig::preset(name)
+                            .ok_or_else(|| format!("Unknown movement preset '{name}'"))?;
+                        app.set_movement(preset);
+                    }
+                    _ => return Err(String::from("Expected at most one preset")),
+                }
+
+                let MovementConfig {
+                    friction,
+                    impulse,
+                    max_speed,
+                    mouse_sensitivity,
+                    classic_diagonals,
+                } = app.movement;
+                Ok(format!(
+                    "Friction {friction}, impulse {impulse}, max speed {max_speed}, \
+                     mouse sensitivity {} {}, classic diagonals {}",
+                    mouse_sensitivity.x,
+                    mouse_sensitivity.y,
+                    if classic_diagonals { "on" } else { "off" }
+                ))
+            },
+        );
+
+        self.console.register(
+            "classic_diagonals",
+            "",
+            "Toggles moving faster whilst moving forwards and strafing at once",
+            |app, _| {
+                app.set_movement(MovementConfig {
+                    classic_diagonals: !app.movement.classic_diagonals,
+                    ..app.movement
+                });
+                Ok(format!(
+                    "Classic diagonals {}",
+                    if app.movement.classic_diagonals {
+                        "on"
+                    } else {
+                        "off"
+                    }
+                ))
+            },
+        );
+
+        self.console.register(
+            "lightmaps",
+            "[<ambient> | off]",
+            "Bakes the map's lights into lightmaps, over the given ambient level, or removes them",
+            |app, args| match args {
+                ["off"] => {
+                    lightmap::clear(&mut app.sectors);
+                    app.lightmap_ambient = None;
+                    Ok(String::from("Removed lightmaps"))
+                }
+                [] | [_] => {
+                    let ambient = match args.first() {
+                        Some(ambient) => parse_arg(ambient)?,
+                        None => LIGHTMAP_AMBIENT,
+                    };
+                    app.bake_lightmaps(ambient);
+                    Ok(format!("Baked lightmaps from {} lights", app.lights.len()))
+                }
+                _ => Err(String::from("Expected an ambient level or off")),
+            },
+        );
+
+        self.console.register(
+            "teleport",
+            "<x> <y> [<sector>]",
+            "Moves the player to a position on the map, in the given sector where sectors overlap",
+            |app, args| {
+                let (x, y, sector) = match args {
+                    [x, y] => (x, y, None),
+                    [x, y, sector] => (x, y, Some(parse_arg::<usize>(sector)?)),
+                    _ => return Err(String::from("Expected an x and y position")),
+                };
+                let position = Vec2f::new(parse_arg(x)?, parse_arg(y)?);
+                let sector_index = match sector {
+                    Some(sector_index) => app
+                        .sectors
+                        .get(sector_index)
+                        .filter(|sector| sector.contains_point(position))
+                        .map(|_| sector_index)
+                        .ok_or("Position is outside of that sector")?,
+                    None => app
+                        .map_index
+                        .find_sector(position, &app.sectors)
+                        .ok_or("Position is outside of the map")?,
+                };
+
+                app.player.translate(position - app.player.camera.position);
+                app.player.prev_position = position;
+                app.player.velocity = Vec2f::ZERO;
+                app.enter_sector(sector_index);
+
+                Ok(format!("Teleported to sector {sector_index}"))
+            },
+        );
+
+        self.console.register(
+            "export_map",
+            "[hints]",
+            "Writes a top-down view of the map, optionally highlighting sectors that overlap",
+            |app, args| {
+                let overlap_hints = match args {
+                    [] => false,
+                    [hints] if *hints == "hints" => true,
+                    _ => return Err(String::from("Expected 'hints' or nothing")),
+                };
+                app.export_map(overlap_hints)
+                    .map_err(|error| format!("Failed to export map: {error}"))?;
+
+                Ok(format!("Exported map to '{MAP_EXPORT_PATH}'"))
+            },
+        );
+
+        self.console.register(
+            "split_wall",
+            "<sector> <wall> [<fraction>]",
+            "Splits a wall in two, halfway along or at the given fraction of its length",
+            |app, args| {
+                let (sector_index, wall_index, fraction) = match args {
+                    [sector, wall] => (parse_arg(sector)?, parse_arg(wall)?, 0.5),
+                    [sector, wall, fraction] => {
+                        (parse_arg(sector)?, parse_arg(wall)?, parse_arg(fraction)?)
+                    }
+                    _ => return Err(String::from("Expected a sector and wall index")),
+                };
+                if app
+                    .sectors
+                    .get(sector_index)
+                    .is_none_or(|sector: &Sector| wall_index >= sector.walls.len())
+                {
+                    return Err(String::from("No such wall"));
+                }
+
+                let id = WallId {
+                    sector_index,
+                    wall_index,
+                };
+                let segment = id.get(&app.sectors).segment;
+                let point = segment.a + (segment.b - segment.a) * fraction;
+                let split = app.split_wall(id, point)?;
+
+                Ok(match split.twin_rest {
+                    Some(twin_rest) => format!(
+                        "Added wall {} to sector {sector_index}, and wall {} to sector {}",
+                        split.rest.wall_index, twin_rest.wall_index, twin_rest.sector_index
+                    ),
+                    None => format!(
+                        "Added wall {} to sector {sector_index}",
+                        split.rest.wall_index
+                    ),
+                })
+            },
+        );
+
+        self.console.register(
+            "remove_wall",
+            "<sector> <wall>",
+            "Removes a wall, moving the next one back to close the gap",
+            |app, args| {
+                let [sector, wall] = args else {
+                    return Err(String::from("Expected a sector and wall index"));
+                };
+                let id = WallId {
+                    sector_index: parse_arg(sector)?,
+                    wall_index: parse_arg(wall)?,
+                };
+                if app
+                    .sectors
+                    .get(id.sector_index)
+                    .is_none_or(|sector: &Sector| id.wall_index >= sector.walls.len())
+                {
+                    return Err(String::from("No such wall"));
+                }
+
+                app.remove_wall(id)?;
+
+                Ok(format!(
+                    "Removed wall {} of sector {}",
+                    id.wall_index, id.sector_index
+                ))
+            },
+        );
+
+        self.console.register(
+            "reload_script",
+            "",
+            "Reloads the level's script from disk and runs its on_start function again",
+            |app, _| {
+                app.load_script().map_err(|error| error.to_string())?;
+                Ok(format!("Reloaded '{SCRIPT_PATH}'"))
+            },
+        );
+
+        self.console.register(
+            "fov",
+            "<degrees>",
+            "Sets the horizontal field of view",
+            |app, args| {
+                let fov: f32 = parse_arg(args.first().ok_or("Expected a field of view")?)?;
+                let fov = fov.clamp(MIN_HFOV, MAX_HFOV);
+                app.renderer.set_fov(fov);
+
+                Ok(format!("FOV set to {fov}"))
+            },
+        );
+
+        self.console.register(
+            "render_scale",
+            "<scale>",
+            "Sets the resolution that the world is drawn at, relative to the screen",
+            |app, args| {
+                let scale: f32 = parse_arg(args.first().ok_or("Expected a scale")?)?;
+                let scale = app
+                    .set_render_scale(scale)
+                    .ok_or("Expected a finite scale")?;
+
+                Ok(format!("Render scale set to {scale}"))
+            },
+        );
+
+        self.console.register(
+            "adaptive_resolution",
+            "[<milliseconds> | off]",
+            "Lowers the render scale whilst drawing the world takes longer than the given time",
+            |app, args| match args {
+                ["off"] => {
+                    if let Some(adaptive) = app.adaptive_resolution.take() {
+                        app.renderer.set_render_scale(adaptive.max_scale());
+                    }
+                    Ok(String::from("Adaptive resolution off"))
+                }
+                [] | [_] => {
+                    let budget_ms = match args.first() {
+                        Some(budget_ms) => parse_arg::<f32>(budget_ms)?.max(0.1),
+                        None => ADAPTIVE_BUDGET_MS,
+                    };
+                    app.set_adaptive_resolution(budget_ms);
+                    Ok(format!("Adaptive resolution on, within {budget_ms} ms"))
+                }
+                _ => Err(String::from("Expected a time in milliseconds or off")),
+            },
+        );
+
+        self.console.register(
+            "bench",
+            "[<frames>]",
+            "Renders the current view repeatedly, and shows how long it took",
+            |app, args| {
+                let frames = match args.first() {
+                    Some(frames) => parse_arg::<usize>(frames)?.max(1),
+                    None => BENCH_FRAMES,
+                };
+                let camera = app.renderer.state().camera.clone();
+                let sector_index = match app.view_mode {
+                    ViewMode::Player => app.player.sector_index,
+                    ViewMode::Spectator => app.spectator.sector_index,
+                };
+
+                let mut total = Duration::ZERO;
+                let mut fastest = Duration::MAX;
+                for _ in 0..frames {
+                    let start = Instant::now();
+                    app.renderer
+                        .update(
+                            &camera,
+                            sector_index,
+                            &app.assets.textures,
+                            &app.sectors,
+                            world_sprites!(app),
+                        )
+                        .map_err(|error| error.to_string())?;
+
+                    let elapsed = start.elapsed();
+                    total += elapsed;
+                    fastest = fastest.min(elapsed);
+                }
+
+                let maths = if cfg!(feature = "fixed-point") {
+                    "fixed point"
+                } else {
+                    "floating point"
+                };
+                let mean = total.as_secs_f64() * 1000.0 / frames as f64;
+                let best = fastest.as_secs_f64() * 1000.0;
+                Ok(format!(
+                    "{frames} frames with {maths} maths: {mean:.3}ms mean, {best:.3}ms best"
+                ))
+            },
+        );
+
+        self.console.register(
+            "depth_range",
+            "[<near> <far>]",
+            "Shows or sets the distances to the near and far clipping planes",
+            |app, args| {
+                if let [near, far] = args {
+                    let (near, far): (f32, f32) = (parse_arg(near)?, parse_arg(far)?);
+                    if near <= 0.0 || far <= near {
+                        return Err(String::from(
+                            "Expected a near plane above zero, and a far plane beyond it",
+                        ));
+                    }
+                    app.renderer.set_depth_range(near, far);
+                } else if !args.is_empty() {
+                    return Err(String::from("Expected a near and far distance, or nothing"));
+                }
+
+                let (near, far) = app.renderer.depth_range();
+                Ok(format!("Depth range is {near} to {far}"))
+            },
+        );
+
+        self.console.register(
+            "spawn",
+            "<texture>",
+            "Places a sprite in front of the player",
+            |app, args| {
+                let texture: usize = parse_arg(args.first().ok_or("Expected a texture index")?)?;
+                if texture >= app.assets.textures.len() {
+                    return Err(format!(
+                        "Texture index must be less than {}",
+                        app.assets.textures.len()
+                    ));
+                }
+
+                let position =
+                    app.player.camera.position + app.player.camera.direction * SPAWN_DISTANCE;
+                let sector_index = app
+                    .map_index
+                    .find_sector_near(position, &app.sectors, app.player.sector_index)
+                    .ok_or("There is no room in front of the player")?;
+
+                app.sprites.push(Sprite::new(
+                    position,
+                    sector_index,
+                    WallTexture::new(texture, Vec2f::ZERO, Vec2f::uniform(8.0)),
+                    15.0,
+                    15.0,
+                ));
+
+                Ok(format!("Spawned sprite in sector {sector_index}"))
+            },
+        );
+
+        self.console.register(
+            "reload_textures",
+            "",
+            "Reloads all textures from disk",
+            |app, _| {
+                app.assets.textures.reload_all();
+                Ok(format!("Reloading {} textures", app.assets.textures.len()))
+            },
+        );
+
+        self.console.register(
+            "assets",
+            "",
+            "Lists the assets that took longest to load",
+            |app, _| {
+                let load_times = app.assets.load_times();
+                let total: Duration = load_times.iter().map(|(_, time)| *time).sum();
+
+                let mut output = format!(
+                    "{} assets, {:.1}ms of loading across all threads",
+                    load_times.len(),
+                    total.as_secs_f64() * 1000.0
+                );
+                for (path, time) in load_times.iter().take(SLOWEST_ASSETS_LISTED) {
+                    output.push_str(&format!(
+                        "\n  {:>6.1}ms  {}",
+                        time.as_secs_f64() * 1000.0,
+                        path.display()
+                    ));
+                }
+
+                Ok(output)
+            },
+        );
+
+        self.console.register(
+            "events",
+            "[<count>]",
+            "Lists the most recent gameplay events, along with the frame each was raised in",
+            |app, args| {
+                let count = match args.first() {
+                    Some(count) => parse_arg::<usize>(count)?,
+                    None => EVENTS_LISTED,
+                };
+
+                let history = app.events.history();
+                let skip = history.len().saturating_sub(count);
+                Ok(history
+                    .skip(skip)
+                    .map(|(tick, event)| format!("{tick:>8}  {event:?}"))
+                    .collect::<Vec<_>>()
+                    .join("\n"))
+            },
+        );
+    }
+
+    /// Moves the player into another sector, keeping their height above the floor
+    fn enter_sector(&mut self, sector_index: usize) {
+        let z_delta = self.sectors[sector_index].floor.height
+            - self.sectors[self.player.sector_index].floor.height;
+        self.player.raise(z_delta);
+
+        self.player.sector_index = sector_index;
+        self.events.push(GameEvent::SectorEntered { sector_index });
+    }
+
+    /// Adds a newly spawned thing to the level
+    fn add_spawned(&mut self, spawned: Spawned) {
+        let floor_height = |sector_index: usize| self.sectors[sector_index].floor.height;
+
+        match spawned {
+            Spawned::PlayerStart(start) => {
+                self.player_start = start;
+                self.spawn_player(start, PlayerStats::new());
+            }
+            Spawned::RespawnPoint(start) => self.respawn_points.push(start),
+            Spawned::Enemy(mut enemy) => {
+                enemy.sprite.z = floor_height(enemy.sprite.sector_index);
+                self.enemies.push(enemy);
+            }
+            Spawned::Item(mut item) => {
+                item.sprite.z = floor_height(item.sprite.sector_index);
+                self.items.push(item);
+            }
+            Spawned::Decoration(mut sprite) => {
+                sprite.z = floor_height(sprite.sector_index);
+                self.sprites.push(sprite);
+            }
+            Spawned::Prop(mut prop) => {
+                prop.sprite.z = floor_height(prop.sprite.sector_index);
+                self.props.push(prop);
+            }
+            Spawned::Light(mut light) => {
+                light.hang(floor_height(light.sector_index));
+                self.lights.push(light);
+            }
+            Spawned::Hanging(mut sprite) => {
+                sprite.z = self.sectors[sprite.sector_index].ceiling.height - sprite.height;
+                self.hanging.push(sprite);
+            }
+        }
+    }
+
+    fn bake_lightmaps(&mut self, ambient: f32) {
+        let start = Instant::now();
+        lightmap::bake(&mut self.sectors, &self.lights, ambient);
+        self.lightmap_ambient = Some(ambient);
+        log::info!(
+            "Baked lightmaps from {} lights in {:.1}ms",
+            self.lights.len(),
+            start.elapsed().as_secs_f64() * 1000.0
+        );
+    }
+
+    /// Replaces the player with a new one standing at a start, keeping only whether noclip is on
+    /// and which trigger volumes they were in, so that leaving them is still noticed
+    fn spawn_player(&mut self, start: PlayerStart, stats: PlayerStats) {
+        let noclip = self.player.noclip;
+        let triggers = std::mem::take(&mut self.player.triggers);
+        self.player = Player::new(&start, self.sectors[start.sector_index].floor.height);
+        self.player.stats = stats;
+        self.player.noclip = noclip;
+        self.player.triggers = triggers;
+
+        self.respawn_timer = None;
+        self.events.push(GameEvent::SectorEntered {
+            sector_index: start.sector_index,
+        });
+    }
+
+    /// Brings the player back at the last respawn point they reached, with the keys they had
+    /// collected but otherwise starting over
+    fn respawn_player(&mut self) {
+        let start = self
+            .respawn_point
+            .map_or(self.player_start, |index| self.respawn_points[index]);
+
+        let mut stats = PlayerStats::new();
+        stats.keys = self.player.stats.keys;
+        self.spawn_player(start, stats);
+    }
+
+    /// Hurts the player, unless they are still recovering from the last hit
+    fn damage_player(&mut self, damage: Damage) {
+        if self.player.take_damage(&damage) {
+            self.events.push(GameEvent::PlayerDamaged { damage });
+        }
+    }
+
+    /// Finds what is drawn under the cursor, or under the crosshair whilst the cursor is grabbed
+    fn pick(&self) -> Option<Pick> {
+        let framebuffer = self.renderer.framebuffer();
+        let screen = self
+            .input
+            .mouse
+            .surface_position()
+            .filter(|_| !self.input.mouse.is_grabbed())
+            .unwrap_or(Vec2f::new(
+                framebuffer.half_width(),
+                framebuffer.half_height(),
+            ));
+        let sector_index = match self.view_mode {
+            ViewMode::Player => self.player.sector_index,
+            ViewMode::Spectator => self.spectator.sector_index,
+        };
+
+        pick::pick(
+            &self.sectors,
+            &self.renderer.view_ray(screen, sector_index),
+            self.renderer.depth_range().1,
+            self.pickable_sprites(),
+        )
+    }
+
+    /// Every sprite that is drawn, in the order that [PickTarget::Sprite] indexes them
+    fn pickable_sprites(&self) -> impl Iterator<Item = &Sprite> {
+        world_sprites!(self)
+    }
+
+    /// How many streamed textures are loaded, and how much of their budget they take
+    fn texture_streaming_text(&self) -> String {
+        let textures = &self.assets.textures;
+        let (streamed, loaded) = textures.streamed_count();
+
+        format!(
+            "{loaded}/{streamed} streamed, {} of {} KiB",
+            textures.streamed_size() / 1024,
+            textures.budget() / 1024
+        )
+    }
+
+    /// Pauses on the newest recorded frame, or carries on from it, returning whether now paused
+    fn toggle_rewind(&mut self) -> bool {
+        if self.rewind.is_paused() {
+            self.rewind.resume(&mut self.player, &mut self.sectors);
+        } else {
+            self.rewind.pause();
+        }
+
+        self.rewind.is_paused()
+    }
+
+    /// Freezes the world and shows the pause menu, or carries on, returning whether now paused
+    fn toggle_pause(&mut self) -> bool {
+        let paused = !self.clock.is_paused();
+        self.clock.set_paused(paused);
+        if paused {
+            self.pause_menu.reset();
+        }
+
+        // The cursor is freed to use the menu, and grabbed again to carry on, unless the console
+        // still wants it
+        self.set_cursor_grabbed(!paused && !self.console.is_open());
+
+        paused
+    }
+
+    /// Carries out what was chosen from the pause menu
+    fn choose_pause_item(&mut self, item: PauseItem) {
+        match item {
+            PauseItem::Resume => {}
+            PauseItem::Respawn => self.respawn_player(),
+            PauseItem::ReloadSettings => match self.load_settings() {
+                Ok(()) => log::info!("Reloaded '{SETTINGS_PATH}'"),
+                Err(error) => log::warn!("{error}"),
+            },
+        }
+
+        if item != PauseItem::ReloadSettings {
+            self.toggle_pause();
+        }
+    }
+
+    /// Grabs and hides the cursor, so that the mouse turns the view, or frees it to be used as usual
+    fn set_cursor_grabbed(&mut self, grabbed: bool) {
+        self.window.set_cursor_grab(grabbed);
+        self.window.set_cursor_visible(!grabbed);
+        self.input.mouse.grabbed = grabbed;
+    }
+
+    /// Applies the settings file, leaving the current settings as they are if it fails to load.
+    /// Settings with invalid values are left at their defaults, and are logged along with any that
+    /// aren't recognised.
+    fn load_settings(&mut self) -> Result<(), SettingsError> {
+        let settings = Settings::from_path(SETTINGS_PATH)?;
+        self.set_movement(MovementConfig::from_settings(&settings));
+        self.texture_config = TextureConfig::from_settings(&settings);
+
+        if let Some(scale) = settings.get_valid("render.scale", |scale: &f32| scale.is_finite()) {
+            self.set_render_scale(scale);
+        }
+        if let Some(budget_ms) = settings.get_valid("render.adaptive_budget_ms", |budget: &f32| {
+            budget.is_finite()
+        }) {
+            self.set_adaptive_resolution(budget_ms.max(0.1));
+        }
+
+        for problem in settings.problems() {
+            log::warn!("{problem}");
+        }
+
+        Ok(())
+    }
+
+    /// Sets the resolution that the world is drawn at, within the range it can be set to, and
+    /// returns the scale that was set, or nothing if it isn't a finite number. With adaptive
+    /// resolution, this is the most it will draw at.
+    fn set_render_scale(&mut self, scale: f32) -> Option<f32> {
+        if !scale.is_finite() {
+            return None;
+        }
+
+        let scale = scale.clamp(MIN_RENDER_SCALE, MAX_RENDER_SCALE);
+        self.renderer.set_render_scale(scale);
+        if let Some(adaptive) = self.adaptive_resolution.as_mut() {
+            adaptive.set_max_scale(scale);
+        }
+
+        Some(scale)
+    }
+
+    /// Turns on adaptive resolution, which lowers the render scale from the one that was set
+    /// whilst drawing the world takes longer than the budget, in milliseconds
+    fn set_adaptive_resolution(&mut self, budget_ms: f32) {
+        let max_scale = match &self.adaptive_resolution {
+            Some(adaptive) => adaptive.max_scale(),
+            None => self.renderer.render_scale(),
+        };
+        self.renderer.set_render_scale(max_scale);
+        self.adaptive_resolution = Some(AdaptiveResolution::new(budget_ms / 1000.0, max_scale));
+    }
+
+    /// Changes how the player moves, including how their input is read
+    fn set_movement(&mut self, movement: MovementConfig) {
+        self.movement = movement;
+        self.input.classic_diagonals = movement.classic_diagonals;
+    }
+
+    /// Loads the level's script and starts it. The previous script is kept if it fails to load.
+    fn load_script(&mut self) -> Result<(), ScriptError> {
+        self.script = Script::from_path(SCRIPT_PATH)?;
+        self.script.call("on_start", ())?;
+        self.run_script_commands();
+
+        Ok(())
+    }
+
+    /// Carries out what the script asked for. Commands that refer to things that don't exist are
+    /// reported on the console, rather than stopping the rest.
+    fn run_script_commands(&mut self) {
+        for mut command in self.script.take_commands() {
+            let sector = match &mut command {
+                ScriptCommand::OpenDoor(sector)
+                | ScriptCommand::RaiseLift(sector)
+                | ScriptCommand::SetFloor { sector, .. }
+                | ScriptCommand::SetCeiling { sector, .. }
+                | ScriptCommand::Spawn(Thing {
+                    sector_index: sector,
+                    ..
+                })
+                | ScriptCommand::Noise { sector, .. } => Some(sector),
+                ScriptCommand::Message(_) | ScriptCommand::Trigger(_) => None,
+            };
+            // The script numbers sectors as they were before any were merged
+            if let Some(sector) = sector {
+                match self.sector_remap.get(*sector) {
+                    Some(merged) => *sector = merged,
+                    None => {
+                        log::warn!("Script referred to a missing sector: {command:?}");
+                        continue;
+                    }
+                }
+            }
+
+            match command {
+                ScriptCommand::Message(text) => self.hud.show_message(text),
+                ScriptCommand::OpenDoor(sector) => {
+                    match self
+                        .doors
+                        .iter_mut()
+                        .find(|door| door.sector_index == sector)
+                    {
+                        Some(door) => door.open(),
+                        None => {
+                            log::warn!("Script tried to open sector {sector}, which isn't a door")
+                        }
+                    }
+                }
+                ScriptCommand::RaiseLift(sector) => {
+                    match self
+                        .lifts
+                        .iter_mut()
+                        .find(|lift| lift.sector_index == sector)
+                    {
+                        Some(lift) => lift.raise(),
+                        None => {
+                            log::warn!("Script tried to raise sector {sector}, which isn't a lift")
+                        }
+                    }
+                }
+                ScriptCommand::SetFloor { sector, height } => {
+                    self.sectors[sector].floor.height = height;
+                }
+                ScriptCommand::SetCeiling { sector, height } => {
+                    self.sectors[sector].ceiling.height = height;
+                }
+                ScriptCommand::Spawn(thing) => match self.thing_registry.spawn(&thing) {
+                    Some(spawned) => self.add_spawned(spawned),
+                    None => log::warn!(
+                        "Script tried to spawn a thing of type {}, which doesn't exist",
+                        thing.kind.0
+                    ),
+                },
+                ScriptCommand::Noise { sector, range } => {
+                    self.events.push(GameEvent::Noise {
+                        sector_index: sector,
+                        range,
+                    });
+                }
+                ScriptCommand::Trigger(trigger) => {
+                    self.events
+                        .push(GameEvent::SwitchToggled { trigger, on: true });
+                }
+            }
+        }
+    }
+
+    pub fn update(&mut self) {
+        profile::scope!("app", "update");
+        let delta_seconds = self.timer.delta_seconds();
+        let world_seconds = self.clock.tick(delta_seconds);
+        self.alloc_audit.end_frame();
+        self.input
+            .mouse
+            .set_surface_transform(self.window.surface_transform());
+
+        if let Some(error) = self.error.as_deref() {
+            Self::draw_error(&mut self.renderer, &self.assets, error);
+            self.input.update();
+            return;
+        }
+
+        // The console takes all input whilst open
+        if self.console.is_open() {
+            if let Some(line) = self.console.update(&self.input) {
+                Console::execute(self, &line, |app| &mut app.console);
+            }
+        } else if self.input.is_action_pressed(Action::ToggleConsole) {
+            self.console.set_open(true);
+            self.set_cursor_grabbed(false);
+        }
+        self.input.set_text_capture(self.console.is_open());
+
+        // Failed reloads keep the previous version, so they are only reported
+        for error in self.assets.update() {
+            log::warn!("{error}");
+        }
+
+        if self.rewind.is_paused() {
+            self.update_rewind(delta_seconds);
+        } else if !self.clock.is_paused() {
+            self.update_world(world_seconds);
+            self.rewind.record(
+                self.clock.time(),
+                &self.player,
+                self.enemies
+                    .iter()
+                    .map(|enemy| &enemy.sprite)
+                    .chain(self.debris.iter().map(|debris| &debris.sprite))
+                    .chain(self.projectiles.iter().map(|projectile| &projectile.sprite)),
+                &self.sectors,
+            );
+        }
+
+        // No need to render if window is minimised
+        if self.window.get_minimised() {
+            return;
+        }
+
+        // Toggle debug drawing
+        if self.input.is_action_pressed(Action::ToggleDebug) {
+            self.renderer.state_mut().debug = !self.renderer.state().debug;
+        }
+
+        // Toggle free-fly spectator camera (debug only)
+        if self.renderer.state().debug && self.input.is_action_pressed(Action::ToggleSpectator) {
+            self.view_mode = match self.view_mode {
+                ViewMode::Player => {
+                    self.spectator =
+                        Spectator::new(self.player.camera.clone(), self.player.sector_index);
+                    ViewMode::Spectator
+                }
+                ViewMode::Spectator => ViewMode::Player,
+            };
+        }
+
+        // Pause to step back through recent frames (debug only), which can always be left
+        if (self.renderer.state().debug || self.rewind.is_paused())
+            && self.input.is_action_pressed(Action::ToggleRewind)
+        {
+            self.toggle_rewind();
+        }
+
+        if self.input.is_action_pressed(Action::Pause) {
+            self.toggle_pause();
+        } else if self.clock.is_paused() && !self.console.is_open() {
+            let framebuffer = self.renderer.framebuffer();
+            let (width, height) = (framebuffer.width(), framebuffer.height());
+            if let Some(item) = self.pause_menu.update(&self.input, width, height) {
+                self.choose_pause_item(item);
+            }
+        }
+
+        // Toggle security monitor
+        if self.input.is_action_pressed(Action::ToggleMonitor) {
+            self.show_monitor = !self.show_monitor;
+        }
+
+        // Adjust field of view
+        let fov_delta = if self.input.is_action_held(Action::ZoomIn) {
+            FOV_SPEED * delta_seconds
+        } else if self.input.is_action_held(Action::ZoomOut) {
+            -FOV_SPEED * delta_seconds
+        } else {
+            0.0
+        };
+        if fov_delta != 0.0 {
+            let fov = (self.renderer.fov() + fov_delta).clamp(MIN_HFOV, MAX_HFOV);
+            self.renderer.set_fov(fov);
+        }
+
+        // Toggle frame rate cap
+        if self.input.is_action_pressed(Action::ToggleFrameCap) {
+            let target_fps = match self.timer.pacer.target_fps() {
+                Some(_) => None,
+                None => Some(FPS),
+            };
+            self.timer.pacer.set_target_fps(target_fps);
+        }
+
+        // Toggle display synchronisation
+        if self.input.is_action_pressed(Action::ToggleVsync) {
+            self.vsync = !self.vsync;
+            self.apply_present_mode();
+        }
+
+        // Cycle output scaling filter
+        if self.input.is_action_pressed(Action::CycleScaleFilter) {
+            let filter = match self.window.get_scale_filter() {
+                ScaleFilter::Nearest => ScaleFilter::Linear,
+                ScaleFilter::Linear => ScaleFilter::Integer,
+                ScaleFilter::Integer => ScaleFilter::Nearest,
+            };
+            self.window.set_scale_filter(filter);
+        }
+
+        // Toggle CRT output filter
+        if self.input.is_action_pressed(Action::ToggleCrt) {
+            let filter = match self.window.get_crt_filter() {
+                Some(_) => None,
+                None => Some(CrtFilter::default().with_intensity(CRT_INTENSITY)),
+            };
+            self.window.set_crt_filter(filter);
+        }
+
+        // Toggle wide field of view distortion correction
+        if self
+            .input
+            .is_action_pressed(Action::ToggleDistortionCorrection)
+        {
+            let enabled = !self.renderer.distortion_correction();
+            self.renderer.set_distortion_correction(enabled);
+        }
+
+        // Export top-down view of the map
+        if self.input.is_action_pressed(Action::ExportMap) {
+            if let Err(error) = self.export_map(false) {
+                log::error!("Failed to export map to '{MAP_EXPORT_PATH}': {error}");
+            }
+        }
+
+        // Play/skip demo cutscene
+        if self.input.is_action_pressed(Action::PlayCutscene) {
+            if self.spectator.is_playing_path() {
+                self.spectator.stop_path();
+            } else {
+                self.spectator.play_path(Self::demo_camera_path(), 0);
+                self.view_mode = ViewMode::Spectator;
+            }
+        }
+
+        // Grab the cursor on clicking the view. It is freed again by pausing.
+        if !self.window.get_cursor_grab()
+            && !self.console.is_open()
+            && !self.clock.is_paused()
+            && self.input.mouse.is_button_pressed(MouseButton::Left)
+        {
+            self.set_cursor_grabbed(true);
+        }
+
+        // Integer window scaling
+        let actions = [
+            Action::WindowScale1,
+            Action::WindowScale2,
+            Action::WindowScale3,
+            Action::WindowScale4,
+            Action::WindowScale5,
+        ];
+
+        if let Some(i) = actions
+            .iter()
+            .position(|action| self.input.is_action_pressed(*action))
+        {
+            self.set_window_scale(i + 1);
+        } else if self.window.get_scale_factor() != self.window_scale_factor {
+            // Moved to a monitor with a different DPI. The platform has already resized the window
+            // to keep its logical size, which rarely lands on a whole multiple of the framebuffer.
+            self.set_window_scale(self.window_scale);
+        }
+
+        // Test changing sector ceiling height, which shares its keys with the pause menu, so goes by
+        // the world's time to stay put whilst paused
+        if self.input.is_action_held(Action::RaiseCeiling) {
+            self.sectors[self.player.sector_index].ceiling.height += 10.0 * world_seconds
+        } else if self.input.is_action_held(Action::LowerCeiling) {
+            self.sectors[self.player.sector_index].ceiling.height -= 10.0 * world_seconds
+        }
+
+        // Test changing floor/ceiling texture rotation
+        if self.input.is_action_held(Action::RotateFlatsLeft) {
+            self.sectors[self.player.sector_index]
+                .floor
+                .texture_data
+                .scale_rotate =
+                Mat2f::rotation(1.2 * self.clock.time()) * Mat2f::scale(Vec2f::uniform(5.0));
+            self.sectors[self.player.sector_index]
+                .ceiling
+                .texture_data
+                .scale_rotate =
+                Mat2f::rotation(1.2 * self.clock.time()) * Mat2f::scale(Vec2f::uniform(5.0));
+        } else if self.input.is_action_held(Action::RotateFlatsRight) {
+            self.sectors[self.player.sector_index]
+                .floor
+                .texture_data
+                .scale_rotate =
+                Mat2f::rotation(-1.2 * self.clock.time()) * Mat2f::scale(Vec2f::uniform(5.0));
+            self.sectors[self.player.sector_index]
+                .ceiling
+                .texture_data
+                .scale_rotate =
+                Mat2f::rotation(-1.2 * self.clock.time()) * Mat2f::scale(Vec2f::uniform(5.0));
+        }
+
+        // Test rotating the current sector
+        if self.input.is_action_pressed(Action::RotateSector) {
+            let sector_index = self.player.sector_index;
+
+            if let Some(i) = self
+                .sector_motions
+                .iter()
+                .position(|motion| motion.sector_index == sector_index)
+            {
+                self.sector_motions.remove(i);
+            } else {
+                let walls = &self.sectors[sector_index].walls;
+                let centre = walls
+                    .iter()
+                    .fold(Vec2f::ZERO, |sum, wall| sum + wall.segment.a)
+                    / walls.len() as f32;
+
+                self.sector_motions.push(SectorMotion::new(
+                    &self.sectors,
+                    sector_index,
+                    centre,
+                    0.2,
+                    Vec2f::ZERO,
+                ));
+            }
+        }
+
+        let chasing = self.view_mode == ViewMode::Player && self.chase_camera;
+        let (camera, sector_index) = match self.view_mode {
+            ViewMode::Player if chasing => chase::follow(
+                &self.player.camera,
+                self.player.sector_index,
+                &self.sectors,
+                &self.map_index,
+            ),
+            ViewMode::Player => (
+                self.player.view_camera(&self.clock),
+                self.player.sector_index,
+            ),
+            ViewMode::Spectator => (self.spectator.camera.clone(), self.spectator.sector_index),
+        };
+        // Seen from outside, the player needs a body, which a spectator looking back at a recorded
+        // frame will want to see too
+        let rewinding = self.rewind.is_paused();
+        let player_sprite = (chasing || (rewinding && self.view_mode == ViewMode::Spectator))
+            .then(|| self.player.sprite());
+
+        // Whilst rewinding, everything that moves is drawn as it was in the frame looked at
+        let rewound = self
+            .rewind
+            .current()
+            .map_or(&[][..], |snapshot| &snapshot.sprites[..]);
+        let (enemies, debris, projectiles) = if rewinding {
+            (&[][..], &[][..], &[][..])
+        } else {
+            (&self.enemies[..], &self.debris[..], &self.projectiles[..])
+        };
+
+        // The muzzle flash lights up the world around the player, but not around a spectator, nor
+        // around a chase camera, which would light the wrong place
+        self.renderer.state_mut().camera_light = match self.view_mode {
+            ViewMode::Player if !chasing => self.player.weapon.muzzle_flash(),
+            _ => 0.0,
+        };
+
+        if self.show_monitor || self.camera_window.is_some() {
+            let result = self.renderer.update_offscreen(
+                &mut self.monitor_framebuffer,
+                &self.monitor_camera,
+                self.monitor_sector_index,
+                &self.assets.textures,
+                &self.sectors,
+                world_sprites!(self, enemies, debris, projectiles).chain(rewound.iter()),
+            );
+
+            if let Err(error) = result {
+                self.error = Some(format!("Failed to render the monitor: {error}"));
+            }
+        }
+
+        let render_start = Instant::now();
+        let result = self.renderer.update(
+            &camera,
+            sector_index,
+            &self.assets.textures,
+            &self.sectors,
+            world_sprites!(self, enemies, debris, projectiles)
+                .chain(rewound.iter())
+                .chain(player_sprite.iter()),
+        );
+        if let Err(error) = result {
+            self.error = Some(format!("Failed to render the world: {error}"));
+            return;
+        }
+
+        // The scale is changed for the next frame, as this one has already been drawn
+        if let Some(adaptive) = self.adaptive_resolution.as_mut() {
+            if let Some(scale) = adaptive.record(render_start.elapsed().as_secs_f32()) {
+                self.renderer.set_render_scale(scale);
+            }
+        }
+
+        if self.renderer.state().debug {
+            self.draw_debug_labels();
+        }
+
+        if self.show_monitor {
+            self.renderer
+                .draw_framebuffer(&self.monitor_framebuffer, 0.73, 0.02, 0.25, 0.25);
+        }
+
+        if self.view_mode == ViewMode::Player {
+            self.hud.draw(
+                &mut self.renderer,
+                &self.assets.textures,
+                &self.assets.fonts,
+                &self.player.camera,
+                &self.player.stats,
+                (!self.player.is_dead() && !chasing).then(|| WeaponView {
+                    frame: self.player.weapon.frame(),
+                    bob: self.player.weapon_bob(&self.clock),
+                    flash: self.player.weapon.muzzle_flash(),
+                }),
+            );
+        }
+
+        if self.clock.is_paused() {
+            self.pause_menu.draw(
+                &mut self.renderer,
+                &self.assets.fonts[Handle::from_index(FONT_DEFAULT)],
+            );
+        }
+
+        self.input.update();
+
+        // Show what has been logged this frame
+        for record in logger::take_records() {
+            self.console.print(&record.to_string());
+            self.toasts.push(&record);
+        }
+
+        // Draw debug text
+        if self.renderer.state().debug {
+            let alloc_text = if AllocAudit::ENABLED {
+                let stats = self.alloc_audit.last_frame();
+                format!(
+                    "\nAllocs:   {:>6} ({} B, peak {} KiB), worst {}",
+                    stats.allocations,
+                    stats.allocated_bytes,
+                    stats.peak_live_bytes / 1024,
+                    self.alloc_audit.worst_frame_allocations()
+                )
+            } else {
+                String::new()
+            };
+
+            // Only solid walls, as portals are usually seen through
+            let camera = &self.player.camera;
+            let aim_text = map::ray_cast(
+                &self.sectors,
+                self.player.sector_index,
+                camera.position,
+                camera.position + camera.direction * DEBUG_AIM_DISTANCE,
+                |wall| wall.portal.is_none(),
+            )
+            .map_or(String::from("none"), |hit| {
+                format!(
+                    "sector {}, wall {}, {:.2} away",
+                    hit.wall.sector_index,
+                    hit.wall.wall_index,
+                    hit.t * DEBUG_AIM_DISTANCE
+                )
+            });
+
+            let rewind_text = match (self.rewind.position(), self.rewind.current()) {
+                (Some((frames, recorded)), Some(snapshot)) => format!(
+                    "{frames} of {recorded} frames back, at {:.2}s",
+                    snapshot.time
+                ),
+                _ => String::from("recording"),
+            };
+
+            let render_stats = *self.renderer.stats();
+            let pick_text = self
+                .pick()
+                .map_or(String::from("none"), |pick| pick.target.to_string());
+
+            let cursor_text = match self.input.mouse.surface_position() {
+                Some(position) if !self.input.mouse.is_grabbed() => {
+                    format!("{:>3.0} {:>3.0}", position.x, position.y)
+                }
+                _ => String::from("none"),
+            };
+
+            self.renderer.draw_text(
+                &self.assets.fonts[Handle::from_index(FONT_DEFAULT)],
+                BGRA8::ORANGE,
+                (AlignWidth::Left, AlignHeight::Top),
+                0.01,
+                0.01,
+                &format!(
+                    "View:     {:?}
+FOV:      {:>6.2}{}
+Sector:   {:>3}
+Position: {:>6.2} {:>6.2} {:>6.2}
+Rotation: {:>6.2} {:>6.2}
+Velocity: {:>6.2} {:>6.2}
+Speed:    {:>6.2}
+Rewind:   {}
+Textures: {}
+Aim:      {}
+Pick:     {}
+Awake:    {}/{}
+Walls:    {} drawn, {} culled, portals {} (depth {}, {} skipped, {} pruned)
+Spans:    {}, {} px
+Sprites:  {} drawn, {} culled
+Cursor:   {}
+FPS cap:  {}, missed {}
+Present:  {:?}, {:.2?}{}
+Backend:  {:?}, {:?}{}{}",
+                    self.view_mode,
+                    self.renderer.fov(),
+                    if self.renderer.distortion_correction() {
+                        " (corrected)"
+                    } else {
+                        ""
+                    },
+                    self.player.sector_index,
+                    self.player.camera.position.x,
+                    self.player.camera.position.y,
+                    self.player.camera.z,
+                    self.player.camera.yaw,
+                    self.player.camera.pitch,
+                    self.player.velocity.x,
+                    self.player.velocity.y,
+                    self.player.velocity.magnitude(),
+                    rewind_text,
+                    self.texture_streaming_text(),
+                    aim_text,
+                    pick_text,
+                    self.enemies.iter().filter(|enemy| enemy.is_awake()).count(),
+                    self.enemies.len(),
+                    render_stats.walls_drawn,
+                    render_stats.walls_culled(),
+                    render_stats.portal_nodes,
+                    render_stats.max_portal_depth,
+                    render_stats.portal_nodes_skipped,
+                    render_stats.portals_pruned,
+                    render_stats.spans,
+                    render_stats.pixels,
+                    render_stats.sprites_drawn,
+                    render_stats.sprites_culled(),
+                    cursor_text,
+                    self.timer
+                        .pacer
+                        .target_fps()
+                        .map_or(String::from("uncapped"), |fps| format!("{fps:.0}")),
+                    self.timer.pacer.missed_frames,
+                    self.window.get_present_mode(),
+                    self.window.present_timing().present_duration,
+                    if self.window.get_double_buffered() {
+                        ", double buffered"
+                    } else {
+                        ""
+                    },
+                    self.window.get_backend(),
+                    self.window.get_scale_filter(),
+                    if self.window.get_crt_filter().is_some() {
+                        ", CRT"
+                    } else {
+                        ""
+                    },
+                    alloc_text
+                ),
+            );
+
+            self.toasts.draw(
+                &mut self.renderer,
+                &self.assets.fonts[Handle::from_index(FONT_DEFAULT)],
+            );
+        }
+
+        self.console.draw(
+            &mut self.renderer,
+            &self.assets.fonts[Handle::from_index(FONT_DEFAULT)],
+        );
+    }
+
+    /// Moves the game on by a frame: sectors, doors and lifts, projectiles, the player and enemies
+    fn update_world(&mut self, delta_seconds: f32) {
+        profile::scope!("world", "update");
+        if let Err(error) = self.script.update(delta_seconds) {
+            log::error!("{error}");
+        }
+
+        self.update_sector_motions(delta_seconds);
+
+        for sector in self.sectors.iter_mut() {
+            sector.animate_textures(delta_seconds);
+        }
+
+        for door in self.doors.iter_mut() {
+            let occupied = self.player.sector_index == door.sector_index;
+            door.update(&mut self.sectors, delta_seconds, occupied);
+        }
+
+        for lift in self.lifts.iter_mut() {
+            lift.update(&mut self.sectors, delta_seconds);
+        }
+
+        self.update_standing(delta_seconds);
+
+        for switch in self.switches.iter_mut() {
+            if switch.update(&mut self.sectors, delta_seconds) {
+                self.events.push(GameEvent::SwitchToggled {
+                    trigger: switch.trigger,
+                    on: false,
+                });
+            }
+        }
+
+        // Debris is moved by the solver, then kept within the walls, which the solver knows nothing
+        // of. The bodies stay with their debris and props, so are only gathered whilst needed.
+        if !self.debris.is_empty() {
+            let mut bodies: Vec<&mut RigidBody> = self
+                .props
+                .iter_mut()
+                .map(Prop::body_mut)
+                .chain(self.debris.iter_mut().map(Debris::body_mut))
+                .collect();
+            self.solver.step(&mut bodies, delta_seconds);
+        }
+
+        let sectors = &self.sectors;
+        self.debris
+            .retain_mut(|debris| debris.update(sectors, delta_seconds));
+
+        let events = &mut self.events;
+        let enemies = &mut self.enemies;
+        let player = &self.player;
+        let mut player_hit = None;
+        self.projectiles.retain_mut(|projectile| {
+            if let Some((wall, point)) = projectile.update(sectors, delta_seconds) {
+                events.push(GameEvent::WallDamaged {
+                    wall,
+                    point,
+                    amount: projectile.damage,
+                });
+                return false;
+            }
+
+            if projectile.hostile {
+                if projectile.touches(player.camera.position, player.collider.radius) {
+                    player_hit = Some(
+                        Damage::new(projectile.damage, DamageKind::Ballistic)
+                            .with_source(projectile.origin),
+                    );
+                    return false;
+                }
+            } else if let Some(enemy) = enemies
+                .iter_mut()
+                .find(|enemy| projectile.touches(enemy.sprite.position, enemy.sprite.width * 0.5))
+            {
+                enemy.damage(projectile.damage);
+                events.push(GameEvent::EnemyHit);
+                return false;
+            }
+
+            !projectile.is_expired()
+        });
+        if let Some(damage) = player_hit {
+            self.damage_player(damage);
+        }
+
+        if let Some(respawn_timer) = self.respawn_timer.as_mut() {
+            *respawn_timer -= delta_seconds;
+            if *respawn_timer <= 0.0 {
+                self.respawn_player();
+            }
+        }
+
+        match self.view_mode {
+            // The dead can't move or act, but are still carried along by sector motions
+            ViewMode::Player if self.player.is_dead() => {}
+            ViewMode::Player => {
+                let ground_velocity = self.sectors[self.player.sector_index]
+                    .floor
+                    .conveyor_velocity();
+                self.player.update_movement(
+                    delta_seconds,
+                    self.input.movement(),
+                    self.input.look(),
+                    ground_velocity,
+                    &self.movement,
+                );
+                if self.input.is_action_pressed(Action::Crouch) {
+                    self.player.toggle_crouch();
+                }
+
+                if self.input.is_action_pressed(Action::Use) {
+                    self.events.push(GameEvent::ActionPressed {
+                        action: Action::Use,
+                    });
+                }
+
+                self.update_weapon_selection();
+                self.player.weapon.update(delta_seconds);
+
+                if self.input.is_action_held(Action::Fire) {
+                    if let Some(weapon) = self.player.weapon.fire(&mut self.player.stats) {
+                        if let Some(range) = weapon.noise_range {
+                            self.events.push(GameEvent::Noise {
+                                sector_index: self.player.sector_index,
+                                range,
+                            });
+                        }
+                        self.shoot(weapon);
+                    }
+                }
+            }
+            ViewMode::Spectator => {
+                // The player stands still whilst spectating, so the move into their sector mustn't
+                // be seen as crossing its portal again
+                self.player.prev_position = self.player.camera.position;
+                self.spectator
+                    .update(delta_seconds, &self.input, &self.sectors);
+
+                // Return to the player once a cutscene has finished
+                if !self.spectator.is_playing_path() && !self.renderer.state().debug {
+                    self.view_mode = ViewMode::Player;
+                }
+            }
+        }
+
+        // Update current sector
+        if self.player.noclip {
+            // Walls may have been passed through, rather than portals
+            let sector_index = self.map_index.find_sector_near(
+                self.player.camera.position,
+                &self.sectors,
+                self.player.sector_index,
+            );
+            if let Some(sector_index) =
+                sector_index.filter(|index| *index != self.player.sector_index)
+            {
+                self.enter_sector(sector_index);
+            }
+        } else if let Some(portal) = self.sectors[self.player.sector_index]
+            .crossed_portal(self.player.camera.position, self.player.prev_position)
+        {
+            let sector_index = portal.sector;
+            self.enter_sector(sector_index);
+        }
+
+        // Hazard damage
+        let hazard = self.sectors[self.player.sector_index].hazard;
+        let damage = self
+            .player
+            .hazard_exposure
+            .update(hazard.as_ref(), delta_seconds);
+        if let Some(hazard) = hazard.filter(|_| damage > 0) {
+            self.damage_player(Damage::new(damage, hazard.kind.into()));
+        }
+        self.player.update_invulnerability(delta_seconds);
+
+        if self.player.is_dead() && self.respawn_timer.is_none() {
+            self.events.push(GameEvent::PlayerDied);
+        }
+
+        // Wall collision, including walls of neighbouring sectors that the collider reaches into
+        // through a portal
+        self.nearby_walls.clear();
+        if !self.player.noclip {
+            self.map_index
+                .walls_near(&self.player.collider.extents(), &mut self.nearby_walls);
+        }
+        // Walls of sectors that only overlap this one aren't reachable, however close they are
+        self.nearby_walls.retain(|id| {
+            if id.sector_index == self.player.sector_index {
+                return true;
+            }
+
+            // The neighbour's side of a portal into this sector is the same as this sector's side
+            let is_twin = id
+                .get(&self.sectors)
+                .portal
+                .is_some_and(|portal| portal.sector == self.player.sector_index);
+
+            map::are_adjacent(&self.sectors, self.player.sector_index, id.sector_index) && !is_twin
+        });
+
+        for id in self.nearby_walls.iter() {
+            let wall = id.get(&self.sectors);
+            let position = self.player.camera.position;
+            let distance_sq = wall.segment.point_distance_sq(position);
+
+            if distance_sq <= self.player.collider.radius * self.player.collider.radius {
+                if let Some(portal) = wall.portal.filter(|portal| portal.passable) {
+                    let sector = &self.sectors[id.sector_index];
+                    let next_sector = &self.sectors[portal.sector];
+
+                    // If player fits through the opening between both sectors, don't collide
+                    let (bottom, top) = portal.gap(sector, next_sector);
+                    if self.player.head_z < top && self.player.knee_z > bottom {
+                        continue;
+                    }
+                }
+
+                // Walls of neighbouring sectors may be approached from their outside, so push away
+                // from whichever side the player is on
+                let normal = if wall.normal.dot(position - wall.segment.a) > 0.0 {
+                    -wall.normal
+                } else {
+                    wall.normal
+                };
+
+                let depth = self.player.collider.radius - distance_sq.sqrt();
+                let correction = normal * depth;
+                self.player.translate(-correction);
+
+                self.player.velocity -= normal * normal.dot(self.player.velocity) * 0.5;
+            }
+        }
+
+        // Props block the way too, unless the player is high enough to clear them
+        if !self.player.noclip {
+            for prop in self.props.iter() {
+                if self.player.knee_z >= prop.sprite.z + prop.sprite.height {
+                    continue;
+                }
+
+                if let Some(collision) = prop.collide(&self.player.collider) {
+                    let normal = collision.normal();
+                    self.player.translate(normal * collision.depth());
+
+                    self.player.velocity -=
+                        normal * normal.dot(self.player.velocity).min(0.0) * 0.5;
+                }
+            }
+        }
+
+        // Nothing can bring back a player whose position has become NaN or infinite, such as
+        // through a degenerate wall, so start them over rather than leave them lost outside the map
+        let position = self.player.camera.position;
+        if !position.x.is_finite() || !position.y.is_finite() {
+            log::warn!(
+                "Player position became {position:?} in sector {}, respawning",
+                self.player.sector_index
+            );
+            self.respawn_player();
+        }
+
+        self.collect_items();
+        self.player.triggers.update(
+            &self.triggers,
+            Occupant::Player,
+            self.player.camera.position,
+            &mut self.events,
+        );
+        self.update_enemies(delta_seconds);
+        self.handle_events();
+        self.events.end_tick();
+    }
+
+    /// Steps through the recorded frames whilst rewinding, with the world otherwise standing still.
+    /// A spectator can still fly around, to look at the frame from anywhere.
+    fn update_rewind(&mut self, delta_seconds: f32) {
+        if self.view_mode == ViewMode::Spectator {
+            self.spectator
+                .update(delta_seconds, &self.input, &self.sectors);
+        }
+
+        let Some((frames, _)) = self.rewind.position() else {
+            return;
+        };
+        if self.input.is_action_pressed(Action::RewindBack) {
+            self.rewind
+                .seek(frames + 1, &mut self.player, &mut self.sectors);
+        } else if self.input.is_action_pressed(Action::RewindForward) {
+            self.rewind.seek(
+                frames.saturating_sub(1),
+                &mut self.player,
+                &mut self.sectors,
+            );
+        }
+    }
+
+    /// Labels each sector with its index, and each enemy that is still alive with its health
+    fn draw_debug_labels(&mut self) {
+        let font = &self.assets.fonts[Handle::from_index(FONT_DEFAULT)];
+
+        for (sector_index, sector) in self.sectors.iter().enumerate() {
+            self.renderer.draw_world_text(
+                font,
+                BGRA8::CYAN,
+                sector.extents().centre(),
+                (sector.floor.height + sector.ceiling.height) * 0.5,
+                WorldTextSize::Scaled(DEBUG_LABEL_DEPTH),
+                &sector_index.to_string(),
+            );
+        }
+
+        // Whilst rewinding, enemies are drawn where they were rather than where they are now
+        if self.rewind.is_paused() {
+            return;
+        }
+
+        for enemy in self.enemies.iter().filter(|enemy| !enemy.is_dead()) {
+            self.renderer.draw_world_text(
+                font,
+                BGRA8::RED,
+                enemy.sprite.position,
+                enemy.sprite.z + enemy.sprite.height,
+                WorldTextSize::Fixed,
+                &format!("{}/{}", enemy.health, enemy.enemy_type().health),
+            );
+        }
+    }
+
+    /// Presents in sync with the display if enabled, otherwise limiting the frame rate with the timer.
+    /// Where the window can't synchronise, the timer is limited to the display's refresh rate instead.
+    fn apply_present_mode(&mut self) {
+        if !self.vsync {
+            self.window.set_present_mode(PresentMode::Immediate);
+            self.timer.pacer.set_target_fps(Some(FPS));
+            return;
+        }
+
+        if self.window.set_present_mode(PresentMode::VSync) {
+            // Presenting already waits for the display, so limiting as well would only add latency
+            self.timer.pacer.set_target_fps(None);
+        } else {
+            let refresh_rate = self.window.present_timing().refresh_rate;
+            self.timer.pacer.set_target_fps(refresh_rate.or(Some(FPS)));
+        }
+    }
+
+    /// Moves the walls of any moving sectors, carrying along the player and sprites inside them
+    fn update_sector_motions(&mut self, delta_seconds: f32) {
+        for motion in self.sector_motions.iter_mut() {
+            let prev_transform = motion.update(&mut self.sectors, delta_seconds);
+            for sector_index in motion.affected_sectors() {
+                self.map_index.update_sector(sector_index, &self.sectors);
+            }
+
+            if self.player.sector_index == motion.sector_index {
+                let position = motion.carry(&prev_transform, self.player.camera.position);
+                self.player
+                    .translate(position - self.player.camera.position);
+
+                // Yaw is measured clockwise, whereas the sector rotates anti-clockwise
+                self.player
+                    .rotate(Vec2f::new(-motion.rotation_delta(&prev_transform), 0.0));
+            }
+
+            for sprite in self
+                .sprites
+                .iter_mut()
+                .chain(self.items.iter_mut().map(|item| &mut item.sprite))
+                .chain(self.enemies.iter_mut().map(|enemy| &mut enemy.sprite))
+                .chain(self.hanging.iter_mut())
+                .filter(|sprite| sprite.sector_index == motion.sector_index)
+            {
+                sprite.position = motion.carry(&prev_transform, sprite.position);
+            }
+
+            // Debris and props are carried along with their bodies, although prop footprints keep
+            // facing the same way
+            for debris in self
+                .debris
+                .iter_mut()
+                .filter(|debris| debris.sprite.sector_index == motion.sector_index)
+            {
+                let position = motion.carry(&prev_transform, debris.sprite.position);
+                debris.translate(position - debris.sprite.position);
+            }
+            for prop in self
+                .props
+                .iter_mut()
+                .filter(|prop| prop.sprite.sector_index == motion.sector_index)
+            {
+                let position = motion.carry(&prev_transform, prop.sprite.position);
+                prop.translate(position - prop.sprite.position);
+            }
+        }
+    }
+
+    /// Keeps everything standing on the floor of its sector, so that floors which move, whether
+    /// lifts or those set by the script, carry the player and sprites up and down with them. Things
+    /// that can fall do so under gravity instead, landing on the floor of whichever sector they
+    /// end up in.
+    fn update_standing(&mut self, delta_seconds: f32) {
+        if let Some(prev_height) = self.floor_heights.get(self.player.sector_index) {
+            let delta_z = self.sectors[self.player.sector_index].floor.height - prev_height;
+            if delta_z != 0.0 {
+                self.player.raise(delta_z);
+            }
+        }
+        self.floor_heights.clear();
+        self.floor_heights
+            .extend(self.sectors.iter().map(|sector| sector.floor.height));
+
+        // Decorations never leave the floor or ceiling that they rest on or hang from
+        for sprite in self.sprites.iter_mut() {
+            sprite.z = self.sectors[sprite.sector_index].floor.height;
+        }
+        for sprite in self.hanging.iter_mut() {
+            sprite.z = self.sectors[sprite.sector_index].ceiling.height - sprite.height;
+        }
+
+        for (sprite, fall) in self
+            .items
+            .iter_mut()
+            .map(|item| (&mut item.sprite, &mut item.fall))
+            .chain(
+                self.enemies
+                    .iter_mut()
+                    .map(|enemy| (&mut enemy.sprite, &mut enemy.fall)),
+            )
+            .chain(
+                self.debris
+                    .iter_mut()
+                    .map(|debris| (&mut debris.sprite, &mut debris.fall)),
+            )
+            .chain(
+                self.props
+                    .iter_mut()
+                    .map(|prop| (&mut prop.sprite, &mut prop.fall)),
+            )
+        {
+            fall.update(sprite, &self.sectors[sprite.sector_index], delta_seconds);
+        }
+    }
+
+    /// Switches weapon with the number keys, the mouse wheel or the gamepad's bumpers
+    fn update_weapon_selection(&mut self) {
+        let slots = [
+            Action::Weapon1,
+            Action::Weapon2,
+            Action::Weapon3,
+            Action::Weapon4,
+        ];
+        let held = &mut self.player.weapon;
+        let stats = &self.player.stats;
+
+        for (action, kind) in slots.into_iter().zip(WeaponKind::ALL) {
+            if self.input.is_action_pressed(action) {
+                held.select(kind, stats);
+            }
+        }
+
+        let wheel = if self.input.mouse.is_grabbed() {
+            self.input.mouse.wheel_delta().y
+        } else {
+            0.0
+        };
+        if self.input.is_action_pressed(Action::NextWeapon) || wheel > 0.0 {
+            held.cycle(true, stats);
+        } else if self.input.is_action_pressed(Action::PreviousWeapon) || wheel < 0.0 {
+            held.cycle(false, stats);
+        }
+    }
+
+    /// Fires a weapon from the player, fanning its pellets out across its spread. Hitscan pellets
+    /// damage the first enemy or solid wall that they hit, whereas projectiles are launched to do
+    /// so later.
+    fn shoot(&mut self, weapon: &Weapon) {
+        let from = self.player.camera.position;
+        let direction = self.player.camera.direction;
 
-pub struct App {
-    window: Window,
-    input: Input,
-    timer: Timer,
-    renderer: Renderer,
+        let pellets = match weapon.fire_mode {
+            FireMode::Hitscan { pellets } => pellets.max(1),
+            FireMode::Projectile { .. } => 1,
+            FireMode::Melee { range, half_angle } => {
+                self.melee(weapon, range, half_angle);
+                return;
+            }
+        };
+        for pellet in 0..pellets {
+            let angle = if pellets > 1 {
+                weapon.spread * (pellet as f32 / (pellets - 1) as f32 * 2.0 - 1.0)
+            } else {
+                0.0
+            };
+            let (sin, cos) = angle.sin_cos();
+            let direction = direction.rotate(sin, cos);
 
-    player: Player,
-    textures: Vec<Texture>,
-    fonts: Vec<Font>,
-    sectors: Vec<Sector>,
-    sprites: Vec<Sprite>,
-}
+            if let FireMode::Projectile { speed } = weapon.fire_mode {
+                self.projectiles.push(Projectile::bolt(
+                    from,
+                    self.player.camera.z,
+                    self.player.sector_index,
+                    direction * speed,
+                    weapon.damage,
+                ));
+                continue;
+            }
 
-impl App {
-    pub fn new() -> Self {
-        let window = Window::new(WindowAttributes {
-            title: format!("Pseudo3D  {}x{}  ({}x)", INTERNAL_WIDTH, INTERNAL_HEIGHT, 2),
-            size: WindowSize::new(INTERNAL_WIDTH * 2, INTERNAL_HEIGHT * 2),
-            surface_size: Some(WindowSize::new(INTERNAL_WIDTH, INTERNAL_HEIGHT)),
-            position: WindowPosition::new(200, 200),
-            resizable: false,
-            ..Default::default()
-        });
-        let input = Input::new();
-        let timer = Timer::new();
-        let renderer = Renderer::new(INTERNAL_WIDTH, INTERNAL_HEIGHT, HFOV);
+            let interaction = interact::interact(
+                &self.sectors,
+                self.player.sector_index,
+                from,
+                direction,
+                GUNSHOT_DISTANCE,
+                self.enemies.iter().map(|enemy| &enemy.sprite),
+                |_| false,
+            );
+            match interaction {
+                Some(Interaction {
+                    target: Target::Sprite(index),
+                    ..
+                }) => {
+                    self.enemies[index].damage(weapon.damage);
+                    self.events.push(GameEvent::EnemyHit);
+                }
+                Some(Interaction {
+                    target: Target::Wall(wall),
+                    distance,
+                }) => self.events.push(GameEvent::WallDamaged {
+                    wall,
+                    point: from + direction * distance,
+                    amount: weapon.damage,
+                }),
+                None => {}
+            }
+        }
+    }
 
-        let textures = Vec::new();
-        let fonts = Vec::new();
-        let player = Player::new(Vec2f::ZERO, 15.0, 0);
+    /// Attacks whatever is within an arc in front of the player, damaging every enemy hit. With
+    /// nothing else in reach, the wall in front is hit instead.
+    fn melee(&mut self, weapon: &Weapon, range: f32, half_angle: f32) {
+        let origin = self.player.camera.position;
+        let direction = self.player.camera.direction;
 
-        App {
-            window,
-            input,
-            timer,
-            renderer,
+        let hits = interact::melee_arc(
+            &self.sectors,
+            self.player.sector_index,
+            origin,
+            direction,
+            range,
+            half_angle,
+            self.enemies.iter().map(|enemy| &enemy.sprite),
+        );
+        for index in hits.iter() {
+            self.enemies[*index].damage(weapon.damage);
+        }
+        if !hits.is_empty() {
+            self.events.push(GameEvent::EnemyHit);
+            return;
+        }
 
-            player,
-            textures,
-            fonts,
-            sectors: Vec::new(),
-            sprites: Vec::new(),
+        let interaction = interact::interact(
+            &self.sectors,
+            self.player.sector_index,
+            origin,
+            direction,
+            range,
+            [],
+            |_| false,
+        );
+        if let Some(Interaction {
+            target: Target::Wall(wall),
+            distance,
+        }) = interaction
+        {
+            self.events.push(GameEvent::WallDamaged {
+                wall,
+                point: origin + direction * distance,
+                amount: weapon.damage,
+            });
         }
     }
 
-    pub fn setup(&mut self) {
-        // This is used to reduce the minimum sleep time on Windows from ~15ms to ~1ms
-        #[cfg(target_os = "windows")]
-        unsafe {
-            timeBeginPeriod(1)
-        };
+    /// Runs each enemy's state machine, resolving any attacks that land, then leaves the bodies of
+    /// those that died behind as corpses
+    fn update_enemies(&mut self, delta_seconds: f32) {
+        profile::scope!("world", "enemies");
+        perception::update_sight(&mut self.enemies, &self.sectors, &self.player);
+
+        for index in 0..self.enemies.len() {
+            let attack = self.enemies[index].update(
+                delta_seconds,
+                &self.nav_mesh,
+                &self.sectors,
+                &self.player,
+            );
+            if let Some(attack) = attack {
+                self.events.push(GameEvent::EnemyAttacked { attack });
+            }
 
-        // Load default assets
-        for path in TEXTURE_TILE_PATHS {
-            self.textures.push(Texture::from_path_png(path).unwrap());
+            let enemy = &mut self.enemies[index];
+            enemy.triggers.update(
+                &self.triggers,
+                Occupant::Enemy,
+                enemy.sprite.position,
+                &mut self.events,
+            );
         }
-        for path in TEXTURE_SPRITE_PATHS {
-            self.textures.push(Texture::from_path_png(path).unwrap());
+
+        let mut index = 0;
+        while index < self.enemies.len() {
+            if self.enemies[index].is_dead() {
+                let mut corpse = self.enemies.remove(index);
+                corpse
+                    .triggers
+                    .leave_all(&self.triggers, Occupant::Enemy, &mut self.events);
+                self.sprites.push(corpse.sprite);
+            } else {
+                index += 1;
+            }
         }
-        for (path, size) in FONT_PATHS.iter().zip(FONT_SIZES.iter()) {
-            self.fonts
-                .push(Font::from_path_png(path, size.0, size.1, 1).unwrap());
+    }
+
+    /// Carries out an enemy's attack on the player. Melee attacks miss if the player has backed
+    /// out of reach whilst it was wound up, and hitscan attacks miss if they have ducked out of
+    /// sight.
+    fn resolve_attack(&mut self, attack: &Attack) {
+        let target = self.player.camera.position;
+        let distance = (target - attack.origin).magnitude();
+
+        match attack.kind {
+            AttackKind::Melee => {
+                if distance <= attack.range + self.player.collider.radius {
+                    self.damage_player(
+                        Damage::new(attack.damage, DamageKind::Melee).with_source(attack.origin),
+                    );
+                }
+            }
+            AttackKind::Hitscan => {
+                let visible = map::line_of_sight(
+                    &self.sectors,
+                    attack.sector_index,
+                    attack.origin,
+                    attack.z,
+                    target,
+                    self.player.camera.z,
+                );
+                if distance <= attack.range && visible {
+                    self.damage_player(
+                        Damage::new(attack.damage, DamageKind::Ballistic)
+                            .with_source(attack.origin),
+                    );
+                }
+            }
+            AttackKind::Projectile { speed } => {
+                let direction = (target - attack.origin).normalise();
+                self.projectiles.push(
+                    Projectile::bolt(
+                        attack.origin,
+                        attack.z,
+                        attack.sector_index,
+                        direction * speed,
+                        attack.damage,
+                    )
+                    .hostile(),
+                );
+            }
         }
+    }
 
-        // Enable debug drawing by default
-        self.renderer.state_mut().debug = true;
+    /// Writes a top-down view of the map to [MAP_EXPORT_PATH]
+    fn export_map(&self, overlap_hints: bool) -> io::Result<()> {
+        export::export_svg(
+            MAP_EXPORT_PATH,
+            &self.sectors,
+            world_sprites!(self),
+            &self.player,
+            overlap_hints,
+        )
+    }
 
-        // Place player in sector 0
-        self.player.camera.position = Vec2f::new(105.0, 180.0);
-
-        // Point player towards sector 1
-        self.player.camera.yaw = core::f32::consts::PI;
-        self.player.camera.translate(Vec2f::ZERO);
-        self.player.camera.rotate(Vec2f::ZERO);
-
-        let stone_brick_wall = WallTexture::new(STONE_BRICK, Vec2f::ZERO, Vec2f::uniform(5.0));
-        let leaf_wall = WallTexture::new(LEAF, Vec2f::ZERO, Vec2f::uniform(5.0));
-        let grass_floor = PlaneTexture::new(GRASS, Vec2f::ZERO, Vec2f::uniform(5.0), 0.0);
-        let wood_ceiling = PlaneTexture::new(PLANK, Vec2f::ZERO, Vec2f::uniform(5.0), 0.0);
-
-        self.sectors = vec![
-            Sector {
-                id: 0,
-                walls: vec![
-                    Wall::new(
-                        Vec2f::new(80.0, 600.0),
-                        Vec2f::new(130.0, 600.0),
-                        stone_brick_wall,
-                        None,
-                    ),
-                    Wall::new(
-                        Vec2f::new(130.0, 600.0),
-                        Vec2f::new(130.0, 140.0),
-                        stone_brick_wall,
-                        None,
-                    ),
-                    Wall::new(
-                        Vec2f::new(130.0, 140.0),
-                        Vec2f::new(90.0, 140.0),
-                        stone_brick_wall,
-                        Some(Portal::new(1, stone_brick_wall, stone_brick_wall)),
-                    ),
-                    Wall::new(
-                        Vec2f::new(90.0, 140.0),
-                        Vec2f::new(80.0, 160.0),
-                        stone_brick_wall,
-                        Some(Portal::new(4, stone_brick_wall, stone_brick_wall)),
-                    ),
-                    Wall::new(
-                        Vec2f::new(80.0, 160.0),
-                        Vec2f::new(80.0, 600.0),
-                        stone_brick_wall,
-                        None,
-                    ),
-                ],
-                floor: Plane::new(0.0, grass_floor),
-                ceiling: Plane::new(25.0, wood_ceiling),
-            },
-            Sector {
-                id: 1,
-                walls: vec![
-                    Wall::new(
-                        Vec2f::new(90.0, 140.0),
-                        Vec2f::new(130.0, 140.0),
-                        stone_brick_wall,
-                        Some(Portal::new(0, stone_brick_wall, stone_brick_wall)),
-                    ),
-                    Wall::new(
-                        Vec2f::new(130.0, 140.0),
-                        Vec2f::new(130.0, 100.0),
-                        stone_brick_wall,
-                        None,
-                    ),
-                    Wall::new(
-                        Vec2f::new(130.0, 100.0),
-                        Vec2f::new(80.0, 100.0),
-                        stone_brick_wall,
-                        Some(Portal::new(2, stone_brick_wall, stone_brick_wall)),
-                    ),
-                    Wall::new(
-                        Vec2f::new(80.0, 100.0),
-                        Vec2f::new(80.0, 130.0),
-                        stone_brick_wall,
-                        None,
-                    ),
-                    Wall::new(
-                        Vec2f::new(80.0, 130.0),
-                        Vec2f::new(90.0, 140.0),
-                        stone_brick_wall,
-                        Some(Portal::new(4, stone_brick_wall, stone_brick_wall)),
-                    ),
-                ],
-                floor: Plane::new(0.0, grass_floor),
-                ceiling: Plane::new(25.0, wood_ceiling),
-            },
-            Sector {
-                id: 2,
-                walls: vec![
-                    Wall::new(
-                        Vec2f::new(80.0, 100.0),
-                        Vec2f::new(130.0, 100.0),
-                        stone_brick_wall,
-                        Some(Portal::new(1, stone_brick_wall, stone_brick_wall)),
-                    ),
-                    Wall::new(
-                        Vec2f::new(130.0, 100.0),
-                        Vec2f::new(150.0, 80.0),
-                        stone_brick_wall,
-                        None,
-                    ),
-                    Wall::new(
-                        Vec2f::new(150.0, 80.0),
-                        Vec2f::new(150.0, 60.0),
-                        stone_brick_wall,
-                        None,
-                    ),
-                    Wall::new(
-                        Vec2f::new(150.0, 60.0),
-                        Vec2f::new(100.0, 60.0),
-                        stone_brick_wall,
-                        Some(Portal::new(3, stone_brick_wall, stone_brick_wall)),
-                    ),
-                    Wall::new(
-                        Vec2f::new(100.0, 60.0),
-                        Vec2f::new(60.0, 60.0),
-                        stone_brick_wall,
-                        None,
-                    ),
-                    Wall::new(
-                        Vec2f::new(60.0, 60.0),
-                        Vec2f::new(60.0, 80.0),
-                        stone_brick_wall,
-                        None,
-                    ),
-                    Wall::new(
-                        Vec2f::new(60.0, 80.0),
-                        Vec2f::new(80.0, 100.0),
-                        stone_brick_wall,
-                        None,
-                    ),
-                ],
-                floor: Plane::new(0.0, grass_floor),
-                ceiling: Plane::new(30.0, wood_ceiling),
-            },
-            Sector {
-                id: 3,
-                walls: vec![
-                    Wall::new(
-                        Vec2f::new(100.0, 60.0),
-                        Vec2f::new(150.0, 60.0),
-                        stone_brick_wall,
-                        Some(Portal::new(2, stone_brick_wall, stone_brick_wall)),
-                    ),
-                    Wall::new(
-                        Vec2f::new(150.0, 60.0),
-                        Vec2f::new(150.0, 30.0),
-                        stone_brick_wall,
-                        None,
-                    ),
-                    Wall::new(
-                        Vec2f::new(150.0, 30.0),
-                        Vec2f::new(100.0, 30.0),
-                        leaf_wall,
-                        None,
-                    ),
-                    Wall::new(
-                        Vec2f::new(100.0, 30.0),
-                        Vec2f::new(100.0, 60.0),
-                        stone_brick_wall,
-                        None,
-                    ),
-                ],
-                floor: Plane::new(2.0, grass_floor),
-                ceiling: Plane::new(25.0, wood_ceiling),
-            },
-            Sector {
-                id: 4,
-                walls: vec![
-                    Wall::new(
-                        Vec2f::new(40.0, 160.0),
-                        Vec2f::new(80.0, 160.0),
-                        stone_brick_wall,
-                        None,
-                    ),
-                    Wall::new(
-                        Vec2f::new(80.0, 160.0),
-                        Vec2f::new(90.0, 140.0),
-                        stone_brick_wall,
-                        Some(Portal::new(0, stone_brick_wall, stone_brick_wall)),
-                    ),
-                    Wall::new(
-                        Vec2f::new(90.0, 140.0),
-                        Vec2f::new(80.0, 130.0),
-                        stone_brick_wall,
-                        Some(Portal::new(1, stone_brick_wall, stone_brick_wall)),
-                    ),
-                    Wall::new(
-                        Vec2f::new(80.0, 130.0),
-                        Vec2f::new(40.0, 130.0),
-                        stone_brick_wall,
-                        None,
-                    ),
-                    Wall::new(
-                        Vec2f::new(40.0, 130.0),
-                        Vec2f::new(40.0, 160.0),
-                        stone_brick_wall,
-                        None,
-                    ),
-                ],
-                floor: Plane::new(10.0, grass_floor),
-                ceiling: Plane::new(20.0, wood_ceiling),
-            },
-        ];
+    /// Splits a wall along with its twin, like [map::split_wall], and updates everything that refers
+    /// to the walls of the affected sectors. Walls of moving sectors can't be split, as their
+    /// motion keeps track of each wall.
+    fn split_wall(&mut self, id: WallId, point: Vec2f) -> Result<WallSplit, String> {
+        let twin = map::twin_wall(&self.sectors, id);
+        if self.is_sector_moving(id.sector_index)
+            || twin.is_some_and(|twin| self.is_sector_moving(twin.sector_index))
+        {
+            return Err(String::from("Can't split the walls of a moving sector"));
+        }
 
-        self.sprites = vec![
-            Sprite::new(
-                Vec2f::new(140.0, 80.0),
-                2,
-                WallTexture::new(GOBLIN, Vec2f::ZERO, Vec2f::uniform(8.0)),
-                15.0,
-                15.0,
-            ),
-            Sprite::new(
-                Vec2f::new(80.0, 80.0),
-                2,
-                WallTexture::new(GOBLIN, Vec2f::ZERO, Vec2f::uniform(8.0)),
-                15.0,
-                15.0,
-            ),
-        ];
+        let split = map::split_wall(&mut self.sectors, id, point)
+            .ok_or("Can't split a wall at either end")?;
+
+        for switch in self.switches.iter_mut() {
+            switch.wall = split.remap(switch.wall);
+        }
+        for destructible in self.destructibles.iter_mut() {
+            destructible.wall = split.remap(destructible.wall);
+        }
+
+        self.map_index.update_sector(id.sector_index, &self.sectors);
+        if let Some(twin_rest) = split.twin_rest {
+            self.map_index
+                .update_sector(twin_rest.sector_index, &self.sectors);
+        }
+        self.nav_mesh = NavMesh::build(&self.sectors);
+
+        Ok(split)
     }
 
-    pub fn update(&mut self) {
-        let delta_seconds = self.timer.delta_seconds();
+    /// Removes a wall like [map::remove_wall], along with anything on it, and updates everything
+    /// else that refers to walls of the sector
+    fn remove_wall(&mut self, id: WallId) -> Result<(), String> {
+        if self.is_sector_moving(id.sector_index) {
+            return Err(String::from("Can't remove the walls of a moving sector"));
+        }
 
-        self.player.update_movement(delta_seconds, &self.input);
+        map::remove_wall(&mut self.sectors, id)
+            .map_err(|error| format!("Can't remove the wall, as {error}"))?;
 
-        // Update current sector
-        let displacement_segment =
-            Segment::new(self.player.camera.position, self.player.prev_position);
-        for wall in self.sectors[self.player.sector_index].walls.iter() {
-            if let Some(portal) = wall.portal {
-                let wall_segment = Segment::new(wall.segment.a, wall.segment.b);
-                if displacement_segment.intersects(&wall_segment) {
-                    // Adjust z position for new sector
-                    let z_delta = self.sectors[portal.sector].floor.height
-                        - self.sectors[self.player.sector_index].floor.height;
+        self.switches.retain_mut(|switch| {
+            let Some(wall) = switch.wall.after_remove(id) else {
+                return false;
+            };
+            switch.wall = wall;
+            true
+        });
+        self.destructibles.retain_mut(|destructible| {
+            let Some(wall) = destructible.wall.after_remove(id) else {
+                return false;
+            };
+            destructible.wall = wall;
+            true
+        });
 
-                    self.player.camera.z += z_delta;
-                    self.player.head_z += z_delta;
-                    self.player.knee_z += z_delta;
+        self.map_index.update_sector(id.sector_index, &self.sectors);
+        self.nav_mesh = NavMesh::build(&self.sectors);
 
-                    self.player.sector_index = portal.sector;
+        // The wall that moved to close the gap lost its lightmap, and the light falls differently
+        // on the rest now the wall is gone
+        if let Some(ambient) = self.lightmap_ambient {
+            self.bake_lightmaps(ambient);
+        }
 
-                    break;
-                }
+        Ok(())
+    }
+
+    /// Returns whether a motion is moving any walls of the sector, which it keeps track of by index
+    fn is_sector_moving(&self, sector_index: usize) -> bool {
+        self.sector_motions
+            .iter()
+            .any(|motion| motion.affected_sectors().any(|index| index == sector_index))
+    }
+
+    /// Picks up any items that the player is touching, removing them from the map. Items that would
+    /// have no effect are left in place.
+    fn collect_items(&mut self) {
+        let collider = &self.player.collider;
+        let stats = &mut self.player.stats;
+        let events = &mut self.events;
+        let sectors = &self.sectors;
+        let sector_index = self.player.sector_index;
+
+        self.items.retain(|item| {
+            // Items in a sector that only overlaps the player's one are out of reach
+            if !item.sprite.overlaps(collider)
+                || !map::are_adjacent(sectors, sector_index, item.sprite.sector_index)
+                || !item.kind.apply(stats)
+            {
+                return true;
             }
-        }
 
-        // Wall collision
-        for wall in self.sectors[self.player.sector_index].walls.iter() {
-            let distance_sq = wall.segment.point_distance_sq(self.player.camera.position);
+            events.push(GameEvent::ItemPickedUp { kind: item.kind });
+            false
+        });
+    }
 
-            if distance_sq <= self.player.collider.radius * self.player.collider.radius {
-                if let Some(portal) = wall.portal {
-                    let next_sector_floor_z = self.sectors[portal.sector].floor.height;
-                    let next_sector_ceiling_z = self.sectors[portal.sector].ceiling.height;
+    /// Activates the switch or door that the player is looking at, if it is within reach and not
+    /// hidden behind another wall or an enemy. Failing that, a door nearby is opened.
+    fn use_target(&mut self) {
+        let doors = &self.doors;
+        let interaction = interact::interact(
+            &self.sectors,
+            self.player.sector_index,
+            self.player.camera.position,
+            self.player.camera.direction,
+            USE_DISTANCE,
+            self.enemies.iter().map(|enemy| &enemy.sprite),
+            |sector_index| doors.iter().any(|door| door.sector_index == sector_index),
+        );
 
-                    // If player fits through portal, don't collide
-                    if self.player.head_z < next_sector_ceiling_z
-                        && self.player.knee_z > next_sector_floor_z
-                    {
-                        continue;
+        match interaction.map(|interaction| interaction.target) {
+            Some(Target::Wall(wall)) => {
+                if let Some(switch) = self.switches.iter_mut().find(|switch| switch.wall == wall) {
+                    if switch.activate(&mut self.sectors) {
+                        self.events.push(GameEvent::SwitchToggled {
+                            trigger: switch.trigger,
+                            on: switch.is_on(),
+                        });
                     }
+                    return;
                 }
 
-                let depth = self.player.collider.radius - distance_sq.sqrt();
-                let correction = wall.normal * depth;
-                self.player.translate(-correction);
-
-                self.player.velocity -= wall.normal * wall.normal.dot(self.player.velocity) * 0.5;
+                let portal = wall.get(&self.sectors).portal;
+                if let Some(door_index) = portal.and_then(|portal| {
+                    self.doors
+                        .iter()
+                        .position(|door| door.sector_index == portal.sector)
+                }) {
+                    self.open_door(door_index);
+                    return;
+                }
             }
+            Some(Target::Sprite(_)) => return,
+            None => {}
         }
 
-        // No need to render if window is minimised
-        if self.window.get_minimised() {
-            return;
-        }
+        self.use_door();
+    }
 
-        // Toggle debug drawing
-        if self.input.keyboard.is_key_pressed(KeyCode::F3) {
-            self.renderer.state_mut().debug = !self.renderer.state().debug;
+    /// Opens the door that the player is standing in, or one next to the player's sector whose
+    /// portal is within reach
+    fn use_door(&mut self) {
+        let position = self.player.camera.position;
+        let sector_index = self.player.sector_index;
+        let walls = &self.sectors[sector_index].walls;
+
+        let door_index = self.doors.iter().position(|door| {
+            door.sector_index == sector_index
+                || walls.iter().any(|wall| {
+                    wall.portal
+                        .is_some_and(|portal| portal.sector == door.sector_index)
+                        && wall.segment.point_distance_sq(position) <= USE_DISTANCE * USE_DISTANCE
+                })
+        });
+
+        if let Some(door_index) = door_index {
+            self.open_door(door_index);
         }
+    }
 
-        // Toggle cursor grab
-        if !self.window.get_cursor_grab() && self.input.mouse.is_button_pressed(MouseButton::Left) {
-            self.window.set_cursor_grab(true);
-            self.window.set_cursor_visible(false);
-            self.input.mouse.grabbed = true;
-        } else if self.window.get_cursor_grab()
-            && self.input.keyboard.is_key_pressed(KeyCode::Escape)
-        {
-            self.window.set_cursor_grab(false);
-            self.window.set_cursor_visible(true);
-            self.input.mouse.grabbed = false;
+    /// Opens a door, unless it is locked and the player doesn't have the key, in which case an
+    /// event is raised instead
+    fn open_door(&mut self, door_index: usize) {
+        let door = &mut self.doors[door_index];
+
+        if door.can_unlock(&self.player.stats) {
+            door.open();
+        } else if let Some(lock) = door.lock {
+            self.events.push(GameEvent::DoorLocked { lock });
         }
+    }
 
-        // Integer window scaling
-        if self.input.keyboard.is_key_held(KeyCode::ControlLeft)
-            || self.input.keyboard.is_key_held(KeyCode::ControlRight)
-        {
-            let keys = [
-                KeyCode::Digit1,
-                KeyCode::Digit2,
-                KeyCode::Digit3,
-                KeyCode::Digit4,
-                KeyCode::Digit5,
-            ];
-
-            for (i, key) in keys.iter().enumerate() {
-                if self.input.keyboard.is_key_pressed(*key) {
-                    let window = self.get_window_mut();
-                    let scale = i + 1;
-
-                    window.set_size(WindowSize::new(
-                        INTERNAL_WIDTH * scale,
-                        INTERNAL_HEIGHT * scale,
-                    ));
-                    window.set_title(&format!(
-                        "Pseudo3D  {}x{}  ({}x)",
-                        INTERNAL_WIDTH, INTERNAL_HEIGHT, scale
-                    ));
+    /// Reacts to the events raised during the frame, in the order they were raised, including any
+    /// raised whilst handling them, such as an attack hurting the player
+    fn handle_events(&mut self) {
+        let mut events = std::mem::take(&mut self.handling_events);
+        loop {
+            self.events.take_into(&mut events);
+            if events.is_empty() {
+                break;
+            }
 
-                    break;
-                }
+            for event in events.drain(..) {
+                self.handle_event(event);
             }
         }
+        self.handling_events = events;
 
-        // Test changing sector ceiling height
-        if self.input.keyboard.is_key_held(KeyCode::ArrowUp) {
-            self.sectors[self.player.sector_index].ceiling.height += 10.0 * delta_seconds
-        } else if self.input.keyboard.is_key_held(KeyCode::ArrowDown) {
-            self.sectors[self.player.sector_index].ceiling.height -= 10.0 * delta_seconds
+        self.run_script_commands();
+    }
+
+    fn handle_event(&mut self, event: GameEvent) {
+        let script_event = match event {
+            GameEvent::SectorEntered { sector_index } => GameEvent::SectorEntered {
+                sector_index: self.sector_remap.original(sector_index),
+            },
+            event => event,
+        };
+        if let Err(error) = self.script.handle_event(&script_event) {
+            log::error!("{error}");
         }
 
-        // Test changing floor/ceiling texture rotation
-        if self.input.keyboard.is_key_held(KeyCode::ArrowLeft) {
-            self.sectors[self.player.sector_index]
-                .floor
-                .texture_data
-                .scale_rotate = Mat2f::rotation(0.01 * self.timer.frame_count as f32)
-                * Mat2f::scale(Vec2f::uniform(5.0));
-            self.sectors[self.player.sector_index]
-                .ceiling
-                .texture_data
-                .scale_rotate = Mat2f::rotation(0.01 * self.timer.frame_count as f32)
-                * Mat2f::scale(Vec2f::uniform(5.0));
-        } else if self.input.keyboard.is_key_held(KeyCode::ArrowRight) {
-            self.sectors[self.player.sector_index]
-                .floor
-                .texture_data
-                .scale_rotate = Mat2f::rotation(-0.01 * self.timer.frame_count as f32)
-                * Mat2f::scale(Vec2f::uniform(5.0));
-            self.sectors[self.player.sector_index]
-                .ceiling
-                .texture_data
-                .scale_rotate = Mat2f::rotation(-0.01 * self.timer.frame_count as f32)
-                * Mat2f::scale(Vec2f::uniform(5.0));
+        match event {
+            GameEvent::ActionPressed {
+                action: Action::Use,
+            } => self.use_target(),
+            GameEvent::ActionPressed { .. } => {}
+            GameEvent::EnemyAttacked { attack } => self.resolve_attack(&attack),
+            GameEvent::EnemyHit => self.crosshair.borrow_mut().hit(),
+            GameEvent::ItemPickedUp { kind } => {
+                self.hud.show_message(format!("Picked up {}", kind.name()));
+                self.hud.flash(hud::PICKUP_FLASH, BlendMode::Screen);
+
+                if let ItemKind::Weapon(weapon) = kind {
+                    self.player.weapon.select(weapon, &self.player.stats);
+                }
+            }
+            GameEvent::PlayerDamaged { damage } => {
+                self.hud.flash(damage.kind.flash_colour(), BlendMode::Alpha);
+                if let Some(source) = damage.source {
+                    self.hud.show_damage_source(source);
+                }
+            }
+            GameEvent::Noise {
+                sector_index,
+                range,
+            } => {
+                perception::hear_noise(&mut self.enemies, &self.sectors, sector_index, range);
+            }
+            GameEvent::SwitchToggled { trigger, on } => {
+                if on {
+                    self.doors
+                        .iter_mut()
+                        .filter(|door| door.trigger == Some(trigger))
+                        .for_each(Door::open);
+                    self.lifts
+                        .iter_mut()
+                        .filter(|lift| lift.trigger == Some(trigger))
+                        .for_each(Lift::raise);
+                }
+            }
+            GameEvent::WallDamaged {
+                wall,
+                point,
+                amount,
+            } => {
+                let Some(destructible) = self
+                    .destructibles
+                    .iter_mut()
+                    .find(|destructible| destructible.wall == wall)
+                else {
+                    return;
+                };
+
+                let texture = wall.get(&self.sectors).texture_data;
+                if !destructible.damage(&mut self.sectors, amount) {
+                    return;
+                }
+
+                // Thrown out into the sector that the wall faces, back towards the player
+                let direction = self.player.camera.position - point;
+                self.debris.extend(Debris::burst(
+                    point + direction.normalise(),
+                    wall.sector_index,
+                    direction,
+                    texture,
+                    DEBRIS_COUNT,
+                ));
+
+                if matches!(destructible.breakage, Breakage::Portal(_)) {
+                    self.nav_mesh = NavMesh::build(&self.sectors);
+                }
+            }
+            // This is also where a sound would be played, once there is audio
+            GameEvent::DoorLocked { lock } => {
+                self.hud.show_message(format!(
+                    "You need the {} to open this door",
+                    ItemKind::Key(lock).name()
+                ));
+            }
+            GameEvent::SectorEntered { sector_index } => {
+                if let Some(index) = self
+                    .respawn_points
+                    .iter()
+                    .position(|point| point.sector_index == sector_index)
+                {
+                    self.respawn_point = Some(index);
+                }
+            }
+            GameEvent::PlayerDied => {
+                self.respawn_timer = Some(RESPAWN_DELAY);
+                self.hud.show_message(String::from("You died"));
+            }
+            // Only the script reacts to these
+            GameEvent::TriggerEntered { .. } | GameEvent::TriggerExited { .. } => {}
         }
+    }
 
-        self.renderer.update(
-            &self.timer,
-            &self.player,
-            &self.textures,
-            &self.sectors,
-            &self.sprites,
-        );
-        self.input.update();
+    /// Replaces the whole screen with an error message, for when the game can't continue
+    fn draw_error(renderer: &mut Renderer, assets: &AssetManager, error: &str) {
+        const BACKGROUND: BGRA8 = BGRA8 {
+            b: 0x20,
+            g: 0x10,
+            r: 0x60,
+            a: 0xFF,
+        };
 
-        // Draw debug text
-        if self.renderer.state().debug {
-            self.renderer.draw_text(
-                &self.fonts[0],
-                BGRA8::ORANGE,
+        let width = renderer.framebuffer().width();
+        let height = renderer.framebuffer().height();
+        renderer.fill_rect(BACKGROUND, Rect::new(0, 0, width, height));
+
+        // The message is also printed to stderr, in case the font is what failed to load
+        if let Some(font) = assets.fonts.get(Handle::from_index(FONT_DEFAULT)) {
+            renderer.draw_text(
+                font,
+                BGRA8::WHITE,
                 (AlignWidth::Left, AlignHeight::Top),
-                0.01,
-                0.01,
-                &format!(
-                    "Sector:   {:>3}
-Position: {:>6.2} {:>6.2} {:>6.2}
-Rotation: {:>6.2} {:>6.2}
-Velocity: {:>6.2} {:>6.2}
-Speed:    {:>6.2}",
-                    self.player.sector_index,
-                    self.player.camera.position.x,
-                    self.player.camera.position.y,
-                    self.player.camera.z,
-                    self.player.camera.yaw,
-                    self.player.camera.pitch,
-                    self.player.velocity.x,
-                    self.player.velocity.y,
-                    self.player.velocity.magnitude()
-                ),
+                0.02,
+                0.02,
+                &format!("Something went wrong:\n\n{error}"),
             );
         }
     }
 
+    /// A short fly-through of the first few sectors, starting in sector 0
+    fn demo_camera_path() -> CameraPath {
+        let yaw = core::f32::consts::PI;
+
+        CameraPath::new(
+            vec![
+                CameraKeyframe::new(0.0, Vec2f::new(105.0, 400.0), 15.0, yaw, 0.0),
+                CameraKeyframe::new(3.0, Vec2f::new(100.0, 200.0), 18.0, yaw + 0.3, -0.2),
+                CameraKeyframe::new(5.0, Vec2f::new(110.0, 120.0), 15.0, yaw - 0.3, 0.0),
+                CameraKeyframe::new(7.0, Vec2f::new(120.0, 75.0), 17.0, yaw + 0.8, 0.1),
+                CameraKeyframe::new(9.0, Vec2f::new(125.0, 45.0), 15.0, yaw, 0.0),
+            ],
+            false,
+        )
+    }
+
     pub fn run(mut self) -> ! {
-        self.setup();
+        if let Err(error) = self.setup() {
+            log::error!("{error}");
+            self.error = Some(error.to_string());
+        }
 
         // Execute the event loop of the window
         <Self as WindowApplication>::run(self)
@@ -508,13 +3171,65 @@ impl WindowApplication for App {
         &mut self.window
     }
 
+    fn get_window_by_id_mut(&mut self, id: WindowId) -> Option<&mut Window> {
+        if self.window.id() == id {
+            return Some(&mut self.window);
+        }
+        self.camera_window
+            .as_mut()
+            .filter(|window| window.id() == id)
+    }
+
+    fn on_events_cleared(&mut self, target: &WindowTarget) {
+        if self.camera_window_wanted == self.camera_window.is_some() {
+            return;
+        }
+
+        self.camera_window = self.camera_window_wanted.then(|| {
+            let size = WindowSize::new(
+                self.monitor_framebuffer.width(),
+                self.monitor_framebuffer.height(),
+            );
+            Window::with_target(
+                WindowAttributes {
+                    title: String::from("Security camera"),
+                    size: size.to_physical(self.window.get_scale_factor() * 4.0),
+                    surface_size: Some(size),
+                    position: WindowPosition::new(100, 100),
+                    backend: GraphicsBackend::OpenGl,
+                    ..Default::default()
+                },
+                target,
+            )
+        });
+    }
+
     fn on_event(&mut self, event: &Event) {
         self.input.handle_event(event);
 
         match event {
-            Event::RenderEvent(render_event) => match render_event {
+            Event::RenderEvent(id, RenderEvent::RedrawRequested)
+                if self
+                    .camera_window
+                    .as_ref()
+                    .is_some_and(|window| window.id() == *id) =>
+            {
+                let Some(window) = self.camera_window.as_mut() else {
+                    return;
+                };
+                let mut ctx = window.graphics_context();
+                let buffer = ctx.framebuffer_mut();
+                let pixels = self.monitor_framebuffer.pixels_as_u32();
+                let len = pixels.len().min(buffer.len());
+                buffer[..len].copy_from_slice(&pixels[..len]);
+            }
+
+            Event::RenderEvent(id, render_event) if *id == self.window.id() => match render_event {
                 RenderEvent::RedrawRequested => {
                     self.update();
+                    if let Some(window) = &self.camera_window {
+                        window.request_redraw();
+                    }
 
                     // Copy renderer framebuffer to window framebuffer
                     let mut ctx = self.window.graphics_context();
@@ -530,8 +3245,9 @@ impl WindowApplication for App {
 
                     // Debug timings
                     let update_time_elapsed = Instant::now() - self.timer.prev_update;
-                    self.timer.time_buffer[self.timer.frame_count % 128] =
-                        update_time_elapsed.as_secs_f32();
+                    self.timer
+                        .time_buffer
+                        .push(update_time_elapsed.as_secs_f32());
                     let average = Duration::from_secs_f32(
                         self.timer.time_buffer.iter().sum::<f32>()
                             / self.timer.time_buffer.len() as f32,
@@ -540,24 +3256,20 @@ impl WindowApplication for App {
                         let fps = self.timer.frame_count as f32
                             / self.timer.start.elapsed().as_secs_f32();
 
-                        print!("Update: {:?}, FPS: {:?}\r", average, fps);
-                        std::io::Write::flush(&mut std::io::stdout()).unwrap();
+                        log::debug!("Update: {:?}, FPS: {:?}", average, fps);
                     }
 
-                    // Sleep until next frame
-                    let target = self.timer.prev_frame + self.timer.frame_time;
-                    // round down to nearest millisecond as sleep is not accurate and often overshoots
-                    let delta = Duration::from_millis((target - Instant::now()).as_millis() as u64);
-                    self.timer.prev_frame = target;
+                    // Wait until next frame
+                    self.timer.pacer.wait();
                     self.timer.frame_count += 1;
-
-                    if !delta.is_zero() {
-                        std::thread::sleep(delta)
-                    };
                 }
             },
 
-            Event::WindowEvent(window_event) => match window_event {
+            Event::WindowEvent(id, WindowEvent::CloseRequested) if *id != self.window.id() => {
+                self.camera_window_wanted = false;
+            }
+
+            Event::WindowEvent(_, window_event) => match window_event {
                 WindowEvent::Resized(size) => {
                     if size.width == 0 && size.height == 0 {
                         return;
@@ -567,6 +3279,13 @@ impl WindowApplication for App {
                 }
 
                 WindowEvent::CloseRequested => {
+                    #[cfg(feature = "profile")]
+                    if let Err(error) = profile::write_trace(PROFILE_TRACE_PATH) {
+                        log::error!(
+                            "Failed to write the profile to '{PROFILE_TRACE_PATH}': {error}"
+                        );
+                    }
+
                     // This must match the call to `timeBeginPeriod` at the start of the program
                     #[cfg(target_os = "windows")]
                     unsafe {
@@ -581,3 +3300,8 @@ impl WindowApplication for App {
         }
     }
 }
+
+/// Parses a console command argument, with an error message naming the argument if it is invalid
+fn parse_arg<T: core::str::FromStr>(arg: &str) -> Result<T, String> {
+    arg.parse().map_err(|_| format!("Invalid argument '{arg}'"))
+}
@@ -0,0 +1,632 @@
+//! Loading of textures and fonts on background threads, with hot reloading of changed files.
+//! Assets can also be streamed, loading only once first used and unloading again when memory runs
+//! short, so that maps can refer to many more textures than fit in memory at once.
+
+use std::{
+    error::Error,
+    fmt, fs,
+    marker::PhantomData,
+    ops::Index,
+    path::{Path, PathBuf},
+    sync::{
+        atomic::{AtomicBool, AtomicU64, Ordering},
+        Arc,
+    },
+    time::{Duration, Instant, SystemTime},
+};
+
+use jobs::{JobHandle, JobSystem};
+
+use crate::{
+    consts::TEXTURE_BUDGET,
+    font::{Font, FontError},
+    profile,
+    settings::Settings,
+    textures::{emissive_mask_path, Texture, TextureError},
+};
+
+/// How often files are checked for changes, when hot reloading is enabled
+const HOT_RELOAD_INTERVAL: Duration = Duration::from_secs(1);
+
+/// Something that can be kept in an [Assets] collection
+pub trait Asset {
+    /// Bytes of memory taken by the asset, which count towards the budget of streamed assets
+    fn memory_size(&self) -> usize;
+
+    /// Another file that is read along with the one at `path`, if it exists, so that the asset is
+    /// reloaded when either changes
+    fn companion_path(_path: &Path) -> Option<PathBuf> {
+        None
+    }
+}
+
+impl Asset for Texture {
+    fn memory_size(&self) -> usize {
+        self.memory_size()
+    }
+
+    fn companion_path(path: &Path) -> Option<PathBuf> {
+        Some(emissive_mask_path(path))
+    }
+}
+
+impl Asset for Font {
+    fn memory_size(&self) -> usize {
+        core::mem::size_of_val(self.char_metadata()) + core::mem::size_of_val(self.run_lengths())
+    }
+}
+
+/// Refers to an asset in an [Assets] collection, which stays valid when the asset is reloaded
+pub struct Handle<T> {
+    index: usize,
+    marker: PhantomData<fn() -> T>,
+}
+
+impl<T> Handle<T> {
+    /// Creates a handle from the position at which the asset was loaded, which is how maps refer
+    /// to textures
+    pub const fn from_index(index: usize) -> Self {
+        Self {
+            index,
+            marker: PhantomData,
+        }
+    }
+}
+
+// Derives would needlessly require `T` to implement these traits
+impl<T> Clone for Handle<T> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<T> Copy for Handle<T> {}
+
+impl<T> PartialEq for Handle<T> {
+    fn eq(&self, other: &Self) -> bool {
+        self.index == other.index
+    }
+}
+
+impl<T> Eq for Handle<T> {}
+
+impl<T> fmt::Debug for Handle<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Handle({})", self.index)
+    }
+}
+
+#[derive(Debug)]
+pub enum AssetError {
+    Texture(TextureError),
+    Font(FontError),
+}
+
+impl fmt::Display for AssetError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            AssetError::Texture(error) => error.fmt(f),
+            AssetError::Font(error) => error.fmt(f),
+        }
+    }
+}
+
+impl Error for AssetError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            AssetError::Texture(error) => Some(error),
+            AssetError::Font(error) => Some(error),
+        }
+    }
+}
+
+impl From<TextureError> for AssetError {
+    fn from(error: TextureError) -> Self {
+        AssetError::Texture(error)
+    }
+}
+
+impl From<FontError> for AssetError {
+    fn from(error: FontError) -> Self {
+        AssetError::Font(error)
+    }
+}
+
+/// An asset that failed to load, along with the file it was loaded from
+#[derive(Debug)]
+pub struct LoadError {
+    pub path: PathBuf,
+    pub error: AssetError,
+}
+
+impl fmt::Display for LoadError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "Failed to load '{}': {}",
+            self.path.display(),
+            self.error
+        )
+    }
+}
+
+impl Error for LoadError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        Some(&self.error)
+    }
+}
+
+type Loader<T> = Arc<dyn Fn(&Path) -> Result<T, AssetError> + Send + Sync>;
+
+/// The outcome of a load, along with how long it took on its worker thread
+type LoadResult<T> = (Result<T, AssetError>, Duration);
+
+struct Slot<T> {
+    path: PathBuf,
+    /// See [Asset::companion_path]
+    companion_path: Option<PathBuf>,
+    loader: Loader<T>,
+    /// Modification times of the file and its companion when it was last loaded
+    modified: [Option<SystemTime>; 2],
+    /// Time taken by the most recent load, whether or not it succeeded
+    load_time: Option<Duration>,
+    asset: Option<T>,
+    job: Option<JobHandle<LoadResult<T>>>,
+
+    /// Whether the asset is only loaded once it is used, and can be unloaded again
+    streamed: bool,
+    /// Set when a streamed asset that isn't loaded is asked for, so that it is loaded next update.
+    /// Atomic, like `last_used`, as assets are looked up whilst shared between threads.
+    requested: AtomicBool,
+    /// Number of the update after which the asset was last asked for
+    last_used: AtomicU64,
+    /// Memory taken by the asset, whilst it is loaded
+    size: usize,
+    /// Whether the most recent load failed, in which case a streamed asset isn't loaded again
+    /// until its file changes
+    failed: bool,
+}
+
+/// A collection of assets of one type, each loaded from a file on the job system. Whilst an asset
+/// is being reloaded, the previous version remains available.
+pub struct Assets<T> {
+    jobs: Arc<JobSystem>,
+    slots: Vec<Slot<T>>,
+    /// Counts calls to [Assets::update], which is how the use of assets is timed
+    frame: u64,
+    /// Memory that streamed assets may take before the least recently used are unloaded
+    budget: usize,
+    /// Asset that stands in for streamed assets whilst they load, which should itself be loaded
+    /// up front
+    placeholder: Option<Handle<T>>,
+}
+
+impl<T: Asset + Send + 'static> Assets<T> {
+    pub fn new(jobs: Arc<JobSystem>) -> Self {
+        Self {
+            jobs,
+            slots: Vec::new(),
+            frame: 0,
+            budget: usize::MAX,
+            placeholder: None,
+        }
+    }
+
+    /// Starts loading an asset in the background, using the given function to read the file
+    pub fn load(
+        &mut self,
+        path: impl Into<PathBuf>,
+        loader: impl Fn(&Path) -> Result<T, AssetError> + Send + Sync + 'static,
+    ) -> Handle<T> {
+        let handle = self.add(path.into(), Arc::new(loader), false);
+        self.start_load(handle.index);
+
+        handle
+    }
+
+    /// Adds an asset that isn't loaded until it is first used, and may be unloaded when it hasn't
+    /// been used for a while to keep streamed assets within the budget
+    pub fn stream(
+        &mut self,
+        path: impl Into<PathBuf>,
+        loader: impl Fn(&Path) -> Result<T, AssetError> + Send + Sync + 'static,
+    ) -> Handle<T> {
+        self.add(path.into(), Arc::new(loader), true)
+    }
+
+    fn add(&mut self, path: PathBuf, loader: Loader<T>, streamed: bool) -> Handle<T> {
+        let index = self.slots.len();
+
+        self.slots.push(Slot {
+            companion_path: T::companion_path(&path),
+            path,
+            loader,
+            modified: [None; 2],
+            load_time: None,
+            asset: None,
+            job: None,
+
+            streamed,
+            requested: AtomicBool::new(false),
+            last_used: AtomicU64::new(0),
+            size: 0,
+            failed: false,
+        });
+
+        Handle::from_index(index)
+    }
+
+    pub fn len(&self) -> usize {
+        self.slots.len()
+    }
+
+    /// Number of loads that haven't finished yet
+    pub fn pending(&self) -> usize {
+        self.slots.iter().filter(|slot| slot.job.is_some()).count()
+    }
+
+    /// Blocks until every pending load has finished, calling `on_finish` after each one, and
+    /// returns any that failed
+    pub fn wait(&mut self, mut on_finish: impl FnMut()) -> Vec<LoadError> {
+        let mut errors = Vec::new();
+
+        for slot in self.slots.iter_mut() {
+            if let Some(job) = slot.job.take() {
+                errors.extend(Self::finish_load(slot, job.wait()));
+                on_finish();
+            }
+        }
+
+        errors
+    }
+
+    /// Starts loading any streamed assets that have been asked for, swaps in any assets that have
+    /// finished loading, and unloads streamed assets that are over budget. Returns any assets that
+    /// failed to load.
+    pub fn update(&mut self) -> Vec<LoadError> {
+        profile::scope!("assets", "update");
+        let mut errors = Vec::new();
+
+        for index in 0..self.slots.len() {
+            let slot = &self.slots[index];
+            if slot.requested.swap(false, Ordering::Relaxed) && slot.job.is_none() {
+                self.start_load(index);
+            }
+        }
+
+        for slot in self.slots.iter_mut() {
+            let Some(result) = slot.job.as_mut().and_then(|job| job.try_take()) else {
+                continue;
+            };
+            slot.job = None;
+
+            errors.extend(Self::finish_load(slot, result));
+            // Counted as used, so that it isn't unloaded before it has had the chance to be
+            *slot.last_used.get_mut() = self.frame;
+        }
+
+        self.unload_over_budget();
+        self.frame += 1;
+
+        errors
+    }
+
+    /// Unloads the least recently used streamed assets until they fit within the budget. Assets
+    /// used since the last update are never unloaded, as they are still being drawn, so the budget
+    /// can be exceeded for as long as they are.
+    fn unload_over_budget(&mut self) {
+        let mut streamed_size = self.streamed_size();
+
+        while streamed_size > self.budget {
+            let frame = self.frame;
+            let Some(slot) = self
+                .slots
+                .iter_mut()
+                .filter(|slot| slot.streamed && slot.asset.is_some() && slot.job.is_none())
+                .filter(|slot| slot.last_used.load(Ordering::Relaxed) < frame)
+                .min_by_key(|slot| slot.last_used.load(Ordering::Relaxed))
+            else {
+                break;
+            };
+
+            slot.asset = None;
+            streamed_size -= slot.size;
+            slot.size = 0;
+        }
+    }
+
+    /// Starts reloading every asset that is loaded, or is always kept loaded
+    pub fn reload_all(&mut self) {
+        for index in 0..self.slots.len() {
+            let slot = &self.slots[index];
+            if !slot.streamed || slot.asset.is_some() {
+                self.start_load(index);
+            }
+        }
+    }
+
+    /// Starts reloading the assets whose files have been modified since they were loaded,
+    /// returning how many there were
+    pub fn reload_changed(&mut self) -> usize {
+        let mut count = 0;
+
+        for index in 0..self.slots.len() {
+            let slot = &self.slots[index];
+            // Streamed assets that aren't loaded will be read afresh when next used anyway
+            if slot.streamed && slot.asset.is_none() && !slot.failed {
+                continue;
+            }
+
+            if slot.job.is_none() && slot.modified_times() != slot.modified {
+                self.start_load(index);
+                count += 1;
+            }
+        }
+
+        count
+    }
+
+    fn start_load(&mut self, index: usize) {
+        let slot = &mut self.slots[index];
+
+        // Recorded before reading, so that changes made whilst loading are picked up next time
+        slot.modified = slot.modified_times();
+
+        let path = slot.path.clone();
+        let loader = slot.loader.clone();
+        slot.job = Some(self.jobs.spawn(move || {
+            profile::scope!("assets", path.display().to_string());
+            let start = Instant::now();
+            let result = loader(&path);
+            (result, start.elapsed())
+        }));
+    }
+
+    fn finish_load(slot: &mut Slot<T>, (result, load_time): LoadResult<T>) -> Option<LoadError> {
+        slot.load_time = Some(load_time);
+        slot.failed = result.is_err();
+
+        match result {
+            Ok(asset) => {
+                slot.size = asset.memory_size();
+                slot.asset = Some(asset);
+                None
+            }
+            Err(error) => Some(LoadError {
+                path: slot.path.clone(),
+                error,
+            }),
+        }
+    }
+
+    /// Sets how much memory streamed assets may take, unloading any that no longer fit at the next
+    /// update
+    pub fn set_budget(&mut self, budget: usize) {
+        self.budget = budget;
+    }
+
+    pub fn set_placeholder(&mut self, placeholder: Handle<T>) {
+        self.placeholder = Some(placeholder);
+    }
+}
+
+impl<T> Assets<T> {
+    /// Returns the asset, unless it hasn't finished loading for the first time. Streamed assets
+    /// that aren't loaded are loaded at the next update.
+    pub fn get(&self, handle: Handle<T>) -> Option<&T> {
+        let slot = self.slots.get(handle.index)?;
+
+        if slot.asset.is_some() {
+            slot.last_used.store(self.frame, Ordering::Relaxed);
+        } else if slot.streamed && !slot.failed {
+            slot.requested.store(true, Ordering::Relaxed);
+        }
+
+        slot.asset.as_ref()
+    }
+
+    /// Returns the asset, or the placeholder if there is one whilst the asset is loading. Only
+    /// assets that don't exist are missing.
+    pub fn get_or_placeholder(&self, handle: Handle<T>) -> Option<&T> {
+        if handle.index >= self.slots.len() {
+            return None;
+        }
+
+        self.get(handle).or_else(|| self.get(self.placeholder?))
+    }
+
+    pub fn budget(&self) -> usize {
+        self.budget
+    }
+
+    /// Memory taken by the streamed assets that are loaded
+    pub fn streamed_size(&self) -> usize {
+        self.slots
+            .iter()
+            .filter(|slot| slot.streamed)
+            .map(|slot| slot.size)
+            .sum()
+    }
+
+    /// Number of streamed assets, and how many of them are loaded
+    pub fn streamed_count(&self) -> (usize, usize) {
+        let streamed = self.slots.iter().filter(|slot| slot.streamed);
+        let loaded = streamed.clone().filter(|slot| slot.asset.is_some()).count();
+
+        (streamed.count(), loaded)
+    }
+
+    /// Returns the file of each asset that has finished loading at least once, along with how long
+    /// its most recent load took
+    pub fn load_times(&self) -> impl Iterator<Item = (&Path, Duration)> {
+        self.slots
+            .iter()
+            .filter_map(|slot| Some((slot.path.as_path(), slot.load_time?)))
+    }
+}
+
+impl<T> Index<Handle<T>> for Assets<T> {
+    type Output = T;
+
+    /// Panics if the asset hasn't finished loading for the first time, unless there is a
+    /// placeholder to show instead
+    fn index(&self, handle: Handle<T>) -> &T {
+        self.get_or_placeholder(handle)
+            .expect("Asset has not been loaded")
+    }
+}
+
+impl<T> Slot<T> {
+    fn modified_times(&self) -> [Option<SystemTime>; 2] {
+        [
+            modified_time(&self.path),
+            self.companion_path.as_deref().and_then(modified_time),
+        ]
+    }
+}
+
+fn modified_time(path: &Path) -> Option<SystemTime> {
+    fs::metadata(path)
+        .and_then(|metadata| metadata.modified())
+        .ok()
+}
+
+/// How textures are loaded and kept in memory, which can be set in the settings file
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct TextureConfig {
+    /// Whether the textures used in maps are only loaded once they are first drawn
+    pub streaming: bool,
+    /// Bytes that streamed textures may take before the least recently used are unloaded
+    pub budget: usize,
+    /// Whether textures are stored as a palette and an index per pixel, taking a quarter of the
+    /// memory at the cost of some colours in textures with more than 256
+    pub paletted: bool,
+}
+
+impl TextureConfig {
+    pub const DEFAULT: Self = Self {
+        streaming: true,
+        budget: TEXTURE_BUDGET,
+        paletted: false,
+    };
+
+    /// Reads the texture options from the settings, where the budget is given in mebibytes
+    pub fn from_settings(settings: &Settings) -> Self {
+        let mut config = Self::DEFAULT;
+
+        if let Some(streaming) = settings.get("textures.streaming") {
+            config.streaming = streaming;
+        }
+        if let Some(budget) = settings.get::<usize>("textures.budget_mb") {
+            config.budget = budget.saturating_mul(1024 * 1024);
+        }
+        if let Some(paletted) = settings.get("textures.paletted") {
+            config.paletted = paletted;
+        }
+
+        config
+    }
+}
+
+/// Owns every texture and font, so that they can be loaded in the background and reloaded when
+/// their files change
+pub struct AssetManager {
+    pub textures: Assets<Texture>,
+    pub fonts: Assets<Font>,
+    /// Reload assets when their files are modified, which is useful whilst editing them
+    pub hot_reload: bool,
+    /// Store textures loaded from now on with a palette, see [Texture::into_paletted]
+    pub paletted_textures: bool,
+    last_reload_check: Instant,
+}
+
+impl AssetManager {
+    pub fn new(jobs: Arc<JobSystem>) -> Self {
+        Self {
+            textures: Assets::new(jobs.clone()),
+            fonts: Assets::new(jobs),
+            hot_reload: cfg!(debug_assertions),
+            paletted_textures: false,
+            last_reload_check: Instant::now(),
+        }
+    }
+
+    pub fn load_texture(&mut self, path: impl Into<PathBuf>) -> Handle<Texture> {
+        self.textures
+            .load(path, texture_loader(self.paletted_textures))
+    }
+
+    /// Adds a texture that is only loaded once it is first drawn, showing a placeholder until then
+    pub fn stream_texture(&mut self, path: impl Into<PathBuf>) -> Handle<Texture> {
+        self.textures
+            .stream(path, texture_loader(self.paletted_textures))
+    }
+
+    pub fn load_font(
+        &mut self,
+        path: impl Into<PathBuf>,
+        char_width: usize,
+        char_height: usize,
+        char_spacing: usize,
+    ) -> Handle<Font> {
+        self.fonts.load(path, move |path| {
+            Ok(Font::from_path_png(
+                path,
+                char_width,
+                char_height,
+                char_spacing,
+            )?)
+        })
+    }
+
+    /// Blocks until every pending load has finished, returning any that failed. As each one
+    /// finishes, `progress` is called with the number that have finished and the total.
+    pub fn wait(&mut self, mut progress: impl FnMut(usize, usize)) -> Vec<LoadError> {
+        let total = self.textures.pending() + self.fonts.pending();
+        let mut finished = 0;
+        let mut on_finish = || {
+            finished += 1;
+            progress(finished, total);
+        };
+
+        let mut errors = self.textures.wait(&mut on_finish);
+        errors.extend(self.fonts.wait(&mut on_finish));
+        errors
+    }
+
+    /// Returns the file of every loaded asset along with how long it took to load, slowest first
+    pub fn load_times(&self) -> Vec<(&Path, Duration)> {
+        let mut load_times: Vec<_> = self
+            .textures
+            .load_times()
+            .chain(self.fonts.load_times())
+            .collect();
+        load_times.sort_unstable_by_key(|(_, time)| core::cmp::Reverse(*time));
+        load_times
+    }
+
+    /// Swaps in any assets that have finished loading and, if enabled, starts reloading any that
+    /// have changed. Returns any that failed to load.
+    pub fn update(&mut self) -> Vec<LoadError> {
+        if self.hot_reload && self.last_reload_check.elapsed() >= HOT_RELOAD_INTERVAL {
+            self.last_reload_check = Instant::now();
+            self.textures.reload_changed();
+            self.fonts.reload_changed();
+        }
+
+        let mut errors = self.textures.update();
+        errors.extend(self.fonts.update());
+        errors
+    }
+}
+
+fn texture_loader(paletted: bool) -> impl Fn(&Path) -> Result<Texture, AssetError> {
+    move |path| {
+        let texture = Texture::from_path_png(path)?;
+        Ok(if paletted {
+            texture.into_paletted()
+        } else {
+            texture
+        })
+    }
+}
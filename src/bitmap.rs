@@ -1,7 +1,56 @@
-use std::{fs::File, mem::ManuallyDrop, path::Path};
+use std::{error::Error, fmt, fs::File, io, mem::ManuallyDrop, path::Path};
 
 use crate::colour::BGRA8;
 
+#[derive(Debug)]
+pub enum BitmapError {
+    Io(io::Error),
+    Decode(png::DecodingError),
+    /// The image decoded to something other than 8-bit RGBA
+    UnsupportedFormat {
+        bit_depth: png::BitDepth,
+        colour_type: png::ColorType,
+    },
+}
+
+impl fmt::Display for BitmapError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            BitmapError::Io(error) => write!(f, "failed to open file: {error}"),
+            BitmapError::Decode(error) => write!(f, "failed to decode PNG: {error}"),
+            BitmapError::UnsupportedFormat {
+                bit_depth,
+                colour_type,
+            } => write!(
+                f,
+                "unsupported format {colour_type:?} with bit depth {bit_depth:?}, expected 8-bit RGBA"
+            ),
+        }
+    }
+}
+
+impl Error for BitmapError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            BitmapError::Io(error) => Some(error),
+            BitmapError::Decode(error) => Some(error),
+            BitmapError::UnsupportedFormat { .. } => None,
+        }
+    }
+}
+
+impl From<io::Error> for BitmapError {
+    fn from(error: io::Error) -> Self {
+        BitmapError::Io(error)
+    }
+}
+
+impl From<png::DecodingError> for BitmapError {
+    fn from(error: png::DecodingError) -> Self {
+        BitmapError::Decode(error)
+    }
+}
+
 #[derive(Debug)]
 pub struct Bitmap {
     width: usize,
@@ -18,29 +67,22 @@ impl Bitmap {
         }
     }
 
-    pub fn from_path_png(path: impl AsRef<Path>) -> Result<Self, &'static str> {
-        let mut decoder = png::Decoder::new(File::open(path).map_err(|_| "Failed to open file")?);
+    pub fn from_path_png(path: impl AsRef<Path>) -> Result<Self, BitmapError> {
+        let mut decoder = png::Decoder::new(File::open(path)?);
         decoder.set_transformations(png::Transformations::ALPHA | png::Transformations::STRIP_16);
 
-        let mut reader = decoder.read_info().map_err(|_| "Failed to read info")?;
+        let mut reader = decoder.read_info()?;
 
         let mut buffer = vec![0; reader.output_buffer_size()];
 
-        let info = reader
-            .next_frame(&mut buffer)
-            .map_err(|_| "Failed to read frame")?;
-
-        assert_eq!(
-            info.bit_depth,
-            png::BitDepth::Eight,
-            "Unsupported bit depth"
-        );
-
-        assert_eq!(
-            info.color_type,
-            png::ColorType::Rgba,
-            "Unsupported colour type"
-        );
+        let info = reader.next_frame(&mut buffer)?;
+
+        if info.bit_depth != png::BitDepth::Eight || info.color_type != png::ColorType::Rgba {
+            return Err(BitmapError::UnsupportedFormat {
+                bit_depth: info.bit_depth,
+                colour_type: info.color_type,
+            });
+        }
 
         // Convert from RGBA to BGRA
         for pixel in buffer.chunks_exact_mut(4) {
@@ -1,6 +1,8 @@
-use std::f32::consts::FRAC_PI_4;
+use std::f32::consts::{FRAC_PI_4, PI, TAU};
 
 use maths::linear::Vec2f;
+#[cfg(feature = "fixed-point")]
+use maths::trig;
 
 #[derive(Clone, Debug)]
 pub struct Camera {
@@ -42,13 +44,166 @@ impl Camera {
     }
 
     pub fn rotate(&mut self, rotation: Vec2f) {
-        self.yaw += rotation.x;
-        (self.yaw_sin, self.yaw_cos) = self.yaw.sin_cos();
+        self.set_rotation(self.yaw + rotation.x, self.pitch + rotation.y);
+    }
 
+    /// Sets the absolute yaw and pitch (in radians), updating the cached trigonometric values
+    pub fn set_rotation(&mut self, yaw: f32, pitch: f32) {
+        self.yaw = yaw;
         // Clamps pitch to +-45 degrees
-        self.pitch = (self.pitch + rotation.y).clamp(-FRAC_PI_4, FRAC_PI_4);
-        self.pitch_tan = self.pitch.tan();
+        self.pitch = pitch.clamp(-FRAC_PI_4, FRAC_PI_4);
+
+        // Looked up from tables with fixed point maths, which snaps the view to the nearest step
+        #[cfg(feature = "fixed-point")]
+        {
+            let (sin, cos) = trig::sin_cos(self.yaw);
+            (self.yaw_sin, self.yaw_cos) = (sin.to_f32(), cos.to_f32());
+            self.pitch_tan = trig::tan(self.pitch).to_f32();
+        }
+        #[cfg(not(feature = "fixed-point"))]
+        {
+            (self.yaw_sin, self.yaw_cos) = self.yaw.sin_cos();
+            self.pitch_tan = self.pitch.tan();
+        }
 
         self.direction = Vec2f::new(self.yaw_sin, self.yaw_cos);
     }
+
+    /// Returns a camera that is linearly interpolated between this camera and `other`, where `t` is
+    /// in the range 0.0 to 1.0. Yaw takes the shortest path around the circle.
+    pub fn lerp(&self, other: &Camera, t: f32) -> Camera {
+        let mut camera = Camera::new(
+            self.position.lerp(other.position, t),
+            self.z + (other.z - self.z) * t,
+        );
+        camera.set_rotation(
+            lerp_angle(self.yaw, other.yaw, t),
+            self.pitch + (other.pitch - self.pitch) * t,
+        );
+
+        camera
+    }
+
+    /// Moves this camera towards `target` using exponential smoothing. A higher `sharpness` results
+    /// in the camera catching up more quickly. This is independent of the frame rate.
+    pub fn smooth_towards(&mut self, target: &Camera, sharpness: f32, delta_seconds: f32) {
+        let t = 1.0 - (-sharpness * delta_seconds).exp();
+        *self = self.lerp(target, t);
+    }
+}
+
+/// Interpolates between two angles (in radians), taking the shortest path around the circle
+pub fn lerp_angle(a: f32, b: f32, t: f32) -> f32 {
+    let delta = (b - a + PI).rem_euclid(TAU) - PI;
+    a + delta * t
+}
+
+/// Smooth Hermite interpolation of `t` in the range 0.0 to 1.0
+fn smoothstep(t: f32) -> f32 {
+    t * t * (3.0 - 2.0 * t)
+}
+
+/// Catmull-Rom spline interpolation between `p1` and `p2`, using `p0` and `p3` as control points
+fn catmull_rom(p0: Vec2f, p1: Vec2f, p2: Vec2f, p3: Vec2f, t: f32) -> Vec2f {
+    let t2 = t * t;
+    let t3 = t2 * t;
+
+    ((p1 * 2.0)
+        + (p2 - p0) * t
+        + (p0 * 2.0 - p1 * 5.0 + p2 * 4.0 - p3) * t2
+        + (p3 - p0 + (p1 - p2) * 3.0) * t3)
+        * 0.5
+}
+
+#[derive(Clone, Copy, Debug)]
+pub struct CameraKeyframe {
+    /// Time, in seconds, from the start of the path
+    pub time: f32,
+    pub position: Vec2f,
+    pub z: f32,
+    pub yaw: f32,
+    pub pitch: f32,
+}
+
+impl CameraKeyframe {
+    pub fn new(time: f32, position: Vec2f, z: f32, yaw: f32, pitch: f32) -> Self {
+        Self {
+            time,
+            position,
+            z,
+            yaw,
+            pitch,
+        }
+    }
+}
+
+/// A scripted camera path, defined by keyframes that are sorted by time. Position follows a smooth
+/// spline through each keyframe, whilst rotation is eased between them.
+#[derive(Clone, Debug)]
+pub struct CameraPath {
+    keyframes: Vec<CameraKeyframe>,
+    pub looping: bool,
+}
+
+impl CameraPath {
+    pub fn new(mut keyframes: Vec<CameraKeyframe>, looping: bool) -> Self {
+        debug_assert!(
+            !keyframes.is_empty(),
+            "Camera path must have at least one keyframe"
+        );
+        keyframes.sort_by(|a, b| a.time.total_cmp(&b.time));
+
+        Self { keyframes, looping }
+    }
+
+    pub fn duration(&self) -> f32 {
+        self.keyframes.last().map_or(0.0, |keyframe| keyframe.time)
+    }
+
+    pub fn is_finished(&self, time: f32) -> bool {
+        !self.looping && time >= self.duration()
+    }
+
+    /// Samples the path at the given time, returning the interpolated camera
+    pub fn sample(&self, time: f32) -> Camera {
+        let duration = self.duration();
+        let time = if self.looping && duration > 0.0 {
+            time.rem_euclid(duration)
+        } else {
+            time.clamp(0.0, duration)
+        };
+
+        // Index of the first keyframe that is after the given time
+        let next = self
+            .keyframes
+            .iter()
+            .position(|keyframe| keyframe.time > time)
+            .unwrap_or(self.keyframes.len() - 1)
+            .max(1)
+            .min(self.keyframes.len() - 1);
+        let prev = next.saturating_sub(1);
+
+        let k1 = &self.keyframes[prev];
+        let k2 = &self.keyframes[next];
+        let k0 = &self.keyframes[prev.saturating_sub(1)];
+        let k3 = &self.keyframes[(next + 1).min(self.keyframes.len() - 1)];
+
+        let span = k2.time - k1.time;
+        let t = if span > 0.0 {
+            ((time - k1.time) / span).clamp(0.0, 1.0)
+        } else {
+            1.0
+        };
+
+        let position = catmull_rom(k0.position, k1.position, k2.position, k3.position, t);
+        let eased = smoothstep(t);
+
+        let mut camera = Camera::new(position, k1.z + (k2.z - k1.z) * eased);
+        camera.set_rotation(
+            lerp_angle(k1.yaw, k2.yaw, eased),
+            k1.pitch + (k2.pitch - k1.pitch) * eased,
+        );
+
+        camera
+    }
 }
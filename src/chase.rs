@@ -0,0 +1,63 @@
+//! Third-person camera that follows the player from behind and above, for watching how they move
+//! and collide, or for playing from a different point of view
+
+use crate::{
+    camera::Camera,
+    map::{self, MapIndex},
+    surface::{Sector, Wall},
+};
+
+/// How far behind the player the camera sits, when there is room
+const DISTANCE: f32 = 40.0;
+/// How far above the player's eyes the camera sits, when there is room
+const HEIGHT: f32 = 10.0;
+/// Distance kept between the camera and any wall behind it, so the near plane doesn't cut into it
+const WALL_MARGIN: f32 = 2.0;
+/// Distance kept between the camera and the floor and ceiling
+const PLANE_MARGIN: f32 = 1.0;
+
+/// Places the camera behind the player, given the player's own camera and sector, and returns it
+/// along with the sector it ended up in. The camera is pulled in towards the player wherever a
+/// wall, or a portal too low or high to fit through, is in the way, so it never leaves the map.
+pub fn follow(
+    player: &Camera,
+    sector_index: usize,
+    sectors: &[Sector],
+    map_index: &MapIndex,
+) -> (Camera, usize) {
+    let from = player.position;
+    let to = from - player.direction * DISTANCE;
+    let z = player.z + HEIGHT;
+
+    let blocked = |wall: &Wall| match wall.portal {
+        Some(portal) if portal.visible => {
+            let (bottom, top) = portal.next_heights(&sectors[portal.sector]);
+            z - PLANE_MARGIN <= bottom || z + PLANE_MARGIN >= top
+        }
+        _ => true,
+    };
+    let distance = match map::ray_cast(sectors, sector_index, from, to, blocked) {
+        Some(hit) => (hit.t * DISTANCE - WALL_MARGIN).max(0.0),
+        None => DISTANCE,
+    };
+
+    let position = from - player.direction * distance;
+    let sector_index = map_index
+        .find_sector_near(position, sectors, sector_index)
+        .unwrap_or(sector_index);
+
+    // Kept between the floor and ceiling of wherever it ended up, which may be lower than the
+    // player's own ceiling
+    let sector = &sectors[sector_index];
+    let z = z
+        .min(sector.ceiling.height - PLANE_MARGIN)
+        .max(sector.floor.height + PLANE_MARGIN);
+
+    // Tilted down to keep the player in view, by however far above them the camera ended up
+    let tilt = (z - player.z).atan2(distance.max(WALL_MARGIN));
+
+    let mut camera = Camera::new(position, z);
+    camera.set_rotation(player.yaw, player.pitch - tilt);
+
+    (camera, sector_index)
+}
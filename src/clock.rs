@@ -0,0 +1,92 @@
+//! Time as the world sees it, which unlike the real time kept by [Timer](crate::timer::Timer) can
+//! be paused or slowed down. Everything that moves or animates in the world goes by this clock, so
+//! that it all freezes and slows together.
+
+use crate::consts::{MAX_TIME_SCALE, MIN_TIME_SCALE};
+
+#[derive(Debug, Clone)]
+pub struct GameClock {
+    /// Seconds that have passed in the world, which stops whilst paused
+    time: f32,
+    /// Rate at which time passes in the world, relative to real time
+    scale: f32,
+    paused: bool,
+}
+
+impl Default for GameClock {
+    fn default() -> Self {
+        Self {
+            time: 0.0,
+            scale: 1.0,
+            paused: false,
+        }
+    }
+}
+
+impl GameClock {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Moves the clock on by the real time since the last tick, in seconds, and returns how much
+    /// time passed in the world, which is none whilst paused
+    pub fn tick(&mut self, real_seconds: f32) -> f32 {
+        let delta = match self.paused {
+            true => 0.0,
+            false => real_seconds * self.scale,
+        };
+        self.time += delta;
+
+        delta
+    }
+
+    /// Seconds that have passed in the world
+    pub fn time(&self) -> f32 {
+        self.time
+    }
+
+    /// Sets the rate at which time passes in the world, where below 1 is slow motion, within the
+    /// range it can be set to. Returns the rate that was set, or nothing if it isn't a finite
+    /// number, leaving the rate as it was.
+    pub fn set_scale(&mut self, scale: f32) -> Option<f32> {
+        if !scale.is_finite() {
+            return None;
+        }
+
+        self.scale = scale.clamp(MIN_TIME_SCALE, MAX_TIME_SCALE);
+        Some(self.scale)
+    }
+
+    pub fn is_paused(&self) -> bool {
+        self.paused
+    }
+
+    pub fn set_paused(&mut self, paused: bool) {
+        self.paused = paused;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn game_clock_test() {
+        let mut clock = GameClock::new();
+        assert_eq!(clock.tick(0.5), 0.5);
+
+        // Slow motion stretches real time out, down to the slowest it can go
+        assert_eq!(clock.set_scale(0.5), Some(0.5));
+        assert_eq!(clock.tick(0.5), 0.25);
+        assert_eq!(clock.set_scale(0.0), Some(MIN_TIME_SCALE));
+        assert_eq!(clock.set_scale(f32::NAN), None);
+        assert_eq!(clock.set_scale(f32::INFINITY), None);
+
+        // Whilst paused, no time passes in the world at all
+        clock.set_paused(true);
+        assert_eq!(clock.tick(1.0), 0.0);
+        assert_eq!(clock.time(), 0.75);
+        clock.set_paused(false);
+        assert!(clock.tick(1.0) > 0.0);
+    }
+}
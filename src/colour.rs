@@ -1,3 +1,70 @@
+use std::sync::LazyLock;
+
+/// Largest value of a channel in linear light, as returned by [srgb_to_linear]. This is more
+/// precise than 8 bits, as dark sRGB values are spread across a narrow range of linear values.
+pub const LINEAR_MAX: u16 = 4095;
+
+static SRGB_TO_LINEAR: LazyLock<[u16; 256]> = LazyLock::new(|| {
+    core::array::from_fn(|i| {
+        let srgb = i as f32 / 255.0;
+        let linear = if srgb <= 0.04045 {
+            srgb / 12.92
+        } else {
+            ((srgb + 0.055) / 1.055).powf(2.4)
+        };
+
+        (linear * LINEAR_MAX as f32).round() as u16
+    })
+});
+
+static LINEAR_TO_SRGB: LazyLock<[u8; LINEAR_MAX as usize + 1]> = LazyLock::new(|| {
+    core::array::from_fn(|i| {
+        let linear = i as f32 / LINEAR_MAX as f32;
+        let srgb = if linear <= 0.0031308 {
+            linear * 12.92
+        } else {
+            1.055 * linear.powf(1.0 / 2.4) - 0.055
+        };
+
+        (srgb * 255.0).round() as u8
+    })
+});
+
+/// Converts an sRGB encoded channel into linear light, between 0 and [LINEAR_MAX]
+pub fn srgb_to_linear(srgb: u8) -> u16 {
+    SRGB_TO_LINEAR[srgb as usize]
+}
+
+/// Converts a channel in linear light, between 0 and [LINEAR_MAX], back into sRGB encoding
+pub fn linear_to_srgb(linear: u16) -> u8 {
+    LINEAR_TO_SRGB[linear.min(LINEAR_MAX) as usize]
+}
+
+/// How a colour is combined with the colour beneath it. In every mode, the alpha of the colour on
+/// top is the strength of the effect, so an alpha of zero leaves the colour beneath unchanged.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum BlendMode {
+    /// Covers the colour beneath, e.g. for tints
+    Alpha,
+    /// Adds to the colour beneath, saturating, so it can only brighten (e.g. for flashes and glows)
+    Add,
+    /// Scales the colour beneath, so it can only darken (e.g. for shadows and tinted glass)
+    Multiply,
+    /// Inverse of multiplying the inverses of both colours, which brightens like adding but
+    /// approaches white gradually rather than clipping
+    Screen,
+}
+
+/// Multiplies two values where 0xFF is 1.0, rounding to the nearest
+fn mul_unit(a: u8, b: u8) -> u8 {
+    ((a as u32 * b as u32 + 0x7F) / 0xFF) as u8
+}
+
+/// Interpolates from one value to another, where `t` of 0xFF is all the way
+fn lerp_unit(from: u8, to: u8, t: u8) -> u8 {
+    mul_unit(from, 0xFF - t) + mul_unit(to, t)
+}
+
 #[repr(C)]
 #[derive(Clone, Copy, Debug, Default)]
 pub struct BGRA8 {
@@ -123,9 +190,9 @@ impl BGRA8 {
 
     /// Multiplies the colour by the given value (where 0xFF is 1.0), and saturates the result.
     pub fn lighten(self, l: usize) -> Self {
-        let r = ((self.r as usize * l) >> 8).max(0xFF);
-        let g = ((self.g as usize * l) >> 8).max(0xFF);
-        let b = ((self.b as usize * l) >> 8).max(0xFF);
+        let r = ((self.r as usize * l) >> 8).min(0xFF);
+        let g = ((self.g as usize * l) >> 8).min(0xFF);
+        let b = ((self.b as usize * l) >> 8).min(0xFF);
 
         Self {
             b: b as u8,
@@ -135,6 +202,64 @@ impl BGRA8 {
         }
     }
 
+    /// Adds this colour, scaled by its alpha, to the other colour, saturating each channel
+    pub fn additive(self, other: Self) -> Self {
+        let add = |top: u8, beneath: u8| beneath.saturating_add(mul_unit(top, self.a));
+
+        Self {
+            b: add(self.b, other.b),
+            g: add(self.g, other.g),
+            r: add(self.r, other.r),
+            a: 0xFF,
+        }
+    }
+
+    /// Multiplies the other colour by this colour, with this colour's alpha as strength
+    pub fn multiply(self, other: Self) -> Self {
+        let multiply = |top: u8, beneath: u8| lerp_unit(beneath, mul_unit(top, beneath), self.a);
+
+        Self {
+            b: multiply(self.b, other.b),
+            g: multiply(self.g, other.g),
+            r: multiply(self.r, other.r),
+            a: 0xFF,
+        }
+    }
+
+    /// Screens the other colour with this colour, with this colour's alpha as strength
+    pub fn screen(self, other: Self) -> Self {
+        let screen = |top: u8, beneath: u8| {
+            lerp_unit(beneath, 0xFF - mul_unit(0xFF - top, 0xFF - beneath), self.a)
+        };
+
+        Self {
+            b: screen(self.b, other.b),
+            g: screen(self.g, other.g),
+            r: screen(self.r, other.r),
+            a: 0xFF,
+        }
+    }
+
+    /// Combines this colour with the other colour beneath it, using the given mode
+    pub fn composite(self, other: Self, mode: BlendMode) -> Self {
+        match mode {
+            BlendMode::Alpha => self.blend(other),
+            BlendMode::Add => self.additive(other),
+            BlendMode::Multiply => self.multiply(other),
+            BlendMode::Screen => self.screen(other),
+        }
+    }
+
+    /// Interpolates from this colour to the other, including alpha, where `t` of 0xFF is all the way
+    pub fn lerp(self, other: Self, t: u8) -> Self {
+        Self {
+            b: lerp_unit(self.b, other.b, t),
+            g: lerp_unit(self.g, other.g, t),
+            r: lerp_unit(self.r, other.r, t),
+            a: lerp_unit(self.a, other.a, t),
+        }
+    }
+
     pub fn as_u32(self) -> u32 {
         unsafe { core::mem::transmute(self) }
     }
@@ -0,0 +1,305 @@
+//! Drop-down developer console, for running commands whilst the game is running
+
+use collections::RingBuffer;
+use input::Input;
+use window::event::KeyCode;
+
+use crate::{
+    colour::BGRA8,
+    font::{AlignHeight, AlignWidth, Font},
+    renderer::{Rect, Renderer},
+};
+
+/// Number of lines of output that are kept, including those scrolled off the top
+const OUTPUT_LINES: usize = 128;
+const MAX_HISTORY: usize = 32;
+/// Height of the console, relative to the height of the screen
+const HEIGHT: f32 = 0.5;
+const BACKGROUND: BGRA8 = BGRA8 {
+    b: 0x18,
+    g: 0x10,
+    r: 0x10,
+    a: 0xD0,
+};
+const TEXT_COLOUR: BGRA8 = BGRA8::WHITE;
+const INPUT_COLOUR: BGRA8 = BGRA8::YELLOW;
+
+/// Runs a command with its arguments, returning any output or an error message
+pub type CommandHandler<C> = fn(&mut C, &[&str]) -> Result<String, String>;
+
+pub struct Command<C> {
+    pub name: &'static str,
+    /// Arguments taken by the command, e.g. `<x> <y>`
+    pub usage: &'static str,
+    pub help: &'static str,
+    pub handler: CommandHandler<C>,
+}
+
+/// A console for typing commands into, which run on a context of type `C`
+pub struct Console<C> {
+    open: bool,
+    line: String,
+    output: RingBuffer<String, OUTPUT_LINES>,
+    history: Vec<String>,
+    /// Entry of the history being shown on the input line, when browsing it
+    history_index: Option<usize>,
+    commands: Vec<Command<C>>,
+}
+
+impl<C> Console<C> {
+    pub fn new() -> Self {
+        Self {
+            open: false,
+            line: String::new(),
+            output: RingBuffer::new(),
+            history: Vec::new(),
+            history_index: None,
+            commands: Vec::new(),
+        }
+    }
+
+    /// Adds a command, replacing any existing command with the same name
+    pub fn register(
+        &mut self,
+        name: &'static str,
+        usage: &'static str,
+        help: &'static str,
+        handler: CommandHandler<C>,
+    ) {
+        let command = Command {
+            name,
+            usage,
+            help,
+            handler,
+        };
+
+        match self
+            .commands
+            .iter_mut()
+            .find(|command| command.name == name)
+        {
+            Some(existing) => *existing = command,
+            None => self.commands.push(command),
+        }
+    }
+
+    pub fn commands(&self) -> &[Command<C>] {
+        &self.commands
+    }
+
+    pub fn is_open(&self) -> bool {
+        self.open
+    }
+
+    pub fn set_open(&mut self, open: bool) {
+        self.open = open;
+    }
+
+    /// Adds text to the output, which may span several lines
+    pub fn print(&mut self, text: &str) {
+        for line in text.lines() {
+            self.output.push(line.to_owned());
+        }
+    }
+
+    pub fn clear(&mut self) {
+        self.output.clear();
+    }
+
+    /// Handles typing and editing of the input line whilst open, returning a line once it has been
+    /// submitted
+    pub fn update(&mut self, input: &Input) -> Option<String> {
+        if !self.open {
+            return None;
+        }
+
+        let keyboard = &input.keyboard;
+
+        if keyboard.is_key_pressed(KeyCode::Escape) || keyboard.is_key_pressed(KeyCode::Backquote) {
+            self.open = false;
+            return None;
+        }
+
+        // The font only covers ASCII, and the toggle key shouldn't end up in the line
+        self.line.extend(
+            input
+                .text()
+                .chars()
+                .filter(|c| c.is_ascii() && !matches!(c, '`' | '~')),
+        );
+
+        if keyboard.is_key_pressed(KeyCode::Backspace) {
+            self.line.pop();
+        }
+
+        if keyboard.is_key_pressed(KeyCode::Tab) {
+            self.complete();
+        }
+
+        if keyboard.is_key_pressed(KeyCode::ArrowUp) {
+            self.browse_history(-1);
+        } else if keyboard.is_key_pressed(KeyCode::ArrowDown) {
+            self.browse_history(1);
+        }
+
+        if keyboard.is_key_pressed(KeyCode::Enter) || keyboard.is_key_pressed(KeyCode::NumpadEnter)
+        {
+            return self.submit();
+        }
+
+        None
+    }
+
+    /// Clears the input line, adding it to the history and echoing it to the output
+    fn submit(&mut self) -> Option<String> {
+        let line = core::mem::take(&mut self.line);
+        self.history_index = None;
+
+        let line = line.trim().to_owned();
+        if line.is_empty() {
+            return None;
+        }
+
+        self.print(&format!("> {line}"));
+
+        if self.history.last() != Some(&line) {
+            if self.history.len() == MAX_HISTORY {
+                self.history.remove(0);
+            }
+            self.history.push(line.clone());
+        }
+
+        Some(line)
+    }
+
+    /// Runs a submitted line on the context, printing its output. The console must be reachable
+    /// from the context, so that commands can also use the console.
+    pub fn execute(context: &mut C, line: &str, console: fn(&mut C) -> &mut Console<C>) {
+        let mut args = line.split_whitespace();
+        let Some(name) = args.next() else {
+            return;
+        };
+        let args: Vec<&str> = args.collect();
+
+        let handler = console(context)
+            .commands
+            .iter()
+            .find(|command| command.name == name)
+            .map(|command| command.handler);
+
+        let output = match handler {
+            Some(handler) => {
+                handler(context, &args).unwrap_or_else(|error| format!("Error: {error}"))
+            }
+            None => format!("Unknown command '{name}', type 'help' for a list of commands"),
+        };
+
+        console(context).print(&output);
+    }
+
+    /// Steps through previously submitted lines, where a negative step goes back in time
+    fn browse_history(&mut self, step: isize) {
+        if self.history.is_empty() {
+            return;
+        }
+
+        let index = match self.history_index {
+            Some(index) => index as isize + step,
+            None if step < 0 => self.history.len() as isize - 1,
+            None => return,
+        };
+
+        if index < 0 {
+            return;
+        }
+
+        if index as usize >= self.history.len() {
+            // Stepped past the newest entry, so go back to an empty line
+            self.history_index = None;
+            self.line.clear();
+            return;
+        }
+
+        self.history_index = Some(index as usize);
+        self.line = self.history[index as usize].clone();
+    }
+
+    /// Completes the command name on the input line as far as it is unambiguous, listing the
+    /// candidates if there is more than one
+    fn complete(&mut self) {
+        // Only command names are completed, not their arguments
+        if self.line.contains(' ') {
+            return;
+        }
+
+        let mut candidates: Vec<&str> = self
+            .commands
+            .iter()
+            .map(|command| command.name)
+            .filter(|name| name.starts_with(self.line.as_str()))
+            .collect();
+        candidates.sort_unstable();
+
+        match candidates.as_slice() {
+            [] => {}
+            [name] => self.line = format!("{name} "),
+            [first, rest @ ..] => {
+                let common = rest.iter().fold(first.len(), |len, name| {
+                    first
+                        .bytes()
+                        .zip(name.bytes())
+                        .take(len)
+                        .take_while(|(a, b)| a == b)
+                        .count()
+                });
+                self.line = first[..common].to_owned();
+
+                let list = candidates.join("  ");
+                self.print(&list);
+            }
+        }
+    }
+
+    pub fn draw(&self, renderer: &mut Renderer, font: &Font) {
+        if !self.open {
+            return;
+        }
+
+        let screen_width = renderer.framebuffer().width();
+        let screen_height = renderer.framebuffer().height();
+        let height = (screen_height as f32 * HEIGHT) as usize;
+
+        renderer.draw_rect(BACKGROUND, Rect::new(0, 0, screen_width, height));
+
+        let line_height = font.char_height();
+        let margin = font.char_width();
+        let x = margin as f32 / screen_width as f32;
+
+        // The input line sits at the bottom, with the most recent output stacked above it
+        let mut y = height.saturating_sub(margin);
+        renderer.draw_text(
+            font,
+            INPUT_COLOUR,
+            (AlignWidth::Left, AlignHeight::Bottom),
+            x,
+            y as f32 / screen_height as f32,
+            &format!("> {}_", self.line),
+        );
+
+        for line in self.output.iter().rev() {
+            if y < line_height * 2 {
+                break;
+            }
+            y -= line_height;
+
+            renderer.draw_text(
+                font,
+                TEXT_COLOUR,
+                (AlignWidth::Left, AlignHeight::Bottom),
+                x,
+                y as f32 / screen_height as f32,
+                line,
+            );
+        }
+    }
+}
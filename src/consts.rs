@@ -4,14 +4,43 @@
 pub const INTERNAL_WIDTH: usize = 640;
 pub const INTERNAL_HEIGHT: usize = 400;
 pub const HFOV: f32 = 75.0;
+/// Range that the horizontal field of view can be adjusted within at runtime, in degrees
+pub const MIN_HFOV: f32 = 30.0;
+pub const MAX_HFOV: f32 = 150.0;
+/// Rate at which the field of view changes whilst adjusting it, in degrees per second
+pub const FOV_SPEED: f32 = 30.0;
+/// Range of the resolution that the world is drawn at, relative to the internal resolution. Below
+/// 1 it is scaled up, and above 1 it is averaged down for supersampling.
+pub const MIN_RENDER_SCALE: f32 = 0.5;
+pub const MAX_RENDER_SCALE: f32 = 2.0;
+/// Time that drawing the world may take before adaptive resolution lowers the render scale, in
+/// milliseconds, when turned on without saying
+pub const ADAPTIVE_BUDGET_MS: f32 = 6.0;
+/// Amount that adaptive resolution changes the render scale by at a time
+pub const ADAPTIVE_STEP: f32 = 0.1;
+/// Fraction of the budget that frames must take less than before the render scale is raised again.
+/// A step up from the lowest scale costs almost half as much again, so this is well below that.
+pub const ADAPTIVE_HEADROOM: f32 = 0.6;
+/// Weight given to each frame's time in the running average that adaptive resolution goes by
+pub const ADAPTIVE_SMOOTHING: f32 = 0.1;
+/// Frames to wait after changing the render scale before changing it again
+pub const ADAPTIVE_COOLDOWN_FRAMES: usize = 30;
 pub const FPS: f32 = 120.0;
+/// Strength of the CRT output filter, where 1 is its default look
+pub const CRT_INTENSITY: f32 = 1.0;
+/// Frames rendered by the `bench` console command, unless told otherwise
+pub const BENCH_FRAMES: usize = 200;
 
 /*
   Render clipping planes
 */
-pub const NEAR: f32 = 0.00001;
-pub const FAR: f32 = 512.0;
-pub const MAP_DEPTH_RANGE: f32 = 1.0 / (FAR - NEAR);
+/// Distances to the clipping planes that the renderer starts with, which can be changed at runtime
+/// to suit the scale of a map. Lighting and mip selection are based on depth between the two.
+pub const DEFAULT_NEAR: f32 = 0.1;
+pub const DEFAULT_FAR: f32 = 512.0;
+/// Projected sizes, in pixels, below which walls and sprites are skipped by hardened rendering, as
+/// the gradients found by dividing by them are too steep to interpolate along
+pub const MIN_PROJECTED_SIZE: f32 = 0.0001;
 
 /*
   Texture mip mapping
@@ -25,9 +54,71 @@ pub const MIP_FACTOR: f32 = 4.0;
 /// As subsequent mip maps are smaller resolutions, we use this to scale texture coordinates
 pub const MIP_SCALES: [f32; MIP_LEVELS] = [1.0 / 1.0, 1.0 / 2.0, 1.0 / 4.0];
 
+/*
+  Gameplay
+*/
+/// Maximum distance from a door's portal at which the player can open it
+pub const USE_DISTANCE: f32 = 20.0;
+/// Number of portals that the sound of a gunshot travels through
+pub const GUNSHOT_RANGE: usize = 3;
+/// Furthest distance at which a gunshot hits a wall
+pub const GUNSHOT_DISTANCE: f32 = 1000.0;
+/// Pieces of debris thrown out when something breaks
+pub const DEBRIS_COUNT: usize = 8;
+/// Passes that the physics solver makes over the contacts between debris and props each update
+pub const SOLVER_ITERATIONS: usize = 4;
+/// Seconds between the player dying and being respawned
+pub const RESPAWN_DELAY: f32 = 2.0;
+/// Range of the rate at which time passes in the world, relative to real time, for slow motion
+pub const MIN_TIME_SCALE: f32 = 0.1;
+pub const MAX_TIME_SCALE: f32 = 2.0;
+/// Trigger of the button that opens the door from the inside
+pub const DOOR_BUTTON_TRIGGER: usize = 0;
+/// Level of light baked into surfaces that no light reaches, where 1 is as bright as without
+/// lightmaps
+pub const LIGHTMAP_AMBIENT: f32 = 0.6;
+/// Script with the demo level's logic
+pub const SCRIPT_PATH: &str = "assets/scripts/demo.rhai";
+/// Settings that players can change without rebuilding, such as how the player moves
+pub const SETTINGS_PATH: &str = "settings.cfg";
+
+/*
+  Debug
+*/
+/// How far in front of the player the console's `spawn` command places sprites
+pub const SPAWN_DISTANCE: f32 = 20.0;
+/// How far ahead the debug text looks for the wall being aimed at
+pub const DEBUG_AIM_DISTANCE: f32 = 1000.0;
+/// Distance at which sector labels are drawn at normal size whilst debugging, growing nearer
+pub const DEBUG_LABEL_DEPTH: f32 = 40.0;
+/// Number of the most recent gameplay events kept, for the console's `events` command
+pub const EVENT_HISTORY: usize = 256;
+/// How many events the console's `events` command lists, unless told otherwise
+pub const EVENTS_LISTED: usize = 10;
+/// Where the top-down view of the map is written when exported
+pub const MAP_EXPORT_PATH: &str = "map.svg";
+/// How many of the slowest assets the console's `assets` command lists
+pub const SLOWEST_ASSETS_LISTED: usize = 5;
+/// Where the timings recorded with the `profile` feature are written on exit
+#[cfg(feature = "profile")]
+pub const PROFILE_TRACE_PATH: &str = "profile.json";
+/// Most scopes that each thread keeps whilst profiling, after which the oldest are dropped, so
+/// that the trace covers the most recent frames however long the game has run
+#[cfg(feature = "profile")]
+pub const PROFILE_EVENT_LIMIT: usize = 1 << 16;
+/// Number of the most recent frames recorded to step back through, ten seconds at the frame cap
+pub const REWIND_FRAMES: usize = 1200;
+
 /*
   Textures
 */
+/// Memory that streamed textures may take by default before the least recently used are unloaded
+pub const TEXTURE_BUDGET: usize = 64 * 1024 * 1024;
+/// Texels at which the offsets of scrolling textures wrap around, so that they don't grow until
+/// they lose precision. Textures are sized in powers of two, so this is a whole number of times
+/// the size of any texture no larger than it, and wrapping by it doesn't move the texture.
+pub const TEXTURE_SCROLL_WRAP: f32 = 4096.0;
+
 // Tile texture paths
 pub const TEXTURE_TILE_PATHS: [&str; 13] = [
     "assets/textures/tile/placeholder.png",
@@ -44,7 +135,40 @@ pub const TEXTURE_TILE_PATHS: [&str; 13] = [
     "assets/textures/tile/obsidian.png",
     "assets/textures/tile/portal.png",
 ];
-pub const TEXTURE_SPRITE_PATHS: [&str; 1] = ["assets/textures/entity/goblin.png"];
+pub const TEXTURE_SPRITE_PATHS: [&str; 21] = [
+    "assets/textures/entity/goblin.png",
+    "assets/textures/entity/goblin_pain.png",
+    "assets/textures/entity/goblin_dead.png",
+    "assets/textures/entity/archer.png",
+    "assets/textures/entity/archer_pain.png",
+    "assets/textures/entity/archer_dead.png",
+    "assets/textures/entity/shaman.png",
+    "assets/textures/entity/shaman_pain.png",
+    "assets/textures/entity/shaman_dead.png",
+    "assets/textures/entity/health.png",
+    "assets/textures/entity/armour.png",
+    "assets/textures/entity/ammo.png",
+    "assets/textures/entity/key_red.png",
+    "assets/textures/entity/key_blue.png",
+    "assets/textures/entity/key_yellow.png",
+    "assets/textures/entity/pistol.png",
+    "assets/textures/entity/shotgun.png",
+    "assets/textures/entity/crossbow.png",
+    "assets/textures/entity/bolt.png",
+    "assets/textures/entity/light_cone.png",
+    "assets/textures/entity/player.png",
+];
+pub const TEXTURE_HUD_PATHS: [&str; 9] = [
+    "assets/textures/hud/status_bar.png",
+    "assets/textures/weapon/fist.png",
+    "assets/textures/weapon/fist_punch.png",
+    "assets/textures/weapon/pistol.png",
+    "assets/textures/weapon/pistol_fire.png",
+    "assets/textures/weapon/shotgun.png",
+    "assets/textures/weapon/shotgun_fire.png",
+    "assets/textures/weapon/crossbow.png",
+    "assets/textures/weapon/crossbow_fire.png",
+];
 
 // Tile texture indices
 pub const PLACEHOLDER: usize = 0;
@@ -63,6 +187,37 @@ pub const PORTAL: usize = 12;
 
 // Sprite texture indices
 pub const GOBLIN: usize = 13;
+pub const GOBLIN_PAIN: usize = 14;
+pub const GOBLIN_DEAD: usize = 15;
+pub const ARCHER: usize = 16;
+pub const ARCHER_PAIN: usize = 17;
+pub const ARCHER_DEAD: usize = 18;
+pub const SHAMAN: usize = 19;
+pub const SHAMAN_PAIN: usize = 20;
+pub const SHAMAN_DEAD: usize = 21;
+pub const HEALTH: usize = 22;
+pub const ARMOUR: usize = 23;
+pub const AMMO: usize = 24;
+pub const KEY_RED: usize = 25;
+pub const KEY_BLUE: usize = 26;
+pub const KEY_YELLOW: usize = 27;
+pub const PISTOL: usize = 28;
+pub const SHOTGUN: usize = 29;
+pub const CROSSBOW: usize = 30;
+pub const BOLT: usize = 31;
+pub const LIGHT_CONE: usize = 32;
+pub const PLAYER: usize = 33;
+
+// HUD texture indices
+pub const STATUS_BAR: usize = 34;
+pub const FIST_IDLE: usize = 35;
+pub const FIST_FIRING: usize = 36;
+pub const PISTOL_IDLE: usize = 37;
+pub const PISTOL_FIRING: usize = 38;
+pub const SHOTGUN_IDLE: usize = 39;
+pub const SHOTGUN_FIRING: usize = 40;
+pub const CROSSBOW_IDLE: usize = 41;
+pub const CROSSBOW_FIRING: usize = 42;
 
 /*
   Fonts
@@ -79,3 +234,4 @@ pub const FONT_SIZES: [(usize, usize); 3] = [(5, 9), (6, 9), (8, 12)];
 
 // Font indices
 pub const FONT_DEFAULT: usize = 0;
+pub const FONT_LARGE: usize = 2;
@@ -0,0 +1,183 @@
+//! Aiming reticle at the centre of the main view, which briefly expands when a shot lands
+
+use std::time::{Duration, Instant};
+
+use crate::{colour::BGRA8, renderer::RendererState};
+
+/// How long the crosshair stays expanded after a hit, shrinking back as it fades
+const HIT_DURATION: Duration = Duration::from_millis(250);
+/// Extra space opened up at the centre of the crosshair at the moment of a hit, in pixels
+const HIT_SPREAD: f32 = 3.0;
+/// Largest that the size and the gap may each be, relative to the smaller side of the framebuffer
+const MAX_EXTENT: f32 = 0.25;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum CrosshairStyle {
+    /// Horizontal and vertical arms
+    Cross,
+    /// Arms pointing out diagonally, like an X
+    Diagonal,
+    /// A square dot
+    Dot,
+    /// A ring
+    Circle,
+}
+
+impl core::str::FromStr for CrosshairStyle {
+    type Err = ();
+
+    fn from_str(name: &str) -> Result<Self, Self::Err> {
+        match name {
+            "cross" => Ok(CrosshairStyle::Cross),
+            "diagonal" => Ok(CrosshairStyle::Diagonal),
+            "dot" => Ok(CrosshairStyle::Dot),
+            "circle" => Ok(CrosshairStyle::Circle),
+            _ => Err(()),
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug)]
+pub struct CrosshairConfig {
+    pub style: CrosshairStyle,
+    pub colour: BGRA8,
+    /// Colour shown at the moment of a hit, fading back to `colour`
+    pub hit_colour: BGRA8,
+    /// Length of each arm, or the half-width of a dot or ring, in pixels, not counting the gap
+    pub size: usize,
+    /// Pixels left clear either side of the centre. Without a gap, arms meet at a centre pixel.
+    pub gap: usize,
+}
+
+impl Default for CrosshairConfig {
+    fn default() -> Self {
+        Self {
+            style: CrosshairStyle::Cross,
+            colour: BGRA8 {
+                b: 255,
+                g: 255,
+                r: 255,
+                a: 160,
+            },
+            hit_colour: BGRA8 {
+                b: 0,
+                g: 0,
+                r: 255,
+                a: 224,
+            },
+            size: 2,
+            gap: 0,
+        }
+    }
+}
+
+impl CrosshairConfig {
+    /// Limits the size and the gap to a fraction of a framebuffer of the given size, so that the
+    /// crosshair can't be made too large to draw each frame
+    pub fn clamp_to(&mut self, width: usize, height: usize) {
+        let max = (width.min(height) as f32 * MAX_EXTENT) as usize;
+        self.size = self.size.min(max);
+        self.gap = self.gap.min(max);
+    }
+}
+
+pub struct Crosshair {
+    pub config: CrosshairConfig,
+    /// When a shot last landed
+    hit: Option<Instant>,
+}
+
+impl Crosshair {
+    pub fn new(config: CrosshairConfig) -> Self {
+        Self { config, hit: None }
+    }
+
+    /// Flashes the hit colour and spreads the crosshair out, both settling back over a short time
+    pub fn hit(&mut self) {
+        self.hit = Some(Instant::now());
+    }
+
+    /// How much of the most recent hit's effect remains, from 1 at the moment of the hit to 0
+    fn hit_remaining(&self) -> f32 {
+        self.hit.map_or(0.0, |hit| {
+            (1.0 - hit.elapsed().as_secs_f32() / HIT_DURATION.as_secs_f32()).max(0.0)
+        })
+    }
+
+    /// Draws the crosshair at the centre of the framebuffer, blended over what is there. Frames drawn
+    /// offscreen, such as for monitors, are left alone.
+    pub fn draw(&self, state: &mut RendererState) {
+        if state.offscreen {
+            return;
+        }
+
+        let remaining = self.hit_remaining();
+        let colour = self
+            .config
+            .colour
+            .lerp(self.config.hit_colour, (remaining * 255.0) as u8);
+        let gap = self.config.gap + (HIT_SPREAD * remaining).round() as usize;
+        let size = self.config.size;
+
+        let centre_x = (state.framebuffer.width() / 2) as isize;
+        let centre_y = (state.framebuffer.height() / 2) as isize;
+        let mut plot = |dx: isize, dy: isize| {
+            let x = centre_x + dx;
+            let y = centre_y + dy;
+            if x >= 0
+                && y >= 0
+                && (x as usize) < state.framebuffer.width()
+                && (y as usize) < state.framebuffer.height()
+            {
+                state
+                    .framebuffer
+                    .blend_pixel(x as usize, y as usize, colour);
+            }
+        };
+
+        let inner = gap as isize + 1;
+        let outer = (gap + size) as isize;
+
+        match self.config.style {
+            CrosshairStyle::Cross | CrosshairStyle::Diagonal => {
+                if gap == 0 {
+                    plot(0, 0);
+                }
+
+                let diagonal = self.config.style == CrosshairStyle::Diagonal;
+                for d in inner..=outer {
+                    if diagonal {
+                        plot(d, d);
+                        plot(-d, d);
+                        plot(d, -d);
+                        plot(-d, -d);
+                    } else {
+                        plot(d, 0);
+                        plot(-d, 0);
+                        plot(0, d);
+                        plot(0, -d);
+                    }
+                }
+            }
+            CrosshairStyle::Dot => {
+                // Spreading grows the dot rather than hollowing it out
+                for dy in -outer..=outer {
+                    for dx in -outer..=outer {
+                        plot(dx, dy);
+                    }
+                }
+            }
+            CrosshairStyle::Circle => {
+                let radius = outer as f32;
+                for dy in -outer..=outer {
+                    for dx in -outer..=outer {
+                        let distance = ((dx * dx + dy * dy) as f32).sqrt();
+                        if (distance - radius).abs() < 0.5 {
+                            plot(dx, dy);
+                        }
+                    }
+                }
+            }
+        }
+    }
+}
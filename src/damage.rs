@@ -0,0 +1,86 @@
+//! Damage dealt to the player, and how much of it their armour soaks up
+
+use maths::linear::Vec2f;
+
+use crate::{colour::BGRA8, hazard::HazardKind};
+
+/// Seconds after being hurt during which further damage is ignored
+pub const INVULNERABILITY_DURATION: f32 = 0.25;
+
+/// What dealt the damage, which decides how well armour protects against it
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum DamageKind {
+    Melee,
+    /// Gunshots and projectiles
+    Ballistic,
+    Burn,
+    Acid,
+}
+
+impl DamageKind {
+    /// Fraction of the damage that armour absorbs whilst it lasts
+    pub fn armour_absorption(&self) -> f32 {
+        match self {
+            DamageKind::Melee => 1.0 / 3.0,
+            DamageKind::Ballistic => 0.5,
+            DamageKind::Burn => 0.25,
+            // Eats straight through armour
+            DamageKind::Acid => 0.0,
+        }
+    }
+
+    /// Colour that the screen flashes when hurt, using the alpha as the strength of a heavy hit
+    pub fn flash_colour(&self) -> BGRA8 {
+        match self {
+            DamageKind::Melee | DamageKind::Ballistic => BGRA8::new(255, 0, 0, 128),
+            DamageKind::Burn => BGRA8::new(255, 64, 0, 112),
+            DamageKind::Acid => BGRA8::new(64, 224, 32, 96),
+        }
+    }
+}
+
+impl core::str::FromStr for DamageKind {
+    type Err = ();
+
+    fn from_str(name: &str) -> Result<Self, Self::Err> {
+        match name {
+            "melee" => Ok(DamageKind::Melee),
+            "ballistic" => Ok(DamageKind::Ballistic),
+            "burn" => Ok(DamageKind::Burn),
+            "acid" => Ok(DamageKind::Acid),
+            _ => Err(()),
+        }
+    }
+}
+
+impl From<HazardKind> for DamageKind {
+    fn from(kind: HazardKind) -> Self {
+        match kind {
+            HazardKind::Lava => DamageKind::Burn,
+            HazardKind::Slime => DamageKind::Acid,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Damage {
+    pub amount: u32,
+    pub kind: DamageKind,
+    /// Where the damage came from, if anywhere in particular
+    pub source: Option<Vec2f>,
+}
+
+impl Damage {
+    pub fn new(amount: u32, kind: DamageKind) -> Self {
+        Self {
+            amount,
+            kind,
+            source: None,
+        }
+    }
+
+    pub fn with_source(mut self, source: Vec2f) -> Self {
+        self.source = Some(source);
+        self
+    }
+}
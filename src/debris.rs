@@ -0,0 +1,112 @@
+use std::f32::consts::PI;
+
+use maths::{geometry::Circle, linear::Vec2f};
+use physics::{Collider, RigidBody};
+
+use crate::{
+    fall::Fall,
+    surface::{Sector, Sprite, WallTexture},
+};
+
+/// Width and height of each piece of debris
+const DEBRIS_SIZE: f32 = 3.0;
+/// Speed at which debris is thrown out, in units per second
+const DEBRIS_SPEED: f32 = 40.0;
+/// Fraction of its speed that debris loses each second, as it skids along the floor
+const DEBRIS_FRICTION: f32 = 0.9;
+/// Upward speed at which debris is thrown out, in units per second
+const DEBRIS_LAUNCH_SPEED: f32 = 60.0;
+/// Fraction of its speed that debris keeps when it bounces off the floor or ceiling
+const DEBRIS_BOUNCE: f32 = 0.4;
+/// Seconds before debris disappears
+const DEBRIS_LIFETIME: f32 = 3.0;
+/// Mass of each piece of debris, which only matters against other debris, as props don't move
+const DEBRIS_MASS: f32 = 1.0;
+
+/// A small piece of something that was broken, which skids to a halt and then disappears. It is
+/// moved across the ground by the physics solver, bouncing off props and other debris.
+#[derive(Debug)]
+pub struct Debris {
+    pub sprite: Sprite,
+    pub fall: Fall,
+    body: RigidBody,
+    /// Seconds remaining before it disappears
+    lifetime: f32,
+}
+
+impl Debris {
+    /// Creates `count` pieces of debris at a point, spread across a half circle facing `direction`.
+    /// Each piece shows a different part of the texture, as if broken off of the surface.
+    pub fn burst(
+        position: Vec2f,
+        sector_index: usize,
+        direction: Vec2f,
+        texture: WallTexture,
+        count: usize,
+    ) -> impl Iterator<Item = Debris> {
+        let direction = direction.normalise();
+
+        (0..count).map(move |i| {
+            let fraction = (i as f32 + 0.5) / count as f32;
+            let angle = (fraction - 0.5) * PI;
+            let (sin, cos) = angle.sin_cos();
+            let velocity = Vec2f::new(
+                direction.x * cos - direction.y * sin,
+                direction.x * sin + direction.y * cos,
+            );
+            // Varied, so that the pieces don't land in a neat arc
+            let speed = DEBRIS_SPEED * (0.5 + (i * 7 % count) as f32 / count as f32);
+
+            let mut texture = texture;
+            texture.offset += Vec2f::uniform(i as f32 * DEBRIS_SIZE);
+
+            let collider = Collider::from_circle(Circle::new(position, DEBRIS_SIZE * 0.5));
+            let mut body =
+                RigidBody::new(collider, DEBRIS_MASS, false, i).with_restitution(DEBRIS_BOUNCE);
+            body.velocity = velocity * speed;
+
+            Debris {
+                sprite: Sprite::new(position, sector_index, texture, DEBRIS_SIZE, DEBRIS_SIZE),
+                fall: Fall::launched(DEBRIS_LAUNCH_SPEED * (1.0 - fraction * 0.5), DEBRIS_BOUNCE),
+                body,
+                lifetime: DEBRIS_LIFETIME,
+            }
+        })
+    }
+
+    pub fn body_mut(&mut self) -> &mut RigidBody {
+        &mut self.body
+    }
+
+    /// Moves the debris along with its body, such as when carried by a moving sector
+    pub fn translate(&mut self, translation: Vec2f) {
+        self.sprite.position += translation;
+        self.body.translate(translation);
+    }
+
+    /// Follows the body to where the physics solver moved it, moving between sectors through
+    /// portals and stopping at walls. Returns whether it should be kept.
+    pub fn update(&mut self, sectors: &[Sector], delta_seconds: f32) -> bool {
+        self.lifetime -= delta_seconds;
+
+        let prev_position = self.sprite.position;
+        let position = self.body.collider.centre();
+        self.body.velocity *= (1.0 - DEBRIS_FRICTION).powf(delta_seconds);
+
+        let sector = &sectors[self.sprite.sector_index];
+        if let Some(portal) = sector
+            .crossed_portal(prev_position, position)
+            .filter(|portal| portal.passable)
+        {
+            self.sprite.position = position;
+            self.sprite.sector_index = portal.sector;
+        } else if sector.contains_point(position) {
+            self.sprite.position = position;
+        } else {
+            self.body.translate(prev_position - position);
+            self.body.velocity = Vec2f::ZERO;
+        }
+
+        self.lifetime > 0.0
+    }
+}
@@ -0,0 +1,383 @@
+//! The map that the game starts in, built in code until maps can be loaded from files
+
+use std::f32::consts::PI;
+
+use maths::linear::Vec2f;
+
+use crate::{
+    colour::BGRA8,
+    consts::{CONCRETE, GRASS, LEAF, PLANK, SAND, STONE_BRICK},
+    hazard::Hazard,
+    surface::{Fog, Plane, PlaneTexture, Portal, Sector, Wall, WallTexture},
+    thing::{Thing, ThingType},
+};
+
+/// Sectors that the level refers to by index: the door, the walls with the button and the cracks,
+/// the lift, the security camera's room and those the level script watches for. These are kept
+/// as they are when merging sectors.
+pub const PINNED_SECTORS: [usize; 5] = [0, 3, 5, 6, 7];
+
+/// Sector 5 is meant to be a door between sectors 0 and 6, sector 6 has a button on its first
+/// wall, and its second wall can be broken through into sector 7, which is otherwise closed off.
+/// These are set up separately as they aren't part of the geometry.
+pub fn sectors() -> Vec<Sector> {
+    let stone_brick_wall = WallTexture::new(STONE_BRICK, Vec2f::ZERO, Vec2f::uniform(5.0));
+    let leaf_wall = WallTexture::new(LEAF, Vec2f::ZERO, Vec2f::uniform(5.0));
+    let concrete_wall = WallTexture::new(CONCRETE, Vec2f::ZERO, Vec2f::uniform(5.0));
+    let door_wall = WallTexture::new(PLANK, Vec2f::ZERO, Vec2f::uniform(5.0));
+    let grass_floor = PlaneTexture::new(GRASS, Vec2f::ZERO, Vec2f::uniform(5.0), 0.0);
+    let wood_ceiling = PlaneTexture::new(PLANK, Vec2f::ZERO, Vec2f::uniform(5.0), 0.0);
+    let glass_tint = BGRA8::new(96, 160, 192, 64);
+    // Gaps between the columns along the 50 units that sectors 2 and 3 share, which are the same
+    // measured from either end
+    let colonnade = |sector| {
+        [0.0..12.0, 19.0..31.0, 38.0..50.0].map(|range| {
+            (
+                range,
+                Portal::new(sector, stone_brick_wall, stone_brick_wall),
+            )
+        })
+    };
+
+    vec![
+        Sector {
+            id: 0,
+            walls: vec![
+                Wall::new(
+                    Vec2f::new(80.0, 600.0),
+                    Vec2f::new(130.0, 600.0),
+                    stone_brick_wall,
+                    Some(Portal::new(5, door_wall, stone_brick_wall)),
+                ),
+                Wall::new(
+                    Vec2f::new(130.0, 600.0),
+                    Vec2f::new(130.0, 140.0),
+                    stone_brick_wall,
+                    None,
+                ),
+                Wall::new(
+                    Vec2f::new(130.0, 140.0),
+                    Vec2f::new(90.0, 140.0),
+                    stone_brick_wall,
+                    Some(Portal::new(1, stone_brick_wall, stone_brick_wall)),
+                ),
+                Wall::new(
+                    Vec2f::new(90.0, 140.0),
+                    Vec2f::new(80.0, 160.0),
+                    stone_brick_wall,
+                    Some(Portal::new(4, stone_brick_wall, stone_brick_wall)),
+                ),
+                Wall::new(
+                    Vec2f::new(80.0, 160.0),
+                    Vec2f::new(80.0, 600.0),
+                    stone_brick_wall,
+                    None,
+                ),
+            ],
+            floor: Plane::new(0.0, grass_floor),
+            ceiling: Plane::new(25.0, wood_ceiling),
+            hazard: None,
+            fog: None,
+        },
+        Sector {
+            id: 1,
+            walls: vec![
+                Wall::new(
+                    Vec2f::new(90.0, 140.0),
+                    Vec2f::new(130.0, 140.0),
+                    stone_brick_wall,
+                    Some(Portal::new(0, stone_brick_wall, stone_brick_wall)),
+                ),
+                Wall::new(
+                    Vec2f::new(130.0, 140.0),
+                    Vec2f::new(130.0, 100.0),
+                    stone_brick_wall,
+                    None,
+                ),
+                Wall::new(
+                    Vec2f::new(130.0, 100.0),
+                    Vec2f::new(80.0, 100.0),
+                    stone_brick_wall,
+                    Some(Portal::new(2, stone_brick_wall, stone_brick_wall)),
+                ),
+                Wall::new(
+                    Vec2f::new(80.0, 100.0),
+                    Vec2f::new(80.0, 130.0),
+                    stone_brick_wall,
+                    None,
+                ),
+                Wall::new(
+                    Vec2f::new(80.0, 130.0),
+                    Vec2f::new(90.0, 140.0),
+                    stone_brick_wall,
+                    Some(
+                        Portal::window(4, stone_brick_wall, stone_brick_wall, Some(glass_tint))
+                            .with_opening(12.0, 18.0),
+                    ),
+                ),
+            ],
+            floor: Plane::new(0.0, grass_floor),
+            ceiling: Plane::new(25.0, wood_ceiling),
+            hazard: None,
+            fog: None,
+        },
+        Sector {
+            id: 2,
+            // Its south wall is a colonnade looking into the slime room
+            walls: [
+                Wall::new(
+                    Vec2f::new(80.0, 100.0),
+                    Vec2f::new(130.0, 100.0),
+                    stone_brick_wall,
+                    Some(Portal::new(1, stone_brick_wall, stone_brick_wall)),
+                ),
+                Wall::new(
+                    Vec2f::new(130.0, 100.0),
+                    Vec2f::new(150.0, 80.0),
+                    stone_brick_wall,
+                    None,
+                ),
+                Wall::new(
+                    Vec2f::new(150.0, 80.0),
+                    Vec2f::new(150.0, 60.0),
+                    stone_brick_wall,
+                    None,
+                ),
+            ]
+            .into_iter()
+            .chain(Wall::with_portals(
+                Vec2f::new(150.0, 60.0),
+                Vec2f::new(60.0, 60.0),
+                stone_brick_wall,
+                &colonnade(3),
+            ))
+            .chain([
+                Wall::new(
+                    Vec2f::new(60.0, 60.0),
+                    Vec2f::new(60.0, 80.0),
+                    stone_brick_wall,
+                    None,
+                ),
+                Wall::new(
+                    Vec2f::new(60.0, 80.0),
+                    Vec2f::new(80.0, 100.0),
+                    stone_brick_wall,
+                    None,
+                ),
+            ])
+            .collect(),
+            floor: Plane::new(0.0, grass_floor),
+            ceiling: Plane::new(30.0, wood_ceiling),
+            hazard: None,
+            fog: None,
+        },
+        Sector {
+            id: 3,
+            walls: Wall::with_portals(
+                Vec2f::new(100.0, 60.0),
+                Vec2f::new(150.0, 60.0),
+                stone_brick_wall,
+                &colonnade(2),
+            )
+            .into_iter()
+            .chain([
+                Wall::new(
+                    Vec2f::new(150.0, 60.0),
+                    Vec2f::new(150.0, 30.0),
+                    stone_brick_wall,
+                    None,
+                ),
+                Wall::new(
+                    Vec2f::new(150.0, 30.0),
+                    Vec2f::new(100.0, 30.0),
+                    leaf_wall,
+                    None,
+                ),
+                Wall::new(
+                    Vec2f::new(100.0, 30.0),
+                    Vec2f::new(100.0, 60.0),
+                    stone_brick_wall,
+                    None,
+                ),
+            ])
+            .collect(),
+            floor: Plane::new(2.0, grass_floor),
+            ceiling: Plane::new(25.0, wood_ceiling),
+            hazard: Some(Hazard::slime()),
+            fog: Some(Fog::toxic()),
+        },
+        Sector {
+            id: 4,
+            walls: vec![
+                Wall::new(
+                    Vec2f::new(40.0, 160.0),
+                    Vec2f::new(80.0, 160.0),
+                    stone_brick_wall,
+                    None,
+                ),
+                Wall::new(
+                    Vec2f::new(80.0, 160.0),
+                    Vec2f::new(90.0, 140.0),
+                    stone_brick_wall,
+                    Some(Portal::new(0, stone_brick_wall, stone_brick_wall)),
+                ),
+                Wall::new(
+                    Vec2f::new(90.0, 140.0),
+                    Vec2f::new(80.0, 130.0),
+                    stone_brick_wall,
+                    Some(
+                        Portal::window(1, stone_brick_wall, stone_brick_wall, Some(glass_tint))
+                            .with_opening(12.0, 18.0),
+                    ),
+                ),
+                Wall::new(
+                    Vec2f::new(80.0, 130.0),
+                    Vec2f::new(40.0, 130.0),
+                    stone_brick_wall,
+                    None,
+                ),
+                Wall::new(
+                    Vec2f::new(40.0, 130.0),
+                    Vec2f::new(40.0, 160.0),
+                    leaf_wall.with_scroll(Vec2f::new(0.0, -4.0)),
+                    None,
+                ),
+            ],
+            floor: Plane::conveyor(
+                10.0,
+                PlaneTexture::new(SAND, Vec2f::ZERO, Vec2f::uniform(5.0), 0.0)
+                    .with_scroll(Vec2f::new(-6.0, 0.0)),
+            ),
+            ceiling: Plane::new(20.0, wood_ceiling),
+            hazard: None,
+            fog: None,
+        },
+        Sector {
+            id: 5,
+            walls: vec![
+                Wall::new(
+                    Vec2f::new(80.0, 610.0),
+                    Vec2f::new(130.0, 610.0),
+                    door_wall,
+                    Some(Portal::new(6, concrete_wall, concrete_wall)),
+                ),
+                Wall::new(
+                    Vec2f::new(130.0, 610.0),
+                    Vec2f::new(130.0, 600.0),
+                    stone_brick_wall,
+                    None,
+                ),
+                Wall::new(
+                    Vec2f::new(130.0, 600.0),
+                    Vec2f::new(80.0, 600.0),
+                    door_wall,
+                    Some(Portal::new(0, stone_brick_wall, stone_brick_wall)),
+                ),
+                Wall::new(
+                    Vec2f::new(80.0, 600.0),
+                    Vec2f::new(80.0, 610.0),
+                    stone_brick_wall,
+                    None,
+                ),
+            ],
+            floor: Plane::new(0.0, grass_floor),
+            ceiling: Plane::new(25.0, wood_ceiling),
+            hazard: None,
+            fog: None,
+        },
+        Sector {
+            id: 6,
+            walls: vec![
+                Wall::new(
+                    Vec2f::new(80.0, 660.0),
+                    Vec2f::new(130.0, 660.0),
+                    concrete_wall,
+                    None,
+                ),
+                Wall::new(
+                    Vec2f::new(130.0, 660.0),
+                    Vec2f::new(130.0, 610.0),
+                    concrete_wall,
+                    None,
+                ),
+                Wall::new(
+                    Vec2f::new(130.0, 610.0),
+                    Vec2f::new(80.0, 610.0),
+                    concrete_wall,
+                    Some(Portal::new(5, door_wall, concrete_wall)),
+                ),
+                Wall::new(
+                    Vec2f::new(80.0, 610.0),
+                    Vec2f::new(80.0, 660.0),
+                    concrete_wall,
+                    None,
+                ),
+            ],
+            floor: Plane::new(0.0, grass_floor),
+            ceiling: Plane::new(30.0, wood_ceiling),
+            hazard: Some(Hazard::lava()),
+            fog: None,
+        },
+        Sector {
+            id: 7,
+            // A rounded grotto, curving out from the lava room's wall
+            walls: Wall::arc(
+                Vec2f::new(130.0, 635.0),
+                25.0,
+                PI * 0.5,
+                PI * -0.5,
+                8,
+                concrete_wall,
+                None,
+            )
+            .into_iter()
+            .chain([Wall::new(
+                Vec2f::new(130.0, 610.0),
+                Vec2f::new(130.0, 660.0),
+                concrete_wall,
+                None,
+            )])
+            .collect(),
+            floor: Plane::new(4.0, grass_floor),
+            ceiling: Plane::new(26.0, wood_ceiling),
+            hazard: None,
+            fog: None,
+        },
+    ]
+}
+
+/// The player's start and respawn point, enemies, items and decorations placed around the map
+pub fn things() -> Vec<Thing> {
+    vec![
+        // Facing down the corridor towards sector 1
+        Thing::new(ThingType::PLAYER_START, Vec2f::new(105.0, 180.0), 0).with_angle(PI),
+        // Facing back up the corridor, for once the player has made it to sector 1
+        Thing::new(ThingType::RESPAWN_POINT, Vec2f::new(95.0, 120.0), 1),
+        Thing::new(ThingType::GOBLIN, Vec2f::new(140.0, 80.0), 2),
+        Thing::new(ThingType::GOBLIN, Vec2f::new(80.0, 80.0), 2),
+        Thing::new(ThingType::ARCHER, Vec2f::new(100.0, 65.0), 2),
+        Thing::new(ThingType::ARMOUR, Vec2f::new(95.0, 300.0), 0),
+        Thing::new(ThingType::AMMO, Vec2f::new(115.0, 350.0), 0),
+        Thing::new(ThingType::SHOTGUN, Vec2f::new(95.0, 400.0), 0),
+        Thing::new(ThingType::HEALTH, Vec2f::new(105.0, 450.0), 0),
+        Thing::new(ThingType::KEY_YELLOW, Vec2f::new(90.0, 580.0), 0),
+        Thing::new(ThingType::KEY_RED, Vec2f::new(110.0, 120.0), 1),
+        Thing::new(ThingType::KEY_BLUE, Vec2f::new(110.0, 75.0), 2),
+        Thing::new(ThingType::AMMO_BOX, Vec2f::new(105.0, 640.0), 6),
+        Thing::new(ThingType::SHAMAN, Vec2f::new(90.0, 650.0), 6),
+        Thing::new(ThingType::HEALTH_LARGE, Vec2f::new(145.0, 635.0), 7),
+        Thing::new(ThingType::CROSSBOW, Vec2f::new(140.0, 650.0), 7),
+        Thing::new(ThingType::BUSH, Vec2f::new(55.0, 145.0), 4),
+        // Obstacles along the corridor, to be walked around
+        Thing::new(ThingType::CRATE, Vec2f::new(89.0, 330.0), 0),
+        Thing::new(ThingType::CRATE, Vec2f::new(118.0, 420.0), 0).with_angle(0.4),
+        Thing::new(ThingType::PILLAR, Vec2f::new(122.0, 520.0), 0),
+        // Lamps along the corridor, casting the pillar's shadow, and in the goblins' room
+        Thing::new(ThingType::LAMP, Vec2f::new(105.0, 250.0), 0),
+        Thing::new(ThingType::LAMP, Vec2f::new(112.0, 490.0), 0),
+        Thing::new(ThingType::LAMP, Vec2f::new(110.0, 85.0), 2),
+        // Shafts of light beneath the corridor's lamps
+        Thing::new(ThingType::LIGHT_CONE, Vec2f::new(105.0, 250.0), 0),
+        Thing::new(ThingType::LIGHT_CONE, Vec2f::new(112.0, 490.0), 0),
+    ]
+}
@@ -0,0 +1,67 @@
+use crate::{
+    map::{self, WallId},
+    surface::{Portal, Sector, WallTexture},
+};
+
+/// What a destructible wall becomes once it runs out of health
+#[derive(Clone, Copy, Debug)]
+pub enum Breakage {
+    /// Stays solid, but shows a broken texture
+    Texture(WallTexture),
+    /// Opens up into a portal, usually to a sector that was hidden behind the wall
+    Portal(Portal),
+}
+
+/// A wall that breaks after taking enough damage
+#[derive(Debug)]
+pub struct DestructibleWall {
+    pub wall: WallId,
+    pub health: u32,
+    pub breakage: Breakage,
+}
+
+impl DestructibleWall {
+    pub fn new(wall: WallId, health: u32, breakage: Breakage) -> Self {
+        Self {
+            wall,
+            health,
+            breakage,
+        }
+    }
+
+    pub fn is_broken(&self) -> bool {
+        self.health == 0
+    }
+
+    /// Takes damage, breaking the wall once its health runs out. Returns whether this broke it.
+    pub fn damage(&mut self, sectors: &mut [Sector], amount: u32) -> bool {
+        if self.is_broken() {
+            return false;
+        }
+
+        self.health = self.health.saturating_sub(amount);
+        if !self.is_broken() {
+            return false;
+        }
+
+        let wall = &mut sectors[self.wall.sector_index].walls[self.wall.wall_index];
+        match self.breakage {
+            Breakage::Texture(texture) => wall.texture_data = texture,
+            Breakage::Portal(portal) => {
+                wall.portal = Some(portal);
+
+                // The hidden side may have been left solid, so that it couldn't be seen or walked
+                // through from behind either
+                if let Some(twin) = map::twin_wall(sectors, self.wall) {
+                    let twin_wall = &mut sectors[twin.sector_index].walls[twin.wall_index];
+                    twin_wall.portal.get_or_insert(Portal {
+                        sector: self.wall.sector_index,
+                        ..portal
+                    });
+                }
+            }
+        }
+
+        true
+    }
+}
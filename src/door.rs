@@ -0,0 +1,104 @@
+use crate::{item::KeyColour, player::PlayerStats, surface::Sector};
+
+/// Rate at which doors open and close, in units per second
+const DOOR_SPEED: f32 = 40.0;
+/// How long a door stays open before closing again, in seconds
+const DOOR_WAIT: f32 = 3.0;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum DoorState {
+    Closed,
+    Opening,
+    Open,
+    Closing,
+}
+
+/// A sector whose ceiling is lowered to its floor whilst closed, blocking the portals into it. Once
+/// opened, the ceiling rises, waits, then lowers again.
+#[derive(Debug)]
+pub struct Door {
+    pub sector_index: usize,
+    /// Ceiling height whilst fully open. Whilst closed, the ceiling is level with the floor.
+    pub open_height: f32,
+    /// Key that must be held to open the door, if any
+    pub lock: Option<KeyColour>,
+    /// Switches with this trigger open the door when switched on, regardless of its lock
+    pub trigger: Option<usize>,
+    state: DoorState,
+    /// Time remaining before an open door starts to close
+    wait: f32,
+}
+
+impl Door {
+    /// Creates a door and closes its sector
+    pub fn new(sectors: &mut [Sector], sector_index: usize, lock: Option<KeyColour>) -> Self {
+        let sector = &mut sectors[sector_index];
+        let open_height = sector.ceiling.height;
+        sector.ceiling.height = sector.floor.height;
+
+        Self {
+            sector_index,
+            open_height,
+            lock,
+            trigger: None,
+            state: DoorState::Closed,
+            wait: 0.0,
+        }
+    }
+
+    /// Starts opening the door, or keeps it open for longer if it already is. Locks are not
+    /// checked here, see [Door::can_unlock].
+    pub fn open(&mut self) {
+        match self.state {
+            DoorState::Closed | DoorState::Closing => self.state = DoorState::Opening,
+            DoorState::Open => self.wait = DOOR_WAIT,
+            DoorState::Opening => {}
+        }
+    }
+
+    /// Whether the door may be opened by a player with the given stats
+    pub fn can_unlock(&self, stats: &PlayerStats) -> bool {
+        self.lock.is_none_or(|lock| stats.has_key(lock))
+    }
+
+    /// Moves the door's ceiling. Whilst `occupied`, an open door will not close, and a closing door
+    /// reopens, so that nothing is crushed.
+    pub fn update(&mut self, sectors: &mut [Sector], delta_seconds: f32, occupied: bool) {
+        let sector = &mut sectors[self.sector_index];
+
+        match self.state {
+            DoorState::Closed => {}
+            DoorState::Opening => {
+                sector.ceiling.height += DOOR_SPEED * delta_seconds;
+
+                if sector.ceiling.height >= self.open_height {
+                    sector.ceiling.height = self.open_height;
+                    self.state = DoorState::Open;
+                    self.wait = DOOR_WAIT;
+                }
+            }
+            DoorState::Open => {
+                if !occupied {
+                    self.wait -= delta_seconds;
+                }
+
+                if self.wait <= 0.0 {
+                    self.state = DoorState::Closing;
+                }
+            }
+            DoorState::Closing => {
+                if occupied {
+                    self.state = DoorState::Opening;
+                    return;
+                }
+
+                sector.ceiling.height -= DOOR_SPEED * delta_seconds;
+
+                if sector.ceiling.height <= sector.floor.height {
+                    sector.ceiling.height = sector.floor.height;
+                    self.state = DoorState::Closed;
+                }
+            }
+        }
+    }
+}
@@ -1,13 +1,389 @@
-use crate::surface::Sprite;
+use maths::linear::Vec2f;
+
+use crate::{
+    consts::*,
+    fall::Fall,
+    map,
+    nav::NavMesh,
+    player::Player,
+    surface::{Sector, Sprite, WallTexture},
+    trigger::Occupancy,
+};
+
+/// Distance from the target at which enemies stop chasing
+const CHASE_STOP_DISTANCE: f32 = 20.0;
+/// How often the path to the target is recalculated, in seconds
+const REPATH_INTERVAL: f32 = 0.5;
+/// Seconds that an enemy takes to react after noticing the player, before giving chase
+const ALERT_DURATION: f32 = 0.4;
+/// Seconds that an enemy is staggered for when it flinches from being hurt
+const PAIN_DURATION: f32 = 0.3;
+
+/// How an enemy's attacks reach the player
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum AttackKind {
+    /// Hits the player if they are still within range once the attack lands
+    Melee,
+    /// Hits instantly, unless a wall is in the way
+    Hitscan,
+    /// Fires a projectile at the player that travels at this speed, in units per second
+    Projectile { speed: f32 },
+}
+
+/// Definition of a type of enemy
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct EnemyType {
+    pub health: u32,
+    /// Movement speed whilst chasing, in units per second
+    pub speed: f32,
+    pub attack: AttackKind,
+    pub damage: u32,
+    /// Distance to the player within which the enemy starts an attack
+    pub range: f32,
+    /// Seconds between starting an attack and it landing
+    pub wind_up: f32,
+    /// Seconds after an attack lands before the next can be started
+    pub cooldown: f32,
+    /// Chance, from 0 to 1, of flinching when hurt, which interrupts any attack being wound up
+    pub pain_chance: f32,
+    /// Height of the eyes above the floor, used for line of sight and as where attacks come from
+    pub eye_height: f32,
+    /// Sprite texture index whilst moving or attacking
+    pub idle_frame: usize,
+    /// Sprite texture index whilst flinching
+    pub pain_frame: usize,
+    /// Sprite texture index of the corpse
+    pub dead_frame: usize,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum EnemyKind {
+    Goblin,
+    Archer,
+    Shaman,
+}
+
+impl EnemyKind {
+    pub fn enemy_type(&self) -> &'static EnemyType {
+        match self {
+            EnemyKind::Goblin => &EnemyType {
+                health: 30,
+                speed: 25.0,
+                attack: AttackKind::Melee,
+                damage: 10,
+                range: 25.0,
+                wind_up: 0.3,
+                cooldown: 1.0,
+                pain_chance: 0.6,
+                eye_height: 12.0,
+                idle_frame: GOBLIN,
+                pain_frame: GOBLIN_PAIN,
+                dead_frame: GOBLIN_DEAD,
+            },
+            EnemyKind::Archer => &EnemyType {
+                health: 25,
+                speed: 20.0,
+                attack: AttackKind::Projectile { speed: 150.0 },
+                damage: 15,
+                range: 200.0,
+                wind_up: 0.5,
+                cooldown: 2.0,
+                pain_chance: 0.5,
+                eye_height: 12.0,
+                idle_frame: ARCHER,
+                pain_frame: ARCHER_PAIN,
+                dead_frame: ARCHER_DEAD,
+            },
+            EnemyKind::Shaman => &EnemyType {
+                health: 40,
+                speed: 18.0,
+                attack: AttackKind::Hitscan,
+                damage: 8,
+                range: 150.0,
+                wind_up: 0.6,
+                cooldown: 1.5,
+                pain_chance: 0.3,
+                eye_height: 12.0,
+                idle_frame: SHAMAN,
+                pain_frame: SHAMAN_PAIN,
+                dead_frame: SHAMAN_DEAD,
+            },
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum EnemyState {
+    /// Waiting to see the player, or hear a loud noise nearby
+    Idle,
+    /// Has just noticed the player, and is reacting before giving chase
+    Alert {
+        timer: f32,
+    },
+    /// Walking towards the player until close enough to attack
+    Chase,
+    /// Winding up an attack, which lands once the timer runs out
+    Attack {
+        timer: f32,
+    },
+    /// Flinching from being hurt
+    Pain {
+        timer: f32,
+    },
+    Dead,
+}
+
+/// An attack that has landed, aimed at the player, for the game to resolve
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Attack {
+    pub kind: AttackKind,
+    pub damage: u32,
+    pub range: f32,
+    /// Where the attack comes from, being the enemy's eyes
+    pub origin: Vec2f,
+    pub z: f32,
+    pub sector_index: usize,
+}
 
 pub struct Enemy {
-    sprite: Sprite,
+    pub kind: EnemyKind,
+    pub sprite: Sprite,
+    pub fall: Fall,
+    pub triggers: Occupancy,
+    pub state: EnemyState,
+    pub health: u32,
+    /// Remaining corners of the path being followed, in reverse order
+    path: Vec<Vec2f>,
+    /// Time until the path is next recalculated
+    repath_timer: f32,
+    /// Time until another attack can be started
+    cooldown: f32,
+    /// State of the generator used to roll for flinching
+    seed: u32,
+}
+
+impl Enemy {
+    pub fn new(kind: EnemyKind, position: Vec2f, sector_index: usize) -> Self {
+        let enemy_type = kind.enemy_type();
+
+        Self {
+            kind,
+            sprite: Sprite::new(
+                position,
+                sector_index,
+                WallTexture::new(enemy_type.idle_frame, Vec2f::ZERO, Vec2f::uniform(8.0)),
+                15.0,
+                15.0,
+            ),
+            fall: Fall::new(0.0),
+            triggers: Occupancy::new(),
+            state: EnemyState::Idle,
+            health: enemy_type.health,
+            path: Vec::new(),
+            repath_timer: 0.0,
+            cooldown: 0.0,
+            // Any non-zero seed will do, so enemies placed apart roll differently
+            seed: (position.x.to_bits() ^ position.y.to_bits().rotate_left(16)) | 1,
+        }
+    }
+
+    pub fn enemy_type(&self) -> &'static EnemyType {
+        self.kind.enemy_type()
+    }
+
+    /// Whether the enemy has noticed the player and is still alive
+    pub fn is_awake(&self) -> bool {
+        !matches!(self.state, EnemyState::Idle | EnemyState::Dead)
+    }
+
+    pub fn is_dead(&self) -> bool {
+        self.state == EnemyState::Dead
+    }
+
+    /// Notices the player, if the enemy hasn't already
+    pub fn wake(&mut self) {
+        if self.state == EnemyState::Idle {
+            self.state = EnemyState::Alert {
+                timer: ALERT_DURATION,
+            };
+        }
+    }
+
+    /// Reduces health, which may kill the enemy or make it flinch. Being hurt always wakes it.
+    pub fn damage(&mut self, amount: u32) {
+        if self.is_dead() {
+            return;
+        }
+
+        self.health = self.health.saturating_sub(amount);
+        if self.health == 0 {
+            self.state = EnemyState::Dead;
+            self.sprite.texture_data.index = self.enemy_type().dead_frame;
+            return;
+        }
+
+        if self.roll() < self.enemy_type().pain_chance {
+            self.state = EnemyState::Pain {
+                timer: PAIN_DURATION,
+            };
+            self.sprite.texture_data.index = self.enemy_type().pain_frame;
+        } else {
+            self.wake();
+        }
+    }
+
+    /// Advances the state machine, returning an attack if one has landed this tick
+    pub fn update(
+        &mut self,
+        delta_seconds: f32,
+        nav_mesh: &NavMesh,
+        sectors: &[Sector],
+        player: &Player,
+    ) -> Option<Attack> {
+        self.cooldown = (self.cooldown - delta_seconds).max(0.0);
+
+        match &mut self.state {
+            EnemyState::Idle | EnemyState::Dead => {}
+            EnemyState::Alert { timer } => {
+                *timer -= delta_seconds;
+                if *timer <= 0.0 {
+                    self.state = EnemyState::Chase;
+                }
+            }
+            EnemyState::Chase => {
+                if self.cooldown <= 0.0 && self.can_attack(sectors, player) {
+                    self.state = EnemyState::Attack {
+                        timer: self.enemy_type().wind_up,
+                    };
+                } else {
+                    self.chase(
+                        delta_seconds,
+                        nav_mesh,
+                        sectors,
+                        player.camera.position,
+                        player.sector_index,
+                    );
+                }
+            }
+            EnemyState::Attack { timer } => {
+                *timer -= delta_seconds;
+                if *timer <= 0.0 {
+                    self.state = EnemyState::Chase;
+                    self.cooldown = self.enemy_type().cooldown;
+                    return Some(self.attack());
+                }
+            }
+            EnemyState::Pain { timer } => {
+                *timer -= delta_seconds;
+                if *timer <= 0.0 {
+                    self.state = EnemyState::Chase;
+                    self.sprite.texture_data.index = self.enemy_type().idle_frame;
+                }
+            }
+        }
+
+        None
+    }
+
+    /// Height of the enemy's eyes, used for line of sight
+    pub fn eye_z(&self) -> f32 {
+        self.sprite.z + self.enemy_type().eye_height
+    }
+
+    /// Whether the player is within range, and can be seen
+    fn can_attack(&self, sectors: &[Sector], player: &Player) -> bool {
+        let range = self.enemy_type().range;
+        let target = player.camera.position;
+
+        (target - self.sprite.position).magnitude_sq() <= range * range
+            && map::line_of_sight(
+                sectors,
+                self.sprite.sector_index,
+                self.sprite.position,
+                self.eye_z(),
+                target,
+                player.camera.z,
+            )
+    }
+
+    fn attack(&self) -> Attack {
+        let enemy_type = self.enemy_type();
+
+        Attack {
+            kind: enemy_type.attack,
+            damage: enemy_type.damage,
+            range: enemy_type.range,
+            origin: self.sprite.position,
+            z: self.eye_z(),
+            sector_index: self.sprite.sector_index,
+        }
+    }
+
+    /// Returns a number from 0 to 1, using a xorshift generator
+    fn roll(&mut self) -> f32 {
+        self.seed ^= self.seed << 13;
+        self.seed ^= self.seed >> 17;
+        self.seed ^= self.seed << 5;
+
+        (self.seed >> 8) as f32 / (1 << 24) as f32
+    }
+
+    /// Walks along the navigation mesh towards the target
+    fn chase(
+        &mut self,
+        delta_seconds: f32,
+        nav_mesh: &NavMesh,
+        sectors: &[Sector],
+        target: Vec2f,
+        target_sector: usize,
+    ) {
+        self.repath_timer -= delta_seconds;
+        if self.repath_timer <= 0.0 {
+            self.repath_timer = REPATH_INTERVAL;
+
+            self.path = nav_mesh
+                .find_path(
+                    self.sprite.position,
+                    self.sprite.sector_index,
+                    target,
+                    target_sector,
+                    self.sprite.width * 0.5,
+                )
+                .unwrap_or_default();
+            self.path.reverse();
+        }
+
+        let prev_position = self.sprite.position;
+        if (target - prev_position).magnitude_sq() < CHASE_STOP_DISTANCE * CHASE_STOP_DISTANCE {
+            return;
+        }
+
+        let mut step = self.enemy_type().speed * delta_seconds;
+        while let Some(&corner) = self.path.last() {
+            let offset = corner - self.sprite.position;
+            let distance = offset.magnitude();
+
+            if distance > step {
+                self.sprite.position += offset / distance * step;
+                break;
+            }
+
+            self.sprite.position = corner;
+            step -= distance;
+            self.path.pop();
+        }
 
+        if let Some(portal) =
+            sectors[self.sprite.sector_index].crossed_portal(prev_position, self.sprite.position)
+        {
+            self.sprite.sector_index = portal.sector;
+        }
+    }
 }
 
-/* 
+/*
     Each enemy may have numerous sprite textures, each associated with a different direction relative
-    to the player's direction. We could modify the texture index within the WallTexture stored with the 
+    to the player's direction. We could modify the texture index within the WallTexture stored with the
     Sprite struct. Then there is that of animation, which would require a similar approach, but taking into
     account the time (or whatever the animation is based on).
-*/
\ No newline at end of file
+*/
@@ -0,0 +1,117 @@
+use std::collections::VecDeque;
+
+use input::Action;
+use maths::linear::Vec2f;
+
+use crate::{
+    consts::EVENT_HISTORY,
+    damage::Damage,
+    enemy::Attack,
+    item::{ItemKind, KeyColour},
+    map::WallId,
+    trigger::Occupant,
+};
+
+/// Something that happened during gameplay, which other systems may want to react to. Events are
+/// plain data, so that the stream of them can be recorded and played back.
+#[derive(Clone, Copy, Debug)]
+pub enum GameEvent {
+    /// The player pressed a button for an action that is carried out once the frame is over
+    ActionPressed {
+        action: Action,
+    },
+    /// An enemy's attack landed, aimed at the player, who may yet avoid it
+    EnemyAttacked {
+        attack: Attack,
+    },
+    /// An enemy was hurt by the player
+    EnemyHit,
+    ItemPickedUp {
+        kind: ItemKind,
+    },
+    /// The player tried to open a door without holding its key
+    DoorLocked {
+        lock: KeyColour,
+    },
+    /// The player was hurt, by an attack or by the hazard of the sector they are standing in
+    PlayerDamaged {
+        damage: Damage,
+    },
+    /// A loud noise, which wakes enemies up to `range` portals away
+    Noise {
+        sector_index: usize,
+        range: usize,
+    },
+    /// A wall was hit by something that deals damage, such as a gunshot
+    WallDamaged {
+        wall: WallId,
+        point: Vec2f,
+        amount: u32,
+    },
+    /// The player's health ran out
+    PlayerDied,
+    /// The player moved into another sector
+    SectorEntered {
+        sector_index: usize,
+    },
+    /// A switch was turned on or off, either by the player or by resetting itself
+    SwitchToggled {
+        trigger: usize,
+        on: bool,
+    },
+    /// The player or an enemy moved into a trigger volume
+    TriggerEntered {
+        trigger: usize,
+        occupant: Occupant,
+    },
+    /// The player or an enemy moved out of a trigger volume, or died whilst inside it
+    TriggerExited {
+        trigger: usize,
+        occupant: Occupant,
+    },
+}
+
+/// Events raised during a frame, to be handled once the frame's gameplay update is complete. This
+/// is how subsystems tell each other what happened, rather than calling into each other.
+#[derive(Debug, Default)]
+pub struct EventQueue {
+    events: Vec<GameEvent>,
+    /// Number of the frame that events are currently being raised in
+    tick: u64,
+    /// The most recent events, oldest first, along with the tick that each was raised in
+    history: VecDeque<(u64, GameEvent)>,
+}
+
+impl EventQueue {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn push(&mut self, event: GameEvent) {
+        if self.history.len() == EVENT_HISTORY {
+            self.history.pop_front();
+        }
+        self.history.push_back((self.tick, event));
+
+        self.events.push(event);
+    }
+
+    /// Moves all queued events into the buffer, in the order they were raised, replacing what was
+    /// there. Events raised whilst handling them are queued up again, rather than being lost.
+    pub fn take_into(&mut self, buffer: &mut Vec<GameEvent>) {
+        buffer.clear();
+        buffer.append(&mut self.events);
+    }
+
+    /// Moves on to the next frame, which later events are recorded as being raised in
+    pub fn end_tick(&mut self) {
+        self.tick += 1;
+    }
+
+    /// The most recent events, oldest first, along with the tick that each was raised in
+    pub fn history(
+        &self,
+    ) -> impl DoubleEndedIterator<Item = &(u64, GameEvent)> + ExactSizeIterator {
+        self.history.iter()
+    }
+}
@@ -0,0 +1,192 @@
+use std::{fmt::Write, fs, io, path::Path};
+
+use maths::linear::Vec2f;
+
+use crate::{
+    map,
+    player::Player,
+    surface::{Sector, Sprite},
+};
+
+/// Space, in world units, left around the map
+const MARGIN: f32 = 20.0;
+const FONT_SIZE: f32 = 6.0;
+
+/// Writes a top-down view of the map to an SVG file. See [map_to_svg].
+pub fn export_svg<'a>(
+    path: impl AsRef<Path>,
+    sectors: &[Sector],
+    sprites: impl Iterator<Item = &'a Sprite> + Clone,
+    player: &Player,
+    overlap_hints: bool,
+) -> io::Result<()> {
+    fs::write(path, map_to_svg(sectors, sprites, player, overlap_hints))
+}
+
+/// Renders the 2D layout of the map as an SVG document. Solid walls are drawn in black, portals
+/// are dashed red lines (or blue, if impassable), and each sector is labelled with its id and
+/// floor/ceiling heights. Sprites and the player are drawn as circles, with a line showing the player's direction.
+///
+/// With `overlap_hints`, sectors that overlap others, which portals allow in non-Euclidean maps,
+/// are shaded orange and list the ids of the sectors they overlap, and the player's sector is
+/// shaded green to show which of them the player is in. Otherwise overlapping sectors are drawn on
+/// top of each other, which is hard to make sense of.
+///
+/// The y-axis is flipped, so that positive y points up in the image, as it does in the world.
+pub fn map_to_svg<'a>(
+    sectors: &[Sector],
+    sprites: impl Iterator<Item = &'a Sprite> + Clone,
+    player: &Player,
+    overlap_hints: bool,
+) -> String {
+    let mut min = Vec2f::uniform(f32::MAX);
+    let mut max = Vec2f::uniform(f32::MIN);
+
+    let points = sectors
+        .iter()
+        .flat_map(|sector| sector.walls.iter())
+        .flat_map(|wall| [wall.segment.a, wall.segment.b])
+        .chain(sprites.clone().map(|sprite| sprite.position))
+        .chain(std::iter::once(player.camera.position));
+
+    for point in points {
+        min = Vec2f::new(min.x.min(point.x), min.y.min(point.y));
+        max = Vec2f::new(max.x.max(point.x), max.y.max(point.y));
+    }
+
+    min -= Vec2f::uniform(MARGIN);
+    max += Vec2f::uniform(MARGIN);
+    let size = max - min;
+
+    // Converts from world space to image space
+    let to_image = |point: Vec2f| Vec2f::new(point.x - min.x, max.y - point.y);
+
+    // Writing to a String cannot fail, so results are ignored
+    let mut svg = String::new();
+    let _ = writeln!(
+        svg,
+        r#"<svg xmlns="http://www.w3.org/2000/svg" viewBox="0 0 {} {}" width="{}" height="{}">"#,
+        size.x,
+        size.y,
+        size.x * 4.0,
+        size.y * 4.0
+    );
+    let _ = writeln!(svg, r#"<rect width="100%" height="100%" fill="white"/>"#);
+
+    for (sector_index, sector) in sectors.iter().enumerate() {
+        let _ = writeln!(svg, r#"<g id="sector-{}">"#, sector.id);
+
+        let overlapping: Vec<_> = if overlap_hints {
+            sectors
+                .iter()
+                .enumerate()
+                .filter(|(other_index, other)| {
+                    *other_index != sector_index && map::sectors_overlap(sector, other)
+                })
+                .map(|(_, other)| other.id.to_string())
+                .collect()
+        } else {
+            Vec::new()
+        };
+
+        let fill = if overlap_hints && sector_index == player.sector_index {
+            Some("green")
+        } else if !overlapping.is_empty() {
+            Some("orange")
+        } else {
+            None
+        };
+        if let Some(fill) = fill {
+            let points: Vec<_> = sector
+                .walls
+                .iter()
+                .map(|wall| to_image(wall.segment.a))
+                .map(|point| format!("{},{}", point.x, point.y))
+                .collect();
+            let _ = writeln!(
+                svg,
+                r#"<polygon points="{}" fill="{}" fill-opacity="0.2"/>"#,
+                points.join(" "),
+                fill
+            );
+        }
+
+        for wall in sector.walls.iter() {
+            let a = to_image(wall.segment.a);
+            let b = to_image(wall.segment.b);
+            let style = match wall.portal {
+                Some(portal) if !portal.passable => {
+                    r#"stroke="blue" stroke-width="0.5" stroke-dasharray="2,1""#
+                }
+                Some(_) => r#"stroke="red" stroke-width="0.5" stroke-dasharray="2,1""#,
+                None => r#"stroke="black" stroke-width="1""#,
+            };
+
+            let _ = writeln!(
+                svg,
+                r#"<line x1="{}" y1="{}" x2="{}" y2="{}" {}/>"#,
+                a.x, a.y, b.x, b.y, style
+            );
+        }
+
+        if !sector.walls.is_empty() {
+            let centre = sector
+                .walls
+                .iter()
+                .fold(Vec2f::ZERO, |sum, wall| sum + wall.segment.a)
+                / sector.walls.len() as f32;
+            let centre = to_image(centre);
+
+            let _ = writeln!(
+                svg,
+                r#"<text x="{}" y="{}" font-size="{}" font-family="monospace" text-anchor="middle">{}: {}/{}</text>"#,
+                centre.x,
+                centre.y,
+                FONT_SIZE,
+                sector.id,
+                sector.floor.height,
+                sector.ceiling.height
+            );
+
+            if !overlapping.is_empty() {
+                let _ = writeln!(
+                    svg,
+                    r#"<text x="{}" y="{}" font-size="{}" font-family="monospace" text-anchor="middle" fill="darkorange">overlaps {}</text>"#,
+                    centre.x,
+                    centre.y - FONT_SIZE,
+                    FONT_SIZE,
+                    overlapping.join(", ")
+                );
+            }
+        }
+
+        let _ = writeln!(svg, "</g>");
+    }
+
+    for sprite in sprites {
+        let position = to_image(sprite.position);
+        let _ = writeln!(
+            svg,
+            r#"<circle cx="{}" cy="{}" r="{}" fill="blue"/>"#,
+            position.x,
+            position.y,
+            sprite.width * 0.5
+        );
+    }
+
+    let position = to_image(player.camera.position);
+    let direction = to_image(player.camera.position + player.camera.direction * 15.0);
+    let _ = writeln!(
+        svg,
+        r#"<circle cx="{}" cy="{}" r="{}" fill="green"/>"#,
+        position.x, position.y, player.collider.radius
+    );
+    let _ = writeln!(
+        svg,
+        r#"<line x1="{}" y1="{}" x2="{}" y2="{}" stroke="green" stroke-width="1"/>"#,
+        position.x, position.y, direction.x, direction.y
+    );
+
+    svg.push_str("</svg>\n");
+    svg
+}
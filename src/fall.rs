@@ -0,0 +1,83 @@
+//! Vertical movement of sprites in the world, which fall onto the floor of their sector under
+//! gravity and bump against its ceiling
+
+use crate::surface::{Sector, Sprite};
+
+/// Downward acceleration, in units per second squared
+const GRAVITY: f32 = 250.0;
+/// Fastest speed anything falls at, in units per second
+const TERMINAL_VELOCITY: f32 = 400.0;
+/// Speed below which a bounce off the floor is ignored and the sprite comes to rest instead, in
+/// units per second
+const MIN_BOUNCE_SPEED: f32 = 10.0;
+/// Furthest the floor can drop from under a resting sprite in one step with the sprite staying
+/// stuck to it, such as when on a lift going down, rather than falling
+const STICK_DISTANCE: f32 = 2.0;
+
+/// How a sprite is moving up or down, such as a piece of debris thrown into the air or an enemy
+/// that has walked off a ledge into a lower sector
+#[derive(Clone, Copy, Debug)]
+pub struct Fall {
+    /// Upward speed, in units per second, which is negative whilst falling
+    pub velocity: f32,
+    /// Fraction of its speed kept when hitting the floor or ceiling, from 0 (stops dead) to 1
+    pub bounce: f32,
+    /// Whether resting on the floor
+    grounded: bool,
+}
+
+impl Fall {
+    /// Resting on the floor
+    pub fn new(bounce: f32) -> Self {
+        Self {
+            velocity: 0.0,
+            bounce,
+            grounded: true,
+        }
+    }
+
+    /// Thrown upwards at a speed, in units per second
+    pub fn launched(velocity: f32, bounce: f32) -> Self {
+        Self {
+            velocity,
+            bounce,
+            grounded: false,
+        }
+    }
+
+    /// Moves the sprite up or down by its velocity, accelerating it under gravity, and keeps it
+    /// between the floor and ceiling of the sector it is in. Sprites that are resting on the floor
+    /// follow it as it moves, unless it drops away from beneath them, such as at the edge of a
+    /// portal into a lower sector, in which case they fall.
+    pub fn update(&mut self, sprite: &mut Sprite, sector: &Sector, delta_seconds: f32) {
+        let floor = sector.floor.height;
+        let ceiling = sector.ceiling.height;
+
+        if self.grounded && sprite.z - floor <= STICK_DISTANCE {
+            sprite.z = floor;
+            self.velocity = 0.0;
+            return;
+        }
+
+        self.grounded = false;
+        self.velocity = (self.velocity - GRAVITY * delta_seconds).max(-TERMINAL_VELOCITY);
+        sprite.z += self.velocity * delta_seconds;
+
+        // Ceilings lower than the sprite is tall leave it squashed against the floor, so the
+        // floor is checked last
+        if sprite.z + sprite.height > ceiling && self.velocity > 0.0 {
+            sprite.z = ceiling - sprite.height;
+            self.velocity *= -self.bounce;
+        }
+
+        if sprite.z <= floor {
+            sprite.z = floor;
+
+            self.velocity *= -self.bounce;
+            if self.velocity < MIN_BOUNCE_SPEED {
+                self.velocity = 0.0;
+                self.grounded = true;
+            }
+        }
+    }
+}
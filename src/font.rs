@@ -1,6 +1,52 @@
-use std::path::Path;
+use std::{error::Error, fmt, path::Path};
 
-use crate::bitmap::Bitmap;
+use crate::bitmap::{Bitmap, BitmapError};
+
+#[derive(Debug)]
+pub enum FontError {
+    Bitmap(BitmapError),
+    /// The image isn't made up of a whole number of characters of the given size
+    InvalidCharSize {
+        image_width: usize,
+        image_height: usize,
+        char_width: usize,
+        char_height: usize,
+        char_spacing: usize,
+    },
+}
+
+impl fmt::Display for FontError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            FontError::Bitmap(error) => error.fmt(f),
+            FontError::InvalidCharSize {
+                image_width,
+                image_height,
+                char_width,
+                char_height,
+                char_spacing,
+            } => write!(
+                f,
+                "{image_width}x{image_height} image can't be divided into {char_width}x{char_height} characters with spacing {char_spacing}"
+            ),
+        }
+    }
+}
+
+impl Error for FontError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            FontError::Bitmap(error) => Some(error),
+            FontError::InvalidCharSize { .. } => None,
+        }
+    }
+}
+
+impl From<BitmapError> for FontError {
+    fn from(error: BitmapError) -> Self {
+        FontError::Bitmap(error)
+    }
+}
 
 pub enum AlignWidth {
     Left,
@@ -67,9 +113,21 @@ impl Font {
         char_width: usize,
         char_height: usize,
         char_spacing: usize,
-    ) -> Result<Self, &'static str> {
+    ) -> Result<Self, FontError> {
         let bitmap = Bitmap::from_path_png(path)?;
 
+        if !(bitmap.width() + char_spacing).is_multiple_of(char_width + char_spacing)
+            || !(bitmap.height() + char_spacing).is_multiple_of(char_height + char_spacing)
+        {
+            return Err(FontError::InvalidCharSize {
+                image_width: bitmap.width(),
+                image_height: bitmap.height(),
+                char_width,
+                char_height,
+                char_spacing,
+            });
+        }
+
         Ok(Self::from_bitmap(
             &bitmap,
             char_width,
@@ -117,16 +175,8 @@ impl Font {
         char_height: usize,
         char_spacing: usize,
     ) -> (Vec<CharMetadata>, Vec<RunLength>) {
-        assert_eq!(
-            (bitmap.width() + char_spacing) % (char_width + char_spacing),
-            0,
-            "Invalid char width or spacing"
-        );
-        assert_eq!(
-            (bitmap.height() + char_spacing) % (char_height + char_spacing),
-            0,
-            "Invalid char height or spacing"
-        );
+        debug_assert_eq!((bitmap.width() + char_spacing) % (char_width + char_spacing), 0);
+        debug_assert_eq!((bitmap.height() + char_spacing) % (char_height + char_spacing), 0);
 
         let mut run_lengths = Vec::new();
         let mut metadata = Vec::new();
@@ -0,0 +1,180 @@
+//! Renders the demo map from random viewpoints, with random sector heights, viewport sizes and
+//! fields of view. The rasterisers index the framebuffer and bounds buffers without checking, so in
+//! debug builds this is a search for the cases that trip their debug assertions instead.
+
+use std::{cell::RefCell, f32::consts::TAU};
+
+use maths::linear::Vec2f;
+use proptest::{
+    prelude::*,
+    test_runner::{Config, TestCaseError, TestRunner},
+};
+
+use crate::{
+    camera::Camera,
+    consts::{MAX_HFOV, MIN_HFOV},
+    demo,
+    enemy::{Enemy, EnemyKind},
+    golden_tests::load_renderer,
+};
+
+const CASES: u32 = 256;
+
+#[derive(Debug, Clone)]
+struct Scene {
+    width: usize,
+    height: usize,
+    h_fov: f32,
+    distortion_correction: bool,
+    /// Distances to the near and far planes
+    depth_range: (f32, f32),
+    sector_index: usize,
+    /// Position within the bounds of the sector, from 0 to 1 on each axis
+    position: (f32, f32),
+    /// Height between the floor and ceiling of the sector, from 0 to 1
+    z: f32,
+    yaw: f32,
+    pitch: f32,
+    /// Amounts added to the floor and ceiling height of each sector. Where these meet or cross,
+    /// the sector is closed off like a door.
+    heights: Vec<(f32, f32)>,
+}
+
+/// A value from 0 to 1, favouring either end, where problems are most likely
+fn fraction() -> impl Strategy<Value = f32> {
+    prop_oneof![Just(0.0), Just(1.0), 0.0f32..=1.0]
+}
+
+/// As for [fraction], but stopping just short of either end, so that a position stays within its
+/// sector whilst being right up against the walls
+fn inner_fraction() -> impl Strategy<Value = f32> {
+    prop_oneof![Just(0.0001), Just(0.9999), 0.0f32..=1.0]
+}
+
+fn scene(sector_count: usize) -> impl Strategy<Value = Scene> {
+    (
+        (
+            1usize..=320,
+            1usize..=240,
+            MIN_HFOV..=MAX_HFOV,
+            any::<bool>(),
+            (0.001f32..5.0, 10.0f32..2000.0),
+        ),
+        (
+            0..sector_count,
+            (inner_fraction(), inner_fraction()),
+            fraction(),
+        ),
+        (-TAU..TAU, -1.0f32..1.0),
+        prop::collection::vec((-100.0f32..100.0, -100.0f32..100.0), sector_count),
+    )
+        .prop_map(
+            |(
+                (width, height, h_fov, distortion_correction, depth_range),
+                (sector_index, position, z),
+                (yaw, pitch),
+                heights,
+            )| Scene {
+                width,
+                height,
+                h_fov,
+                distortion_correction,
+                depth_range,
+                sector_index,
+                position,
+                z,
+                yaw,
+                pitch,
+                heights,
+            },
+        )
+}
+
+#[test]
+fn render_random_scenes_test() {
+    let (assets, renderer) = load_renderer();
+    let sector_count = demo::sectors().len();
+    let enemies = [
+        Enemy::new(EnemyKind::Goblin, Vec2f::new(140.0, 80.0), 2),
+        Enemy::new(EnemyKind::Goblin, Vec2f::new(80.0, 80.0), 2),
+    ];
+
+    // Reused between cases, so that resizing the viewport is tested too
+    let renderer = RefCell::new(renderer);
+    let mut runner = TestRunner::new(Config::with_cases(CASES));
+
+    runner
+        .run(&scene(sector_count), |scene| {
+            let mut sectors = demo::sectors();
+            for (sector, (floor, ceiling)) in sectors.iter_mut().zip(scene.heights.iter()) {
+                sector.floor.height += floor;
+                sector.ceiling.height += ceiling;
+            }
+
+            let sector = &sectors[scene.sector_index];
+            let extents = sector.extents();
+            let position = Vec2f::new(
+                extents.min.x + (extents.max.x - extents.min.x) * scene.position.0,
+                extents.min.y + (extents.max.y - extents.min.y) * scene.position.1,
+            );
+            if !sector.contains_point(position) {
+                return Err(TestCaseError::reject("Position is outside of the sector"));
+            }
+            let z = sector.floor.height + (sector.ceiling.height - sector.floor.height) * scene.z;
+
+            let mut camera = Camera::new(position, z);
+            camera.set_rotation(scene.yaw, scene.pitch);
+
+            let mut renderer = renderer.borrow_mut();
+            renderer.set_viewport(scene.width, scene.height);
+            renderer.set_fov(scene.h_fov);
+            renderer.set_distortion_correction(scene.distortion_correction);
+            renderer.set_depth_range(scene.depth_range.0, scene.depth_range.1);
+            renderer
+                .update(
+                    &camera,
+                    scene.sector_index,
+                    &assets.textures,
+                    &sectors,
+                    enemies.iter().map(|enemy| &enemy.sprite),
+                )
+                .map_err(|error| TestCaseError::fail(error.to_string()))?;
+
+            Ok(())
+        })
+        .unwrap();
+}
+
+#[test]
+fn hardened_non_finite_test() {
+    let (assets, mut renderer) = load_renderer();
+    renderer.set_fov(MIN_HFOV);
+    renderer.state_mut().hardened = true;
+
+    // A sprite with no size, which can't be interpolated across
+    let mut enemy = Enemy::new(EnemyKind::Goblin, Vec2f::new(105.0, 150.0), 0);
+    enemy.sprite.width = 0.0;
+    enemy.sprite.height = 0.0;
+
+    let values = [f32::NAN, f32::INFINITY, f32::NEG_INFINITY, f32::MAX];
+    for value in values {
+        let cameras = [
+            Camera::new(Vec2f::new(value, 100.0), 15.0),
+            Camera::new(Vec2f::new(105.0, 100.0), value),
+            {
+                let mut camera = Camera::new(Vec2f::new(105.0, 100.0), 15.0);
+                camera.set_rotation(value, 0.0);
+                camera
+            },
+        ];
+
+        for camera in cameras.iter() {
+            let mut sectors = demo::sectors();
+            sectors[1].ceiling.height = value;
+
+            renderer
+                .update(camera, 0, &assets.textures, &sectors, [&enemy.sprite])
+                .unwrap();
+        }
+    }
+}
@@ -0,0 +1,198 @@
+//! Renders the demo map from fixed viewpoints and compares hashes of the frames against known good
+//! ones, to catch unintended changes to the rasterisers. When a change to the output is intended,
+//! check the frames written to [OUTPUT_DIR] and update the hashes.
+
+use std::{f32::consts::PI, fs, io::BufWriter, sync::Arc};
+
+use jobs::JobSystem;
+use maths::linear::Vec2f;
+
+use crate::{
+    assets::AssetManager,
+    camera::Camera,
+    consts::{TEXTURE_HUD_PATHS, TEXTURE_SPRITE_PATHS, TEXTURE_TILE_PATHS},
+    demo,
+    enemy::{Enemy, EnemyKind},
+    map,
+    renderer::{Framebuffer, Renderer},
+};
+
+/// Size of the frames that tests render, and the field of view they are rendered with
+pub const WIDTH: usize = 160;
+pub const HEIGHT: usize = 100;
+pub const H_FOV: f32 = 90.0;
+/// Each pose is rendered this many times, as every frame must come out the same
+const FRAMES_PER_POSE: usize = 2;
+/// Where frames that don't match their hash are written
+const OUTPUT_DIR: &str = "target/golden";
+
+pub struct Pose {
+    pub name: &'static str,
+    pub position: Vec2f,
+    pub z: f32,
+    pub yaw: f32,
+    pub pitch: f32,
+    hash: u64,
+    /// Hash of the frame with the `fixed-point` feature, which steps across textures and rounds
+    /// the view angles slightly differently
+    fixed_point_hash: u64,
+}
+
+pub const POSES: [Pose; 4] = [
+    // Looking down the long corridor of sector 0, towards sector 1
+    Pose {
+        name: "corridor",
+        position: Vec2f { x: 105.0, y: 180.0 },
+        z: 15.0,
+        yaw: PI,
+        pitch: 0.0,
+        hash: 0xa80857b10290e9ac,
+        fixed_point_hash: 0x92646651e217e816,
+    },
+    // Through the tinted window into sector 4, past its scrolling wall and conveyor floor
+    Pose {
+        name: "window",
+        position: Vec2f { x: 110.0, y: 150.0 },
+        z: 15.0,
+        yaw: PI * 1.5,
+        pitch: -0.1,
+        hash: 0x75b8f0f2252849ec,
+        fixed_point_hash: 0xfa803b8d24aafc5f,
+    },
+    // From the corner of sector 3 towards the goblins in sector 2, like the security camera, between
+    // the columns and through the haze that hangs over the slime
+    Pose {
+        name: "goblins",
+        position: Vec2f { x: 145.0, y: 35.0 },
+        z: 20.0,
+        yaw: -0.6,
+        pitch: -0.15,
+        hash: 0xdfc0d7f7ff4dbe7b,
+        fixed_point_hash: 0xea9b09db09eec823,
+    },
+    // Looking up at the ceiling of the lava room, sector 6, away from the long corridor whose far
+    // end lies beyond the far plane
+    Pose {
+        name: "lava_room",
+        position: Vec2f { x: 105.0, y: 620.0 },
+        z: 15.0,
+        yaw: 0.5,
+        pitch: 0.4,
+        hash: 0xff25eb8f801b8804,
+        fixed_point_hash: 0xb64ade3bd43d3a82,
+    },
+];
+
+/// 64-bit FNV-1a, which unlike the standard library's hasher is guaranteed not to change
+fn fnv1a(bytes: &[u8]) -> u64 {
+    bytes.iter().fold(0xcbf29ce484222325, |hash, byte| {
+        (hash ^ *byte as u64).wrapping_mul(0x100000001b3)
+    })
+}
+
+fn write_png(name: &str, framebuffer: &Framebuffer) {
+    fs::create_dir_all(OUTPUT_DIR).unwrap();
+    let file = fs::File::create(format!("{OUTPUT_DIR}/{name}.png")).unwrap();
+
+    let mut encoder = png::Encoder::new(
+        BufWriter::new(file),
+        framebuffer.width() as u32,
+        framebuffer.height() as u32,
+    );
+    encoder.set_color(png::ColorType::Rgba);
+    encoder.set_depth(png::BitDepth::Eight);
+
+    let rgba: Vec<u8> = framebuffer
+        .pixels()
+        .iter()
+        .flat_map(|pixel| [pixel.r, pixel.g, pixel.b, 0xFF])
+        .collect();
+
+    let mut writer = encoder.write_header().unwrap();
+    writer.write_image_data(&rgba).unwrap();
+}
+
+/// Loads every texture in the same order as the game, so that the demo map's texture indices
+/// refer to the right ones
+fn load_textures(jobs: Arc<JobSystem>) -> AssetManager {
+    let mut assets = AssetManager::new(jobs);
+    for path in TEXTURE_TILE_PATHS
+        .iter()
+        .chain(TEXTURE_SPRITE_PATHS.iter())
+        .chain(TEXTURE_HUD_PATHS.iter())
+    {
+        assets.load_texture(*path);
+    }
+    let errors = assets.wait(|_, _| {});
+    assert!(errors.is_empty(), "Failed to load textures: {errors:?}");
+
+    assets
+}
+
+/// Loads every texture and creates a renderer to draw with them, sharing a job system between the
+/// two, for tests that render frames
+pub fn load_renderer() -> (AssetManager, Renderer) {
+    let jobs = Arc::new(JobSystem::new(2));
+    let assets = load_textures(jobs.clone());
+
+    (assets, Renderer::new(WIDTH, HEIGHT, H_FOV, jobs))
+}
+
+#[test]
+fn golden_frames_test() {
+    let (assets, mut renderer) = load_renderer();
+
+    let sectors = demo::sectors();
+    let enemies = [
+        Enemy::new(EnemyKind::Goblin, Vec2f::new(140.0, 80.0), 2),
+        Enemy::new(EnemyKind::Goblin, Vec2f::new(80.0, 80.0), 2),
+    ];
+
+    let mut mismatches = Vec::new();
+
+    for pose in POSES.iter() {
+        let mut camera = Camera::new(pose.position, pose.z);
+        camera.set_rotation(pose.yaw, pose.pitch);
+        let sector_index = map::find_sector(pose.position, &sectors)
+            .unwrap_or_else(|| panic!("Pose '{}' is outside of the map", pose.name));
+
+        let mut hashes = Vec::new();
+        for _ in 0..FRAMES_PER_POSE {
+            renderer
+                .update(
+                    &camera,
+                    sector_index,
+                    &assets.textures,
+                    &sectors,
+                    enemies.iter().map(|enemy| &enemy.sprite),
+                )
+                .unwrap();
+            hashes.push(fnv1a(renderer.framebuffer().pixels_as_u8()));
+        }
+
+        assert!(
+            hashes.iter().all(|hash| *hash == hashes[0]),
+            "Pose '{}' rendered differently each frame",
+            pose.name
+        );
+
+        let expected = if cfg!(feature = "fixed-point") {
+            pose.fixed_point_hash
+        } else {
+            pose.hash
+        };
+        if hashes[0] != expected {
+            write_png(pose.name, renderer.framebuffer());
+            mismatches.push(format!(
+                "{}: expected {expected:#018x}, got {:#018x}",
+                pose.name, hashes[0]
+            ));
+        }
+    }
+
+    assert!(
+        mismatches.is_empty(),
+        "Frames don't match, see {OUTPUT_DIR}:\n{}",
+        mismatches.join("\n")
+    );
+}
@@ -0,0 +1,64 @@
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum HazardKind {
+    Lava,
+    Slime,
+}
+
+/// Damages anything standing on the floor of a sector
+#[derive(Clone, Copy, Debug)]
+pub struct Hazard {
+    pub kind: HazardKind,
+    /// Damage dealt every `interval` seconds
+    pub damage: u32,
+    pub interval: f32,
+}
+
+impl Hazard {
+    pub fn new(kind: HazardKind, damage: u32, interval: f32) -> Self {
+        Self {
+            kind,
+            damage,
+            interval,
+        }
+    }
+
+    pub fn lava() -> Self {
+        Self::new(HazardKind::Lava, 10, 0.5)
+    }
+
+    pub fn slime() -> Self {
+        Self::new(HazardKind::Slime, 5, 1.0)
+    }
+}
+
+/// Tracks an entity's exposure to hazards, so that damage is dealt once on entering a hazard, then
+/// once per interval whilst remaining in it
+#[derive(Debug, Default)]
+pub struct HazardExposure {
+    /// Time until the next damage is dealt
+    cooldown: f32,
+}
+
+impl HazardExposure {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Returns the damage to deal this frame, given the hazard of the sector the entity is standing
+    /// in, if any
+    pub fn update(&mut self, hazard: Option<&Hazard>, delta_seconds: f32) -> u32 {
+        let Some(hazard) = hazard else {
+            self.cooldown = 0.0;
+            return 0;
+        };
+
+        self.cooldown -= delta_seconds;
+
+        if self.cooldown > 0.0 {
+            return 0;
+        }
+
+        self.cooldown = hazard.interval;
+        hazard.damage
+    }
+}
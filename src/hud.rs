@@ -0,0 +1,361 @@
+use std::time::{Duration, Instant};
+
+use maths::linear::Vec2f;
+
+use crate::{
+    assets::{Assets, Handle},
+    camera::Camera,
+    colour::{BlendMode, BGRA8},
+    consts::*,
+    font::{AlignHeight, AlignWidth, Font},
+    item::{ItemKind, KeyColour},
+    player::PlayerStats,
+    profile,
+    renderer::{Rect, Renderer},
+    textures::Texture,
+};
+
+/// Space, in pixels, between a widget's icon and its counter
+const ICON_SPACING: usize = 3;
+/// How long messages remain on screen
+const MESSAGE_DURATION: Duration = Duration::from_secs(2);
+/// How long it takes for a screen flash to fade out
+const FLASH_DURATION: Duration = Duration::from_millis(300);
+/// Width and height of the held weapon, relative to the height of the screen
+const WEAPON_SIZE: f32 = 0.35;
+/// How long a damage indicator remains on screen, fading out as it goes
+const DAMAGE_INDICATOR_DURATION: Duration = Duration::from_millis(1000);
+/// Length of a damage indicator, from its base to the tip pointing towards the source, relative to
+/// the height of the screen
+const DAMAGE_INDICATOR_SIZE: f32 = 0.04;
+/// Distance of damage indicators from the centre of the view, relative to its half-width and
+/// half-height
+const DAMAGE_INDICATOR_RADIUS: f32 = 0.8;
+const DAMAGE_INDICATOR_COLOUR: BGRA8 = BGRA8 {
+    b: 0,
+    g: 0,
+    r: 255,
+    a: 192,
+};
+/// Multiplied over the screen whilst the player is dead, draining it of all but red
+const DEATH_TINT: BGRA8 = BGRA8 {
+    b: 96,
+    g: 96,
+    r: 255,
+    a: 192,
+};
+/// Added to the held weapon whilst its muzzle flash lights it up
+const MUZZLE_FLASH: BGRA8 = BGRA8 {
+    b: 48,
+    g: 120,
+    r: 160,
+    a: 255,
+};
+/// Screened over the screen when an item is picked up
+pub const PICKUP_FLASH: BGRA8 = BGRA8 {
+    b: 96,
+    g: 192,
+    r: 224,
+    a: 96,
+};
+
+#[derive(Clone, Copy, Debug)]
+pub enum HudValue {
+    Health,
+    Armour,
+    Ammo,
+}
+
+impl HudValue {
+    /// Returns the current and maximum value
+    fn get(&self, stats: &PlayerStats) -> (u32, u32) {
+        match self {
+            HudValue::Health => (stats.health, stats.max_health),
+            HudValue::Armour => (stats.armour, stats.max_armour),
+            HudValue::Ammo => (stats.ammo, stats.max_ammo),
+        }
+    }
+}
+
+/// The held weapon, as it is drawn this frame
+#[derive(Clone, Copy, Debug)]
+pub struct WeaponView {
+    /// HUD texture index
+    pub frame: usize,
+    /// Offset from where the weapon rests, relative to its size, with positive Y being downwards
+    pub bob: Vec2f,
+    /// Brightness of the muzzle flash lighting up the weapon
+    pub flash: f32,
+}
+
+/// A numeric counter, optionally accompanied by an icon to its left
+#[derive(Clone, Copy, Debug)]
+pub struct HudWidget {
+    pub value: HudValue,
+    /// Texture index
+    pub icon: Option<usize>,
+    /// Horizontal position of the widget, between its icon and counter, relative to the width of the
+    /// status bar
+    pub x: f32,
+}
+
+impl HudWidget {
+    pub fn new(value: HudValue, icon: Option<usize>, x: f32) -> Self {
+        Self { value, icon, x }
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct HudConfig {
+    /// Height of the status bar, in pixels
+    pub height: usize,
+    /// Texture index of the nine-slice background
+    pub background: usize,
+    /// Size of the corners of the background texture, in texels
+    pub border: usize,
+    /// Font index used for counters
+    pub font: usize,
+    pub colour: BGRA8,
+    /// Colour used for counters below `low_threshold`, as a fraction of their maximum
+    pub low_colour: BGRA8,
+    pub low_threshold: f32,
+    pub widgets: Vec<HudWidget>,
+}
+
+impl Default for HudConfig {
+    fn default() -> Self {
+        Self {
+            height: 32,
+            background: STATUS_BAR,
+            border: 4,
+            font: FONT_LARGE,
+            colour: BGRA8::YELLOW,
+            low_colour: BGRA8::RED,
+            low_threshold: 0.25,
+            widgets: vec![
+                HudWidget::new(HudValue::Health, Some(HEALTH), 0.2),
+                HudWidget::new(HudValue::Armour, Some(ARMOUR), 0.5),
+                HudWidget::new(HudValue::Ammo, Some(AMMO), 0.8),
+            ],
+        }
+    }
+}
+
+/// Status bar along the bottom of the screen, showing the player's stats
+pub struct Hud {
+    pub config: HudConfig,
+    /// Most recent message, and when it was shown
+    message: Option<(String, Instant)>,
+    /// Most recent screen flash, how it is combined with the screen, and when it started
+    flash: Option<(BGRA8, BlendMode, Instant)>,
+    /// Where recent damage came from, and when
+    damage_sources: Vec<(Vec2f, Instant)>,
+}
+
+impl Hud {
+    pub fn new(config: HudConfig) -> Self {
+        Self {
+            config,
+            message: None,
+            flash: None,
+            damage_sources: Vec::new(),
+        }
+    }
+
+    /// Shows a message near the top of the screen for a short time, replacing any previous message
+    pub fn show_message(&mut self, text: String) {
+        self.message = Some((text, Instant::now()));
+    }
+
+    /// Combines the colour with the whole screen, fading out over a short time. The alpha of the
+    /// colour is its initial strength.
+    pub fn flash(&mut self, colour: BGRA8, mode: BlendMode) {
+        self.flash = Some((colour, mode, Instant::now()));
+    }
+
+    /// Points towards where damage came from, at the edge of the view, for a short time
+    pub fn show_damage_source(&mut self, source: Vec2f) {
+        self.damage_sources
+            .retain(|(_, shown)| shown.elapsed() < DAMAGE_INDICATOR_DURATION);
+        self.damage_sources.push((source, Instant::now()));
+    }
+
+    pub fn draw(
+        &self,
+        renderer: &mut Renderer,
+        textures: &Assets<Texture>,
+        fonts: &Assets<Font>,
+        camera: &Camera,
+        stats: &PlayerStats,
+        weapon: Option<WeaponView>,
+    ) {
+        profile::scope!("hud", "draw");
+        let screen_width = renderer.framebuffer().width();
+        let screen_height = renderer.framebuffer().height();
+
+        if stats.health == 0 {
+            renderer.draw_overlay(DEATH_TINT, BlendMode::Multiply);
+        }
+
+        if let Some((colour, mode, start)) = self.flash {
+            let remaining = 1.0 - start.elapsed().as_secs_f32() / FLASH_DURATION.as_secs_f32();
+
+            if remaining > 0.0 {
+                let alpha = (colour.a as f32 * remaining) as u8;
+                renderer.draw_overlay(BGRA8 { a: alpha, ..colour }, mode);
+            }
+        }
+
+        let height = self.config.height.min(screen_height);
+        let bar = Rect::new(0, screen_height - height, screen_width, height);
+
+        self.draw_damage_indicators(renderer, camera, bar.y);
+
+        // The held weapon rests on top of the status bar
+        if let Some(weapon) = weapon {
+            let size = screen_height as f32 * WEAPON_SIZE;
+            let x = (screen_width as f32 - size) * 0.5 + weapon.bob.x * size;
+            let y = bar.y as f32 - size + weapon.bob.y * size;
+            let dst = Rect::new(
+                x.max(0.0) as usize,
+                y.max(0.0) as usize,
+                size as usize,
+                size as usize,
+            );
+
+            let texture = &textures[Handle::from_index(weapon.frame)];
+            let flash = (weapon.flash * MUZZLE_FLASH.a as f32).min(255.0) as u8;
+            if flash > 0 {
+                renderer.draw_image_tinted(
+                    texture,
+                    dst,
+                    BGRA8 {
+                        a: flash,
+                        ..MUZZLE_FLASH
+                    },
+                    BlendMode::Add,
+                );
+            } else {
+                renderer.draw_image(texture, dst);
+            }
+        }
+
+        renderer.draw_nine_slice(
+            &textures[Handle::from_index(self.config.background)],
+            self.config.border,
+            bar,
+        );
+
+        let font = &fonts[Handle::from_index(self.config.font)];
+        let icon_size = height.saturating_sub(self.config.border * 2);
+        let centre_y = bar.y + height / 2;
+
+        for widget in self.config.widgets.iter() {
+            let centre_x = (screen_width as f32 * widget.x) as usize;
+
+            if let Some(icon) = widget.icon {
+                renderer.draw_image(
+                    &textures[Handle::from_index(icon)],
+                    Rect::new(
+                        centre_x.saturating_sub(icon_size + ICON_SPACING),
+                        centre_y - icon_size / 2,
+                        icon_size,
+                        icon_size,
+                    ),
+                );
+            }
+
+            let (value, max) = widget.value.get(stats);
+            let colour = if (value as f32) < max as f32 * self.config.low_threshold {
+                self.config.low_colour
+            } else {
+                self.config.colour
+            };
+
+            renderer.draw_text(
+                font,
+                colour,
+                (AlignWidth::Left, AlignHeight::Centre),
+                centre_x as f32 / screen_width as f32,
+                centre_y as f32 / screen_height as f32,
+                &value.to_string(),
+            );
+        }
+
+        // Held keys are shown in fixed slots at the right end of the bar
+        for (i, colour) in KeyColour::ALL.iter().enumerate() {
+            if !stats.has_key(*colour) {
+                continue;
+            }
+
+            let slot = KeyColour::ALL.len() - i;
+            renderer.draw_image(
+                &textures[Handle::from_index(ItemKind::Key(*colour).texture())],
+                Rect::new(
+                    screen_width.saturating_sub(self.config.border + icon_size * slot),
+                    centre_y - icon_size / 2,
+                    icon_size,
+                    icon_size,
+                ),
+            );
+        }
+
+        if let Some((text, shown)) = &self.message {
+            if shown.elapsed() < MESSAGE_DURATION {
+                renderer.draw_text(
+                    font,
+                    self.config.colour,
+                    (AlignWidth::Centre, AlignHeight::Top),
+                    0.5,
+                    0.1,
+                    text,
+                );
+            }
+        }
+    }
+    /// Draws an arrowhead for each recent damage source, placed around the centre of the view in
+    /// the direction of the source relative to where the camera is facing, so that something behind
+    /// is marked at the bottom. `view_height` is the height of the view above the status bar.
+    fn draw_damage_indicators(&self, renderer: &mut Renderer, camera: &Camera, view_height: usize) {
+        let half_width = renderer.framebuffer().width() as f32 * 0.5;
+        let half_height = view_height as f32 * 0.5;
+        let size = renderer.framebuffer().height() as f32 * DAMAGE_INDICATOR_SIZE;
+
+        for (source, shown) in self.damage_sources.iter() {
+            let remaining =
+                1.0 - shown.elapsed().as_secs_f32() / DAMAGE_INDICATOR_DURATION.as_secs_f32();
+            if remaining <= 0.0 {
+                continue;
+            }
+
+            let offset = *source - camera.position;
+            let forward = offset.dot(camera.direction);
+            let right = -offset.dot(camera.direction.perpendicular());
+            let on_screen = Vec2f::new(right, -forward).normalise();
+            if on_screen == Vec2f::ZERO {
+                continue;
+            }
+
+            let centre = Vec2f::new(
+                half_width + on_screen.x * half_width * DAMAGE_INDICATOR_RADIUS,
+                half_height + on_screen.y * half_height * DAMAGE_INDICATOR_RADIUS,
+            );
+            let across = Vec2f::new(-on_screen.y, on_screen.x) * (size * 0.6);
+            let base = centre - on_screen * (size * 0.5);
+            let alpha = (DAMAGE_INDICATOR_COLOUR.a as f32 * remaining) as u8;
+
+            renderer.draw_polygon(
+                BGRA8 {
+                    a: alpha,
+                    ..DAMAGE_INDICATOR_COLOUR
+                },
+                &[
+                    centre + on_screen * (size * 0.5),
+                    base + across,
+                    base - across,
+                ],
+                Some(BlendMode::Alpha),
+            );
+        }
+    }
+}
@@ -0,0 +1,110 @@
+//! Queries for what is within reach of the player, such as what the use key activates and what a
+//! melee attack hits
+
+use maths::linear::Vec2f;
+
+use crate::{
+    map::{self, WallId},
+    surface::{Sector, Sprite},
+};
+
+/// Something within reach
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Target {
+    Wall(WallId),
+    /// Index of the sprite, in the order they were given to the query
+    Sprite(usize),
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Interaction {
+    pub target: Target,
+    /// Distance from the origin of the query to the wall, or to the centre of the sprite
+    pub distance: f32,
+}
+
+/// Finds the nearest wall or sprite within `reach` of `origin`, along a line in `direction`, where
+/// `origin` lies within the sector at `sector_index`. The line stops at solid walls, and at portals
+/// into sectors that `stops_at` accepts, such as doors. Sprites are found where the line passes
+/// through their footprint in front of the wall.
+pub fn interact<'a>(
+    sectors: &[Sector],
+    sector_index: usize,
+    origin: Vec2f,
+    direction: Vec2f,
+    reach: f32,
+    sprites: impl IntoIterator<Item = &'a Sprite>,
+    stops_at: impl Fn(usize) -> bool,
+) -> Option<Interaction> {
+    let direction = direction.normalise();
+    let to = origin + direction * reach;
+
+    let wall = map::ray_cast(sectors, sector_index, origin, to, |wall| {
+        wall.portal.is_none_or(|portal| stops_at(portal.sector))
+    })
+    .map(|hit| Interaction {
+        target: Target::Wall(hit.wall),
+        distance: hit.t * reach,
+    });
+    let max_distance = wall.map_or(reach, |wall| wall.distance);
+
+    let sprite = sprites
+        .into_iter()
+        .enumerate()
+        .filter_map(|(index, sprite)| {
+            let offset = sprite.position - origin;
+            let distance = offset.dot(direction);
+            let radius = sprite.width * 0.5;
+
+            // Distance of the centre either side of the line
+            let side = offset.dot(direction.perpendicular());
+
+            (distance >= 0.0 && distance <= max_distance && side.abs() <= radius).then_some(
+                Interaction {
+                    target: Target::Sprite(index),
+                    distance,
+                },
+            )
+        })
+        .min_by(|a, b| a.distance.total_cmp(&b.distance));
+
+    sprite.or(wall)
+}
+
+/// Finds the sprites that a melee attack from `origin` would hit, being those whose footprint is
+/// within `range` and whose centre is within `half_angle` radians either side of `direction`, as
+/// long as there is no wall in the way. Returns their indices, in the order they were given.
+pub fn melee_arc<'a>(
+    sectors: &[Sector],
+    sector_index: usize,
+    origin: Vec2f,
+    direction: Vec2f,
+    range: f32,
+    half_angle: f32,
+    sprites: impl IntoIterator<Item = &'a Sprite>,
+) -> Vec<usize> {
+    let direction = direction.normalise();
+    let min_cos = half_angle.cos();
+
+    sprites
+        .into_iter()
+        .enumerate()
+        .filter(|(_, sprite)| {
+            let offset = sprite.position - origin;
+            let distance = offset.magnitude();
+            if distance - sprite.width * 0.5 > range {
+                return false;
+            }
+
+            // Anything overlapping the origin is hit, whichever way it is facing
+            let in_arc = distance <= f32::EPSILON || offset.dot(direction) / distance >= min_cos;
+
+            in_arc
+                && map::ray_cast(sectors, sector_index, origin, sprite.position, |wall| {
+                    wall.portal.is_none()
+                })
+                .is_none()
+        })
+        .map(|(index, _)| index)
+        .collect()
+}
@@ -0,0 +1,133 @@
+use maths::linear::Vec2f;
+
+use crate::{
+    consts::*,
+    fall::Fall,
+    player::PlayerStats,
+    surface::{Sprite, WallTexture},
+    weapon::WeaponKind,
+};
+
+/// Width and height of item sprites, in world units
+const ITEM_SIZE: f32 = 6.0;
+/// Ammo that comes with a weapon when it is picked up
+const WEAPON_AMMO: u32 = 20;
+/// Fraction of its speed that an item keeps when it bounces off the floor
+const ITEM_BOUNCE: f32 = 0.3;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum KeyColour {
+    Red,
+    Blue,
+    Yellow,
+}
+
+impl KeyColour {
+    pub const ALL: [KeyColour; 3] = [KeyColour::Red, KeyColour::Blue, KeyColour::Yellow];
+
+    /// Bit used to store the key in [PlayerStats::keys]
+    pub fn bit(&self) -> u8 {
+        match self {
+            KeyColour::Red => 1 << 0,
+            KeyColour::Blue => 1 << 1,
+            KeyColour::Yellow => 1 << 2,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ItemKind {
+    Health(u32),
+    Armour(u32),
+    Ammo(u32),
+    Key(KeyColour),
+    Weapon(WeaponKind),
+}
+
+impl ItemKind {
+    /// Texture index of the item's sprite
+    pub fn texture(&self) -> usize {
+        match self {
+            ItemKind::Health(_) => HEALTH,
+            ItemKind::Armour(_) => ARMOUR,
+            ItemKind::Ammo(_) => AMMO,
+            ItemKind::Key(KeyColour::Red) => KEY_RED,
+            ItemKind::Key(KeyColour::Blue) => KEY_BLUE,
+            ItemKind::Key(KeyColour::Yellow) => KEY_YELLOW,
+            // Fists are never left lying around
+            ItemKind::Weapon(WeaponKind::Fist) => PLACEHOLDER,
+            ItemKind::Weapon(WeaponKind::Pistol) => PISTOL,
+            ItemKind::Weapon(WeaponKind::Shotgun) => SHOTGUN,
+            ItemKind::Weapon(WeaponKind::Crossbow) => CROSSBOW,
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            ItemKind::Health(_) => "health",
+            ItemKind::Armour(_) => "armour",
+            ItemKind::Ammo(_) => "ammo",
+            ItemKind::Key(KeyColour::Red) => "red key",
+            ItemKind::Key(KeyColour::Blue) => "blue key",
+            ItemKind::Key(KeyColour::Yellow) => "yellow key",
+            ItemKind::Weapon(kind) => kind.name(),
+        }
+    }
+
+    /// Adds the item to the stats, clamped to their maximums. Returns false if the item would have
+    /// no effect (e.g. health when already at full health), in which case it should not be collected.
+    pub fn apply(&self, stats: &mut PlayerStats) -> bool {
+        fn add(value: &mut u32, max: u32, amount: u32) -> bool {
+            if *value >= max {
+                return false;
+            }
+
+            *value = (*value + amount).min(max);
+            true
+        }
+
+        match *self {
+            ItemKind::Health(amount) => add(&mut stats.health, stats.max_health, amount),
+            ItemKind::Armour(amount) => add(&mut stats.armour, stats.max_armour, amount),
+            ItemKind::Ammo(amount) => add(&mut stats.ammo, stats.max_ammo, amount),
+            ItemKind::Key(colour) => {
+                if stats.has_key(colour) {
+                    return false;
+                }
+
+                stats.give_key(colour);
+                true
+            }
+            // Once carried, a weapon is only worth picking up for its ammo
+            ItemKind::Weapon(kind) => {
+                let has_weapon = stats.has_weapon(kind);
+                stats.give_weapon(kind);
+                add(&mut stats.ammo, stats.max_ammo, WEAPON_AMMO) || !has_weapon
+            }
+        }
+    }
+}
+
+/// An item placed in the map, which is collected when the player touches it
+#[derive(Debug)]
+pub struct Item {
+    pub kind: ItemKind,
+    pub sprite: Sprite,
+    pub fall: Fall,
+}
+
+impl Item {
+    pub fn new(kind: ItemKind, position: Vec2f, sector_index: usize) -> Self {
+        let texture = WallTexture::new(
+            kind.texture(),
+            Vec2f::ZERO,
+            Vec2f::uniform(16.0 / ITEM_SIZE),
+        );
+
+        Self {
+            kind,
+            sprite: Sprite::new(position, sector_index, texture, ITEM_SIZE, ITEM_SIZE),
+            fall: Fall::new(ITEM_BOUNCE),
+        }
+    }
+}
@@ -0,0 +1,84 @@
+use crate::surface::Sector;
+
+/// Rate at which lifts rise and fall, in units per second
+const LIFT_SPEED: f32 = 10.0;
+/// How long a lift stays raised before lowering again, in seconds
+const LIFT_WAIT: f32 = 3.0;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum LiftState {
+    Lowered,
+    Raising,
+    Raised,
+    Lowering,
+}
+
+/// A sector whose floor rises when activated, waits, then lowers back down, carrying along whatever
+/// is standing on it
+#[derive(Debug)]
+pub struct Lift {
+    pub sector_index: usize,
+    /// Floor height whilst lowered, which is the height the floor started at
+    pub low_height: f32,
+    pub high_height: f32,
+    /// Switches with this trigger raise the lift when switched on
+    pub trigger: Option<usize>,
+    state: LiftState,
+    /// Time remaining before a raised lift starts to lower
+    wait: f32,
+}
+
+impl Lift {
+    pub fn new(sectors: &[Sector], sector_index: usize, high_height: f32) -> Self {
+        Self {
+            sector_index,
+            low_height: sectors[sector_index].floor.height,
+            high_height,
+            trigger: None,
+            state: LiftState::Lowered,
+            wait: 0.0,
+        }
+    }
+
+    /// Starts raising the lift, or keeps it raised for longer if it already is
+    pub fn raise(&mut self) {
+        match self.state {
+            LiftState::Lowered | LiftState::Lowering => self.state = LiftState::Raising,
+            LiftState::Raised => self.wait = LIFT_WAIT,
+            LiftState::Raising => {}
+        }
+    }
+
+    /// Moves the lift's floor
+    pub fn update(&mut self, sectors: &mut [Sector], delta_seconds: f32) {
+        let floor = &mut sectors[self.sector_index].floor;
+
+        match self.state {
+            LiftState::Lowered => {}
+            LiftState::Raising => {
+                floor.height += LIFT_SPEED * delta_seconds;
+
+                if floor.height >= self.high_height {
+                    floor.height = self.high_height;
+                    self.state = LiftState::Raised;
+                    self.wait = LIFT_WAIT;
+                }
+            }
+            LiftState::Raised => {
+                self.wait -= delta_seconds;
+
+                if self.wait <= 0.0 {
+                    self.state = LiftState::Lowering;
+                }
+            }
+            LiftState::Lowering => {
+                floor.height -= LIFT_SPEED * delta_seconds;
+
+                if floor.height <= self.low_height {
+                    floor.height = self.low_height;
+                    self.state = LiftState::Lowered;
+                }
+            }
+        }
+    }
+}
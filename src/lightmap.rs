@@ -0,0 +1,342 @@
+//! Light baked into walls, floors and ceilings from lights placed in the map. Each surface stores
+//! a coarse grid of light levels, which the renderer scales its own lighting by as it draws, so any
+//! number of lights cost nothing per frame. The lights can't move, and surfaces that do (such as
+//! doors and lifts) keep whatever light was baked where they started.
+
+use maths::{geometry::Shape, linear::Vec2f};
+
+use crate::{map, profile, surface::Sector};
+
+/// Width and height of each texel of a lightmap, in world units
+const TEXEL_SIZE: f32 = 4.0;
+const INV_TEXEL_SIZE: f32 = 1.0 / TEXEL_SIZE;
+/// Distance that points on a surface are moved off it before checking whether lights can see
+/// them, so that the surface itself doesn't get in the way
+const SURFACE_OFFSET: f32 = 0.05;
+/// Number of times a texel centre that lies outside of its sector is moved towards the sector's
+/// centre, in search of a point inside it to light
+const INSIDE_STEPS: usize = 4;
+
+/// Height above the floor that lamps placed in the map hang at
+const LAMP_HEIGHT: f32 = 18.0;
+const LAMP_RADIUS: f32 = 80.0;
+const LAMP_INTENSITY: f32 = 1.5;
+
+/// A light placed in the map, which shines equally in every direction
+#[derive(Clone, Copy, Debug)]
+pub struct StaticLight {
+    pub position: Vec2f,
+    pub z: f32,
+    pub sector_index: usize,
+    /// Distance at which the light has faded out completely
+    pub radius: f32,
+    /// Brightness added to surfaces right next to the light, where 1 is the level of a surface
+    /// without a lightmap
+    pub intensity: f32,
+}
+
+impl StaticLight {
+    pub fn new(position: Vec2f, z: f32, sector_index: usize, radius: f32, intensity: f32) -> Self {
+        Self {
+            position,
+            z,
+            sector_index,
+            radius,
+            intensity,
+        }
+    }
+
+    /// A lamp placed in the map, which is given its height once the floor of its sector is known
+    pub fn lamp(position: Vec2f, sector_index: usize) -> Self {
+        Self::new(position, 0.0, sector_index, LAMP_RADIUS, LAMP_INTENSITY)
+    }
+
+    /// Puts the lamp at its height above the floor
+    pub fn hang(&mut self, floor_height: f32) {
+        self.z = floor_height + LAMP_HEIGHT;
+    }
+
+    /// Brightness that the light adds to a point on a surface facing the given way, which may be
+    /// nothing if it is out of reach, facing away, or hidden from the light
+    fn illuminate(&self, sectors: &[Sector], point: Vec2f, z: f32, normal: (Vec2f, f32)) -> f32 {
+        let offset = self.position - point;
+        let offset_z = self.z - z;
+        let distance = (offset.magnitude_sq() + offset_z * offset_z).sqrt();
+        if distance >= self.radius || distance == 0.0 {
+            return 0.0;
+        }
+
+        let facing = (offset.dot(normal.0) + offset_z * normal.1) / distance;
+        if facing <= 0.0 {
+            return 0.0;
+        }
+
+        if !map::line_of_sight(sectors, self.sector_index, self.position, self.z, point, z) {
+            return 0.0;
+        }
+
+        let falloff = 1.0 - distance / self.radius;
+        self.intensity * facing * falloff * falloff
+    }
+}
+
+/// Light levels across a surface, sampled by the nearest texel
+#[derive(Clone, Debug)]
+pub struct Lightmap {
+    /// Corner of the first texel, in the surface's own coordinates
+    origin: Vec2f,
+    width: usize,
+    height: usize,
+    texels: Vec<u8>,
+}
+
+impl Lightmap {
+    /// Level that leaves the lighting of a surface as it would be without a lightmap. Anything
+    /// above this brightens it, up to double.
+    pub const NEUTRAL: u8 = 128;
+
+    /// Bakes a lightmap covering `size` from `origin`, with the level of each texel found from
+    /// the position of its centre
+    fn bake(origin: Vec2f, size: Vec2f, mut level: impl FnMut(Vec2f) -> f32) -> Self {
+        let width = ((size.x * INV_TEXEL_SIZE).ceil() as usize).max(1);
+        let height = ((size.y * INV_TEXEL_SIZE).ceil() as usize).max(1);
+
+        let mut texels = Vec::with_capacity(width * height);
+        for y in 0..height {
+            for x in 0..width {
+                let centre = origin + Vec2f::new(x as f32 + 0.5, y as f32 + 0.5) * TEXEL_SIZE;
+                let texel = level(centre) * Self::NEUTRAL as f32;
+                texels.push(texel.clamp(0.0, u8::MAX as f32) as u8);
+            }
+        }
+
+        Self {
+            origin,
+            width,
+            height,
+            texels,
+        }
+    }
+
+    /// Returns a copy for a surface whose own coordinates start from `offset` in this one's, such
+    /// as the second half of a wall that was split, so that it lines up with the original
+    pub fn shifted(&self, offset: Vec2f) -> Self {
+        Self {
+            origin: self.origin - offset,
+            ..self.clone()
+        }
+    }
+
+    /// Level at a point in the surface's own coordinates. Points beyond the edges take the level
+    /// of the nearest texel.
+    #[inline(always)]
+    pub fn sample(&self, x: f32, y: f32) -> u8 {
+        let x = ((x - self.origin.x) * INV_TEXEL_SIZE) as isize;
+        let y = ((y - self.origin.y) * INV_TEXEL_SIZE) as isize;
+        let x = x.clamp(0, self.width as isize - 1) as usize;
+        let y = y.clamp(0, self.height as isize - 1) as usize;
+
+        // Clamped to the grid above, so always in bounds
+        unsafe { *self.texels.get_unchecked(y * self.width + x) }
+    }
+}
+
+/// Bakes lightmaps for every wall, floor and ceiling of the sectors, replacing any they had.
+/// `ambient` is the level of surfaces that no light reaches, where 1 leaves them as they would be
+/// without a lightmap.
+///
+/// Walls are mapped by the distance along them and down from the ceiling, and floors and ceilings
+/// by their position in the world.
+pub fn bake(sectors: &mut [Sector], lights: &[StaticLight], ambient: f32) {
+    profile::scope!("lightmap", "bake");
+    let level = |sectors: &[Sector], point: Vec2f, z: f32, normal: (Vec2f, f32)| {
+        lights.iter().fold(ambient, |level, light| {
+            level + light.illuminate(sectors, point, z, normal)
+        })
+    };
+
+    // Everything is baked before any is stored, as storing needs the sectors mutably
+    let baked: Vec<_> = sectors
+        .iter()
+        .map(|sector| {
+            let polygon = sector.polygon();
+            let centre = polygon.centre();
+            let floor = sector.floor.height;
+            let ceiling = sector.ceiling.height;
+
+            let walls: Vec<_> = sector
+                .walls
+                .iter()
+                .map(|wall| {
+                    let direction = (wall.segment.b - wall.segment.a) / wall.width;
+                    let inward = if wall.normal.dot(centre - wall.segment.a) > 0.0 {
+                        wall.normal
+                    } else {
+                        -wall.normal
+                    };
+
+                    let size = Vec2f::new(wall.width, ceiling - floor);
+                    Lightmap::bake(Vec2f::ZERO, size, |texel| {
+                        let point = wall.segment.a + direction * texel.x + inward * SURFACE_OFFSET;
+                        level(sectors, point, ceiling - texel.y, (inward, 0.0))
+                    })
+                })
+                .collect();
+
+            // Texels of the floor and ceiling along the walls may have their centres outside of
+            // the sector, so are lit from the nearest point found inside instead
+            let inside = |mut point: Vec2f| {
+                for _ in 0..INSIDE_STEPS {
+                    if sector.contains_point(point) {
+                        break;
+                    }
+                    point = point.lerp(centre, 0.25);
+                }
+                point
+            };
+
+            let extents = polygon.extents();
+            let size = extents.max - extents.min;
+            let floor_map = Lightmap::bake(extents.min, size, |texel| {
+                level(
+                    sectors,
+                    inside(texel),
+                    floor + SURFACE_OFFSET,
+                    (Vec2f::ZERO, 1.0),
+                )
+            });
+            let ceiling_map = Lightmap::bake(extents.min, size, |texel| {
+                level(
+                    sectors,
+                    inside(texel),
+                    ceiling - SURFACE_OFFSET,
+                    (Vec2f::ZERO, -1.0),
+                )
+            });
+
+            (walls, floor_map, ceiling_map)
+        })
+        .collect();
+
+    for (sector, (walls, floor, ceiling)) in sectors.iter_mut().zip(baked) {
+        for (wall, lightmap) in sector.walls.iter_mut().zip(walls) {
+            wall.lightmap = Some(lightmap);
+        }
+        sector.floor.lightmap = Some(floor);
+        sector.ceiling.lightmap = Some(ceiling);
+    }
+}
+
+/// Removes the lightmaps of every surface of the sectors, so they are lit as normal
+pub fn clear(sectors: &mut [Sector]) {
+    for sector in sectors.iter_mut() {
+        for wall in sector.walls.iter_mut() {
+            wall.lightmap = None;
+        }
+        sector.floor.lightmap = None;
+        sector.ceiling.lightmap = None;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::surface::{Plane, PlaneTexture, Wall, WallTexture};
+
+    /// Square room with a floor at 0 and ceiling at 20, with its walls running clockwise from the
+    /// one along the top
+    fn square(id: usize, min: Vec2f, size: f32) -> Sector {
+        let texture = WallTexture::new(0, Vec2f::ZERO, Vec2f::uniform(1.0));
+        let plane = PlaneTexture::new(0, Vec2f::ZERO, Vec2f::uniform(1.0), 0.0);
+        let corners = [
+            min + Vec2f::new(0.0, size),
+            min + Vec2f::uniform(size),
+            min + Vec2f::new(size, 0.0),
+            min,
+        ];
+
+        Sector {
+            id,
+            walls: (0..4)
+                .map(|i| Wall::new(corners[i], corners[(i + 1) % 4], texture, None))
+                .collect(),
+            floor: Plane::new(0.0, plane),
+            ceiling: Plane::new(20.0, plane),
+            hazard: None,
+            fog: None,
+        }
+    }
+
+    #[test]
+    fn sample_test() {
+        // Two texels across and one down, darker to the left
+        let lightmap = Lightmap::bake(Vec2f::ZERO, Vec2f::new(8.0, 3.0), |centre| centre.x / 8.0);
+        assert_eq!((lightmap.width, lightmap.height), (2, 1));
+        assert_eq!(lightmap.sample(1.0, 1.0), 32);
+        assert_eq!(lightmap.sample(5.0, 2.5), 96);
+
+        // Beyond the edges takes the nearest texel
+        assert_eq!(lightmap.sample(-10.0, -10.0), 32);
+        assert_eq!(lightmap.sample(100.0, 100.0), 96);
+
+        // Levels are clamped to what a texel can hold
+        let bright = Lightmap::bake(Vec2f::ZERO, Vec2f::uniform(4.0), |_| 10.0);
+        assert_eq!(bright.sample(0.0, 0.0), u8::MAX);
+
+        // Shifted to start halfway along, so the same points on a split surface keep their level
+        let rest = lightmap.shifted(Vec2f::new(4.0, 0.0));
+        assert_eq!(rest.sample(1.0, 1.0), 96);
+        assert_eq!(rest.sample(-3.0, 1.0), 32);
+    }
+
+    #[test]
+    fn bake_test() {
+        // A lit room beside a dark one, with a solid wall between them
+        let mut sectors = vec![
+            square(0, Vec2f::ZERO, 40.0),
+            square(1, Vec2f::new(40.0, 0.0), 40.0),
+        ];
+        let mut light = StaticLight::lamp(Vec2f::uniform(20.0), 0);
+        light.hang(sectors[0].floor.height);
+        bake(&mut sectors, &[light], 0.5);
+
+        let ambient = (0.5 * Lightmap::NEUTRAL as f32) as u8;
+        let level = |sector: &Sector, wall: usize, x: f32, y: f32| {
+            sector.walls[wall].lightmap.as_ref().unwrap().sample(x, y)
+        };
+
+        // Walls are lit most where nearest the light, and near the ceiling where it hangs
+        let lit = &sectors[0];
+        assert!(level(lit, 0, 20.0, 2.0) > level(lit, 0, 2.0, 2.0));
+        assert!(level(lit, 0, 20.0, 2.0) > level(lit, 0, 20.0, 18.0));
+        assert!(level(lit, 0, 2.0, 2.0) > ambient);
+        let floor = lit.floor.lightmap.as_ref().unwrap();
+        assert!(floor.sample(20.0, 20.0) > floor.sample(2.0, 2.0));
+        assert!(lit.ceiling.lightmap.is_some());
+
+        // None reaches through the wall, so the other room is left at the ambient level
+        let dark = &sectors[1];
+        for wall in 0..4 {
+            assert_eq!(level(dark, wall, 10.0, 10.0), ambient);
+        }
+        assert_eq!(
+            dark.floor.lightmap.as_ref().unwrap().sample(60.0, 20.0),
+            ambient
+        );
+
+        // Splitting a wall keeps the light where it was along it, but removing one moves the next
+        // wall, which loses its lightmap until baked again
+        let middle = level(&sectors[0], 0, 20.0, 2.0);
+        let rest = sectors[0].split_wall(0, Vec2f::new(10.0, 40.0)).unwrap();
+        assert_eq!(level(&sectors[0], rest, 10.0, 2.0), middle);
+        sectors[0].remove_wall(0);
+        assert!(sectors[0].walls[0].lightmap.is_none());
+
+        clear(&mut sectors);
+        assert!(sectors.iter().all(|sector| {
+            sector.floor.lightmap.is_none()
+                && sector.ceiling.lightmap.is_none()
+                && sector.walls.iter().all(|wall| wall.lightmap.is_none())
+        }));
+    }
+}
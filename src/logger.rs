@@ -0,0 +1,136 @@
+//! Sink for the `log` facade. Records are echoed to stderr, and kept until the game collects them to
+//! show in the console, with warnings and errors also shown briefly on screen whilst debugging.
+
+use std::{
+    fmt,
+    sync::Mutex,
+    time::{Duration, Instant},
+};
+
+use collections::RingBuffer;
+use log::{Level, LevelFilter, Log, Metadata, Record};
+
+use crate::{
+    colour::BGRA8,
+    font::{AlignHeight, AlignWidth, Font},
+    renderer::Renderer,
+};
+
+/// Most records kept whilst waiting to be collected, beyond which the oldest are dropped
+const MAX_PENDING: usize = 256;
+/// Most toasts shown at once
+const MAX_TOASTS: usize = 4;
+/// How long each toast remains on screen
+const TOAST_DURATION: Duration = Duration::from_secs(5);
+
+static LOGGER: Logger = Logger {
+    pending: Mutex::new(Vec::new()),
+};
+
+/// A message that has been logged
+#[derive(Clone, Debug)]
+pub struct LogRecord {
+    pub level: Level,
+    /// Module that logged the message
+    pub target: String,
+    pub message: String,
+}
+
+impl fmt::Display for LogRecord {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "[{}] {}: {}", self.level, self.target, self.message)
+    }
+}
+
+struct Logger {
+    pending: Mutex<Vec<LogRecord>>,
+}
+
+impl Log for Logger {
+    fn enabled(&self, metadata: &Metadata) -> bool {
+        metadata.level() <= log::max_level()
+    }
+
+    fn log(&self, record: &Record) {
+        if !self.enabled(record.metadata()) {
+            return;
+        }
+
+        let record = LogRecord {
+            level: record.level(),
+            target: record.target().to_owned(),
+            message: record.args().to_string(),
+        };
+        eprintln!("{record}");
+
+        let Ok(mut pending) = self.pending.lock() else {
+            return;
+        };
+        if pending.len() >= MAX_PENDING {
+            pending.remove(0);
+        }
+        pending.push(record);
+    }
+
+    fn flush(&self) {}
+}
+
+/// Installs the logger, showing records up to the given level. Does nothing if a logger has already
+/// been installed.
+pub fn init(level: LevelFilter) {
+    if log::set_logger(&LOGGER).is_ok() {
+        log::set_max_level(level);
+    }
+}
+
+/// Takes the records logged since the last call, oldest first
+pub fn take_records() -> Vec<LogRecord> {
+    LOGGER
+        .pending
+        .lock()
+        .map(|mut pending| std::mem::take(&mut *pending))
+        .unwrap_or_default()
+}
+
+/// Recent warnings and errors, shown in a corner of the screen for a short time
+pub struct Toasts {
+    toasts: RingBuffer<(LogRecord, Instant), MAX_TOASTS>,
+}
+
+impl Toasts {
+    pub fn new() -> Self {
+        Self {
+            toasts: RingBuffer::new(),
+        }
+    }
+
+    /// Shows the record, if it is a warning or an error, replacing the oldest toast if full
+    pub fn push(&mut self, record: &LogRecord) {
+        if record.level <= Level::Warn {
+            self.toasts.push((record.clone(), Instant::now()));
+        }
+    }
+
+    /// Draws the toasts still showing along the bottom left of the screen, newest at the bottom
+    pub fn draw(&self, renderer: &mut Renderer, font: &Font) {
+        let (older, newer) = self.toasts.as_slices();
+        let text = older
+            .iter()
+            .chain(newer.iter())
+            .filter(|(_, shown)| shown.elapsed() < TOAST_DURATION)
+            .map(|(record, _)| record.to_string())
+            .collect::<Vec<_>>();
+        if text.is_empty() {
+            return;
+        }
+
+        renderer.draw_text(
+            font,
+            BGRA8::YELLOW,
+            (AlignWidth::Left, AlignHeight::Bottom),
+            0.01,
+            0.7,
+            &text.join("\n"),
+        );
+    }
+}
@@ -1,20 +1,68 @@
+mod alloc_audit;
+mod assets;
 mod app;
 mod bitmap;
 mod camera;
+mod chase;
+mod clock;
 mod colour;
+mod console;
 mod consts;
+mod crosshair;
+mod damage;
+mod debris;
+mod demo;
+mod destructible;
+mod door;
+mod events;
+mod export;
+mod fall;
 mod font;
+mod hazard;
+mod hud;
+mod interact;
+mod item;
+mod lift;
+mod lightmap;
+mod logger;
+mod map;
+mod merge;
+mod motion;
+mod nav;
+mod pacing;
+mod pause;
+mod perception;
+mod pick;
 mod player;
+mod projectile;
+mod profile;
+mod prop;
 mod renderer;
+mod resolution;
+mod rewind;
+mod script;
+mod settings;
+mod spectator;
 mod surface;
+mod switch;
 mod textures;
+mod thing;
 mod timer;
+mod trigger;
+mod weapon;
 mod collision;
 mod enemy;
 
+#[cfg(test)]
+mod fuzz_tests;
+#[cfg(test)]
+mod golden_tests;
+
 use app::App;
 
 fn main() {
+    logger::init(log::LevelFilter::Info);
+
     let app = App::new();
     app.run();
 }
@@ -0,0 +1,1016 @@
+//! Queries over the sector graph, where sectors are connected by the portals in their walls
+
+use std::{collections::VecDeque, error::Error, fmt};
+
+use maths::{
+    geometry::{Polygon, Segment, UniformGrid, AABB},
+    linear::Vec2f,
+};
+
+use crate::{
+    surface::{Sector, Wall},
+    thing::{Thing, ThingType},
+};
+
+/// Width and height of each cell of a [MapIndex]
+const GRID_CELL_SIZE: f32 = 64.0;
+
+/// Returns the index of the sector containing the point, if any, by checking every sector. Where
+/// sectors overlap, the one with the lowest index is returned.
+pub fn find_sector(point: Vec2f, sectors: &[Sector]) -> Option<usize> {
+    sectors
+        .iter()
+        .position(|sector| sector.contains_point(point))
+}
+
+/// A problem with the map data that would stop it from being played
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MapError {
+    NoSectors,
+    /// A sector needs at least three walls to enclose an area
+    TooFewWalls {
+        sector: usize,
+    },
+    /// A portal leads to a sector that doesn't exist
+    InvalidPortal {
+        sector: usize,
+        wall: usize,
+        target: usize,
+    },
+    /// A wall, floor or ceiling uses a texture index beyond those loaded
+    MissingTexture {
+        sector: usize,
+        texture: usize,
+    },
+    /// A thing is placed outside of the sector it is meant to be in, or in one that doesn't exist
+    ThingOutsideSector {
+        thing: usize,
+        sector: usize,
+    },
+    /// A thing's type has no spawn function registered for it
+    UnknownThing {
+        thing: usize,
+        kind: u16,
+    },
+    /// There is nowhere for the player to start the level
+    NoPlayerStart,
+    /// More than one thing is the player's start, so it isn't clear which to use
+    DuplicatePlayerStart {
+        thing: usize,
+    },
+}
+
+impl fmt::Display for MapError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            MapError::NoSectors => write!(f, "the map has no sectors"),
+            MapError::TooFewWalls { sector } => {
+                write!(f, "sector {sector} has fewer than three walls")
+            }
+            MapError::InvalidPortal {
+                sector,
+                wall,
+                target,
+            } => write!(
+                f,
+                "wall {wall} of sector {sector} is a portal to sector {target}, which doesn't exist"
+            ),
+            MapError::MissingTexture { sector, texture } => {
+                write!(
+                    f,
+                    "sector {sector} uses texture {texture}, which isn't loaded"
+                )
+            }
+            MapError::ThingOutsideSector { thing, sector } => {
+                write!(f, "thing {thing} is not inside sector {sector}")
+            }
+            MapError::UnknownThing { thing, kind } => {
+                write!(f, "thing {thing} has type {kind}, which can't be spawned")
+            }
+            MapError::NoPlayerStart => write!(f, "the map has no player start"),
+            MapError::DuplicatePlayerStart { thing } => {
+                write!(f, "thing {thing} is a second player start")
+            }
+        }
+    }
+}
+
+impl Error for MapError {}
+
+/// Checks that the sectors form a playable map, where every portal leads somewhere and every
+/// texture index is below `texture_count`
+pub fn validate(sectors: &[Sector], texture_count: usize) -> Result<(), MapError> {
+    if sectors.is_empty() {
+        return Err(MapError::NoSectors);
+    }
+
+    for (sector_index, sector) in sectors.iter().enumerate() {
+        if sector.walls.len() < 3 {
+            return Err(MapError::TooFewWalls {
+                sector: sector_index,
+            });
+        }
+
+        let mut textures = vec![
+            sector.floor.texture_data.index,
+            sector.ceiling.texture_data.index,
+        ];
+
+        for (wall_index, wall) in sector.walls.iter().enumerate() {
+            textures.push(wall.texture_data.index);
+
+            if let Some(portal) = wall.portal {
+                if portal.sector >= sectors.len() {
+                    return Err(MapError::InvalidPortal {
+                        sector: sector_index,
+                        wall: wall_index,
+                        target: portal.sector,
+                    });
+                }
+
+                textures.push(portal.upper_texture.index);
+                textures.push(portal.lower_texture.index);
+            }
+        }
+
+        if let Some(texture) = textures.into_iter().find(|index| *index >= texture_count) {
+            return Err(MapError::MissingTexture {
+                sector: sector_index,
+                texture,
+            });
+        }
+    }
+
+    Ok(())
+}
+
+/// Checks that every thing lies within the sector it is placed in, and that there is exactly one
+/// player start
+pub fn validate_things(things: &[Thing], sectors: &[Sector]) -> Result<(), MapError> {
+    let mut player_start = None;
+
+    for (thing_index, thing) in things.iter().enumerate() {
+        if thing.kind == ThingType::PLAYER_START {
+            if player_start.is_some() {
+                return Err(MapError::DuplicatePlayerStart { thing: thing_index });
+            }
+            player_start = Some(thing_index);
+        }
+
+        let inside = sectors
+            .get(thing.sector_index)
+            .is_some_and(|sector| sector.contains_point(thing.position));
+
+        if !inside {
+            return Err(MapError::ThingOutsideSector {
+                thing: thing_index,
+                sector: thing.sector_index,
+            });
+        }
+    }
+
+    if player_start.is_none() {
+        return Err(MapError::NoPlayerStart);
+    }
+
+    Ok(())
+}
+
+/// Identifies a wall by the sector it belongs to and its position within that sector
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct WallId {
+    pub sector_index: usize,
+    pub wall_index: usize,
+}
+
+impl WallId {
+    pub fn get<'a>(&self, sectors: &'a [Sector]) -> &'a Wall {
+        &sectors[self.sector_index].walls[self.wall_index]
+    }
+
+    /// Returns the id that refers to the same wall after the wall at `removed` was removed, unless
+    /// it was that wall
+    pub fn after_remove(self, removed: WallId) -> Option<WallId> {
+        if self.sector_index != removed.sector_index || self.wall_index < removed.wall_index {
+            Some(self)
+        } else if self.wall_index > removed.wall_index {
+            Some(WallId {
+                wall_index: self.wall_index - 1,
+                ..self
+            })
+        } else {
+            None
+        }
+    }
+
+    /// Returns the id that refers to the same wall after a wall was inserted at `inserted`, which
+    /// moves the later walls of that sector up by one
+    fn after_insert(self, inserted: WallId) -> WallId {
+        if self.sector_index == inserted.sector_index && self.wall_index >= inserted.wall_index {
+            WallId {
+                wall_index: self.wall_index + 1,
+                ..self
+            }
+        } else {
+            self
+        }
+    }
+}
+
+/// Why a wall couldn't be removed with [remove_wall]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RemoveWallError {
+    /// A sector needs at least three walls to enclose an area
+    TooFewWalls,
+    /// Closing the gap would leave the sector concave
+    NotConvex,
+    /// The following wall is a portal, which would no longer meet its twin once moved
+    MovesPortal,
+}
+
+impl fmt::Display for RemoveWallError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            RemoveWallError::TooFewWalls => write!(f, "a sector needs at least three walls"),
+            RemoveWallError::NotConvex => write!(f, "the sector would no longer be convex"),
+            RemoveWallError::MovesPortal => {
+                write!(
+                    f,
+                    "the next wall is a portal, which would no longer meet its twin"
+                )
+            }
+        }
+    }
+}
+
+impl Error for RemoveWallError {}
+
+/// Removes a wall like [Sector::remove_wall], returning it. If it was a portal, its twin becomes a
+/// solid wall, as there is no longer an opening to pass through. Only walls of this sector move to
+/// close the gap, so this is refused where the wall that moves is a portal, as its twin would be
+/// left where it was. It is also refused where the sector would be left concave.
+///
+/// Other ids of walls in the sector need adjusting with [WallId::after_remove], and any
+/// [MapIndex] needs updating for the sector.
+pub fn remove_wall(sectors: &mut [Sector], id: WallId) -> Result<Wall, RemoveWallError> {
+    let walls = &sectors[id.sector_index].walls;
+    if walls.len() <= 3 {
+        return Err(RemoveWallError::TooFewWalls);
+    }
+
+    let next = (id.wall_index + 1) % walls.len();
+    if walls[next].portal.is_some() {
+        return Err(RemoveWallError::MovesPortal);
+    }
+
+    // The start of the next wall moves back to the start of the removed one, so its own start is
+    // the vertex that goes
+    let outline = Polygon::from_vertices(
+        walls
+            .iter()
+            .enumerate()
+            .filter(|(index, _)| *index != next)
+            .map(|(_, wall)| wall.segment.a)
+            .collect(),
+    );
+    if !outline.is_convex() {
+        return Err(RemoveWallError::NotConvex);
+    }
+
+    if let Some(twin) = twin_wall(sectors, id) {
+        sectors[twin.sector_index].walls[twin.wall_index].portal = None;
+    }
+
+    Ok(sectors[id.sector_index].remove_wall(id.wall_index))
+}
+
+/// The walls added by [split_wall]
+#[derive(Debug, Clone, Copy)]
+pub struct WallSplit {
+    /// Second half of the wall that was split
+    pub rest: WallId,
+    /// Second half of its twin, if it was a portal with one
+    pub twin_rest: Option<WallId>,
+}
+
+impl WallSplit {
+    /// Returns the id that refers to the same wall as `id` did before the split, as later walls of
+    /// the affected sectors move up to make room
+    pub fn remap(&self, id: WallId) -> WallId {
+        let id = id.after_insert(self.rest);
+        match self.twin_rest {
+            Some(twin_rest) => id.after_insert(twin_rest),
+            None => id,
+        }
+    }
+}
+
+/// Returns the wall on the other side of a portal, which runs between the same points in the
+/// opposite direction. There is none for solid walls, or portals that only lead one way.
+pub fn twin_wall(sectors: &[Sector], id: WallId) -> Option<WallId> {
+    let wall = id.get(sectors);
+    let portal = wall.portal?;
+
+    let wall_index = sectors[portal.sector].walls.iter().position(|next_wall| {
+        next_wall.segment.a == wall.segment.b && next_wall.segment.b == wall.segment.a
+    })?;
+
+    Some(WallId {
+        sector_index: portal.sector,
+        wall_index,
+    })
+}
+
+/// Splits a wall like [Sector::split_wall], along with its twin if it is a portal, so that the
+/// sectors on either side still meet along matching walls. Returns `None` if the point is at
+/// either end of the wall.
+///
+/// Other ids of walls in the affected sectors need adjusting with [WallSplit::remap], and any
+/// [MapIndex] needs updating for both sectors.
+pub fn split_wall(sectors: &mut [Sector], id: WallId, point: Vec2f) -> Option<WallSplit> {
+    let twin = twin_wall(sectors, id);
+
+    let wall_index = sectors[id.sector_index].split_wall(id.wall_index, point)?;
+    let rest = WallId {
+        sector_index: id.sector_index,
+        wall_index,
+    };
+    let split_point = rest.get(sectors).segment.a;
+
+    let twin_rest = twin.and_then(|twin| {
+        let twin = twin.after_insert(rest);
+        let wall_index = sectors[twin.sector_index].split_wall(twin.wall_index, split_point)?;
+        let walls = &mut sectors[twin.sector_index].walls;
+
+        // Projecting the point onto the twin may round it differently, so the twins are made to
+        // meet exactly where the wall was split, or they would no longer be found as twins
+        let first = &mut walls[twin.wall_index];
+        first.set_segment(first.segment.a, split_point);
+        let second = &mut walls[wall_index];
+        second.set_segment(split_point, second.segment.b);
+
+        Some(WallId {
+            sector_index: twin.sector_index,
+            wall_index,
+        })
+    });
+
+    Some(WallSplit { rest, twin_rest })
+}
+
+/// The nearest wall hit by a [ray_cast]
+#[derive(Debug, Clone, Copy)]
+pub struct RayHit {
+    pub wall: WallId,
+    /// Fraction of the way along the ray at which the wall was hit
+    pub t: f32,
+}
+
+/// Uniform grids over the map, listing the sectors and walls in each cell. This narrows down what
+/// needs checking when finding the sector containing a point or colliding with walls, so that these
+/// stay fast on large maps. As sectors may overlap, anything found here still needs checking
+/// against the portal graph, e.g. with [are_adjacent].
+#[derive(Debug, Default)]
+pub struct MapIndex {
+    sectors: UniformGrid<usize>,
+    walls: UniformGrid<WallId>,
+}
+
+impl MapIndex {
+    pub fn build(sectors: &[Sector]) -> Self {
+        if sectors.is_empty() {
+            return Self::default();
+        }
+
+        let mut min = Vec2f::uniform(f32::MAX);
+        let mut max = Vec2f::uniform(f32::MIN);
+
+        for extents in sectors.iter().map(|sector| sector.extents()) {
+            min = Vec2f::new(min.x.min(extents.min.x), min.y.min(extents.min.y));
+            max = Vec2f::new(max.x.max(extents.max.x), max.y.max(extents.max.y));
+        }
+
+        let bounds = AABB::new(min, max);
+        let mut index = Self {
+            sectors: UniformGrid::new(&bounds, GRID_CELL_SIZE),
+            walls: UniformGrid::new(&bounds, GRID_CELL_SIZE),
+        };
+
+        for sector_index in 0..sectors.len() {
+            index.insert_sector(sector_index, sectors);
+        }
+
+        index
+    }
+
+    /// Returns the index of the sector containing the point, if any, like [find_sector]. Where
+    /// sectors overlap this may not be the one the point was reached through, so prefer
+    /// [MapIndex::find_sector_near] when that is known.
+    pub fn find_sector(&self, point: Vec2f, sectors: &[Sector]) -> Option<usize> {
+        // Not built yet, so fall back to checking every sector
+        if self.sectors.is_empty() {
+            return find_sector(point, sectors);
+        }
+
+        self.sectors
+            .query_point(point)
+            .iter()
+            .copied()
+            .filter(|i| sectors[*i].contains_point(point))
+            .min()
+    }
+
+    /// Returns the index of the sector containing the point, preferring the `near` sector and then
+    /// its neighbours through portals, before any others. In maps with overlapping sectors, this
+    /// picks the one that is joined to where the point was reached from.
+    pub fn find_sector_near(&self, point: Vec2f, sectors: &[Sector], near: usize) -> Option<usize> {
+        let sector = &sectors[near];
+        if sector.contains_point(point) {
+            return Some(near);
+        }
+
+        sector
+            .walls
+            .iter()
+            .filter_map(|wall| wall.portal.as_ref())
+            .map(|portal| portal.sector)
+            .find(|index| sectors[*index].contains_point(point))
+            .or_else(|| self.find_sector(point, sectors))
+    }
+
+    /// Moves a sector and its walls to the cells they now cover, after its walls have moved. Parts
+    /// that move beyond the bounds of the whole map are kept in its edge cells.
+    pub fn update_sector(&mut self, sector_index: usize, sectors: &[Sector]) {
+        self.sectors.remove(sector_index);
+        self.walls.retain(|wall| wall.sector_index != sector_index);
+
+        self.insert_sector(sector_index, sectors);
+    }
+
+    fn insert_sector(&mut self, sector_index: usize, sectors: &[Sector]) {
+        let sector = &sectors[sector_index];
+        self.sectors.insert(sector_index, &sector.extents());
+
+        for (wall_index, wall) in sector.walls.iter().enumerate() {
+            let id = WallId {
+                sector_index,
+                wall_index,
+            };
+            self.walls.insert_segment(id, &wall.segment);
+        }
+    }
+
+    /// Adds the walls that may overlap the bounds to `walls`, from any sector
+    pub fn walls_near(&self, bounds: &AABB, walls: &mut Vec<WallId>) {
+        self.walls.query_aabb(bounds, walls);
+    }
+}
+
+/// Returns whether two sectors are the same, or joined by a portal in either direction
+pub fn are_adjacent(sectors: &[Sector], a: usize, b: usize) -> bool {
+    let joins = |from: usize, to: usize| {
+        sectors[from]
+            .walls
+            .iter()
+            .any(|wall| wall.portal.is_some_and(|portal| portal.sector == to))
+    };
+
+    a == b || joins(a, b) || joins(b, a)
+}
+
+/// Returns whether the areas of two sectors overlap, rather than only meeting along their edges.
+/// This can't happen in a flat map, but portals can join sectors that share the same space, for a
+/// corridor that is longer on the inside than the outside.
+pub fn sectors_overlap(a: &Sector, b: &Sector) -> bool {
+    /// Overlaps shallower than this are rounding errors along a shared edge
+    const EPSILON: f32 = 0.01;
+
+    let project = |sector: &Sector, axis: Vec2f| {
+        sector
+            .walls
+            .iter()
+            .map(|wall| wall.segment.a.dot(axis))
+            .fold((f32::MAX, f32::MIN), |(min, max), d| {
+                (min.min(d), max.max(d))
+            })
+    };
+
+    // Sectors are convex, so they overlap unless the normal of one of their walls separates them
+    a.walls
+        .iter()
+        .chain(b.walls.iter())
+        .map(|wall| wall.normal)
+        .all(|axis| {
+            let (a_min, a_max) = project(a, axis);
+            let (b_min, b_max) = project(b, axis);
+
+            a_max - EPSILON > b_min && b_max - EPSILON > a_min
+        })
+}
+
+/// Returns whether there is a gap between the floor and ceiling where two sectors meet, e.g. it is
+/// false for a closed door
+fn is_open_between(a: &Sector, b: &Sector) -> bool {
+    a.ceiling.height.min(b.ceiling.height) > a.floor.height.max(b.floor.height)
+}
+
+/// Searches breadth-first through the portals from the `start` sector, returning the number of
+/// portals crossed to reach each sector (or `None` if it was not reached within `max_depth`).
+/// Portals are only followed where they are open, so closed doors stop the flood.
+pub fn flood_sectors(sectors: &[Sector], start: usize, max_depth: usize) -> Vec<Option<usize>> {
+    let mut depths = vec![None; sectors.len()];
+    let mut queue = VecDeque::new();
+
+    depths[start] = Some(0);
+    queue.push_back((start, 0));
+
+    while let Some((sector_index, depth)) = queue.pop_front() {
+        if depth == max_depth {
+            continue;
+        }
+
+        let sector = &sectors[sector_index];
+
+        for portal in sector.walls.iter().filter_map(|wall| wall.portal.as_ref()) {
+            if depths[portal.sector].is_none() && is_open_between(sector, &sectors[portal.sector]) {
+                depths[portal.sector] = Some(depth + 1);
+                queue.push_back((portal.sector, depth + 1));
+            }
+        }
+    }
+
+    depths
+}
+
+/// Returns whether there is an unobstructed line between two points, where `from` lies within the
+/// sector at `sector_index`. The line may pass through visible portals, as long as it fits through
+/// the gap they leave between the floors and ceilings on either side at the point where it crosses.
+pub fn line_of_sight(
+    sectors: &[Sector],
+    sector_index: usize,
+    from: Vec2f,
+    from_z: f32,
+    to: Vec2f,
+    to_z: f32,
+) -> bool {
+    let ray = Segment::new(from, to);
+    let direction = to - from;
+    let length_sq = direction.magnitude_sq();
+
+    if length_sq == 0.0 {
+        return true;
+    }
+
+    let mut sector_index = sector_index;
+    let mut prev_sector_index = None;
+    // Fraction of the way along the line at which the current sector was entered
+    let mut t_entry = 0.0;
+
+    // Each step enters a different sector, so this only runs out on malformed maps
+    for _ in 0..sectors.len() {
+        let sector = &sectors[sector_index];
+
+        // Sectors are convex, so the nearest wall that the line crosses is where it leaves
+        let exit = sector
+            .walls
+            .iter()
+            .filter(|wall| {
+                wall.portal
+                    .is_none_or(|portal| Some(portal.sector) != prev_sector_index)
+            })
+            .filter_map(|wall| {
+                let point = ray.intersection(&wall.segment)?;
+                let t = (point - from).dot(direction) / length_sq;
+
+                (t >= t_entry).then_some((t, wall))
+            })
+            .min_by(|a, b| a.0.total_cmp(&b.0));
+
+        // The line ends within this sector
+        let Some((t, wall)) = exit else {
+            return true;
+        };
+
+        let Some(portal) = wall.portal.filter(|portal| portal.visible) else {
+            return false;
+        };
+
+        let next_sector = &sectors[portal.sector];
+        let z = from_z + (to_z - from_z) * t;
+
+        let (bottom, top) = portal.gap(sector, next_sector);
+        if z <= bottom || z >= top {
+            return false;
+        }
+
+        prev_sector_index = Some(sector_index);
+        sector_index = portal.sector;
+        t_entry = t;
+    }
+
+    false
+}
+
+/// Returns the nearest wall crossed by the line between two points, out of those accepted by the
+/// filter, where `from` lies within the sector at `sector_index`. The line follows portals rather
+/// than checking every wall it crosses, so it can't hit walls of sectors that only overlap the ones
+/// it passes through. It stops at the first wall that isn't a portal, whether or not that is
+/// accepted.
+pub fn ray_cast(
+    sectors: &[Sector],
+    sector_index: usize,
+    from: Vec2f,
+    to: Vec2f,
+    filter: impl Fn(&Wall) -> bool,
+) -> Option<RayHit> {
+    let ray = Segment::new(from, to);
+    let direction = to - from;
+    let length_sq = direction.magnitude_sq();
+
+    if length_sq == 0.0 {
+        return None;
+    }
+
+    let mut sector_index = sector_index;
+    let mut prev_sector_index = None;
+    let mut t_entry = 0.0;
+
+    for _ in 0..sectors.len() {
+        let sector = &sectors[sector_index];
+
+        let (t, wall_index, wall) = sector
+            .walls
+            .iter()
+            .enumerate()
+            .filter(|(_, wall)| {
+                wall.portal
+                    .is_none_or(|portal| Some(portal.sector) != prev_sector_index)
+            })
+            .filter_map(|(wall_index, wall)| {
+                let point = ray.intersection(&wall.segment)?;
+                let t = (point - from).dot(direction) / length_sq;
+
+                (t >= t_entry).then_some((t, wall_index, wall))
+            })
+            .min_by(|a, b| a.0.total_cmp(&b.0))?;
+
+        if filter(wall) {
+            return Some(RayHit {
+                wall: WallId {
+                    sector_index,
+                    wall_index,
+                },
+                t,
+            });
+        }
+
+        let portal = wall.portal?;
+        prev_sector_index = Some(sector_index);
+        sector_index = portal.sector;
+        t_entry = t;
+    }
+
+    None
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+    use crate::surface::{Plane, PlaneTexture, Portal, WallTexture};
+
+    /// Sector with a wall from each vertex to the next, which should run clockwise
+    pub(crate) fn sector(id: usize, vertices: &[Vec2f]) -> Sector {
+        let texture = WallTexture::new(0, Vec2f::ZERO, Vec2f::uniform(1.0));
+        let plane = PlaneTexture::new(0, Vec2f::ZERO, Vec2f::uniform(1.0), 0.0);
+        let count = vertices.len();
+
+        Sector {
+            id,
+            walls: (0..count)
+                .map(|i| Wall::new(vertices[i], vertices[(i + 1) % count], texture, None))
+                .collect(),
+            floor: Plane::new(0.0, plane),
+            ceiling: Plane::new(20.0, plane),
+            hazard: None,
+            fog: None,
+        }
+    }
+
+    /// Rectangular room between two corners, with the wall along `max.y` first
+    pub(crate) fn room(id: usize, min: Vec2f, max: Vec2f) -> Sector {
+        let corners = [Vec2f::new(min.x, max.y), max, Vec2f::new(max.x, min.y), min];
+        sector(id, &corners)
+    }
+
+    /// Joins the wall along the top of sector `a` to the wall along the bottom of sector `b`
+    fn join(sectors: &mut [Sector], a: usize, b: usize) {
+        let texture = WallTexture::new(0, Vec2f::ZERO, Vec2f::uniform(1.0));
+        sectors[a].walls[0].portal = Some(Portal::new(b, texture, texture));
+        sectors[b].walls[2].portal = Some(Portal::new(a, texture, texture));
+    }
+
+    /// Two square rooms, the second above the first along +y and open to it
+    pub(crate) fn two_rooms() -> Vec<Sector> {
+        let mut sectors = vec![
+            room(0, Vec2f::new(0.0, 0.0), Vec2f::new(50.0, 50.0)),
+            room(1, Vec2f::new(0.0, 50.0), Vec2f::new(50.0, 100.0)),
+        ];
+        join(&mut sectors, 0, 1);
+        sectors
+    }
+
+    fn is_solid(wall: &Wall) -> bool {
+        wall.portal.is_none()
+    }
+
+    #[test]
+    fn ray_cast_portal_test() {
+        let mut sectors = two_rooms();
+        // Shares the space of the second room, but isn't joined to anything
+        sectors.push(room(2, Vec2f::new(10.0, 60.0), Vec2f::new(40.0, 90.0)));
+
+        let from = Vec2f::new(25.0, 10.0);
+
+        // Carries on through the portal to the far wall of the second room, without hitting the
+        // walls of the sector that only overlaps it
+        let hit = ray_cast(&sectors, 0, from, Vec2f::new(25.0, 150.0), is_solid).unwrap();
+        let expected = WallId {
+            sector_index: 1,
+            wall_index: 0,
+        };
+        assert_eq!(hit.wall, expected);
+        assert!((hit.t - 90.0 / 140.0).abs() < 1e-5);
+
+        // Stops at the portal itself when that is accepted
+        let hit = ray_cast(&sectors, 0, from, Vec2f::new(25.0, 150.0), |_| true).unwrap();
+        assert_eq!(hit.wall.sector_index, 0);
+        assert!(hit.wall.get(&sectors).portal.is_some());
+        assert!((hit.t - 40.0 / 140.0).abs() < 1e-5);
+
+        // Ends within the second room, short of any wall
+        assert!(ray_cast(&sectors, 0, from, Vec2f::new(25.0, 95.0), is_solid).is_none());
+
+        // Back the other way, without turning around at the portal it came through
+        let hit = ray_cast(
+            &sectors,
+            1,
+            Vec2f::new(25.0, 90.0),
+            Vec2f::new(25.0, -50.0),
+            is_solid,
+        );
+        let hit = hit.unwrap();
+        assert_eq!(hit.wall.sector_index, 0);
+        assert_eq!(hit.wall.wall_index, 2);
+    }
+
+    #[test]
+    fn ray_cast_corner_test() {
+        let sectors = two_rooms();
+
+        // Through the corner where the portal meets a solid wall, which it hits either side of the
+        // portal, at the corner itself
+        let hit = ray_cast(
+            &sectors,
+            0,
+            Vec2f::new(25.0, 25.0),
+            Vec2f::new(75.0, 75.0),
+            is_solid,
+        )
+        .unwrap();
+        assert!(hit.wall.get(&sectors).portal.is_none());
+        assert!((hit.t - 0.5).abs() < 1e-5);
+
+        // Along a wall and through the end of the portal, where it misses both walls it grazes
+        let along = ray_cast(
+            &sectors,
+            0,
+            Vec2f::new(0.0, 10.0),
+            Vec2f::new(0.0, 90.0),
+            is_solid,
+        );
+        assert!(along.is_none());
+    }
+
+    #[test]
+    fn ray_cast_degenerate_test() {
+        let mut sectors = two_rooms();
+        let from = Vec2f::new(25.0, 25.0);
+
+        // A ray with no length hits nothing
+        assert!(ray_cast(&sectors, 0, from, from, |_| true).is_none());
+
+        // A wall with no length at the corner is never hit, and doesn't stop the ray reaching the
+        // walls either side of it
+        let texture = WallTexture::new(0, Vec2f::ZERO, Vec2f::uniform(1.0));
+        let corner = Vec2f::new(50.0, 50.0);
+        sectors[0]
+            .walls
+            .insert(1, Wall::new(corner, corner, texture, None));
+        let hit = ray_cast(&sectors, 0, from, Vec2f::new(75.0, 75.0), is_solid).unwrap();
+        let wall = hit.wall.get(&sectors);
+        assert_ne!(wall.segment.a, wall.segment.b);
+        assert!((hit.t - 0.5).abs() < 1e-5);
+
+        // Portals into a sector with no walls lead nowhere
+        sectors[1].walls.clear();
+        assert!(ray_cast(&sectors, 0, from, Vec2f::new(25.0, 150.0), is_solid).is_none());
+    }
+
+    #[test]
+    fn sectors_overlap_test() {
+        let square = room(0, Vec2f::new(0.0, 0.0), Vec2f::new(50.0, 50.0));
+
+        // Meeting along an edge or at a corner isn't overlapping
+        let above = room(1, Vec2f::new(0.0, 50.0), Vec2f::new(50.0, 100.0));
+        let diagonal = room(1, Vec2f::new(50.0, 50.0), Vec2f::new(100.0, 100.0));
+        assert!(!sectors_overlap(&square, &above));
+        assert!(!sectors_overlap(&square, &diagonal));
+
+        let apart = room(1, Vec2f::new(100.0, 0.0), Vec2f::new(150.0, 50.0));
+        assert!(!sectors_overlap(&square, &apart));
+
+        let across = room(1, Vec2f::new(25.0, 25.0), Vec2f::new(75.0, 75.0));
+        let inside = room(1, Vec2f::new(10.0, 10.0), Vec2f::new(20.0, 20.0));
+        assert!(sectors_overlap(&square, &across));
+        assert!(sectors_overlap(&across, &square));
+        assert!(sectors_overlap(&square, &inside));
+        assert!(sectors_overlap(&square, &square));
+    }
+
+    #[test]
+    fn find_sector_near_test() {
+        // The first sector shares the space of the third, which only the second is joined to
+        let mut sectors = vec![room(0, Vec2f::new(0.0, 50.0), Vec2f::new(50.0, 100.0))];
+        sectors.extend(two_rooms().into_iter().map(|mut sector| {
+            sector.id += 1;
+            for portal in sector
+                .walls
+                .iter_mut()
+                .filter_map(|wall| wall.portal.as_mut())
+            {
+                portal.sector += 1;
+            }
+            sector
+        }));
+        let index = MapIndex::build(&sectors);
+
+        let point = Vec2f::new(25.0, 75.0);
+        assert_eq!(index.find_sector(point, &sectors), Some(0));
+        assert_eq!(index.find_sector_near(point, &sectors, 1), Some(2));
+        assert_eq!(index.find_sector_near(point, &sectors, 2), Some(2));
+        assert_eq!(index.find_sector_near(point, &sectors, 0), Some(0));
+
+        // Falls back to any sector containing the point, then to none
+        let below = Vec2f::new(25.0, 25.0);
+        assert_eq!(index.find_sector_near(below, &sectors, 0), Some(1));
+        let outside = Vec2f::new(-10.0, 25.0);
+        assert_eq!(index.find_sector_near(outside, &sectors, 1), None);
+    }
+
+    /// Returns whether each wall of the sector ends where the next one starts
+    fn is_closed(sector: &Sector) -> bool {
+        let count = sector.walls.len();
+        (0..count).all(|i| sector.walls[i].segment.b == sector.walls[(i + 1) % count].segment.a)
+    }
+
+    #[test]
+    fn split_wall_test() {
+        let mut sectors = two_rooms();
+        let portal = WallId {
+            sector_index: 0,
+            wall_index: 0,
+        };
+        let left = WallId {
+            sector_index: 1,
+            wall_index: 3,
+        };
+
+        // Both sides of the portal are split at the same point, and still match up as twins
+        let split = split_wall(&mut sectors, portal, Vec2f::new(20.0, 50.0)).unwrap();
+        let twin_rest = split.twin_rest.unwrap();
+        assert_eq!(split.rest.wall_index, 1);
+        assert_eq!(twin_rest.sector_index, 1);
+        assert_eq!(sectors[0].walls.len(), 5);
+        assert_eq!(sectors[1].walls.len(), 5);
+        assert_eq!(portal.get(&sectors).segment.b, Vec2f::new(20.0, 50.0));
+        assert_eq!(twin_wall(&sectors, portal), Some(twin_rest));
+        assert_eq!(twin_wall(&sectors, twin_rest), Some(portal));
+        assert!(twin_wall(&sectors, split.rest).is_some());
+
+        // Walls after the new ones move up to make room
+        let moved = split.remap(left);
+        assert_eq!(moved.wall_index, 4);
+        assert_eq!(moved.get(&sectors).segment.a, Vec2f::new(0.0, 50.0));
+
+        for sector in sectors.iter() {
+            assert!(is_closed(sector));
+            assert!(sector.polygon().is_convex());
+        }
+
+        // There is nothing to split off at either end
+        assert!(split_wall(&mut sectors, portal, Vec2f::new(0.0, 50.0)).is_none());
+        assert_eq!(sectors[0].walls.len(), 5);
+    }
+
+    #[test]
+    fn insert_wall_test() {
+        let texture = WallTexture::new(0, Vec2f::ZERO, Vec2f::uniform(1.0));
+        let mut square = room(0, Vec2f::new(0.0, 0.0), Vec2f::new(50.0, 50.0));
+
+        // Cuts off a corner, with the walls either side shortened to meet it
+        let (a, b) = (Vec2f::new(40.0, 50.0), Vec2f::new(50.0, 40.0));
+        square.insert_wall(1, Wall::new(a, b, texture, None));
+        assert_eq!(square.walls.len(), 5);
+        assert_eq!(square.walls[0].segment.b, a);
+        assert_eq!(square.walls[2].segment.a, b);
+        assert_eq!(square.walls[0].width, 40.0);
+        assert!(is_closed(&square));
+        assert!(square.polygon().is_convex());
+
+        // Wrapping around to meet the first wall when added at the end
+        let (a, b) = (Vec2f::new(0.0, 10.0), Vec2f::new(10.0, 50.0));
+        square.insert_wall(5, Wall::new(a, b, texture, None));
+        assert_eq!(square.walls.len(), 6);
+        assert_eq!(square.walls[0].segment.a, b);
+        assert!(is_closed(&square));
+        assert!(square.polygon().is_convex());
+    }
+
+    #[test]
+    fn remove_wall_test() {
+        let mut square = vec![room(0, Vec2f::new(0.0, 0.0), Vec2f::new(50.0, 50.0))];
+        let ends = |sector: &Sector| -> Vec<(Vec2f, Vec2f)> {
+            sector
+                .walls
+                .iter()
+                .map(|wall| (wall.segment.a, wall.segment.b))
+                .collect()
+        };
+        let original = ends(&square[0]);
+        let right = WallId {
+            sector_index: 0,
+            wall_index: 1,
+        };
+
+        // Removing the first half of a split wall stretches the second back over it
+        split_wall(&mut square, right, Vec2f::new(50.0, 25.0)).unwrap();
+        remove_wall(&mut square, right).unwrap();
+        assert_eq!(ends(&square[0]), original);
+
+        // The twin of a removed portal is closed off
+        let mut sectors = two_rooms();
+        let portal = WallId {
+            sector_index: 0,
+            wall_index: 0,
+        };
+        let removed = remove_wall(&mut sectors, portal).unwrap();
+        assert!(removed.portal.is_some());
+        assert!(sectors[1].walls.iter().all(|wall| wall.portal.is_none()));
+        assert_eq!(sectors[0].walls[0].segment.a, Vec2f::new(0.0, 50.0));
+        assert!(is_closed(&sectors[0]));
+        assert_eq!(
+            remove_wall(&mut sectors, portal).unwrap_err(),
+            RemoveWallError::TooFewWalls
+        );
+
+        // Moving the start of a portal would leave it apart from its twin
+        let mut sectors = two_rooms();
+        let before_portal = WallId {
+            sector_index: 0,
+            wall_index: 3,
+        };
+        assert_eq!(
+            remove_wall(&mut sectors, before_portal).unwrap_err(),
+            RemoveWallError::MovesPortal
+        );
+        assert_eq!(sectors[0].walls.len(), 4);
+        assert_eq!(
+            twin_wall(&sectors, portal).map(|twin| twin.wall_index),
+            Some(2)
+        );
+
+        // Leaving only points along a line, with no area between them
+        let mut triangle = vec![sector(
+            0,
+            &[
+                Vec2f::new(0.0, 50.0),
+                Vec2f::new(50.0, 0.0),
+                Vec2f::new(0.0, 0.0),
+            ],
+        )];
+        let hypotenuse = WallId {
+            sector_index: 0,
+            wall_index: 0,
+        };
+        split_wall(&mut triangle, hypotenuse, Vec2f::new(25.0, 25.0)).unwrap();
+        let bottom = WallId {
+            sector_index: 0,
+            wall_index: 2,
+        };
+        assert_eq!(
+            remove_wall(&mut triangle, bottom).unwrap_err(),
+            RemoveWallError::NotConvex
+        );
+        assert_eq!(triangle[0].walls.len(), 4);
+    }
+}
@@ -0,0 +1,357 @@
+//! Merging of neighbouring sectors that can't be told apart, done once when a map is loaded.
+//! Imported maps are often cut into many small sectors with the same floor and ceiling, and each
+//! portal between them costs the renderer a node to traverse without there being anything to draw.
+
+use std::mem;
+
+use maths::geometry::Polygon;
+
+use crate::{
+    map::WallId,
+    surface::{Plane, Portal, Sector, Wall},
+};
+
+/// Where each sector of a map ended up after [merge_sectors]
+#[derive(Debug, Clone, Default)]
+pub struct SectorRemap {
+    /// Index of each sector after merging, by its index before
+    new_indices: Vec<usize>,
+}
+
+impl SectorRemap {
+    /// Returns where a sector is now, which is the sector it was merged into if it was. Returns
+    /// `None` if there was no such sector before merging.
+    pub fn get(&self, index: usize) -> Option<usize> {
+        self.new_indices.get(index).copied()
+    }
+
+    /// Returns the index that a sector had before merging. Where sectors were merged into it, this
+    /// is the lowest of their indices, which is the one it kept.
+    pub fn original(&self, index: usize) -> usize {
+        self.new_indices
+            .iter()
+            .position(|&new_index| new_index == index)
+            .unwrap_or(index)
+    }
+}
+
+/// Merges each pair of neighbouring sectors that look and behave the same, and whose outlines
+/// together are still convex, into one, removing the walls between them. This repeats until no
+/// more pairs can be merged, so a room cut into many pieces becomes as few as it can be. Sectors
+/// in `pinned`, such as doors, lifts and those the level refers to by index, are left alone.
+///
+/// Of each pair, the sector with the lower index is kept, and the rest move down to fill the gaps,
+/// so any indices into the map need updating with the returned [SectorRemap]. Any
+/// [MapIndex](crate::map::MapIndex) needs building again afterwards.
+pub fn merge_sectors(sectors: &mut Vec<Sector>, pinned: &[usize]) -> SectorRemap {
+    // Sector that each sector has been merged into so far, or itself if it hasn't been
+    let mut merged_into: Vec<usize> = (0..sectors.len()).collect();
+    let mut neighbours = Vec::new();
+
+    let mut merged_any = true;
+    while merged_any {
+        merged_any = false;
+
+        for a in 0..sectors.len() {
+            if merged_into[a] != a || pinned.contains(&a) {
+                continue;
+            }
+
+            neighbours.clear();
+            neighbours.extend(
+                sectors[a]
+                    .walls
+                    .iter()
+                    .filter_map(|wall| wall.portal.map(|portal| portal.sector))
+                    .filter(|&b| b != a && !pinned.contains(&b)),
+            );
+            neighbours.dedup();
+
+            for &b in neighbours.iter() {
+                let (keep, remove) = (a.min(b), a.max(b));
+                let Some(outline) = merged_outline(sectors, keep, remove) else {
+                    continue;
+                };
+
+                let mut walls: Vec<Option<Wall>> = mem::take(&mut sectors[keep].walls)
+                    .into_iter()
+                    .map(Some)
+                    .collect();
+                let mut removed_walls: Vec<Option<Wall>> = mem::take(&mut sectors[remove].walls)
+                    .into_iter()
+                    .map(Some)
+                    .collect();
+
+                sectors[keep].walls = outline
+                    .iter()
+                    .filter_map(|id| match id.sector_index == keep {
+                        true => walls[id.wall_index].take(),
+                        false => removed_walls[id.wall_index].take(),
+                    })
+                    .collect();
+
+                // The removed sector is left without walls until the end, so that indices stay put
+                // whilst merging
+                for wall in sectors
+                    .iter_mut()
+                    .flat_map(|sector| sector.walls.iter_mut())
+                {
+                    if let Some(portal) = wall.portal.as_mut().filter(|p| p.sector == remove) {
+                        portal.sector = keep;
+                    }
+                }
+                for merged in merged_into.iter_mut().filter(|merged| **merged == remove) {
+                    *merged = keep;
+                }
+
+                merged_any = true;
+                break;
+            }
+        }
+    }
+
+    // Sectors that were kept are numbered in order, and the rest take the number of the sector
+    // they were merged into
+    let mut kept = 0;
+    let kept_indices: Vec<usize> = merged_into
+        .iter()
+        .enumerate()
+        .map(|(index, &merged)| {
+            kept += usize::from(merged == index);
+            kept.saturating_sub(1)
+        })
+        .collect();
+    let new_indices: Vec<usize> = merged_into
+        .iter()
+        .map(|&merged| kept_indices[merged])
+        .collect();
+
+    let mut index = 0;
+    sectors.retain(|_| {
+        index += 1;
+        merged_into[index - 1] == index - 1
+    });
+
+    for (index, sector) in sectors.iter_mut().enumerate() {
+        sector.id = index;
+
+        for portal in sector
+            .walls
+            .iter_mut()
+            .filter_map(|wall| wall.portal.as_mut())
+        {
+            portal.sector = new_indices[portal.sector];
+        }
+    }
+
+    SectorRemap { new_indices }
+}
+
+/// Returns the walls of the outline that two sectors would have as one, in order, or `None` if they
+/// can't be merged. They can't if they differ in any way, if anything other than a plain opening
+/// divides them, or if together they wouldn't be convex.
+fn merged_outline(sectors: &[Sector], a: usize, b: usize) -> Option<Vec<WallId>> {
+    if !compatible(&sectors[a], &sectors[b]) {
+        return None;
+    }
+
+    let (a_start, a_count) = shared_walls(&sectors[a], b)?;
+    let (b_start, b_count) = shared_walls(&sectors[b], a)?;
+
+    // Each sector's own walls, starting from the end of those it shares, run from one end of the
+    // shared stretch to the other, so one after the other they go all the way around
+    let remaining = |sector_index: usize, start: usize, count: usize| {
+        let wall_count = sectors[sector_index].walls.len();
+        (count..wall_count).map(move |offset| WallId {
+            sector_index,
+            wall_index: (start + offset) % wall_count,
+        })
+    };
+    let outline: Vec<WallId> = remaining(a, a_start, a_count)
+        .chain(remaining(b, b_start, b_count))
+        .collect();
+
+    let closed = outline.iter().enumerate().all(|(index, id)| {
+        let next = outline[(index + 1) % outline.len()];
+        id.get(sectors).segment.b == next.get(sectors).segment.a
+    });
+    if !closed {
+        return None;
+    }
+
+    let vertices = outline.iter().map(|id| id.get(sectors).segment.a).collect();
+    Polygon::from_vertices(vertices)
+        .is_convex()
+        .then_some(outline)
+}
+
+/// Returns whether nothing would look, sound or behave any differently if the two sectors were
+/// one. Hazards, fog and baked lighting are ones that can't be compared, so sectors with any of
+/// them are left alone.
+fn compatible(a: &Sector, b: &Sector) -> bool {
+    let planes_match = |a: &Plane, b: &Plane| {
+        a.height == b.height
+            && a.texture_data == b.texture_data
+            && a.conveyor == b.conveyor
+            && a.lightmap.is_none()
+            && b.lightmap.is_none()
+    };
+
+    planes_match(&a.floor, &b.floor)
+        && planes_match(&a.ceiling, &b.ceiling)
+        && a.hazard.is_none()
+        && b.hazard.is_none()
+        && a.fog.is_none()
+        && b.fog.is_none()
+}
+
+/// Returns the index of the first of a sector's walls that lead into `other`, and how many there
+/// are, or `None` if there are none that can be removed. They can only be removed if they are plain
+/// openings with a matching wall on the other side, and run one after the other, as otherwise
+/// merging would leave a hole in the outline or a window floating in the room.
+fn shared_walls(sector: &Sector, other: usize) -> Option<(usize, usize)> {
+    let wall_count = sector.walls.len();
+    let leads_to_other = |index: usize| {
+        let wall = &sector.walls[index % wall_count];
+        wall.portal.is_some_and(|portal| portal.sector == other)
+    };
+
+    let count = (0..wall_count)
+        .filter(|&index| leads_to_other(index))
+        .count();
+    if count == 0 || count == wall_count {
+        return None;
+    }
+
+    let start = (0..wall_count)
+        .find(|&index| leads_to_other(index) && !leads_to_other(index + wall_count - 1))?;
+    let removable = (start..start + count).all(|index| {
+        let wall = &sector.walls[index % wall_count];
+        wall.portal.is_some_and(is_plain)
+    });
+
+    removable.then_some((start, count))
+}
+
+/// Returns whether a portal is an opening that can be seen and walked through, with nothing
+/// in it and no wall around it
+fn is_plain(portal: Portal) -> bool {
+    portal.visible && portal.passable && portal.tint.is_none() && portal.opening.is_none()
+}
+
+#[cfg(test)]
+mod tests {
+    use maths::linear::Vec2f;
+
+    use super::*;
+    use crate::{
+        camera::Camera,
+        consts::{GRASS, PLANK, STONE_BRICK},
+        golden_tests::{load_renderer, WIDTH},
+        surface::{PlaneTexture, WallTexture},
+    };
+
+    /// Square rooms in a row along +y, each open to the next, and otherwise all the same
+    fn row_of_rooms(count: usize) -> Vec<Sector> {
+        const SIZE: f32 = 50.0;
+        let wall = WallTexture::new(STONE_BRICK, Vec2f::ZERO, Vec2f::uniform(5.0));
+        let floor = PlaneTexture::new(GRASS, Vec2f::ZERO, Vec2f::uniform(5.0), 0.0);
+        let ceiling = PlaneTexture::new(PLANK, Vec2f::ZERO, Vec2f::uniform(5.0), 0.0);
+
+        (0..count)
+            .map(|index| {
+                let (y_min, y_max) = (index as f32 * SIZE, (index + 1) as f32 * SIZE);
+                let portal = |sector| Some(Portal::new(sector, wall, wall));
+
+                Sector {
+                    id: index,
+                    walls: vec![
+                        Wall::new(
+                            Vec2f::new(0.0, y_max),
+                            Vec2f::new(SIZE, y_max),
+                            wall,
+                            (index + 1 < count).then(|| portal(index + 1)).flatten(),
+                        ),
+                        Wall::new(Vec2f::new(SIZE, y_max), Vec2f::new(SIZE, y_min), wall, None),
+                        Wall::new(
+                            Vec2f::new(SIZE, y_min),
+                            Vec2f::new(0.0, y_min),
+                            wall,
+                            index.checked_sub(1).and_then(portal),
+                        ),
+                        Wall::new(Vec2f::new(0.0, y_min), Vec2f::new(0.0, y_max), wall, None),
+                    ],
+                    floor: Plane::new(0.0, floor),
+                    ceiling: Plane::new(25.0, ceiling),
+                    hazard: None,
+                    fog: None,
+                }
+            })
+            .collect()
+    }
+
+    #[test]
+    fn merged_sectors_test() {
+        let (assets, mut renderer) = load_renderer();
+
+        // Looking down the row from the first room
+        let mut render = |sectors: &[Sector]| {
+            let mut camera = Camera::new(Vec2f::new(25.0, 10.0), 15.0);
+            camera.set_rotation(0.0, 0.0);
+            renderer
+                .update(&camera, 0, &assets.textures, sectors, [])
+                .unwrap();
+            (
+                renderer.framebuffer().pixels().to_vec(),
+                renderer.stats().portal_nodes,
+            )
+        };
+
+        let rooms = row_of_rooms(3);
+        let (pixels, portal_nodes) = render(&rooms);
+
+        let mut merged = row_of_rooms(3);
+        let remap = merge_sectors(&mut merged, &[]);
+        assert_eq!(merged.len(), 1);
+        assert_eq!(merged[0].walls.len(), 8);
+        assert!(merged[0].walls.iter().all(|wall| wall.portal.is_none()));
+        assert!(merged[0].polygon().is_convex());
+        assert_eq!(remap.get(2), Some(0));
+        assert_eq!(remap.original(0), 0);
+
+        // Nothing between the rooms was visible, so they look the same as one, for less
+        // traversal. Only the odd pixel differs, where clipping to the portals rounded differently
+        // at their edges.
+        let (merged_pixels, merged_portal_nodes) = render(&merged);
+        let differing = pixels
+            .iter()
+            .zip(merged_pixels.iter())
+            .filter(|(a, b)| (a.r, a.g, a.b) != (b.r, b.g, b.b))
+            .count();
+        assert!(differing < WIDTH, "{differing} pixels differ once merged");
+        assert!(merged_portal_nodes < portal_nodes);
+
+        // Pinning the middle room keeps the ends apart
+        let mut pinned = row_of_rooms(3);
+        merge_sectors(&mut pinned, &[1]);
+        assert_eq!(pinned.len(), 3);
+
+        // A lower ceiling sets the last room apart, which moves down to take the middle's place
+        let mut lowered = row_of_rooms(3);
+        lowered[2].ceiling.height -= 5.0;
+        let remap = merge_sectors(&mut lowered, &[]);
+        assert_eq!(lowered.len(), 2);
+        assert_eq!(remap.get(1), Some(0));
+        assert_eq!(remap.get(2), Some(1));
+        assert_eq!(remap.original(1), 2);
+        assert_eq!(lowered[1].id, 1);
+        assert!(lowered[0]
+            .walls
+            .iter()
+            .any(|wall| wall.portal.is_some_and(|portal| portal.sector == 1)));
+        assert!(lowered[1]
+            .walls
+            .iter()
+            .any(|wall| wall.portal.is_some_and(|portal| portal.sector == 0)));
+    }
+}
@@ -0,0 +1,215 @@
+use maths::{geometry::Segment, linear::Vec2f};
+
+use crate::surface::{Sector, Wall};
+
+/// A rigid transformation, consisting of a rotation about a pivot followed by a translation
+#[derive(Clone, Copy, Debug)]
+pub struct SectorTransform {
+    pub pivot: Vec2f,
+    pub rotation: f32,
+    pub translation: Vec2f,
+}
+
+impl SectorTransform {
+    pub fn new(pivot: Vec2f) -> Self {
+        Self {
+            pivot,
+            rotation: 0.0,
+            translation: Vec2f::ZERO,
+        }
+    }
+
+    /// Transforms a point from local space (the original sector geometry) into world space
+    pub fn apply(&self, point: Vec2f) -> Vec2f {
+        let (sin, cos) = self.rotation.sin_cos();
+        (point - self.pivot).rotate(sin, cos) + self.pivot + self.translation
+    }
+
+    /// Transforms a point from world space back into local space
+    pub fn apply_inverse(&self, point: Vec2f) -> Vec2f {
+        let (sin, cos) = (-self.rotation).sin_cos();
+        (point - self.translation - self.pivot).rotate(sin, cos) + self.pivot
+    }
+}
+
+/// Moves the walls of a sector at runtime (e.g. rotating rooms or moving platforms).
+///
+/// The original wall geometry is kept, and the current transform is applied to it every update.
+/// Walls of other sectors that meet this sector's vertices are moved too, at the ends that meet
+/// them, so that portals remain connected and neighbouring sectors stay closed. Note that the
+/// neighbouring sectors must remain convex for them to be rendered correctly.
+#[derive(Debug)]
+pub struct SectorMotion {
+    pub sector_index: usize,
+    pub transform: SectorTransform,
+    /// Radians per second
+    pub angular_velocity: f32,
+    /// Units per second
+    pub velocity: Vec2f,
+
+    /// Untransformed segments of this sector's walls
+    local_walls: Vec<Segment>,
+    /// Walls of other sectors that move with this sector
+    linked_walls: Vec<LinkedWall>,
+}
+
+/// A wall of another sector with one or both ends at vertices of the moving sector, such as the
+/// other side of a portal, or the walls either side of it
+#[derive(Debug)]
+struct LinkedWall {
+    sector_index: usize,
+    wall_index: usize,
+    /// Untransformed segment of the wall
+    local: Segment,
+    /// Whether each end moves with the sector, rather than staying where it is
+    moves: (bool, bool),
+}
+
+impl SectorMotion {
+    pub fn new(
+        sectors: &[Sector],
+        sector_index: usize,
+        pivot: Vec2f,
+        angular_velocity: f32,
+        velocity: Vec2f,
+    ) -> Self {
+        let sector = &sectors[sector_index];
+        let local_walls: Vec<Segment> = sector.walls.iter().map(|wall| wall.segment).collect();
+        let is_vertex = |point: Vec2f| local_walls.iter().any(|local| local.a == point);
+
+        let mut linked_walls = Vec::new();
+        for (i, other) in sectors.iter().enumerate() {
+            if i == sector_index {
+                continue;
+            }
+
+            for (j, wall) in other.walls.iter().enumerate() {
+                let portal = wall
+                    .portal
+                    .is_some_and(|portal| portal.sector == sector_index);
+                let moves = (
+                    portal || is_vertex(wall.segment.a),
+                    portal || is_vertex(wall.segment.b),
+                );
+
+                if moves.0 || moves.1 {
+                    linked_walls.push(LinkedWall {
+                        sector_index: i,
+                        wall_index: j,
+                        local: wall.segment,
+                        moves,
+                    });
+                }
+            }
+        }
+
+        Self {
+            sector_index,
+            transform: SectorTransform::new(pivot),
+            angular_velocity,
+            velocity,
+
+            local_walls,
+            linked_walls,
+        }
+    }
+
+    /// Advances the motion and updates the walls of the affected sectors, returning the transform
+    /// from before the update. This can be used with [SectorMotion::carry] to move any entities that
+    /// are inside the sector.
+    pub fn update(&mut self, sectors: &mut [Sector], delta_seconds: f32) -> SectorTransform {
+        let prev_transform = self.transform;
+
+        self.transform.rotation += self.angular_velocity * delta_seconds;
+        self.transform.translation += self.velocity * delta_seconds;
+
+        for (wall, local) in sectors[self.sector_index]
+            .walls
+            .iter_mut()
+            .zip(self.local_walls.iter())
+        {
+            set_wall_segment(wall, &self.transform, local);
+        }
+
+        let moved = |point: Vec2f, moves: bool| match moves {
+            true => self.transform.apply(point),
+            false => point,
+        };
+        for linked in self.linked_walls.iter() {
+            let a = moved(linked.local.a, linked.moves.0);
+            let b = moved(linked.local.b, linked.moves.1);
+
+            sectors[linked.sector_index].walls[linked.wall_index].set_segment(a, b);
+        }
+
+        prev_transform
+    }
+
+    /// Returns the indices of the sectors whose walls are moved, starting with this sector. A
+    /// neighbouring sector may appear more than once.
+    pub fn affected_sectors(&self) -> impl Iterator<Item = usize> + '_ {
+        core::iter::once(self.sector_index)
+            .chain(self.linked_walls.iter().map(|linked| linked.sector_index))
+    }
+
+    /// Returns where a point that was attached to the sector, when it had the given previous
+    /// transform, is now located
+    pub fn carry(&self, prev_transform: &SectorTransform, point: Vec2f) -> Vec2f {
+        self.transform.apply(prev_transform.apply_inverse(point))
+    }
+
+    /// Returns the change in rotation since the given previous transform
+    pub fn rotation_delta(&self, prev_transform: &SectorTransform) -> f32 {
+        self.transform.rotation - prev_transform.rotation
+    }
+}
+
+fn set_wall_segment(wall: &mut Wall, transform: &SectorTransform, local: &Segment) {
+    wall.set_segment(transform.apply(local.a), transform.apply(local.b));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::map::{
+        self,
+        tests::{room, two_rooms},
+        WallId,
+    };
+
+    #[test]
+    fn neighbour_test() {
+        // A third room only touches the corner of the second
+        let mut sectors = two_rooms();
+        sectors.push(room(2, Vec2f::new(50.0, 100.0), Vec2f::new(100.0, 150.0)));
+        let mut motion = SectorMotion::new(&sectors, 1, Vec2f::new(25.0, 75.0), 0.5, Vec2f::ZERO);
+        assert_eq!(
+            motion.affected_sectors().collect::<Vec<_>>(),
+            [1, 0, 0, 0, 2, 2]
+        );
+
+        motion.update(&mut sectors, 0.2);
+
+        // The first room still meets the second along the portal, and closes up around the ends of
+        // it that moved, whilst its far corners stay where they were
+        let portal = WallId {
+            sector_index: 0,
+            wall_index: 0,
+        };
+        assert_eq!(
+            map::twin_wall(&sectors, portal).map(|twin| twin.sector_index),
+            Some(1)
+        );
+        for sector in sectors.iter() {
+            let count = sector.walls.len();
+            assert!((0..count)
+                .all(|i| { sector.walls[i].segment.b == sector.walls[(i + 1) % count].segment.a }));
+        }
+        assert_eq!(sectors[0].walls[2].segment.a, Vec2f::new(50.0, 0.0));
+        assert_eq!(sectors[0].walls[2].segment.b, Vec2f::new(0.0, 0.0));
+
+        // Sectors that only meet at a corner are joined there too
+        assert_eq!(sectors[2].walls[3].segment.a, sectors[1].walls[1].segment.a);
+        assert_eq!(sectors[2].walls[3].segment.b, Vec2f::new(50.0, 150.0));
+    }
+}
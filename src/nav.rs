@@ -0,0 +1,426 @@
+//! Navigation mesh built from the floors of sectors, used by enemies to find paths through the map
+
+use std::{cmp::Ordering, collections::BinaryHeap, collections::HashMap};
+
+use maths::{
+    geometry::{Shape, Triangle},
+    linear::Vec2f,
+};
+
+use crate::{
+    map::{self, WallId},
+    surface::Sector,
+};
+
+/// Tallest step between sector floors that can be walked up
+const MAX_STEP_HEIGHT: f32 = 6.0;
+
+#[derive(Debug)]
+pub struct NavTriangle {
+    pub sector_index: usize,
+    pub triangle: Triangle,
+    /// Triangle on the other side of each edge, where edge `i` starts at vertex `i`
+    pub neighbours: [Option<usize>; 3],
+}
+
+impl NavTriangle {
+    fn vertex(&self, i: usize) -> Vec2f {
+        self.triangle.points()[i % 3]
+    }
+
+    /// Whether the point lies within the triangle, including on its edges. Unlike
+    /// [Triangle::contains_point], points on an edge shared with a neighbour are found in both.
+    fn contains_point(&self, point: Vec2f) -> bool {
+        let sides = [0, 1, 2].map(|i| {
+            let (a, b) = (self.vertex(i), self.vertex(i + 1));
+            (b - a).cross(point - a)
+        });
+
+        sides.iter().all(|side| *side >= 0.0) || sides.iter().all(|side| *side <= 0.0)
+    }
+}
+
+/// Candidate triangle in the path search, ordered so that the lowest estimated cost is popped first
+struct Candidate {
+    triangle: usize,
+    estimate: f32,
+}
+
+impl PartialEq for Candidate {
+    fn eq(&self, other: &Self) -> bool {
+        self.estimate == other.estimate
+    }
+}
+
+impl Eq for Candidate {}
+
+impl PartialOrd for Candidate {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Candidate {
+    fn cmp(&self, other: &Self) -> Ordering {
+        other.estimate.total_cmp(&self.estimate)
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct NavMesh {
+    pub triangles: Vec<NavTriangle>,
+}
+
+impl NavMesh {
+    /// Triangulates the floor of each sector, and links triangles that share an edge, either within
+    /// a sector or across a passable portal with a small enough step.
+    pub fn build(sectors: &[Sector]) -> Self {
+        let mut triangles = Vec::new();
+        // Triangle and edge that lies along each wall, indexed by sector then wall
+        let mut wall_edges: Vec<Vec<Option<(usize, usize)>>> = Vec::with_capacity(sectors.len());
+
+        for (sector_index, sector) in sectors.iter().enumerate() {
+            let wall_count = sector.walls.len();
+            let polygon = sector.polygon();
+
+            let first = triangles.len();
+            let mut edges = vec![None; wall_count];
+            // Interior edges, keyed by their vertex indices in ascending order
+            let mut interior: HashMap<(usize, usize), (usize, usize)> = HashMap::new();
+
+            for (i, indices) in polygon.triangulate().into_iter().enumerate() {
+                let triangle_index = first + i;
+
+                triangles.push(NavTriangle {
+                    sector_index,
+                    triangle: Triangle::new(
+                        polygon.vertices[indices[0]],
+                        polygon.vertices[indices[1]],
+                        polygon.vertices[indices[2]],
+                    ),
+                    neighbours: [None; 3],
+                });
+
+                for edge in 0..3 {
+                    let a = indices[edge];
+                    let b = indices[(edge + 1) % 3];
+
+                    if b == (a + 1) % wall_count {
+                        edges[a] = Some((triangle_index, edge));
+                    } else if a == (b + 1) % wall_count {
+                        edges[b] = Some((triangle_index, edge));
+                    } else if let Some((other, other_edge)) = interior.remove(&(a.min(b), a.max(b)))
+                    {
+                        triangles[triangle_index].neighbours[edge] = Some(other);
+                        triangles[other].neighbours[other_edge] = Some(triangle_index);
+                    } else {
+                        interior.insert((a.min(b), a.max(b)), (triangle_index, edge));
+                    }
+                }
+            }
+
+            wall_edges.push(edges);
+        }
+
+        for (sector_index, sector) in sectors.iter().enumerate() {
+            for (wall_index, wall) in sector.walls.iter().enumerate() {
+                let Some(portal) = wall.portal.filter(|portal| portal.passable) else {
+                    continue;
+                };
+                // A window's sill counts as a step, as it has to be climbed over
+                let (bottom, _) = portal.next_heights(&sectors[portal.sector]);
+                if bottom - sector.floor.height > MAX_STEP_HEIGHT {
+                    continue;
+                }
+
+                let id = WallId {
+                    sector_index,
+                    wall_index,
+                };
+                let Some(twin) = map::twin_wall(sectors, id) else {
+                    continue;
+                };
+
+                if let (Some((triangle, edge)), Some((next_triangle, _))) = (
+                    wall_edges[sector_index][wall_index],
+                    wall_edges[twin.sector_index][twin.wall_index],
+                ) {
+                    triangles[triangle].neighbours[edge] = Some(next_triangle);
+                }
+            }
+        }
+
+        Self { triangles }
+    }
+
+    /// Returns the index of the triangle containing the point, searching the given sector first
+    pub fn find_triangle(&self, point: Vec2f, sector_index: usize) -> Option<usize> {
+        let in_sector = self.triangles.iter().position(|triangle| {
+            triangle.sector_index == sector_index && triangle.contains_point(point)
+        });
+
+        in_sector.or_else(|| {
+            self.triangles
+                .iter()
+                .position(|triangle| triangle.contains_point(point))
+        })
+    }
+
+    /// Finds a path between two points, returning the corners to walk between (including the end
+    /// point, but not the start). The path is as short as possible through the triangles that the
+    /// search passes through, rather than following their centres, whilst keeping `radius` away
+    /// from the ends of the edges that it crosses.
+    pub fn find_path(
+        &self,
+        start: Vec2f,
+        start_sector: usize,
+        end: Vec2f,
+        end_sector: usize,
+        radius: f32,
+    ) -> Option<Vec<Vec2f>> {
+        let start_triangle = self.find_triangle(start, start_sector)?;
+        let end_triangle = self.find_triangle(end, end_sector)?;
+
+        let triangles = self.search(start_triangle, end_triangle, end)?;
+
+        // Edges crossed along the way, as (left, right) when facing the direction of travel
+        let mut portals = Vec::with_capacity(triangles.len() + 1);
+        portals.push((start, start));
+
+        for pair in triangles.windows(2) {
+            let triangle = &self.triangles[pair[0]];
+            let edge = triangle
+                .neighbours
+                .iter()
+                .position(|neighbour| *neighbour == Some(pair[1]))?;
+
+            let mut a = triangle.vertex(edge);
+            let mut b = triangle.vertex(edge + 1);
+            let centre = triangle.triangle.centre();
+
+            // Shrink the edge, so that the path doesn't cut corners
+            let length = (b - a).magnitude();
+            if length > radius * 2.0 {
+                let direction = (b - a) / length;
+                a += direction * radius;
+                b -= direction * radius;
+            } else {
+                a = (a + b) * 0.5;
+                b = a;
+            }
+
+            if (a - centre).cross(b - centre) > 0.0 {
+                portals.push((b, a));
+            } else {
+                portals.push((a, b));
+            }
+        }
+
+        portals.push((end, end));
+
+        Some(Self::string_pull(&portals))
+    }
+
+    /// A* search across triangles, returning the triangles passed through from start to end
+    fn search(&self, start: usize, end: usize, end_point: Vec2f) -> Option<Vec<usize>> {
+        let centre = |index: usize| self.triangles[index].triangle.centre();
+
+        let mut costs = vec![f32::INFINITY; self.triangles.len()];
+        let mut came_from = vec![None; self.triangles.len()];
+        let mut open = BinaryHeap::new();
+
+        costs[start] = 0.0;
+        open.push(Candidate {
+            triangle: start,
+            estimate: (end_point - centre(start)).magnitude(),
+        });
+
+        while let Some(Candidate { triangle, .. }) = open.pop() {
+            if triangle == end {
+                let mut path = vec![end];
+                let mut current = end;
+
+                while let Some(previous) = came_from[current] {
+                    path.push(previous);
+                    current = previous;
+                }
+
+                path.reverse();
+                return Some(path);
+            }
+
+            for neighbour in self.triangles[triangle].neighbours.iter().flatten() {
+                let cost = costs[triangle] + (centre(*neighbour) - centre(triangle)).magnitude();
+
+                if cost < costs[*neighbour] {
+                    costs[*neighbour] = cost;
+                    came_from[*neighbour] = Some(triangle);
+                    open.push(Candidate {
+                        triangle: *neighbour,
+                        estimate: cost + (end_point - centre(*neighbour)).magnitude(),
+                    });
+                }
+            }
+        }
+
+        None
+    }
+
+    /// Simple stupid funnel algorithm: pulls the path taut through the sequence of portals, keeping
+    /// only the corners that it bends around
+    fn string_pull(portals: &[(Vec2f, Vec2f)]) -> Vec<Vec2f> {
+        // Positive if `c` is to the left of the line from `a` to `b`
+        let turn = |a: Vec2f, b: Vec2f, c: Vec2f| (b - a).cross(c - a);
+
+        let mut path = Vec::new();
+
+        let (mut apex, mut left, mut right) = (portals[0].0, portals[0].0, portals[0].1);
+        let (mut left_index, mut right_index) = (0, 0);
+
+        let mut i = 1;
+        while i < portals.len() {
+            let (new_left, new_right) = portals[i];
+
+            // Try to narrow the funnel from the right
+            if turn(apex, right, new_right) >= 0.0 {
+                if apex == right || turn(apex, left, new_right) < 0.0 {
+                    right = new_right;
+                    right_index = i;
+                } else {
+                    // Right crosses over the left, so the left becomes a corner of the path
+                    apex = left;
+                    let apex_index = left_index;
+                    path.push(apex);
+
+                    (left, right) = (apex, apex);
+                    (left_index, right_index) = (apex_index, apex_index);
+                    i = apex_index + 1;
+                    continue;
+                }
+            }
+
+            // Try to narrow the funnel from the left
+            if turn(apex, left, new_left) <= 0.0 {
+                if apex == left || turn(apex, right, new_left) > 0.0 {
+                    left = new_left;
+                    left_index = i;
+                } else {
+                    // Left crosses over the right, so the right becomes a corner of the path
+                    apex = right;
+                    let apex_index = right_index;
+                    path.push(apex);
+
+                    (left, right) = (apex, apex);
+                    (left_index, right_index) = (apex_index, apex_index);
+                    i = apex_index + 1;
+                    continue;
+                }
+            }
+
+            i += 1;
+        }
+
+        let end = portals[portals.len() - 1].0;
+        if path.last() != Some(&end) {
+            path.push(end);
+        }
+
+        path
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        map::tests::{room, two_rooms},
+        surface::{Portal, WallTexture},
+    };
+
+    const RADIUS: f32 = 5.0;
+
+    /// Three square rooms in an L, going up from the first then right, around the corner at
+    /// (50, 50)
+    fn corner_rooms() -> Vec<Sector> {
+        let texture = WallTexture::new(0, Vec2f::ZERO, Vec2f::uniform(1.0));
+        let mut sectors = two_rooms();
+        sectors.push(room(2, Vec2f::new(50.0, 50.0), Vec2f::new(100.0, 100.0)));
+        sectors[1].walls[1].portal = Some(Portal::new(2, texture, texture));
+        sectors[2].walls[3].portal = Some(Portal::new(1, texture, texture));
+        sectors
+    }
+
+    fn inside(sectors: &[Sector], point: Vec2f) -> bool {
+        sectors.iter().any(|sector| sector.contains_point(point))
+    }
+
+    #[test]
+    fn string_pull_test() {
+        let start = Vec2f::new(0.0, 0.0);
+        let end = Vec2f::new(0.0, 100.0);
+
+        // Straight through wide openings, with nothing to bend around
+        let portals = [
+            (start, start),
+            (Vec2f::new(-10.0, 30.0), Vec2f::new(10.0, 30.0)),
+            (Vec2f::new(-10.0, 60.0), Vec2f::new(10.0, 60.0)),
+            (end, end),
+        ];
+        assert_eq!(NavMesh::string_pull(&portals), [end]);
+
+        // Through an opening off to the right, so the path bends around its left end
+        let portals = [
+            (start, start),
+            (Vec2f::new(20.0, 50.0), Vec2f::new(40.0, 50.0)),
+            (end, end),
+        ];
+        assert_eq!(
+            NavMesh::string_pull(&portals),
+            [Vec2f::new(20.0, 50.0), end]
+        );
+    }
+
+    #[test]
+    fn find_path_test() {
+        let sectors = two_rooms();
+        let mesh = NavMesh::build(&sectors);
+        assert_eq!(mesh.triangles.len(), 4);
+
+        // Nothing in the way between the rooms. The start lies on the edge between the triangles
+        // of the first room, so is in both.
+        let start = Vec2f::new(10.0, 10.0);
+        assert!(mesh.find_triangle(start, 0).is_some());
+        let end = Vec2f::new(40.0, 90.0);
+        assert_eq!(mesh.find_path(start, 0, end, 1, RADIUS), Some(vec![end]));
+
+        // Around the corner, keeping clear of it rather than cutting across
+        let sectors = corner_rooms();
+        let mesh = NavMesh::build(&sectors);
+        let end = Vec2f::new(90.0, 75.0);
+        let path = mesh.find_path(start, 0, end, 2, RADIUS).unwrap();
+        assert_eq!(path.last(), Some(&end));
+        assert!(path.len() > 1);
+
+        let corner = Vec2f::new(50.0, 50.0);
+        let mut from = start;
+        for point in path.iter().copied() {
+            assert!(inside(&sectors, point));
+            assert!((point - corner).magnitude() >= RADIUS - 1e-3);
+            assert!(inside(&sectors, (from + point) * 0.5));
+            from = point;
+        }
+    }
+
+    #[test]
+    fn step_height_test() {
+        // A floor too high to step up onto can still be stepped down from
+        let mut sectors = two_rooms();
+        sectors[1].floor.height = MAX_STEP_HEIGHT * 2.0;
+        let mesh = NavMesh::build(&sectors);
+
+        let low = Vec2f::new(25.0, 25.0);
+        let high = Vec2f::new(25.0, 75.0);
+        assert!(mesh.find_path(low, 0, high, 1, RADIUS).is_none());
+        assert_eq!(mesh.find_path(high, 1, low, 0, RADIUS), Some(vec![low]));
+    }
+}
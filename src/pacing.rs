@@ -0,0 +1,109 @@
+//! Limits the frame rate by waiting until each frame is due
+
+use std::time::{Duration, Instant};
+
+/// Initial guess at how late `thread::sleep` wakes up, before any sleeps have been measured
+const INITIAL_SLEEP_MARGIN: Duration = Duration::from_millis(2);
+/// Largest margin that will be left to spin through, so that a single badly delayed wake up doesn't
+/// leave us spinning for most of every frame afterwards
+const MAX_SLEEP_MARGIN: Duration = Duration::from_millis(4);
+/// Fraction by which the margin shrinks towards the latest measured oversleep, when it was smaller
+const MARGIN_DECAY: f64 = 0.05;
+
+#[derive(Debug)]
+pub struct FramePacer {
+    /// Time between frames, or `None` if the frame rate is uncapped
+    interval: Option<Duration>,
+    /// When the next frame is due
+    next_frame: Instant,
+    /// How long before a frame is due to stop sleeping and start spinning. This adapts to how late
+    /// the OS tends to wake us up, so that we sleep for as much of the frame as we can afford to.
+    sleep_margin: Duration,
+    /// Number of frames that were dropped because an earlier frame ran over
+    pub missed_frames: u64,
+}
+
+impl FramePacer {
+    pub fn new(target_fps: Option<f32>) -> Self {
+        Self {
+            interval: Self::interval(target_fps),
+            next_frame: Instant::now(),
+            sleep_margin: INITIAL_SLEEP_MARGIN,
+            missed_frames: 0,
+        }
+    }
+
+    fn interval(target_fps: Option<f32>) -> Option<Duration> {
+        target_fps
+            .filter(|fps| fps.is_finite() && *fps > 0.0)
+            .map(|fps| Duration::from_secs_f32(1.0 / fps))
+    }
+
+    /// Returns the target frame rate, or `None` if uncapped
+    pub fn target_fps(&self) -> Option<f32> {
+        self.interval.map(|interval| 1.0 / interval.as_secs_f32())
+    }
+
+    /// Sets the target frame rate, where `None` (or a non-positive rate) leaves it uncapped
+    pub fn set_target_fps(&mut self, target_fps: Option<f32>) {
+        self.interval = Self::interval(target_fps);
+        self.next_frame = Instant::now();
+    }
+
+    /// Blocks until the next frame is due. Most of the wait is spent asleep, but as sleeping is
+    /// imprecise, the last part is spent spinning.
+    ///
+    /// If the frame was already late, this returns immediately. When it is more than a whole frame
+    /// late, the missed frames are skipped rather than rushed through to catch up.
+    pub fn wait(&mut self) {
+        let Some(interval) = self.interval else {
+            return;
+        };
+
+        let now = Instant::now();
+        let due = self.next_frame;
+
+        if now >= due {
+            let late = now - due;
+
+            if late >= interval {
+                self.missed_frames += (late.as_nanos() / interval.as_nanos()) as u64;
+                self.next_frame = now + interval;
+            } else {
+                // Keep to the original schedule, so that the frame rate doesn't drift
+                self.next_frame = due + interval;
+            }
+
+            return;
+        }
+
+        let remaining = due - now;
+        if remaining > self.sleep_margin {
+            let sleep = remaining - self.sleep_margin;
+            std::thread::sleep(sleep);
+
+            let oversleep = Instant::now()
+                .saturating_duration_since(now)
+                .saturating_sub(sleep);
+            self.update_sleep_margin(oversleep);
+        }
+
+        while Instant::now() < due {
+            std::hint::spin_loop();
+        }
+
+        self.next_frame = due + interval;
+    }
+
+    /// Grows the margin straight away if we woke later than expected, but only shrinks it gradually,
+    /// so that it settles close to the worst recent oversleep
+    fn update_sleep_margin(&mut self, oversleep: Duration) {
+        let margin = if oversleep > self.sleep_margin {
+            oversleep
+        } else {
+            self.sleep_margin.mul_f64(1.0 - MARGIN_DECAY) + oversleep.mul_f64(MARGIN_DECAY)
+        };
+
+        self.sleep_margin = margin.min(MAX_SLEEP_MARGIN);
+    }
+}
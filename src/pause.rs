@@ -0,0 +1,162 @@
+//! Menu shown over a dimmed view of the world whilst the game is paused
+
+use input::{Action, Input};
+use window::event::MouseButton;
+
+use crate::{
+    colour::{BlendMode, BGRA8},
+    font::{AlignHeight, AlignWidth, Font},
+    renderer::Renderer,
+};
+
+/// Multiplied over the world whilst paused, so the menu stands out from it
+const DIM: BGRA8 = BGRA8 {
+    b: 96,
+    g: 96,
+    r: 96,
+    a: 255,
+};
+const TITLE_COLOUR: BGRA8 = BGRA8::WHITE;
+const ITEM_COLOUR: BGRA8 = BGRA8::GREY;
+const SELECTED_COLOUR: BGRA8 = BGRA8::YELLOW;
+/// Heights of the title and the first item, and the spacing between items, relative to the height
+/// of the screen
+const TITLE_Y: f32 = 0.35;
+const FIRST_ITEM_Y: f32 = 0.45;
+const ITEM_SPACING: f32 = 0.06;
+/// Width of the area around the items that the cursor selects them in, relative to the width of
+/// the screen
+const ITEM_WIDTH: f32 = 0.3;
+const CENTRE: (AlignWidth, AlignHeight) = (AlignWidth::Centre, AlignHeight::Centre);
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum PauseItem {
+    Resume,
+    /// Starts again from the last respawn point reached
+    Respawn,
+    ReloadSettings,
+}
+
+impl PauseItem {
+    pub const ALL: [Self; 3] = [Self::Resume, Self::Respawn, Self::ReloadSettings];
+
+    pub fn label(self) -> &'static str {
+        match self {
+            Self::Resume => "Resume",
+            Self::Respawn => "Respawn",
+            Self::ReloadSettings => "Reload settings",
+        }
+    }
+}
+
+/// Which item of the pause menu is selected. Whether the menu is shown is up to the caller, which
+/// shows it for as long as the game is paused.
+#[derive(Debug, Default)]
+pub struct PauseMenu {
+    selected: usize,
+}
+
+impl PauseMenu {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Selects the first item, ready to be shown again
+    pub fn reset(&mut self) {
+        self.selected = 0;
+    }
+
+    /// Moves the selection with the menu actions, and returns the item that was chosen, if any,
+    /// either with the menu actions or by clicking on it. The cursor is in pixels on a screen of
+    /// the given size.
+    pub fn update(
+        &mut self,
+        input: &Input,
+        screen_width: usize,
+        screen_height: usize,
+    ) -> Option<PauseItem> {
+        let count = PauseItem::ALL.len();
+        if input.is_action_pressed(Action::MenuUp) {
+            self.selected = (self.selected + count - 1) % count;
+        }
+        if input.is_action_pressed(Action::MenuDown) {
+            self.selected = (self.selected + 1) % count;
+        }
+
+        let hovered = input.mouse.surface_position().and_then(|cursor| {
+            let x = cursor.x / screen_width as f32;
+            let y = cursor.y / screen_height as f32;
+            if (x - 0.5).abs() > ITEM_WIDTH * 0.5 {
+                return None;
+            }
+
+            let row = ((y - FIRST_ITEM_Y) / ITEM_SPACING + 0.5).floor();
+            (row >= 0.0 && row < count as f32).then_some(row as usize)
+        });
+        if let Some(clicked) = hovered.filter(|_| input.mouse.is_button_pressed(MouseButton::Left))
+        {
+            self.selected = clicked;
+            return Some(PauseItem::ALL[clicked]);
+        }
+
+        input
+            .is_action_pressed(Action::MenuSelect)
+            .then_some(PauseItem::ALL[self.selected])
+    }
+
+    pub fn draw(&self, renderer: &mut Renderer, font: &Font) {
+        renderer.draw_overlay(DIM, BlendMode::Multiply);
+
+        renderer.draw_text(font, TITLE_COLOUR, CENTRE, 0.5, TITLE_Y, "Paused");
+
+        for (index, item) in PauseItem::ALL.iter().enumerate() {
+            let colour = match index == self.selected {
+                true => SELECTED_COLOUR,
+                false => ITEM_COLOUR,
+            };
+            let y = FIRST_ITEM_Y + index as f32 * ITEM_SPACING;
+
+            renderer.draw_text(font, colour, CENTRE, 0.5, y, item.label());
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use window::event::{Event, KeyCode, KeyboardEvent};
+
+    use super::*;
+    use crate::golden_tests::{HEIGHT, WIDTH};
+
+    #[test]
+    fn pause_menu_test() {
+        let mut input = Input::new();
+        let mut menu = PauseMenu::new();
+        let press = |input: &mut Input, menu: &mut PauseMenu, key: KeyCode| {
+            input.update();
+            input.handle_event(&Event::KeyboardEvent(KeyboardEvent::KeyPressed(key)));
+            input.handle_event(&Event::KeyboardEvent(KeyboardEvent::KeyReleased(key)));
+            menu.update(input, WIDTH, HEIGHT)
+        };
+
+        // The selection wraps around from the first item to the last
+        assert_eq!(press(&mut input, &mut menu, KeyCode::ArrowUp), None);
+        assert_eq!(
+            press(&mut input, &mut menu, KeyCode::Enter),
+            PauseItem::ALL.last().copied()
+        );
+        assert_eq!(press(&mut input, &mut menu, KeyCode::ArrowDown), None);
+        assert_eq!(press(&mut input, &mut menu, KeyCode::ArrowDown), None);
+        assert_eq!(
+            press(&mut input, &mut menu, KeyCode::Enter),
+            Some(PauseItem::Respawn)
+        );
+
+        // Showing the menu again starts from the top
+        menu.reset();
+        assert_eq!(
+            press(&mut input, &mut menu, KeyCode::Enter),
+            Some(PauseItem::Resume)
+        );
+    }
+}
@@ -0,0 +1,50 @@
+//! How sleeping enemies become aware of the player
+
+use crate::{
+    enemy::{Enemy, EnemyState},
+    map,
+    player::Player,
+    surface::Sector,
+};
+
+/// Furthest distance at which enemies can see the player
+const SIGHT_DISTANCE: f32 = 300.0;
+
+/// Wakes any sleeping enemies that have line of sight to the player
+pub fn update_sight(enemies: &mut [Enemy], sectors: &[Sector], player: &Player) {
+    let target = player.camera.position;
+
+    for enemy in enemies
+        .iter_mut()
+        .filter(|enemy| enemy.state == EnemyState::Idle)
+    {
+        let position = enemy.sprite.position;
+
+        if (target - position).magnitude_sq() > SIGHT_DISTANCE * SIGHT_DISTANCE {
+            continue;
+        }
+
+        if map::line_of_sight(
+            sectors,
+            enemy.sprite.sector_index,
+            position,
+            enemy.eye_z(),
+            target,
+            player.camera.z,
+        ) {
+            enemy.wake();
+        }
+    }
+}
+
+/// Wakes all enemies within `range` portals of a noise made in the sector at `sector_index`. The
+/// noise does not travel through closed doors.
+pub fn hear_noise(enemies: &mut [Enemy], sectors: &[Sector], sector_index: usize, range: usize) {
+    let depths = map::flood_sectors(sectors, sector_index, range);
+
+    for enemy in enemies.iter_mut() {
+        if depths[enemy.sprite.sector_index].is_some() {
+            enemy.wake();
+        }
+    }
+}
@@ -0,0 +1,221 @@
+//! Finds what is drawn at a point on the screen, for the editor, console commands and debugging
+
+use std::fmt;
+
+use maths::{geometry::Segment, linear::Vec2f};
+
+use crate::{
+    map::WallId,
+    surface::{Sector, Sprite},
+};
+
+/// Which texture of a wall was picked
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum WallPart {
+    /// The whole of a solid wall
+    Middle,
+    /// Above a portal, where the ceiling of the sector beyond is lower
+    Upper,
+    /// Below a portal, where the floor of the sector beyond is higher
+    Lower,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum PickTarget {
+    Wall {
+        wall: WallId,
+        part: WallPart,
+    },
+    /// Index of the sector whose floor was picked
+    Floor(usize),
+    /// Index of the sector whose ceiling was picked
+    Ceiling(usize),
+    /// Index of the sprite, in the order they were given to [pick]
+    Sprite(usize),
+}
+
+impl fmt::Display for PickTarget {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PickTarget::Wall { wall, part } => {
+                write!(
+                    f,
+                    "wall {} of sector {}",
+                    wall.wall_index, wall.sector_index
+                )?;
+                match part {
+                    WallPart::Middle => Ok(()),
+                    WallPart::Upper => write!(f, " (upper)"),
+                    WallPart::Lower => write!(f, " (lower)"),
+                }
+            }
+            PickTarget::Floor(sector) => write!(f, "floor of sector {sector}"),
+            PickTarget::Ceiling(sector) => write!(f, "ceiling of sector {sector}"),
+            PickTarget::Sprite(index) => write!(f, "sprite {index}"),
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Pick {
+    pub target: PickTarget,
+    /// Where the ray hit, on the map and in height
+    pub position: Vec2f,
+    pub z: f32,
+    /// Distance from the camera along its view direction, as used for depth when rendering
+    pub depth: f32,
+}
+
+/// A ray from the camera through a point on the screen, parameterised by depth, i.e. distance
+/// along the camera's view direction rather than along the ray itself
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct ViewRay {
+    pub origin: Vec2f,
+    pub z: f32,
+    /// Sector that the origin is within
+    pub sector_index: usize,
+    /// View direction of the camera, along which depth is measured
+    pub forward: Vec2f,
+    /// Movement across the map for each unit of depth
+    pub direction: Vec2f,
+    /// Change in height for each unit of depth
+    pub slope: f32,
+}
+
+impl ViewRay {
+    pub fn at(&self, depth: f32) -> (Vec2f, f32) {
+        (
+            self.origin + self.direction * depth,
+            self.z + self.slope * depth,
+        )
+    }
+}
+
+/// Finds the nearest wall, floor, ceiling or sprite along the ray, up to `max_depth`. The ray
+/// follows portals, passing through them where it is between the floors and ceilings of the
+/// sectors either side, like line of sight. Sprites are treated as upright rectangles facing the
+/// camera, as they are drawn.
+pub fn pick<'a>(
+    sectors: &[Sector],
+    ray: &ViewRay,
+    max_depth: f32,
+    sprites: impl IntoIterator<Item = &'a Sprite>,
+) -> Option<Pick> {
+    let surface = pick_surface(sectors, ray, max_depth);
+    let max_depth = surface.map_or(max_depth, |pick| pick.depth);
+
+    // Sprites are drawn facing the camera, so span sideways to its view
+    let right = Vec2f::new(ray.forward.y, -ray.forward.x);
+
+    let sprite = sprites
+        .into_iter()
+        .enumerate()
+        .filter_map(|(index, sprite)| {
+            let depth = (sprite.position - ray.origin).dot(ray.forward);
+            if depth <= 0.0 || depth > max_depth {
+                return None;
+            }
+
+            let (position, z) = ray.at(depth);
+            let side = (position - sprite.position).dot(right);
+            let hit =
+                side.abs() <= sprite.width * 0.5 && z >= sprite.z && z <= sprite.z + sprite.height;
+
+            hit.then_some(Pick {
+                target: PickTarget::Sprite(index),
+                position,
+                z,
+                depth,
+            })
+        })
+        .min_by(|a, b| a.depth.total_cmp(&b.depth));
+
+    sprite.or(surface)
+}
+
+/// Finds the nearest wall, floor or ceiling along the ray
+fn pick_surface(sectors: &[Sector], ray: &ViewRay, max_depth: f32) -> Option<Pick> {
+    let (end, _) = ray.at(max_depth);
+    let segment = Segment::new(ray.origin, end);
+
+    let mut sector_index = ray.sector_index;
+    let mut prev_sector_index = None;
+    let mut depth_entry = 0.0;
+
+    // Each step enters a different sector, so this only runs out on malformed maps
+    for _ in 0..sectors.len() {
+        let sector = &sectors[sector_index];
+
+        // Sectors are convex, so the nearest wall that the ray crosses is where it leaves
+        let exit = sector
+            .walls
+            .iter()
+            .enumerate()
+            .filter(|(_, wall)| {
+                wall.portal
+                    .is_none_or(|portal| Some(portal.sector) != prev_sector_index)
+            })
+            .filter_map(|(wall_index, wall)| {
+                let point = segment.intersection(&wall.segment)?;
+                let depth = (point - ray.origin).dot(ray.forward);
+
+                (depth >= depth_entry).then_some((depth, wall_index, wall))
+            })
+            .min_by(|a, b| a.0.total_cmp(&b.0));
+        let exit_depth = exit.map_or(max_depth, |(depth, _, _)| depth);
+
+        // Looking up or down, the ray may meet the floor or ceiling before leaving the sector
+        let plane = if ray.slope < 0.0 {
+            Some((sector.floor.height, PickTarget::Floor(sector_index)))
+        } else if ray.slope > 0.0 {
+            Some((sector.ceiling.height, PickTarget::Ceiling(sector_index)))
+        } else {
+            None
+        };
+        if let Some((height, target)) = plane {
+            let depth = (height - ray.z) / ray.slope;
+            if depth >= depth_entry && depth <= exit_depth {
+                let (position, z) = ray.at(depth);
+                return Some(Pick {
+                    target,
+                    position,
+                    z,
+                    depth,
+                });
+            }
+        }
+
+        let (depth, wall_index, wall) = exit?;
+        let (position, z) = ray.at(depth);
+        let wall_pick = |part| Pick {
+            target: PickTarget::Wall {
+                wall: WallId {
+                    sector_index,
+                    wall_index,
+                },
+                part,
+            },
+            position,
+            z,
+            depth,
+        };
+
+        let Some(portal) = wall.portal.filter(|portal| portal.visible) else {
+            return Some(wall_pick(WallPart::Middle));
+        };
+
+        let (bottom, top) = portal.next_heights(&sectors[portal.sector]);
+        if z < bottom {
+            return Some(wall_pick(WallPart::Lower));
+        }
+        if z > top {
+            return Some(wall_pick(WallPart::Upper));
+        }
+
+        prev_sector_index = Some(sector_index);
+        sector_index = portal.sector;
+        depth_entry = depth;
+    }
+
+    None
+}
@@ -1,13 +1,168 @@
-use input::Input;
 use maths::{
     geometry::{Circle, Shape},
     linear::Vec2f,
 };
-use window::event::KeyCode;
 
-use crate::camera::Camera;
+use crate::{
+    camera::Camera,
+    clock::GameClock,
+    consts::PLAYER,
+    damage::{Damage, DamageKind, INVULNERABILITY_DURATION},
+    hazard::HazardExposure,
+    item::KeyColour,
+    settings::Settings,
+    surface::{Sprite, WallTexture},
+    thing::PlayerStart,
+    trigger::Occupancy,
+    weapon::{HeldWeapon, WeaponKind},
+};
+
+/// Height of the player's eyes above their feet
+const EYE_HEIGHT: f32 = 15.0;
+/// Width and height of the sprite that stands in for the player when they are seen from outside
+const SPRITE_SIZE: f32 = 18.0;
+
+/// How the player moves and turns, which can be tuned in the settings file
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct MovementConfig {
+    /// Deceleration that always opposes the player's movement, in units per second squared
+    pub friction: f32,
+    /// Acceleration from movement input, in units per second squared
+    pub impulse: f32,
+    /// Fastest the player can move by themselves, in units per second
+    pub max_speed: f32,
+    /// Radians turned per second for each unit of look input, horizontally and vertically
+    pub mouse_sensitivity: Vec2f,
+    /// Lets moving forwards and strafing at once go faster than either alone, as in classic
+    /// engines, rather than at the same speed in every direction
+    pub classic_diagonals: bool,
+}
+
+impl MovementConfig {
+    pub const DEFAULT: Self = Self {
+        friction: 175.0,
+        impulse: 300.0,
+        max_speed: 50.0,
+        mouse_sensitivity: Vec2f { x: 0.1, y: 0.05 },
+        classic_diagonals: false,
+    };
+    /// Slow to get going and slow to stop, sliding on after letting go and running faster
+    /// diagonally, like classic shooters
+    pub const CLASSIC: Self = Self {
+        friction: 80.0,
+        impulse: 200.0,
+        max_speed: 60.0,
+        classic_diagonals: true,
+        ..Self::DEFAULT
+    };
+    /// Reaches full speed and stops almost at once, for precise movement
+    pub const MODERN: Self = Self {
+        friction: 600.0,
+        impulse: 900.0,
+        max_speed: 50.0,
+        ..Self::DEFAULT
+    };
+
+    /// Every preset, along with the name that the settings file and console refer to it by
+    pub const PRESETS: [(&'static str, Self); 3] = [
+        ("default", Self::DEFAULT),
+        ("classic", Self::CLASSIC),
+        ("modern", Self::MODERN),
+    ];
+
+    pub fn preset(name: &str) -> Option<Self> {
+        Self::PRESETS
+            .iter()
+            .find(|(preset, _)| *preset == name)
+            .map(|(_, config)| *config)
+    }
+
+    /// Reads the movement from the settings, starting from the preset named by `movement.preset`
+    /// (or the default) and replacing any of its values that are set individually
+    pub fn from_settings(settings: &Settings) -> Self {
+        let mut config = settings
+            .get_valid("movement.preset", |name: &String| Self::preset(name).is_some())
+            .and_then(|name| Self::preset(&name))
+            .unwrap_or(Self::DEFAULT);
+
+        // Rates can't be negative, and NaN would spread to the player's position
+        let is_rate = |value: &f32| value.is_finite() && *value >= 0.0;
+        if let Some(friction) = settings.get_valid("movement.friction", is_rate) {
+            config.friction = friction;
+        }
+        if let Some(impulse) = settings.get_valid("movement.impulse", is_rate) {
+            config.impulse = impulse;
+        }
+        if let Some(max_speed) = settings.get_valid("movement.max_speed", is_rate) {
+            config.max_speed = max_speed;
+        }
+        if let Some(x) = settings.get_valid("mouse.sensitivity_x", |x: &f32| x.is_finite()) {
+            config.mouse_sensitivity.x = x;
+        }
+        if let Some(y) = settings.get_valid("mouse.sensitivity_y", |y: &f32| y.is_finite()) {
+            config.mouse_sensitivity.y = y;
+        }
+        if let Some(classic_diagonals) = settings.get("movement.classic_diagonals") {
+            config.classic_diagonals = classic_diagonals;
+        }
+
+        config
+    }
+}
+
+#[derive(Clone, Copy, Debug)]
+pub struct PlayerStats {
+    pub health: u32,
+    pub max_health: u32,
+    pub armour: u32,
+    pub max_armour: u32,
+    pub ammo: u32,
+    pub max_ammo: u32,
+    /// Bitmask of collected keys, see [KeyColour::bit]
+    pub keys: u8,
+    /// Bitmask of carried weapons, see [WeaponKind::bit]
+    pub weapons: u8,
+}
+
+impl PlayerStats {
+    pub fn new() -> Self {
+        Self {
+            health: 100,
+            max_health: 100,
+            armour: 0,
+            max_armour: 100,
+            ammo: 50,
+            max_ammo: 200,
+            keys: 0,
+            weapons: WeaponKind::Fist.bit() | WeaponKind::Pistol.bit(),
+        }
+    }
+
+    pub fn has_key(&self, colour: KeyColour) -> bool {
+        self.keys & colour.bit() != 0
+    }
+
+    pub fn give_key(&mut self, colour: KeyColour) {
+        self.keys |= colour.bit();
+    }
+
+    pub fn has_weapon(&self, kind: WeaponKind) -> bool {
+        self.weapons & kind.bit() != 0
+    }
+
+    pub fn give_weapon(&mut self, kind: WeaponKind) {
+        self.weapons |= kind.bit();
+    }
 
-const MOUSE_SENSITIVITY: Vec2f = Vec2f { x: 0.1, y: 0.05 };
+    /// Reduces health by the given amount, with armour absorbing part of the damage whilst it
+    /// lasts, depending on the kind of damage
+    pub fn damage(&mut self, amount: u32, kind: DamageKind) {
+        let absorbed = ((amount as f32 * kind.armour_absorption()) as u32).min(self.armour);
+
+        self.armour -= absorbed;
+        self.health = self.health.saturating_sub(amount - absorbed);
+    }
+}
 
 pub struct Player {
     pub camera: Camera,
@@ -15,76 +170,92 @@ pub struct Player {
     pub collider: Circle,
     pub prev_position: Vec2f,
     pub velocity: Vec2f,
+    pub stats: PlayerStats,
+    pub weapon: HeldWeapon,
+    pub hazard_exposure: HazardExposure,
+    pub triggers: Occupancy,
+    /// Seconds remaining during which damage is ignored
+    invulnerable: f32,
 
     pub crouch: bool,
     pub head_z: f32,
     pub knee_z: f32,
+
+    /// Lets the player pass through walls, for debugging
+    pub noclip: bool,
 }
 
 impl Player {
-    pub fn new(position: Vec2f, z: f32, sector_index: usize) -> Self {
-        let camera = Camera::new(position, z);
+    /// Creates a player standing at a start, on a floor of the given height
+    pub fn new(start: &PlayerStart, floor_height: f32) -> Self {
+        let position = start.position;
+        let height = EYE_HEIGHT;
+        let z = floor_height + height;
+
+        let mut camera = Camera::new(position, z);
+        camera.set_rotation(start.yaw, 0.0);
         let collider = Circle::new(position, 10.0);
 
-        let height = 15.0;
         let head_z = z + height * 0.2;
         let knee_z = z - height * 0.6;
 
         Self {
             camera,
-            sector_index,
+            sector_index: start.sector_index,
             collider,
             prev_position: position,
             velocity: Vec2f::ZERO,
+            stats: PlayerStats::new(),
+            weapon: HeldWeapon::new(),
+            hazard_exposure: HazardExposure::new(),
+            triggers: Occupancy::new(),
+            invulnerable: 0.0,
 
             crouch: false,
             head_z,
             knee_z,
+
+            noclip: false,
         }
     }
 
-    pub fn update_movement(&mut self, delta_seconds: f32, input: &Input) {
-        let friction_mag = 175.0;
-        let impulse_mag = 300.0;
-        let max_speed = 50.0;
-
-        let mut mouse_delta = Vec2f::ZERO;
+    /// Accelerates the player in the direction of `movement`, where x is to the right and y is
+    /// forwards, then turns them by `look` and moves them. The length of `movement` scales the
+    /// speed that they accelerate up to, rather than how hard they accelerate. `ground_velocity` is
+    /// the velocity of the floor being stood on, such as a conveyor, which carries the player along.
+    pub fn update_movement(
+        &mut self,
+        delta_seconds: f32,
+        movement: Vec2f,
+        look: Vec2f,
+        ground_velocity: Vec2f,
+        config: &MovementConfig,
+    ) {
+        // A stick pushed only partway still accelerates at full strength, as a weaker push
+        // could be overcome by friction and never get going
+        let direction = movement.normalise();
+        let impulse = (self.camera.direction * direction.y
+            - self.camera.direction.perpendicular() * direction.x)
+            * config.impulse;
 
-        if input.mouse.is_grabbed() {
-            let mut impulse = Vec2f::ZERO;
+        // Accelerating can't take the player beyond the speed asked for, but they may already be
+        // going faster, such as after easing off the stick, and are then slowed by friction alone
+        let coasting_speed = (self.velocity.magnitude() - config.friction * delta_seconds).max(0.0);
+        let speed_limit = (config.max_speed * movement.magnitude()).max(coasting_speed);
 
-            if input.keyboard.is_key_held(KeyCode::W) {
-                impulse += self.camera.direction;
-            } else if input.keyboard.is_key_held(KeyCode::S) {
-                impulse -= self.camera.direction;
-            }
-
-            if input.keyboard.is_key_held(KeyCode::A) {
-                impulse += self.camera.direction.perpendicular();
-            } else if input.keyboard.is_key_held(KeyCode::D) {
-                impulse -= self.camera.direction.perpendicular();
-            }
-
-            // normalise the direction, and scale by impulse
-            impulse = impulse.normalise() * impulse_mag;
-
-            // apply acceleration
-            self.velocity += impulse * delta_seconds;
-
-            mouse_delta = input.mouse.delta();
-            // Negate y-axis to make up positive, as the y-axis is flipped in screen space
-            mouse_delta.y = -mouse_delta.y;
-        }
+        // apply acceleration
+        self.velocity += impulse * delta_seconds;
 
         // Apply friction
-        let friction_impulse = -(self.velocity / self.velocity.magnitude().max(1.0)) * friction_mag;
+        let friction_impulse =
+            -(self.velocity / self.velocity.magnitude().max(1.0)) * config.friction;
         self.velocity += friction_impulse * delta_seconds;
 
         // Clamp velocity to max speed
         let speed = self.velocity.magnitude();
-        if speed > max_speed {
+        if speed > speed_limit {
             self.velocity /= speed;
-            self.velocity *= max_speed;
+            self.velocity *= speed_limit;
         }
 
         // Flush velocity to zero if it's small enough
@@ -92,15 +263,38 @@ impl Player {
             self.velocity = Vec2f::ZERO;
         }
 
-        let translation = self.velocity * delta_seconds;
-        let rotation = mouse_delta * MOUSE_SENSITIVITY * delta_seconds;
+        let translation = (self.velocity + ground_velocity) * delta_seconds;
+        let rotation = look * config.mouse_sensitivity * delta_seconds;
 
         self.rotate(rotation);
         self.translate(translation);
+    }
+
+    /// Moves the player up or down, such as when the floor beneath them moves
+    pub fn raise(&mut self, delta_z: f32) {
+        self.camera.z += delta_z;
+        self.head_z += delta_z;
+        self.knee_z += delta_z;
+    }
 
-        if input.keyboard.is_key_pressed(KeyCode::ShiftLeft) {
-            self.toggle_crouch();
+    pub fn is_dead(&self) -> bool {
+        self.stats.health == 0
+    }
+
+    /// Applies damage, unless dead or still recovering from the last hit. Returns whether it was
+    /// applied.
+    pub fn take_damage(&mut self, damage: &Damage) -> bool {
+        if self.is_dead() || self.invulnerable > 0.0 {
+            return false;
         }
+
+        self.stats.damage(damage.amount, damage.kind);
+        self.invulnerable = INVULNERABILITY_DURATION;
+        true
+    }
+
+    pub fn update_invulnerability(&mut self, delta_seconds: f32) {
+        self.invulnerable = (self.invulnerable - delta_seconds).max(0.0);
     }
 
     pub fn toggle_crouch(&mut self) {
@@ -121,4 +315,106 @@ impl Player {
     pub fn rotate(&mut self, rotation: Vec2f) {
         self.camera.rotate(rotation);
     }
+
+    /// Returns the camera used to view the world from the player's perspective, which includes
+    /// view bobbing based on the player's speed
+    pub fn view_camera(&self, clock: &GameClock) -> Camera {
+        let mut camera = self.camera.clone();
+
+        // View bobbing
+        let (phase, amplitude) = self.bob(clock);
+        let bob_yaw = phase.sin() * amplitude * 0.03;
+        let bob_pitch = (phase * 2.0).sin() * amplitude * 0.0075;
+
+        camera.rotate(Vec2f::new(bob_yaw, bob_pitch));
+
+        camera
+    }
+
+    /// Returns how far the held weapon is swayed from where it rests, relative to its size. It
+    /// swings from side to side in step with the view bobbing, and dips at either end of each
+    /// swing, but never rises, so it stays on the status bar.
+    pub fn weapon_bob(&self, clock: &GameClock) -> Vec2f {
+        let (phase, amplitude) = self.bob(clock);
+        let amplitude = amplitude.min(1.0);
+
+        Vec2f::new(
+            phase.sin() * amplitude * 0.08,
+            phase.sin().abs() * amplitude * 0.05,
+        )
+    }
+
+    /// Returns a sprite of the player standing where they are, for when they are seen from a
+    /// camera other than their own
+    pub fn sprite(&self) -> Sprite {
+        let mut sprite = Sprite::new(
+            self.camera.position,
+            self.sector_index,
+            WallTexture::new(PLAYER, Vec2f::ZERO, Vec2f::uniform(64.0 / SPRITE_SIZE)),
+            SPRITE_SIZE,
+            SPRITE_SIZE,
+        );
+        sprite.z = self.camera.z - EYE_HEIGHT;
+
+        sprite
+    }
+
+    /// Phase and strength of the bobbing as the player moves, which grows with their speed
+    fn bob(&self, clock: &GameClock) -> (f32, f32) {
+        let amplitude = self.velocity.magnitude_sq() / (50.0 * 50.0);
+        let phase = clock.time() * 5.0;
+
+        (phase, amplitude)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn movement_settings_test() {
+        let settings = Settings::from_source(
+            "movement.preset = classic
+            movement.friction = NaN
+            movement.max_speed = -10
+            movement.impulse = 250",
+        )
+        .unwrap();
+        let config = MovementConfig::from_settings(&settings);
+
+        // Invalid rates keep the preset's values, without stopping valid ones from being read
+        assert_eq!(config.friction, MovementConfig::CLASSIC.friction);
+        assert_eq!(config.max_speed, MovementConfig::CLASSIC.max_speed);
+        assert_eq!(config.impulse, 250.0);
+        assert_eq!(settings.problems().len(), 2);
+
+        let settings = Settings::from_source("movement.preset = fast").unwrap();
+        assert_eq!(MovementConfig::from_settings(&settings), MovementConfig::DEFAULT);
+        assert_eq!(settings.problems().len(), 1);
+    }
+
+    #[test]
+    fn analog_movement_test() {
+        let start = PlayerStart {
+            position: Vec2f::ZERO,
+            yaw: 0.0,
+            sector_index: 0,
+        };
+        let mut player = Player::new(&start, 0.0);
+        let config = MovementConfig::DEFAULT;
+        let mut run = |movement: Vec2f| {
+            for _ in 0..120 {
+                player.update_movement(1.0 / 60.0, movement, Vec2f::ZERO, Vec2f::ZERO, &config);
+            }
+            player.velocity.magnitude()
+        };
+
+        // Pushing the stick partway reaches a lower speed
+        assert!((run(Vec2f::new(0.0, 1.0)) - config.max_speed).abs() < 0.01);
+        assert!((run(Vec2f::new(0.0, 0.5)) - config.max_speed * 0.5).abs() < 0.01);
+
+        // Easing off from there slows down further, rather than holding the speed already reached
+        assert!((run(Vec2f::new(0.0, 0.25)) - config.max_speed * 0.25).abs() < 0.01);
+    }
 }
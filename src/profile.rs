@@ -0,0 +1,186 @@
+//! Timing of scopes throughout the engine, written out in the Chrome trace event format so that
+//! slow frames can be looked into with `chrome://tracing` or Perfetto.
+//!
+//! Recording is only done when the `profile` feature is enabled. Otherwise, [scope] expands to
+//! nothing and costs nothing, not even evaluating its arguments.
+
+/// Times the rest of the enclosing block, labelled with the subsystem it belongs to and a name.
+/// The name may be a `&'static str` or a `String`, such as the path of an asset being loaded.
+#[cfg(feature = "profile")]
+macro_rules! scope {
+    ($category:expr, $name:expr) => {
+        let _profile_scope = $crate::profile::Scope::new($category, $name);
+    };
+}
+
+#[cfg(not(feature = "profile"))]
+macro_rules! scope {
+    ($category:expr, $name:expr) => {};
+}
+
+pub(crate) use scope;
+
+#[cfg(feature = "profile")]
+pub use recording::*;
+
+#[cfg(feature = "profile")]
+mod recording {
+    use std::{
+        borrow::Cow,
+        collections::VecDeque,
+        fmt::Write as _,
+        fs, io, mem,
+        path::Path,
+        sync::{
+            atomic::{AtomicU64, Ordering},
+            Arc, Mutex, OnceLock,
+        },
+        thread,
+        time::Instant,
+    };
+
+    use crate::consts::PROFILE_EVENT_LIMIT;
+
+    /// The scopes recorded by each thread that has ended one
+    static THREADS: Mutex<Vec<Arc<ThreadEvents>>> = Mutex::new(Vec::new());
+    /// Time that the first scope started, which the trace's timestamps are relative to
+    static START: OnceLock<Instant> = OnceLock::new();
+    static NEXT_THREAD: AtomicU64 = AtomicU64::new(0);
+
+    thread_local! {
+        /// Scopes recorded by the current thread, which are set up the first time one ends
+        static EVENTS: Arc<ThreadEvents> = {
+            let events = Arc::new(ThreadEvents {
+                id: NEXT_THREAD.fetch_add(1, Ordering::Relaxed),
+                name: thread::current().name().unwrap_or("unnamed").to_owned(),
+                events: Mutex::new(VecDeque::new()),
+            });
+            THREADS.lock().unwrap().push(events.clone());
+            events
+        };
+    }
+
+    /// Scopes that have ended on one thread, in the order they ended. Each thread has its own, so
+    /// that threads don't wait on each other to record, and only keeps the most recent
+    /// [PROFILE_EVENT_LIMIT], so that a long session doesn't take ever more memory.
+    struct ThreadEvents {
+        /// ID of the thread in the trace
+        id: u64,
+        name: String,
+        /// Only locked by other threads whilst the trace is written
+        events: Mutex<VecDeque<Event>>,
+    }
+
+    struct Event {
+        category: &'static str,
+        name: Cow<'static, str>,
+        /// Microseconds since [START]
+        start: u64,
+        duration: u64,
+    }
+
+    /// A scope being timed, which is recorded when dropped. Created by [scope](super::scope).
+    pub struct Scope {
+        category: &'static str,
+        name: Cow<'static, str>,
+        start: Instant,
+    }
+
+    impl Scope {
+        pub fn new(category: &'static str, name: impl Into<Cow<'static, str>>) -> Self {
+            let start = Instant::now();
+            START.get_or_init(|| start);
+
+            Self {
+                category,
+                name: name.into(),
+                start,
+            }
+        }
+    }
+
+    impl Drop for Scope {
+        fn drop(&mut self) {
+            let end = Instant::now();
+            let origin = *START.get().unwrap();
+
+            let event = Event {
+                category: self.category,
+                name: mem::take(&mut self.name),
+                start: self.start.duration_since(origin).as_micros() as u64,
+                duration: end.duration_since(self.start).as_micros() as u64,
+            };
+
+            EVENTS.with(|thread| {
+                let mut events = thread.events.lock().unwrap();
+                if events.len() == PROFILE_EVENT_LIMIT {
+                    events.pop_front();
+                }
+                events.push_back(event);
+            });
+        }
+    }
+
+    /// Writes the scopes recorded so far to a JSON file in the Chrome trace event format
+    pub fn write_trace(path: impl AsRef<Path>) -> io::Result<()> {
+        let threads = THREADS.lock().unwrap();
+
+        let mut json = String::from("{\"traceEvents\":[\n");
+        let mut first = true;
+        let mut separator = |json: &mut String| {
+            if !first {
+                json.push_str(",\n");
+            }
+            first = false;
+        };
+
+        for thread in threads.iter() {
+            separator(&mut json);
+            let _ = write!(
+                json,
+                "{{\"name\":\"thread_name\",\"ph\":\"M\",\"pid\":1,\"tid\":{},\
+                 \"args\":{{\"name\":\"{}\"}}}}",
+                thread.id,
+                escape(&thread.name)
+            );
+
+            for event in thread.events.lock().unwrap().iter() {
+                separator(&mut json);
+                let _ = write!(
+                    json,
+                    "{{\"name\":\"{}\",\"cat\":\"{}\",\"ph\":\"X\",\"ts\":{},\"dur\":{},\
+                     \"pid\":1,\"tid\":{}}}",
+                    escape(&event.name),
+                    escape(event.category),
+                    event.start,
+                    event.duration,
+                    thread.id
+                );
+            }
+        }
+
+        json.push_str("\n]}\n");
+        fs::write(path, json)
+    }
+
+    /// Escapes a string for use within quotes in JSON
+    fn escape(text: &str) -> Cow<'_, str> {
+        if !text.contains(|c: char| c == '"' || c == '\\' || c.is_control()) {
+            return Cow::Borrowed(text);
+        }
+
+        let mut escaped = String::with_capacity(text.len() + 2);
+        for c in text.chars() {
+            match c {
+                '"' => escaped.push_str("\\\""),
+                '\\' => escaped.push_str("\\\\"),
+                c if c.is_control() => {
+                    let _ = write!(escaped, "\\u{:04x}", c as u32);
+                }
+                c => escaped.push(c),
+            }
+        }
+
+        Cow::Owned(escaped)
+    }
+}
@@ -0,0 +1,95 @@
+use maths::linear::Vec2f;
+
+use crate::{
+    consts::BOLT,
+    map::{self, WallId},
+    surface::{Sector, Sprite, WallTexture},
+};
+
+/// Width and height of projectile sprites
+const PROJECTILE_SIZE: f32 = 4.0;
+/// Seconds before a projectile that hasn't hit anything disappears
+const PROJECTILE_LIFETIME: f32 = 5.0;
+
+/// Something fired by a weapon, which flies in a straight line until it hits a wall or a target
+#[derive(Debug)]
+pub struct Projectile {
+    pub sprite: Sprite,
+    velocity: Vec2f,
+    pub damage: u32,
+    /// Where it was fired from
+    pub origin: Vec2f,
+    /// Whether it was fired by an enemy, and so hurts the player rather than enemies
+    pub hostile: bool,
+    /// Seconds remaining before it disappears
+    lifetime: f32,
+}
+
+impl Projectile {
+    /// Creates a crossbow bolt, centred on a point at height `z`
+    pub fn bolt(
+        position: Vec2f,
+        z: f32,
+        sector_index: usize,
+        velocity: Vec2f,
+        damage: u32,
+    ) -> Self {
+        let texture = WallTexture::new(BOLT, Vec2f::ZERO, Vec2f::uniform(16.0 / PROJECTILE_SIZE));
+        let mut sprite = Sprite::new(
+            position,
+            sector_index,
+            texture,
+            PROJECTILE_SIZE,
+            PROJECTILE_SIZE,
+        );
+        // Flies straight, rather than standing on the floor like other sprites
+        sprite.z = z - PROJECTILE_SIZE * 0.5;
+
+        Self {
+            sprite,
+            velocity,
+            damage,
+            origin: position,
+            hostile: false,
+            lifetime: PROJECTILE_LIFETIME,
+        }
+    }
+
+    /// Marks the projectile as fired by an enemy
+    pub fn hostile(mut self) -> Self {
+        self.hostile = true;
+        self
+    }
+
+    /// Whether the projectile overlaps a circle on the ground
+    pub fn touches(&self, centre: Vec2f, radius: f32) -> bool {
+        let reach = radius + PROJECTILE_SIZE * 0.5;
+        (self.sprite.position - centre).magnitude_sq() <= reach * reach
+    }
+
+    pub fn is_expired(&self) -> bool {
+        self.lifetime <= 0.0
+    }
+
+    /// Moves the projectile, moving it between sectors through passable portals. Returns the wall
+    /// it hit and where, in which case it should be removed.
+    pub fn update(&mut self, sectors: &[Sector], delta_seconds: f32) -> Option<(WallId, Vec2f)> {
+        self.lifetime -= delta_seconds;
+
+        let from = self.sprite.position;
+        let to = from + self.velocity * delta_seconds;
+
+        if let Some(hit) = map::ray_cast(sectors, self.sprite.sector_index, from, to, |wall| {
+            wall.portal.is_none_or(|portal| !portal.passable)
+        }) {
+            return Some((hit.wall, from + (to - from) * hit.t));
+        }
+
+        if let Some(portal) = sectors[self.sprite.sector_index].crossed_portal(from, to) {
+            self.sprite.sector_index = portal.sector;
+        }
+        self.sprite.position = to;
+
+        None
+    }
+}
@@ -0,0 +1,110 @@
+//! Solid objects placed in the map, such as crates and furniture, which block the player's way
+
+use std::f32::consts::{FRAC_PI_2, TAU};
+
+use maths::{
+    geometry::{Circle, Polygon, Shape, AABB},
+    linear::Vec2f,
+};
+use physics::{collision::Collision, Collider, RigidBody};
+
+use crate::{
+    consts::*,
+    fall::Fall,
+    surface::{Sprite, WallTexture},
+};
+
+/// Number of sides of the polygon that round props, like pillars, are approximated by
+const ROUND_SIDES: usize = 8;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum PropKind {
+    /// A wooden box with a square footprint
+    Crate,
+    /// A stone column with a round footprint
+    Pillar,
+}
+
+impl PropKind {
+    fn texture(&self) -> WallTexture {
+        match self {
+            PropKind::Crate => WallTexture::new(PLANK, Vec2f::ZERO, Vec2f::uniform(2.0)),
+            PropKind::Pillar => WallTexture::new(STONE_BRICK, Vec2f::ZERO, Vec2f::uniform(2.0)),
+        }
+    }
+
+    /// Width across the footprint and height of the sprite, in world units
+    fn size(&self) -> (f32, f32) {
+        match self {
+            PropKind::Crate => (16.0, 16.0),
+            PropKind::Pillar => (10.0, 25.0),
+        }
+    }
+
+    /// Shape the prop covers on the ground, centred on its position and turned by its angle
+    fn footprint(&self, position: Vec2f, angle: f32) -> Collider {
+        let (width, _) = self.size();
+
+        match self {
+            // Squared up with the axes, a box is cheaper to test against than a polygon
+            PropKind::Crate if angle.rem_euclid(FRAC_PI_2) == 0.0 => {
+                Collider::from_aabb(AABB::from_dimensions(position, Vec2f::uniform(width)))
+            }
+            PropKind::Crate => {
+                let (sin, cos) = angle.sin_cos();
+                let mut polygon = Polygon::from_rect(Vec2f::ZERO, Vec2f::uniform(width));
+                polygon.rotate(sin, cos);
+                polygon.translate(position);
+
+                Collider::from_polygon(polygon)
+            }
+            PropKind::Pillar => {
+                let radius = width * 0.5;
+                let vertices = (0..ROUND_SIDES)
+                    .map(|i| {
+                        let (sin, cos) = (angle + i as f32 * TAU / ROUND_SIDES as f32).sin_cos();
+                        position + Vec2f::new(cos, sin) * radius
+                    })
+                    .collect();
+
+                Collider::from_polygon(Polygon::from_vertices(vertices))
+            }
+        }
+    }
+}
+
+/// A prop placed in the map, which the player can't walk through, and which debris bounces off
+pub struct Prop {
+    pub sprite: Sprite,
+    pub fall: Fall,
+    /// Fixed in place, so the physics solver only moves what hits it
+    body: RigidBody,
+}
+
+impl Prop {
+    pub fn new(kind: PropKind, position: Vec2f, angle: f32, sector_index: usize) -> Self {
+        let (width, height) = kind.size();
+
+        Self {
+            sprite: Sprite::new(position, sector_index, kind.texture(), width, height),
+            fall: Fall::new(0.0),
+            body: RigidBody::new(kind.footprint(position, angle), f32::INFINITY, true, 0),
+        }
+    }
+
+    pub fn body_mut(&mut self) -> &mut RigidBody {
+        &mut self.body
+    }
+
+    /// Moves the prop along with its footprint, such as when carried by a moving sector
+    pub fn translate(&mut self, translation: Vec2f) {
+        self.sprite.position += translation;
+        self.body.translate(translation);
+    }
+
+    /// Finds whether the circle overlaps the prop's footprint. The normal of the collision is the
+    /// direction to push the circle out of the prop.
+    pub fn collide(&self, circle: &Circle) -> Option<Collision> {
+        Collider::from_circle(circle.clone()).intersects(&self.body.collider)
+    }
+}
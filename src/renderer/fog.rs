@@ -0,0 +1,95 @@
+use crate::{colour::BGRA8, surface::Fog};
+
+/// Fog as seen through a portal, made up of whatever fog lies between the camera and the portal,
+/// and the fog of the sector beyond it. Surfaces just beyond the portal are fogged as much as the
+/// portal itself would be, so there is no seam where one sector's fog hands off to the next.
+#[derive(Clone, Copy, Debug)]
+pub struct PortalFog {
+    /// Combined colour and strength of the fog between the camera and the portal
+    entry_colour: BGRA8,
+    entry_amount: f32,
+    /// Distance from the camera to the portal
+    entry_depth: f32,
+    /// Fog of the sector seen through the portal
+    fog: Fog,
+}
+
+impl PortalFog {
+    /// Fog of the sector that the camera is in, with nothing in between
+    pub fn new(fog: Fog) -> Self {
+        Self {
+            entry_colour: fog.colour,
+            entry_amount: 0.0,
+            entry_depth: 0.0,
+            fog,
+        }
+    }
+
+    /// Fog to blend over a surface at the given distance from the camera, within the sector
+    pub fn at(&self, depth: f32) -> FogBlend {
+        let distance = (depth - self.entry_depth).max(0.0);
+        let transmittance = (-self.fog.density * distance).exp();
+
+        // Whatever light made it through the fog before the portal is then fogged by this sector
+        let added = (1.0 - self.entry_amount) * (1.0 - transmittance);
+        let amount = self.entry_amount + added;
+        let colour = if amount > 0.0 {
+            self.entry_colour
+                .lerp(self.fog.colour, (added / amount * 255.0) as u8)
+        } else {
+            self.fog.colour
+        };
+
+        FogBlend {
+            colour,
+            amount,
+            strength: (amount * 255.0) as u8,
+        }
+    }
+
+    /// Fog seen through a portal at the given distance from the camera, into a sector with the
+    /// given fog
+    pub fn through(&self, depth: f32, fog: Fog) -> Self {
+        let blend = self.at(depth);
+
+        Self {
+            entry_colour: blend.colour,
+            entry_amount: blend.amount,
+            entry_depth: depth.max(self.entry_depth),
+            fog,
+        }
+    }
+}
+
+/// Fog at a particular distance, ready to be blended over each pixel of a span
+#[derive(Clone, Copy, Debug)]
+pub struct FogBlend {
+    colour: BGRA8,
+    amount: f32,
+    strength: u8,
+}
+
+impl FogBlend {
+    /// Fades the colour towards the fog, keeping its alpha so that transparent texels stay so
+    #[inline(always)]
+    pub fn apply(&self, colour: BGRA8) -> BGRA8 {
+        if self.strength == 0 {
+            return colour;
+        }
+
+        BGRA8 {
+            a: colour.a,
+            ..colour.lerp(self.colour, self.strength)
+        }
+    }
+    /// Weakens the colour by how much of it the fog hides, for colours that are added to or
+    /// otherwise combined with what is behind them, where fading towards the fog's colour would
+    /// add the fog's colour too
+    #[inline(always)]
+    pub fn fade(&self, colour: BGRA8) -> BGRA8 {
+        BGRA8 {
+            a: ((colour.a as u16 * (0xFF - self.strength) as u16) / 0xFF) as u8,
+            ..colour
+        }
+    }
+}
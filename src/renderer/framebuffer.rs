@@ -1,6 +1,10 @@
+use jobs::JobSystem;
 use maths::linear::Vec2f;
 
-use crate::colour::BGRA8;
+use crate::{
+    bitmap::Bitmap,
+    colour::{BlendMode, BGRA8},
+};
 
 pub struct Framebuffer {
     width: usize,
@@ -9,6 +13,8 @@ pub struct Framebuffer {
     half_height: f32,
     aspect_ratio: f32,
     pixels: Vec<BGRA8>,
+    /// Scratch space for where the edges of a polygon cross each row, kept to reuse its allocation
+    crossings: Vec<f32>,
 }
 
 impl Framebuffer {
@@ -23,6 +29,7 @@ impl Framebuffer {
             half_height: height as f32 * 0.5,
             aspect_ratio: width as f32 / height as f32,
             pixels,
+            crossings: Vec::new(),
         }
     }
 
@@ -67,6 +74,177 @@ impl Framebuffer {
         self.pixels.fill(colour);
     }
 
+    /// Fills a rectangle with the colour, ignoring its alpha. The rectangle is clipped to the
+    /// framebuffer.
+    pub fn fill_rect(&mut self, x: usize, y: usize, width: usize, height: usize, colour: BGRA8) {
+        let x_end = (x + width).min(self.width);
+        let y_end = (y + height).min(self.height);
+
+        for row in y.min(y_end)..y_end {
+            let start = row * self.width;
+            self.pixels[start + x.min(x_end)..start + x_end].fill(colour);
+        }
+    }
+
+    /// Fills the inside of a polygon, which may be concave or self-intersecting, using the even-odd
+    /// rule. Pixels are filled if their centres are inside, so polygons that share an edge don't
+    /// overlap. The colour is copied as is, or combined with what is beneath if given a mode.
+    pub fn fill_polygon(&mut self, points: &[Vec2f], colour: BGRA8, mode: Option<BlendMode>) {
+        if points.len() < 3 {
+            return;
+        }
+
+        let (min_y, max_y) = points
+            .iter()
+            .fold((f32::INFINITY, f32::NEG_INFINITY), |(min, max), point| {
+                (min.min(point.y), max.max(point.y))
+            });
+        let y_start = ((min_y - 0.5).ceil().max(0.0) as usize).min(self.height);
+        let y_end = ((max_y - 0.5).ceil().max(0.0) as usize).min(self.height);
+
+        let mut crossings = core::mem::take(&mut self.crossings);
+        for row in y_start..y_end {
+            let sample_y = row as f32 + 0.5;
+
+            // Where each edge crosses the centre line of this row
+            crossings.clear();
+            for (index, a) in points.iter().enumerate() {
+                let b = points[(index + 1) % points.len()];
+                if (a.y <= sample_y) != (b.y <= sample_y) {
+                    crossings.push(a.x + (sample_y - a.y) / (b.y - a.y) * (b.x - a.x));
+                }
+            }
+            crossings.sort_unstable_by(f32::total_cmp);
+
+            let start = row * self.width;
+            for span in crossings.chunks_exact(2) {
+                let x_start = (span[0] - 0.5).ceil().clamp(0.0, self.width as f32) as usize;
+                let x_end = (span[1] - 0.5).ceil().clamp(0.0, self.width as f32) as usize;
+                let pixels = &mut self.pixels[start + x_start..start + x_end];
+
+                match mode {
+                    Some(mode) => {
+                        for pixel in pixels.iter_mut() {
+                            *pixel = colour.composite(*pixel, mode);
+                        }
+                    }
+                    None => pixels.fill(colour),
+                }
+            }
+        }
+
+        self.crossings = crossings;
+    }
+
+    /// Draws the bitmap at its original size with its top left corner at the given position, which
+    /// may be partly or wholly offscreen. Pixels whose colour matches the colour key, ignoring
+    /// alpha, are skipped. The rest are copied as is, or combined with what is beneath using their
+    /// alpha if given a mode.
+    ///
+    /// Textures and fonts are drawn by their own renderers, so nothing in the engine calls this
+    /// yet. It is kept for overlays and tools that draw images loaded as they are.
+    #[allow(dead_code)]
+    pub fn blit_bitmap(
+        &mut self,
+        bitmap: &Bitmap,
+        x: isize,
+        y: isize,
+        colour_key: Option<BGRA8>,
+        mode: Option<BlendMode>,
+    ) {
+        let x_start = x.clamp(0, self.width as isize);
+        let y_start = y.clamp(0, self.height as isize);
+        let x_end = (x + bitmap.width() as isize).clamp(x_start, self.width as isize);
+        let y_end = (y + bitmap.height() as isize).clamp(y_start, self.height as isize);
+        let len = (x_end - x_start) as usize;
+        if len == 0 {
+            return;
+        }
+
+        let is_key = |colour: BGRA8| {
+            colour_key.is_some_and(|key| (key.r, key.g, key.b) == (colour.r, colour.g, colour.b))
+        };
+
+        for dst_y in y_start..y_end {
+            let src_start = (dst_y - y) as usize * bitmap.width() + (x_start - x) as usize;
+            let dst_start = dst_y as usize * self.width + x_start as usize;
+
+            let src = &bitmap.pixels()[src_start..src_start + len];
+            let dst = &mut self.pixels[dst_start..dst_start + len];
+
+            for (dst, &src) in dst.iter_mut().zip(src.iter()) {
+                if is_key(src) {
+                    continue;
+                }
+
+                *dst = match mode {
+                    Some(mode) => src.composite(*dst, mode),
+                    None => src,
+                };
+            }
+        }
+    }
+
+    /// Blends the colour over a rectangle, using its alpha as strength. The rectangle is clipped to
+    /// the framebuffer.
+    pub fn blend_fill_rect(
+        &mut self,
+        x: usize,
+        y: usize,
+        width: usize,
+        height: usize,
+        colour: BGRA8,
+    ) {
+        self.composite_rect(x, y, width, height, colour, BlendMode::Alpha);
+    }
+
+    /// Combines the colour with every pixel, using the given mode
+    pub fn composite_fill(&mut self, colour: BGRA8, mode: BlendMode) {
+        for pixel in self.pixels.iter_mut() {
+            *pixel = colour.composite(*pixel, mode);
+        }
+    }
+
+    /// Combines the colour with a rectangle of pixels, using the given mode. The rectangle is
+    /// clipped to the framebuffer.
+    pub fn composite_rect(
+        &mut self,
+        x: usize,
+        y: usize,
+        width: usize,
+        height: usize,
+        colour: BGRA8,
+        mode: BlendMode,
+    ) {
+        let y_end = (y + height).min(self.height);
+
+        for row in y.min(y_end)..y_end {
+            self.composite_h_line(x, row, width, colour, mode);
+        }
+    }
+
+    /// Combines the colour with a horizontal run of pixels, using the given mode. The run is clipped
+    /// to the framebuffer.
+    pub fn composite_h_line(
+        &mut self,
+        x: usize,
+        y: usize,
+        length: usize,
+        colour: BGRA8,
+        mode: BlendMode,
+    ) {
+        if y >= self.height {
+            return;
+        }
+
+        let x_end = (x + length).min(self.width);
+        let start = y * self.width;
+
+        for pixel in self.pixels[start + x.min(x_end)..start + x_end].iter_mut() {
+            *pixel = colour.composite(*pixel, mode);
+        }
+    }
+
     pub fn set_pixel(&mut self, x: usize, y: usize, colour: BGRA8) {
         debug_assert!(x < self.width && y < self.height);
         let index = (y * self.width) + x;
@@ -97,23 +275,41 @@ impl Framebuffer {
         *self.pixels.get_unchecked(index)
     }
 
+    /// Blends the colour over the pixel, using its alpha as strength
     pub fn blend_pixel(&mut self, x: usize, y: usize, colour: BGRA8) {
         debug_assert!(x < self.width && y < self.height);
         let index = (y * self.width) + x;
 
-        let blended = self.pixels[index].blend(colour);
+        let blended = colour.blend(self.pixels[index]);
         self.pixels[index] = blended;
     }
 
+    /// Blends the colour over the pixel, using its alpha as strength
     pub unsafe fn blend_pixel_unchecked(&mut self, x: usize, y: usize, colour: BGRA8) {
         debug_assert!(x < self.width && y < self.height);
         let index = (y * self.width) + x;
 
         debug_assert!(index < self.pixels.len());
-        let blended = self.pixels.get_unchecked(index).blend(colour);
+        let blended = colour.blend(*self.pixels.get_unchecked(index));
         *self.pixels.get_unchecked_mut(index) = blended;
     }
 
+    /// Combines the colour with the pixel, using the given mode
+    pub unsafe fn composite_pixel_unchecked(
+        &mut self,
+        x: usize,
+        y: usize,
+        colour: BGRA8,
+        mode: BlendMode,
+    ) {
+        debug_assert!(x < self.width && y < self.height);
+        let index = (y * self.width) + x;
+
+        debug_assert!(index < self.pixels.len());
+        let composited = colour.composite(*self.pixels.get_unchecked(index), mode);
+        *self.pixels.get_unchecked_mut(index) = composited;
+    }
+
     pub fn draw_h_line(&mut self, x: usize, y: usize, length: usize, colour: BGRA8) {
         debug_assert!(x < self.width && y < self.height);
         debug_assert!(x + length <= self.width);
@@ -174,25 +370,308 @@ impl Framebuffer {
         }
     }
 
-    /// # Deprecated
-    pub fn draw_line(&mut self, mut start: Vec2f, mut end: Vec2f, colour: BGRA8) {
-        start.x = start.x.clamp(0.0, (self.width - 1) as f32);
-        start.y = start.y.clamp(0.0, (self.height - 1) as f32);
-        end.x = end.x.clamp(0.0, (self.width - 1) as f32);
-        end.y = end.y.clamp(0.0, (self.height - 1) as f32);
+    /// Copies the source framebuffer into the given rectangle of this framebuffer, using nearest
+    /// neighbour sampling to scale it. Any part of the rectangle that is offscreen is clipped.
+    pub fn blit_scaled(
+        &mut self,
+        source: &Framebuffer,
+        x: usize,
+        y: usize,
+        width: usize,
+        height: usize,
+    ) {
+        if width == 0 || height == 0 {
+            return;
+        }
+
+        let x_max = (x + width).min(self.width);
+        let y_max = (y + height).min(self.height);
 
-        let delta = end - start;
-        let steps = delta.x.abs().max(delta.y.abs());
-        let increment = delta / steps;
+        // Source step per destination pixel, in 16.16 fixed point
+        let step_x = (source.width << 16) / width;
+        let step_y = (source.height << 16) / height;
 
-        let mut position = start;
+        for dst_y in y..y_max {
+            let src_y = ((dst_y - y) * step_y) >> 16;
+            let src_row = src_y * source.width;
+            let dst_row = dst_y * self.width;
 
-        for _ in 0..steps as usize {
-            let x = position.x.round() as usize;
-            let y = position.y.round() as usize;
+            for dst_x in x..x_max {
+                let src_x = ((dst_x - x) * step_x) >> 16;
 
-            self.set_pixel(x, y, colour);
-            position += increment;
+                self.pixels[dst_row + dst_x] = source.pixels[src_row + src_x];
+            }
         }
     }
+
+    /// Scales the source framebuffer to cover the whole of this framebuffer. Each pixel takes the
+    /// average of the source pixels beneath it, so a larger source is box filtered down, and a
+    /// smaller one is scaled up with nearest neighbour sampling.
+    pub fn resample(&mut self, source: &Framebuffer) {
+        if source.width == self.width && source.height == self.height {
+            self.pixels.copy_from_slice(&source.pixels);
+            return;
+        }
+
+        // Range of source pixels beneath the destination pixel at `dst`, of which there is always
+        // at least one
+        let span = |dst: usize, dst_len: usize, src_len: usize| {
+            let min = dst * src_len / dst_len;
+            let max = ((dst + 1) * src_len / dst_len).max(min + 1);
+            min..max
+        };
+
+        for dst_y in 0..self.height {
+            let src_ys = span(dst_y, self.height, source.height);
+
+            for dst_x in 0..self.width {
+                let src_xs = span(dst_x, self.width, source.width);
+
+                let mut sum = [0u32; 4];
+                for src_y in src_ys.clone() {
+                    let row = src_y * source.width;
+                    for pixel in &source.pixels[row + src_xs.start..row + src_xs.end] {
+                        sum[0] += pixel.r as u32;
+                        sum[1] += pixel.g as u32;
+                        sum[2] += pixel.b as u32;
+                        sum[3] += pixel.a as u32;
+                    }
+                }
+
+                let count = (src_xs.len() * src_ys.len()) as u32;
+                let [r, g, b, a] = sum.map(|channel| ((channel + count / 2) / count) as u8);
+                self.pixels[dst_y * self.width + dst_x] = BGRA8::new(r, g, b, a);
+            }
+        }
+    }
+
+    /// Rearranges the columns of each row, such that the column at `x` is replaced by the column at
+    /// `columns[x]`. The scratch buffer must be a multiple of the width of this framebuffer, as the
+    /// rows are split into that many bands which are processed in parallel, each using one row of
+    /// scratch space.
+    pub fn remap_columns(&mut self, columns: &[usize], scratch: &mut [BGRA8], jobs: &JobSystem) {
+        debug_assert!(columns.len() == self.width);
+        debug_assert!(scratch.len() >= self.width && scratch.len() % self.width == 0);
+
+        let width = self.width;
+        let bands = scratch.len() / width;
+        let band_height = self.height.div_ceil(bands);
+
+        jobs.scope(|scope| {
+            for (pixels, row) in self
+                .pixels
+                .chunks_mut(band_height * width)
+                .zip(scratch.chunks_exact_mut(width))
+            {
+                scope.spawn(move || {
+                    for pixels in pixels.chunks_exact_mut(width) {
+                        row.copy_from_slice(pixels);
+
+                        for (pixel, &source_x) in pixels.iter_mut().zip(columns.iter()) {
+                            *pixel = row[source_x];
+                        }
+                    }
+                });
+            }
+        });
+    }
+
+    /// Draws a line between two points, in pixels, clipped to the framebuffer. Lines of thickness
+    /// up to 1 are drawn one pixel wide with Bresenham's algorithm. Thicker lines are filled as a
+    /// rectangle centred on the line, with square ends flush with the points.
+    pub fn draw_line(&mut self, start: Vec2f, end: Vec2f, thickness: f32, colour: BGRA8) {
+        if thickness > 1.0 {
+            let across = (end - start).normalise().perpendicular() * (thickness * 0.5);
+            if across == Vec2f::ZERO {
+                return;
+            }
+
+            self.fill_polygon(
+                &[start + across, end + across, end - across, start - across],
+                colour,
+                None,
+            );
+            return;
+        }
+
+        let max = Vec2f::new((self.width - 1) as f32, (self.height - 1) as f32);
+        let Some((start, end)) = clip_segment(start, end, max) else {
+            return;
+        };
+
+        let (mut x, mut y) = (start.x.round() as isize, start.y.round() as isize);
+        let (x_end, y_end) = (end.x.round() as isize, end.y.round() as isize);
+
+        let dx = (x_end - x).abs();
+        let dy = -(y_end - y).abs();
+        let step_x = if x < x_end { 1 } else { -1 };
+        let step_y = if y < y_end { 1 } else { -1 };
+        let mut error = dx + dy;
+
+        loop {
+            self.set_pixel(x as usize, y as usize, colour);
+            if x == x_end && y == y_end {
+                break;
+            }
+
+            let error_2 = error * 2;
+            if error_2 >= dy {
+                error += dy;
+                x += step_x;
+            }
+            if error_2 <= dx {
+                error += dx;
+                y += step_y;
+            }
+        }
+    }
+}
+
+/// Clips a segment to the rectangle from the origin to `max`, using the Liang-Barsky algorithm.
+/// Returns `None` if no part of it is inside, or it isn't finite.
+fn clip_segment(start: Vec2f, end: Vec2f, max: Vec2f) -> Option<(Vec2f, Vec2f)> {
+    if !(start.x.is_finite() && start.y.is_finite() && end.x.is_finite() && end.y.is_finite()) {
+        return None;
+    }
+
+    let delta = end - start;
+    let (mut t_start, mut t_end) = (0.0f32, 1.0f32);
+
+    // Each edge as the rate at which the segment leaves the inside, and how far inside the start is
+    for (rate, distance) in [
+        (-delta.x, start.x),
+        (delta.x, max.x - start.x),
+        (-delta.y, start.y),
+        (delta.y, max.y - start.y),
+    ] {
+        if rate == 0.0 {
+            if distance < 0.0 {
+                return None;
+            }
+            continue;
+        }
+
+        let t = distance / rate;
+        if rate < 0.0 {
+            t_start = t_start.max(t);
+        } else {
+            t_end = t_end.min(t);
+        }
+        if t_start > t_end {
+            return None;
+        }
+    }
+
+    Some((start + delta * t_start, start + delta * t_end))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const GREY: BGRA8 = BGRA8 {
+        b: 10,
+        g: 10,
+        r: 10,
+        a: 255,
+    };
+
+    /// Each row of the framebuffer, with `#` for pixels of the colour and `.` for the rest
+    fn rows(framebuffer: &Framebuffer, colour: BGRA8) -> Vec<String> {
+        framebuffer
+            .pixels()
+            .chunks_exact(framebuffer.width())
+            .map(|row| {
+                row.iter()
+                    .map(|pixel| match pixel.as_u32() == colour.as_u32() {
+                        true => '#',
+                        false => '.',
+                    })
+                    .collect()
+            })
+            .collect()
+    }
+
+    #[test]
+    fn fill_rect_test() {
+        let mut framebuffer = Framebuffer::new(6, 4);
+        framebuffer.fill_rect(1, 1, 2, 2, BGRA8::RED);
+        // Clipped to the framebuffer
+        framebuffer.fill_rect(4, 2, 10, 10, BGRA8::RED);
+        framebuffer.fill_rect(10, 0, 1, 1, BGRA8::RED);
+
+        assert_eq!(
+            rows(&framebuffer, BGRA8::RED),
+            ["......", ".##...", ".##.##", "....##"]
+        );
+    }
+
+    #[test]
+    fn fill_polygon_test() {
+        let mut framebuffer = Framebuffer::new(6, 4);
+        // Only pixels whose centres are inside are filled
+        let triangle = [
+            Vec2f::new(0.0, 0.0),
+            Vec2f::new(6.0, 0.0),
+            Vec2f::new(0.0, 4.0),
+        ];
+        framebuffer.fill_polygon(&triangle, BGRA8::RED, None);
+
+        assert_eq!(
+            rows(&framebuffer, BGRA8::RED),
+            ["#####.", "####..", "##....", "#....."]
+        );
+
+        // Polygons that share an edge don't overlap, so adding both covers each pixel once
+        let mut framebuffer = Framebuffer::new(6, 2);
+        let square = |x: f32| {
+            [
+                Vec2f::new(x, 0.0),
+                Vec2f::new(x + 3.0, 0.0),
+                Vec2f::new(x + 3.0, 2.0),
+                Vec2f::new(x, 2.0),
+            ]
+        };
+        framebuffer.fill_polygon(&square(0.0), GREY, Some(BlendMode::Add));
+        framebuffer.fill_polygon(&square(3.0), GREY, Some(BlendMode::Add));
+
+        assert!(framebuffer.pixels().iter().all(|pixel| pixel.r == 10));
+    }
+
+    #[test]
+    fn blit_bitmap_test() {
+        let mut framebuffer = Framebuffer::new(4, 4);
+        // The bottom right pixel is the colour key, so is skipped
+        let bitmap = Bitmap::new(
+            2,
+            2,
+            vec![BGRA8::RED, BGRA8::RED, BGRA8::RED, BGRA8::MAGENTA],
+        );
+
+        // Partly off the left and bottom edges
+        framebuffer.blit_bitmap(&bitmap, -1, 3, Some(BGRA8::MAGENTA), None);
+        framebuffer.blit_bitmap(&bitmap, 2, 1, Some(BGRA8::MAGENTA), None);
+        // Wholly offscreen
+        framebuffer.blit_bitmap(&bitmap, 4, 0, None, None);
+
+        assert_eq!(
+            rows(&framebuffer, BGRA8::RED),
+            ["....", "..##", "..#.", "#..."]
+        );
+        assert!(framebuffer.pixels().iter().all(|pixel| pixel.g == 0));
+    }
+
+    #[test]
+    fn resample_test() {
+        // Each pixel of a smaller framebuffer averages those it covers
+        let mut source = Framebuffer::new(2, 2);
+        source.set_pixel(0, 0, BGRA8::new(0, 0, 0, 255));
+        source.set_pixel(1, 0, BGRA8::new(100, 0, 0, 255));
+        source.set_pixel(0, 1, BGRA8::new(100, 200, 0, 255));
+        source.set_pixel(1, 1, BGRA8::new(200, 200, 40, 255));
+        let mut target = Framebuffer::new(1, 1);
+        target.resample(&source);
+        let pixel = target.get_pixel(0, 0);
+        assert_eq!((pixel.r, pixel.g, pixel.b), (100, 100, 10));
+    }
 }
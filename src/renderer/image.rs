@@ -0,0 +1,146 @@
+use crate::{
+    colour::{BlendMode, BGRA8},
+    textures::Texture,
+};
+
+use super::RendererState;
+
+/// A rectangle in pixels (or texels, when referring to a region of a texture)
+#[derive(Clone, Copy, Debug)]
+pub struct Rect {
+    pub x: usize,
+    pub y: usize,
+    pub width: usize,
+    pub height: usize,
+}
+
+impl Rect {
+    pub fn new(x: usize, y: usize, width: usize, height: usize) -> Self {
+        Self {
+            x,
+            y,
+            width,
+            height,
+        }
+    }
+}
+
+/// Draws textures directly to the screen, for UI elements
+pub struct ImageRenderer {}
+
+impl ImageRenderer {
+    pub fn new(_state: &RendererState) -> Self {
+        Self {}
+    }
+
+    /// Draws a region of the texture's base level into the destination rectangle, scaling it to fit
+    /// using nearest neighbour sampling. Fully transparent texels are skipped.
+    pub fn draw_image(&self, state: &mut RendererState, texture: &Texture, src: Rect, dst: Rect) {
+        Self::draw_image_with(state, texture, src, dst, |colour| colour);
+    }
+
+    /// Draws a region of the texture like [Self::draw_image], with the tint combined over each
+    /// texel using the given mode, such as adding to brighten it
+    pub fn draw_image_tinted(
+        &self,
+        state: &mut RendererState,
+        texture: &Texture,
+        src: Rect,
+        dst: Rect,
+        tint: BGRA8,
+        mode: BlendMode,
+    ) {
+        Self::draw_image_with(state, texture, src, dst, |colour| {
+            tint.composite(colour, mode)
+        });
+    }
+
+    /// Draws a region of the texture, passing each texel that isn't fully transparent through
+    /// `shade` on its way to the screen
+    #[inline(always)]
+    fn draw_image_with(
+        state: &mut RendererState,
+        texture: &Texture,
+        src: Rect,
+        dst: Rect,
+        shade: impl Fn(BGRA8) -> BGRA8,
+    ) {
+        if src.width == 0 || src.height == 0 || dst.width == 0 || dst.height == 0 {
+            return;
+        }
+        debug_assert!(src.x + src.width <= texture.levels[0].width);
+        debug_assert!(src.y + src.height <= texture.levels[0].height);
+
+        let x_max = (dst.x + dst.width).min(state.framebuffer.width());
+        let y_max = (dst.y + dst.height).min(state.framebuffer.height());
+
+        // Source step per destination pixel, in 16.16 fixed point
+        let step_x = (src.width << 16) / dst.width;
+        let step_y = (src.height << 16) / dst.height;
+
+        for y in dst.y..y_max {
+            let texture_y = src.y + (((y - dst.y) * step_y) >> 16);
+
+            for x in dst.x..x_max {
+                let texture_x = src.x + (((x - dst.x) * step_x) >> 16);
+
+                let colour = unsafe { texture.sample_unchecked(texture_x, texture_y, 0) };
+
+                if colour.a != 0 {
+                    unsafe { state.framebuffer.set_pixel_unchecked(x, y, shade(colour)) };
+                }
+            }
+        }
+    }
+
+    /// Draws the texture stretched over the destination rectangle, such that its corners (of size
+    /// `border` texels) keep their original size, its edges are stretched along one axis and its
+    /// centre is stretched along both. This allows a small texture to be used as the background of
+    /// a panel of any size.
+    pub fn draw_nine_slice(
+        &self,
+        state: &mut RendererState,
+        texture: &Texture,
+        border: usize,
+        dst: Rect,
+    ) {
+        let width = texture.levels[0].width;
+        let height = texture.levels[0].height;
+
+        let src_border = border.min(width / 2).min(height / 2);
+        let dst_border = src_border.min(dst.width / 2).min(dst.height / 2);
+
+        // Offsets and sizes of the three columns/rows, for the source and destination
+        let src_columns = [
+            (0, src_border),
+            (src_border, width - src_border * 2),
+            (width - src_border, src_border),
+        ];
+        let src_rows = [
+            (0, src_border),
+            (src_border, height - src_border * 2),
+            (height - src_border, src_border),
+        ];
+        let dst_columns = [
+            (dst.x, dst_border),
+            (dst.x + dst_border, dst.width - dst_border * 2),
+            (dst.x + dst.width - dst_border, dst_border),
+        ];
+        let dst_rows = [
+            (dst.y, dst_border),
+            (dst.y + dst_border, dst.height - dst_border * 2),
+            (dst.y + dst.height - dst_border, dst_border),
+        ];
+
+        for (src_row, dst_row) in src_rows.iter().zip(dst_rows.iter()) {
+            for (src_column, dst_column) in src_columns.iter().zip(dst_columns.iter()) {
+                self.draw_image(
+                    state,
+                    texture,
+                    Rect::new(src_column.0, src_row.0, src_column.1, src_row.1),
+                    Rect::new(dst_column.0, dst_row.0, dst_column.1, dst_row.1),
+                );
+            }
+        }
+    }
+}
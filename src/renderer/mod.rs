@@ -1,11 +1,19 @@
+mod fog;
 mod framebuffer;
+mod image;
+mod pass;
 mod plane;
 mod portal;
 mod renderer;
 mod sector;
+mod span;
 mod sprite;
+mod stats;
 mod text;
 mod util;
 mod wall;
 
+pub use framebuffer::Framebuffer;
+pub use image::Rect;
+pub use pass::RenderPass;
 pub use renderer::*;
@@ -0,0 +1,143 @@
+use std::fmt;
+
+use super::RendererState;
+
+/// A stage of drawing the world. [Renderer::update](super::Renderer::update) runs the enabled
+/// passes in order, each drawing over what the passes before it drew.
+pub enum RenderPass {
+    /// Walls, floors and ceilings of every sector that can be seen through portals. This also
+    /// decides which parts of the screen each sprite can be seen in, so sprites are only clipped
+    /// correctly if it runs before them.
+    Sectors,
+    /// Sprites, drawn back to front
+    Sprites,
+    /// Colours blended over whatever was seen through tinted portals
+    PortalTints,
+    /// Outlines of the portals, when debug drawing is enabled
+    Debug,
+    /// Resampling of the whole image, for distortion correction when it is enabled
+    PostProcess,
+    /// Drawn by the caller, with access to the framebuffer and camera of the frame being drawn
+    Custom {
+        name: &'static str,
+        draw: Box<dyn FnMut(&mut RendererState)>,
+    },
+}
+
+impl RenderPass {
+    pub fn custom(name: &'static str, draw: impl FnMut(&mut RendererState) + 'static) -> Self {
+        Self::Custom {
+            name,
+            draw: Box::new(draw),
+        }
+    }
+
+    /// Name used to refer to the pass when enabling, disabling or inserting passes
+    pub fn name(&self) -> &'static str {
+        match self {
+            RenderPass::Sectors => "sectors",
+            RenderPass::Sprites => "sprites",
+            RenderPass::PortalTints => "portal_tints",
+            RenderPass::Debug => "debug",
+            RenderPass::PostProcess => "post_process",
+            RenderPass::Custom { name, .. } => name,
+        }
+    }
+
+    /// Built-in passes, in the order they run by default
+    pub(super) fn defaults() -> Vec<(RenderPass, bool)> {
+        [
+            RenderPass::Sectors,
+            RenderPass::Sprites,
+            RenderPass::PortalTints,
+            RenderPass::Debug,
+            RenderPass::PostProcess,
+        ]
+        .into_iter()
+        .map(|pass| (pass, true))
+        .collect()
+    }
+}
+
+impl fmt::Debug for RenderPass {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{cell::Cell, rc::Rc};
+
+    use maths::linear::Vec2f;
+
+    use super::*;
+    use crate::{
+        camera::Camera,
+        demo,
+        enemy::{Enemy, EnemyKind},
+        golden_tests::{load_renderer, POSES},
+        map,
+        renderer::Renderer,
+    };
+
+    #[test]
+    fn render_passes_test() {
+        let (assets, mut renderer) = load_renderer();
+        let sectors = demo::sectors();
+        let enemies = [Enemy::new(EnemyKind::Goblin, Vec2f::new(140.0, 80.0), 2)];
+
+        let pose = &POSES[2];
+        let mut camera = Camera::new(pose.position, pose.z);
+        camera.set_rotation(pose.yaw, pose.pitch);
+        let sector_index = map::find_sector(pose.position, &sectors).unwrap();
+
+        let render = |renderer: &mut Renderer| {
+            renderer
+                .update(
+                    &camera,
+                    sector_index,
+                    &assets.textures,
+                    &sectors,
+                    enemies.iter().map(|enemy| &enemy.sprite),
+                )
+                .unwrap();
+            renderer.framebuffer().pixels_as_u8().to_vec()
+        };
+        let full = render(&mut renderer);
+
+        // Custom passes run in place, and see the frame as drawn by the passes before them
+        let sprites_drawn = Rc::new(Cell::new(None));
+        let seen = sprites_drawn.clone();
+        renderer.insert_pass(
+            RenderPass::custom("probe", move |state| {
+                seen.set(Some(state.stats.sprites_drawn))
+            }),
+            Some("sprites"),
+        );
+        assert_eq!(render(&mut renderer), full);
+        assert_eq!(sprites_drawn.get(), Some(0));
+        assert_eq!(
+            renderer.passes().position(|(name, _)| name == "probe"),
+            renderer
+                .passes()
+                .position(|(name, _)| name == "sprites")
+                .map(|i| i - 1)
+        );
+
+        assert!(renderer.set_pass_enabled("sprites", false));
+        assert_ne!(render(&mut renderer), full);
+        assert_eq!(renderer.stats().sprites_drawn, 0);
+
+        // Without sectors, nothing covers the previous frame, so it is cleared
+        assert!(renderer.set_pass_enabled("sectors", false));
+        render(&mut renderer);
+        assert!(renderer
+            .framebuffer()
+            .pixels_as_u8()
+            .chunks_exact(4)
+            .all(|pixel| pixel == [0, 0, 0, 255]));
+
+        assert!(!renderer.set_pass_enabled("missing", false));
+    }
+}
@@ -1,12 +1,20 @@
-use maths::linear::{Mat2f, Vec2f};
+use maths::linear::Vec2f;
 
 use crate::{
-    consts::{FAR, MIP_SCALES, NEAR},
-    renderer::util::{diminish_lighting, mip_level, normalise_depth},
+    consts::MIP_SCALES,
+    renderer::util::{
+        diminish_lighting, lighting_level, lightmap_level, mip_level, span_coord, span_texel_index,
+    },
+    surface::Plane,
     textures::Texture,
 };
 
-use super::{portal::PortalNode, RendererState};
+use super::{
+    fog::PortalFog,
+    portal::PortalNode,
+    span::{PlaneSpan, SpanBuffer, SpanCommand, SpanSurface},
+    RendererState,
+};
 
 pub struct PlaneRenderer {
     /// For each Y coordinate, stores starting X coordinate (inc.) of the horizontal spans that are
@@ -51,15 +59,16 @@ impl PlaneRenderer {
         }
     }
 
+    /// Finds the rows of a floor or ceiling that are visible between the bounds, and adds their
+    /// spans to the buffer
     pub fn draw_plane(
         &mut self,
-        state: &mut RendererState,
+        state: &RendererState,
         portal: &PortalNode,
+        spans: &mut SpanBuffer,
         y_bounds: (&[u16], &[u16]),
         height_offset: f32,
-        texture: &Texture,
-        texture_offset: Vec2f,
-        texture_scale_rotate: &Mat2f,
+        surface: SpanSurface,
     ) {
         // Portal and wall bounds are collected during rasterisation of walls. We can use these to
         // draw floors and ceilings horizontally, which allows for fewer depth calculations as
@@ -87,11 +96,11 @@ impl PlaneRenderer {
 
             // Bounds have narrowed, so draw any horizontal lines that are now 'closed'
             while min_bound > y_min {
-                self.rasterise_plane_span(
+                self.push_plane_span(
                     state,
-                    texture,
-                    texture_offset,
-                    texture_scale_rotate,
+                    spans,
+                    surface,
+                    portal.fog,
                     height_offset,
                     y_min as usize,
                     self.span_start[y_min as usize] as usize,
@@ -101,11 +110,11 @@ impl PlaneRenderer {
             }
             while max_bound < y_max {
                 y_max -= 1;
-                self.rasterise_plane_span(
+                self.push_plane_span(
                     state,
-                    texture,
-                    texture_offset,
-                    texture_scale_rotate,
+                    spans,
+                    surface,
+                    portal.fog,
                     height_offset,
                     y_max as usize,
                     self.span_start[y_max as usize] as usize,
@@ -116,11 +125,11 @@ impl PlaneRenderer {
 
         // Draw remaining for any horizontal lines that are still 'open'
         for y in y_min..y_max {
-            self.rasterise_plane_span(
+            self.push_plane_span(
                 state,
-                texture,
-                texture_offset,
-                texture_scale_rotate,
+                spans,
+                surface,
+                portal.fog,
                 height_offset,
                 y as usize,
                 self.span_start[y as usize] as usize,
@@ -129,12 +138,13 @@ impl PlaneRenderer {
         }
     }
 
-    fn rasterise_plane_span(
-        &mut self,
-        state: &mut RendererState,
-        texture: &Texture,
-        texture_offset: Vec2f,
-        texture_scale_rotate: &Mat2f,
+    /// Adds a row of a plane to the span buffer, unless it is empty or beyond the clipping planes
+    fn push_plane_span(
+        &self,
+        state: &RendererState,
+        spans: &mut SpanBuffer,
+        surface: SpanSurface,
+        fog: PortalFog,
         height_offset: f32,
         y: usize,
         x_min: usize,
@@ -157,66 +167,117 @@ impl PlaneRenderer {
         let depth = focal_height_ratio * height_offset;
 
         // When at the horizon, depth tends towards infinity, so skip drawing if out of bounds.
-        if depth < NEAR || depth > FAR {
+        if depth < state.near() || depth > state.far() {
             return;
         }
 
-        let normal_depth = normalise_depth(depth);
-        let mip_level = mip_level(normal_depth, focal_height_ratio.abs());
-        let mip_scale = MIP_SCALES[mip_level];
-
-        let lighting =
-            unsafe { (diminish_lighting(normal_depth) * 255.0).to_int_unchecked::<u8>() };
-
-        // Calculate world space coordinates of either end of the span, via reversing the perspective
-        // projection, and use these as the texture coordinates.
-        let ws_1 = Vec2f::new(
-            ((x_min as f32 - state.framebuffer.half_width()) * depth) * state.inv_focal_width(),
-            -depth,
-        )
-        .rotate(state.camera.yaw_sin, state.camera.yaw_cos)
-            + Vec2f::new(state.camera.position.x, -state.camera.position.y);
-
-        let ws_2 = Vec2f::new(
-            ((x_max as f32 - state.framebuffer.half_width()) * depth) * state.inv_focal_width(),
-            -depth,
-        )
-        .rotate(state.camera.yaw_sin, state.camera.yaw_cos)
-            + Vec2f::new(state.camera.position.x, -state.camera.position.y);
-
-        let mut tex_coord_a = ws_1 * mip_scale;
-        let mut tex_coord_b = ws_2 * mip_scale;
-        tex_coord_a += texture_offset;
-        tex_coord_b += texture_offset;
-        tex_coord_a = *texture_scale_rotate * tex_coord_a;
-        tex_coord_b = *texture_scale_rotate * tex_coord_b;
-
-        let inv_x_delta = 1.0 / (x_max - x_min) as f32;
-
-        let v_m = (tex_coord_b.y - tex_coord_a.y) * inv_x_delta;
-        let mut v = tex_coord_a.y;
-
-        let u_m = (tex_coord_b.x - tex_coord_a.x) * inv_x_delta;
-        let mut u = tex_coord_a.x;
-
-        let width_mask = texture.levels[mip_level].width - 1;
-        let height_mask = texture.levels[mip_level].height - 1;
-
-        for x in x_min..x_max {
-            // U and V are in world space, thus could be negative. We would need to `abs` these,
-            // but an unchecked cast works too.
-            let texture_x = unsafe { u.to_int_unchecked::<usize>() } & width_mask;
-            let texture_y = unsafe { v.to_int_unchecked::<usize>() } & height_mask;
-
-            let colour = unsafe {
-                texture
-                    .sample_unchecked(texture_x, texture_y, mip_level)
-                    .darken(lighting)
-            };
-            unsafe { state.framebuffer.set_pixel_unchecked(x, y, colour) };
-
-            u += u_m;
-            v += v_m;
+        spans.push(SpanCommand::Plane(PlaneSpan {
+            surface,
+            y,
+            x_min,
+            x_max,
+            depth,
+            focal_height_ratio,
+            fog,
+        }));
+    }
+}
+
+pub(super) fn rasterise_plane_span(
+    state: &mut RendererState,
+    span: &PlaneSpan,
+    plane: &Plane,
+    texture: &Texture,
+) {
+    let (y, x_min, x_max, depth) = (span.y, span.x_min, span.x_max, span.depth);
+    let texture_offset = plane.texture_data.offset;
+    let texture_scale_rotate = &plane.texture_data.scale_rotate;
+
+    let normal_depth = state.normalise_depth(depth);
+    let mip_level = mip_level(normal_depth, span.focal_height_ratio.abs());
+    let mip_scale = MIP_SCALES[mip_level];
+
+    // Calculate world space coordinates of either end of the span, via reversing the perspective
+    // projection, and use these as the texture coordinates.
+    let ws_1 = Vec2f::new(
+        ((x_min as f32 - state.framebuffer.half_width()) * depth) * state.inv_focal_width(),
+        -depth,
+    )
+    .rotate(state.camera.yaw_sin, state.camera.yaw_cos)
+        + Vec2f::new(state.camera.position.x, -state.camera.position.y);
+
+    let ws_2 = Vec2f::new(
+        ((x_max as f32 - state.framebuffer.half_width()) * depth) * state.inv_focal_width(),
+        -depth,
+    )
+    .rotate(state.camera.yaw_sin, state.camera.yaw_cos)
+        + Vec2f::new(state.camera.position.x, -state.camera.position.y);
+
+    let mut tex_coord_a = ws_1 * mip_scale;
+    let mut tex_coord_b = ws_2 * mip_scale;
+    tex_coord_a += texture_offset;
+    tex_coord_b += texture_offset;
+    tex_coord_a = *texture_scale_rotate * tex_coord_a;
+    tex_coord_b = *texture_scale_rotate * tex_coord_b;
+
+    // A depth of NaN passes the check against the clipping planes, so is caught here too
+    if state.is_degenerate(&[
+        depth,
+        tex_coord_a.x,
+        tex_coord_a.y,
+        tex_coord_b.x,
+        tex_coord_b.y,
+    ]) {
+        return;
+    }
+
+    state.stats.add_span(x_max - x_min);
+
+    let lighting = lighting_level(diminish_lighting(normal_depth), state.linear_lighting);
+    let camera_light = state.camera_light_level(depth);
+    let fog = span.fog.at(depth);
+
+    let inv_x_delta = 1.0 / (x_max - x_min) as f32;
+
+    let v_m = span_coord((tex_coord_b.y - tex_coord_a.y) * inv_x_delta);
+    let mut v = span_coord(tex_coord_a.y);
+
+    let u_m = span_coord((tex_coord_b.x - tex_coord_a.x) * inv_x_delta);
+    let mut u = span_coord(tex_coord_a.x);
+
+    // Lightmaps are mapped by world position, where Y is flipped relative to the span's
+    let light_m = Vec2f::new(ws_2.x - ws_1.x, ws_1.y - ws_2.y) * inv_x_delta;
+    let mut light = Vec2f::new(ws_1.x, -ws_1.y);
+
+    let width_mask = texture.levels[mip_level].width - 1;
+    let height_mask = texture.levels[mip_level].height - 1;
+
+    for x in x_min..x_max {
+        // U and V are in world space, thus could be negative, which wraps like any other
+        // coordinate once masked
+        let texture_x = unsafe { span_texel_index(u) } & width_mask;
+        let texture_y = unsafe { span_texel_index(v) } & height_mask;
+
+        let lit = match &plane.lightmap {
+            Some(lightmap) => lightmap_level(lighting, lightmap.sample(light.x, light.y)),
+            None => lighting,
         }
+        .saturating_add(camera_light);
+
+        let colour = unsafe {
+            let emissive = texture.sample_emissive_unchecked(texture_x, texture_y, mip_level);
+            texture
+                .sample_unchecked(texture_x, texture_y, mip_level)
+                .darken(lit.max(emissive))
+        };
+        unsafe {
+            state
+                .framebuffer
+                .set_pixel_unchecked(x, y, fog.apply(colour))
+        };
+
+        u += u_m;
+        v += v_m;
+        light += light_m;
     }
 }
@@ -1,11 +1,22 @@
+use crate::colour::BGRA8;
+
+use super::fog::PortalFog;
+
 #[derive(Clone, Copy, Debug)]
 pub struct PortalNode {
     pub tree_depth: usize,
     pub sector_index: usize,
     pub x_min: usize,
     pub x_max: usize,
+    /// Squared distance from the camera to the nearest point of the portal that this node is seen
+    /// through
     pub depth_min: f32,
+    /// Squared distance from the camera to the furthest wall drawn within this node
     pub depth_max: f32,
+    /// Tint of the portal this node was created from, which is applied to everything drawn within it
+    pub tint: Option<BGRA8>,
+    /// Fog of everything drawn within this node, including that seen on the way to it
+    pub fog: PortalFog,
 }
 
 /// # Implementation
@@ -22,6 +33,9 @@ pub struct PortalNode {
 /// Nodes are stored in a flat array, in the order they are added. So, depending on the order they are
 /// added, it can be used to traverse the tree breadth/depth-first in forward/reverse order. But, because
 /// the functionality is not needed, nodes do not record their parent/child indices.
+///
+/// Nodes whose bounds are final but which haven't been drawn yet are counted for each X coordinate
+/// where their bounds are open, so that drawing can stop once none of them can add anything.
 pub struct PortalTree {
     screen_width: usize,
     screen_height: usize,
@@ -29,6 +43,11 @@ pub struct PortalTree {
     pub nodes: Vec<PortalNode>,
     pub portal_bounds_min: Vec<Vec<u16>>,
     pub portal_bounds_max: Vec<Vec<u16>>,
+
+    /// For each X coordinate, how many nodes are still to be drawn with open bounds there
+    pending_counts: Vec<u16>,
+    /// Number of X coordinates where any node that is still to be drawn has open bounds
+    open_columns: usize,
 }
 
 impl PortalTree {
@@ -48,6 +67,9 @@ impl PortalTree {
             nodes,
             portal_bounds_min,
             portal_bounds_max,
+
+            pending_counts: vec![0; screen_width],
+            open_columns: 0,
         }
     }
 
@@ -62,6 +84,8 @@ impl PortalTree {
         for bounds in self.portal_bounds_max.iter_mut() {
             bounds.resize(screen_width, self.screen_height as u16);
         }
+
+        self.pending_counts.resize(screen_width, 0);
     }
 
     pub fn reset(&mut self) {
@@ -74,14 +98,21 @@ impl PortalTree {
         for bounds in self.portal_bounds_max.iter_mut() {
             bounds.fill(self.screen_height as u16);
         }
+
+        self.pending_counts.fill(0);
+        self.open_columns = 0;
     }
 
     pub fn push_node(&mut self, node: PortalNode) {
-        while node.tree_depth > (self.portal_bounds_min.len() - 1) {
+        self.reserve_depth(node.tree_depth);
+        self.nodes.push(node);
+    }
+
+    /// Adds layers until there are bounds for nodes of the given depth
+    pub fn reserve_depth(&mut self, tree_depth: usize) {
+        while tree_depth > (self.portal_bounds_min.len() - 1) {
             self.add_layer();
         }
-
-        self.nodes.push(node);
     }
 
     pub unsafe fn get_node_unchecked(&self, index: usize) -> &PortalNode {
@@ -100,6 +131,47 @@ impl PortalTree {
         self.nodes.len()
     }
 
+    /// Counts a node as waiting to be drawn, once its bounds have been written
+    pub fn add_pending(&mut self, index: usize) {
+        self.update_pending(index, true);
+    }
+
+    /// Stops counting a node as waiting to be drawn, once it has been
+    pub fn remove_pending(&mut self, index: usize) {
+        self.update_pending(index, false);
+    }
+
+    /// Whether every column is closed to all of the nodes still waiting to be drawn, in which case
+    /// drawing them would change nothing
+    pub fn is_covered(&self) -> bool {
+        self.open_columns == 0
+    }
+
+    fn update_pending(&mut self, index: usize, add: bool) {
+        let node = &self.nodes[index];
+        let min_bounds = &self.portal_bounds_min[node.tree_depth];
+        let max_bounds = &self.portal_bounds_max[node.tree_depth];
+
+        for x in node.x_min..node.x_max {
+            if min_bounds[x] >= max_bounds[x] {
+                continue;
+            }
+
+            let count = &mut self.pending_counts[x];
+            if add {
+                if *count == 0 {
+                    self.open_columns += 1;
+                }
+                *count += 1;
+            } else {
+                *count -= 1;
+                if *count == 0 {
+                    self.open_columns -= 1;
+                }
+            }
+        }
+    }
+
     pub unsafe fn get_bounds_unchecked(&self, depth: usize) -> (&[u16], &[u16]) {
         debug_assert!(depth < self.portal_bounds_min.len());
 
@@ -167,6 +239,11 @@ impl PortalTree {
     }
 
     fn add_layer(&mut self) {
+        log::warn!(
+            "Portals nested deeper than before, growing the portal tree to {} layers",
+            self.portal_bounds_min.len() + 1
+        );
+
         self.portal_bounds_min.push(vec![0; self.screen_width]);
         self.portal_bounds_max
             .push(vec![self.screen_height as u16; self.screen_width]);
@@ -1,27 +1,75 @@
-use std::f32::consts::PI;
+use std::{error::Error, f32::consts::PI, fmt, sync::Arc};
 
+use jobs::JobSystem;
 use maths::{geometry::Polygon, linear::Vec2f};
 
 use crate::{
+    assets::{Assets, Handle},
     camera::Camera,
-    colour::BGRA8,
-    consts::NEAR,
+    colour::{BlendMode, BGRA8},
+    consts::{DEFAULT_FAR, DEFAULT_NEAR, MIN_PROJECTED_SIZE},
     font::{AlignHeight, AlignWidth, Font},
-    player::Player,
-    surface::{Sector, Sprite},
+    pick::ViewRay,
+    profile,
+    surface::{Fog, Sector, Sprite},
     textures::Texture,
-    timer::Timer,
 };
 
 use super::{
+    fog::PortalFog,
     framebuffer::Framebuffer,
+    image::{ImageRenderer, Rect},
+    pass::RenderPass,
     portal::{PortalNode, PortalTree},
     sector::SectorRenderer,
     sprite::SpriteRenderer,
+    stats::RenderStats,
     text::TextRenderer,
-    util::{focal_dimensions, view_frustum},
+    util::{cylindrical_column_map, focal_dimensions, lighting_level, vertical_fov, view_frustum},
 };
 
+/// Largest multiple that text in the world is scaled up by, however near it is
+const MAX_WORLD_TEXT_SCALE: usize = 4;
+/// Depth at which the light carried by the camera has faded out completely
+const CAMERA_LIGHT_RADIUS: f32 = 192.0;
+const INV_CAMERA_LIGHT_RADIUS: f32 = 1.0 / CAMERA_LIGHT_RADIUS;
+
+/// How text drawn at a point in the world is sized
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum WorldTextSize {
+    /// The same size at any distance, like text on the HUD
+    Fixed,
+    /// Normal size at the given depth, and scaled up by whole multiples nearer than that. It is
+    /// never drawn smaller than normal, so stays readable from afar.
+    Scaled(f32),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RenderError {
+    /// A surface or sprite uses a texture index that hasn't been loaded
+    MissingTexture(usize),
+}
+
+impl fmt::Display for RenderError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            RenderError::MissingTexture(index) => write!(f, "texture {index} is not loaded"),
+        }
+    }
+}
+
+impl Error for RenderError {}
+
+/// Looks up the texture at an index stored in the map data, or the placeholder whilst it loads
+pub(super) fn resolve_texture(
+    textures: &Assets<Texture>,
+    index: usize,
+) -> Result<&Texture, RenderError> {
+    textures
+        .get_or_placeholder(Handle::from_index(index))
+        .ok_or(RenderError::MissingTexture(index))
+}
+
 pub struct RendererState {
     pub framebuffer: Framebuffer,
     pub camera: Camera,
@@ -42,7 +90,29 @@ pub struct RendererState {
     // Number of pixels to shift along Y-axis to simulate pitch (y-shearing)
     pitch_shear: f32,
 
+    // Distances to the near and far clipping planes, and the reciprocal of the distance between
+    // them, used to normalise depth
+    near: f32,
+    far: f32,
+    inv_depth_range: f32,
+
     pub debug: bool,
+    /// Applies lighting to textures in linear light rather than to their sRGB encoded colours,
+    /// which darkens them more gradually with distance
+    pub linear_lighting: bool,
+    /// Checks each wall, span and sprite for degenerate projections and non-finite values, and
+    /// skips any that have them rather than rasterising garbage. Without this, they are only caught
+    /// by debug assertions.
+    pub hardened: bool,
+    /// Set whilst drawing an off-screen view, such as a security monitor, so custom passes can
+    /// skip overlays that only belong on the main view
+    pub offscreen: bool,
+    /// Fog of any sector that doesn't have its own
+    pub fog: Fog,
+    /// Brightness of a light carried by the camera, such as a muzzle flash, which is added to the
+    /// lighting of everything near enough to it. Zero when there is no such light.
+    pub camera_light: f32,
+    pub stats: RenderStats,
 }
 
 impl RendererState {
@@ -50,7 +120,7 @@ impl RendererState {
         let framebuffer = Framebuffer::new(screen_width, screen_height);
         let camera = Camera::new(Vec2f::ZERO, 0.0);
 
-        let v_fov = h_fov / framebuffer.aspect_ratio();
+        let v_fov = vertical_fov(h_fov, framebuffer.aspect_ratio());
 
         let (focal_width, focal_height) = focal_dimensions(
             h_fov,
@@ -63,7 +133,7 @@ impl RendererState {
 
         let pitch_shear = camera.pitch_tan * focal_height;
 
-        let frustum = view_frustum(h_fov);
+        let frustum = view_frustum(h_fov, DEFAULT_NEAR, DEFAULT_FAR);
 
         Self {
             framebuffer,
@@ -80,13 +150,23 @@ impl RendererState {
 
             pitch_shear,
 
+            near: DEFAULT_NEAR,
+            far: DEFAULT_FAR,
+            inv_depth_range: 1.0 / (DEFAULT_FAR - DEFAULT_NEAR),
+
             debug: false,
+            linear_lighting: false,
+            hardened: false,
+            offscreen: false,
+            fog: Fog::NONE,
+            camera_light: 0.0,
+            stats: RenderStats::default(),
         }
     }
 
-    fn set_viewport(&mut self, width: usize, height: usize) {
-        self.framebuffer = Framebuffer::new(width, height);
-        self.v_fov = self.h_fov / self.framebuffer.aspect_ratio();
+    /// Recalculates the values that depend on the dimensions of the current framebuffer
+    fn configure_viewport(&mut self) {
+        self.v_fov = vertical_fov(self.h_fov, self.framebuffer.aspect_ratio());
 
         (self.focal_width, self.focal_height) = focal_dimensions(
             self.h_fov,
@@ -103,37 +183,54 @@ impl RendererState {
 
     fn set_fov(&mut self, h_fov: f32) {
         self.h_fov = h_fov;
-        self.v_fov = self.h_fov / self.framebuffer.aspect_ratio();
+        self.frustum = view_frustum(h_fov, self.near, self.far);
 
-        (self.focal_width, self.focal_height) = focal_dimensions(
-            self.h_fov,
-            self.v_fov,
-            self.framebuffer.half_width(),
-            self.framebuffer.half_height(),
-        );
-        self.inv_focal_width = 1.0 / self.focal_width;
-        self.inv_focal_height = 1.0 / self.focal_height;
+        self.configure_viewport();
+    }
 
-        self.frustum = view_frustum(h_fov);
+    fn set_depth_range(&mut self, near: f32, far: f32) {
+        debug_assert!(near > 0.0 && far > near, "invalid depth range :: {near} to {far}");
 
-        // No need to recalculate pitch shear and focal height ratios here, as they are initialised
-        // at the start of `update`.
+        self.near = near;
+        self.far = far;
+        self.inv_depth_range = 1.0 / (far - near);
+        self.frustum = view_frustum(self.h_fov, near, far);
     }
 
-    fn update(&mut self, timer: &Timer, player: &Player) {
-        // Use player camera
-        self.camera = player.camera.clone();
+    fn update(&mut self, camera: &Camera) {
+        self.camera = camera.clone();
+        self.stats = RenderStats::default();
 
-        let amplitude = player.velocity.magnitude_sq() / (50.0 * 50.0);
-        let speed = timer.start.elapsed().as_secs_f32() * 5.0;
+        self.pitch_shear = self.camera.pitch_tan * self.focal_height;
+    }
 
-        // View bobbing
-        let bob_yaw = speed.sin() * amplitude * 0.03;
-        let bob_pitch = (speed * 2.0).sin() * amplitude * 0.0075;
+    pub fn near(&self) -> f32 {
+        self.near
+    }
 
-        self.camera.rotate(Vec2f::new(bob_yaw, bob_pitch));
+    pub fn far(&self) -> f32 {
+        self.far
+    }
 
-        self.pitch_shear = self.camera.pitch_tan * self.focal_height;
+    /// Maps a depth between the near and far planes to a value between 0.0 and 1.0
+    pub fn normalise_depth(&self, depth: f32) -> f32 {
+        (depth - self.near) * self.inv_depth_range
+    }
+
+    /// Lighting level added by [Self::camera_light] to surfaces at the given depth, which fades
+    /// linearly with distance
+    pub fn camera_light_level(&self, depth: f32) -> u8 {
+        if self.camera_light <= 0.0 {
+            return 0;
+        }
+
+        let falloff = (1.0 - depth * INV_CAMERA_LIGHT_RADIUS).max(0.0);
+        lighting_level(self.camera_light * falloff, self.linear_lighting)
+    }
+
+    /// Fog within the sector, which is the sector's own if it overrides the default
+    pub fn sector_fog(&self, sector: &Sector) -> Fog {
+        sector.fog.unwrap_or(self.fog)
     }
 
     pub fn transform_view(&self, point: Vec2f) -> Vec2f {
@@ -142,7 +239,9 @@ impl RendererState {
 
     pub fn project_screen_space(&self, point: Vec2f, height_offset: f32) -> (Vec2f, f32) {
         let z = point.y;
-        debug_assert!(z > 0.0); // This should never be zero, as we clip against the near plane
+        // This should never be zero, as we clip against the near plane, unless the view itself is
+        // degenerate. Hardened rendering will skip whatever this is projected for instead.
+        debug_assert!(z > 0.0 || self.hardened);
         let inv_z = 1.0 / z;
 
         // construct pseudo vertical camera space coordinate
@@ -162,6 +261,18 @@ impl RendererState {
         (Vec2f::new(screen_space_x, screen_space_y), inv_z)
     }
 
+    /// Returns whether a primitive should be skipped by hardened rendering, because one of the values
+    /// that it is drawn with isn't finite
+    pub fn is_degenerate(&self, values: &[f32]) -> bool {
+        self.hardened && values.iter().any(|value| !value.is_finite())
+    }
+
+    /// Returns whether a primitive should be skipped by hardened rendering, because a projected size
+    /// that is divided by is too close to zero, or isn't a number at all
+    pub fn is_degenerate_size(&self, size: f32) -> bool {
+        self.hardened && (size.is_nan() || size.abs() < MIN_PROJECTED_SIZE)
+    }
+
     pub fn h_fov(&self) -> f32 {
         self.h_fov
     }
@@ -197,15 +308,43 @@ pub struct Renderer {
     sector_renderer: SectorRenderer,
     sprite_renderer: SpriteRenderer,
     text_renderer: TextRenderer,
+    image_renderer: ImageRenderer,
+
+    // When enabled, the rendered image is resampled to a cylindrical projection to reduce distortion
+    // with a wide field of view
+    distortion_correction: bool,
+    column_map: Vec<usize>,
+    // One row of scratch space for each band of rows that is remapped in parallel
+    remap_buffer: Vec<BGRA8>,
+
+    // Resolution that the world is drawn at, relative to the framebuffer. When it isn't 1, the world
+    // is drawn into the scaled framebuffer, which is resampled into the framebuffer afterwards.
+    render_scale: f32,
+    scaled_framebuffer: Framebuffer,
+
+    // Sprites that passed culling this frame, with their positions in view space, sorted back to
+    // front. Kept between frames to reuse its allocation.
+    visible_sprites: Vec<(Vec2f, Sprite)>,
+
+    // Run in order by each update, with whether each is enabled
+    passes: Vec<(RenderPass, bool)>,
+
+    jobs: Arc<JobSystem>,
 }
 
 impl Renderer {
-    pub fn new(screen_width: usize, screen_height: usize, h_fov: f32) -> Self {
+    pub fn new(
+        screen_width: usize,
+        screen_height: usize,
+        h_fov: f32,
+        jobs: Arc<JobSystem>,
+    ) -> Self {
         let state = RendererState::new(screen_width, screen_height, h_fov);
         let portal_tree = PortalTree::with_depth(4, screen_width, screen_height);
         let sector_renderer = SectorRenderer::new(&state);
         let sprite_renderer = SpriteRenderer::new(&state);
         let text_renderer = TextRenderer::new(&state);
+        let image_renderer = ImageRenderer::new(&state);
 
         Self {
             state,
@@ -213,6 +352,20 @@ impl Renderer {
             sector_renderer,
             sprite_renderer,
             text_renderer,
+            image_renderer,
+
+            distortion_correction: false,
+            column_map: cylindrical_column_map(h_fov, screen_width),
+            remap_buffer: vec![BGRA8::default(); screen_width * (jobs.worker_count() + 1)],
+
+            render_scale: 1.0,
+            scaled_framebuffer: Framebuffer::new(0, 0),
+
+            visible_sprites: Vec::new(),
+
+            passes: RenderPass::defaults(),
+
+            jobs,
         }
     }
 
@@ -224,6 +377,11 @@ impl Renderer {
         &mut self.state
     }
 
+    /// Returns what was drawn by the most recent call to [Self::update]
+    pub fn stats(&self) -> &RenderStats {
+        &self.state.stats
+    }
+
     pub fn framebuffer(&self) -> &Framebuffer {
         &self.state.framebuffer
     }
@@ -235,11 +393,32 @@ impl Renderer {
             return;
         }
 
-        self.state.set_viewport(screen_width, screen_height);
+        self.state.framebuffer = Framebuffer::new(screen_width, screen_height);
+        self.configure_viewport();
+    }
+
+    /// Resizes the buffers of each sub-renderer to match the current framebuffer
+    fn configure_viewport(&mut self) {
+        self.state.configure_viewport();
 
-        self.portal_tree.resize_bounds(screen_width, screen_height);
+        self.portal_tree.resize_bounds(
+            self.state.framebuffer.width(),
+            self.state.framebuffer.height(),
+        );
         self.sector_renderer.set_viewport(&self.state);
         self.sprite_renderer.set_viewport(&self.state);
+
+        self.column_map =
+            cylindrical_column_map(self.state.h_fov(), self.state.framebuffer.width());
+        self.remap_buffer.resize(
+            self.state.framebuffer.width() * (self.jobs.worker_count() + 1),
+            BGRA8::default(),
+        );
+    }
+
+    /// Horizontal field of view, in degrees
+    pub fn fov(&self) -> f32 {
+        self.state.h_fov()
     }
 
     pub fn set_fov(&mut self, h_fov: f32) {
@@ -248,59 +427,425 @@ impl Renderer {
         }
 
         self.state.set_fov(h_fov);
+        self.column_map =
+            cylindrical_column_map(self.state.h_fov(), self.state.framebuffer.width());
+    }
+
+    /// Distances to the near and far clipping planes
+    pub fn depth_range(&self) -> (f32, f32) {
+        (self.state.near, self.state.far)
+    }
+
+    /// Sets the distances to the near and far clipping planes. Depth-based lighting and mip selection
+    /// are spread between them, so a larger map would want a more distant far plane.
+    pub fn set_depth_range(&mut self, near: f32, far: f32) {
+        self.state.set_depth_range(near, far);
+    }
+
+    pub fn render_scale(&self) -> f32 {
+        self.render_scale
+    }
+
+    /// Sets the resolution that the world is drawn at, relative to the framebuffer, without
+    /// changing the size of the framebuffer. Below 1 the world is drawn at a lower resolution and
+    /// scaled up, which is cheaper, and above 1 it is drawn at a higher one and averaged down, which
+    /// smooths edges and distant textures. Anything drawn over the world afterwards, such as text,
+    /// is drawn at the framebuffer's resolution either way.
+    pub fn set_render_scale(&mut self, scale: f32) {
+        debug_assert!(scale.is_finite() && scale > 0.0, "invalid render scale :: {scale}");
+        if scale == self.render_scale {
+            return;
+        }
+
+        self.render_scale = scale;
+        self.configure_viewport();
+    }
+
+    /// Swaps the scaled framebuffer in to draw the world into, if the render scale calls for one,
+    /// returning whether it did. Off-screen views are drawn at the size of their own target.
+    fn begin_scaled(&mut self) -> bool {
+        if self.render_scale == 1.0 || self.state.offscreen {
+            return false;
+        }
+
+        let scaled = |size: usize| ((size as f32 * self.render_scale).round() as usize).max(1);
+        let width = scaled(self.state.framebuffer.width());
+        let height = scaled(self.state.framebuffer.height());
+        if width != self.scaled_framebuffer.width() || height != self.scaled_framebuffer.height() {
+            self.scaled_framebuffer = Framebuffer::new(width, height);
+        }
+
+        core::mem::swap(&mut self.state.framebuffer, &mut self.scaled_framebuffer);
+        self.configure_viewport();
+
+        true
+    }
+
+    /// Swaps the framebuffer back in after [Self::begin_scaled], and resamples the world into it
+    fn end_scaled(&mut self) {
+        core::mem::swap(&mut self.state.framebuffer, &mut self.scaled_framebuffer);
+        self.state.framebuffer.resample(&self.scaled_framebuffer);
+
+        // The sector and sprite renderers are left at the scaled size, so that the wall depths of
+        // this frame can still be looked up by `draw_world_text`
+        self.state.configure_viewport();
+        self.state.pitch_shear = self.state.camera.pitch_tan * self.state.focal_height;
+        self.column_map =
+            cylindrical_column_map(self.state.h_fov(), self.state.framebuffer.width());
+    }
+
+    pub fn distortion_correction(&self) -> bool {
+        self.distortion_correction
+    }
+
+    /// Enables resampling of the rendered image to a cylindrical projection, which makes a wide field
+    /// of view usable by removing the stretching towards the edges of the screen. This comes at the
+    /// cost of reduced horizontal detail in the centre of the screen.
+    pub fn set_distortion_correction(&mut self, enabled: bool) {
+        self.distortion_correction = enabled;
     }
 
-    pub fn update(
+    /// Returns the ray from the camera of the last frame through a point on the screen, in pixels from
+    /// the top left, where the camera was within the sector at `sector_index`. This undoes the
+    /// projection, including pitch and distortion correction, so the ray passes through whatever was
+    /// drawn at that point.
+    pub fn view_ray(&self, screen: Vec2f, sector_index: usize) -> ViewRay {
+        let state = &self.state;
+        let camera = &state.camera;
+
+        let mut x = screen.x;
+        if self.distortion_correction && !self.column_map.is_empty() {
+            let column = (x.max(0.0) as usize).min(self.column_map.len() - 1);
+            x = self.column_map[column] as f32 + 0.5;
+        }
+
+        let forward = camera.direction;
+        let right = Vec2f::new(forward.y, -forward.x);
+        let across = (x - state.framebuffer.half_width()) * state.inv_focal_width;
+        let slope = -(screen.y - state.framebuffer.half_height() - state.pitch_shear)
+            * state.inv_focal_height;
+
+        ViewRay {
+            origin: camera.position,
+            z: camera.z,
+            sector_index,
+            forward,
+            direction: forward + right * across,
+            slope,
+        }
+    }
+
+    /// Renders the world from the point of view of the given camera, which must be located within
+    /// the sector at `sector_index`. Sprites may be gathered from several sources, such as items and
+    /// enemies, so any iterator over them is accepted.
+    ///
+    /// Drawing is split into [RenderPass]es, which run in order and can be enabled, disabled or
+    /// added to by the caller. If the sectors pass is disabled, the screen is cleared to black
+    /// first, as nothing else would cover the previous frame.
+    pub fn update<'a>(
         &mut self,
-        timer: &Timer,
-        player: &Player,
-        textures: &[Texture],
+        camera: &Camera,
+        sector_index: usize,
+        textures: &Assets<Texture>,
         sectors: &[Sector],
-        sprites: &[Sprite],
-    ) {
-        self.state.update(timer, player);
+        sprites: impl IntoIterator<Item = &'a Sprite>,
+    ) -> Result<(), RenderError> {
+        profile::scope!("render", "update");
+        let mut scaled = self.begin_scaled();
+        self.state.update(camera);
 
         self.portal_tree.reset();
         self.sector_renderer.update(&self.state);
         self.sprite_renderer.update(&self.state);
 
-        // Add initial task to render the sector that the player is in
+        // Add initial task to render the sector that the camera is in. Portal depths are squared.
+        let near_sq = self.state.near * self.state.near;
         self.portal_tree.push_node(PortalNode {
             tree_depth: 0,
-            sector_index: player.sector_index,
+            sector_index,
             x_min: 0,
             x_max: self.state.framebuffer.width(),
-            depth_min: NEAR,
-            depth_max: NEAR,
+            depth_min: near_sq,
+            depth_max: near_sq,
+            tint: None,
+            fog: PortalFog::new(self.state.sector_fog(&sectors[sector_index])),
         });
 
+        if !self.is_pass_enabled("sectors") {
+            self.state.framebuffer.fill(BGRA8::BLACK);
+        }
+
+        // Passes are taken out whilst they run, so custom passes can borrow the state mutably
+        let mut passes = core::mem::take(&mut self.passes);
+        let mut sprites = Some(sprites);
+        let mut result = Ok(());
+
+        for (pass, _) in passes.iter_mut().filter(|(_, enabled)| *enabled) {
+            profile::scope!("render", pass.name());
+            result = match pass {
+                RenderPass::Sectors => self.draw_sectors(textures, sectors),
+                RenderPass::Sprites => match sprites.take() {
+                    Some(sprites) => self.draw_sprites(textures, sectors, sprites),
+                    None => Ok(()),
+                },
+                RenderPass::PortalTints => {
+                    self.draw_portal_tints();
+                    Ok(())
+                }
+                RenderPass::Debug => {
+                    if self.state.debug {
+                        self.debug_draw_portals();
+                    }
+                    Ok(())
+                }
+                RenderPass::PostProcess => {
+                    if self.distortion_correction {
+                        self.state.framebuffer.remap_columns(
+                            &self.column_map,
+                            &mut self.remap_buffer,
+                            &self.jobs,
+                        );
+                    }
+
+                    // Anything drawn after this is drawn over the resampled image, at full size
+                    if scaled {
+                        self.end_scaled();
+                        scaled = false;
+                    }
+                    Ok(())
+                }
+                RenderPass::Custom { draw, .. } => {
+                    draw(&mut self.state);
+                    Ok(())
+                }
+            };
+
+            if result.is_err() {
+                break;
+            }
+        }
+
+        self.passes = passes;
+        if scaled {
+            self.end_scaled();
+        }
+
+        result
+    }
+
+    /// Names of the render passes, in the order they run, with whether each is enabled
+    pub fn passes(&self) -> impl Iterator<Item = (&'static str, bool)> + '_ {
+        self.passes
+            .iter()
+            .map(|(pass, enabled)| (pass.name(), *enabled))
+    }
+
+    pub fn is_pass_enabled(&self, name: &str) -> bool {
+        self.passes
+            .iter()
+            .any(|(pass, enabled)| *enabled && pass.name() == name)
+    }
+
+    /// Enables or disables every pass with the given name, returning false if there are none
+    pub fn set_pass_enabled(&mut self, name: &str, enabled: bool) -> bool {
+        let mut found = false;
+        for (pass, pass_enabled) in self.passes.iter_mut() {
+            if pass.name() == name {
+                *pass_enabled = enabled;
+                found = true;
+            }
+        }
+
+        found
+    }
+
+    /// Adds an enabled pass to run just before the first pass named `before`, or after all other
+    /// passes if there is no such pass
+    pub fn insert_pass(&mut self, pass: RenderPass, before: Option<&str>) {
+        let index = before
+            .and_then(|before| {
+                self.passes
+                    .iter()
+                    .position(|(pass, _)| pass.name() == before)
+            })
+            .unwrap_or(self.passes.len());
+
+        self.passes.insert(index, (pass, true));
+    }
+
+    /// Draws the sector that the camera is in, and every sector seen through its portals
+    fn draw_sectors(
+        &mut self,
+        textures: &Assets<Texture>,
+        sectors: &[Sector],
+    ) -> Result<(), RenderError> {
         // Sectors are rendered in a breadth-first manner, and each portal encountered is added
         // to the queue of tasks to be rendered.
+        //
+        // Once no node that is left has any open columns, such as when the nearest walls cover the
+        // whole screen, those nodes are skipped, as they could only draw empty spans.
         let mut portal_index = 0;
-        while portal_index < self.portal_tree.nodes_len() {
-            self.sector_renderer.draw_sector(
-                &mut self.state,
-                &mut self.portal_tree,
-                sectors,
-                textures,
-                portal_index,
-            );
+        {
+            profile::scope!("render", "visibility");
+            self.portal_tree.add_pending(0);
+            while portal_index < self.portal_tree.nodes_len() {
+                let first_child = self.portal_tree.nodes_len();
+                self.sector_renderer.draw_sector(
+                    &mut self.state,
+                    &mut self.portal_tree,
+                    sectors,
+                    portal_index,
+                );
+
+                // The bounds of the nodes added whilst drawing are only known once it has finished
+                for child in first_child..self.portal_tree.nodes_len() {
+                    self.portal_tree.add_pending(child);
+                }
+                self.portal_tree.remove_pending(portal_index);
 
-            portal_index += 1;
+                portal_index += 1;
+                if self.portal_tree.is_covered() {
+                    break;
+                }
+            }
         }
 
+        // Only once every portal is known are the spans drawn
+        {
+            profile::scope!("render", "rasterise");
+            self.sector_renderer
+                .rasterise(&mut self.state, sectors, textures)?;
+        }
+
+        self.state.stats.portal_nodes = portal_index;
+        self.state.stats.portal_nodes_skipped = self.portal_tree.nodes_len() - portal_index;
+        self.state.stats.max_portal_depth = self.portal_tree.nodes[..portal_index]
+            .iter()
+            .map(|node| node.tree_depth)
+            .max()
+            .unwrap_or(0);
+
+        Ok(())
+    }
+
+    fn draw_sprites<'a>(
+        &mut self,
+        textures: &Assets<Texture>,
+        sectors: &[Sector],
+        sprites: impl IntoIterator<Item = &'a Sprite>,
+    ) -> Result<(), RenderError> {
+        // Sprites are drawn back to front, so that partly transparent sprites blend over those
+        // behind them. Those that can't be seen are culled first, so they aren't sorted.
+        self.sprite_renderer.prepare(&self.portal_tree, sectors.len());
+        let mut sprite_count = 0;
+        self.visible_sprites.clear();
         for sprite in sprites {
+            sprite_count += 1;
+            if let Some(vs) = self.sprite_renderer.cull(&self.state, sprite) {
+                self.visible_sprites.push((vs, sprite.clone()));
+            }
+        }
+        self.visible_sprites.sort_unstable_by(|a, b| b.0.y.total_cmp(&a.0.y));
+        self.state.stats.sprites = sprite_count;
+
+        for (vs, sprite) in self.visible_sprites.iter() {
             self.sprite_renderer.draw_sprite(
                 &mut self.state,
                 &self.portal_tree,
+                self.sector_renderer.get_wall_inv_depths(),
                 sprite,
-                textures.get(sprite.texture_data.index).unwrap(),
+                *vs,
+                resolve_texture(textures, sprite.texture_data.index)?,
             );
         }
 
-        if self.state.debug {
-            self.debug_draw_portals();
-        }
+        Ok(())
+    }
+
+    /// Renders the world into the given off-screen framebuffer, rather than the main framebuffer.
+    ///
+    /// The viewport is temporarily reconfigured to match the dimensions of the target, so this can be
+    /// called any number of times per frame to render secondary views (e.g. security monitors or
+    /// mirrors), which can then be drawn into the main framebuffer via [Renderer::draw_framebuffer].
+    pub fn update_offscreen<'a>(
+        &mut self,
+        target: &mut Framebuffer,
+        camera: &Camera,
+        sector_index: usize,
+        textures: &Assets<Texture>,
+        sectors: &[Sector],
+        sprites: impl IntoIterator<Item = &'a Sprite>,
+    ) -> Result<(), RenderError> {
+        core::mem::swap(&mut self.state.framebuffer, target);
+        self.configure_viewport();
+
+        // Debug overlays are only relevant to the main view, and the camera's light belongs to the
+        // main view's camera
+        let debug = self.state.debug;
+        let camera_light = self.state.camera_light;
+        self.state.debug = false;
+        self.state.camera_light = 0.0;
+        self.state.offscreen = true;
+
+        let result = self.update(camera, sector_index, textures, sectors, sprites);
+
+        self.state.debug = debug;
+        self.state.camera_light = camera_light;
+        self.state.offscreen = false;
+
+        core::mem::swap(&mut self.state.framebuffer, target);
+        self.configure_viewport();
+
+        result
+    }
+
+    /// Combines the colour with the whole screen using the given mode, with its alpha as strength
+    /// (e.g. blending to flash the screen red when taking damage, or adding to brighten it for a
+    /// muzzle flash)
+    pub fn draw_overlay(&mut self, colour: BGRA8, mode: BlendMode) {
+        self.state.framebuffer.composite_fill(colour, mode);
+    }
+
+    /// Fills the given rectangle of the screen with the colour, ignoring its alpha
+    pub fn fill_rect(&mut self, colour: BGRA8, dst: Rect) {
+        self.state
+            .framebuffer
+            .fill_rect(dst.x, dst.y, dst.width, dst.height, colour);
+    }
+
+    /// Fills a polygon on the screen, with points in pixels. The colour is copied as is, or
+    /// combined with what is beneath if given a mode.
+    pub fn draw_polygon(&mut self, colour: BGRA8, points: &[Vec2f], mode: Option<BlendMode>) {
+        self.state.framebuffer.fill_polygon(points, colour, mode);
+    }
+
+    /// Blends the colour over the given rectangle of the screen, in pixels
+    pub fn draw_rect(&mut self, colour: BGRA8, dst: Rect) {
+        self.state
+            .framebuffer
+            .blend_fill_rect(dst.x, dst.y, dst.width, dst.height, colour);
+    }
+
+    /// Draws the given framebuffer into a rectangle of the main framebuffer, scaling it to fit.
+    /// Coordinates and dimensions are relative to the size of the main framebuffer.
+    pub fn draw_framebuffer(
+        &mut self,
+        source: &Framebuffer,
+        x: f32,
+        y: f32,
+        width: f32,
+        height: f32,
+    ) {
+        let screen_width = self.state.framebuffer.width() as f32;
+        let screen_height = self.state.framebuffer.height() as f32;
+
+        self.state.framebuffer.blit_scaled(
+            source,
+            (screen_width * x) as usize,
+            (screen_height * y) as usize,
+            (screen_width * width) as usize,
+            (screen_height * height) as usize,
+        );
     }
 
     pub fn draw_text(
@@ -316,7 +861,115 @@ impl Renderer {
         let y = (self.state.framebuffer.height() as f32 * y) as usize;
 
         self.text_renderer
-            .render(&mut self.state, font, colour, align, x, y, text)
+            .render(&mut self.state, font, colour, align, x, y, 1, text)
+    }
+
+    /// Draws text centred above a point in the world, at height `z`, as seen in the last frame. It
+    /// isn't drawn if the point is outside of the view, or behind the nearest solid wall in its
+    /// column.
+    pub fn draw_world_text(
+        &mut self,
+        font: &Font,
+        colour: BGRA8,
+        position: Vec2f,
+        z: f32,
+        size: WorldTextSize,
+        text: &str,
+    ) {
+        let state = &self.state;
+
+        let vs = state.transform_view(position);
+        if vs.y <= state.near || vs.y > state.far {
+            return;
+        }
+
+        let (screen, inv_z) = state.project_screen_space(vs, z);
+        if !(screen.x >= 0.0
+            && screen.x < state.framebuffer.width() as f32
+            && screen.y >= 0.0
+            && screen.y < state.framebuffer.height() as f32)
+        {
+            return;
+        }
+
+        // The wall depths are of the columns that the world was drawn at, which differ from those of
+        // the framebuffer when the render scale isn't 1
+        let mut x = screen.x as usize;
+        let wall_inv_depths = self.sector_renderer.get_wall_inv_depths();
+        let depth_x = x * wall_inv_depths.len() / state.framebuffer.width();
+        let wall_inv_depth = wall_inv_depths.get(depth_x);
+        if wall_inv_depth.is_some_and(|wall_inv_depth| *wall_inv_depth > inv_z) {
+            return;
+        }
+
+        // Find where the column was moved to when correcting distortion
+        if self.distortion_correction {
+            x = self.column_map.partition_point(|&source_x| source_x < x);
+        }
+
+        let scale = match size {
+            WorldTextSize::Fixed => 1,
+            WorldTextSize::Scaled(depth) => ((depth / vs.y) as usize).clamp(1, MAX_WORLD_TEXT_SCALE),
+        };
+
+        self.text_renderer.render(
+            &mut self.state,
+            font,
+            colour,
+            (AlignWidth::Centre, AlignHeight::Bottom),
+            x,
+            screen.y as usize,
+            scale,
+            text,
+        );
+    }
+
+    /// Draws the whole texture into the given rectangle of the screen, in pixels
+    pub fn draw_image(&mut self, texture: &Texture, dst: Rect) {
+        let src = Rect::new(0, 0, texture.levels[0].width, texture.levels[0].height);
+
+        self.image_renderer
+            .draw_image(&mut self.state, texture, src, dst);
+    }
+
+    /// Draws the texture like [Self::draw_image], with the tint combined over it using the given
+    /// mode
+    pub fn draw_image_tinted(&mut self, texture: &Texture, dst: Rect, tint: BGRA8, mode: BlendMode) {
+        let src = Rect::new(0, 0, texture.levels[0].width, texture.levels[0].height);
+
+        self.image_renderer
+            .draw_image_tinted(&mut self.state, texture, src, dst, tint, mode);
+    }
+
+    /// Draws the texture as a nine-slice panel filling the given rectangle of the screen, in pixels
+    pub fn draw_nine_slice(&mut self, texture: &Texture, border: usize, dst: Rect) {
+        self.image_renderer
+            .draw_nine_slice(&mut self.state, texture, border, dst);
+    }
+
+    /// Blends the tint of each tinted portal over everything that was drawn within it. Portals are
+    /// visited from deepest to shallowest, so that the tints of nested portals are layered correctly.
+    fn draw_portal_tints(&mut self) {
+        for portal in self.portal_tree.nodes.iter().rev() {
+            let Some(tint) = portal.tint else {
+                continue;
+            };
+
+            let y_bounds = unsafe { self.portal_tree.get_bounds_unchecked(portal.tree_depth) };
+
+            for x in portal.x_min..portal.x_max {
+                for y in y_bounds.0[x]..y_bounds.1[x] {
+                    unsafe {
+                        let colour = self.state.framebuffer.get_pixel_unchecked(x, y as usize);
+                        self.state.framebuffer.set_pixel_unchecked(
+                            x,
+                            y as usize,
+                            tint.blend(colour),
+                        );
+                    }
+                }
+            }
+        }
     }
 
     fn debug_draw_portals(&mut self) {
@@ -337,22 +990,17 @@ impl Renderer {
             let left_x = portal.x_min;
             let right_x = portal.x_max.saturating_sub(1);
 
-            // Draw left side
-            for y in y_bounds.0[left_x]..y_bounds.1[left_x] {
-                unsafe {
-                    self.state
-                        .framebuffer
-                        .set_pixel_unchecked(left_x, y as usize, colour)
-                };
-            }
-
-            // Draw right side
-            for y in y_bounds.0[right_x]..y_bounds.1[right_x] {
-                unsafe {
-                    self.state
-                        .framebuffer
-                        .set_pixel_unchecked(right_x, y as usize, colour)
-                };
+            // Draw left and right sides
+            for x in [left_x, right_x] {
+                let (top_y, bottom_y) = (y_bounds.0[x], y_bounds.1[x]);
+                if top_y < bottom_y {
+                    self.state.framebuffer.draw_line(
+                        Vec2f::new(x as f32, top_y as f32),
+                        Vec2f::new(x as f32, (bottom_y - 1) as f32),
+                        1.0,
+                        colour,
+                    );
+                }
             }
 
             // Draw top/bottom sides
@@ -376,3 +1024,79 @@ impl Renderer {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use std::{cell::Cell, rc::Rc};
+
+    use super::*;
+    use crate::{
+        demo,
+        golden_tests::{load_renderer, POSES, WIDTH},
+        map,
+    };
+
+    #[test]
+    fn render_scale_test() {
+        let (assets, mut renderer) = load_renderer();
+        let sectors = demo::sectors();
+
+        let pose = &POSES[0];
+        let mut camera = Camera::new(pose.position, pose.z);
+        camera.set_rotation(pose.yaw, pose.pitch);
+        let sector_index = map::find_sector(pose.position, &sectors).unwrap();
+
+        let render = |renderer: &mut Renderer| {
+            renderer
+                .update(&camera, sector_index, &assets.textures, &sectors, [])
+                .unwrap();
+            renderer.framebuffer().pixels_as_u8().to_vec()
+        };
+        let full = render(&mut renderer);
+
+        // Passes up to post-processing draw at the scaled size, and those after over the result
+        let sizes = Rc::new(Cell::new((0, 0)));
+        let seen = sizes.clone();
+        renderer.insert_pass(
+            RenderPass::custom("before", move |state| {
+                let after = seen.get().1;
+                seen.set((state.framebuffer.width(), after));
+            }),
+            Some("post_process"),
+        );
+        let seen = sizes.clone();
+        renderer.insert_pass(
+            RenderPass::custom("after", move |state| {
+                let before = seen.get().0;
+                seen.set((before, state.framebuffer.width()));
+            }),
+            None,
+        );
+
+        // Drawn at twice the size and averaged down, it looks much the same, only smoother
+        renderer.set_render_scale(2.0);
+        let supersampled = render(&mut renderer);
+        assert_eq!(sizes.get(), (WIDTH * 2, WIDTH));
+        assert_eq!(supersampled.len(), full.len());
+        let difference: u32 = full
+            .iter()
+            .zip(supersampled.iter())
+            .map(|(a, b)| a.abs_diff(*b) as u32)
+            .sum();
+        let mean_difference = difference as f32 / full.len() as f32;
+        assert!(
+            mean_difference < 16.0,
+            "mean difference of {mean_difference}"
+        );
+
+        renderer.set_render_scale(0.5);
+        let upscaled = render(&mut renderer);
+        assert_eq!(sizes.get(), (WIDTH / 2, WIDTH));
+        assert_eq!(upscaled.len(), full.len());
+
+        // Back at full size, nothing is left over from drawing at the other sizes
+        renderer.set_render_scale(1.0);
+        assert_eq!(render(&mut renderer), full);
+        assert_eq!(sizes.get(), (WIDTH, WIDTH));
+    }
+}
@@ -1,10 +1,18 @@
-use crate::{surface::Sector, textures::Texture};
+use crate::{assets::Assets, surface::Sector, textures::Texture};
 
-use super::{plane::PlaneRenderer, portal::PortalTree, wall::WallRenderer, RendererState};
+use super::{
+    plane::PlaneRenderer,
+    portal::PortalTree,
+    span::{SpanBuffer, SpanSurface},
+    wall::WallRenderer,
+    RenderError, RendererState,
+};
 
 pub struct SectorRenderer {
     wall_renderer: WallRenderer,
     plane_renderer: PlaneRenderer,
+    /// Spans of every sector drawn this frame, which are rasterised once all have been found
+    spans: SpanBuffer,
 }
 
 impl SectorRenderer {
@@ -12,6 +20,7 @@ impl SectorRenderer {
         Self {
             wall_renderer: WallRenderer::new(state),
             plane_renderer: PlaneRenderer::new(state),
+            spans: SpanBuffer::new(),
         }
     }
 
@@ -23,22 +32,43 @@ impl SectorRenderer {
     pub fn update(&mut self, state: &RendererState) {
         self.wall_renderer.update(state);
         self.plane_renderer.update(state);
+        self.spans.clear();
     }
 
+    /// For each X coordinate, the inverse depth of the solid wall that has been rendered this frame
+    pub fn get_wall_inv_depths(&self) -> &[f32] {
+        self.wall_renderer.get_wall_inv_depths()
+    }
+
+    /// Finds the visible parts of the walls, floor and ceiling of the portal's sector, adding any
+    /// portals seen through its walls to the tree. Nothing is drawn until [Self::rasterise].
     pub fn draw_sector(
         &mut self,
         state: &mut RendererState,
         portals: &mut PortalTree,
         sectors: &[Sector],
-        textures: &[Texture],
         portal_index: usize,
     ) {
         let sector_index = portals.nodes[portal_index].sector_index;
         let sector = &sectors[sector_index];
 
-        for wall in sector.walls.iter() {
-            self.wall_renderer
-                .render(state, portals, sectors, textures, portal_index, wall)
+        // Walls beyond the far plane are culled, which can leave columns of the portal that none of
+        // the sector's walls cover. These would otherwise keep the wall bounds of whatever was drawn
+        // there before.
+        let portal = &portals.nodes[portal_index];
+        let portal_bounds = unsafe { portals.get_bounds_unchecked(portal.tree_depth) };
+        self.wall_renderer
+            .reset_wall_bounds(portal_bounds, portal.x_min, portal.x_max);
+
+        for wall_index in 0..sector.walls.len() {
+            self.wall_renderer.render(
+                state,
+                portals,
+                sectors,
+                &mut self.spans,
+                portal_index,
+                wall_index,
+            );
         }
 
         let portal = unsafe { portals.get_node_unchecked(portal_index) };
@@ -55,22 +85,34 @@ impl SectorRenderer {
         self.plane_renderer.draw_plane(
             state,
             portal,
+            &mut self.spans,
             (min_portal_bounds, min_wall_bounds),
             vs_ceiling_height,
-            textures.get(sector.ceiling.texture_data.index).unwrap(),
-            sector.ceiling.texture_data.offset,
-            &sector.ceiling.texture_data.scale_rotate,
+            SpanSurface::Ceiling {
+                sector: sector_index,
+            },
         );
 
         // Draw sector floor
         self.plane_renderer.draw_plane(
             state,
             portal,
+            &mut self.spans,
             (max_wall_bounds, max_portal_bounds),
             vs_floor_height,
-            textures.get(sector.floor.texture_data.index).unwrap(),
-            sector.floor.texture_data.offset,
-            &sector.floor.texture_data.scale_rotate,
+            SpanSurface::Floor {
+                sector: sector_index,
+            },
         );
     }
+
+    /// Draws the spans of every sector found since the last update
+    pub fn rasterise(
+        &self,
+        state: &mut RendererState,
+        sectors: &[Sector],
+        textures: &Assets<Texture>,
+    ) -> Result<(), RenderError> {
+        self.spans.rasterise(state, sectors, textures)
+    }
 }
@@ -0,0 +1,141 @@
+use crate::{assets::Assets, surface::Sector, textures::Texture};
+
+use super::{
+    fog::PortalFog, plane::rasterise_plane_span, renderer::resolve_texture,
+    wall::rasterise_wall_span, RenderError, RendererState,
+};
+
+/// The part of the map that a span belongs to, from which its texture and lightmap are found when
+/// it is rasterised
+#[derive(Clone, Copy, Debug)]
+pub enum SpanSurface {
+    Wall {
+        sector: usize,
+        wall: usize,
+    },
+    /// Part of a portal wall above the sector beyond it
+    UpperWall {
+        sector: usize,
+        wall: usize,
+    },
+    /// Part of a portal wall below the sector beyond it
+    LowerWall {
+        sector: usize,
+        wall: usize,
+    },
+    Floor {
+        sector: usize,
+    },
+    Ceiling {
+        sector: usize,
+    },
+}
+
+/// A column of a wall, with its interpolants as they are at the top of the column
+#[derive(Clone, Copy, Debug)]
+pub struct WallSpan {
+    pub surface: SpanSurface,
+    pub x: usize,
+    pub y_min: usize,
+    pub y_max: usize,
+    pub inv_depth: f32,
+    /// U texture coordinate, divided by depth
+    pub u_depth: f32,
+    pub v: f32,
+    /// Change in V for each row
+    pub v_m: f32,
+    /// Brightness of the wall, by the angle it faces
+    pub lighting: f32,
+    pub fog: PortalFog,
+}
+
+/// A row of a floor or ceiling, which is all at the same depth
+#[derive(Clone, Copy, Debug)]
+pub struct PlaneSpan {
+    pub surface: SpanSurface,
+    pub y: usize,
+    pub x_min: usize,
+    pub x_max: usize,
+    pub depth: f32,
+    /// Ratio of the focal height to the row's distance from the horizon, which says how quickly
+    /// the texture coordinates change across the row
+    pub focal_height_ratio: f32,
+    pub fog: PortalFog,
+}
+
+#[derive(Clone, Copy, Debug)]
+pub enum SpanCommand {
+    Wall(WallSpan),
+    Plane(PlaneSpan),
+}
+
+/// Spans of the walls, floors and ceilings that were found to be visible, in the order they were
+/// found. Finding what is visible only fills this, and drawing it is left to a separate stage, so
+/// that the spans could be split between threads, sorted among sprites or drawn again for effects.
+#[derive(Debug, Default)]
+pub struct SpanBuffer {
+    commands: Vec<SpanCommand>,
+}
+
+impl SpanBuffer {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn clear(&mut self) {
+        self.commands.clear();
+    }
+
+    pub fn push(&mut self, command: SpanCommand) {
+        self.commands.push(command);
+    }
+
+    /// Draws every span, in the order they were pushed
+    pub fn rasterise(
+        &self,
+        state: &mut RendererState,
+        sectors: &[Sector],
+        textures: &Assets<Texture>,
+    ) -> Result<(), RenderError> {
+        for command in self.commands.iter() {
+            match command {
+                SpanCommand::Wall(span) => {
+                    let (wall, texture_index) = match span.surface {
+                        SpanSurface::Wall { sector, wall } => {
+                            let wall = &sectors[sector].walls[wall];
+                            (wall, wall.texture_data.index)
+                        }
+                        SpanSurface::UpperWall { sector, wall } => {
+                            let wall = &sectors[sector].walls[wall];
+                            let portal = wall.portal.expect("upper wall span without a portal");
+                            (wall, portal.upper_texture.index)
+                        }
+                        SpanSurface::LowerWall { sector, wall } => {
+                            let wall = &sectors[sector].walls[wall];
+                            let portal = wall.portal.expect("lower wall span without a portal");
+                            (wall, portal.lower_texture.index)
+                        }
+                        SpanSurface::Floor { .. } | SpanSurface::Ceiling { .. } => {
+                            unreachable!("wall span on a plane")
+                        }
+                    };
+                    let texture = resolve_texture(textures, texture_index)?;
+
+                    rasterise_wall_span(state, span, wall, texture);
+                }
+                SpanCommand::Plane(span) => {
+                    let plane = match span.surface {
+                        SpanSurface::Floor { sector } => &sectors[sector].floor,
+                        SpanSurface::Ceiling { sector } => &sectors[sector].ceiling,
+                        _ => unreachable!("plane span on a wall"),
+                    };
+                    let texture = resolve_texture(textures, plane.texture_data.index)?;
+
+                    rasterise_plane_span(state, span, plane, texture);
+                }
+            }
+        }
+
+        Ok(())
+    }
+}
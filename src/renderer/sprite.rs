@@ -1,16 +1,22 @@
 use maths::{geometry::Segment, linear::Vec2f};
 
-use crate::{consts::MIP_SCALES, surface::Sprite, textures::Texture};
+use crate::{colour::BlendMode, consts::MIP_SCALES, surface::Sprite, textures::Texture};
 
 use super::{
-    portal::PortalTree,
-    util::{diminish_lighting, mip_level, normalise_depth},
+    fog::{FogBlend, PortalFog},
+    portal::{PortalNode, PortalTree},
+    util::{
+        diminish_lighting, lighting_level, mip_level, span_coord, span_texel_index, texel_index,
+    },
     RendererState,
 };
 
 pub struct SpriteRenderer {
     clip_min: Vec<u16>,
     clip_max: Vec<u16>,
+    /// For each sector, the indices of the portal nodes through which it is seen this frame, so
+    /// that each sprite only checks the portals into its own sector
+    sector_portals: Vec<Vec<usize>>,
 }
 
 impl SpriteRenderer {
@@ -18,7 +24,11 @@ impl SpriteRenderer {
         let clip_min = vec![0; state.framebuffer.width()];
         let clip_max = vec![state.framebuffer.height() as u16; state.framebuffer.width()];
 
-        Self { clip_min, clip_max }
+        Self {
+            clip_min,
+            clip_max,
+            sector_portals: Vec::new(),
+        }
     }
 
     pub fn set_viewport(&mut self, state: &RendererState) {
@@ -29,31 +39,59 @@ impl SpriteRenderer {
 
     pub fn update(&mut self, state: &RendererState) {}
 
+    /// Groups the portal nodes by the sector they lead into. Must be called once the sectors have
+    /// been rendered, before any sprites are culled or drawn.
+    pub fn prepare(&mut self, portals: &PortalTree, sector_count: usize) {
+        // The inner lists are cleared rather than dropped, so their allocations are reused
+        self.sector_portals.resize_with(sector_count, Vec::new);
+        for sector_portals in self.sector_portals.iter_mut() {
+            sector_portals.clear();
+        }
+
+        for (index, portal) in portals.nodes.iter().enumerate() {
+            if let Some(sector_portals) = self.sector_portals.get_mut(portal.sector_index) {
+                sector_portals.push(index);
+            }
+        }
+    }
+
+    /// Returns the sprite's position in view space, unless it can't be seen because its sector
+    /// wasn't rendered or it lies outside of the view frustum
+    pub fn cull(&self, state: &RendererState, sprite: &Sprite) -> Option<Vec2f> {
+        if self
+            .sector_portals
+            .get(sprite.sector_index)
+            .is_none_or(|portals| portals.is_empty())
+        {
+            return None;
+        }
+
+        let vs = state.transform_view(sprite.position);
+        let (vs_a, vs_b) = billboard(vs, sprite.width);
+
+        Segment::new(vs_a, vs_b)
+            .overlaps_polygon(&state.frustum)
+            .then_some(vs)
+    }
+
+    /// Draws a sprite that has passed [Self::cull], given its position in view space
     pub fn draw_sprite(
         &mut self,
         state: &mut RendererState,
         portals: &PortalTree,
+        wall_inv_depths: &[f32],
         sprite: &Sprite,
+        vs: Vec2f,
         texture: &Texture,
     ) {
-        let vs = state.transform_view(sprite.position);
-
         let depth = vs.y;
-
-        // Reconstruct view space based on single transformed point, so that it always faces the camera
-        let vs_a = vs - Vec2f::new(sprite.width * 0.5, 0.0);
-        let vs_b = vs + Vec2f::new(sprite.width * 0.5, 0.0);
-
-        // Frustum culling
-        if !Segment::new(vs_a, vs_b).overlaps_polygon(&state.frustum) {
-            return;
-        }
+        let (vs_a, vs_b) = billboard(vs, sprite.width);
 
         // Perspective projection into screen space
         // We only need to project the top-left and bottom-right corners, as the sprite is always
         // parallel to the screen.
-        let top_left = state.project_screen_space(vs_a, sprite.height);
-        let bottom_right = state.project_screen_space(vs_b, 0.0);
+        let top_left = state.project_screen_space(vs_a, sprite.z + sprite.height);
+        let bottom_right = state.project_screen_space(vs_b, sprite.z);
 
         // Early out if outside of screen space
         // We only check Y here, as frustum culling should have already taken care of X
@@ -61,18 +99,55 @@ impl SpriteRenderer {
             return;
         }
 
+        // A sprite with no width or height can't be interpolated across
+        if state.is_degenerate_size(bottom_right.0.x - top_left.0.x)
+            || state.is_degenerate_size(bottom_right.0.y - top_left.0.y)
+            || state.is_degenerate(&[
+                top_left.0.x,
+                top_left.0.y,
+                bottom_right.0.x,
+                bottom_right.0.y,
+                top_left.1,
+            ])
+        {
+            return;
+        }
+
         // Clamp sprite coordinates to screen space bounds
         let sprite_x_min = (top_left.0.x as usize).clamp(0, state.framebuffer.width());
         let sprite_x_max = (bottom_right.0.x as usize).clamp(0, state.framebuffer.width());
 
+        // Columns that aren't covered by any of the portals below are left empty
+        if sprite_x_min < sprite_x_max {
+            self.clip_min[sprite_x_min..sprite_x_max].fill(0);
+            self.clip_max[sprite_x_min..sprite_x_max].fill(0);
+        }
+
+        // Nearest and furthest squared distances of the sprite, to compare with the portal depths
+        let distance = vs.magnitude();
+        let near_depth = (distance - sprite.width * 0.5).max(0.0).powi(2);
+        let far_depth = (distance + sprite.width * 0.5).powi(2);
+
         let mut portals_x_min = state.framebuffer.width();
         let mut portals_x_max = 0;
 
-        for portal in portals.nodes.iter() {
-            if portal.sector_index != sprite.sector_index {
+        // Where the sector is seen through several portals, the sprite is fogged as if through the
+        // nearest of them
+        let mut nearest_portal: Option<&PortalNode> = None;
+
+        for &portal_index in self.sector_portals[sprite.sector_index].iter() {
+            let portal = &portals.nodes[portal_index];
+
+            // The sector may be seen through several portals, but the sprite can only be seen
+            // through those that are nearer than it, and is hidden by the walls drawn behind them
+            if far_depth < portal.depth_min || near_depth > portal.depth_max {
                 continue;
             }
 
+            if nearest_portal.is_none_or(|nearest| portal.depth_min < nearest.depth_min) {
+                nearest_portal = Some(portal);
+            }
+
             let portal_bounds = unsafe { portals.get_bounds_unchecked(portal.tree_depth) };
 
             // X bounds overlap between the sprite and this portal
@@ -96,8 +171,22 @@ impl SpriteRenderer {
         let sprite_x_max = portals_x_max;
 
         // Early out if the sprite is completely obscured
-        if sprite_x_min >= sprite_x_max {
+        let Some(portal) = nearest_portal.filter(|_| sprite_x_min < sprite_x_max) else {
             return;
+        };
+
+        // Empty the columns where a wall nearer than the sprite has been drawn, such as a pillar
+        // that the sprite is standing partly behind
+        let inv_depth = top_left.1;
+        for ((clip_min, clip_max), wall_inv_depth) in self.clip_min[sprite_x_min..sprite_x_max]
+            .iter_mut()
+            .zip(self.clip_max[sprite_x_min..sprite_x_max].iter_mut())
+            .zip(wall_inv_depths[sprite_x_min..sprite_x_max].iter())
+        {
+            if *wall_inv_depth > inv_depth {
+                *clip_min = 0;
+                *clip_max = 0;
+            }
         }
 
         // TODO: Consider precalculating these values, but we must then make sure to update
@@ -117,11 +206,14 @@ impl SpriteRenderer {
             sprite_x_min as f32,
         );
 
+        state.stats.sprites_drawn += 1;
         self.rasterise_sprite(
             state,
             sprite_lerp,
             texture,
             depth,
+            portal.fog,
+            sprite.blend,
             sprite_x_min,
             sprite_x_max,
         );
@@ -133,15 +225,18 @@ impl SpriteRenderer {
         mut sprite: SpriteInterpolator,
         texture: &Texture,
         depth: f32,
+        fog: PortalFog,
+        blend: BlendMode,
         x_min: usize,
         x_max: usize,
     ) {
-        let normal_depth = normalise_depth(depth);
+        let normal_depth = state.normalise_depth(depth);
         let mip_level = mip_level(normal_depth, 0.0);
         let mip_scale = MIP_SCALES[mip_level];
 
-        let lighting =
-            unsafe { (diminish_lighting(normal_depth) * 255.0).to_int_unchecked::<u8>() };
+        let lighting = lighting_level(diminish_lighting(normal_depth), state.linear_lighting)
+            .saturating_add(state.camera_light_level(depth));
+        let fog = fog.at(depth);
 
         // Draw sprite, one column at a time
         for x in x_min..x_max {
@@ -158,6 +253,8 @@ impl SpriteRenderer {
                 mip_level,
                 mip_scale,
                 lighting,
+                &fog,
+                blend,
                 x,
                 y_min,
                 y_max,
@@ -175,37 +272,74 @@ impl SpriteRenderer {
         mip_level: usize,
         mip_scale: f32,
         lighting: u8,
+        fog: &FogBlend,
+        blend: BlendMode,
         x: usize,
         y_min: usize,
         y_max: usize,
     ) {
+        // Columns hidden by walls are clipped to nothing
+        if y_min >= y_max {
+            return;
+        }
+        state.stats.add_span(y_max - y_min);
+
         sprite.init_y(y_min);
 
         let width_mask = texture.levels[mip_level].width - 1;
         let height_mask = texture.levels[mip_level].height - 1;
 
-        let texture_x = unsafe { (sprite.u * mip_scale).to_int_unchecked::<usize>() } & width_mask;
+        let texture_x = unsafe { texel_index(sprite.u * mip_scale) } & width_mask;
+
+        // Scaling before stepping rather than after gives the same result, as mip scales are powers
+        // of two
+        let mut v = span_coord(sprite.v * mip_scale);
+        let v_m = span_coord(sprite.v_m * mip_scale);
 
         for y in y_min..y_max {
-            let texture_y =
-                unsafe { (sprite.v * mip_scale).to_int_unchecked::<usize>() } & height_mask;
+            let texture_y = unsafe { span_texel_index(v) } & height_mask;
 
             let colour = unsafe {
+                let emissive = texture.sample_emissive_unchecked(texture_x, texture_y, mip_level);
                 texture
                     .sample_unchecked(texture_x, texture_y, mip_level)
-                    .darken(lighting)
+                    .darken(lighting.max(emissive))
             };
 
-            if colour.a != 0 {
-                unsafe { state.framebuffer.set_pixel_unchecked(x, y, colour) };
+            match blend {
+                BlendMode::Alpha => {
+                    // Opaque texels are written directly, as blending would slightly darken them
+                    let colour = fog.apply(colour);
+                    match colour.a {
+                        0 => {}
+                        255 => unsafe { state.framebuffer.set_pixel_unchecked(x, y, colour) },
+                        _ => unsafe { state.framebuffer.blend_pixel_unchecked(x, y, colour) },
+                    }
+                }
+                _ => {
+                    let colour = fog.fade(colour);
+                    if colour.a != 0 {
+                        unsafe {
+                            state
+                                .framebuffer
+                                .composite_pixel_unchecked(x, y, colour, blend)
+                        };
+                    }
+                }
             }
-            // unsafe { state.framebuffer.blend_pixel_unchecked(x, y, colour)}
 
-            sprite.step_y();
+            v += v_m;
         }
     }
 }
 
+/// Reconstructs the view space endpoints of a sprite from its centre, so that it always faces the
+/// camera
+fn billboard(vs: Vec2f, width: f32) -> (Vec2f, Vec2f) {
+    let half_width = Vec2f::new(width * 0.5, 0.0);
+    (vs - half_width, vs + half_width)
+}
+
 struct SpriteInterpolator {
     top_y: f32,
     bottom_y: f32,
@@ -281,8 +415,4 @@ impl SpriteInterpolator {
     fn step_x(&mut self) {
         self.u += self.u_m;
     }
-
-    fn step_y(&mut self) {
-        self.v += self.v_m;
-    }
 }
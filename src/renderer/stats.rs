@@ -0,0 +1,90 @@
+/// Counts of what was drawn by the most recent call to [super::Renderer::update], for tuning maps
+/// and checking how well culling works
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct RenderStats {
+    /// Walls of the sectors that were visited, including those seen through several portals once
+    /// for each
+    pub walls: usize,
+    /// Walls that were rasterised, rather than culled
+    pub walls_drawn: usize,
+    /// Number of times a sector was drawn, through a portal or as the sector the camera is in
+    pub portal_nodes: usize,
+    /// Portals that were found but not drawn through, as the screen was already covered
+    pub portal_nodes_skipped: usize,
+    /// Portals that weren't drawn through, as they were closed in every column they covered
+    pub portals_pruned: usize,
+    /// Most portals that were seen through in a row
+    pub max_portal_depth: usize,
+    /// Horizontal or vertical lines of pixels rasterised, for walls, floors, ceilings and sprites
+    pub spans: usize,
+    /// Pixels covered by those spans, which may include transparent parts of sprites
+    pub pixels: usize,
+    /// Sprites that were passed to the renderer
+    pub sprites: usize,
+    /// Sprites that were rasterised, rather than culled or hidden behind walls
+    pub sprites_drawn: usize,
+}
+
+impl RenderStats {
+    pub fn walls_culled(&self) -> usize {
+        self.walls - self.walls_drawn
+    }
+
+    pub fn sprites_culled(&self) -> usize {
+        self.sprites - self.sprites_drawn
+    }
+
+    /// Counts a span of pixels that was rasterised
+    pub(super) fn add_span(&mut self, length: usize) {
+        self.spans += 1;
+        self.pixels += length;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use maths::linear::Vec2f;
+
+    use crate::{
+        camera::Camera,
+        demo,
+        enemy::{Enemy, EnemyKind},
+        golden_tests::{load_renderer, HEIGHT, POSES, WIDTH},
+        map,
+    };
+
+    #[test]
+    fn render_stats_test() {
+        let (assets, mut renderer) = load_renderer();
+        let sectors = demo::sectors();
+        let enemies = [
+            Enemy::new(EnemyKind::Goblin, Vec2f::new(140.0, 80.0), 2),
+            Enemy::new(EnemyKind::Goblin, Vec2f::new(80.0, 80.0), 2),
+            // Behind the camera
+            Enemy::new(EnemyKind::Goblin, Vec2f::new(147.0, 32.0), 3),
+        ];
+
+        let pose = &POSES[2];
+        let mut camera = Camera::new(pose.position, pose.z);
+        camera.set_rotation(pose.yaw, pose.pitch);
+
+        renderer
+            .update(
+                &camera,
+                map::find_sector(pose.position, &sectors).unwrap(),
+                &assets.textures,
+                &sectors,
+                enemies.iter().map(|enemy| &enemy.sprite),
+            )
+            .unwrap();
+
+        let stats = renderer.stats();
+        assert_eq!(stats.sprites, 3);
+        assert_eq!(stats.sprites_drawn, 2);
+        assert!(stats.walls_drawn > 0 && stats.walls_culled() > 0);
+        assert!(stats.portal_nodes > 1);
+        assert!(stats.max_portal_depth >= 1 && stats.max_portal_depth < stats.portal_nodes);
+        // Every pixel is covered by a wall, floor or ceiling at least once
+        assert!(stats.pixels >= WIDTH * HEIGHT);
+    }
+}
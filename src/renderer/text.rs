@@ -12,6 +12,8 @@ impl TextRenderer {
         Self {}
     }
 
+    /// Draws the text aligned to the given point, with each pixel of the font drawn as a square of
+    /// `scale` pixels
     pub fn render(
         &self,
         state: &mut RendererState,
@@ -20,23 +22,29 @@ impl TextRenderer {
         align: (AlignWidth, AlignHeight),
         x: usize,
         y: usize,
+        scale: usize,
         text: &str,
     ) {
         debug_assert!(text.is_ascii());
 
+        let scale = scale.max(1);
+        let char_width = font.char_width() * scale;
+        let char_height = font.char_height() * scale;
+
         let mut text_width = 0;
-        let mut text_height = font.char_height();
+        let mut text_height = char_height;
         let mut current_line_width = 0;
 
         for c in text.chars() {
             if c == '\n' {
-                text_height += font.char_height();
+                text_height += char_height;
                 text_width = text_width.max(current_line_width);
                 current_line_width = 0;
             } else {
-                current_line_width += font.char_width();
+                current_line_width += char_width;
             }
         }
+        text_width = text_width.max(current_line_width);
 
         let x = match align.0 {
             AlignWidth::Left => x,
@@ -55,7 +63,7 @@ impl TextRenderer {
             return;
         }
 
-        self.draw_text(state, font, colour, x, y, text);
+        self.draw_text(state, font, colour, (x, y), scale, text);
     }
 
     fn draw_text(
@@ -63,8 +71,8 @@ impl TextRenderer {
         state: &mut RendererState,
         font: &Font,
         colour: BGRA8,
-        x: usize,
-        y: usize,
+        (x, y): (usize, usize),
+        scale: usize,
         text: &str,
     ) {
         let mut offset_x = 0;
@@ -73,7 +81,7 @@ impl TextRenderer {
         for c in text.chars() {
             if c == '\n' {
                 offset_x = 0;
-                offset_y += font.char_height();
+                offset_y += font.char_height() * scale;
             } else {
                 // Exit early if rest of line(s) are offscreen
                 if y + offset_y >= state.framebuffer.height() {
@@ -82,12 +90,12 @@ impl TextRenderer {
 
                 // Skip drawing if character is offscreen
                 if x + offset_x >= state.framebuffer.width() {
-                    offset_x += font.char_width();
+                    offset_x += font.char_width() * scale;
                     continue;
                 }
 
-                self.draw_char(state, font, colour, c, x + offset_x, y + offset_y);
-                offset_x += font.char_width();
+                self.draw_char(state, font, colour, c, (x + offset_x, y + offset_y), scale);
+                offset_x += font.char_width() * scale;
             }
         }
     }
@@ -98,8 +106,8 @@ impl TextRenderer {
         font: &Font,
         colour: BGRA8,
         c: char,
-        x: usize,
-        y: usize,
+        (x, y): (usize, usize),
+        scale: usize,
     ) {
         // At this point, we know at least part of this character is onscreen
         debug_assert!(x < state.framebuffer.width() && y < state.framebuffer.height());
@@ -115,14 +123,17 @@ impl TextRenderer {
 
             if run_length.value() > 0 {
                 // Clamp line length to the framebuffer width
-                let len = run_length
-                    .len()
-                    .min(state.framebuffer.width().saturating_sub(x + offset_x));
-
-                unsafe {
-                    state
-                        .framebuffer
-                        .draw_h_line_unchecked(x + offset_x, y + offset_y, len, colour)
+                let line_x = (x + offset_x * scale).min(state.framebuffer.width());
+                let len = (run_length.len() * scale).min(state.framebuffer.width() - line_x);
+                let line_y = y + offset_y * scale;
+                let line_y_end = (line_y + scale).min(state.framebuffer.height());
+
+                for line_y in line_y..line_y_end {
+                    unsafe {
+                        state
+                            .framebuffer
+                            .draw_h_line_unchecked(line_x, line_y, len, colour)
+                    }
                 }
             }
 
@@ -133,7 +144,7 @@ impl TextRenderer {
             }
 
             // Exit early if we've reached the bottom of the framebuffer
-            if y + offset_y >= state.framebuffer.height() {
+            if y + offset_y * scale >= state.framebuffer.height() {
                 return;
             }
         }
@@ -1,17 +1,75 @@
+#[cfg(feature = "fixed-point")]
+use maths::fixed::Fixed;
 use maths::{geometry::Polygon, linear::Vec2f};
 
-use crate::consts::{FAR, MAP_DEPTH_RANGE, MIP_FACTOR, MIP_LEVELS, NEAR};
-
-/// Map a linear depth value, ranging from [NEAR] to [FAR], to a normalised depth value, ranging from 0.0 to 1.0.
-pub fn normalise_depth(depth: f32) -> f32 {
-    (depth - NEAR) * MAP_DEPTH_RANGE
-}
+use crate::{
+    colour::{linear_to_srgb, LINEAR_MAX},
+    consts::{MIP_FACTOR, MIP_LEVELS},
+    lightmap::Lightmap,
+};
 
 /// Calculates an appropriate mip level based on the normalised depth and a bias.
 pub fn mip_level(normal_depth: f32, bias: f32) -> usize {
     (((MIP_FACTOR + bias) * normal_depth) as usize).min(MIP_LEVELS - 1)
 }
 
+/// Converts a texture coordinate into a texel index, which is expected to be masked to the size of
+/// the texture so that it wraps. Coordinates are often negative, so they go through `isize`, as
+/// converting a negative float directly to `usize` is undefined behaviour.
+///
+/// # Safety
+/// The coordinate must be finite and within the range of `isize`.
+#[inline(always)]
+pub unsafe fn texel_index(coord: f32) -> usize {
+    debug_assert!(
+        coord.is_finite() && coord.abs() < isize::MAX as f32,
+        "texture coordinate out of range :: {coord}"
+    );
+    coord.to_int_unchecked::<isize>() as usize
+}
+
+/// Texture coordinate that is stepped along a span of pixels. With the `fixed-point` feature, this is
+/// 16.16 fixed point, stepped with integer adds. That wraps on overflow, which does no harm as the
+/// texel index is masked to the size of the texture anyway.
+#[cfg(not(feature = "fixed-point"))]
+pub type SpanCoord = f32;
+#[cfg(feature = "fixed-point")]
+pub type SpanCoord = Fixed;
+
+#[cfg(not(feature = "fixed-point"))]
+#[inline(always)]
+pub fn span_coord(coord: f32) -> SpanCoord {
+    coord
+}
+
+#[cfg(feature = "fixed-point")]
+#[inline(always)]
+pub fn span_coord(coord: f32) -> SpanCoord {
+    Fixed::from_f32_wrapping(coord)
+}
+
+/// Converts a texture coordinate that is stepped along a span into a texel index, to be masked to
+/// the size of the texture, as with [texel_index]
+///
+/// # Safety
+/// The coordinate must be finite and within the range of `isize`.
+#[cfg(not(feature = "fixed-point"))]
+#[inline(always)]
+pub unsafe fn span_texel_index(coord: SpanCoord) -> usize {
+    texel_index(coord)
+}
+
+/// Converts a texture coordinate that is stepped along a span into a texel index, to be masked to
+/// the size of the texture, as with [texel_index]
+///
+/// # Safety
+/// Always safe, as any fixed point value is in range, but matches the floating point version.
+#[cfg(feature = "fixed-point")]
+#[inline(always)]
+pub unsafe fn span_texel_index(coord: SpanCoord) -> usize {
+    coord.floor() as isize as usize
+}
+
 /// This is used during perspective projection to convert from camera space to screen space.
 /// It is essentially a scaling factor that is used to get a pixel coordinate from a
 /// coordinate in camera space, taking into account the field of view and screen size.
@@ -24,17 +82,46 @@ pub fn focal_dimensions(h_fov: f32, v_fov: f32, half_width: f32, half_height: f3
     (focal_width, focal_height)
 }
 
-/// Returns a polygon representing the view frustum, based on the given horizontal field of view.
-pub fn view_frustum(h_fov: f32) -> Polygon {
+/// Derives the vertical field of view (in degrees) from the horizontal field of view and the aspect
+/// ratio of the screen. The aspect ratio applies to the size of the projection plane rather than the
+/// angle, so simply dividing the angle by it becomes increasingly inaccurate at wider angles.
+pub fn vertical_fov(h_fov: f32, aspect_ratio: f32) -> f32 {
+    let half_tan = (h_fov * 0.5).to_radians().tan();
+    (half_tan / aspect_ratio).atan().to_degrees() * 2.0
+}
+
+/// Builds a lookup table that maps each column of the screen to the column of a rectilinear
+/// projection that should be sampled, such that each column covers an equal angle of the field of
+/// view (cylindrical projection). This removes the horizontal stretching that is otherwise visible
+/// towards the edges of the screen with a wide field of view.
+pub fn cylindrical_column_map(h_fov: f32, width: usize) -> Vec<usize> {
+    let half_width = width as f32 * 0.5;
+    let half_fov = (h_fov * 0.5).to_radians();
+    let focal_width = half_width / half_fov.tan();
+
+    (0..width)
+        .map(|x| {
+            // Angle of the centre of this column, relative to the view direction
+            let angle = ((x as f32 + 0.5 - half_width) / half_width) * half_fov;
+            let source_x = half_width + angle.tan() * focal_width;
+
+            (source_x as usize).min(width - 1)
+        })
+        .collect()
+}
+
+/// Returns a polygon representing the view frustum, based on the given horizontal field of view
+/// and the distances to the near and far planes.
+pub fn view_frustum(h_fov: f32, near: f32, far: f32) -> Polygon {
     let tan = (h_fov * 0.5).to_radians().tan();
-    let opp_far = FAR * tan;
-    let opp_near = NEAR * tan;
+    let opp_far = far * tan;
+    let opp_near = near * tan;
 
     Polygon::from_vertices(vec![
-        Vec2f::new(-opp_far, FAR),
-        Vec2f::new(opp_far, FAR),
-        Vec2f::new(opp_near, NEAR),
-        Vec2f::new(-opp_near, NEAR),
+        Vec2f::new(-opp_far, far),
+        Vec2f::new(opp_far, far),
+        Vec2f::new(opp_near, near),
+        Vec2f::new(-opp_near, near),
     ])
 }
 
@@ -43,3 +130,24 @@ pub fn diminish_lighting(normal_depth: f32) -> f32 {
     let l = 1.0 - normal_depth;
     ((l * l * l) * 1.5).min(1.0)
 }
+
+/// Converts a lighting intensity, between 0.0 and 1.0, into the factor that texture colours are
+/// darkened by. With linear lighting, the intensity is treated as an amount of light, so is encoded
+/// like the textures are. Scaling an sRGB colour by the encoded intensity closely matches scaling
+/// it in linear light, without converting every pixel.
+pub fn lighting_level(intensity: f32, linear: bool) -> u8 {
+    // Diminished lighting goes negative beyond the far plane, which parts of a wall can reach
+    let intensity = intensity.clamp(0.0, 1.0);
+
+    if linear {
+        linear_to_srgb(unsafe { (intensity * LINEAR_MAX as f32).to_int_unchecked::<u16>() })
+    } else {
+        unsafe { (intensity * 255.0).to_int_unchecked::<u8>() }
+    }
+}
+
+/// Scales a lighting level by a texel of a lightmap, where [Lightmap::NEUTRAL] leaves it as it is
+#[inline(always)]
+pub fn lightmap_level(lighting: u8, light: u8) -> u8 {
+    (lighting as u16 * light as u16 / Lightmap::NEUTRAL as u16).min(0xFF) as u8
+}
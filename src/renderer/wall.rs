@@ -1,14 +1,19 @@
 use maths::{geometry::Segment, linear::Vec2f};
 
 use crate::{
-    consts::{MIP_SCALES, NEAR},
+    consts::MIP_SCALES,
     surface::{Sector, Wall},
     textures::Texture,
 };
 
 use super::{
+    fog::PortalFog,
     portal::{PortalNode, PortalTree},
-    util::{diminish_lighting, mip_level, normalise_depth},
+    span::{SpanBuffer, SpanCommand, SpanSurface, WallSpan},
+    util::{
+        diminish_lighting, lighting_level, lightmap_level, mip_level, span_coord,
+        span_texel_index, texel_index,
+    },
     RendererState,
 };
 
@@ -17,6 +22,9 @@ pub struct WallRenderer {
     wall_bounds_min: Vec<u16>,
     /// For each X coordinate, defines the upper (exc.) Y coordinate of the walls that have been rendered.
     wall_bounds_max: Vec<u16>,
+    /// For each X coordinate, the inverse depth of the nearest solid wall that has been rendered, or
+    /// 0.0 where none has. Sprites use this to hide the columns that are behind a wall.
+    wall_inv_depths: Vec<f32>,
 }
 
 impl WallRenderer {
@@ -24,6 +32,7 @@ impl WallRenderer {
         Self {
             wall_bounds_min: vec![0; state.framebuffer.width()],
             wall_bounds_max: vec![state.framebuffer.height() as u16; state.framebuffer.width()],
+            wall_inv_depths: vec![0.0; state.framebuffer.width()],
         }
     }
 
@@ -31,51 +40,73 @@ impl WallRenderer {
         self.wall_bounds_min.resize(state.framebuffer.width(), 0);
         self.wall_bounds_max
             .resize(state.framebuffer.width(), state.framebuffer.height() as u16);
+        self.wall_inv_depths.resize(state.framebuffer.width(), 0.0);
     }
 
-    pub fn update(&mut self, state: &RendererState) {}
+    pub fn update(&mut self, state: &RendererState) {
+        self.wall_inv_depths.fill(0.0);
+    }
 
     pub fn get_wall_bounds(&self) -> (&[u16], &[u16]) {
         (&self.wall_bounds_min, &self.wall_bounds_max)
     }
 
+    /// Collapses the wall bounds over the X range to the middle of the portal bounds, so that the
+    /// ceiling and floor meet there in any column where no wall is drawn
+    pub fn reset_wall_bounds(
+        &mut self,
+        portal_bounds: (&[u16], &[u16]),
+        x_min: usize,
+        x_max: usize,
+    ) {
+        for x in x_min..x_max {
+            let middle = (portal_bounds.0[x] + portal_bounds.1[x]) / 2;
+            self.wall_bounds_min[x] = middle;
+            self.wall_bounds_max[x] = middle;
+        }
+    }
+
+    pub fn get_wall_inv_depths(&self) -> &[f32] {
+        &self.wall_inv_depths
+    }
+
+    /// Finds the visible parts of a wall of the portal's sector, and adds their spans to the buffer
     pub fn render(
         &mut self,
         state: &mut RendererState,
         portals: &mut PortalTree,
         sectors: &[Sector],
-        textures: &[Texture],
+        spans: &mut SpanBuffer,
         portal_index: usize,
-        wall: &Wall,
+        wall_index: usize,
     ) {
-        if let Some(wall_portal_data) = wall.portal {
-            let sector_index = portals.nodes[portal_index].sector_index;
-            let sector = &sectors[sector_index];
-            let next_sector = &sectors[wall_portal_data.sector];
+        state.stats.walls += 1;
+
+        let sector_index = portals.nodes[portal_index].sector_index;
+        let sector = &sectors[sector_index];
+        let wall = &sector.walls[wall_index];
 
-            let upper_texture = textures.get(wall_portal_data.upper_texture.index).unwrap();
-            let lower_texture = textures.get(wall_portal_data.lower_texture.index).unwrap();
+        if let Some(wall_portal_data) = wall.portal.filter(|portal| portal.visible) {
+            let next_sector = &sectors[wall_portal_data.sector];
 
             self.draw_portal_wall(
                 state,
                 portals,
+                spans,
                 portal_index,
                 sector,
                 next_sector,
                 wall,
-                upper_texture,
-                lower_texture,
+                wall_index,
             );
         } else {
             let portal = portals.nodes.get_mut(portal_index).unwrap();
-            let sector = &sectors[portal.sector_index];
-            let texture = textures.get(wall.texture_data.index).unwrap();
             let y_bounds = (
                 portals.portal_bounds_min[portal.tree_depth].as_slice(),
                 portals.portal_bounds_max[portal.tree_depth].as_slice(),
             );
 
-            self.draw_wall(state, portal, y_bounds, sector, wall, texture);
+            self.draw_wall(state, portal, spans, y_bounds, sector, wall, wall_index);
         }
     }
 
@@ -83,10 +114,11 @@ impl WallRenderer {
         &mut self,
         state: &mut RendererState,
         portal: &mut PortalNode,
+        spans: &mut SpanBuffer,
         y_bounds: (&[u16], &[u16]),
         sector: &Sector,
         wall: &Wall,
-        texture: &Texture,
+        wall_index: usize,
     ) {
         // Transform coordinates based on camera position and orientation
         let mut vs_a = state.transform_view(wall.segment.a);
@@ -107,16 +139,17 @@ impl WallRenderer {
         tex_b *= wall.texture_data.scale;
 
         // Near plane clipping
-        if vs_a.y < NEAR {
-            let t = (NEAR - vs_a.y) / (vs_b.y - vs_a.y);
+        let near = state.near();
+        if vs_a.y < near {
+            let t = (near - vs_a.y) / (vs_b.y - vs_a.y);
 
-            vs_a.y = NEAR;
+            vs_a.y = near;
             vs_a.x += (vs_b.x - vs_a.x) * t;
             tex_a.x += (tex_b.x - tex_a.x) * t;
-        } else if vs_b.y < NEAR {
-            let t = (NEAR - vs_b.y) / (vs_a.y - vs_b.y);
+        } else if vs_b.y < near {
+            let t = (near - vs_b.y) / (vs_a.y - vs_b.y);
 
-            vs_b.y = NEAR;
+            vs_b.y = near;
             vs_b.x += (vs_a.x - vs_b.x) * t;
             tex_b.x += (tex_a.x - tex_b.x) * t;
         }
@@ -145,6 +178,20 @@ impl WallRenderer {
         let x_max = (top_b.0.x as usize).clamp(portal.x_min, portal.x_max);
 
         let x_delta = top_b.0.x - top_a.0.x;
+        if state.is_degenerate_size(x_delta)
+            || state.is_degenerate(&[
+                top_a.0.y,
+                top_b.0.y,
+                bottom_a.0.y,
+                bottom_b.0.y,
+                inv_depth_a,
+                inv_depth_b,
+                tex_a.x,
+                tex_b.x,
+            ])
+        {
+            return;
+        }
         debug_assert!(x_delta > 0.0); // This should never be zero, as we cull back-facing walls
         let inv_x_delta = 1.0 / x_delta;
 
@@ -167,7 +214,7 @@ impl WallRenderer {
         let depth_gradient = (depth_b - depth_a) * inv_x_delta;
 
         let x_min_offset = x_min as f32 - top_a.0.x;
-        let x_max_offset = top_a.0.x - x_max as f32;
+        let x_max_offset = x_max as f32 - top_b.0.x;
 
         let depth_a = depth_a + depth_gradient * x_min_offset;
         let depth_b = depth_b + depth_gradient * x_max_offset;
@@ -181,19 +228,27 @@ impl WallRenderer {
         let light_intensity = 0.7;
         let lighting = (1.0 - light_intensity) + (light_angle * light_intensity);
 
-        self.rasterise_wall(state, wall_lerp, lighting, texture, y_bounds, x_min, x_max);
+        state.stats.walls_drawn += 1;
+        let fog = portal.fog;
+        let surface = SpanSurface::Wall {
+            sector: sector.id,
+            wall: wall_index,
+        };
+        self.push_wall_spans(
+            state, spans, surface, wall_lerp, lighting, fog, y_bounds, x_min, x_max,
+        );
     }
 
     pub fn draw_portal_wall(
         &mut self,
         state: &mut RendererState,
         portals: &mut PortalTree,
+        spans: &mut SpanBuffer,
         portal_index: usize,
         sector: &Sector,
         next_sector: &Sector,
         wall: &Wall,
-        upper_texture: &Texture,
-        lower_texture: &Texture,
+        wall_index: usize,
     ) {
         let portal = unsafe { portals.get_node_mut_unchecked(portal_index) };
 
@@ -206,19 +261,23 @@ impl WallRenderer {
             return;
         }
 
+        // Only what is seen through an opening in the wall counts, with the rest drawn as the
+        // upper and lower walls
+        let (next_floor, next_ceiling) = match wall.portal {
+            Some(portal) => portal.next_heights(next_sector),
+            None => (next_sector.floor.height, next_sector.ceiling.height),
+        };
+
         // TODO: Consider precalculating these values, but we must then make sure to update
         // texture coordinates for all walls in a sector when the sector's ceiling/floor height changes.
         let mut upper_tex_a = Vec2f::new(0.0, 0.0);
-        let mut upper_tex_b = Vec2f::new(
-            wall.width,
-            sector.ceiling.height - next_sector.ceiling.height,
-        );
+        let mut upper_tex_b = Vec2f::new(wall.width, sector.ceiling.height - next_ceiling);
         upper_tex_a += wall.texture_data.offset;
         upper_tex_b += wall.texture_data.offset;
         upper_tex_a *= wall.texture_data.scale;
         upper_tex_b *= wall.texture_data.scale;
 
-        let mut lower_tex_a = Vec2f::new(0.0, sector.ceiling.height - next_sector.floor.height);
+        let mut lower_tex_a = Vec2f::new(0.0, sector.ceiling.height - next_floor);
         let mut lower_tex_b = Vec2f::new(wall.width, sector.ceiling.height - sector.floor.height);
         lower_tex_a += wall.texture_data.offset;
         lower_tex_b += wall.texture_data.offset;
@@ -226,17 +285,18 @@ impl WallRenderer {
         lower_tex_b *= wall.texture_data.scale;
 
         // Near plane clipping
-        if vs_a.y < NEAR {
-            let t = (NEAR - vs_a.y) / (vs_b.y - vs_a.y);
+        let near = state.near();
+        if vs_a.y < near {
+            let t = (near - vs_a.y) / (vs_b.y - vs_a.y);
 
-            vs_a.y = NEAR;
+            vs_a.y = near;
             vs_a.x += (vs_b.x - vs_a.x) * t;
             upper_tex_a.x += (upper_tex_b.x - upper_tex_a.x) * t;
             lower_tex_a.x += (lower_tex_b.x - lower_tex_a.x) * t;
-        } else if vs_b.y < NEAR {
-            let t = (NEAR - vs_b.y) / (vs_a.y - vs_b.y);
+        } else if vs_b.y < near {
+            let t = (near - vs_b.y) / (vs_a.y - vs_b.y);
 
-            vs_b.y = NEAR;
+            vs_b.y = near;
             vs_b.x += (vs_a.x - vs_b.x) * t;
             upper_tex_b.x += (upper_tex_a.x - upper_tex_b.x) * t;
             lower_tex_b.x += (lower_tex_a.x - lower_tex_b.x) * t;
@@ -258,10 +318,10 @@ impl WallRenderer {
 
         let bottom_a = state.project_screen_space(vs_a, sector.floor.height);
         let bottom_b = state.project_screen_space(vs_b, sector.floor.height);
-        let next_top_a = state.project_screen_space(vs_a, next_sector.ceiling.height);
-        let next_top_b = state.project_screen_space(vs_b, next_sector.ceiling.height);
-        let next_bottom_a = state.project_screen_space(vs_a, next_sector.floor.height);
-        let next_bottom_b = state.project_screen_space(vs_b, next_sector.floor.height);
+        let next_top_a = state.project_screen_space(vs_a, next_ceiling);
+        let next_top_b = state.project_screen_space(vs_b, next_ceiling);
+        let next_bottom_a = state.project_screen_space(vs_a, next_floor);
+        let next_bottom_b = state.project_screen_space(vs_b, next_floor);
 
         let inv_depth_a = top_a.1;
         let inv_depth_b = top_b.1;
@@ -271,6 +331,27 @@ impl WallRenderer {
         let x_max = (top_b.0.x as usize).clamp(portal.x_min, portal.x_max);
 
         let x_delta = top_b.0.x - top_a.0.x;
+        // Checked before the portal is added, so that nothing is drawn through a skipped wall
+        if state.is_degenerate_size(x_delta)
+            || state.is_degenerate(&[
+                top_a.0.y,
+                top_b.0.y,
+                bottom_a.0.y,
+                bottom_b.0.y,
+                next_top_a.0.y,
+                next_top_b.0.y,
+                next_bottom_a.0.y,
+                next_bottom_b.0.y,
+                inv_depth_a,
+                inv_depth_b,
+                upper_tex_a.x,
+                upper_tex_b.x,
+                lower_tex_a.x,
+                lower_tex_b.x,
+            ])
+        {
+            return;
+        }
         debug_assert!(x_delta > 0.0); // This should never be zero, as we cull back-facing walls
         let inv_x_delta = 1.0 / x_delta;
 
@@ -305,25 +386,34 @@ impl WallRenderer {
         let depth_gradient = (depth_b - depth_a) * inv_x_delta;
 
         let x_min_offset = x_min as f32 - top_a.0.x;
-        let x_max_offset = top_a.0.x - x_max as f32;
+        let x_max_offset = x_max as f32 - top_b.0.x;
 
         let depth_a = depth_a + depth_gradient * x_min_offset;
         let depth_b = depth_b + depth_gradient * x_max_offset;
 
-        let min_depth = depth_a.min(depth_b);
         let max_depth = depth_a.max(depth_b);
         portal.depth_max = portal.depth_max.max(max_depth);
 
+        // The nearest point of the portal may lie between its ends, and the whole portal is used
+        // rather than just the visible part, so that this never overestimates
+        let min_depth = Segment::new(vs_a, vs_b).point_distance_sq(Vec2f::ZERO);
+
         let current_tree_depth = portal.tree_depth;
-        portals.push_node(PortalNode {
+        let fog = portal.fog;
+        let node = PortalNode {
             tree_depth: current_tree_depth + 1,
             sector_index: next_sector.id,
             x_min,
             x_max,
             depth_min: min_depth,
             depth_max: max_depth,
-        });
+            tint: wall.portal.and_then(|portal| portal.tint),
+            fog: fog.through(min_depth.sqrt(), state.sector_fog(next_sector)),
+        };
 
+        // The portal's bounds are written before deciding whether to add its node, so its layer
+        // must exist already
+        portals.reserve_depth(current_tree_depth + 1);
         let (read_y_bounds, write_y_bounds) =
             portals.get_many_bounds_mut_unchecked(current_tree_depth, current_tree_depth + 1);
 
@@ -335,31 +425,44 @@ impl WallRenderer {
         let light_intensity = 0.7;
         let lighting = (1.0 - light_intensity) + (light_angle * light_intensity);
 
-        self.rasterise_portal_wall(
+        state.stats.walls_drawn += 1;
+        let open = self.push_portal_wall_spans(
             state,
+            spans,
+            sector.id,
+            wall_index,
             upper_wall_lerp,
             lower_wall_lerp,
             lighting,
-            upper_texture,
-            lower_texture,
+            fog,
             read_y_bounds,
             write_y_bounds,
             x_min,
             x_max,
         );
+
+        // Nothing can be seen through a portal that is closed in every column, such as a shut
+        // door or one whose sector's ceiling is below its floor, so it isn't drawn through at all
+        if open {
+            portals.push_node(node);
+        } else {
+            state.stats.portals_pruned += 1;
+        }
     }
 
-    fn rasterise_wall(
+    fn push_wall_spans(
         &mut self,
-        state: &mut RendererState,
+        state: &RendererState,
+        spans: &mut SpanBuffer,
+        surface: SpanSurface,
         mut wall: WallInterpolator,
         lighting: f32,
-        texture: &Texture,
+        fog: PortalFog,
         y_bounds: (&[u16], &[u16]),
         x_min: usize,
         x_max: usize,
     ) {
-        // Draw wall, one column at a time
+        // Find the visible part of the wall in each column, one at a time
         for x in x_min..x_max {
             let min_portal_bound = y_bounds.0[x] as usize;
             let max_portal_bound = y_bounds.1[x] as usize;
@@ -375,29 +478,45 @@ impl WallRenderer {
             // Ensure that max >= min using min as boundary (for no particular reason)
             // let y_max = y_max.max(y_min);
 
-            self.rasterise_wall_span(state, &mut wall, lighting, texture, x, y_min, y_max);
+            push_wall_span(
+                state, spans, surface, &mut wall, lighting, fog, x, y_min, y_max,
+            );
 
             self.wall_bounds_min[x] = y_min as u16;
             self.wall_bounds_max[x] = y_max as u16;
+            self.wall_inv_depths[x] = self.wall_inv_depths[x].max(wall.inv_depth);
 
             wall.step_x();
         }
     }
 
-    fn rasterise_portal_wall(
+    /// Returns whether the next sector can be seen through any column of the portal
+    fn push_portal_wall_spans(
         &mut self,
-        state: &mut RendererState,
+        state: &RendererState,
+        spans: &mut SpanBuffer,
+        sector_index: usize,
+        wall_index: usize,
         mut upper_wall: WallInterpolator,
         mut lower_wall: WallInterpolator,
         lighting: f32,
-        upper_texture: &Texture,
-        lower_texture: &Texture,
+        fog: PortalFog,
         read_y_bounds: (&[u16], &[u16]),
         write_y_bounds: (&mut [u16], &mut [u16]),
         x_min: usize,
         x_max: usize,
-    ) {
-        // Draw wall, one column at a time
+    ) -> bool {
+        let upper_surface = SpanSurface::UpperWall {
+            sector: sector_index,
+            wall: wall_index,
+        };
+        let lower_surface = SpanSurface::LowerWall {
+            sector: sector_index,
+            wall: wall_index,
+        };
+        let mut open = false;
+
+        // Find the visible part of the wall in each column, one at a time
         for x in x_min..x_max {
             let min_portal_bound = read_y_bounds.0[x] as usize;
             let max_portal_bound = read_y_bounds.1[x] as usize;
@@ -418,20 +537,24 @@ impl WallRenderer {
             let lower_y_max = lower_y_max.max(upper_y_max);
             let lower_y_min = lower_y_min.max(upper_y_max);
 
-            self.rasterise_wall_span(
+            push_wall_span(
                 state,
+                spans,
+                upper_surface,
                 &mut upper_wall,
                 lighting,
-                upper_texture,
+                fog,
                 x,
                 upper_y_min,
                 upper_y_max,
             );
-            self.rasterise_wall_span(
+            push_wall_span(
                 state,
+                spans,
+                lower_surface,
                 &mut lower_wall,
                 lighting,
-                lower_texture,
+                fog,
                 x,
                 lower_y_min,
                 lower_y_max,
@@ -442,55 +565,115 @@ impl WallRenderer {
 
             write_y_bounds.0[x] = upper_y_max as u16;
             write_y_bounds.1[x] = lower_y_min as u16;
+            open |= upper_y_max < lower_y_min;
 
             upper_wall.step_x();
             lower_wall.step_x();
         }
-    }
-
-    fn rasterise_wall_span(
-        &mut self,
-        state: &mut RendererState,
-        wall: &mut WallInterpolator,
-        lighting: f32,
-        texture: &Texture,
-        x: usize,
-        y_min: usize,
-        y_max: usize,
-    ) {
-        wall.init_y(y_min);
-
-        let depth = 1.0 / wall.inv_depth;
-        let normal_depth = normalise_depth(depth);
-        // TODO: Bias mip level based on surface angle?
-        let mip_level = mip_level(normal_depth, 0.0);
-        let mip_scale = MIP_SCALES[mip_level];
-
-        let lighting = unsafe {
-            (diminish_lighting(normal_depth) * lighting * 255.0).to_int_unchecked::<u8>()
-        };
 
-        // Recover U texture coordinate after interpolating in depth space
-        let u = wall.u_depth * depth;
-
-        let width_mask = texture.levels[mip_level].width - 1;
-        let height_mask = texture.levels[mip_level].height - 1;
+        open
+    }
+}
 
-        let texture_x = unsafe { (u * mip_scale).to_int_unchecked::<usize>() } & width_mask;
+/// Adds a column of a wall to the span buffer, unless it is empty
+fn push_wall_span(
+    state: &RendererState,
+    spans: &mut SpanBuffer,
+    surface: SpanSurface,
+    wall: &mut WallInterpolator,
+    lighting: f32,
+    fog: PortalFog,
+    x: usize,
+    y_min: usize,
+    y_max: usize,
+) {
+    if y_min >= y_max || state.is_degenerate_size(wall.bottom_y - wall.top_y) {
+        return;
+    }
 
-        for y in y_min..y_max {
-            let texture_y =
-                unsafe { (wall.v * mip_scale).to_int_unchecked::<usize>() } & height_mask;
+    wall.init_y(y_min);
+
+    spans.push(SpanCommand::Wall(WallSpan {
+        surface,
+        x,
+        y_min,
+        y_max,
+        inv_depth: wall.inv_depth,
+        u_depth: wall.u_depth,
+        v: wall.v,
+        v_m: wall.v_m,
+        lighting,
+        fog,
+    }));
+}
 
-            unsafe {
-                let colour = texture
-                    .sample_unchecked(texture_x, texture_y, mip_level)
-                    .darken(lighting);
-                state.framebuffer.set_pixel_unchecked(x, y, colour);
-            }
+pub(super) fn rasterise_wall_span(
+    state: &mut RendererState,
+    span: &WallSpan,
+    surface: &Wall,
+    texture: &Texture,
+) {
+    let depth = 1.0 / span.inv_depth;
+    if state.is_degenerate(&[depth, span.u_depth * depth, span.v, span.v_m]) {
+        return;
+    }
 
-            wall.step_y();
+    let (x, y_min, y_max) = (span.x, span.y_min, span.y_max);
+    state.stats.add_span(y_max - y_min);
+
+    let normal_depth = state.normalise_depth(depth);
+    // TODO: Bias mip level based on surface angle?
+    let mip_level = mip_level(normal_depth, 0.0);
+    let mip_scale = MIP_SCALES[mip_level];
+
+    let lighting = lighting_level(
+        diminish_lighting(normal_depth) * span.lighting,
+        state.linear_lighting,
+    );
+    let camera_light = state.camera_light_level(depth);
+    let fog = span.fog.at(depth);
+
+    // Recover U texture coordinate after interpolating in depth space
+    let u = span.u_depth * depth;
+
+    let width_mask = texture.levels[mip_level].width - 1;
+    let height_mask = texture.levels[mip_level].height - 1;
+
+    let texture_x = unsafe { texel_index(u * mip_scale) } & width_mask;
+
+    // Lightmap coordinates are recovered from the texture coordinates before they are scaled,
+    // as walls are mapped the same way
+    let scale = surface.texture_data.scale;
+    let offset = surface.texture_data.offset;
+    let light_x = u / scale.x - offset.x;
+    let mut light_y = span.v / scale.y - offset.y;
+    let light_y_m = span.v_m / scale.y;
+
+    // Scaling before stepping rather than after gives the same result, as mip scales are powers
+    // of two
+    let mut v = span_coord(span.v * mip_scale);
+    let v_m = span_coord(span.v_m * mip_scale);
+
+    for y in y_min..y_max {
+        let texture_y = unsafe { span_texel_index(v) } & height_mask;
+
+        // The camera's light is added after the lightmap, as it isn't baked into it
+        let lit = match &surface.lightmap {
+            Some(lightmap) => lightmap_level(lighting, lightmap.sample(light_x, light_y)),
+            None => lighting,
         }
+        .saturating_add(camera_light);
+
+        unsafe {
+            let emissive = texture.sample_emissive_unchecked(texture_x, texture_y, mip_level);
+            let colour = texture
+                .sample_unchecked(texture_x, texture_y, mip_level)
+                .darken(lit.max(emissive));
+            state.framebuffer.set_pixel_unchecked(x, y, fog.apply(colour));
+        }
+
+        v += v_m;
+        light_y += light_y_m;
     }
 }
 
@@ -610,8 +793,53 @@ impl WallInterpolator {
         self.bottom_y += self.bottom_y_m;
         self.u_depth += self.u_depth_m;
     }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::f32::consts::PI;
+
+    use super::*;
+    use crate::{
+        camera::Camera,
+        demo,
+        golden_tests::{load_renderer, HEIGHT, POSES, WIDTH},
+        map,
+    };
+
+    #[test]
+    fn closed_portals_test() {
+        let (assets, mut renderer) = load_renderer();
+
+        let mut render = |sectors: &[Sector], position: Vec2f, yaw: f32, pitch: f32| {
+            let sector_index = map::find_sector(position, sectors).unwrap();
+            let mut camera = Camera::new(position, sectors[sector_index].floor.height + 15.0);
+            camera.set_rotation(yaw, pitch);
+            renderer
+                .update(&camera, sector_index, &assets.textures, sectors, [])
+                .unwrap();
+            *renderer.stats()
+        };
+
+        let corridor = &POSES[0];
+        let open = render(&demo::sectors(), corridor.position, corridor.yaw, 0.0);
+        assert_eq!(open.portals_pruned, 0);
+
+        // Sector 1 is seen down the corridor, shut like a door and then with its ceiling below its
+        // floor, neither of which leaves anything to see through the portal into it
+        for ceiling_offset in [0.0, -10.0] {
+            let mut sectors = demo::sectors();
+            sectors[1].ceiling.height = sectors[1].floor.height + ceiling_offset;
+
+            let stats = render(&sectors, corridor.position, corridor.yaw, 0.0);
+            assert!(stats.portals_pruned > 0);
+            assert!(stats.portal_nodes < open.portal_nodes);
+            assert!(stats.pixels >= WIDTH * HEIGHT);
+        }
 
-    fn step_y(&mut self) {
-        self.v += self.v_m;
+        // A portal seen edge on at the side of the screen covers no columns at all, which once left
+        // a node that the floor and ceiling were drawn through from beyond the edge of the screen
+        let stats = render(&demo::sectors(), Vec2f::new(112.0, 162.0), PI, 0.1);
+        assert!(stats.portals_pruned > 0);
     }
 }
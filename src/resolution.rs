@@ -0,0 +1,104 @@
+//! Adaptive resolution, which lowers the render scale whilst drawing the world takes longer than
+//! a budget allows, and raises it again once there is time to spare. As every pixel is drawn in
+//! software, drawing fewer of them is the surest way to keep the frame rate up on weak hardware.
+
+use crate::consts::{
+    ADAPTIVE_COOLDOWN_FRAMES, ADAPTIVE_HEADROOM, ADAPTIVE_SMOOTHING, ADAPTIVE_STEP,
+    MIN_RENDER_SCALE,
+};
+
+#[derive(Debug, Clone)]
+pub struct AdaptiveResolution {
+    /// Longest that drawing the world should take, in seconds
+    budget: f32,
+    /// Scale that is returned to when there is time to spare, which is the one that was chosen
+    max_scale: f32,
+    scale: f32,
+    /// Time that recent frames took to draw, smoothed so that a single slow frame doesn't count
+    /// for much, in seconds
+    average: f32,
+    /// Frames left before the scale may change again, so that the effect of each change is seen
+    /// before deciding on the next
+    cooldown: usize,
+}
+
+impl AdaptiveResolution {
+    pub fn new(budget: f32, max_scale: f32) -> Self {
+        Self {
+            budget,
+            max_scale,
+            scale: max_scale,
+            average: 0.0,
+            cooldown: ADAPTIVE_COOLDOWN_FRAMES,
+        }
+    }
+
+    pub fn max_scale(&self) -> f32 {
+        self.max_scale
+    }
+
+    /// Changes the scale that is returned to, and starts again from it
+    pub fn set_max_scale(&mut self, max_scale: f32) {
+        *self = Self::new(self.budget, max_scale);
+    }
+
+    /// Records how long the world took to draw this frame, in seconds, and returns the scale it
+    /// should be drawn at from now on if that has changed.
+    ///
+    /// The scale is lowered a step at a time whilst over budget. It is only raised again once
+    /// frames take well under the budget, as drawing at the higher scale costs more than at the
+    /// current one, and raising it as soon as there is any time to spare would flip back and forth.
+    pub fn record(&mut self, seconds: f32) -> Option<f32> {
+        self.average += (seconds - self.average) * ADAPTIVE_SMOOTHING;
+
+        if self.cooldown > 0 {
+            self.cooldown -= 1;
+            return None;
+        }
+
+        let scale = if self.average > self.budget {
+            (self.scale - ADAPTIVE_STEP).max(MIN_RENDER_SCALE)
+        } else if self.average < self.budget * ADAPTIVE_HEADROOM {
+            (self.scale + ADAPTIVE_STEP).min(self.max_scale)
+        } else {
+            self.scale
+        };
+
+        if scale == self.scale {
+            return None;
+        }
+
+        self.scale = scale;
+        self.cooldown = ADAPTIVE_COOLDOWN_FRAMES;
+        Some(scale)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn adaptive_resolution_test() {
+        let mut adaptive = AdaptiveResolution::new(0.005, 1.0);
+        let mut run = |seconds: f32, frames: usize| {
+            (0..frames)
+                .filter_map(|_| adaptive.record(seconds))
+                .collect::<Vec<_>>()
+        };
+
+        // Slow frames lower the scale a step at a time, waiting to see the effect of each, down to
+        // the lowest it can go
+        let lowered = run(0.01, 1000);
+        assert!(lowered.windows(2).all(|pair| pair[1] < pair[0]));
+        assert!(lowered.len() > 1 && lowered.len() < 10);
+        assert_eq!(lowered.last(), Some(&MIN_RENDER_SCALE));
+
+        // Frames just within budget leave it be, and only well within it raise it back up to the
+        // top
+        assert!(run(0.004, 1000).is_empty());
+        let raised = run(0.001, 1000);
+        assert!(raised.windows(2).all(|pair| pair[1] > pair[0]));
+        assert_eq!(raised.last(), Some(&1.0));
+    }
+}
@@ -0,0 +1,169 @@
+//! Recording of the most recent frames, for debugging. Recording can be paused to step back
+//! through the frames and see how something went wrong, such as the player falling through a
+//! portal. Only what is needed to look at a frame is recorded, so it can't be played on from.
+
+use collections::RingBuffer;
+use maths::{geometry::Circle, linear::Vec2f};
+
+use crate::{
+    camera::Camera,
+    consts::REWIND_FRAMES,
+    player::Player,
+    surface::{Sector, Sprite},
+};
+
+/// Where the player was and how they were moving during a frame
+#[derive(Clone, Debug)]
+struct PlayerState {
+    camera: Camera,
+    sector_index: usize,
+    prev_position: Vec2f,
+    velocity: Vec2f,
+    head_z: f32,
+    knee_z: f32,
+}
+
+impl PlayerState {
+    fn capture(player: &Player) -> Self {
+        Self {
+            camera: player.camera.clone(),
+            sector_index: player.sector_index,
+            prev_position: player.prev_position,
+            velocity: player.velocity,
+            head_z: player.head_z,
+            knee_z: player.knee_z,
+        }
+    }
+
+    fn apply(&self, player: &mut Player) {
+        player.camera = self.camera.clone();
+        player.sector_index = self.sector_index;
+        player.collider = Circle::new(self.camera.position, player.collider.radius);
+        player.prev_position = self.prev_position;
+        player.velocity = self.velocity;
+        player.head_z = self.head_z;
+        player.knee_z = self.knee_z;
+    }
+}
+
+/// Everything that moves during a frame, as it was at the end of it
+#[derive(Debug)]
+pub struct Snapshot {
+    /// Seconds since the game started
+    pub time: f32,
+    player: PlayerState,
+    /// Sprites of everything that moves on its own, such as enemies and projectiles
+    pub sprites: Vec<Sprite>,
+    /// Floor and ceiling heights of each sector, which doors and lifts move
+    heights: Vec<(f32, f32)>,
+}
+
+impl Snapshot {
+    /// Puts the player and sectors back as they were. Sprites are left to be drawn from the
+    /// snapshot instead, as the things they belong to may since have come or gone.
+    fn apply(&self, player: &mut Player, sectors: &mut [Sector]) {
+        self.player.apply(player);
+
+        for (sector, (floor, ceiling)) in sectors.iter_mut().zip(self.heights.iter()) {
+            sector.floor.height = *floor;
+            sector.ceiling.height = *ceiling;
+        }
+    }
+}
+
+/// The most recently recorded frames, which can be paused on to look back through them
+#[derive(Debug, Default)]
+pub struct Rewind {
+    // Boxed as the frames are stored inline, and there are a lot of them
+    frames: Box<RingBuffer<Snapshot, REWIND_FRAMES>>,
+    /// How many frames back from the newest is being looked at, whilst paused
+    cursor: Option<usize>,
+}
+
+impl Rewind {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn is_paused(&self) -> bool {
+        self.cursor.is_some()
+    }
+
+    /// How many frames back from the newest is being looked at, and how many there are
+    pub fn position(&self) -> Option<(usize, usize)> {
+        Some((self.cursor?, self.frames.len()))
+    }
+
+    /// The frame being looked at, whilst paused
+    pub fn current(&self) -> Option<&Snapshot> {
+        let index = self.frames.len().checked_sub(self.cursor? + 1)?;
+        self.frames.get(index)
+    }
+
+    /// Records the end of a frame, which once full replaces the oldest. Its buffers are reused,
+    /// so that recording doesn't allocate once they have grown to fit.
+    pub fn record<'a>(
+        &mut self,
+        time: f32,
+        player: &Player,
+        sprites: impl Iterator<Item = &'a Sprite>,
+        sectors: &[Sector],
+    ) {
+        let player = PlayerState::capture(player);
+
+        let mut snapshot = if self.frames.is_full() {
+            self.frames.pop_front().unwrap()
+        } else {
+            Snapshot {
+                time,
+                player: player.clone(),
+                sprites: Vec::new(),
+                heights: Vec::with_capacity(sectors.len()),
+            }
+        };
+
+        snapshot.time = time;
+        snapshot.player = player;
+        snapshot.sprites.clear();
+        snapshot.sprites.extend(sprites.cloned());
+        snapshot.heights.clear();
+        snapshot.heights.extend(
+            sectors
+                .iter()
+                .map(|sector| (sector.floor.height, sector.ceiling.height)),
+        );
+
+        self.frames.push(snapshot);
+    }
+
+    /// Stops recording and looks at the newest frame, returning whether there were any frames
+    /// to look at
+    pub fn pause(&mut self) -> bool {
+        if self.frames.is_empty() {
+            return false;
+        }
+
+        self.cursor = Some(0);
+        true
+    }
+
+    /// Puts the player and sectors back as they were in the newest frame, which is where the
+    /// game carries on from, and starts recording again
+    pub fn resume(&mut self, player: &mut Player, sectors: &mut [Sector]) {
+        self.seek(0, player, sectors);
+        self.cursor = None;
+    }
+
+    /// Looks the given number of frames back from the newest, stopping at the oldest, and puts the
+    /// player and sectors as they were then
+    pub fn seek(&mut self, frames: usize, player: &mut Player, sectors: &mut [Sector]) {
+        if self.cursor.is_none() {
+            return;
+        }
+
+        self.cursor = Some(frames.min(self.frames.len().saturating_sub(1)));
+        if let Some(snapshot) = self.current() {
+            snapshot.apply(player, sectors);
+        }
+    }
+}
@@ -0,0 +1,322 @@
+//! Per-level logic written in Rhai, so that maps can have ambushes and puzzles without rebuilding
+//! the game. A script reacts to gameplay by defining any of these functions:
+//!
+//! - `on_start()`, once the level has loaded
+//! - `on_enter(sector)`, when the player moves into a sector
+//! - `on_switch(trigger, on)`, when a switch is turned on or off
+//! - `on_trigger_enter(trigger, who)` and `on_trigger_exit(trigger, who)`, when the `"player"` or
+//!   an `"enemy"` moves into or out of a trigger volume
+//! - `on_pickup(item)`, with the name of an item the player picked up
+//! - `on_death()`, when the player dies
+//!
+//! Functions share state through `this`, which starts out as an empty object map. They act on the
+//! game through the functions registered in [Script::new], which queue up [ScriptCommand]s to be
+//! carried out once the script returns.
+
+use std::{cell::RefCell, error::Error, fmt, fs, io, path::Path, rc::Rc};
+
+use maths::linear::Vec2f;
+use rhai::{CallFnOptions, Dynamic, Engine, EvalAltResult, FuncArgs, ParseError, Scope, AST};
+
+use crate::{
+    events::GameEvent,
+    profile,
+    thing::{SpawnFlags, Thing, ThingType},
+};
+
+/// Limit on the work done by a single call into a script, so that an endless loop can't freeze
+/// the game
+const MAX_OPERATIONS: u64 = 100_000;
+
+/// Something that a script asked the game to do
+#[derive(Debug, Clone, PartialEq)]
+pub enum ScriptCommand {
+    /// Shows a message on the HUD
+    Message(String),
+    /// Opens the door in a sector, ignoring its lock
+    OpenDoor(usize),
+    SetFloor {
+        sector: usize,
+        height: f32,
+    },
+    SetCeiling {
+        sector: usize,
+        height: f32,
+    },
+    /// Raises the lift in a sector
+    RaiseLift(usize),
+    /// Spawns a thing, as if it had been placed in the map
+    Spawn(Thing),
+    /// Makes a loud noise, which wakes enemies up to `range` portals away
+    Noise {
+        sector: usize,
+        range: usize,
+    },
+    /// Acts as if a switch with this trigger was switched on
+    Trigger(usize),
+}
+
+#[derive(Debug)]
+pub enum ScriptError {
+    Io(io::Error),
+    Parse(ParseError),
+    /// The script failed whilst running a function, or its top level statements
+    Runtime {
+        function: String,
+        error: Box<EvalAltResult>,
+    },
+}
+
+impl fmt::Display for ScriptError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ScriptError::Io(error) => write!(f, "Failed to read script: {error}"),
+            ScriptError::Parse(error) => write!(f, "Failed to parse script: {error}"),
+            ScriptError::Runtime { function, error } => {
+                write!(f, "Script failed in '{function}': {error}")
+            }
+        }
+    }
+}
+
+impl Error for ScriptError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            ScriptError::Io(error) => Some(error),
+            ScriptError::Parse(error) => Some(error),
+            ScriptError::Runtime { error, .. } => Some(error.as_ref()),
+        }
+    }
+}
+
+impl From<io::Error> for ScriptError {
+    fn from(error: io::Error) -> Self {
+        ScriptError::Io(error)
+    }
+}
+
+impl From<ParseError> for ScriptError {
+    fn from(error: ParseError) -> Self {
+        ScriptError::Parse(error)
+    }
+}
+
+/// A function that a script asked to be called after a delay
+struct ScriptTimer {
+    remaining: f32,
+    function: String,
+}
+
+pub struct Script {
+    engine: Engine,
+    ast: AST,
+    scope: Scope<'static>,
+    /// Bound to `this` whilst calling the script's functions
+    state: Dynamic,
+    commands: Rc<RefCell<Vec<ScriptCommand>>>,
+    timers: Rc<RefCell<Vec<ScriptTimer>>>,
+}
+
+impl Script {
+    /// Creates a script that does nothing, for levels without one
+    pub fn new() -> Self {
+        let commands = Rc::new(RefCell::new(Vec::new()));
+        let timers = Rc::new(RefCell::new(Vec::new()));
+
+        let mut engine = Engine::new();
+        engine.set_max_operations(MAX_OPERATIONS);
+
+        // Indices from scripts may be negative, which wraps to a value that is rejected later on
+        // as being out of range
+        let queue = commands.clone();
+        engine.register_fn("message", move |text: &str| {
+            queue
+                .borrow_mut()
+                .push(ScriptCommand::Message(text.to_string()));
+        });
+        let queue = commands.clone();
+        engine.register_fn("open_door", move |sector: i64| {
+            queue
+                .borrow_mut()
+                .push(ScriptCommand::OpenDoor(sector as usize));
+        });
+        let queue = commands.clone();
+        engine.register_fn("raise_lift", move |sector: i64| {
+            queue
+                .borrow_mut()
+                .push(ScriptCommand::RaiseLift(sector as usize));
+        });
+        let queue = commands.clone();
+        engine.register_fn("set_floor", move |sector: i64, height: f64| {
+            queue.borrow_mut().push(ScriptCommand::SetFloor {
+                sector: sector as usize,
+                height: height as f32,
+            });
+        });
+        let queue = commands.clone();
+        engine.register_fn("set_ceiling", move |sector: i64, height: f64| {
+            queue.borrow_mut().push(ScriptCommand::SetCeiling {
+                sector: sector as usize,
+                height: height as f32,
+            });
+        });
+        let queue = commands.clone();
+        engine.register_fn(
+            "spawn_thing",
+            move |kind: i64, x: f64, y: f64, sector: i64| {
+                queue
+                    .borrow_mut()
+                    .push(ScriptCommand::Spawn(script_thing(kind, x, y, sector)));
+            },
+        );
+        let queue = commands.clone();
+        engine.register_fn(
+            "spawn_awake",
+            move |kind: i64, x: f64, y: f64, sector: i64| {
+                let thing = script_thing(kind, x, y, sector).with_flags(SpawnFlags::AWAKE);
+                queue.borrow_mut().push(ScriptCommand::Spawn(thing));
+            },
+        );
+        let queue = commands.clone();
+        engine.register_fn("noise", move |sector: i64, range: i64| {
+            queue.borrow_mut().push(ScriptCommand::Noise {
+                sector: sector as usize,
+                range: range.max(0) as usize,
+            });
+        });
+        let queue = commands.clone();
+        engine.register_fn("trigger", move |trigger: i64| {
+            queue
+                .borrow_mut()
+                .push(ScriptCommand::Trigger(trigger as usize));
+        });
+        let queue = timers.clone();
+        engine.register_fn("after", move |seconds: f64, function: &str| {
+            queue.borrow_mut().push(ScriptTimer {
+                remaining: seconds as f32,
+                function: function.to_string(),
+            });
+        });
+
+        // Types of things are referred to by name, e.g. `spawn_thing(GOBLIN, x, y, sector)`
+        let mut scope = Scope::new();
+        for (name, kind) in ThingType::NAMED {
+            scope.push_constant(name, kind.0 as i64);
+        }
+
+        Self {
+            engine,
+            ast: AST::empty(),
+            scope,
+            state: Dynamic::from_map(rhai::Map::new()),
+            commands,
+            timers,
+        }
+    }
+
+    /// Compiles a script and runs its top level statements, which may declare constants for its
+    /// functions to use
+    pub fn from_source(source: &str) -> Result<Self, ScriptError> {
+        let mut script = Self::new();
+        script.ast = script.engine.compile(source)?;
+        script
+            .engine
+            .run_ast_with_scope(&mut script.scope, &script.ast)
+            .map_err(|error| ScriptError::Runtime {
+                function: String::from("<top level>"),
+                error,
+            })?;
+
+        Ok(script)
+    }
+
+    pub fn from_path(path: impl AsRef<Path>) -> Result<Self, ScriptError> {
+        Self::from_source(&fs::read_to_string(path)?)
+    }
+
+    /// Calls one of the script's functions, if it defines it with the right number of parameters
+    pub fn call(&mut self, function: &str, args: impl FuncArgs) -> Result<(), ScriptError> {
+        let mut arg_values = Vec::new();
+        args.parse(&mut arg_values);
+
+        let defined = self
+            .ast
+            .iter_functions()
+            .any(|f| f.name == function && f.params.len() == arg_values.len());
+        if !defined {
+            return Ok(());
+        }
+
+        let options = CallFnOptions::new()
+            .eval_ast(false)
+            .bind_this_ptr(&mut self.state);
+        self.engine
+            .call_fn_with_options::<Dynamic>(
+                options,
+                &mut self.scope,
+                &self.ast,
+                function,
+                arg_values,
+            )
+            .map(|_| ())
+            .map_err(|error| ScriptError::Runtime {
+                function: function.to_string(),
+                error,
+            })
+    }
+
+    /// Calls the script's function for a gameplay event, if it has one
+    pub fn handle_event(&mut self, event: &GameEvent) -> Result<(), ScriptError> {
+        match *event {
+            GameEvent::SectorEntered { sector_index } => {
+                self.call("on_enter", (sector_index as i64,))
+            }
+            GameEvent::SwitchToggled { trigger, on } => {
+                self.call("on_switch", (trigger as i64, on))
+            }
+            GameEvent::TriggerEntered { trigger, occupant } => {
+                self.call("on_trigger_enter", (trigger as i64, occupant.name()))
+            }
+            GameEvent::TriggerExited { trigger, occupant } => {
+                self.call("on_trigger_exit", (trigger as i64, occupant.name()))
+            }
+            GameEvent::ItemPickedUp { kind } => self.call("on_pickup", (kind.name(),)),
+            GameEvent::PlayerDied => self.call("on_death", ()),
+            _ => Ok(()),
+        }
+    }
+
+    /// Counts down the delays set with `after`, calling each function once its delay is over
+    pub fn update(&mut self, delta_seconds: f32) -> Result<(), ScriptError> {
+        profile::scope!("script", "update");
+        let mut due = Vec::new();
+        self.timers.borrow_mut().retain_mut(|timer| {
+            timer.remaining -= delta_seconds;
+            if timer.remaining > 0.0 {
+                return true;
+            }
+
+            due.push(std::mem::take(&mut timer.function));
+            false
+        });
+
+        for function in due {
+            self.call(&function, ())?;
+        }
+
+        Ok(())
+    }
+
+    /// Removes the commands queued up by the script, in the order they were given
+    pub fn take_commands(&mut self) -> Vec<ScriptCommand> {
+        std::mem::take(&mut self.commands.borrow_mut())
+    }
+}
+
+fn script_thing(kind: i64, x: f64, y: f64, sector: i64) -> Thing {
+    Thing::new(
+        ThingType(kind as u16),
+        Vec2f::new(x as f32, y as f32),
+        sector as usize,
+    )
+}
@@ -0,0 +1,209 @@
+//! Settings read from a plain text file, so that the game can be tuned without rebuilding it. Each
+//! line holds a `key = value` pair, and blank lines and lines starting with `#` are skipped.
+
+use std::{
+    cell::RefCell,
+    collections::{HashMap, HashSet},
+    error::Error,
+    fmt, fs, io,
+    path::Path,
+    str::FromStr,
+};
+
+#[derive(Debug)]
+pub enum SettingsError {
+    Io(io::Error),
+    /// A line that isn't a `key = value` pair, numbered from 1
+    Syntax {
+        line: usize,
+    },
+    /// A value that couldn't be read as the type its setting needs, or is outside of its range
+    Value {
+        key: String,
+        value: String,
+    },
+    /// A key that no setting goes by, which is most likely misspelt
+    Unknown {
+        key: String,
+    },
+}
+
+impl fmt::Display for SettingsError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SettingsError::Io(error) => write!(f, "Failed to read settings: {error}"),
+            SettingsError::Syntax { line } => {
+                write!(f, "Settings line {line} isn't of the form 'key = value'")
+            }
+            SettingsError::Value { key, value } => {
+                write!(f, "Setting '{key}' has an invalid value '{value}'")
+            }
+            SettingsError::Unknown { key } => write!(f, "There is no setting '{key}'"),
+        }
+    }
+}
+
+impl Error for SettingsError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            SettingsError::Io(error) => Some(error),
+            _ => None,
+        }
+    }
+}
+
+impl From<io::Error> for SettingsError {
+    fn from(error: io::Error) -> Self {
+        SettingsError::Io(error)
+    }
+}
+
+/// Values of the settings, by key. Keys that appear more than once take their last value.
+///
+/// Settings with invalid values are read as if they weren't set, so that one mistake doesn't stop
+/// the rest from being read, and are reported along with unknown keys by [Settings::problems].
+#[derive(Debug, Default)]
+pub struct Settings {
+    values: HashMap<String, String>,
+    /// Keys that have been read, so that those which never are can be reported as unknown
+    read: RefCell<HashSet<String>>,
+    /// Values that have been read but were invalid
+    invalid: RefCell<Vec<SettingsError>>,
+}
+
+impl Settings {
+    pub fn from_source(source: &str) -> Result<Self, SettingsError> {
+        let mut values = HashMap::new();
+
+        for (index, line) in source.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+
+            let Some((key, value)) = line.split_once('=') else {
+                return Err(SettingsError::Syntax { line: index + 1 });
+            };
+            let key = key.trim();
+            if key.is_empty() {
+                return Err(SettingsError::Syntax { line: index + 1 });
+            }
+
+            values.insert(key.to_owned(), value.trim().to_owned());
+        }
+
+        Ok(Self {
+            values,
+            ..Self::default()
+        })
+    }
+
+    pub fn from_path(path: impl AsRef<Path>) -> Result<Self, SettingsError> {
+        Self::from_source(&fs::read_to_string(path)?)
+    }
+
+    /// Reads the value of a setting as the given type, or nothing if it isn't set or is invalid
+    pub fn get<T: FromStr>(&self, key: &str) -> Option<T> {
+        self.get_valid(key, |_| true)
+    }
+
+    /// Reads the value of a setting as the given type, or nothing if it isn't set or is invalid,
+    /// which includes values that `is_valid` rejects
+    pub fn get_valid<T: FromStr>(&self, key: &str, is_valid: impl FnOnce(&T) -> bool) -> Option<T> {
+        self.read.borrow_mut().insert(key.to_owned());
+        let value = self.values.get(key)?;
+
+        match value.parse().ok().filter(is_valid) {
+            Some(parsed) => Some(parsed),
+            None => {
+                self.invalid.borrow_mut().push(SettingsError::Value {
+                    key: key.to_owned(),
+                    value: value.clone(),
+                });
+                None
+            }
+        }
+    }
+
+    /// Returns the settings that were read with invalid values, followed by those that were never
+    /// read at all, so should be checked once everything has read the settings it needs
+    pub fn problems(&self) -> Vec<SettingsError> {
+        let read = self.read.borrow();
+        let mut unknown: Vec<_> = self
+            .values
+            .keys()
+            .filter(|key| !read.contains(*key))
+            .collect();
+        unknown.sort();
+
+        let mut problems = self.invalid.take();
+        problems.extend(
+            unknown
+                .into_iter()
+                .map(|key| SettingsError::Unknown { key: key.clone() }),
+        );
+        problems
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn from_source_test() {
+        let settings = Settings::from_source(
+            "# A comment
+            speed = 10
+
+              name =  classic  
+            speed = 20
+            flag = true",
+        )
+        .unwrap();
+
+        // Whitespace around keys and values is trimmed, and the last value of a key is kept
+        assert_eq!(settings.get::<f32>("speed"), Some(20.0));
+        assert_eq!(settings.get::<String>("name").as_deref(), Some("classic"));
+        assert_eq!(settings.get::<bool>("flag"), Some(true));
+        assert_eq!(settings.get::<f32>("missing"), None);
+        assert!(settings.problems().is_empty());
+    }
+
+    #[test]
+    fn syntax_error_test() {
+        let error = Settings::from_source("a = 1\n\nno equals sign").unwrap_err();
+        assert!(matches!(error, SettingsError::Syntax { line: 3 }));
+
+        let error = Settings::from_source(" = 1").unwrap_err();
+        assert!(matches!(error, SettingsError::Syntax { line: 1 }));
+    }
+
+    #[test]
+    fn problems_test() {
+        let settings = Settings::from_source(
+            "speed = fast
+            friction = NaN
+            size = 5
+            sped = 10",
+        )
+        .unwrap();
+
+        // Invalid values are read as unset, without stopping later settings from being read
+        assert_eq!(settings.get::<f32>("speed"), None);
+        assert_eq!(
+            settings.get_valid("friction", |friction: &f32| friction.is_finite()),
+            None
+        );
+        assert_eq!(settings.get::<usize>("size"), Some(5));
+
+        let problems = settings.problems();
+        assert_eq!(problems.len(), 3);
+        assert_eq!(
+            problems[0].to_string(),
+            "Setting 'speed' has an invalid value 'fast'"
+        );
+        assert!(matches!(&problems[1], SettingsError::Value { key, .. } if key == "friction"));
+        assert!(matches!(&problems[2], SettingsError::Unknown { key } if key == "sped"));
+    }
+}
@@ -0,0 +1,121 @@
+use input::{Action, Input};
+use maths::linear::Vec2f;
+
+use crate::{
+    camera::{Camera, CameraPath},
+    surface::Sector,
+};
+
+const MOUSE_SENSITIVITY: Vec2f = Vec2f { x: 0.1, y: 0.05 };
+const FLY_SPEED: f32 = 60.0;
+const FLY_SHARPNESS: f32 = 12.0;
+
+/// A camera that is detached from the player. It is either flown freely, ignoring collision, or
+/// follows a scripted path (for cutscenes).
+pub struct Spectator {
+    pub camera: Camera,
+    pub sector_index: usize,
+
+    /// The camera is smoothly moved towards this when flying freely
+    target: Camera,
+
+    path: Option<CameraPath>,
+    path_time: f32,
+}
+
+impl Spectator {
+    pub fn new(camera: Camera, sector_index: usize) -> Self {
+        Self {
+            target: camera.clone(),
+            camera,
+            sector_index,
+
+            path: None,
+            path_time: 0.0,
+        }
+    }
+
+    /// Starts following the given path from its beginning. The sector index must be that of the
+    /// sector containing the first keyframe.
+    pub fn play_path(&mut self, path: CameraPath, sector_index: usize) {
+        self.camera = path.sample(0.0);
+        self.sector_index = sector_index;
+        self.path = Some(path);
+        self.path_time = 0.0;
+    }
+
+    pub fn stop_path(&mut self) {
+        self.path = None;
+        self.target = self.camera.clone();
+    }
+
+    pub fn is_playing_path(&self) -> bool {
+        self.path.is_some()
+    }
+
+    pub fn update(&mut self, delta_seconds: f32, input: &Input, sectors: &[Sector]) {
+        let prev_position = self.camera.position;
+
+        match self.path {
+            Some(ref path) => {
+                self.path_time += delta_seconds;
+                self.camera = path.sample(self.path_time);
+
+                if path.is_finished(self.path_time) {
+                    self.stop_path();
+                }
+            }
+
+            None => self.update_free_fly(delta_seconds, input),
+        }
+
+        // Track the sector the camera is in, so that rendering starts from the correct place
+        if let Some(portal) =
+            sectors[self.sector_index].crossed_portal(prev_position, self.camera.position)
+        {
+            self.sector_index = portal.sector;
+        }
+    }
+
+    fn update_free_fly(&mut self, delta_seconds: f32, input: &Input) {
+        if !input.mouse.is_grabbed() {
+            self.camera
+                .smooth_towards(&self.target, FLY_SHARPNESS, delta_seconds);
+            return;
+        }
+
+        let mut direction = Vec2f::ZERO;
+        let mut vertical = 0.0;
+
+        if input.is_action_held(Action::MoveForward) {
+            direction += self.target.direction;
+        } else if input.is_action_held(Action::MoveBackward) {
+            direction -= self.target.direction;
+        }
+
+        if input.is_action_held(Action::StrafeLeft) {
+            direction += self.target.direction.perpendicular();
+        } else if input.is_action_held(Action::StrafeRight) {
+            direction -= self.target.direction.perpendicular();
+        }
+
+        if input.is_action_held(Action::MoveUp) {
+            vertical += 1.0;
+        } else if input.is_action_held(Action::MoveDown) {
+            vertical -= 1.0;
+        }
+
+        let mut mouse_delta = input.mouse.delta();
+        // Negate y-axis to make up positive, as the y-axis is flipped in screen space
+        mouse_delta.y = -mouse_delta.y;
+
+        self.target
+            .translate(direction.normalise() * FLY_SPEED * delta_seconds);
+        self.target.z += vertical * FLY_SPEED * delta_seconds;
+        self.target
+            .rotate(mouse_delta * MOUSE_SENSITIVITY * delta_seconds);
+
+        self.camera
+            .smooth_towards(&self.target, FLY_SHARPNESS, delta_seconds);
+    }
+}
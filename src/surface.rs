@@ -1,13 +1,24 @@
+use std::{f32::consts::TAU, ops::Range};
+
 use maths::{
-    geometry::Segment,
+    geometry::{Circle, Polygon, Segment, AABB},
     linear::{Mat2f, Vec2f},
 };
 
+use crate::{
+    colour::{BlendMode, BGRA8},
+    consts::TEXTURE_SCROLL_WRAP,
+    hazard::Hazard,
+    lightmap::Lightmap,
+};
+
 #[derive(Clone, Copy, Debug)]
 pub struct WallTexture {
     pub index: usize,
     pub offset: Vec2f,
     pub scale: Vec2f,
+    /// Change in offset per second, for moving surfaces such as waterfalls
+    pub scroll: Vec2f,
 }
 
 impl WallTexture {
@@ -16,15 +27,38 @@ impl WallTexture {
             index,
             offset,
             scale,
+            scroll: Vec2f::ZERO,
+        }
+    }
+
+    pub fn with_scroll(mut self, scroll: Vec2f) -> Self {
+        self.scroll = scroll;
+        self
+    }
+
+    fn animate(&mut self, delta_seconds: f32) {
+        if self.scroll == Vec2f::ZERO {
+            return;
+        }
+
+        self.offset += self.scroll * delta_seconds;
+
+        // The offset is scaled along with the position on the wall, so it is wrapped once scaled
+        let texture_offset = self.offset * self.scale;
+        let wrapped = wrap_texture_offset(texture_offset);
+        if wrapped != texture_offset {
+            self.offset = wrapped / self.scale;
         }
     }
 }
 
-#[derive(Clone, Copy, Debug)]
+#[derive(Clone, Copy, Debug, PartialEq)]
 pub struct PlaneTexture {
     pub index: usize,
     pub offset: Vec2f,
     pub scale_rotate: Mat2f,
+    /// Change in offset per second, for moving surfaces such as conveyor belts
+    pub scroll: Vec2f,
 }
 
 impl PlaneTexture {
@@ -33,15 +67,55 @@ impl PlaneTexture {
             index,
             offset,
             scale_rotate: Mat2f::rotation(rotate) * Mat2f::scale(scale),
+            scroll: Vec2f::ZERO,
+        }
+    }
+
+    pub fn with_scroll(mut self, scroll: Vec2f) -> Self {
+        self.scroll = scroll;
+        self
+    }
+
+    fn animate(&mut self, delta_seconds: f32) {
+        if self.scroll == Vec2f::ZERO {
+            return;
+        }
+
+        self.offset += self.scroll * delta_seconds;
+
+        // The offset is scaled and rotated along with the position on the plane, so it is wrapped
+        // once transformed
+        let texture_offset = self.scale_rotate * self.offset;
+        let wrapped = wrap_texture_offset(texture_offset);
+        if wrapped != texture_offset {
+            self.offset = self.scale_rotate.inverse() * wrapped;
         }
     }
 }
 
+/// Wraps an offset in texels into the range that scrolling textures are kept within
+fn wrap_texture_offset(offset: Vec2f) -> Vec2f {
+    Vec2f::new(
+        offset.x.rem_euclid(TEXTURE_SCROLL_WRAP),
+        offset.y.rem_euclid(TEXTURE_SCROLL_WRAP),
+    )
+}
+
 #[derive(Clone, Copy, Debug)]
 pub struct Portal {
     pub sector: usize,
     pub upper_texture: WallTexture,
     pub lower_texture: WallTexture,
+    /// Whether the next sector is rendered through this portal. If not, the wall is drawn as solid.
+    pub visible: bool,
+    /// Whether entities can move through this portal. If not, it is treated as a solid wall for
+    /// collision.
+    pub passable: bool,
+    /// Colour blended over everything seen through this portal, using the alpha as strength
+    pub tint: Option<BGRA8>,
+    /// Opening cut into an otherwise solid wall, for portals that don't reach all the way from the
+    /// next sector's floor to its ceiling. The upper and lower textures cover the wall around it.
+    pub opening: Option<Opening>,
 }
 
 impl Portal {
@@ -50,8 +124,85 @@ impl Portal {
             sector,
             upper_texture,
             lower_texture,
+            visible: true,
+            passable: true,
+            tint: None,
+            opening: None,
+        }
+    }
+
+    /// A portal that can be seen through, but not moved through (e.g. a pane of glass)
+    pub fn window(
+        sector: usize,
+        upper_texture: WallTexture,
+        lower_texture: WallTexture,
+        tint: Option<BGRA8>,
+    ) -> Self {
+        Self {
+            passable: false,
+            tint,
+            ..Self::new(sector, upper_texture, lower_texture)
         }
     }
+
+    /// Cuts the portal down to an opening between the given heights, with wall above and below
+    pub fn with_opening(mut self, bottom: f32, top: f32) -> Self {
+        self.opening = Some(Opening { bottom, top });
+        self
+    }
+
+    /// Heights of the bottom and top of what can be seen of `next` through the portal, which are
+    /// its floor and ceiling unless the portal only has an opening cut into the wall
+    pub fn next_heights(&self, next: &Sector) -> (f32, f32) {
+        let (floor, ceiling) = (next.floor.height, next.ceiling.height);
+
+        match self.opening {
+            Some(opening) => (floor.max(opening.bottom), ceiling.min(opening.top)),
+            None => (floor, ceiling),
+        }
+    }
+
+    /// Heights of the bottom and top of the gap that the portal leaves between `sector` and `next`,
+    /// which anything passing through or seen through it must fit within. The top is no higher
+    /// than the bottom where there is no gap, such as at a closed door.
+    pub fn gap(&self, sector: &Sector, next: &Sector) -> (f32, f32) {
+        let (bottom, top) = self.next_heights(next);
+        (
+            bottom.max(sector.floor.height),
+            top.min(sector.ceiling.height),
+        )
+    }
+}
+
+/// Heights of the bottom and top of an opening in a portal's wall, such as a window with a sill
+#[derive(Clone, Copy, Debug)]
+pub struct Opening {
+    pub bottom: f32,
+    pub top: f32,
+}
+
+/// Haze that fades surfaces towards its colour the further away they are
+#[derive(Clone, Copy, Debug)]
+pub struct Fog {
+    pub colour: BGRA8,
+    /// Fraction of light absorbed per unit of distance, roughly, where 0 is clear air
+    pub density: f32,
+}
+
+impl Fog {
+    pub const NONE: Self = Self {
+        colour: BGRA8::BLACK,
+        density: 0.0,
+    };
+
+    pub fn new(colour: BGRA8, density: f32) -> Self {
+        Self { colour, density }
+    }
+
+    /// Thick green haze, such as over a pool of slime
+    pub fn toxic() -> Self {
+        Self::new(BGRA8::new(48, 112, 32, 0xFF), 0.02)
+    }
 }
 
 #[derive(Debug)]
@@ -60,6 +211,119 @@ pub struct Sector {
     pub walls: Vec<Wall>,
     pub floor: Plane,
     pub ceiling: Plane,
+    /// Damages entities standing on the floor of this sector
+    pub hazard: Option<Hazard>,
+    /// Fog within this sector, in place of the renderer's own
+    pub fog: Option<Fog>,
+}
+
+impl Sector {
+    /// Moves the textures of any scrolling surfaces
+    pub fn animate_textures(&mut self, delta_seconds: f32) {
+        self.floor.texture_data.animate(delta_seconds);
+        self.ceiling.texture_data.animate(delta_seconds);
+
+        for wall in self.walls.iter_mut() {
+            wall.texture_data.animate(delta_seconds);
+
+            if let Some(portal) = wall.portal.as_mut() {
+                portal.upper_texture.animate(delta_seconds);
+                portal.lower_texture.animate(delta_seconds);
+            }
+        }
+    }
+
+    /// Outline of the sector's floor, with a vertex at the start of each wall
+    pub fn polygon(&self) -> Polygon {
+        Polygon::from_vertices(self.walls.iter().map(|wall| wall.segment.a).collect())
+    }
+
+    /// Returns whether the point lies within the outline of the sector's floor. Points exactly on a
+    /// wall may count as being on either side.
+    pub fn contains_point(&self, point: Vec2f) -> bool {
+        // Count the walls crossed by a ray heading along +x from the point, where an odd count means
+        // the point is inside. Treating each wall as half-open in y means a ray through a vertex
+        // only counts one of the walls that meet there.
+        let mut inside = false;
+
+        for wall in self.walls.iter() {
+            let (a, b) = (wall.segment.a, wall.segment.b);
+
+            if (a.y > point.y) != (b.y > point.y) {
+                let x = a.x + (point.y - a.y) / (b.y - a.y) * (b.x - a.x);
+
+                if point.x < x {
+                    inside = !inside;
+                }
+            }
+        }
+
+        inside
+    }
+
+    /// Bounding box of the sector's floor
+    pub fn extents(&self) -> AABB {
+        let mut min = Vec2f::uniform(f32::MAX);
+        let mut max = Vec2f::uniform(f32::MIN);
+
+        for wall in self.walls.iter() {
+            min = Vec2f::new(min.x.min(wall.segment.a.x), min.y.min(wall.segment.a.y));
+            max = Vec2f::new(max.x.max(wall.segment.a.x), max.y.max(wall.segment.a.y));
+        }
+
+        AABB::new(min, max)
+    }
+
+    /// Splits a wall in two at the point along it nearest to `point`, returning the index of the
+    /// second half, which is inserted after the first so that later walls move up by one. Returns
+    /// `None` if the point is at either end, where one half would have no length.
+    pub fn split_wall(&mut self, wall_index: usize, point: Vec2f) -> Option<usize> {
+        let rest = self.walls[wall_index].split(point)?;
+        self.insert_wall(wall_index + 1, rest);
+
+        Some(wall_index + 1)
+    }
+
+    /// Inserts a wall before the one at `index`, moving the ends of its neighbours to meet it so
+    /// that the outline stays closed. If the start of the next wall moves, its lightmap no longer
+    /// lines up with it and is removed.
+    pub fn insert_wall(&mut self, index: usize, wall: Wall) {
+        let count = self.walls.len();
+        if count > 0 {
+            let prev = &mut self.walls[(index + count - 1) % count];
+            prev.set_segment(prev.segment.a, wall.segment.a);
+
+            self.walls[index % count].move_start(wall.segment.b);
+        }
+
+        self.walls.insert(index, wall);
+    }
+
+    /// Removes a wall, moving the start of the following wall back to where the removed one
+    /// started so that the outline stays closed. This can leave the sector concave, or apart from
+    /// its neighbours, which [map::remove_wall](crate::map::remove_wall) checks for. The lightmap
+    /// of the following wall no longer lines up with it, so is removed.
+    pub fn remove_wall(&mut self, index: usize) -> Wall {
+        let wall = self.walls.remove(index);
+
+        let count = self.walls.len();
+        if count > 0 {
+            self.walls[index % count].move_start(wall.segment.a);
+        }
+
+        wall
+    }
+
+    /// Returns the portal that was crossed when moving from `from` to `to`, if any
+    pub fn crossed_portal(&self, from: Vec2f, to: Vec2f) -> Option<&Portal> {
+        let displacement_segment = Segment::new(from, to);
+
+        self.walls.iter().find_map(|wall| {
+            wall.portal
+                .as_ref()
+                .filter(|_| displacement_segment.intersects(&wall.segment))
+        })
+    }
 }
 
 #[derive(Debug)]
@@ -69,6 +333,8 @@ pub struct Wall {
     pub normal: Vec2f,
     pub texture_data: WallTexture,
     pub portal: Option<Portal>,
+    /// Light baked into the wall, from floor to ceiling
+    pub lightmap: Option<Lightmap>,
 }
 
 impl Wall {
@@ -79,14 +345,146 @@ impl Wall {
             width: (b - a).magnitude(),
             texture_data,
             portal,
+            lightmap: None,
+        }
+    }
+
+    /// Walls along an arc of a circle around `centre`, split into `segments` straight walls of
+    /// equal length, for round rooms and pillars. Angles are in radians, increasing from +x
+    /// towards +y, and the walls run from `start_angle` to `end_angle`. A sector's walls run with
+    /// the angle around its middle decreasing, so the arc of a round room starts at the greater
+    /// angle. An arc of a whole turn or more ends exactly where it started, closing the outline.
+    ///
+    /// The texture carries on from each wall to the next, starting from `texture_data`.
+    pub fn arc(
+        centre: Vec2f,
+        radius: f32,
+        start_angle: f32,
+        end_angle: f32,
+        segments: usize,
+        texture_data: WallTexture,
+        portal: Option<Portal>,
+    ) -> Vec<Wall> {
+        let segments = segments.max(1);
+        let step = (end_angle - start_angle) / segments as f32;
+        let closed = (end_angle - start_angle).abs() >= TAU;
+
+        let point = |index: usize| {
+            let index = if closed && index == segments {
+                0
+            } else {
+                index
+            };
+            let angle = start_angle + step * index as f32;
+            centre + Vec2f::new(angle.cos(), angle.sin()) * radius
+        };
+
+        let mut texture_data = texture_data;
+        (0..segments)
+            .map(|index| {
+                let wall = Wall::new(point(index), point(index + 1), texture_data, portal);
+                texture_data.offset.x += wall.width;
+                wall
+            })
+            .collect()
+    }
+
+    /// Walls from `a` to `b` with several portals along them, for a long wall with more than one
+    /// opening such as a colonnade. Each portal covers a range of distances along the wall from
+    /// `a`, in order and without overlapping, and the wall is solid between them. The texture
+    /// carries on from each wall to the next, starting from `texture_data`, so it lines up as if
+    /// it were still one wall.
+    ///
+    /// The wall on the other side is built the same way, running from `b` to `a` with the portals'
+    /// ranges measured from `b`, so that each portal has a twin.
+    pub fn with_portals(
+        a: Vec2f,
+        b: Vec2f,
+        texture_data: WallTexture,
+        portals: &[(Range<f32>, Portal)],
+    ) -> Vec<Wall> {
+        let length = (b - a).magnitude();
+        let direction = (b - a) / length;
+        let point = |distance: f32| match distance < length {
+            true => a + direction * distance,
+            false => b,
+        };
+
+        let mut walls = Vec::with_capacity(portals.len() * 2 + 1);
+        let mut push = |from: f32, to: f32, portal: Option<Portal>| {
+            if to > from {
+                let mut texture_data = texture_data;
+                texture_data.offset.x += from;
+                walls.push(Wall::new(point(from), point(to), texture_data, portal));
+            }
+        };
+
+        let mut solid_from = 0.0;
+        for (range, portal) in portals {
+            let start = range.start.clamp(solid_from, length);
+            let end = range.end.clamp(start, length);
+
+            push(solid_from, start, None);
+            push(start, end, Some(*portal));
+            solid_from = end;
+        }
+        push(solid_from, length, None);
+
+        walls
+    }
+
+    /// Moves the wall to run from `a` to `b`, updating its width and normal to match
+    pub fn set_segment(&mut self, a: Vec2f, b: Vec2f) {
+        self.segment = Segment::new(a, b);
+        self.normal = (b - a).normalise().perpendicular();
+        self.width = (b - a).magnitude();
+    }
+
+    /// Moves the start of the wall, keeping its end where it is. Its lightmap is measured from
+    /// the start, so is removed if that moved.
+    fn move_start(&mut self, a: Vec2f) {
+        if a != self.segment.a {
+            self.set_segment(a, self.segment.b);
+            self.lightmap = None;
         }
     }
+
+    /// Shortens the wall to end at the point along it nearest to `point`, returning a wall that
+    /// covers the rest, with its textures and lightmap carrying on from where this one now ends.
+    /// Returns `None` if the point is at either end.
+    fn split(&mut self, point: Vec2f) -> Option<Wall> {
+        let (a, b) = (self.segment.a, self.segment.b);
+        let t = ((point - a).dot(b - a) / (b - a).magnitude_sq()).clamp(0.0, 1.0);
+        let split_point = a + (b - a) * t;
+
+        if split_point == a || split_point == b {
+            return None;
+        }
+
+        let mut texture_data = self.texture_data;
+        texture_data.offset.x += self.width * t;
+
+        let mut rest = Wall::new(split_point, b, texture_data, self.portal);
+        rest.lightmap = self
+            .lightmap
+            .as_ref()
+            .map(|lightmap| lightmap.shifted(Vec2f::new(self.width * t, 0.0)));
+
+        self.set_segment(a, split_point);
+
+        Some(rest)
+    }
 }
 
 #[derive(Debug)]
 pub struct Plane {
     pub height: f32,
     pub texture_data: PlaneTexture,
+    /// Whether the player is carried along by the scrolling texture whilst standing on this plane,
+    /// which only makes sense for floors
+    pub conveyor: bool,
+    /// Light baked into the plane, by position in the world
+    pub lightmap: Option<Lightmap>,
 }
 
 impl Plane {
@@ -94,17 +492,42 @@ impl Plane {
         Self {
             height,
             texture_data,
+            conveyor: false,
+            lightmap: None,
+        }
+    }
+
+    pub fn conveyor(height: f32, texture_data: PlaneTexture) -> Self {
+        Self {
+            conveyor: true,
+            ..Self::new(height, texture_data)
+        }
+    }
+
+    /// Velocity at which things standing on the plane are carried, which matches the movement of
+    /// its texture if it is a conveyor
+    pub fn conveyor_velocity(&self) -> Vec2f {
+        if !self.conveyor {
+            return Vec2f::ZERO;
         }
+
+        // Texture coordinates are derived from the world position with Y flipped, and increasing
+        // the offset moves the texture the opposite way
+        Vec2f::new(-self.texture_data.scroll.x, self.texture_data.scroll.y)
     }
 }
 
-#[derive(Debug)]
+#[derive(Clone, Debug)]
 pub struct Sprite {
     pub position: Vec2f,
     pub sector_index: usize,
     pub texture_data: WallTexture,
     pub width: f32,
     pub height: f32,
+    /// Height of the bottom of the sprite, which rests on the floor of its sector unless falling
+    pub z: f32,
+    /// How the sprite is combined with what is behind it
+    pub blend: BlendMode,
 }
 
 impl Sprite {
@@ -121,6 +544,179 @@ impl Sprite {
             texture_data,
             width,
             height,
+            z: 0.0,
+            blend: BlendMode::Alpha,
+        }
+    }
+
+    /// Combines the sprite with what is behind it using the given mode, rather than covering it.
+    /// Sprites that are added are only ever drawn brighter, and so can be used for glows.
+    pub fn with_blend(mut self, blend: BlendMode) -> Self {
+        self.blend = blend;
+        self
+    }
+
+    /// Footprint of the sprite on the ground, used for overlap queries
+    pub fn collider(&self) -> Circle {
+        Circle::new(self.position, self.width * 0.5)
+    }
+
+    pub fn overlaps(&self, collider: &Circle) -> bool {
+        self.collider().intersects(collider)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        camera::Camera,
+        consts::{GRASS, PLANK, STONE_BRICK},
+        demo,
+        golden_tests::{load_renderer, HEIGHT, WIDTH},
+        map,
+    };
+
+    #[test]
+    fn scroll_wrap_test() {
+        let mut wall = WallTexture::new(0, Vec2f::ZERO, Vec2f::new(2.0, 0.5))
+            .with_scroll(Vec2f::new(-100.0, 300.0));
+        let mut plane = PlaneTexture::new(0, Vec2f::ZERO, Vec2f::uniform(2.0), 0.5)
+            .with_scroll(Vec2f::new(100.0, -50.0));
+
+        for _ in 0..1000 {
+            wall.animate(1.0);
+            plane.animate(1.0);
+
+            let wall_offset = wall.offset * wall.scale;
+            let plane_offset = plane.scale_rotate * plane.offset;
+            for coord in [wall_offset.x, wall_offset.y, plane_offset.x, plane_offset.y] {
+                assert!((-0.01..TEXTURE_SCROLL_WRAP + 0.01).contains(&coord));
+            }
+        }
+
+        // Wrapped by a whole number of periods, so the texture is where it would have been
+        let expected = -100.0 * 1000.0 * 2.0;
+        let wrapped = wall.offset.x * wall.scale.x;
+        assert!(((wrapped - expected).rem_euclid(TEXTURE_SCROLL_WRAP)).abs() < 0.01);
+
+        // Surfaces that don't scroll are left as they are
+        let mut still = WallTexture::new(0, Vec2f::new(-5.0, 0.0), Vec2f::uniform(1.0));
+        still.animate(1.0);
+        assert_eq!(still.offset, Vec2f::new(-5.0, 0.0));
+    }
+
+    #[test]
+    fn round_room_test() {
+        let (assets, mut renderer) = load_renderer();
+
+        let wall = WallTexture::new(STONE_BRICK, Vec2f::ZERO, Vec2f::uniform(5.0));
+        let walls = Wall::arc(Vec2f::new(50.0, 50.0), 40.0, TAU, 0.0, 12, wall, None);
+
+        // The walls meet end to end all the way around, with the texture carrying on between them
+        assert_eq!(walls.len(), 12);
+        for (index, wall) in walls.iter().enumerate() {
+            let next = &walls[(index + 1) % walls.len()];
+            assert_eq!(wall.segment.b, next.segment.a);
+            if index + 1 < walls.len() {
+                let offset = wall.texture_data.offset.x + wall.width;
+                assert!((next.texture_data.offset.x - offset).abs() < 1e-3);
+            }
         }
+
+        let sectors = [Sector {
+            id: 0,
+            walls,
+            floor: Plane::new(
+                0.0,
+                PlaneTexture::new(GRASS, Vec2f::ZERO, Vec2f::uniform(5.0), 0.0),
+            ),
+            ceiling: Plane::new(
+                25.0,
+                PlaneTexture::new(PLANK, Vec2f::ZERO, Vec2f::uniform(5.0), 0.0),
+            ),
+            hazard: None,
+            fog: None,
+        }];
+        assert!(sectors[0].polygon().is_convex());
+        map::validate(&sectors, assets.textures.len()).unwrap();
+
+        // Every column is covered by the walls, whichever way the room is looked around
+        for step in 0..8 {
+            let mut camera = Camera::new(Vec2f::new(50.0, 50.0), 15.0);
+            camera.set_rotation(step as f32 * TAU / 8.0, 0.0);
+            renderer
+                .update(&camera, 0, &assets.textures, &sectors, [])
+                .unwrap();
+            assert!(renderer.stats().pixels >= WIDTH * HEIGHT);
+        }
+
+        // The demo map's grotto is built the same way, and still meets the lava room's wall with
+        // room for what was placed in it
+        let sectors = demo::sectors();
+        map::validate(&sectors, assets.textures.len()).unwrap();
+        map::validate_things(&demo::things(), &sectors).unwrap();
+    }
+
+    #[test]
+    fn window_opening_test() {
+        let sectors = demo::sectors();
+        let (room, sand_room) = (Vec2f::new(110.0, 125.0), Vec2f::new(60.0, 145.0));
+
+        // The pane between sector 1 and the sand room only reaches from 12 to 18, over a sill that is
+        // higher than the sand room's floor
+        assert!(map::line_of_sight(&sectors, 1, room, 15.0, sand_room, 15.0));
+        assert!(!map::line_of_sight(
+            &sectors, 1, room, 11.0, sand_room, 11.0
+        ));
+        assert!(!map::line_of_sight(
+            &sectors, 1, room, 19.0, sand_room, 19.0
+        ));
+
+        let wall = &sectors[1].walls[4];
+        let portal = wall.portal.unwrap();
+        assert_eq!(portal.next_heights(&sectors[4]), (12.0, 18.0));
+        assert_eq!(portal.gap(&sectors[1], &sectors[4]), (12.0, 18.0));
+    }
+
+    #[test]
+    fn colonnade_test() {
+        let sectors = demo::sectors();
+
+        // The hall's south wall, split into gaps between the columns and the solid stretches between
+        let hall_walls = &sectors[2].walls[3..9];
+        let portals = hall_walls
+            .iter()
+            .filter(|wall| wall.portal.is_some())
+            .count();
+        assert_eq!(portals, 3);
+
+        // Each gap has a twin on the slime room's side, and the texture runs on across the columns
+        for (index, wall) in hall_walls.iter().enumerate() {
+            let id = map::WallId {
+                sector_index: 2,
+                wall_index: index + 3,
+            };
+            assert_eq!(
+                map::twin_wall(&sectors, id).is_some(),
+                wall.portal.is_some()
+            );
+            assert_eq!(wall.texture_data.offset.x, 150.0 - wall.segment.a.x);
+        }
+        assert_eq!(hall_walls[5].segment.b, Vec2f::new(60.0, 60.0));
+
+        // The slime room can be seen into through any of the gaps, but not through a column
+        let sight = |x| {
+            map::line_of_sight(
+                &sectors,
+                2,
+                Vec2f::new(x, 70.0),
+                15.0,
+                Vec2f::new(x, 40.0),
+                15.0,
+            )
+        };
+        assert!([106.0, 125.0, 144.0].into_iter().all(sight));
+        assert!(!sight(115.0));
     }
 }
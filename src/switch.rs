@@ -0,0 +1,88 @@
+use crate::{
+    map::WallId,
+    surface::{Sector, WallTexture},
+};
+
+/// A wall that the player can use, which shows a different texture whilst switched on. Its state
+/// is kept here rather than in the wall, which only holds the texture currently shown.
+#[derive(Debug)]
+pub struct Switch {
+    pub wall: WallId,
+    pub off_texture: WallTexture,
+    pub on_texture: WallTexture,
+    /// Seconds after being switched on before it switches off again, as for a button. Without
+    /// this, using the switch toggles it.
+    pub reset_time: Option<f32>,
+    /// Identifies what the switch controls, and is passed along with its events
+    pub trigger: usize,
+    on: bool,
+    /// Time remaining before an automatically resetting switch turns off
+    timer: f32,
+}
+
+impl Switch {
+    /// Creates a switch that is off, showing the wall's current texture until it is switched on
+    pub fn new(
+        sectors: &[Sector],
+        wall: WallId,
+        on_texture: WallTexture,
+        reset_time: Option<f32>,
+        trigger: usize,
+    ) -> Self {
+        Self {
+            wall,
+            off_texture: wall.get(sectors).texture_data,
+            on_texture,
+            reset_time,
+            trigger,
+            on: false,
+            timer: 0.0,
+        }
+    }
+
+    pub fn is_on(&self) -> bool {
+        self.on
+    }
+
+    /// Switches on, or toggles if the switch doesn't reset itself. Returns whether its state
+    /// changed, as using a button that is already on does nothing.
+    pub fn activate(&mut self, sectors: &mut [Sector]) -> bool {
+        let on = self.reset_time.is_some() || !self.on;
+        if on == self.on {
+            return false;
+        }
+
+        self.set_on(sectors, on);
+        if let Some(reset_time) = self.reset_time {
+            self.timer = reset_time;
+        }
+
+        true
+    }
+
+    /// Counts down the reset timer, returning whether the switch turned off
+    pub fn update(&mut self, sectors: &mut [Sector], delta_seconds: f32) -> bool {
+        if !self.on || self.reset_time.is_none() {
+            return false;
+        }
+
+        self.timer -= delta_seconds;
+        if self.timer > 0.0 {
+            return false;
+        }
+
+        self.set_on(sectors, false);
+        true
+    }
+
+    fn set_on(&mut self, sectors: &mut [Sector], on: bool) {
+        self.on = on;
+
+        let wall = &mut sectors[self.wall.sector_index].walls[self.wall.wall_index];
+        wall.texture_data = if on {
+            self.on_texture
+        } else {
+            self.off_texture
+        };
+    }
+}
@@ -1,6 +1,66 @@
-use std::path::Path;
+use std::{
+    collections::HashMap,
+    error::Error,
+    fmt,
+    path::{Path, PathBuf},
+};
+
+use crate::{
+    bitmap::{Bitmap, BitmapError},
+    colour::{linear_to_srgb, srgb_to_linear, BGRA8},
+    consts::MIP_LEVELS,
+};
+
+/// Most colours that a paletted texture can have, so that each pixel's index fits in a byte
+const PALETTE_SIZE: usize = 256;
+
+/// Added to the file name of a texture to find its emissive mask, e.g. `lamp_emissive.png` for
+/// `lamp.png`
+const EMISSIVE_MASK_SUFFIX: &str = "_emissive";
 
-use crate::{bitmap::Bitmap, colour::BGRA8, consts::MIP_LEVELS};
+#[derive(Debug)]
+pub enum TextureError {
+    Bitmap(BitmapError),
+    /// The emissive mask isn't the same size as the texture it belongs to
+    MaskSize {
+        texture_width: usize,
+        texture_height: usize,
+        mask_width: usize,
+        mask_height: usize,
+    },
+}
+
+impl fmt::Display for TextureError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TextureError::Bitmap(error) => error.fmt(f),
+            TextureError::MaskSize {
+                texture_width,
+                texture_height,
+                mask_width,
+                mask_height,
+            } => write!(
+                f,
+                "emissive mask is {mask_width}x{mask_height}, but the texture is {texture_width}x{texture_height}"
+            ),
+        }
+    }
+}
+
+impl Error for TextureError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            TextureError::Bitmap(error) => Some(error),
+            TextureError::MaskSize { .. } => None,
+        }
+    }
+}
+
+impl From<BitmapError> for TextureError {
+    fn from(error: BitmapError) -> Self {
+        TextureError::Bitmap(error)
+    }
+}
 
 #[derive(Clone, Copy, Debug, Default)]
 pub struct MipLevel {
@@ -9,19 +69,93 @@ pub struct MipLevel {
     pub offset: usize,
 }
 
+/// Colours of the pixels of every level of a texture
+#[derive(Debug)]
+pub enum Texels {
+    /// The colour of each pixel
+    Direct(Vec<BGRA8>),
+    /// Up to [PALETTE_SIZE] colours, and for each pixel the index of its colour. This takes a
+    /// quarter of the memory, and leaves room for lighting a whole palette at once.
+    Paletted {
+        palette: Box<[BGRA8; PALETTE_SIZE]>,
+        indices: Vec<u8>,
+    },
+}
+
 #[derive(Debug)]
 pub struct Texture {
     pub levels: [MipLevel; MIP_LEVELS],
-    pub pixels: Vec<BGRA8>,
+    pub texels: Texels,
+    /// For each pixel, the lowest light level it is drawn at, so that parts like lamps and screens
+    /// glow in the dark. Laid out like `texels`, and only present if the texture has a mask.
+    pub emissive: Option<Vec<u8>>,
 }
 
 impl Texture {
-    pub fn from_path_png(path: impl AsRef<Path>) -> Result<Self, &'static str> {
+    /// Loads a texture, along with its emissive mask if there is one alongside it. The brightness of
+    /// each pixel of the mask is the lowest light level of the texture's pixel.
+    pub fn from_path_png(path: impl AsRef<Path>) -> Result<Self, TextureError> {
+        let path = path.as_ref();
         let bitmap = Bitmap::from_path_png(path)?;
-        Ok(Self::from_bitmap(bitmap))
+
+        let mask_path = emissive_mask_path(path);
+        let mask = if mask_path.exists() {
+            Some(Bitmap::from_path_png(mask_path)?)
+        } else {
+            None
+        };
+
+        Self::from_bitmap(bitmap, mask)
+    }
+
+    /// Bytes taken by the pixels of every level, and those of the emissive mask
+    pub fn memory_size(&self) -> usize {
+        let texels = match &self.texels {
+            Texels::Direct(pixels) => core::mem::size_of_val(pixels.as_slice()),
+            Texels::Paletted { palette, indices } => {
+                core::mem::size_of_val(palette.as_ref()) + indices.len()
+            }
+        };
+
+        texels + self.emissive.as_ref().map_or(0, Vec::len)
     }
 
-    fn from_bitmap(bitmap: Bitmap) -> Self {
+    /// Converts the texture to store a palette and an index per pixel, unless it is too small to
+    /// save any memory by doing so. The palette holds the colours of the full size level, reduced
+    /// with a median cut if there are too many, and the smaller levels use whichever of those
+    /// colours are nearest to their own.
+    pub fn into_paletted(self) -> Self {
+        let Texels::Direct(pixels) = &self.texels else {
+            return self;
+        };
+        // Small textures are left as they are, as they would take more memory with the palette
+        let paletted_size = core::mem::size_of::<[BGRA8; PALETTE_SIZE]>() + pixels.len();
+        if paletted_size >= core::mem::size_of_val(pixels.as_slice()) {
+            return self;
+        }
+
+        let full_size = self.levels[0].width * self.levels[0].height;
+        let palette = Box::new(quantise(&pixels[..full_size]));
+
+        // Textures repeat a lot of colours, so the nearest in the palette is only found once each
+        let mut nearest = HashMap::new();
+        let indices = pixels
+            .iter()
+            .map(|pixel| {
+                let pixel = palette_colour(*pixel);
+                *nearest
+                    .entry(colour_key(pixel))
+                    .or_insert_with(|| nearest_index(&palette, pixel))
+            })
+            .collect();
+
+        Self {
+            texels: Texels::Paletted { palette, indices },
+            ..self
+        }
+    }
+
+    fn from_bitmap(bitmap: Bitmap, mask: Option<Bitmap>) -> Result<Self, TextureError> {
         let levels = Self::calculate_mip_levels(&bitmap);
         let buffer_size = levels[MIP_LEVELS - 1].offset
             + levels[MIP_LEVELS - 1].width * levels[MIP_LEVELS - 1].height;
@@ -31,9 +165,38 @@ impl Texture {
         // Copy the pixels from the bitmap into the first level of the texture
         pixels[..bitmap.pixels().len()].copy_from_slice(bitmap.pixels());
 
-        Self::generate_mip_maps(&levels, &mut pixels);
+        // Averaging opaque pixels can only produce opaque pixels, so every level will be opaque
+        let opaque = bitmap.pixels().iter().all(|pixel| pixel.a == 0xFF);
+
+        Self::generate_mip_maps(&levels, &mut pixels, opaque);
+
+        let emissive = match mask {
+            Some(mask) => {
+                if mask.width() != bitmap.width() || mask.height() != bitmap.height() {
+                    return Err(TextureError::MaskSize {
+                        texture_width: bitmap.width(),
+                        texture_height: bitmap.height(),
+                        mask_width: mask.width(),
+                        mask_height: mask.height(),
+                    });
+                }
+
+                let mut emissive = vec![0; buffer_size];
+                for (level, pixel) in emissive.iter_mut().zip(mask.pixels().iter()) {
+                    *level = pixel.r.max(pixel.g).max(pixel.b);
+                }
+                Self::generate_mask_mip_maps(&levels, &mut emissive);
+
+                Some(emissive)
+            }
+            None => None,
+        };
 
-        Self { levels, pixels }
+        Ok(Self {
+            levels,
+            texels: Texels::Direct(pixels),
+            emissive,
+        })
     }
 
     fn calculate_mip_levels(bitmap: &Bitmap) -> [MipLevel; MIP_LEVELS] {
@@ -57,7 +220,7 @@ impl Texture {
     }
 
     /// Generates mip maps for the given texture, assuming that the first level is already filled
-    fn generate_mip_maps(levels: &[MipLevel], buffer: &mut [BGRA8]) {
+    fn generate_mip_maps(levels: &[MipLevel], buffer: &mut [BGRA8], opaque: bool) {
         for i in 1..MIP_LEVELS {
             let src_width = levels[i - 1].width;
             let src_height = levels[i - 1].height;
@@ -68,7 +231,21 @@ impl Texture {
             let (src, dst) = buffer.split_at_mut(write_index);
             let src = &src[read_index..];
 
-            downscale_3x3_box_filter(src, src_width, src_height, dst);
+            if opaque {
+                downscale_3x3_box_filter_opaque(src, src_width, src_height, dst);
+            } else {
+                downscale_3x3_box_filter(src, src_width, src_height, dst);
+            }
+        }
+    }
+
+    /// Same as [Self::generate_mip_maps], but for a mask with one value per pixel
+    fn generate_mask_mip_maps(levels: &[MipLevel], buffer: &mut [u8]) {
+        for i in 1..MIP_LEVELS {
+            let (src, dst) = buffer.split_at_mut(levels[i].offset);
+            let src = &src[levels[i - 1].offset..];
+
+            downscale_3x3_box_filter_mask(src, levels[i - 1].width, levels[i - 1].height, dst);
         }
     }
 
@@ -78,7 +255,10 @@ impl Texture {
         let local_offset = y * self.levels[level].width + x;
         let global_offset = self.levels[level].offset + local_offset;
 
-        self.pixels[global_offset]
+        match &self.texels {
+            Texels::Direct(pixels) => pixels[global_offset],
+            Texels::Paletted { palette, indices } => palette[indices[global_offset] as usize],
+        }
     }
 
     pub unsafe fn sample_unchecked(&self, x: usize, y: usize, level: usize) -> BGRA8 {
@@ -88,9 +268,171 @@ impl Texture {
         let local_offset = y * self.levels.get_unchecked(level).width + x;
         let global_offset = self.levels.get_unchecked(level).offset + local_offset;
 
-        debug_assert!(global_offset < self.pixels.len());
-        *self.pixels.get_unchecked(global_offset)
+        // Every texel of a texture is stored the same way, so this is predicted well within spans
+        match &self.texels {
+            Texels::Direct(pixels) => {
+                debug_assert!(global_offset < pixels.len());
+                *pixels.get_unchecked(global_offset)
+            }
+            Texels::Paletted { palette, indices } => {
+                debug_assert!(global_offset < indices.len());
+                palette[*indices.get_unchecked(global_offset) as usize]
+            }
+        }
+    }
+
+    /// Returns the lowest light level of a pixel, which is 0 unless the texture has an emissive mask
+    pub unsafe fn sample_emissive_unchecked(&self, x: usize, y: usize, level: usize) -> u8 {
+        let Some(emissive) = &self.emissive else {
+            return 0;
+        };
+
+        debug_assert!(x < self.levels[level].width && y < self.levels[level].height);
+
+        debug_assert!(level < MIP_LEVELS);
+        let local_offset = y * self.levels.get_unchecked(level).width + x;
+        let global_offset = self.levels.get_unchecked(level).offset + local_offset;
+
+        debug_assert!(global_offset < emissive.len());
+        *emissive.get_unchecked(global_offset)
+    }
+}
+
+/// Path of the emissive mask that may sit alongside the texture at `path`
+pub fn emissive_mask_path(path: &Path) -> PathBuf {
+    let mut file_name = path.file_stem().unwrap_or_default().to_owned();
+    file_name.push(EMISSIVE_MASK_SUFFIX);
+    if let Some(extension) = path.extension() {
+        file_name.push(".");
+        file_name.push(extension);
+    }
+
+    path.with_file_name(file_name)
+}
+
+/// Colour as stored in a palette, where every fully transparent colour is the same
+fn palette_colour(colour: BGRA8) -> BGRA8 {
+    if colour.a == 0 {
+        BGRA8::default()
+    } else {
+        colour
+    }
+}
+
+fn colour_key(colour: BGRA8) -> u32 {
+    u32::from_le_bytes([colour.b, colour.g, colour.r, colour.a])
+}
+
+fn channels(colour: BGRA8) -> [u8; 4] {
+    [colour.b, colour.g, colour.r, colour.a]
+}
+
+/// Index of the colour in the palette that is closest to the given colour
+fn nearest_index(palette: &[BGRA8; PALETTE_SIZE], colour: BGRA8) -> u8 {
+    let distance = |entry: &BGRA8| -> u32 {
+        channels(*entry)
+            .into_iter()
+            .zip(channels(colour))
+            .map(|(a, b)| (a as i32 - b as i32).pow(2) as u32)
+            .sum()
+    };
+
+    palette
+        .iter()
+        .enumerate()
+        .min_by_key(|(_, entry)| distance(entry))
+        .map_or(0, |(index, _)| index as u8)
+}
+
+/// Chooses up to [PALETTE_SIZE] colours to represent the given pixels. If there are no more than
+/// that, they are used as they are. Otherwise, the colours are split in two along whichever channel
+/// they vary the most in, repeatedly, and each group is averaged into one colour.
+fn quantise(pixels: &[BGRA8]) -> [BGRA8; PALETTE_SIZE] {
+    // Each distinct colour, along with how many pixels have it
+    let mut counts: HashMap<u32, (BGRA8, u32)> = HashMap::new();
+    for pixel in pixels.iter() {
+        let pixel = palette_colour(*pixel);
+        counts.entry(colour_key(pixel)).or_insert((pixel, 0)).1 += 1;
+    }
+    let mut colours: Vec<(BGRA8, u32)> = counts.into_values().collect();
+    // Sorted so that the palette doesn't depend on the order of the hash map
+    colours.sort_unstable_by_key(|(colour, _)| colour_key(*colour));
+
+    let mut palette = [BGRA8::default(); PALETTE_SIZE];
+
+    if colours.len() <= PALETTE_SIZE {
+        for (entry, (colour, _)) in palette.iter_mut().zip(colours.iter()) {
+            *entry = *colour;
+        }
+        return palette;
+    }
+
+    // The channel that varies the most across a group of colours, and by how much
+    let widest_channel = |group: &[(BGRA8, u32)]| -> (usize, u8) {
+        (0..4)
+            .map(|channel| {
+                let values = group.iter().map(|(colour, _)| channels(*colour)[channel]);
+                let range = values.clone().max().unwrap() - values.min().unwrap();
+                (channel, range)
+            })
+            .max_by_key(|(_, range)| *range)
+            .unwrap()
+    };
+
+    let mut groups = vec![colours];
+    while groups.len() < PALETTE_SIZE {
+        let Some((index, channel)) = groups
+            .iter()
+            .enumerate()
+            .filter(|(_, group)| group.len() > 1)
+            .map(|(index, group)| (index, widest_channel(group)))
+            .max_by_key(|(_, (_, range))| *range)
+            .map(|(index, (channel, _))| (index, channel))
+        else {
+            break;
+        };
+
+        // Split where half of the group's pixels fall either side
+        let mut group = groups.swap_remove(index);
+        group.sort_unstable_by_key(|(colour, _)| channels(*colour)[channel]);
+        let total: u32 = group.iter().map(|(_, count)| count).sum();
+        let mut below = 0;
+        let split = group
+            .iter()
+            .position(|(_, count)| {
+                below += count;
+                below * 2 >= total
+            })
+            .unwrap()
+            .clamp(0, group.len() - 2)
+            + 1;
+
+        let upper = group.split_off(split);
+        groups.push(group);
+        groups.push(upper);
+    }
+
+    // Averaged in linear light, like the mip levels, weighted by how many pixels have each colour
+    for (entry, group) in palette.iter_mut().zip(groups.iter()) {
+        let mut sums = [0; 4];
+        let mut total = 0;
+        for (colour, count) in group.iter() {
+            sums[0] += srgb_to_linear(colour.b) as u32 * count;
+            sums[1] += srgb_to_linear(colour.g) as u32 * count;
+            sums[2] += srgb_to_linear(colour.r) as u32 * count;
+            sums[3] += colour.a as u32 * count;
+            total += count;
+        }
+
+        *entry = BGRA8::new(
+            linear_to_srgb((sums[2] / total) as u16),
+            linear_to_srgb((sums[1] / total) as u16),
+            linear_to_srgb((sums[0] / total) as u16),
+            (sums[3] / total) as u8,
+        );
     }
+
+    palette
 }
 
 fn sample_clamp(src: &[BGRA8], src_width: usize, src_height: usize, x: isize, y: isize) -> BGRA8 {
@@ -100,13 +442,100 @@ fn sample_clamp(src: &[BGRA8], src_width: usize, src_height: usize, x: isize, y:
     src[y * src_width + x]
 }
 
-fn sample_wrap(src: &[BGRA8], src_width: usize, src_height: usize, x: isize, y: isize) -> BGRA8 {
+fn sample_wrap<T: Copy>(src: &[T], src_width: usize, src_height: usize, x: isize, y: isize) -> T {
     let x = x.rem_euclid(src_width as isize) as usize;
     let y = y.rem_euclid(src_height as isize) as usize;
 
     src[y * src_width + x]
 }
 
+/// Gathers the 3x3 block of pixels centred on the given coordinates, wrapping around the edges
+fn samples_3x3<T: Copy>(
+    src: &[T],
+    src_width: usize,
+    src_height: usize,
+    src_x: isize,
+    src_y: isize,
+) -> [T; 9] {
+    // [a, b, c
+    //  d, e, f
+    //  g, h, i]
+
+    [
+        sample_wrap(src, src_width, src_height, src_x - 1, src_y - 1),
+        sample_wrap(src, src_width, src_height, src_x, src_y - 1),
+        sample_wrap(src, src_width, src_height, src_x + 1, src_y - 1),
+        sample_wrap(src, src_width, src_height, src_x - 1, src_y),
+        sample_wrap(src, src_width, src_height, src_x, src_y),
+        sample_wrap(src, src_width, src_height, src_x + 1, src_y),
+        sample_wrap(src, src_width, src_height, src_x - 1, src_y + 1),
+        sample_wrap(src, src_width, src_height, src_x, src_y + 1),
+        sample_wrap(src, src_width, src_height, src_x + 1, src_y + 1),
+    ]
+}
+
+/// Halves the size of a mask, averaging each 3x3 block of values
+fn downscale_3x3_box_filter_mask(src: &[u8], src_width: usize, src_height: usize, dst: &mut [u8]) {
+    let dst_width = src_width / 2;
+    let dst_height = src_height / 2;
+
+    assert!(dst.len() >= dst_width * dst_height);
+
+    for dst_y in 0..dst_height {
+        for dst_x in 0..dst_width {
+            let src_x = (dst_x * 2) as isize;
+            let src_y = (dst_y * 2) as isize;
+
+            let samples = samples_3x3(src, src_width, src_height, src_x, src_y);
+            let sum: u32 = samples.iter().map(|sample| *sample as u32).sum();
+
+            dst[dst_y * dst_width + dst_x] = (sum / 9) as u8;
+        }
+    }
+}
+
+/// Same as [downscale_3x3_box_filter], but for textures without any transparent pixels, which
+/// don't need to be left out of the average
+fn downscale_3x3_box_filter_opaque(
+    src: &[BGRA8],
+    src_width: usize,
+    src_height: usize,
+    dst: &mut [BGRA8],
+) {
+    let dst_width = src_width / 2;
+    let dst_height = src_height / 2;
+
+    assert!(dst.len() >= dst_width * dst_height);
+
+    for dst_y in 0..dst_height {
+        for dst_x in 0..dst_width {
+            let src_x = (dst_x * 2) as isize;
+            let src_y = (dst_y * 2) as isize;
+
+            let samples = samples_3x3(src, src_width, src_height, src_x, src_y);
+
+            let mut r = 0;
+            let mut g = 0;
+            let mut b = 0;
+
+            for sample in samples.iter() {
+                r += srgb_to_linear(sample.r) as u32;
+                g += srgb_to_linear(sample.g) as u32;
+                b += srgb_to_linear(sample.b) as u32;
+            }
+
+            dst[dst_y * dst_width + dst_x] = BGRA8::new(
+                linear_to_srgb((r / 9) as u16),
+                linear_to_srgb((g / 9) as u16),
+                linear_to_srgb((b / 9) as u16),
+                0xFF,
+            );
+        }
+    }
+}
+
+/// Halves the size of an image, averaging each 3x3 block of pixels in linear light so that the
+/// result isn't darker than the original. Transparent pixels are left out of the average.
 fn downscale_3x3_box_filter(src: &[BGRA8], src_width: usize, src_height: usize, dst: &mut [BGRA8]) {
     let dst_width = src_width / 2;
     let dst_height = src_height / 2;
@@ -118,21 +547,7 @@ fn downscale_3x3_box_filter(src: &[BGRA8], src_width: usize, src_height: usize,
             let src_x = (dst_x * 2) as isize;
             let src_y = (dst_y * 2) as isize;
 
-            // [a, b, c
-            //  d, e, f
-            //  g, h, i]
-
-            let samples = [
-                sample_wrap(src, src_width, src_height, src_x - 1, src_y - 1),
-                sample_wrap(src, src_width, src_height, src_x, src_y - 1),
-                sample_wrap(src, src_width, src_height, src_x + 1, src_y - 1),
-                sample_wrap(src, src_width, src_height, src_x - 1, src_y),
-                sample_wrap(src, src_width, src_height, src_x, src_y),
-                sample_wrap(src, src_width, src_height, src_x + 1, src_y),
-                sample_wrap(src, src_width, src_height, src_x - 1, src_y + 1),
-                sample_wrap(src, src_width, src_height, src_x, src_y + 1),
-                sample_wrap(src, src_width, src_height, src_x + 1, src_y + 1),
-            ];
+            let samples = samples_3x3(src, src_width, src_height, src_x, src_y);
 
             let mut r = 0;
             let mut g = 0;
@@ -146,9 +561,9 @@ fn downscale_3x3_box_filter(src: &[BGRA8], src_width: usize, src_height: usize,
                     continue;
                 }
 
-                r += sample.r as u32;
-                g += sample.g as u32;
-                b += sample.b as u32;
+                r += srgb_to_linear(sample.r) as u32;
+                g += srgb_to_linear(sample.g) as u32;
+                b += srgb_to_linear(sample.b) as u32;
                 a += sample.a as u32;
 
                 sample_count += 1;
@@ -163,7 +578,12 @@ fn downscale_3x3_box_filter(src: &[BGRA8], src_width: usize, src_height: usize,
             b /= sample_count;
             a /= sample_count;
 
-            dst[dst_y * dst_width + dst_x] = BGRA8::new(r as u8, g as u8, b as u8, a as u8);
+            dst[dst_y * dst_width + dst_x] = BGRA8::new(
+                linear_to_srgb(r as u16),
+                linear_to_srgb(g as u16),
+                linear_to_srgb(b as u16),
+                a as u8,
+            );
         }
     }
 }
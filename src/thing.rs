@@ -0,0 +1,278 @@
+//! Things placed in the map, such as enemies and items, which are created from their type when the
+//! level starts
+
+use std::{collections::HashMap, ops::BitOr};
+
+use maths::linear::Vec2f;
+
+use crate::{
+    colour::BlendMode,
+    consts::{LEAF, LIGHT_CONE},
+    enemy::{Enemy, EnemyKind},
+    item::{Item, ItemKind, KeyColour},
+    lightmap::StaticLight,
+    prop::{Prop, PropKind},
+    surface::{Sprite, WallTexture},
+    weapon::WeaponKind,
+};
+
+/// Identifies what a [Thing] is, and so which spawn function creates it
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct ThingType(pub u16);
+
+impl ThingType {
+    pub const PLAYER_START: Self = Self(1);
+    /// Where the player comes back after dying, once they have reached its sector
+    pub const RESPAWN_POINT: Self = Self(2);
+    pub const GOBLIN: Self = Self(100);
+    pub const ARCHER: Self = Self(101);
+    pub const SHAMAN: Self = Self(102);
+    pub const HEALTH: Self = Self(200);
+    pub const HEALTH_LARGE: Self = Self(201);
+    pub const ARMOUR: Self = Self(202);
+    pub const AMMO: Self = Self(203);
+    pub const AMMO_BOX: Self = Self(204);
+    pub const KEY_RED: Self = Self(210);
+    pub const KEY_BLUE: Self = Self(211);
+    pub const KEY_YELLOW: Self = Self(212);
+    pub const PISTOL: Self = Self(220);
+    pub const SHOTGUN: Self = Self(221);
+    pub const CROSSBOW: Self = Self(222);
+    pub const BUSH: Self = Self(300);
+    pub const CRATE: Self = Self(301);
+    pub const PILLAR: Self = Self(302);
+    pub const LAMP: Self = Self(400);
+    /// A shaft of light hanging from the ceiling, usually placed under a lamp
+    pub const LIGHT_CONE: Self = Self(401);
+
+    /// Every built-in type, along with the name that scripts refer to it by
+    pub const NAMED: [(&'static str, Self); 21] = [
+        ("PLAYER_START", Self::PLAYER_START),
+        ("RESPAWN_POINT", Self::RESPAWN_POINT),
+        ("GOBLIN", Self::GOBLIN),
+        ("ARCHER", Self::ARCHER),
+        ("SHAMAN", Self::SHAMAN),
+        ("HEALTH", Self::HEALTH),
+        ("HEALTH_LARGE", Self::HEALTH_LARGE),
+        ("ARMOUR", Self::ARMOUR),
+        ("AMMO", Self::AMMO),
+        ("AMMO_BOX", Self::AMMO_BOX),
+        ("KEY_RED", Self::KEY_RED),
+        ("KEY_BLUE", Self::KEY_BLUE),
+        ("KEY_YELLOW", Self::KEY_YELLOW),
+        ("PISTOL", Self::PISTOL),
+        ("SHOTGUN", Self::SHOTGUN),
+        ("CROSSBOW", Self::CROSSBOW),
+        ("BUSH", Self::BUSH),
+        ("CRATE", Self::CRATE),
+        ("PILLAR", Self::PILLAR),
+        ("LAMP", Self::LAMP),
+        ("LIGHT_CONE", Self::LIGHT_CONE),
+    ];
+}
+
+/// Options for how a thing is spawned, as a set of bits
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct SpawnFlags(u8);
+
+impl SpawnFlags {
+    pub const NONE: Self = Self(0);
+    /// Enemies start out chasing the player, rather than waiting to notice them
+    pub const AWAKE: Self = Self(1 << 0);
+
+    pub fn contains(self, flags: Self) -> bool {
+        self.0 & flags.0 == flags.0
+    }
+}
+
+impl BitOr for SpawnFlags {
+    type Output = Self;
+
+    fn bitor(self, other: Self) -> Self {
+        Self(self.0 | other.0)
+    }
+}
+
+/// Placement of something in the map
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Thing {
+    pub kind: ThingType,
+    pub position: Vec2f,
+    /// Direction faced, as a yaw in radians
+    pub angle: f32,
+    pub sector_index: usize,
+    pub flags: SpawnFlags,
+}
+
+impl Thing {
+    pub fn new(kind: ThingType, position: Vec2f, sector_index: usize) -> Self {
+        Self {
+            kind,
+            position,
+            angle: 0.0,
+            sector_index,
+            flags: SpawnFlags::NONE,
+        }
+    }
+
+    pub fn with_angle(mut self, angle: f32) -> Self {
+        self.angle = angle;
+        self
+    }
+
+    pub fn with_flags(mut self, flags: SpawnFlags) -> Self {
+        self.flags = flags;
+        self
+    }
+}
+
+/// A place for the player to be spawned at
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct PlayerStart {
+    pub position: Vec2f,
+    pub yaw: f32,
+    pub sector_index: usize,
+}
+
+impl PlayerStart {
+    fn from_thing(thing: &Thing) -> Self {
+        Self {
+            position: thing.position,
+            yaw: thing.angle,
+            sector_index: thing.sector_index,
+        }
+    }
+}
+
+/// What a thing became once spawned, to be added to the level
+pub enum Spawned {
+    PlayerStart(PlayerStart),
+    RespawnPoint(PlayerStart),
+    Enemy(Enemy),
+    Item(Item),
+    /// A sprite that is only there to be looked at
+    Decoration(Sprite),
+    Prop(Prop),
+    /// A light that is baked into the surfaces around it
+    Light(StaticLight),
+    /// A sprite that hangs from the ceiling rather than resting on the floor, and is only there to
+    /// be looked at
+    Hanging(Sprite),
+}
+
+pub type Spawner = fn(&Thing) -> Spawned;
+
+/// Maps each type of thing to the function that spawns it
+pub struct ThingRegistry {
+    spawners: HashMap<ThingType, Spawner>,
+}
+
+impl ThingRegistry {
+    pub fn new() -> Self {
+        Self {
+            spawners: HashMap::new(),
+        }
+    }
+
+    /// Creates a registry that can spawn every built-in type of thing
+    pub fn with_defaults() -> Self {
+        let mut registry = Self::new();
+
+        registry.register(ThingType::PLAYER_START, |thing| {
+            Spawned::PlayerStart(PlayerStart::from_thing(thing))
+        });
+        registry.register(ThingType::RESPAWN_POINT, |thing| {
+            Spawned::RespawnPoint(PlayerStart::from_thing(thing))
+        });
+        registry.register(ThingType::GOBLIN, |thing| enemy(thing, EnemyKind::Goblin));
+        registry.register(ThingType::ARCHER, |thing| enemy(thing, EnemyKind::Archer));
+        registry.register(ThingType::SHAMAN, |thing| enemy(thing, EnemyKind::Shaman));
+
+        registry.register(ThingType::HEALTH, |thing| item(thing, ItemKind::Health(25)));
+        registry.register(ThingType::HEALTH_LARGE, |thing| {
+            item(thing, ItemKind::Health(50))
+        });
+        registry.register(ThingType::ARMOUR, |thing| item(thing, ItemKind::Armour(25)));
+        registry.register(ThingType::AMMO, |thing| item(thing, ItemKind::Ammo(20)));
+        registry.register(ThingType::AMMO_BOX, |thing| item(thing, ItemKind::Ammo(50)));
+        registry.register(ThingType::KEY_RED, |thing| {
+            item(thing, ItemKind::Key(KeyColour::Red))
+        });
+        registry.register(ThingType::KEY_BLUE, |thing| {
+            item(thing, ItemKind::Key(KeyColour::Blue))
+        });
+        registry.register(ThingType::KEY_YELLOW, |thing| {
+            item(thing, ItemKind::Key(KeyColour::Yellow))
+        });
+        registry.register(ThingType::PISTOL, |thing| {
+            item(thing, ItemKind::Weapon(WeaponKind::Pistol))
+        });
+        registry.register(ThingType::SHOTGUN, |thing| {
+            item(thing, ItemKind::Weapon(WeaponKind::Shotgun))
+        });
+        registry.register(ThingType::CROSSBOW, |thing| {
+            item(thing, ItemKind::Weapon(WeaponKind::Crossbow))
+        });
+
+        registry.register(ThingType::BUSH, |thing| {
+            Spawned::Decoration(Sprite::new(
+                thing.position,
+                thing.sector_index,
+                WallTexture::new(LEAF, Vec2f::ZERO, Vec2f::uniform(4.0)),
+                12.0,
+                10.0,
+            ))
+        });
+        registry.register(ThingType::CRATE, |thing| prop(thing, PropKind::Crate));
+        registry.register(ThingType::PILLAR, |thing| prop(thing, PropKind::Pillar));
+        registry.register(ThingType::LAMP, |thing| {
+            Spawned::Light(StaticLight::lamp(thing.position, thing.sector_index))
+        });
+        registry.register(ThingType::LIGHT_CONE, |thing| {
+            // The texture is stretched to cover the sprite exactly once
+            Spawned::Hanging(
+                Sprite::new(
+                    thing.position,
+                    thing.sector_index,
+                    WallTexture::new(LIGHT_CONE, Vec2f::ZERO, Vec2f::new(2.0, 64.0 / 24.0)),
+                    16.0,
+                    24.0,
+                )
+                .with_blend(BlendMode::Add),
+            )
+        });
+
+        registry
+    }
+
+    /// Sets the function that spawns a type of thing, replacing any previous one
+    pub fn register(&mut self, kind: ThingType, spawner: Spawner) {
+        self.spawners.insert(kind, spawner);
+    }
+
+    /// Spawns a thing, unless its type hasn't been registered
+    pub fn spawn(&self, thing: &Thing) -> Option<Spawned> {
+        self.spawners.get(&thing.kind).map(|spawner| spawner(thing))
+    }
+}
+
+fn enemy(thing: &Thing, kind: EnemyKind) -> Spawned {
+    let mut enemy = Enemy::new(kind, thing.position, thing.sector_index);
+    if thing.flags.contains(SpawnFlags::AWAKE) {
+        enemy.wake();
+    }
+    Spawned::Enemy(enemy)
+}
+
+fn item(thing: &Thing, kind: ItemKind) -> Spawned {
+    Spawned::Item(Item::new(kind, thing.position, thing.sector_index))
+}
+
+fn prop(thing: &Thing, kind: PropKind) -> Spawned {
+    Spawned::Prop(Prop::new(
+        kind,
+        thing.position,
+        thing.angle,
+        thing.sector_index,
+    ))
+}
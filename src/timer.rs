@@ -1,34 +1,34 @@
-use std::time::{Duration, Instant};
+use std::time::Instant;
 
-use crate::consts::FPS;
+use collections::RingBuffer;
+
+use crate::{consts::FPS, pacing::FramePacer};
 
 #[derive(Debug)]
 pub struct Timer {
     pub start: Instant,
-    pub frame_time: Duration,
     pub prev_update: Instant,
-    pub prev_frame: Instant,
+    pub pacer: FramePacer,
     pub frame_count: usize,
 
-    pub time_buffer: Vec<f32>,
+    /// Time taken by recent updates, in seconds
+    pub time_buffer: RingBuffer<f32, 128>,
 }
 
 impl Timer {
     pub fn new() -> Self {
         let start = Instant::now();
-        let frame_time = Duration::from_secs_f32(1.0 / FPS);
         let prev_update = start;
-        let prev_frame = start;
+        let pacer = FramePacer::new(Some(FPS));
         let frame_count = 0;
 
         Self {
             start,
-            frame_time,
             prev_update,
-            prev_frame,
+            pacer,
             frame_count,
 
-            time_buffer: vec![0.0; 128],
+            time_buffer: RingBuffer::new(),
         }
     }
 
@@ -0,0 +1,115 @@
+//! Invisible regions of the map which raise events as the player and enemies move in and out of
+//! them, for the level script to set off traps, cutscenes and the like
+
+use maths::{
+    geometry::{Circle, Polygon},
+    linear::Vec2f,
+};
+use physics::Collider;
+
+use crate::events::{EventQueue, GameEvent};
+
+/// What moved into or out of a trigger volume
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Occupant {
+    Player,
+    Enemy,
+}
+
+impl Occupant {
+    pub fn name(&self) -> &'static str {
+        match self {
+            Occupant::Player => "player",
+            Occupant::Enemy => "enemy",
+        }
+    }
+}
+
+/// A region of the map, only as seen from above, so it extends from floor to ceiling
+pub struct TriggerVolume {
+    /// Identifies the volume, and is passed along with its events
+    pub id: usize,
+    region: Collider,
+}
+
+impl TriggerVolume {
+    pub fn circle(id: usize, centre: Vec2f, radius: f32) -> Self {
+        Self {
+            id,
+            region: Collider::from_circle(Circle::new(centre, radius)),
+        }
+    }
+
+    /// A region bounded by a polygon, which must be convex
+    pub fn polygon(id: usize, vertices: Vec<Vec2f>) -> Self {
+        Self {
+            id,
+            region: Collider::from_polygon(Polygon::from_vertices(vertices)),
+        }
+    }
+
+    pub fn contains(&self, point: Vec2f) -> bool {
+        self.region.contains_point(point)
+    }
+}
+
+/// Trigger volumes that something is currently inside, by their index, so that it can tell when
+/// it enters or leaves one
+#[derive(Debug, Default)]
+pub struct Occupancy {
+    inside: Vec<usize>,
+}
+
+impl Occupancy {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Checks which volumes the position is inside, raising an event for each volume that has
+    /// been entered or left since the last check
+    pub fn update(
+        &mut self,
+        volumes: &[TriggerVolume],
+        occupant: Occupant,
+        position: Vec2f,
+        events: &mut EventQueue,
+    ) {
+        self.inside.retain(|&index| {
+            let volume = &volumes[index];
+            let inside = volume.contains(position);
+            if !inside {
+                events.push(GameEvent::TriggerExited {
+                    trigger: volume.id,
+                    occupant,
+                });
+            }
+
+            inside
+        });
+
+        for (index, volume) in volumes.iter().enumerate() {
+            if !self.inside.contains(&index) && volume.contains(position) {
+                self.inside.push(index);
+                events.push(GameEvent::TriggerEntered {
+                    trigger: volume.id,
+                    occupant,
+                });
+            }
+        }
+    }
+
+    /// Leaves every volume, such as when an enemy dies, raising an event for each
+    pub fn leave_all(
+        &mut self,
+        volumes: &[TriggerVolume],
+        occupant: Occupant,
+        events: &mut EventQueue,
+    ) {
+        for index in self.inside.drain(..) {
+            events.push(GameEvent::TriggerExited {
+                trigger: volumes[index].id,
+                occupant,
+            });
+        }
+    }
+}
@@ -0,0 +1,223 @@
+//! Weapons the player can carry, and the one they are holding
+
+use crate::{consts::*, player::PlayerStats};
+
+/// Longest time that the firing frame is shown for after each shot, in seconds
+const FIRING_FRAME_DURATION: f32 = 0.1;
+/// Time taken for the light of a muzzle flash to fade out after each shot, in seconds
+const MUZZLE_FLASH_DURATION: f32 = 0.15;
+
+/// How a weapon's shots reach their target
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum FireMode {
+    /// Hits instantly, with each shot firing a number of pellets fanned out across the spread
+    Hitscan { pellets: usize },
+    /// Fires a projectile that travels at this speed, in units per second
+    Projectile { speed: f32 },
+    /// Hits whatever is within `range` and `half_angle` radians either side of straight ahead, or
+    /// failing that, the wall in front
+    Melee { range: f32, half_angle: f32 },
+}
+
+/// Definition of a type of weapon
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Weapon {
+    /// Seconds between shots whilst fire is held
+    pub fire_delay: f32,
+    /// Damage dealt by each pellet or projectile
+    pub damage: u32,
+    /// Angle, in radians, either side of straight ahead that shots may be fired at
+    pub spread: f32,
+    pub ammo_per_shot: u32,
+    pub fire_mode: FireMode,
+    /// Number of portals that the sound of the weapon travels through, if it is loud enough to
+    /// wake enemies
+    pub noise_range: Option<usize>,
+    /// Brightness of the light that each shot briefly casts around the player, or zero if it
+    /// doesn't flash
+    pub muzzle_flash: f32,
+    /// HUD texture index shown whilst the weapon is held
+    pub idle_frame: usize,
+    /// HUD texture index shown just after firing
+    pub firing_frame: usize,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum WeaponKind {
+    Fist,
+    Pistol,
+    Shotgun,
+    Crossbow,
+}
+
+impl WeaponKind {
+    /// Every weapon, in the order of the slots they are selected with
+    pub const ALL: [WeaponKind; 4] = [
+        WeaponKind::Fist,
+        WeaponKind::Pistol,
+        WeaponKind::Shotgun,
+        WeaponKind::Crossbow,
+    ];
+
+    /// Bit used to store the weapon in [PlayerStats::weapons]
+    pub fn bit(&self) -> u8 {
+        match self {
+            WeaponKind::Fist => 1 << 0,
+            WeaponKind::Pistol => 1 << 1,
+            WeaponKind::Shotgun => 1 << 2,
+            WeaponKind::Crossbow => 1 << 3,
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            WeaponKind::Fist => "fist",
+            WeaponKind::Pistol => "pistol",
+            WeaponKind::Shotgun => "shotgun",
+            WeaponKind::Crossbow => "crossbow",
+        }
+    }
+
+    pub fn weapon(&self) -> &'static Weapon {
+        match self {
+            WeaponKind::Fist => &Weapon {
+                fire_delay: 0.5,
+                damage: 5,
+                spread: 0.0,
+                ammo_per_shot: 0,
+                fire_mode: FireMode::Melee {
+                    range: 12.0,
+                    half_angle: 0.5,
+                },
+                noise_range: None,
+                muzzle_flash: 0.0,
+                idle_frame: FIST_IDLE,
+                firing_frame: FIST_FIRING,
+            },
+            WeaponKind::Pistol => &Weapon {
+                fire_delay: 0.4,
+                damage: 10,
+                spread: 0.0,
+                ammo_per_shot: 1,
+                fire_mode: FireMode::Hitscan { pellets: 1 },
+                noise_range: Some(GUNSHOT_RANGE),
+                muzzle_flash: 0.5,
+                idle_frame: PISTOL_IDLE,
+                firing_frame: PISTOL_FIRING,
+            },
+            WeaponKind::Shotgun => &Weapon {
+                fire_delay: 1.0,
+                damage: 6,
+                spread: 0.1,
+                ammo_per_shot: 2,
+                fire_mode: FireMode::Hitscan { pellets: 7 },
+                noise_range: Some(GUNSHOT_RANGE + 1),
+                muzzle_flash: 0.8,
+                idle_frame: SHOTGUN_IDLE,
+                firing_frame: SHOTGUN_FIRING,
+            },
+            WeaponKind::Crossbow => &Weapon {
+                fire_delay: 0.8,
+                damage: 40,
+                spread: 0.0,
+                ammo_per_shot: 1,
+                fire_mode: FireMode::Projectile { speed: 250.0 },
+                noise_range: None,
+                muzzle_flash: 0.0,
+                idle_frame: CROSSBOW_IDLE,
+                firing_frame: CROSSBOW_FIRING,
+            },
+        }
+    }
+}
+
+/// The weapon that the player is holding, and how long until it can fire again
+#[derive(Clone, Copy, Debug)]
+pub struct HeldWeapon {
+    pub kind: WeaponKind,
+    /// Seconds until a weapon can be fired again. This carries over when switching weapons, so
+    /// that switching can't be used to fire faster.
+    cooldown: f32,
+    /// Seconds since the held weapon was last fired
+    since_fired: f32,
+}
+
+impl HeldWeapon {
+    pub fn new() -> Self {
+        Self {
+            kind: WeaponKind::Pistol,
+            cooldown: 0.0,
+            since_fired: f32::INFINITY,
+        }
+    }
+
+    /// Switches to a weapon, if it is carried. Returns whether the weapon changed.
+    pub fn select(&mut self, kind: WeaponKind, stats: &PlayerStats) -> bool {
+        if kind == self.kind || !stats.has_weapon(kind) {
+            return false;
+        }
+
+        self.kind = kind;
+        self.since_fired = f32::INFINITY;
+        true
+    }
+
+    /// Switches to the next carried weapon in slot order, or the previous one if `forwards` is
+    /// false, wrapping around at either end
+    pub fn cycle(&mut self, forwards: bool, stats: &PlayerStats) -> bool {
+        let count = WeaponKind::ALL.len();
+        let current = WeaponKind::ALL
+            .iter()
+            .position(|kind| *kind == self.kind)
+            .unwrap_or_default();
+
+        let next = (1..count)
+            .map(|step| {
+                if forwards {
+                    (current + step) % count
+                } else {
+                    (current + count - step) % count
+                }
+            })
+            .map(|index| WeaponKind::ALL[index])
+            .find(|kind| stats.has_weapon(*kind));
+
+        next.is_some_and(|kind| self.select(kind, stats))
+    }
+
+    pub fn update(&mut self, delta_seconds: f32) {
+        self.cooldown = (self.cooldown - delta_seconds).max(0.0);
+        self.since_fired += delta_seconds;
+    }
+
+    /// Fires the weapon if it is ready and there is enough ammo, using up the ammo. Returns the
+    /// weapon fired.
+    pub fn fire(&mut self, stats: &mut PlayerStats) -> Option<&'static Weapon> {
+        let weapon = self.kind.weapon();
+        if self.cooldown > 0.0 || stats.ammo < weapon.ammo_per_shot {
+            return None;
+        }
+
+        stats.ammo -= weapon.ammo_per_shot;
+        self.cooldown = weapon.fire_delay;
+        self.since_fired = 0.0;
+        Some(weapon)
+    }
+
+    /// Brightness of the light cast by the last shot, which fades out shortly after firing
+    pub fn muzzle_flash(&self) -> f32 {
+        let remaining = 1.0 - self.since_fired / MUZZLE_FLASH_DURATION;
+        self.kind.weapon().muzzle_flash * remaining.max(0.0)
+    }
+
+    /// HUD texture index of the current frame of the weapon
+    pub fn frame(&self) -> usize {
+        let weapon = self.kind.weapon();
+
+        if self.since_fired < FIRING_FRAME_DURATION.min(weapon.fire_delay) {
+            weapon.firing_frame
+        } else {
+            weapon.idle_frame
+        }
+    }
+}